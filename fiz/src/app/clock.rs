@@ -0,0 +1,83 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Clock is a source of time for an `App`.
+pub trait Clock {
+    /// now returns the time since some fixed point, such as when the clock
+    /// was made. It must never decrease.
+    fn now(&mut self) -> Duration;
+}
+
+/// SystemClock is the monotonic clock of the operating system.
+#[derive(Copy, Clone, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl SystemClock {
+    /// new returns a clock which measures time from now.
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// ManualClock is a clock which only advances when told to, for driving an
+/// `App` deterministically, as in tests. Its clones share the same time, so
+/// that one may be kept to advance the clock given to an app.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use fiz::app::{Clock, ManualClock};
+///
+/// let mut clock = ManualClock::new();
+/// let handle = clock.clone();
+/// handle.advance(Duration::from_millis(20));
+/// assert_eq!(clock.now(), Duration::from_millis(20));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    /// new returns a clock at time zero.
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    /// advance moves the clock forward by the duration.
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    /// set sets the time of the clock.
+    ///
+    /// # Panics
+    ///
+    /// set panics if the time is earlier than the current time of the clock.
+    pub fn set(&self, now: Duration) {
+        let mut current = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        assert!(now >= *current, "ManualClock::set: time can't go backwards");
+        *current = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&mut self) -> Duration {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,353 @@
+//! Application main loop.
+//!
+//! An `App` owns a world and a schedule of systems, and runs them in a loop
+//! of frames. The simulation (the pre-update, update and post-update stages)
+//! runs in fixed timesteps, as many per frame as the time since the last
+//! frame allows, carrying the remainder over to the next frame. Rendering
+//! (the render stage) runs once per frame, with the fraction of a timestep
+//! left over as the alpha for interpolating between simulation states.
+
+mod clock;
+mod time;
+
+use std::time::Duration;
+use ecs::{Schedule, ScheduleError, Stage, System, World};
+
+pub use self::clock::{Clock, ManualClock, SystemClock};
+pub use self::time::{from_secs, secs, Time};
+
+/// AppExit is a resource which, once inserted into the world of an app,
+/// makes `App::run` return after the current frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AppExit;
+
+/// App runs a schedule of systems on a world, with a fixed timestep for the
+/// simulation and a variable one for rendering.
+///
+/// The time since the last frame is clamped to a maximum, so that the
+/// simulation can't fall ever further behind when it's slower than real
+/// time, and then scaled by the time scale, before being added to the time
+/// left to simulate.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use fiz::app::{App, ManualClock, Time};
+/// use fiz::ecs::{Stage, System};
+///
+/// struct Position { previous: f32, current: f32 }
+/// struct Drawn(f32);
+///
+/// let clock = ManualClock::new();
+/// let mut app = App::with_clock(Duration::from_millis(100), clock.clone());
+/// app.world_mut().insert_resource(Position { previous: 0.0, current: 0.0 });
+/// app.add_system(Stage::Update, System::new("move", |ctx| {
+///     let dt = ctx.resource::<Time>().unwrap().timestep_secs::<f32>();
+///     let mut p = ctx.resource_mut::<Position>().unwrap();
+///     p.previous = p.current;
+///     p.current += 10.0 * dt;
+/// }).read_resource::<Time>().write_resource::<Position>());
+/// app.add_system(Stage::Render, System::new("draw", |ctx| {
+///     let alpha = ctx.resource::<Time>().unwrap().alpha::<f32>();
+///     let p = ctx.resource::<Position>().unwrap();
+///     ctx.commands().insert_resource(Drawn(p.previous + (p.current - p.previous) * alpha));
+/// }).read_resource::<Time>().read_resource::<Position>());
+///
+/// app.frame().unwrap();
+/// clock.advance(Duration::from_millis(250));
+/// assert_eq!(app.frame().unwrap(), 2);
+/// assert_eq!(app.time().elapsed(), Duration::from_millis(200));
+/// assert_eq!(app.time().alpha::<f64>(), 0.5);
+/// assert!((app.world().resource::<Drawn>().unwrap().0 - 1.5).abs() < 1e-6);
+/// ```
+#[derive(Debug)]
+pub struct App<C: Clock = SystemClock> {
+    world: World,
+    schedule: Schedule,
+    clock: C,
+    time: Time,
+
+    max_frame_time: Duration,
+    time_scale: f64,
+    paused: bool,
+    steps: u32,
+
+    // The clock's time at the last frame, and the scaled time not yet
+    // simulated.
+    last: Option<Duration>,
+    accumulator: Duration,
+}
+
+impl App<SystemClock> {
+    /// new returns a new app with an empty world and schedule, simulating in
+    /// steps of the timestep, timed by the system clock.
+    ///
+    /// # Panics
+    ///
+    /// new panics if the timestep is zero.
+    pub fn new(timestep: Duration) -> Self {
+        App::with_clock(timestep, SystemClock::new())
+    }
+}
+
+impl<C: Clock> App<C> {
+    /// with_clock returns a new app with an empty world and schedule,
+    /// simulating in steps of the timestep, timed by the clock.
+    ///
+    /// # Panics
+    ///
+    /// with_clock panics if the timestep is zero.
+    pub fn with_clock(timestep: Duration, clock: C) -> Self {
+        assert!(!timestep.is_zero(), "App::with_clock: timestep must be positive");
+        let time = Time::new(timestep);
+        let mut world = World::new();
+        world.insert_resource(time);
+        App {
+            world,
+            schedule: Schedule::new(),
+            clock,
+            time,
+            max_frame_time: Duration::from_millis(250),
+            time_scale: 1.0,
+            paused: false,
+            steps: 0,
+            last: None,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// world returns the world of the app.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// world_mut returns the world of the app mutably.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// schedule returns the schedule of the app.
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// schedule_mut returns the schedule of the app mutably.
+    pub fn schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.schedule
+    }
+
+    /// add_system adds the system to the end of the stage of the schedule.
+    pub fn add_system(&mut self, stage: Stage, system: System) -> &mut Self {
+        self.schedule.add_system(stage, system);
+        self
+    }
+
+    /// clock returns the clock of the app.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// time returns the timing of the last fixed update or frame.
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// max_frame_time returns the most real time a frame may simulate,
+    /// before scaling.
+    pub fn max_frame_time(&self) -> Duration {
+        self.max_frame_time
+    }
+
+    /// set_max_frame_time sets the most real time a frame may simulate,
+    /// before scaling. It defaults to a quarter of a second.
+    pub fn set_max_frame_time(&mut self, max: Duration) {
+        self.max_frame_time = max;
+    }
+
+    /// time_scale returns the rate of simulated time to real time.
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// set_time_scale sets the rate of simulated time to real time, so that
+    /// for example 0.5 runs the simulation in slow motion at half speed. The
+    /// timestep stays the same, only the number of steps per frame changes.
+    ///
+    /// # Panics
+    ///
+    /// set_time_scale panics if the scale is negative or not finite.
+    pub fn set_time_scale(&mut self, scale: f64) {
+        assert!(scale >= 0.0 && scale.is_finite(), "App::set_time_scale: scale must be finite and non-negative");
+        self.time_scale = scale;
+    }
+
+    /// is_paused tells if the simulation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// pause pauses the simulation. Frames still render, with the alpha they
+    /// had when paused.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// resume resumes the simulation. The time spent paused isn't simulated.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// step makes the next frame run one more fixed update, even while the
+    /// simulation is paused.
+    pub fn step(&mut self) {
+        self.steps += 1;
+    }
+
+    /// frame runs a frame: the fixed updates the time since the last frame
+    /// allows, then the render stage. It returns the number of fixed updates
+    /// run. The first frame only renders.
+    ///
+    /// # Panics
+    ///
+    /// frame panics if a system does.
+    pub fn frame(&mut self) -> Result<u32, ScheduleError> {
+        let now = self.clock.now();
+        let frame_time = self.last.map_or(Duration::ZERO, |last| now.saturating_sub(last));
+        self.last = Some(now);
+        self.time.frame(frame_time);
+
+        if !self.paused {
+            self.accumulator += frame_time.min(self.max_frame_time).mul_f64(self.time_scale);
+        }
+        let timestep = self.time.timestep();
+        let mut steps = 0;
+        loop {
+            if self.steps > 0 {
+                self.steps -= 1;
+            } else if !self.paused && self.accumulator >= timestep {
+                self.accumulator -= timestep;
+            } else {
+                break;
+            }
+            self.time.tick();
+            self.world.insert_resource(self.time);
+            for stage in [Stage::PreUpdate, Stage::Update, Stage::PostUpdate] {
+                self.schedule.run_stage(stage, &mut self.world)?;
+            }
+            steps += 1;
+        }
+
+        if !self.paused {
+            self.time.set_alpha(self.accumulator.as_secs_f64() / timestep.as_secs_f64());
+        }
+        self.world.insert_resource(self.time);
+        self.schedule.run_stage(Stage::Render, &mut self.world)?;
+        Ok(steps)
+    }
+
+    /// run runs frames until a system inserts the `AppExit` resource, which
+    /// it then removes.
+    ///
+    /// # Panics
+    ///
+    /// run panics if a system does.
+    pub fn run(&mut self) -> Result<(), ScheduleError> {
+        while self.world.remove_resource::<AppExit>().is_none() {
+            self.frame()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use ecs::{Stage, System};
+    use super::{App, AppExit, ManualClock, Time};
+
+    // app returns an app with a 10ms timestep, counting updates and renders
+    // in resources of type (u32,) and u32.
+    fn app() -> (App<ManualClock>, ManualClock) {
+        let clock = ManualClock::new();
+        let mut app = App::with_clock(Duration::from_millis(10), clock.clone());
+        app.world_mut().insert_resource((0u32,));
+        app.world_mut().insert_resource(0u32);
+        app.add_system(Stage::Update, System::new("update", |ctx| ctx.resource_mut::<(u32,)>().unwrap().0 += 1)
+            .write_resource::<(u32,)>());
+        app.add_system(Stage::Render, System::new("render", |ctx| *ctx.resource_mut::<u32>().unwrap() += 1)
+            .write_resource::<u32>());
+        (app, clock)
+    }
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn fixed_timestep() {
+        let (mut app, clock) = app();
+        assert_eq!(app.frame(), Ok(0));
+        clock.advance(ms(25));
+        assert_eq!(app.frame(), Ok(2));
+        assert_eq!(app.time().alpha::<f64>(), 0.5);
+        clock.advance(ms(5));
+        assert_eq!(app.frame(), Ok(1));
+        assert_eq!(app.time().alpha::<f64>(), 0.0);
+        assert_eq!(app.time().ticks(), 3);
+        assert_eq!(app.time().frames(), 3);
+        assert_eq!(app.time().elapsed(), ms(30));
+        assert_eq!(app.time().frame_time(), ms(5));
+        assert_eq!(app.time().real_elapsed_secs::<f32>(), 0.03);
+        assert_eq!(app.world().resource::<(u32,)>().unwrap().0, 3);
+        assert_eq!(*app.world().resource::<u32>().unwrap(), 3);
+        assert_eq!(*app.world().resource::<Time>().unwrap(), *app.time());
+
+        // Long frames are clamped.
+        app.set_max_frame_time(ms(100));
+        clock.advance(ms(1000));
+        assert_eq!(app.frame(), Ok(10));
+        assert_eq!(app.time().frame_time(), ms(1000));
+    }
+
+    #[test]
+    fn controls() {
+        let (mut app, clock) = app();
+        app.frame().unwrap();
+
+        app.set_time_scale(0.5);
+        clock.advance(ms(40));
+        assert_eq!(app.frame(), Ok(2));
+
+        app.pause();
+        clock.advance(ms(40));
+        assert_eq!(app.frame(), Ok(0));
+        app.step();
+        clock.advance(ms(40));
+        assert_eq!(app.frame(), Ok(1));
+        assert_eq!(app.time().elapsed(), ms(30));
+
+        app.resume();
+        app.set_time_scale(2.0);
+        clock.advance(ms(15));
+        assert_eq!(app.frame(), Ok(3));
+        assert_eq!(app.world().resource::<(u32,)>().unwrap().0, 6);
+        assert_eq!(*app.world().resource::<u32>().unwrap(), 5);
+    }
+
+    #[test]
+    fn exit() {
+        let (mut app, clock) = app();
+        let mut frames = 0;
+        app.add_system(Stage::Render, System::new("exit", move |ctx| {
+            clock.advance(Duration::from_millis(10));
+            frames += 1;
+            if frames == 4 {
+                ctx.commands().insert_resource(AppExit);
+            }
+        }));
+        app.run().unwrap();
+        assert_eq!(app.time().ticks(), 3);
+        assert!(!app.world().has_resource::<AppExit>());
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+use math::Float;
+
+/// Time is the timing of the current step of an `App`, which it keeps as a
+/// resource of its world for systems to read. Durations are available both
+/// as `Duration` and as seconds of any `Float` type.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use fiz::app::Time;
+///
+/// let time = Time::new(Duration::from_millis(250));
+/// assert_eq!(time.timestep(), Duration::from_millis(250));
+/// assert_eq!(time.timestep_secs::<f32>(), 0.25);
+/// assert_eq!(time.elapsed_secs::<f64>(), 0.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Time {
+    timestep: Duration,
+    elapsed: Duration,
+    frame_time: Duration,
+    real_elapsed: Duration,
+    alpha: f64,
+    ticks: u64,
+    frames: u64,
+}
+
+impl Time {
+    /// new returns the time before the first frame of an app with the fixed
+    /// timestep.
+    pub fn new(timestep: Duration) -> Self {
+        Time {
+            timestep,
+            elapsed: Duration::ZERO,
+            frame_time: Duration::ZERO,
+            real_elapsed: Duration::ZERO,
+            alpha: 0.0,
+            ticks: 0,
+            frames: 0,
+        }
+    }
+
+    /// timestep returns the simulated time of each fixed update.
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// timestep_secs returns the simulated time of each fixed update, in
+    /// seconds.
+    pub fn timestep_secs<F: Float>(&self) -> F {
+        secs(self.timestep)
+    }
+
+    /// elapsed returns the simulated time, which is the number of fixed
+    /// updates run times the timestep.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// elapsed_secs returns the simulated time in seconds.
+    pub fn elapsed_secs<F: Float>(&self) -> F {
+        secs(self.elapsed)
+    }
+
+    /// frame_time returns the real time between the last two frames, before
+    /// clamping, scaling or pausing.
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// frame_secs returns the real time between the last two frames in
+    /// seconds.
+    pub fn frame_secs<F: Float>(&self) -> F {
+        secs(self.frame_time)
+    }
+
+    /// real_elapsed returns the real time since the first frame.
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+
+    /// real_elapsed_secs returns the real time since the first frame in
+    /// seconds.
+    pub fn real_elapsed_secs<F: Float>(&self) -> F {
+        secs(self.real_elapsed)
+    }
+
+    /// alpha returns how far the render of the current frame is between the
+    /// last fixed update and the next one, in the range [0, 1), for
+    /// interpolating between their states.
+    pub fn alpha<F: Float>(&self) -> F {
+        F::from(self.alpha).unwrap()
+    }
+
+    /// ticks returns the number of fixed updates run.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// frames returns the number of frames run.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    // tick records a fixed update.
+    pub(super) fn tick(&mut self) {
+        self.ticks += 1;
+        self.elapsed += self.timestep;
+    }
+
+    // frame records the start of a frame taking the real time.
+    pub(super) fn frame(&mut self, frame_time: Duration) {
+        self.frames += 1;
+        self.frame_time = frame_time;
+        self.real_elapsed += frame_time;
+    }
+
+    pub(super) fn set_alpha(&mut self, alpha: f64) {
+        self.alpha = alpha;
+    }
+}
+
+/// secs returns the duration in seconds.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// assert_eq!(fiz::app::secs::<f32>(Duration::from_millis(1500)), 1.5);
+/// ```
+pub fn secs<F: Float>(d: Duration) -> F {
+    F::from(d.as_secs_f64()).unwrap()
+}
+
+/// from_secs returns a duration of the given non-negative number of seconds.
+///
+/// # Panics
+///
+/// from_secs panics if the number is negative, not finite or too large.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// assert_eq!(fiz::app::from_secs(0.5f32), Duration::from_millis(500));
+/// ```
+pub fn from_secs<F: Float>(secs: F) -> Duration {
+    Duration::from_secs_f64(secs.to_f64().unwrap())
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+use super::{Bundle, Component, Entity, World};
+
+/// Commands is a buffer of changes to a world, for systems to defer the
+/// changes they can't make while other systems borrow the world. Changes
+/// are applied in the order they were added.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::{Commands, World};
+///
+/// let mut world = World::new();
+/// let a = world.spawn((1u32,));
+///
+/// let mut commands = Commands::new();
+/// commands.despawn(a);
+/// commands.spawn((2u32, "b"));
+/// commands.spawn((3u32,));
+/// assert_eq!(world.len(), 1);
+///
+/// commands.apply(&mut world);
+/// assert!(commands.is_empty());
+/// assert!(!world.is_alive(a));
+/// assert_eq!(world.query::<&u32, ()>().count(), 2);
+/// ```
+#[derive(Default)]
+pub struct Commands {
+    ops: Vec<Command>,
+}
+
+// Command is a buffered change.
+type Command = Box<dyn FnOnce(&mut World) + Send>;
+
+impl fmt::Debug for Commands {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Commands").field("len", &self.ops.len()).finish()
+    }
+}
+
+impl Commands {
+    /// new returns a new empty buffer.
+    pub fn new() -> Self {
+        Commands::default()
+    }
+
+    /// len returns the number of buffered changes.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// is_empty tells if there are no buffered changes.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// add buffers an arbitrary change.
+    pub fn add<F>(&mut self, f: F)
+        where F: FnOnce(&mut World) + Send + 'static
+    {
+        self.ops.push(Box::new(f));
+    }
+
+    /// spawn buffers spawning an entity with the components.
+    pub fn spawn<B: Bundle>(&mut self, components: B) {
+        self.add(move |world| {
+            world.spawn(components);
+        });
+    }
+
+    /// despawn buffers despawning the entity, if it's still alive then.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    /// insert buffers attaching the component to the entity, if it's still
+    /// alive then.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.add(move |world| if world.is_alive(entity) {
+            world.insert(entity, component);
+        });
+    }
+
+    /// remove buffers removing the component of type T from the entity.
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.add(move |world| {
+            world.remove::<T>(entity);
+        });
+    }
+
+    /// insert_resource buffers inserting the resource.
+    pub fn insert_resource<R: Component>(&mut self, resource: R) {
+        self.add(move |world| {
+            world.insert_resource(resource);
+        });
+    }
+
+    /// remove_resource buffers removing the resource of type R.
+    pub fn remove_resource<R: Component>(&mut self) {
+        self.add(|world| {
+            world.remove_resource::<R>();
+        });
+    }
+
+    /// append moves the changes of the other buffer to the end of this one.
+    pub fn append(&mut self, other: &mut Commands) {
+        self.ops.append(&mut other.ops);
+    }
+
+    /// apply applies the buffered changes to the world, leaving the buffer
+    /// empty.
+    pub fn apply(&mut self, world: &mut World) {
+        for op in self.ops.drain(..) {
+            op(world);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use math::{Quat, Transform, Vec3};
+use math::unit::Rad;
+
+/// Velocity is the rate of change of an entity's `Transform<f32>`, the
+/// component holding its position, rotation and scale.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::Velocity;
+/// use fiz::math::{Transform, Vec3};
+///
+/// let v = Velocity { linear: Vec3(1.0, 0.0, 0.0), angular: Vec3(0.0, std::f32::consts::PI, 0.0) };
+/// let mut t = Transform::identity();
+/// v.integrate(&mut t, 0.5);
+/// assert_eq!(t.translation, Vec3(0.5, 0.0, 0.0));
+///
+/// // A quarter turn about +Y.
+/// assert!(t.rotation.rotate(Vec3(1.0, 0.0, 0.0)).almost_equal(Vec3(0.0, 0.0, -1.0), 1e-6));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Velocity {
+    /// The linear velocity, in units per second.
+    pub linear: Vec3<f32>,
+
+    /// The angular velocity, as the axis of rotation scaled by the rate of
+    /// rotation in radians per second (counter-clockwise, looking down the
+    /// axis).
+    pub angular: Vec3<f32>,
+}
+
+impl Default for Velocity {
+    /// default returns zero velocity.
+    fn default() -> Self {
+        Velocity {
+            linear: Vec3(0.0, 0.0, 0.0),
+            angular: Vec3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl Velocity {
+    /// integrate moves the transform along the velocity for a step of dt
+    /// seconds.
+    pub fn integrate(&self, transform: &mut Transform<f32>, dt: f32) {
+        transform.translation = transform.translation + self.linear.mul_scalar(dt);
+        let rate = self.angular.length();
+        if rate > 0.0 {
+            let spin = Quat::from_axis_angle(self.angular, Rad(rate * dt));
+            transform.rotation = (spin * transform.rotation).normalize().unwrap_or(transform.rotation);
+        }
+    }
+}
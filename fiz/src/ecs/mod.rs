@@ -0,0 +1,637 @@
+//! Entity-component-system.
+//!
+//! A `World` holds entities, which are plain identifiers, and the
+//! components attached to them, which are any `Send + Sync` values, stored
+//! in one sparse set per component type. It also holds resources: values of
+//! which there's at most one of each type, like the time or settings.
+//!
+//! Systems are functions which run on the world through typed queries over
+//! entities with given components, such as `(&mut Transform<f32>,
+//! &Velocity)`, filtered by `With`, `Without`, `Changed` and `Added`.
+//! Systems declare which components and resources they read and write, so
+//! that a scheduler can run those which don't conflict in parallel, and
+//! defer structural changes (spawning, despawning, and inserting or removing
+//! components) to a `Commands` buffer applied afterwards.
+//!
+//! Changes are tracked per component with ticks of the world's clock, which
+//! advances each time a system runs. `Changed` matches components written
+//! to through a mutable borrow since the system last ran.
+
+mod storage;
+mod query;
+mod system;
+mod commands;
+mod components;
+mod schedule;
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use self::storage::{AnyColumn, Column};
+
+pub use self::query::{Added, Changed, Query, QueryData, QueryFilter, With, Without};
+pub use self::system::{Access, System, SystemContext};
+pub use self::commands::Commands;
+pub use self::components::Velocity;
+pub use self::schedule::{Schedule, ScheduleError, Stage};
+
+/// Component is implemented for all types which can be attached to
+/// entities: those which can be shared between threads.
+pub trait Component: Any + Send + Sync {}
+
+impl<T: Any + Send + Sync> Component for T {}
+
+/// Entity identifies an entity of a `World`. Identifiers of despawned
+/// entities are never equal to those of later ones, which may reuse their
+/// index with a new generation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// index returns the index of the entity, which is unique among living
+    /// entities.
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// generation returns the number of times the index was used before.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+/// Bundle is a set of components inserted together, implemented for tuples
+/// of up to eight components.
+pub trait Bundle: Send + 'static {
+    /// insert inserts the components into the world for the entity.
+    fn insert(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: Component),*> Bundle for ($($name,)*) {
+            #[allow(unused_variables)]
+            fn insert(self, world: &mut World, entity: Entity) {
+                $(world.insert(entity, self.$index);)*
+            }
+        }
+    }
+}
+
+impl_bundle!();
+impl_bundle!(A: 0);
+impl_bundle!(A: 0, B: 1);
+impl_bundle!(A: 0, B: 1, C: 2);
+impl_bundle!(A: 0, B: 1, C: 2, D: 3);
+impl_bundle!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_bundle!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_bundle!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_bundle!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Ref is a shared borrow of a component, returned by `World::get`.
+pub struct Ref<'w, T: 'w> {
+    data: RwLockReadGuard<'w, Vec<T>>,
+    index: usize,
+}
+
+impl<'w, T> Deref for Ref<'w, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data[self.index]
+    }
+}
+
+/// Mut is a mutable borrow of a component, which marks it as changed when
+/// it's dereferenced mutably.
+pub struct Mut<'a, T: 'a> {
+    value: &'a mut T,
+    changed: &'a AtomicU64,
+    tick: u64,
+}
+
+impl<'a, T> Mut<'a, T> {
+    /// is_changed tells if the component was marked as changed by the
+    /// current system (or since the world's trackers were last cleared).
+    pub fn is_changed(&self) -> bool {
+        self.changed.load(Ordering::Relaxed) >= self.tick
+    }
+}
+
+impl<'a, T> Deref for Mut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for Mut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed.store(self.tick, Ordering::Relaxed);
+        self.value
+    }
+}
+
+// Entities allocates entity identifiers, reusing the indices of despawned
+// entities with a new generation.
+#[derive(Clone, Debug, Default)]
+struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<Entity>,
+    // The position of each living entity in alive.
+    positions: Vec<Option<usize>>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    fn alloc(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(i) => i,
+            None => {
+                self.generations.push(0);
+                self.positions.push(None);
+                (self.generations.len() - 1) as u32
+            }
+        };
+        let e = Entity {
+            index,
+            generation: self.generations[index as usize],
+        };
+        self.positions[index as usize] = Some(self.alive.len());
+        self.alive.push(e);
+        e
+    }
+
+    fn contains(&self, e: Entity) -> bool {
+        self.generations.get(e.index as usize) == Some(&e.generation) && self.positions[e.index as usize].is_some()
+    }
+
+    fn free(&mut self, e: Entity) -> bool {
+        if !self.contains(e) {
+            return false;
+        }
+        let i = self.positions[e.index as usize].take().unwrap();
+        self.alive.swap_remove(i);
+        if let Some(&moved) = self.alive.get(i) {
+            self.positions[moved.index as usize] = Some(i);
+        }
+        self.generations[e.index as usize] += 1;
+        self.free.push(e.index);
+        true
+    }
+}
+
+/// World holds entities, their components, and resources.
+///
+/// Components and resources may be borrowed through a shared world, as
+/// systems running in parallel do, and are locked while borrowed. Borrowing
+/// one mutably while it's borrowed elsewhere panics, as the declared access
+/// of systems should prevent.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::{With, World};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Health(u32);
+/// struct Player;
+///
+/// let mut world = World::new();
+/// let player = world.spawn((Health(10), Player));
+/// let monster = world.spawn((Health(30),));
+///
+/// // Players are healed.
+/// world.query::<&mut Health, With<Player>>().for_each(|mut h| h.0 += 5);
+/// assert_eq!(*world.get::<Health>(player).unwrap(), Health(15));
+/// assert_eq!(*world.get::<Health>(monster).unwrap(), Health(30));
+///
+/// world.despawn(monster);
+/// assert!(!world.is_alive(monster));
+/// assert_eq!(world.query::<&Health, ()>().count(), 1);
+/// ```
+pub struct World {
+    entities: Entities,
+    columns: HashMap<TypeId, Box<dyn AnyColumn>>,
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+
+    // The clock for change detection, advanced each time a system runs, and
+    // the time trackers were last cleared.
+    tick: AtomicU64,
+    cleared: u64,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("World")
+            .field("entities", &self.entities.alive.len())
+            .field("components", &self.columns.len())
+            .field("resources", &self.resources.len())
+            .finish()
+    }
+}
+
+impl World {
+    /// new returns a new empty world.
+    pub fn new() -> Self {
+        World {
+            entities: Entities::default(),
+            columns: HashMap::new(),
+            resources: HashMap::new(),
+            tick: AtomicU64::new(1),
+            cleared: 0,
+        }
+    }
+
+    /// len returns the number of living entities.
+    pub fn len(&self) -> usize {
+        self.entities.alive.len()
+    }
+
+    /// is_empty tells if there are no living entities.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// entities returns the living entities, in no particular order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities.alive
+    }
+
+    /// spawn creates a new entity with the given components, returning it.
+    pub fn spawn<B: Bundle>(&mut self, components: B) -> Entity {
+        let e = self.entities.alloc();
+        components.insert(self, e);
+        e
+    }
+
+    /// despawn destroys the entity and its components, returning whether it
+    /// was alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for column in self.columns.values_mut() {
+            column.remove_entity(entity);
+        }
+        true
+    }
+
+    /// is_alive tells if the entity was spawned and not yet despawned.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    /// tick returns the current tick of the world's clock, which changes
+    /// are marked with.
+    pub fn tick(&self) -> u64 {
+        self.tick.load(Ordering::Relaxed)
+    }
+
+    // advance returns the current tick and advances the clock.
+    fn advance(&self) -> u64 {
+        self.tick.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// clear_trackers forgets changes so far as far as queries of the world
+    /// itself are concerned, so that `Changed` and `Added` filters only
+    /// match later changes. Systems track changes on their own.
+    pub fn clear_trackers(&mut self) {
+        self.cleared = self.advance();
+    }
+
+    fn column<T: Component>(&self) -> Option<&Column<T>> {
+        self.columns.get(&TypeId::of::<T>()).map(|c| c.as_any().downcast_ref().unwrap())
+    }
+
+    fn column_mut<T: Component>(&mut self) -> &mut Column<T> {
+        self.columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Column::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// insert attaches the component to the entity, returning the component
+    /// of the same type it replaced, if any.
+    ///
+    /// # Panics
+    ///
+    /// insert panics if the entity isn't alive.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> Option<T> {
+        assert!(self.is_alive(entity), "World::insert: entity {} isn't alive", entity);
+        let tick = self.tick();
+        self.column_mut().insert(entity, component, tick)
+    }
+
+    /// insert_bundle attaches the components to the entity.
+    ///
+    /// # Panics
+    ///
+    /// insert_bundle panics if the entity isn't alive.
+    pub fn insert_bundle<B: Bundle>(&mut self, entity: Entity, components: B) {
+        assert!(self.is_alive(entity), "World::insert_bundle: entity {} isn't alive", entity);
+        components.insert(self, entity);
+    }
+
+    /// remove detaches the component of the given type from the entity,
+    /// returning it.
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.columns.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut::<Column<T>>().unwrap().remove(entity)
+    }
+
+    /// has tells if the entity has a component of the given type.
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.column::<T>().is_some_and(|c| c.index(entity).is_some())
+    }
+
+    /// get returns the entity's component of the given type, if it has one.
+    ///
+    /// # Panics
+    ///
+    /// get panics if components of the type are borrowed mutably.
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        let column = self.column::<T>()?;
+        let index = column.index(entity)?;
+        Some(Ref {
+            data: read(&column.data),
+            index,
+        })
+    }
+
+    /// get_mut returns the entity's component of the given type mutably, if
+    /// it has one.
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<Mut<'_, T>> {
+        let tick = self.tick();
+        let column = self.columns.get_mut(&TypeId::of::<T>())?.as_any_mut().downcast_mut::<Column<T>>().unwrap();
+        let index = column.index(entity)?;
+        Some(Mut {
+            value: &mut column.data.get_mut().unwrap_or_else(|e| e.into_inner())[index],
+            changed: &column.changed[index],
+            tick,
+        })
+    }
+
+    /// query returns a query of the entities with the components of Q
+    /// which pass the filter F. Its `Changed` and `Added` filters match
+    /// changes since the trackers were last cleared.
+    ///
+    /// # Panics
+    ///
+    /// query panics if the components it borrows mutably are borrowed
+    /// elsewhere, or those it reads are borrowed mutably.
+    pub fn query<Q: QueryData, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        Query::new(self, self.cleared, self.tick())
+    }
+
+    /// insert_resource inserts the resource, returning the one of the same
+    /// type it replaced, if any.
+    pub fn insert_resource<R: Component>(&mut self, resource: R) -> Option<R> {
+        self.resources
+            .insert(TypeId::of::<R>(), Box::new(RwLock::new(resource)))
+            .map(|old| old.downcast::<RwLock<R>>().unwrap().into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// remove_resource removes the resource of the given type, returning it.
+    pub fn remove_resource<R: Component>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .map(|old| old.downcast::<RwLock<R>>().unwrap().into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// has_resource tells if there's a resource of the given type.
+    pub fn has_resource<R: Component>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// resource returns the resource of the given type, if there is one.
+    ///
+    /// # Panics
+    ///
+    /// resource panics if the resource is borrowed mutably.
+    pub fn resource<R: Component>(&self) -> Option<RwLockReadGuard<'_, R>> {
+        self.resources.get(&TypeId::of::<R>()).map(|r| read(r.downcast_ref::<RwLock<R>>().unwrap()))
+    }
+
+    /// resource_mut returns the resource of the given type mutably, if there
+    /// is one.
+    ///
+    /// # Panics
+    ///
+    /// resource_mut panics if the resource is borrowed.
+    pub fn resource_mut<R: Component>(&self) -> Option<RwLockWriteGuard<'_, R>> {
+        self.resources.get(&TypeId::of::<R>()).map(|r| write(r.downcast_ref::<RwLock<R>>().unwrap()))
+    }
+
+    /// run_system runs the system on the world, then applies its commands.
+    pub fn run_system(&mut self, system: &mut System) {
+        let mut commands = Commands::new();
+        system.run(self, &mut commands);
+        commands.apply(self);
+    }
+}
+
+// read locks the value for reading, panicking if it's locked for writing.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    use std::sync::TryLockError;
+    match lock.try_read() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed mutably", type_name::<T>()),
+    }
+}
+
+// write locks the value for writing, panicking if it's locked.
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    use std::sync::TryLockError;
+    match lock.try_write() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed", type_name::<T>()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Transform, Vec3};
+    use std::sync::atomic::Ordering;
+    use super::{Added, Changed, Entity, System, Velocity, With, Without, World};
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+    struct Frozen;
+
+    #[test]
+    fn entities() {
+        let mut world = World::new();
+        let a = world.spawn((Name("a"), 1u32));
+        let b = world.spawn((Name("b"),));
+        assert_eq!(world.len(), 2);
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        assert!(!world.has::<u32>(a) && world.get::<Name>(a).is_none());
+
+        // Indices are reused with a new generation.
+        let c = world.spawn(());
+        assert_eq!((c.index(), c.generation()), (a.index(), 1));
+        assert!(!world.is_alive(a) && world.is_alive(c));
+        assert!(world.get::<Name>(c).is_none());
+        assert_eq!(world.insert(c, Name("c")), None);
+        assert_eq!(world.insert(c, Name("d")), Some(Name("c")));
+        assert_eq!(world.remove::<Name>(b), Some(Name("b")));
+        assert_eq!(world.remove::<Name>(b), None);
+        assert_eq!(world.query::<(Entity, &Name), ()>().entities(), vec![c]);
+        let mut names = Vec::new();
+        world.query::<&Name, ()>().for_each(|n| names.push(n.0));
+        assert_eq!(names, vec!["d"]);
+    }
+
+    #[test]
+    fn queries() {
+        let mut world = World::new();
+        let moving = Velocity { linear: Vec3(1.0, 0.0, 0.0), ..Velocity::default() };
+        let a = world.spawn((Name("a"), Transform::<f32>::identity(), moving));
+        let b = world.spawn((Name("b"), Transform::<f32>::identity(), moving, Frozen));
+        let c = world.spawn((Name("c"), Transform::<f32>::identity()));
+
+        // Filters.
+        assert_eq!(world.query::<&Name, With<Velocity>>().count(), 2);
+        assert_eq!(world.query::<Entity, (With<Velocity>, Without<Frozen>)>().entities(), vec![a]);
+        let mut optional = Vec::new();
+        world.query::<(&Name, Option<&Velocity>), ()>().for_each(|(n, v)| optional.push((n.0, v.is_some())));
+        optional.sort();
+        assert_eq!(optional, vec![("a", true), ("b", true), ("c", false)]);
+        assert!(world.query::<&Name, With<String>>().entities().is_empty());
+        assert!(world.query::<&String, ()>().get(a).is_none());
+
+        // Mutation through queries.
+        world.query::<(&mut Transform<f32>, &Velocity), Without<Frozen>>().for_each(|(mut t, v)| {
+            v.integrate(&mut t, 2.0);
+        });
+        assert_eq!(world.get::<Transform<f32>>(a).unwrap().translation, Vec3(2.0, 0.0, 0.0));
+        assert_eq!(world.get::<Transform<f32>>(b).unwrap().translation, Vec3(0.0, 0.0, 0.0));
+        let mut q = world.query::<&mut Name, ()>();
+        q.get(c).unwrap().0 = "z";
+        drop(q);
+        assert_eq!(*world.get::<Name>(c).unwrap(), Name("z"));
+
+        // Change detection, from the world's point of view.
+        world.clear_trackers();
+        assert_eq!(world.query::<Entity, Changed<Name>>().count(), 0);
+        world.get_mut::<Name>(b).unwrap().0 = "y";
+        let _ = world.get_mut::<Name>(a).unwrap();
+        let d = world.spawn((Name("d"),));
+        assert_eq!(world.query::<Entity, Changed<Name>>().entities(), vec![b, d]);
+        assert_eq!(world.query::<Entity, Added<Name>>().entities(), vec![d]);
+    }
+
+    #[test]
+    fn systems() {
+        let mut world = World::new();
+        world.insert_resource(0u32);
+        let a = world.spawn((Name("a"),));
+
+        // A system counting changed names, and despawning renamed entities.
+        let mut counter = System::new("counter", |ctx| {
+            let mut n = 0;
+            ctx.query::<(Entity, &Name), Changed<Name>>().for_each(|(e, name)| {
+                n += 1;
+                if name.0 == "gone" {
+                    ctx.commands().despawn(e);
+                }
+            });
+            *ctx.resource_mut::<u32>().unwrap() = n;
+        })
+            .read::<Name>()
+            .write_resource::<u32>();
+        let mut renamer = System::new("renamer", |ctx| {
+            ctx.query::<&mut Name, ()>().for_each(|mut n| {
+                if n.0 == "b" {
+                    n.0 = "gone";
+                }
+            });
+            ctx.commands().spawn((Name("b"),));
+        })
+            .write::<Name>();
+        assert!(!counter.access().is_compatible(renamer.access()));
+
+        world.run_system(&mut counter);
+        assert_eq!(*world.resource::<u32>().unwrap(), 1);
+        world.run_system(&mut counter);
+        assert_eq!(*world.resource::<u32>().unwrap(), 0);
+        world.run_system(&mut renamer);
+        world.run_system(&mut counter);
+        assert_eq!(*world.resource::<u32>().unwrap(), 1);
+        world.run_system(&mut renamer);
+        world.run_system(&mut counter);
+        assert_eq!(*world.resource::<u32>().unwrap(), 2);
+        assert_eq!(world.len(), 2);
+        assert!(world.is_alive(a));
+        assert_eq!(world.remove_resource::<u32>(), Some(2));
+    }
+
+    #[test]
+    fn ticks_past_u32() {
+        // Changes are still seen once the clock passes what fits in a u32.
+        let mut world = World::new();
+        world.tick.store(u32::MAX as u64 - 2, Ordering::Relaxed);
+        let a = world.spawn((Name("a"),));
+        let mut renamer = System::new("renamer", |ctx| {
+            ctx.query::<&mut Name, ()>().for_each(|mut n| n.0 = "b");
+        })
+            .write::<Name>();
+        let mut counter = System::new("counter", |ctx| {
+            let changed = ctx.query::<&Name, Changed<Name>>().count();
+            let added = ctx.query::<&Name, Added<Name>>().count();
+            ctx.resource_mut::<Vec<(usize, usize)>>().unwrap().push((changed, added));
+        })
+            .read::<Name>()
+            .write_resource::<Vec<(usize, usize)>>();
+        world.insert_resource(Vec::<(usize, usize)>::new());
+        for _ in 0..3 {
+            world.run_system(&mut renamer);
+            world.run_system(&mut counter);
+        }
+        world.run_system(&mut counter);
+        assert_eq!(*world.resource::<Vec<(usize, usize)>>().unwrap(), vec![(1, 1), (1, 0), (1, 0), (0, 0)]);
+        assert!(world.tick() > u32::MAX as u64);
+
+        world.clear_trackers();
+        assert_eq!(world.query::<Entity, Changed<Name>>().count(), 0);
+        assert!(!world.get_mut::<Name>(a).unwrap().is_changed());
+        world.get_mut::<Name>(a).unwrap().0 = "c";
+        assert_eq!(world.query::<Entity, Changed<Name>>().entities(), vec![a]);
+        assert_eq!(world.query::<Entity, Added<Name>>().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't declare")]
+    fn undeclared_access() {
+        let mut world = World::new();
+        let mut system = System::new("sneaky", |ctx| {
+            ctx.query::<&mut Name, ()>().for_each(|_| ());
+        })
+            .read::<Name>();
+        world.run_system(&mut system);
+    }
+}
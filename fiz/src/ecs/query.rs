@@ -0,0 +1,375 @@
+use std::marker::PhantomData;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use super::storage::Column;
+use super::{read, write, Access, Component, Entity, Mut, World};
+
+/// QueryData is the data a query fetches for each entity, implemented for
+/// `Entity`, `&T` and `&mut T` for components T, `Option` of those, and
+/// tuples of up to eight of them. Entities match if they have all the
+/// components which aren't optional.
+///
+/// Components borrowed mutably are fetched as `Mut`, which marks them as
+/// changed when written to.
+pub trait QueryData {
+    /// The borrows the query holds while it's alive.
+    type State<'w>;
+
+    /// The data fetched for each entity.
+    type Item<'a>;
+
+    /// access adds the components the query reads and writes.
+    fn access(access: &mut Access);
+
+    /// borrow borrows the components of the query from the world.
+    fn borrow(world: &World) -> Self::State<'_>;
+
+    /// candidates returns the entities the query may match, or None if it
+    /// doesn't narrow them down from all entities.
+    fn candidates<'s>(state: &'s Self::State<'_>) -> Option<&'s [Entity]>;
+
+    /// matches tells if the entity has the components of the query.
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool;
+
+    /// fetch returns the data of a matching entity, marking changes with
+    /// the given tick.
+    fn fetch<'a>(state: &'a mut Self::State<'_>, entity: Entity, tick: u64) -> Self::Item<'a>;
+}
+
+/// QueryFilter is a condition which entities must pass to match a query,
+/// implemented for `With`, `Without`, `Changed` and `Added`, and tuples of
+/// up to eight of them, which must all pass. `()` passes all entities.
+pub trait QueryFilter {
+    /// The borrows the filter holds while it's alive.
+    type State<'w>;
+
+    /// access adds the components the filter reads.
+    fn access(access: &mut Access);
+
+    /// borrow borrows what the filter needs from the world.
+    fn borrow(world: &World) -> Self::State<'_>;
+
+    /// matches tells if the entity passes the filter, given the tick since
+    /// which changes count.
+    fn matches(state: &Self::State<'_>, entity: Entity, since: u64) -> bool;
+}
+
+impl QueryData for Entity {
+    type State<'w> = ();
+    type Item<'a> = Entity;
+
+    fn access(_: &mut Access) {}
+
+    fn borrow(_: &World) {}
+
+    fn candidates(_: &()) -> Option<&[Entity]> {
+        None
+    }
+
+    fn matches(_: &(), _: Entity) -> bool {
+        true
+    }
+
+    fn fetch(_: &mut (), entity: Entity, _: u64) -> Entity {
+        entity
+    }
+}
+
+impl<T: Component> QueryData for &T {
+    type State<'w> = Option<(&'w Column<T>, RwLockReadGuard<'w, Vec<T>>)>;
+    type Item<'a> = &'a T;
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>().map(|c| (c, read(&c.data)))
+    }
+
+    fn candidates<'s>(state: &'s Self::State<'_>) -> Option<&'s [Entity]> {
+        Some(state.as_ref().map_or(&[], |s| &s.0.entities))
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+        state.as_ref().is_some_and(|s| s.0.index(entity).is_some())
+    }
+
+    fn fetch<'a>(state: &'a mut Self::State<'_>, entity: Entity, _: u64) -> &'a T {
+        let (column, data) = state.as_ref().unwrap();
+        &data[column.index(entity).unwrap()]
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type State<'w> = Option<(&'w Column<T>, RwLockWriteGuard<'w, Vec<T>>)>;
+    type Item<'a> = Mut<'a, T>;
+
+    fn access(access: &mut Access) {
+        access.write::<T>();
+    }
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>().map(|c| (c, write(&c.data)))
+    }
+
+    fn candidates<'s>(state: &'s Self::State<'_>) -> Option<&'s [Entity]> {
+        Some(state.as_ref().map_or(&[], |s| &s.0.entities))
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+        state.as_ref().is_some_and(|s| s.0.index(entity).is_some())
+    }
+
+    fn fetch<'a>(state: &'a mut Self::State<'_>, entity: Entity, tick: u64) -> Mut<'a, T> {
+        let (column, data) = state.as_mut().unwrap();
+        let i = column.index(entity).unwrap();
+        Mut {
+            value: &mut data[i],
+            changed: &column.changed[i],
+            tick,
+        }
+    }
+}
+
+impl<Q: QueryData> QueryData for Option<Q> {
+    type State<'w> = Q::State<'w>;
+    type Item<'a> = Option<Q::Item<'a>>;
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        Q::borrow(world)
+    }
+
+    fn candidates<'s>(_: &'s Self::State<'_>) -> Option<&'s [Entity]> {
+        None
+    }
+
+    fn matches(_: &Self::State<'_>, _: Entity) -> bool {
+        true
+    }
+
+    fn fetch<'a>(state: &'a mut Self::State<'_>, entity: Entity, tick: u64) -> Self::Item<'a> {
+        if Q::matches(state, entity) { Some(Q::fetch(state, entity, tick)) } else { None }
+    }
+}
+
+macro_rules! impl_query_data {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type State<'w> = ($($name::State<'w>,)*);
+            type Item<'a> = ($($name::Item<'a>,)*);
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            fn borrow(world: &World) -> Self::State<'_> {
+                ($($name::borrow(world),)*)
+            }
+
+            // The smallest set of candidates of any element.
+            fn candidates<'s>(state: &'s Self::State<'_>) -> Option<&'s [Entity]> {
+                let mut best: Option<&'s [Entity]> = None;
+                $(
+                    if let Some(c) = $name::candidates(&state.$index) {
+                        if best.is_none_or(|b| c.len() < b.len()) {
+                            best = Some(c);
+                        }
+                    }
+                )*
+                best
+            }
+
+            fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+                $($name::matches(&state.$index, entity) &&)* true
+            }
+
+            fn fetch<'a>(state: &'a mut Self::State<'_>, entity: Entity, tick: u64) -> Self::Item<'a> {
+                ($($name::fetch(&mut state.$index, entity, tick),)*)
+            }
+        }
+    }
+}
+
+impl_query_data!(A: 0);
+impl_query_data!(A: 0, B: 1);
+impl_query_data!(A: 0, B: 1, C: 2);
+impl_query_data!(A: 0, B: 1, C: 2, D: 3);
+impl_query_data!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_query_data!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// With is a filter passing entities which have a component of type T.
+pub struct With<T>(PhantomData<T>);
+
+/// Without is a filter passing entities which don't have a component of
+/// type T.
+pub struct Without<T>(PhantomData<T>);
+
+/// Changed is a filter passing entities whose component of type T was
+/// added or written to since the system last ran.
+pub struct Changed<T>(PhantomData<T>);
+
+/// Added is a filter passing entities whose component of type T was added
+/// since the system last ran.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    type State<'w> = Option<&'w Column<T>>;
+
+    // Which entities have a component only changes with exclusive access to
+    // the world, so presence doesn't borrow the components.
+    fn access(_: &mut Access) {}
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity, _: u64) -> bool {
+        state.is_some_and(|c| c.index(entity).is_some())
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State<'w> = Option<&'w Column<T>>;
+
+    fn access(_: &mut Access) {}
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity, _: u64) -> bool {
+        !state.is_some_and(|c| c.index(entity).is_some())
+    }
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State<'w> = Option<&'w Column<T>>;
+
+    // Change ticks aren't locked, but are written by systems writing T.
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity, since: u64) -> bool {
+        state.is_some_and(|c| c.index(entity).is_some_and(|i| c.changed_since(i, since)))
+    }
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    type State<'w> = Option<&'w Column<T>>;
+
+    fn access(_: &mut Access) {}
+
+    fn borrow(world: &World) -> Self::State<'_> {
+        world.column::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity, since: u64) -> bool {
+        state.is_some_and(|c| c.index(entity).is_some_and(|i| c.added[i] > since))
+    }
+}
+
+macro_rules! impl_query_filter {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type State<'w> = ($($name::State<'w>,)*);
+
+            #[allow(unused_variables)]
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            #[allow(unused_variables, clippy::unused_unit)]
+            fn borrow(world: &World) -> Self::State<'_> {
+                ($($name::borrow(world),)*)
+            }
+
+            #[allow(unused_variables)]
+            fn matches(state: &Self::State<'_>, entity: Entity, since: u64) -> bool {
+                $($name::matches(&state.$index, entity, since) &&)* true
+            }
+        }
+    }
+}
+
+impl_query_filter!();
+impl_query_filter!(A: 0);
+impl_query_filter!(A: 0, B: 1);
+impl_query_filter!(A: 0, B: 1, C: 2);
+impl_query_filter!(A: 0, B: 1, C: 2, D: 3);
+impl_query_filter!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_query_filter!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_query_filter!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_query_filter!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Query is a borrow of the components of the entities which have those of
+/// Q and pass the filter F, returned by `World::query` and
+/// `SystemContext::query`. The components stay borrowed until the query is
+/// dropped.
+///
+/// Entities are visited in the order they're stored, which only depends on
+/// the order of earlier changes to the world.
+pub struct Query<'w, Q: QueryData, F: QueryFilter = ()> {
+    world: &'w World,
+    data: Q::State<'w>,
+    filter: F::State<'w>,
+    since: u64,
+    tick: u64,
+}
+
+impl<'w, Q: QueryData, F: QueryFilter> Query<'w, Q, F> {
+    // new borrows the query's components from the world, for filters
+    // passing changes after since and changes marked with tick.
+    pub(super) fn new(world: &'w World, since: u64, tick: u64) -> Self {
+        Query {
+            world,
+            data: Q::borrow(world),
+            filter: F::borrow(world),
+            since,
+            tick,
+        }
+    }
+
+    fn matches(&self, entity: Entity) -> bool {
+        Q::matches(&self.data, entity) && F::matches(&self.filter, entity, self.since)
+    }
+
+    /// entities returns the matching entities.
+    pub fn entities(&self) -> Vec<Entity> {
+        let candidates = Q::candidates(&self.data).unwrap_or(self.world.entities());
+        candidates.iter().cloned().filter(|&e| self.matches(e)).collect()
+    }
+
+    /// count returns the number of matching entities.
+    pub fn count(&self) -> usize {
+        let candidates = Q::candidates(&self.data).unwrap_or(self.world.entities());
+        candidates.iter().filter(|&&e| self.matches(e)).count()
+    }
+
+    /// get returns the data of the entity, or None if it doesn't match.
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        if !self.world.is_alive(entity) || !self.matches(entity) {
+            return None;
+        }
+        Some(Q::fetch(&mut self.data, entity, self.tick))
+    }
+
+    /// for_each calls f with the data of each matching entity.
+    pub fn for_each<G>(&mut self, mut f: G)
+        where G: FnMut(Q::Item<'_>)
+    {
+        for entity in self.entities() {
+            f(Q::fetch(&mut self.data, entity, self.tick));
+        }
+    }
+}
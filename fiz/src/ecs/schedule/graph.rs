@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+use super::super::System;
+use super::{ScheduleError, Stage};
+
+// Graph is the order of the systems of a stage: an edge from a to b means b
+// runs after a finishes.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    // The edges, and whether each was given by labels rather than added
+    // because the systems conflict.
+    pub edges: Vec<(usize, usize, bool)>,
+    pub dependents: Vec<Vec<usize>>,
+    pub dependencies: Vec<usize>,
+
+    // The order the systems run in on a single thread.
+    pub order: Vec<usize>,
+}
+
+impl Graph {
+    // build returns the graph of the systems of a stage, in the order they
+    // were added. Conflicting systems which the labels don't order run in
+    // the order they were added.
+    pub fn build(stage: Stage, systems: &[System]) -> Result<Graph, ScheduleError> {
+        let n = systems.len();
+        let mut graph = Graph {
+            edges: Vec::new(),
+            dependents: vec![Vec::new(); n],
+            dependencies: vec![0; n],
+            order: Vec::new(),
+        };
+        for (i, system) in systems.iter().enumerate() {
+            let (before, after) = system.ordering();
+            for (labels, forward) in [(before, true), (after, false)] {
+                for label in labels {
+                    let matching: Vec<usize> = (0..n).filter(|&j| j != i && systems[j].has_label(label)).collect();
+                    if matching.is_empty() {
+                        return Err(ScheduleError::UnknownLabel {
+                            system: system.name().to_string(),
+                            label: label.clone(),
+                        });
+                    }
+                    for j in matching {
+                        graph.add(if forward { (i, j) } else { (j, i) }, true);
+                    }
+                }
+            }
+        }
+        if let Err(cycle) = graph.sort() {
+            return Err(ScheduleError::Cycle {
+                stage,
+                systems: cycle.into_iter().map(|i| systems[i].name().to_string()).collect(),
+            });
+        }
+
+        // Order conflicting systems which aren't ordered already. As added
+        // edges go from earlier systems to later ones, and only between
+        // systems without a path between them, the graph stays acyclic.
+        let mut reach = graph.closure();
+        for a in 0..n {
+            for b in a + 1..n {
+                if reach[a][b] || reach[b][a] || systems[a].access().is_compatible(systems[b].access()) {
+                    continue;
+                }
+                graph.add((a, b), false);
+                for x in (0..n).filter(|&x| x == a || reach[x][a]).collect::<Vec<_>>() {
+                    for y in (0..n).filter(|&y| y == b || reach[b][y]).collect::<Vec<_>>() {
+                        reach[x][y] = true;
+                    }
+                }
+            }
+        }
+        graph.sort().expect("Graph::build: conflict ordering made a cycle");
+        Ok(graph)
+    }
+
+    fn add(&mut self, (a, b): (usize, usize), explicit: bool) {
+        if self.dependents[a].contains(&b) {
+            return;
+        }
+        self.edges.push((a, b, explicit));
+        self.dependents[a].push(b);
+        self.dependencies[b] += 1;
+    }
+
+    // sort sets the order to a topological sort of the graph, taking the
+    // earliest added of the systems ready to run first, or returns the
+    // systems on or after a cycle.
+    fn sort(&mut self) -> Result<(), Vec<usize>> {
+        let n = self.dependents.len();
+        let mut remaining = self.dependencies.clone();
+        let mut ready: BTreeSet<usize> = (0..n).filter(|&i| remaining[i] == 0).collect();
+        self.order.clear();
+        while let Some(i) = ready.pop_first() {
+            self.order.push(i);
+            for &j in &self.dependents[i] {
+                remaining[j] -= 1;
+                if remaining[j] == 0 {
+                    ready.insert(j);
+                }
+            }
+        }
+        if self.order.len() < n {
+            return Err((0..n).filter(|&i| remaining[i] > 0).collect());
+        }
+        Ok(())
+    }
+
+    // closure returns whether there's a path from each system to each other.
+    fn closure(&self) -> Vec<Vec<bool>> {
+        let n = self.dependents.len();
+        let mut reach = vec![vec![false; n]; n];
+        // Visit systems in reverse topological order, so that dependents are
+        // complete first.
+        for &i in self.order.iter().rev() {
+            for &j in &self.dependents[i] {
+                let below = reach[j].clone();
+                reach[i][j] = true;
+                for (r, b) in reach[i].iter_mut().zip(below) {
+                    *r |= b;
+                }
+            }
+        }
+        reach
+    }
+}
@@ -0,0 +1,534 @@
+//! Scheduling of systems.
+//!
+//! A `Schedule` runs systems in named stages, one stage after another. The
+//! systems of a stage run in parallel unless the components or resources
+//! they access conflict, in which case they run in the order they were
+//! added, or they're ordered by labels with `System::before` and
+//! `System::after`. Commands of the systems of a stage are applied at its
+//! end, in the order the systems were added.
+
+mod graph;
+
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use super::{Commands, System, World};
+use self::graph::Graph;
+
+/// Stage is a step of a `Schedule`. Stages run in the order they're listed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    /// The stage for preparing the update, such as handling input.
+    PreUpdate,
+
+    /// The stage for the update of the game logic.
+    Update,
+
+    /// The stage for reacting to the update, such as updating transforms.
+    PostUpdate,
+
+    /// The stage for rendering.
+    Render,
+}
+
+impl Stage {
+    /// ALL is every stage, in the order they run.
+    pub const ALL: [Stage; 4] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate, Stage::Render];
+
+    /// name returns the name of the stage, like "pre-update".
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::PreUpdate => "pre-update",
+            Stage::Update => "update",
+            Stage::PostUpdate => "post-update",
+            Stage::Render => "render",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// ScheduleError describes why the systems of a schedule can't be ordered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// A system is ordered relative to a label which no other system in its
+    /// stage has.
+    UnknownLabel {
+        /// The name of the system.
+        system: String,
+
+        /// The unknown label.
+        label: String,
+    },
+
+    /// The ordering of systems of a stage is circular.
+    Cycle {
+        /// The stage.
+        stage: Stage,
+
+        /// The names of the systems on, or ordered after, the cycle.
+        systems: Vec<String>,
+    },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScheduleError::UnknownLabel { ref system, ref label } => {
+                write!(f, "system {} is ordered relative to unknown label {}", system, label)
+            }
+            ScheduleError::Cycle { stage, ref systems } => {
+                write!(f, "ordering of systems in stage {} is circular: {}", stage, systems.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// Schedule runs systems on a world, in stages, in parallel where their
+/// access allows it.
+///
+/// With one thread, systems run in a deterministic order: that of a
+/// topological sort of each stage, taking the earliest added of the
+/// systems ready to run first.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::{Schedule, Stage, System, World};
+///
+/// struct Position(f32);
+/// struct Speed(f32);
+/// struct Log(Vec<String>);
+///
+/// let mut world = World::new();
+/// world.spawn((Position(0.0), Speed(2.0)));
+/// world.insert_resource(Log(Vec::new()));
+///
+/// let mut schedule = Schedule::new();
+/// schedule
+///     .add_system(Stage::Update, System::new("report", |ctx| {
+///         let mut log = ctx.resource_mut::<Log>().unwrap();
+///         ctx.query::<&Position, ()>().for_each(|p| log.0.push(format!("at {}", p.0)));
+///     })
+///         .read::<Position>()
+///         .write_resource::<Log>()
+///         .after("physics"))
+///     .add_system(Stage::Update, System::new("movement", |ctx| {
+///         ctx.query::<(&mut Position, &Speed), ()>().for_each(|(mut p, s)| p.0 += s.0);
+///     })
+///         .write::<Position>()
+///         .read::<Speed>()
+///         .label("physics"));
+///
+/// assert_eq!(schedule.order(Stage::Update).unwrap(), vec!["movement", "report"]);
+/// schedule.run(&mut world).unwrap();
+/// schedule.run(&mut world).unwrap();
+/// assert_eq!(world.resource::<Log>().unwrap().0, vec!["at 2", "at 4"]);
+/// ```
+#[derive(Debug)]
+pub struct Schedule {
+    stages: [Vec<System>; 4],
+    threads: usize,
+
+    // The graphs of the stages, built when first needed after systems are
+    // added.
+    graphs: [Option<Graph>; 4],
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::new()
+    }
+}
+
+impl Schedule {
+    /// new returns a new empty schedule, which runs systems on as many
+    /// threads as the machine has available.
+    pub fn new() -> Self {
+        Schedule {
+            stages: Default::default(),
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            graphs: Default::default(),
+        }
+    }
+
+    /// threads returns the maximum number of systems run at once.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// set_threads sets the maximum number of systems run at once. With one
+    /// thread (or zero), systems run on the calling thread, one after the
+    /// other.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// add_system adds the system to the end of the stage.
+    pub fn add_system(&mut self, stage: Stage, system: System) -> &mut Self {
+        self.stages[stage as usize].push(system);
+        self.graphs[stage as usize] = None;
+        self
+    }
+
+    /// systems returns the systems of the stage, in the order they were
+    /// added.
+    pub fn systems(&self, stage: Stage) -> &[System] {
+        &self.stages[stage as usize]
+    }
+
+    /// order returns the names of the systems of the stage in the order they
+    /// run with one thread.
+    pub fn order(&self, stage: Stage) -> Result<Vec<&str>, ScheduleError> {
+        let systems = &self.stages[stage as usize];
+        let graph = Graph::build(stage, systems)?;
+        Ok(graph.order.iter().map(|&i| systems[i].name()).collect())
+    }
+
+    /// run runs each stage on the world, in order. It doesn't run anything
+    /// unless the systems of every stage can be ordered.
+    ///
+    /// # Panics
+    ///
+    /// run panics if a system does. The systems running at the same time
+    /// finish first, but the rest of the schedule doesn't run.
+    pub fn run(&mut self, world: &mut World) -> Result<(), ScheduleError> {
+        for stage in Stage::ALL {
+            self.build(stage)?;
+        }
+        for stage in Stage::ALL {
+            self.run_stage(stage, world)?;
+        }
+        Ok(())
+    }
+
+    /// run_stage runs the systems of the stage on the world, then applies
+    /// their commands.
+    ///
+    /// # Panics
+    ///
+    /// run_stage panics if a system does.
+    pub fn run_stage(&mut self, stage: Stage, world: &mut World) -> Result<(), ScheduleError> {
+        self.build(stage)?;
+        let graph = self.graphs[stage as usize].as_ref().unwrap();
+        let systems = &mut self.stages[stage as usize];
+
+        // Criteria see the world as it is before the stage.
+        let run: Vec<bool> = systems.iter_mut().map(|s| s.should_run(world)).collect();
+        let mut commands: Vec<Commands> = systems.iter().map(|_| Commands::new()).collect();
+        if self.threads == 1 || systems.len() <= 1 {
+            for &i in &graph.order {
+                if run[i] {
+                    systems[i].run(world, &mut commands[i]);
+                }
+            }
+        } else {
+            run_parallel(graph, systems, &run, &mut commands, world, self.threads);
+        }
+        for c in &mut commands {
+            c.apply(world);
+        }
+        Ok(())
+    }
+
+    // build builds the graph of the stage, unless it's built already.
+    fn build(&mut self, stage: Stage) -> Result<(), ScheduleError> {
+        if self.graphs[stage as usize].is_none() {
+            self.graphs[stage as usize] = Some(Graph::build(stage, &self.stages[stage as usize])?);
+        }
+        Ok(())
+    }
+
+    /// to_dot returns the graph of the schedule in the DOT language of
+    /// Graphviz, with a cluster for each stage. Edges from labels are solid,
+    /// and those ordering conflicting systems are dashed and labeled with
+    /// the conflicting types.
+    pub fn to_dot(&self) -> Result<String, ScheduleError> {
+        let mut dot = String::from("digraph schedule {\n    node [shape=box];\n");
+        for stage in Stage::ALL {
+            let systems = &self.stages[stage as usize];
+            let graph = Graph::build(stage, systems)?;
+            let s = stage as usize;
+            writeln!(dot, "    subgraph cluster_{} {{", s).unwrap();
+            writeln!(dot, "        label=\"{}\";", stage).unwrap();
+            for (i, system) in systems.iter().enumerate() {
+                let mut label = escape(system.name());
+                let (reads, writes) = (system.access().reads(), system.access().writes());
+                if !reads.is_empty() {
+                    label += &format!("\\nreads: {}", escape(&reads.join(", ")));
+                }
+                if !writes.is_empty() {
+                    label += &format!("\\nwrites: {}", escape(&writes.join(", ")));
+                }
+                writeln!(dot, "        s{}_{} [label=\"{}\"];", s, i, label).unwrap();
+            }
+            for &(a, b, explicit) in &graph.edges {
+                if explicit {
+                    writeln!(dot, "        s{}_{} -> s{}_{};", s, a, s, b).unwrap();
+                } else {
+                    let conflicts = systems[a].access().conflicts(systems[b].access()).join(", ");
+                    writeln!(dot, "        s{}_{} -> s{}_{} [style=dashed, label=\"{}\"];", s, a, s, b,
+                             escape(&conflicts)).unwrap();
+                }
+            }
+            dot += "    }\n";
+        }
+        dot += "}\n";
+        Ok(dot)
+    }
+}
+
+// escape escapes the string for a quoted DOT identifier.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Slot is a system of a stage with its commands, taken by the thread
+// running it.
+type Slot<'s> = Option<(&'s mut System, &'s mut Commands)>;
+
+// Progress is the state of a stage run in parallel, shared by the threads
+// running it.
+struct Progress<'s> {
+    ready: BTreeSet<usize>,
+    remaining: Vec<usize>,
+    slots: Vec<Slot<'s>>,
+    done: usize,
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+// run_parallel runs the systems of the stage on up to the given number of
+// threads, each system once those it depends on have finished.
+fn run_parallel(graph: &Graph, systems: &mut [System], run: &[bool], commands: &mut [Commands], world: &World,
+                threads: usize) {
+    let n = systems.len();
+    let progress = Mutex::new(Progress {
+        ready: (0..n).filter(|&i| graph.dependencies[i] == 0).collect(),
+        remaining: graph.dependencies.clone(),
+        slots: systems.iter_mut().zip(commands.iter_mut()).map(Some).collect(),
+        done: 0,
+        panic: None,
+    });
+    let wake = Condvar::new();
+    thread::scope(|scope| {
+        for _ in 0..threads.min(n) {
+            scope.spawn(|| loop {
+                let (i, system, commands) = {
+                    let mut p = lock(&progress);
+                    loop {
+                        if p.done == n || p.panic.is_some() {
+                            return;
+                        }
+                        if let Some(i) = p.ready.pop_first() {
+                            let (system, commands) = p.slots[i].take().unwrap();
+                            break (i, system, commands);
+                        }
+                        p = wake.wait(p).unwrap_or_else(|e| e.into_inner());
+                    }
+                };
+                let result = if run[i] {
+                    panic::catch_unwind(AssertUnwindSafe(|| system.run(world, commands)))
+                } else {
+                    Ok(())
+                };
+                let mut p = lock(&progress);
+                p.done += 1;
+                match result {
+                    Ok(()) => {
+                        for &j in &graph.dependents[i] {
+                            p.remaining[j] -= 1;
+                            if p.remaining[j] == 0 {
+                                p.ready.insert(j);
+                            }
+                        }
+                    }
+                    Err(payload) => {
+                        p.panic.get_or_insert(payload);
+                    }
+                }
+                wake.notify_all();
+            });
+        }
+    });
+    if let Some(payload) = progress.into_inner().unwrap_or_else(|e| e.into_inner()).panic {
+        panic::resume_unwind(payload);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use std::any::type_name;
+    use std::sync::{Arc, Mutex};
+    use super::{Schedule, ScheduleError, Stage};
+    use super::super::{System, World};
+
+    struct A(u32);
+    struct B(u32);
+    struct C(u32);
+
+    // logger returns a system which appends its name to the shared log.
+    fn logger(name: &'static str, log: &Arc<Mutex<Vec<&'static str>>>) -> System {
+        let log = log.clone();
+        System::new(name, move |_| log.lock().unwrap().push(name))
+    }
+
+    #[test]
+    fn ordering() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut schedule = Schedule::new();
+        schedule.set_threads(1);
+        schedule
+            .add_system(Stage::Render, logger("draw", &log))
+            .add_system(Stage::Update, logger("c", &log).after("ab"))
+            .add_system(Stage::Update, logger("b", &log).label("ab"))
+            .add_system(Stage::Update, logger("a", &log).label("ab").before("b"))
+            .add_system(Stage::PreUpdate, logger("input", &log));
+        assert_eq!(schedule.order(Stage::Update).unwrap(), vec!["a", "b", "c"]);
+
+        // Conflicting systems keep the order they were added in.
+        schedule
+            .add_system(Stage::PostUpdate, logger("w2", &log).write::<A>())
+            .add_system(Stage::PostUpdate, logger("r", &log).read::<A>().read::<B>())
+            .add_system(Stage::PostUpdate, logger("w1", &log).write::<A>().before("w2"));
+        assert_eq!(schedule.order(Stage::PostUpdate).unwrap(), vec!["w1", "w2", "r"]);
+
+        let mut world = World::new();
+        schedule.run(&mut world).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["input", "a", "b", "c", "w1", "w2", "r", "draw"]);
+    }
+
+    #[test]
+    fn errors() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, logger("a", &log).after("missing"));
+        assert_eq!(schedule.run(&mut World::new()), Err(ScheduleError::UnknownLabel {
+            system: "a".to_string(),
+            label: "missing".to_string(),
+        }));
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Render, logger("draw", &log))
+            .add_system(Stage::Update, logger("a", &log).after("b"))
+            .add_system(Stage::Update, logger("b", &log).after("a"))
+            .add_system(Stage::Update, logger("c", &log))
+            .add_system(Stage::Update, logger("d", &log).after("b"));
+        assert_eq!(schedule.order(Stage::Update), Err(ScheduleError::Cycle {
+            stage: Stage::Update,
+            systems: vec!["a".to_string(), "b".to_string(), "d".to_string()],
+        }));
+        assert!(schedule.to_dot().is_err());
+
+        // Nothing runs, not even the stages which could be.
+        assert!(schedule.run(&mut World::new()).is_err());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn criteria() {
+        struct Paused(bool);
+
+        let mut world = World::new();
+        world.insert_resource(Paused(false));
+        world.insert_resource(0u32);
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(Stage::Update, System::new("count", |ctx| *ctx.resource_mut::<u32>().unwrap() += 1)
+                .write_resource::<u32>()
+                .run_if(|world| !world.resource::<Paused>().unwrap().0))
+            .add_system(Stage::Update, System::new("pause", |ctx| ctx.commands().insert_resource(Paused(true))));
+        schedule.run(&mut world).unwrap();
+        schedule.run(&mut world).unwrap();
+        assert_eq!(*world.resource::<u32>().unwrap(), 1);
+    }
+
+    // build returns a world and a schedule of systems which would give
+    // different results if run in the wrong order.
+    fn build(threads: usize) -> (World, Schedule) {
+        let mut world = World::new();
+        for i in 0..100 {
+            world.spawn((A(i), B(0), C(0)));
+        }
+        let mut schedule = Schedule::new();
+        schedule.set_threads(threads);
+        schedule
+            .add_system(Stage::Update, System::new("a to b", |ctx| {
+                ctx.query::<(&A, &mut B), ()>().for_each(|(a, mut b)| b.0 = b.0.wrapping_mul(3).wrapping_add(a.0));
+            }).read::<A>().write::<B>())
+            .add_system(Stage::Update, System::new("a to c", |ctx| {
+                ctx.query::<(&A, &mut C), ()>().for_each(|(a, mut c)| c.0 = c.0.wrapping_mul(5).wrapping_add(a.0));
+            }).read::<A>().write::<C>())
+            .add_system(Stage::Update, System::new("b and c to a", |ctx| {
+                ctx.query::<(&mut A, &B, &C), ()>()
+                    .for_each(|(mut a, b, c)| a.0 = a.0.wrapping_mul(7).wrapping_add(b.0 ^ c.0));
+            }).write::<A>().read::<B>().read::<C>())
+            .add_system(Stage::Update, System::new("spawn", |ctx| ctx.commands().spawn((A(1), B(0), C(0)))))
+            .add_system(Stage::Update, System::new("b to c", |ctx| {
+                ctx.query::<(&B, &mut C), ()>().for_each(|(b, mut c)| c.0 = c.0.wrapping_mul(11).wrapping_add(b.0));
+            }).read::<B>().write::<C>().before("a to c"));
+        (world, schedule)
+    }
+
+    #[test]
+    fn parallel() {
+        let (mut single, mut schedule) = build(1);
+        for _ in 0..10 {
+            schedule.run(&mut single).unwrap();
+        }
+        let expected: Vec<(u32, u32, u32)> = single.entities().iter().map(|&e| {
+            (single.get::<A>(e).unwrap().0, single.get::<B>(e).unwrap().0, single.get::<C>(e).unwrap().0)
+        }).collect();
+        assert_eq!(expected.len(), 110);
+
+        for _ in 0..5 {
+            let (mut world, mut schedule) = build(4);
+            for _ in 0..10 {
+                schedule.run(&mut world).unwrap();
+            }
+            let values: Vec<(u32, u32, u32)> = world.entities().iter().map(|&e| {
+                (world.get::<A>(e).unwrap().0, world.get::<B>(e).unwrap().0, world.get::<C>(e).unwrap().0)
+            }).collect();
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "oops")]
+    fn parallel_panic() {
+        let mut schedule = Schedule::new();
+        schedule.set_threads(2);
+        schedule
+            .add_system(Stage::Update, System::new("a", |_| panic!("oops")))
+            .add_system(Stage::Update, System::new("b", |_| {}));
+        schedule.run(&mut World::new()).unwrap();
+    }
+
+    #[test]
+    fn dot() {
+        let (_, schedule) = build(1);
+        let dot = schedule.to_dot().unwrap();
+        assert!(dot.starts_with("digraph schedule {"));
+        assert!(dot.contains("subgraph cluster_1 {\n        label=\"update\";"));
+        assert!(dot.contains("s1_4 [label=\"b to c\\nreads: "));
+        assert!(dot.contains("s1_4 -> s1_1;"));
+        let edge = dot.lines().find(|l| l.contains("s1_0 -> s1_2 [style=dashed")).unwrap();
+        assert!(edge.contains(type_name::<A>()) && edge.contains(type_name::<B>()));
+    }
+}
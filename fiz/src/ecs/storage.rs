@@ -0,0 +1,106 @@
+use std::any::Any;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use super::{Component, Entity};
+
+// AnyColumn is a column of any component type, so that the world can
+// remove the components of despawned entities without knowing their types.
+pub(super) trait AnyColumn: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn remove_entity(&mut self, entity: Entity);
+}
+
+// Column is a sparse set holding the components of one type: a sparse
+// array from entity index to a position in the dense arrays, which are
+// packed for iteration.
+//
+// The layout only changes with exclusive access to the world, while the
+// components themselves are locked, so systems may borrow them from a
+// shared world. Change ticks are atomic so that change detection doesn't
+// need to lock the components.
+//
+// Column is public only so that the states of queries can name it; it
+// can't be reached from outside the crate.
+pub struct Column<T> {
+    pub sparse: Vec<Option<usize>>,
+    pub entities: Vec<Entity>,
+    pub added: Vec<u64>,
+    pub changed: Vec<AtomicU64>,
+    pub data: RwLock<Vec<T>>,
+}
+
+impl<T: Component> Column<T> {
+    pub fn new() -> Self {
+        Column {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            added: Vec::new(),
+            changed: Vec::new(),
+            data: RwLock::new(Vec::new()),
+        }
+    }
+
+    // index returns the position of the entity's component in the dense
+    // arrays.
+    pub fn index(&self, entity: Entity) -> Option<usize> {
+        let i = self.sparse.get(entity.index as usize).copied().flatten()?;
+        if self.entities[i] == entity { Some(i) } else { None }
+    }
+
+    // insert inserts or replaces the entity's component, returning the old
+    // one.
+    pub fn insert(&mut self, entity: Entity, component: T, tick: u64) -> Option<T> {
+        let data = self.data.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = self.sparse.get(entity.index as usize).copied().flatten() {
+            if self.entities[i] == entity {
+                self.changed[i] = AtomicU64::new(tick);
+                return Some(::std::mem::replace(&mut data[i], component));
+            }
+        }
+        let index = entity.index as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.entities.len());
+        self.entities.push(entity);
+        self.added.push(tick);
+        self.changed.push(AtomicU64::new(tick));
+        data.push(component);
+        None
+    }
+
+    // remove removes the entity's component, moving the last one into its
+    // place.
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let i = self.index(entity)?;
+        self.sparse[entity.index as usize] = None;
+        self.entities.swap_remove(i);
+        self.added.swap_remove(i);
+        self.changed.swap_remove(i);
+        let component = self.data.get_mut().unwrap_or_else(|e| e.into_inner()).swap_remove(i);
+        if let Some(&moved) = self.entities.get(i) {
+            self.sparse[moved.index as usize] = Some(i);
+        }
+        Some(component)
+    }
+
+    // changed_since tells if the entity's component changed after the tick.
+    pub fn changed_since(&self, i: usize, tick: u64) -> bool {
+        self.changed[i].load(Ordering::Relaxed) > tick
+    }
+}
+
+impl<T: Component> AnyColumn for Column<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+}
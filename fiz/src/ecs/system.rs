@@ -0,0 +1,391 @@
+use std::any::{type_name, TypeId};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+use super::{Commands, Component, Query, QueryData, QueryFilter, World};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    resource: bool,
+    type_id: TypeId,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Borrow {
+    name: &'static str,
+    write: bool,
+}
+
+/// Access is the set of component and resource types a system reads and
+/// writes. Two systems conflict if one writes a type the other reads or
+/// writes.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::Access;
+///
+/// struct Position;
+/// struct Velocity;
+///
+/// let mut movement = Access::new();
+/// movement.write::<Position>();
+/// movement.read::<Velocity>();
+/// let mut render = Access::new();
+/// render.read::<Position>();
+///
+/// assert!(!movement.is_compatible(&render));
+/// assert_eq!(movement.conflicts(&render), vec![std::any::type_name::<Position>()]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Access {
+    borrows: BTreeMap<Key, Borrow>,
+}
+
+impl Access {
+    /// new returns an empty access set.
+    pub fn new() -> Self {
+        Access::default()
+    }
+
+    fn add<T: 'static>(&mut self, resource: bool, write: bool) {
+        let key = Key {
+            resource,
+            type_id: TypeId::of::<T>(),
+        };
+        let borrow = self.borrows.entry(key).or_insert(Borrow {
+            name: type_name::<T>(),
+            write: false,
+        });
+        borrow.write |= write;
+    }
+
+    /// read adds reading components of type T.
+    pub fn read<T: Component>(&mut self) {
+        self.add::<T>(false, false);
+    }
+
+    /// write adds reading and writing components of type T.
+    pub fn write<T: Component>(&mut self) {
+        self.add::<T>(false, true);
+    }
+
+    /// read_resource adds reading the resource of type R.
+    pub fn read_resource<R: Component>(&mut self) {
+        self.add::<R>(true, false);
+    }
+
+    /// write_resource adds reading and writing the resource of type R.
+    pub fn write_resource<R: Component>(&mut self) {
+        self.add::<R>(true, true);
+    }
+
+    /// extend adds the access of the other set.
+    pub fn extend(&mut self, other: &Access) {
+        for (&key, &borrow) in &other.borrows {
+            self.borrows.entry(key).or_insert(Borrow { write: false, ..borrow }).write |= borrow.write;
+        }
+    }
+
+    /// is_empty tells if nothing is accessed.
+    pub fn is_empty(&self) -> bool {
+        self.borrows.is_empty()
+    }
+
+    /// covers tells if everything the other set accesses is accessed at
+    /// least as strongly by this one.
+    pub fn covers(&self, other: &Access) -> bool {
+        other.borrows.iter().all(|(k, b)| self.borrows.get(k).is_some_and(|s| s.write || !b.write))
+    }
+
+    /// conflicts returns the names of the types which one of the sets writes
+    /// and the other accesses.
+    pub fn conflicts(&self, other: &Access) -> Vec<&'static str> {
+        self.borrows
+            .iter()
+            .filter(|&(k, b)| other.borrows.get(k).is_some_and(|o| b.write || o.write))
+            .map(|(_, b)| b.name)
+            .collect()
+    }
+
+    /// is_compatible tells if the sets don't conflict, so that systems with
+    /// them may run at the same time.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        self.conflicts(other).is_empty()
+    }
+
+    /// reads returns the names of the component and resource types which
+    /// are only read.
+    pub fn reads(&self) -> Vec<&'static str> {
+        self.borrows.values().filter(|b| !b.write).map(|b| b.name).collect()
+    }
+
+    /// writes returns the names of the component and resource types which
+    /// are written.
+    pub fn writes(&self) -> Vec<&'static str> {
+        self.borrows.values().filter(|b| b.write).map(|b| b.name).collect()
+    }
+}
+
+/// SystemContext is what a system sees of the world while it runs.
+pub struct SystemContext<'w> {
+    world: &'w World,
+    name: &'w str,
+    access: &'w Access,
+    commands: &'w mut Commands,
+    last_run: u64,
+    tick: u64,
+}
+
+impl<'w> SystemContext<'w> {
+    /// name returns the name of the running system.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// last_run returns the tick at which the system last ran, or zero if it
+    /// hasn't.
+    pub fn last_run(&self) -> u64 {
+        self.last_run
+    }
+
+    /// tick returns the tick of this run of the system, with which its
+    /// changes are marked.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // check panics unless the system declared the access.
+    fn check(&self, needed: &Access) {
+        if !self.access.covers(needed) {
+            let mut missing = needed.clone();
+            missing.borrows.retain(|k, b| !self.access.borrows.get(k).is_some_and(|s| s.write || !b.write));
+            panic!("system {} doesn't declare access to {}", self.name, missing.borrows
+                .values()
+                .map(|b| if b.write { format!("mut {}", b.name) } else { b.name.to_string() })
+                .collect::<Vec<_>>()
+                .join(", "));
+        }
+    }
+
+    /// query returns a query of the entities with the components of Q which
+    /// pass the filter F. Its `Changed` and `Added` filters match changes
+    /// since the system last ran.
+    ///
+    /// # Panics
+    ///
+    /// query panics if the system didn't declare the access of the query.
+    pub fn query<Q: QueryData, F: QueryFilter>(&self) -> Query<'w, Q, F> {
+        let mut needed = Access::new();
+        Q::access(&mut needed);
+        F::access(&mut needed);
+        self.check(&needed);
+        Query::new(self.world, self.last_run, self.tick)
+    }
+
+    /// resource returns the resource of the given type, if there is one.
+    ///
+    /// # Panics
+    ///
+    /// resource panics if the system didn't declare reading it.
+    pub fn resource<R: Component>(&self) -> Option<RwLockReadGuard<'w, R>> {
+        let mut needed = Access::new();
+        needed.read_resource::<R>();
+        self.check(&needed);
+        self.world.resource()
+    }
+
+    /// resource_mut returns the resource of the given type mutably, if there
+    /// is one.
+    ///
+    /// # Panics
+    ///
+    /// resource_mut panics if the system didn't declare writing it.
+    pub fn resource_mut<R: Component>(&self) -> Option<RwLockWriteGuard<'w, R>> {
+        let mut needed = Access::new();
+        needed.write_resource::<R>();
+        self.check(&needed);
+        self.world.resource_mut()
+    }
+
+    /// commands returns the buffer of changes applied to the world after
+    /// the system runs.
+    pub fn commands(&mut self) -> &mut Commands {
+        self.commands
+    }
+}
+
+/// System is a named function run on a world, which declares the
+/// components and resources it accesses.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::ecs::{System, World};
+///
+/// struct Position(f32);
+/// struct Speed(f32);
+///
+/// let mut world = World::new();
+/// world.spawn((Position(0.0), Speed(2.0)));
+/// world.insert_resource(0.5f32);
+///
+/// let mut movement = System::new("movement", |ctx| {
+///     let dt = *ctx.resource::<f32>().unwrap();
+///     ctx.query::<(&mut Position, &Speed), ()>().for_each(|(mut p, s)| p.0 += s.0 * dt);
+/// })
+///     .write::<Position>()
+///     .read::<Speed>()
+///     .read_resource::<f32>();
+///
+/// world.run_system(&mut movement);
+/// world.run_system(&mut movement);
+/// assert_eq!(world.query::<&Position, ()>().entities().len(), 1);
+/// world.query::<&Position, ()>().for_each(|p| assert_eq!(p.0, 2.0));
+/// ```
+pub struct System {
+    name: String,
+    access: Access,
+    last_run: u64,
+    run: Box<dyn FnMut(&mut SystemContext<'_>) + Send>,
+
+    // Ordering and conditions, for schedules.
+    labels: Vec<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+    criteria: Option<Criteria>,
+}
+
+// Criteria tells if a system should run.
+type Criteria = Box<dyn FnMut(&World) -> bool + Send>;
+
+impl fmt::Debug for System {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("System")
+            .field("name", &self.name)
+            .field("access", &self.access)
+            .field("last_run", &self.last_run)
+            .field("labels", &self.labels)
+            .field("before", &self.before)
+            .field("after", &self.after)
+            .finish()
+    }
+}
+
+impl System {
+    /// new returns a new system with the given name, running the function,
+    /// which accesses nothing until declared otherwise.
+    pub fn new<F>(name: &str, run: F) -> Self
+        where F: FnMut(&mut SystemContext<'_>) + Send + 'static
+    {
+        System {
+            name: name.to_string(),
+            access: Access::new(),
+            last_run: 0,
+            run: Box::new(run),
+            labels: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            criteria: None,
+        }
+    }
+
+    /// read declares that the system reads components of type T.
+    pub fn read<T: Component>(mut self) -> Self {
+        self.access.read::<T>();
+        self
+    }
+
+    /// write declares that the system reads and writes components of type
+    /// T.
+    pub fn write<T: Component>(mut self) -> Self {
+        self.access.write::<T>();
+        self
+    }
+
+    /// read_resource declares that the system reads the resource of type R.
+    pub fn read_resource<R: Component>(mut self) -> Self {
+        self.access.read_resource::<R>();
+        self
+    }
+
+    /// write_resource declares that the system reads and writes the
+    /// resource of type R.
+    pub fn write_resource<R: Component>(mut self) -> Self {
+        self.access.write_resource::<R>();
+        self
+    }
+
+    /// label adds a label by which other systems in the same stage of a
+    /// schedule may order themselves relative to this one. The name of a
+    /// system is a label too.
+    pub fn label(mut self, label: &str) -> Self {
+        self.labels.push(label.to_string());
+        self
+    }
+
+    /// before orders the system before those with the label, in the same
+    /// stage of a schedule.
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.to_string());
+        self
+    }
+
+    /// after orders the system after those with the label, in the same
+    /// stage of a schedule.
+    pub fn after(mut self, label: &str) -> Self {
+        self.after.push(label.to_string());
+        self
+    }
+
+    /// run_if makes schedules run the system only when the criteria returns
+    /// true. Schedules evaluate the criteria of the systems of a stage
+    /// before running any of them.
+    pub fn run_if<F>(mut self, criteria: F) -> Self
+        where F: FnMut(&World) -> bool + Send + 'static
+    {
+        self.criteria = Some(Box::new(criteria));
+        self
+    }
+
+    /// has_label tells if the system has the label, or is named it.
+    pub fn has_label(&self, label: &str) -> bool {
+        self.name == label || self.labels.iter().any(|l| l == label)
+    }
+
+    /// name returns the name of the system.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// access returns the components and resources the system accesses.
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    // ordering returns the labels the system runs before and after.
+    pub(super) fn ordering(&self) -> (&[String], &[String]) {
+        (&self.before, &self.after)
+    }
+
+    /// should_run evaluates the run criteria of the system, if it has any.
+    pub fn should_run(&mut self, world: &World) -> bool {
+        self.criteria.as_mut().is_none_or(|c| c(world))
+    }
+
+    /// run runs the system on the world, adding the changes it defers to
+    /// the commands.
+    pub fn run(&mut self, world: &World, commands: &mut Commands) {
+        let tick = world.advance();
+        let mut ctx = SystemContext {
+            world,
+            name: &self.name,
+            access: &self.access,
+            commands,
+            last_run: self.last_run,
+            tick,
+        };
+        (self.run)(&mut ctx);
+        self.last_run = tick;
+    }
+}
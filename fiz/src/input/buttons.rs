@@ -0,0 +1,112 @@
+use std::fmt;
+
+// named defines an enum of physical inputs, each with the name used by the
+// text format of `InputMap`.
+macro_rules! named {
+    ($(#[$m:meta])* pub enum $t:ident { $($v:ident = $n:expr,)* }) => {
+        $(#[$m])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum $t {
+            $($v,)*
+        }
+
+        impl $t {
+            /// ALL is every value, in order.
+            pub const ALL: &'static [$t] = &[$($t::$v,)*];
+
+            /// name returns the name of the value, as written by `InputMap`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($t::$v => $n,)*
+                }
+            }
+
+            /// from_name returns the value with the name, if there is one.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($n => Some($t::$v),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+    };
+}
+
+named! {
+    /// Key is a key of a keyboard, identified by its position on a US
+    /// layout rather than the character it types.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz::input::Key;
+    ///
+    /// assert_eq!(Key::LeftShift.name(), "left_shift");
+    /// assert_eq!(Key::from_name("f1"), Some(Key::F1));
+    /// ```
+    pub enum Key {
+        A = "a", B = "b", C = "c", D = "d", E = "e", F = "f", G = "g", H = "h", I = "i", J = "j", K = "k",
+        L = "l", M = "m", N = "n", O = "o", P = "p", Q = "q", R = "r", S = "s", T = "t", U = "u", V = "v",
+        W = "w", X = "x", Y = "y", Z = "z",
+        Num0 = "0", Num1 = "1", Num2 = "2", Num3 = "3", Num4 = "4", Num5 = "5", Num6 = "6", Num7 = "7",
+        Num8 = "8", Num9 = "9",
+        F1 = "f1", F2 = "f2", F3 = "f3", F4 = "f4", F5 = "f5", F6 = "f6", F7 = "f7", F8 = "f8", F9 = "f9",
+        F10 = "f10", F11 = "f11", F12 = "f12",
+        Up = "up", Down = "down", Left = "left", Right = "right",
+        Space = "space", Enter = "enter", Escape = "escape", Tab = "tab", Backspace = "backspace",
+        Insert = "insert", Delete = "delete", Home = "home", End = "end", PageUp = "page_up",
+        PageDown = "page_down",
+        LeftShift = "left_shift", RightShift = "right_shift", LeftControl = "left_control",
+        RightControl = "right_control", LeftAlt = "left_alt", RightAlt = "right_alt", LeftSuper = "left_super",
+        RightSuper = "right_super",
+        Grave = "grave", Minus = "minus", Equal = "equal", LeftBracket = "left_bracket",
+        RightBracket = "right_bracket", Backslash = "backslash", Semicolon = "semicolon",
+        Apostrophe = "apostrophe", Comma = "comma", Period = "period", Slash = "slash",
+    }
+}
+
+named! {
+    /// MouseButton is a button of a mouse.
+    pub enum MouseButton {
+        Left = "left", Right = "right", Middle = "middle", Back = "back", Forward = "forward",
+    }
+}
+
+named! {
+    /// GamepadButton is a button of a gamepad, named by its position on
+    /// the layout of a typical console controller. The face buttons are
+    /// named by compass direction, so that south is A on an Xbox controller
+    /// and cross on a PlayStation one.
+    pub enum GamepadButton {
+        South = "south", East = "east", West = "west", North = "north",
+        LeftBumper = "left_bumper", RightBumper = "right_bumper",
+        Select = "select", Start = "start", Mode = "mode",
+        LeftStick = "left_stick", RightStick = "right_stick",
+        DpadUp = "dpad_up", DpadDown = "dpad_down", DpadLeft = "dpad_left", DpadRight = "dpad_right",
+    }
+}
+
+named! {
+    /// GamepadAxis is an analog input of a gamepad. Sticks range from -1 to
+    /// 1, positive to the right and up, and triggers from 0 to 1.
+    pub enum GamepadAxis {
+        LeftX = "left_x", LeftY = "left_y", RightX = "right_x", RightY = "right_y",
+        LeftTrigger = "left_trigger", RightTrigger = "right_trigger",
+    }
+}
+
+/// GamepadId identifies a connected gamepad, as numbered by the event
+/// source.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GamepadId(pub u32);
+
+/// TouchId identifies a touch, from when it starts until it ends, as
+/// numbered by the event source.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TouchId(pub u64);
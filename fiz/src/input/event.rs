@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::Receiver;
+use math::Vec2;
+use super::{GamepadAxis, GamepadButton, GamepadId, Key, MouseButton, TouchId};
+
+/// InputEvent is a change of the state of an input device, as reported by
+/// the windowing system. Positions are in pixels from the top left of the
+/// window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    /// A key was pressed. Key repeats aren't events.
+    KeyDown(Key),
+
+    /// A key was released.
+    KeyUp(Key),
+
+    /// A mouse button was pressed.
+    MouseDown(MouseButton),
+
+    /// A mouse button was released.
+    MouseUp(MouseButton),
+
+    /// The mouse cursor moved to the position.
+    MouseMove(Vec2<f32>),
+
+    /// The mouse moved by the amount, regardless of the cursor, as when
+    /// the cursor is captured.
+    MouseMotion(Vec2<f32>),
+
+    /// The mouse wheel scrolled by the amount, in lines, positive to the
+    /// right and away from the user.
+    MouseWheel(Vec2<f32>),
+
+    /// A gamepad was connected.
+    GamepadConnected(GamepadId),
+
+    /// A gamepad was disconnected.
+    GamepadDisconnected(GamepadId),
+
+    /// A button of a gamepad was pressed.
+    GamepadDown(GamepadId, GamepadButton),
+
+    /// A button of a gamepad was released.
+    GamepadUp(GamepadId, GamepadButton),
+
+    /// An axis of a gamepad moved to the value.
+    GamepadAxis(GamepadId, GamepadAxis, f32),
+
+    /// A touch started at the position.
+    TouchStart(TouchId, Vec2<f32>),
+
+    /// A touch moved to the position.
+    TouchMove(TouchId, Vec2<f32>),
+
+    /// A touch ended at the position.
+    TouchEnd(TouchId, Vec2<f32>),
+
+    /// A touch was cancelled by the system, as when a gesture took it over.
+    TouchCancel(TouchId),
+}
+
+/// EventSource is a source of input events, such as a window.
+pub trait EventSource {
+    /// poll appends the events which happened since the last poll.
+    fn poll(&mut self, events: &mut Vec<InputEvent>);
+}
+
+impl EventSource for Receiver<InputEvent> {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        events.extend(self.try_iter());
+    }
+}
+
+/// ScriptedEvents is an event source which replays scripted frames of
+/// events, one frame per poll, for driving input without a window.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::input::{EventSource, InputEvent, Key, ScriptedEvents};
+///
+/// let mut source = ScriptedEvents::new();
+/// source.frame(vec![InputEvent::KeyDown(Key::Space)]).frame(vec![]).frame(vec![InputEvent::KeyUp(Key::Space)]);
+///
+/// let mut events = Vec::new();
+/// source.poll(&mut events);
+/// assert_eq!(events, vec![InputEvent::KeyDown(Key::Space)]);
+/// assert_eq!(source.len(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedEvents {
+    frames: VecDeque<Vec<InputEvent>>,
+}
+
+impl ScriptedEvents {
+    /// new returns a source with nothing scripted.
+    pub fn new() -> Self {
+        ScriptedEvents::default()
+    }
+
+    /// frame adds a frame of events, to be returned by the poll after those
+    /// of the frames added before it.
+    pub fn frame<I>(&mut self, events: I) -> &mut Self
+        where I: IntoIterator<Item = InputEvent>
+    {
+        self.frames.push_back(events.into_iter().collect());
+        self
+    }
+
+    /// len returns the number of frames not yet polled.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// is_empty tells if every frame has been polled, after which polls
+    /// return no events.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl EventSource for ScriptedEvents {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        if let Some(frame) = self.frames.pop_front() {
+            events.extend(frame);
+        }
+    }
+}
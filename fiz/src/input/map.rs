@@ -0,0 +1,622 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use super::{Buttons, GamepadAxis, GamepadButton, Input, Key, MouseButton};
+
+/// Binding is a physical input which an action may be bound to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Binding {
+    /// A key, written `key:<name>`.
+    Key(Key),
+
+    /// A mouse button, written `mouse:<name>`.
+    Mouse(MouseButton),
+
+    /// A button of any gamepad, written `gamepad:<name>`.
+    Gamepad(GamepadButton),
+
+    /// An axis of any gamepad pushed at least halfway in the positive or
+    /// negative direction, written `axis:<name>+` or `axis:<name>-`.
+    GamepadAxis(GamepadAxis, bool),
+}
+
+impl Binding {
+    // is_held tells if the input is down.
+    fn is_held(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.keys.is_held(key),
+            Binding::Mouse(button) => input.mouse.buttons.is_held(button),
+            Binding::Gamepad(button) => input.gamepads().any(|(_, g)| g.buttons.is_held(button)),
+            Binding::GamepadAxis(axis, positive) => input.gamepads().any(|(_, g)| {
+                let v = g.axis(axis);
+                if positive { v >= 0.5 } else { v <= -0.5 }
+            }),
+        }
+    }
+
+    // is_pressed tells if the input was pressed this frame.
+    fn is_pressed(self, input: &Input) -> bool {
+        match self {
+            Binding::Key(key) => input.keys.is_pressed(key),
+            Binding::Mouse(button) => input.mouse.buttons.is_pressed(button),
+            Binding::Gamepad(button) => input.gamepads().any(|(_, g)| g.buttons.is_pressed(button)),
+            Binding::GamepadAxis(..) => false,
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Binding::Key(key) => write!(f, "key:{}", key),
+            Binding::Mouse(button) => write!(f, "mouse:{}", button),
+            Binding::Gamepad(button) => write!(f, "gamepad:{}", button),
+            Binding::GamepadAxis(axis, positive) => write!(f, "axis:{}{}", axis, if positive { '+' } else { '-' }),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let (kind, name) = s.split_once(':').ok_or(())?;
+        match kind {
+            "key" => Key::from_name(name).map(Binding::Key),
+            "mouse" => MouseButton::from_name(name).map(Binding::Mouse),
+            "gamepad" => GamepadButton::from_name(name).map(Binding::Gamepad),
+            "axis" => {
+                let (name, positive) = if let Some(name) = name.strip_suffix('+') {
+                    (name, true)
+                } else {
+                    (name.strip_suffix('-').ok_or(())?, false)
+                };
+                GamepadAxis::from_name(name).map(|a| Binding::GamepadAxis(a, positive))
+            }
+            _ => None,
+        }.ok_or(())
+    }
+}
+
+/// AxisBinding is a physical input which an axis may be bound to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AxisBinding {
+    /// A pair of keys, for -1 and 1, written `keys:<negative>:<positive>`.
+    Keys(Key, Key),
+
+    /// A pair of buttons of any gamepad, for -1 and 1, written
+    /// `buttons:<negative>:<positive>`.
+    Buttons(GamepadButton, GamepadButton),
+
+    /// An axis of any gamepad, written `axis:<name>`. Of several gamepads,
+    /// the one pushed furthest counts.
+    GamepadAxis(GamepadAxis),
+
+    /// The horizontal motion of the mouse, written `mouse:x`.
+    MouseX,
+
+    /// The vertical motion of the mouse, written `mouse:y`.
+    MouseY,
+
+    /// The horizontal scrolling of the mouse wheel, written `wheel:x`.
+    WheelX,
+
+    /// The vertical scrolling of the mouse wheel, written `wheel:y`.
+    WheelY,
+}
+
+impl AxisBinding {
+    // value returns the raw value of the input.
+    fn value(self, input: &Input) -> f32 {
+        fn pair<T: Ord + Copy>(buttons: &Buttons<T>, negative: T, positive: T) -> f32 {
+            (buttons.is_held(positive) as i32 - buttons.is_held(negative) as i32) as f32
+        }
+        let furthest = |a: f32, b: f32| if b.abs() > a.abs() { b } else { a };
+        match self {
+            AxisBinding::Keys(negative, positive) => pair(&input.keys, negative, positive),
+            AxisBinding::Buttons(negative, positive) => {
+                input.gamepads().map(|(_, g)| pair(&g.buttons, negative, positive)).fold(0.0, furthest)
+            }
+            AxisBinding::GamepadAxis(axis) => input.gamepads().map(|(_, g)| g.axis(axis)).fold(0.0, furthest),
+            AxisBinding::MouseX => input.mouse.motion.0,
+            AxisBinding::MouseY => input.mouse.motion.1,
+            AxisBinding::WheelX => input.mouse.wheel.0,
+            AxisBinding::WheelY => input.mouse.wheel.1,
+        }
+    }
+
+    // is_bounded tells if the values of the input range from -1 to 1.
+    fn is_bounded(self) -> bool {
+        !matches!(self, AxisBinding::MouseX | AxisBinding::MouseY | AxisBinding::WheelX | AxisBinding::WheelY)
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AxisBinding::Keys(negative, positive) => write!(f, "keys:{}:{}", negative, positive),
+            AxisBinding::Buttons(negative, positive) => write!(f, "buttons:{}:{}", negative, positive),
+            AxisBinding::GamepadAxis(axis) => write!(f, "axis:{}", axis),
+            AxisBinding::MouseX => f.write_str("mouse:x"),
+            AxisBinding::MouseY => f.write_str("mouse:y"),
+            AxisBinding::WheelX => f.write_str("wheel:x"),
+            AxisBinding::WheelY => f.write_str("wheel:y"),
+        }
+    }
+}
+
+impl FromStr for AxisBinding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let mut parts = s.split(':');
+        let binding = match (parts.next(), parts.next(), parts.next()) {
+            (Some("keys"), Some(n), Some(p)) => Key::from_name(n).zip(Key::from_name(p)).map(|(n, p)| {
+                AxisBinding::Keys(n, p)
+            }),
+            (Some("buttons"), Some(n), Some(p)) => {
+                GamepadButton::from_name(n).zip(GamepadButton::from_name(p)).map(|(n, p)| AxisBinding::Buttons(n, p))
+            }
+            (Some("axis"), Some(name), None) => GamepadAxis::from_name(name).map(AxisBinding::GamepadAxis),
+            (Some("mouse"), Some("x"), None) => Some(AxisBinding::MouseX),
+            (Some("mouse"), Some("y"), None) => Some(AxisBinding::MouseY),
+            (Some("wheel"), Some("x"), None) => Some(AxisBinding::WheelX),
+            (Some("wheel"), Some("y"), None) => Some(AxisBinding::WheelY),
+            _ => None,
+        };
+        match parts.next() {
+            None => binding.ok_or(()),
+            Some(_) => Err(()),
+        }
+    }
+}
+
+/// Curve is the response curve of an axis, mapping values from 0 to 1
+/// (after the dead zone) to values from 0 to 1, symmetrically for negative
+/// values.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Curve {
+    /// The value itself, written `linear`.
+    Linear,
+
+    /// The value raised to the power, written `power:<exponent>`. Powers
+    /// above one give finer control near the center.
+    Power(f32),
+
+    /// The smoothstep of the value, written `smooth`, which is gentle at
+    /// both ends.
+    Smooth,
+}
+
+impl Curve {
+    /// apply returns the value through the curve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz::input::Curve;
+    ///
+    /// assert_eq!(Curve::Linear.apply(-0.5), -0.5);
+    /// assert_eq!(Curve::Power(2.0).apply(-0.5), -0.25);
+    /// assert_eq!(Curve::Smooth.apply(0.5), 0.5);
+    /// ```
+    pub fn apply(self, value: f32) -> f32 {
+        let x = value.abs().min(1.0);
+        let y = match self {
+            Curve::Linear => x,
+            Curve::Power(e) => x.powf(e),
+            Curve::Smooth => x * x * (3.0 - 2.0 * x),
+        };
+        y.copysign(value)
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Curve::Linear => f.write_str("linear"),
+            Curve::Power(e) => write!(f, "power:{}", e),
+            Curve::Smooth => f.write_str("smooth"),
+        }
+    }
+}
+
+impl FromStr for Curve {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "linear" => Ok(Curve::Linear),
+            "smooth" => Ok(Curve::Smooth),
+            _ => {
+                let e: f32 = s.strip_prefix("power:").ok_or(())?.parse().map_err(|_| ())?;
+                if e > 0.0 && e.is_finite() { Ok(Curve::Power(e)) } else { Err(()) }
+            }
+        }
+    }
+}
+
+/// Axis is the bindings and response of an analog action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Axis {
+    /// The inputs bound to the axis. Of several, the one furthest from zero
+    /// counts.
+    pub bindings: Vec<AxisBinding>,
+
+    /// The magnitude below which gamepad axes read as zero. Values above it
+    /// are rescaled to range from zero again.
+    pub dead_zone: f32,
+
+    /// The response curve of gamepad axes and buttons and keys, after the
+    /// dead zone.
+    pub curve: Curve,
+
+    /// The factor by which all values are multiplied, after the curve.
+    pub sensitivity: f32,
+}
+
+impl Default for Axis {
+    fn default() -> Self {
+        Axis {
+            bindings: Vec::new(),
+            dead_zone: 0.0,
+            curve: Curve::Linear,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+impl Axis {
+    /// new returns an axis with the bindings, a linear response and no dead
+    /// zone.
+    pub fn new(bindings: Vec<AxisBinding>) -> Self {
+        Axis {
+            bindings,
+            ..Axis::default()
+        }
+    }
+
+    /// value returns the value of the axis for the input.
+    pub fn value(&self, input: &Input) -> f32 {
+        let mut best = 0.0f32;
+        for &binding in &self.bindings {
+            let mut v = binding.value(input);
+            if binding.is_bounded() {
+                let x = v.abs().min(1.0);
+                v = if x <= self.dead_zone {
+                    0.0
+                } else {
+                    self.curve.apply(((x - self.dead_zone) / (1.0 - self.dead_zone)).copysign(v))
+                };
+            }
+            if v.abs() > best.abs() {
+                best = v;
+            }
+        }
+        best * self.sensitivity
+    }
+}
+
+/// InputMapError describes why the text of an `InputMap` is invalid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputMapError {
+    /// A line doesn't start with `action` or `axis` and a name.
+    InvalidStatement {
+        /// The line number, starting at one.
+        line: usize,
+    },
+
+    /// A binding or setting is invalid.
+    InvalidArgument {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The argument as written.
+        text: String,
+    },
+
+    /// An action or axis is defined twice.
+    Duplicate {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The name of the action or axis.
+        name: String,
+    },
+}
+
+impl fmt::Display for InputMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputMapError::InvalidStatement { line } => {
+                write!(f, "line {}: expected \"action <name>\" or \"axis <name>\"", line)
+            }
+            InputMapError::InvalidArgument { line, ref text } => {
+                write!(f, "line {}: invalid argument {:?}", line, text)
+            }
+            InputMapError::Duplicate { line, ref name } => write!(f, "line {}: {:?} is already defined", line, name),
+        }
+    }
+}
+
+impl Error for InputMapError {}
+
+/// InputMap binds physical inputs to named actions, which are either down
+/// or up, and named axes, which have analog values.
+///
+/// Maps are written as text, one action or axis per line, for saving the
+/// controls a player chose. Actions are written `action <name>` followed by
+/// their bindings, and axes `axis <name>` followed by their bindings and
+/// settings of the form `dead_zone=<value>`, `curve=<curve>` and
+/// `sensitivity=<value>`. Names can't contain whitespace. Blank lines and
+/// those starting with `#` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::input::{Binding, InputMap, Key};
+///
+/// let mut map: InputMap = "
+///     action jump key:space gamepad:south
+///     axis move_x keys:a:d axis:left_x dead_zone=0.2 curve=power:2
+/// ".parse().unwrap();
+/// assert_eq!(map.action("jump").unwrap()[0], Binding::Key(Key::Space));
+/// assert_eq!(map.axis("move_x").unwrap().dead_zone, 0.2);
+///
+/// // The player remaps jump to W.
+/// map.unbind("jump", Binding::Key(Key::Space));
+/// map.bind("jump", Binding::Key(Key::W));
+///
+/// let text = map.to_string();
+/// assert_eq!(text.lines().next(), Some("action jump gamepad:south key:w"));
+/// assert_eq!(text.parse::<InputMap>().unwrap(), map);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Binding>>,
+    axes: BTreeMap<String, Axis>,
+}
+
+impl InputMap {
+    /// new returns a map with no actions or axes.
+    pub fn new() -> Self {
+        InputMap::default()
+    }
+
+    /// action returns the bindings of the action, if it's defined.
+    pub fn action(&self, name: &str) -> Option<&[Binding]> {
+        self.actions.get(name).map(|b| &b[..])
+    }
+
+    /// actions returns the names and bindings of the actions, in order of
+    /// name.
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        self.actions.iter().map(|(n, b)| (&n[..], &b[..]))
+    }
+
+    /// bind binds the input to the action, defining the action if needed.
+    /// Binding an input twice does nothing.
+    ///
+    /// # Panics
+    ///
+    /// bind panics if the name is empty or contains whitespace.
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        check_name(action);
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// unbind unbinds the input from the action, returning whether it was
+    /// bound. The action stays defined.
+    pub fn unbind(&mut self, action: &str, binding: Binding) -> bool {
+        let bindings = match self.actions.get_mut(action) {
+            Some(b) => b,
+            None => return false,
+        };
+        let before = bindings.len();
+        bindings.retain(|&b| b != binding);
+        bindings.len() != before
+    }
+
+    /// remove_action removes the action, returning its bindings.
+    pub fn remove_action(&mut self, action: &str) -> Option<Vec<Binding>> {
+        self.actions.remove(action)
+    }
+
+    /// axis returns the axis, if it's defined.
+    pub fn axis(&self, name: &str) -> Option<&Axis> {
+        self.axes.get(name)
+    }
+
+    /// axis_mut returns the axis mutably, if it's defined.
+    pub fn axis_mut(&mut self, name: &str) -> Option<&mut Axis> {
+        self.axes.get_mut(name)
+    }
+
+    /// axes returns the names and settings of the axes, in order of name.
+    pub fn axes(&self) -> impl Iterator<Item = (&str, &Axis)> {
+        self.axes.iter().map(|(n, a)| (&n[..], a))
+    }
+
+    /// set_axis defines the axis, returning its previous definition.
+    ///
+    /// # Panics
+    ///
+    /// set_axis panics if the name is empty or contains whitespace.
+    pub fn set_axis(&mut self, name: &str, axis: Axis) -> Option<Axis> {
+        check_name(name);
+        self.axes.insert(name.to_string(), axis)
+    }
+
+    /// remove_axis removes the axis, returning its definition.
+    pub fn remove_axis(&mut self, name: &str) -> Option<Axis> {
+        self.axes.remove(name)
+    }
+}
+
+// check_name panics unless the name can be written in the text format.
+fn check_name(name: &str) {
+    assert!(!name.is_empty() && !name.contains(char::is_whitespace),
+            "InputMap: name {:?} is empty or contains whitespace", name);
+}
+
+impl fmt::Display for InputMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, bindings) in &self.actions {
+            write!(f, "action {}", name)?;
+            for binding in bindings {
+                write!(f, " {}", binding)?;
+            }
+            writeln!(f)?;
+        }
+        for (name, axis) in &self.axes {
+            write!(f, "axis {}", name)?;
+            for binding in &axis.bindings {
+                write!(f, " {}", binding)?;
+            }
+            writeln!(f, " dead_zone={} curve={} sensitivity={}", axis.dead_zone, axis.curve, axis.sensitivity)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for InputMap {
+    type Err = InputMapError;
+
+    fn from_str(s: &str) -> Result<Self, InputMapError> {
+        let mut map = InputMap::new();
+        for (i, text) in s.lines().enumerate() {
+            let line = i + 1;
+            let mut tokens = text.split_whitespace();
+            let (statement, name) = match (tokens.next(), tokens.next()) {
+                (None, _) => continue,
+                (Some(s), _) if s.starts_with('#') => continue,
+                (Some(s @ "action"), Some(name)) | (Some(s @ "axis"), Some(name)) => (s, name.to_string()),
+                _ => return Err(InputMapError::InvalidStatement { line }),
+            };
+            if map.actions.contains_key(&name) || map.axes.contains_key(&name) {
+                return Err(InputMapError::Duplicate { line, name });
+            }
+            let invalid = |text: &str| InputMapError::InvalidArgument {
+                line,
+                text: text.to_string(),
+            };
+            if statement == "action" {
+                let bindings = tokens.map(|t| t.parse().map_err(|_| invalid(t))).collect::<Result<_, _>>()?;
+                map.actions.insert(name, bindings);
+                continue;
+            }
+            let mut axis = Axis::default();
+            for token in tokens {
+                let number = |v: &str| v.parse::<f32>().ok().filter(|v| v.is_finite()).ok_or_else(|| invalid(token));
+                match token.split_once('=') {
+                    Some(("dead_zone", v)) => {
+                        axis.dead_zone = number(v)?;
+                        if !(0.0..1.0).contains(&axis.dead_zone) {
+                            return Err(invalid(token));
+                        }
+                    }
+                    Some(("curve", v)) => axis.curve = v.parse().map_err(|_| invalid(token))?,
+                    Some(("sensitivity", v)) => axis.sensitivity = number(v)?,
+                    Some(_) => return Err(invalid(token)),
+                    None => axis.bindings.push(token.parse().map_err(|_| invalid(token))?),
+                }
+            }
+            map.axes.insert(name, axis);
+        }
+        Ok(map)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct ActionState {
+    held: bool,
+    pressed: bool,
+    released: bool,
+}
+
+/// Actions is the state of the actions and axes of an `InputMap`, updated
+/// once per frame from the state of the devices. Actions are pressed,
+/// held and released like buttons, when any of their bindings are.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::input::{Actions, AxisBinding, Axis, Binding, Input, InputEvent, InputMap, Key, MouseButton};
+///
+/// let mut map = InputMap::new();
+/// map.bind("fire", Binding::Key(Key::Space));
+/// map.bind("fire", Binding::Mouse(MouseButton::Left));
+/// map.set_axis("turn", Axis::new(vec![AxisBinding::Keys(Key::Left, Key::Right)]));
+///
+/// let mut input = Input::new();
+/// let mut actions = Actions::new();
+/// input.handle(&InputEvent::MouseDown(MouseButton::Left));
+/// input.handle(&InputEvent::KeyDown(Key::Left));
+/// actions.update(&map, &input);
+/// assert!(actions.is_pressed("fire"));
+/// assert_eq!(actions.value("turn"), -1.0);
+///
+/// input.begin_frame();
+/// input.handle(&InputEvent::KeyDown(Key::Space));
+/// input.handle(&InputEvent::MouseUp(MouseButton::Left));
+/// actions.update(&map, &input);
+/// assert!(actions.is_held("fire") && !actions.is_pressed("fire") && !actions.is_released("fire"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Actions {
+    actions: BTreeMap<String, ActionState>,
+    axes: BTreeMap<String, f32>,
+}
+
+impl Actions {
+    /// new returns the state of no actions.
+    pub fn new() -> Self {
+        Actions::default()
+    }
+
+    /// update updates the state of the actions and axes of the map from the
+    /// state of the devices. Actions and axes which aren't in the map are
+    /// forgotten.
+    pub fn update(&mut self, map: &InputMap, input: &Input) {
+        let previous = std::mem::take(&mut self.actions);
+        for (name, bindings) in &map.actions {
+            let was = previous.get(name).is_some_and(|s| s.held);
+            let held = bindings.iter().any(|b| b.is_held(input));
+            let tapped = bindings.iter().any(|b| b.is_pressed(input));
+            self.actions.insert(name.clone(), ActionState {
+                held,
+                pressed: !was && (held || tapped),
+                released: (was || tapped) && !held,
+            });
+        }
+        self.axes = map.axes.iter().map(|(name, axis)| (name.clone(), axis.value(input))).collect();
+    }
+
+    fn state(&self, action: &str) -> ActionState {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+
+    /// is_held tells if any input bound to the action is down.
+    pub fn is_held(&self, action: &str) -> bool {
+        self.state(action).held
+    }
+
+    /// is_pressed tells if the action went down this frame.
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.state(action).pressed
+    }
+
+    /// is_released tells if the action went up this frame.
+    pub fn is_released(&self, action: &str) -> bool {
+        self.state(action).released
+    }
+
+    /// value returns the value of the axis, or zero if it isn't in the map.
+    pub fn value(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+}
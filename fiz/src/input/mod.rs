@@ -0,0 +1,184 @@
+//! Input devices and action mapping.
+//!
+//! Input is independent of windowing: a window (or a script, in tests) is
+//! an `EventSource` of `InputEvent`s, from which `Input` tracks the state of
+//! the keyboard, mouse, gamepads and touches once per frame, including what
+//! was pressed and released since the last frame.
+//!
+//! Games rarely read devices directly. Instead, an `InputMap` binds physical
+//! inputs to named actions, like "jump", and axes, like "move_x", with dead
+//! zones, response curves and sensitivity, and `Actions` tracks their state.
+//! Input maps can be written as text and parsed back, so players can remap
+//! controls and save them.
+
+mod buttons;
+mod event;
+mod state;
+mod map;
+
+pub use self::buttons::{GamepadAxis, GamepadButton, GamepadId, Key, MouseButton, TouchId};
+pub use self::event::{EventSource, InputEvent, ScriptedEvents};
+pub use self::state::{Buttons, Gamepad, Input, Mouse, Touch};
+pub use self::map::{Actions, Axis, AxisBinding, Binding, Curve, InputMap, InputMapError};
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+    use math::Vec2;
+    use super::*;
+
+    #[test]
+    fn devices() {
+        let pad = GamepadId(3);
+        let mut source = ScriptedEvents::new();
+        source
+            .frame(vec![
+                InputEvent::KeyDown(Key::Q),
+                InputEvent::KeyUp(Key::Q),
+                InputEvent::MouseMove(Vec2(10.0, 20.0)),
+                InputEvent::MouseMotion(Vec2(1.0, 2.0)),
+                InputEvent::MouseMotion(Vec2(3.0, 4.0)),
+                InputEvent::GamepadDown(pad, GamepadButton::Start),
+                InputEvent::TouchStart(TouchId(1), Vec2(5.0, 5.0)),
+                InputEvent::TouchStart(TouchId(2), Vec2(6.0, 6.0)),
+            ])
+            .frame(vec![
+                InputEvent::TouchMove(TouchId(1), Vec2(7.0, 8.0)),
+                InputEvent::TouchEnd(TouchId(2), Vec2(9.0, 9.0)),
+                InputEvent::GamepadDisconnected(pad),
+            ]);
+
+        let mut input = Input::new();
+        input.update(&mut source);
+        assert!(input.keys.is_pressed(Key::Q) && input.keys.is_released(Key::Q) && !input.keys.is_held(Key::Q));
+        assert_eq!(input.mouse.position, Vec2(10.0, 20.0));
+        assert_eq!(input.mouse.motion, Vec2(4.0, 6.0));
+        assert!(input.gamepad(pad).unwrap().buttons.is_pressed(GamepadButton::Start));
+        assert_eq!(input.touches_started().count(), 2);
+
+        input.update(&mut source);
+        assert!(!input.keys.is_released(Key::Q));
+        assert_eq!(input.mouse.motion, Vec2(0.0, 0.0));
+        assert!(input.gamepad(pad).is_none());
+        assert_eq!(input.touches_started().count(), 0);
+        let touch = input.touch(TouchId(1)).unwrap();
+        assert_eq!((touch.start, touch.position), (Vec2(5.0, 5.0), Vec2(7.0, 8.0)));
+        assert_eq!(input.touches_ended().len(), 1);
+        assert_eq!(input.touches_ended()[0].position, Vec2(9.0, 9.0));
+        assert!(source.is_empty());
+
+        // Channels are sources too, for windows on other threads.
+        let (send, mut receive) = mpsc::channel();
+        send.send(InputEvent::KeyDown(Key::Escape)).unwrap();
+        input.update(&mut receive);
+        assert!(input.keys.is_pressed(Key::Escape));
+    }
+
+    #[test]
+    fn axes() {
+        let pad = GamepadId(0);
+        let mut input = Input::new();
+        input.handle(&InputEvent::GamepadAxis(pad, GamepadAxis::LeftX, -0.6));
+        input.handle(&InputEvent::MouseMotion(Vec2(8.0, 0.0)));
+
+        let mut stick = Axis::new(vec![AxisBinding::GamepadAxis(GamepadAxis::LeftX)]);
+        stick.dead_zone = 0.2;
+        assert!((stick.value(&input) + 0.5).abs() < 1e-6);
+        stick.curve = Curve::Power(2.0);
+        stick.sensitivity = 2.0;
+        assert!((stick.value(&input) + 0.5).abs() < 1e-6);
+
+        input.handle(&InputEvent::GamepadAxis(pad, GamepadAxis::LeftX, 0.1));
+        assert_eq!(stick.value(&input), 0.0);
+
+        // Unbounded inputs are only scaled, and the furthest input counts.
+        stick.bindings.push(AxisBinding::MouseX);
+        assert_eq!(stick.value(&input), 16.0);
+
+        // Holding both keys cancels out.
+        let mut keys = Axis::new(vec![AxisBinding::Keys(Key::A, Key::D)]);
+        keys.dead_zone = 0.5;
+        input.handle(&InputEvent::KeyDown(Key::D));
+        assert_eq!(keys.value(&input), 1.0);
+        input.handle(&InputEvent::KeyDown(Key::A));
+        assert_eq!(keys.value(&input), 0.0);
+    }
+
+    #[test]
+    fn actions() {
+        let map: InputMap = "action jump key:space axis:left_y+".parse().unwrap();
+        let mut input = Input::new();
+        let mut actions = Actions::new();
+        let mut frame = |events: &[InputEvent]| {
+            input.begin_frame();
+            for e in events {
+                input.handle(e);
+            }
+            actions.update(&map, &input);
+            (actions.is_pressed("jump"), actions.is_held("jump"), actions.is_released("jump"))
+        };
+        assert_eq!(frame(&[InputEvent::GamepadAxis(GamepadId(0), GamepadAxis::LeftY, 0.7)]), (true, true, false));
+        assert_eq!(frame(&[InputEvent::KeyDown(Key::Space)]), (false, true, false));
+        assert_eq!(frame(&[InputEvent::GamepadAxis(GamepadId(0), GamepadAxis::LeftY, 0.2)]), (false, true, false));
+        assert_eq!(frame(&[InputEvent::KeyUp(Key::Space)]), (false, false, true));
+        assert_eq!(frame(&[]), (false, false, false));
+
+        // A tap within a frame is pressed and released at once.
+        assert_eq!(frame(&[InputEvent::KeyDown(Key::Space), InputEvent::KeyUp(Key::Space)]), (true, false, true));
+    }
+
+    #[test]
+    fn text() {
+        let mut map = InputMap::new();
+        for &key in Key::ALL {
+            map.bind("keys", Binding::Key(key));
+        }
+        for &button in MouseButton::ALL {
+            map.bind("mouse", Binding::Mouse(button));
+        }
+        for &button in GamepadButton::ALL {
+            map.bind("pad", Binding::Gamepad(button));
+        }
+        for &axis in GamepadAxis::ALL {
+            map.bind("sticks", Binding::GamepadAxis(axis, true));
+            map.bind("sticks", Binding::GamepadAxis(axis, false));
+        }
+        map.set_axis("look_x", Axis {
+            bindings: vec![AxisBinding::MouseX, AxisBinding::WheelY, AxisBinding::GamepadAxis(GamepadAxis::RightX)],
+            dead_zone: 0.125,
+            curve: Curve::Smooth,
+            sensitivity: -0.3,
+        });
+        map.set_axis("turn", Axis::new(vec![
+            AxisBinding::Keys(Key::Left, Key::Right),
+            AxisBinding::Buttons(GamepadButton::DpadLeft, GamepadButton::DpadRight),
+        ]));
+        let text = map.to_string();
+        assert_eq!(text.parse::<InputMap>(), Ok(map));
+        assert!(text.contains("axis turn keys:left:right buttons:dpad_left:dpad_right dead_zone=0 curve=linear"));
+
+        let error = |s: &str| s.parse::<InputMap>().unwrap_err();
+        assert_eq!(error("\naxis"), InputMapError::InvalidStatement { line: 2 });
+        assert_eq!(error("bind jump key:space"), InputMapError::InvalidStatement { line: 1 });
+        assert_eq!(error("action jump key:spacebar"), InputMapError::InvalidArgument {
+            line: 1,
+            text: "key:spacebar".to_string(),
+        });
+        assert_eq!(error("axis x dead_zone=1"), InputMapError::InvalidArgument {
+            line: 1,
+            text: "dead_zone=1".to_string(),
+        });
+        assert_eq!(error("axis x curve=power:-1"), InputMapError::InvalidArgument {
+            line: 1,
+            text: "curve=power:-1".to_string(),
+        });
+        assert_eq!(error("axis x keys:a:b:c"), InputMapError::InvalidArgument {
+            line: 1,
+            text: "keys:a:b:c".to_string(),
+        });
+        assert_eq!(error("action x\naxis x"), InputMapError::Duplicate {
+            line: 2,
+            name: "x".to_string(),
+        });
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, BTreeSet};
+use math::Vec2;
+use super::{EventSource, GamepadAxis, GamepadButton, GamepadId, InputEvent, Key, MouseButton, TouchId};
+
+/// Buttons is the state of a set of buttons, such as the keys of a
+/// keyboard: which are held down, and which were pressed or released this
+/// frame. A button pressed and released within one frame is both pressed
+/// and released, but not held.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::input::{Buttons, Key};
+///
+/// let mut keys = Buttons::new();
+/// keys.press(Key::W);
+/// assert!(keys.is_pressed(Key::W) && keys.is_held(Key::W));
+///
+/// keys.clear_edges();
+/// assert!(!keys.is_pressed(Key::W) && keys.is_held(Key::W));
+///
+/// keys.release(Key::W);
+/// assert!(keys.is_released(Key::W) && !keys.is_held(Key::W));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Buttons<T: Ord> {
+    held: BTreeSet<T>,
+    pressed: BTreeSet<T>,
+    released: BTreeSet<T>,
+}
+
+impl<T: Ord> Default for Buttons<T> {
+    fn default() -> Self {
+        Buttons {
+            held: BTreeSet::new(),
+            pressed: BTreeSet::new(),
+            released: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: Ord + Copy> Buttons<T> {
+    /// new returns the state of buttons which are all up.
+    pub fn new() -> Self {
+        Buttons::default()
+    }
+
+    /// press records pressing the button. Pressing a held button does
+    /// nothing.
+    pub fn press(&mut self, button: T) {
+        if self.held.insert(button) {
+            self.pressed.insert(button);
+        }
+    }
+
+    /// release records releasing the button. Releasing a button which isn't
+    /// held does nothing.
+    pub fn release(&mut self, button: T) {
+        if self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    /// release_all releases every held button.
+    pub fn release_all(&mut self) {
+        self.released.extend(self.held.iter().copied());
+        self.held.clear();
+    }
+
+    /// clear_edges forgets which buttons were pressed and released, for the
+    /// start of a new frame.
+    pub fn clear_edges(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// is_held tells if the button is down.
+    pub fn is_held(&self, button: T) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// is_pressed tells if the button was pressed this frame.
+    pub fn is_pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// is_released tells if the button was released this frame.
+    pub fn is_released(&self, button: T) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// held returns the buttons which are down, in order.
+    pub fn held(&self) -> impl Iterator<Item = T> + '_ {
+        self.held.iter().copied()
+    }
+
+    /// pressed returns the buttons pressed this frame, in order.
+    pub fn pressed(&self) -> impl Iterator<Item = T> + '_ {
+        self.pressed.iter().copied()
+    }
+
+    /// released returns the buttons released this frame, in order.
+    pub fn released(&self) -> impl Iterator<Item = T> + '_ {
+        self.released.iter().copied()
+    }
+}
+
+/// Mouse is the state of the mouse.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mouse {
+    /// The buttons.
+    pub buttons: Buttons<MouseButton>,
+
+    /// The position of the cursor.
+    pub position: Vec2<f32>,
+
+    /// The motion of the mouse this frame.
+    pub motion: Vec2<f32>,
+
+    /// The scrolling of the wheel this frame.
+    pub wheel: Vec2<f32>,
+}
+
+impl Default for Mouse {
+    fn default() -> Self {
+        Mouse {
+            buttons: Buttons::new(),
+            position: Vec2(0.0, 0.0),
+            motion: Vec2(0.0, 0.0),
+            wheel: Vec2(0.0, 0.0),
+        }
+    }
+}
+
+/// Gamepad is the state of a gamepad.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gamepad {
+    /// The buttons.
+    pub buttons: Buttons<GamepadButton>,
+
+    axes: BTreeMap<GamepadAxis, f32>,
+}
+
+impl Gamepad {
+    /// axis returns the raw value of the axis, which is zero until it moves.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+
+    /// set_axis sets the raw value of the axis.
+    pub fn set_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.axes.insert(axis, value);
+    }
+}
+
+/// Touch is a point of contact with a touch screen.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Touch {
+    /// The identifier of the touch.
+    pub id: TouchId,
+
+    /// Where the touch started.
+    pub start: Vec2<f32>,
+
+    /// Where the touch is now, or ended.
+    pub position: Vec2<f32>,
+
+    /// Whether the system cancelled the touch, rather than it ending.
+    pub cancelled: bool,
+}
+
+/// Input is the state of every input device, updated once per frame from an
+/// event source. It's typically kept as a resource of a `World`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::input::{GamepadAxis, GamepadId, Input, InputEvent, Key, ScriptedEvents};
+///
+/// let mut source = ScriptedEvents::new();
+/// source
+///     .frame(vec![
+///         InputEvent::KeyDown(Key::A),
+///         InputEvent::GamepadConnected(GamepadId(0)),
+///         InputEvent::GamepadAxis(GamepadId(0), GamepadAxis::LeftX, 0.5),
+///     ])
+///     .frame(vec![InputEvent::KeyUp(Key::A)]);
+///
+/// let mut input = Input::new();
+/// input.update(&mut source);
+/// assert!(input.keys.is_pressed(Key::A));
+/// assert_eq!(input.gamepad(GamepadId(0)).unwrap().axis(GamepadAxis::LeftX), 0.5);
+///
+/// input.update(&mut source);
+/// assert!(input.keys.is_released(Key::A));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Input {
+    /// The keyboard.
+    pub keys: Buttons<Key>,
+
+    /// The mouse.
+    pub mouse: Mouse,
+
+    gamepads: BTreeMap<GamepadId, Gamepad>,
+    touches: BTreeMap<TouchId, Touch>,
+    started: Vec<TouchId>,
+    ended: Vec<Touch>,
+
+    // Reused between updates.
+    events: Vec<InputEvent>,
+}
+
+impl Input {
+    /// new returns the state of devices with nothing pressed.
+    pub fn new() -> Self {
+        Input::default()
+    }
+
+    /// gamepad returns the connected gamepad, if there is one.
+    pub fn gamepad(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.gamepads.get(&id)
+    }
+
+    /// gamepads returns the connected gamepads, in order of identifier.
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &Gamepad)> {
+        self.gamepads.iter().map(|(&id, g)| (id, g))
+    }
+
+    /// touch returns the touch, if it's ongoing.
+    pub fn touch(&self, id: TouchId) -> Option<&Touch> {
+        self.touches.get(&id)
+    }
+
+    /// touches returns the ongoing touches, in order of identifier.
+    pub fn touches(&self) -> impl Iterator<Item = &Touch> {
+        self.touches.values()
+    }
+
+    /// touches_started returns the touches which started this frame and
+    /// haven't ended.
+    pub fn touches_started(&self) -> impl Iterator<Item = &Touch> {
+        self.started.iter().filter_map(move |id| self.touches.get(id))
+    }
+
+    /// touches_ended returns the touches which ended or were cancelled this
+    /// frame.
+    pub fn touches_ended(&self) -> &[Touch] {
+        &self.ended
+    }
+
+    /// begin_frame forgets what was pressed, released, moved, started and
+    /// ended, for the start of a new frame.
+    pub fn begin_frame(&mut self) {
+        self.keys.clear_edges();
+        self.mouse.buttons.clear_edges();
+        self.mouse.motion = Vec2(0.0, 0.0);
+        self.mouse.wheel = Vec2(0.0, 0.0);
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.buttons.clear_edges();
+        }
+        self.started.clear();
+        self.ended.clear();
+    }
+
+    /// handle applies the event.
+    pub fn handle(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::KeyDown(key) => self.keys.press(key),
+            InputEvent::KeyUp(key) => self.keys.release(key),
+            InputEvent::MouseDown(button) => self.mouse.buttons.press(button),
+            InputEvent::MouseUp(button) => self.mouse.buttons.release(button),
+            InputEvent::MouseMove(p) => self.mouse.position = p,
+            InputEvent::MouseMotion(d) => self.mouse.motion = self.mouse.motion + d,
+            InputEvent::MouseWheel(d) => self.mouse.wheel = self.mouse.wheel + d,
+            InputEvent::GamepadConnected(id) => {
+                self.gamepads.entry(id).or_default();
+            }
+            InputEvent::GamepadDisconnected(id) => {
+                self.gamepads.remove(&id);
+            }
+            InputEvent::GamepadDown(id, button) => self.gamepads.entry(id).or_default().buttons.press(button),
+            InputEvent::GamepadUp(id, button) => self.gamepads.entry(id).or_default().buttons.release(button),
+            InputEvent::GamepadAxis(id, axis, value) => self.gamepads.entry(id).or_default().set_axis(axis, value),
+            InputEvent::TouchStart(id, p) => {
+                self.touches.insert(id, Touch {
+                    id,
+                    start: p,
+                    position: p,
+                    cancelled: false,
+                });
+                self.started.push(id);
+            }
+            InputEvent::TouchMove(id, p) => {
+                if let Some(touch) = self.touches.get_mut(&id) {
+                    touch.position = p;
+                }
+            }
+            InputEvent::TouchEnd(id, p) => {
+                if let Some(mut touch) = self.touches.remove(&id) {
+                    touch.position = p;
+                    self.ended.push(touch);
+                }
+            }
+            InputEvent::TouchCancel(id) => {
+                if let Some(mut touch) = self.touches.remove(&id) {
+                    touch.cancelled = true;
+                    self.ended.push(touch);
+                }
+            }
+        }
+    }
+
+    /// update begins a new frame, then applies the events of the source.
+    pub fn update<S: EventSource + ?Sized>(&mut self, source: &mut S) {
+        self.begin_frame();
+        let mut events = std::mem::take(&mut self.events);
+        source.poll(&mut events);
+        for event in events.drain(..) {
+            self.handle(&event);
+        }
+        self.events = events;
+    }
+}
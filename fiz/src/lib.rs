@@ -2,6 +2,12 @@ extern crate fiz_math;
 
 pub use fiz_math as math;
 
+pub mod scene;
+pub mod ecs;
+pub mod app;
+pub mod input;
+pub mod replay;
+
 mod test {
     #[allow(unused_imports)]
     use super::math::Float;
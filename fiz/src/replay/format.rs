@@ -0,0 +1,254 @@
+// The binary format of replays, in which all numbers are little-endian:
+//
+//  - The magic bytes "FIZR" and the version, one byte.
+//  - The timestep in nanoseconds and the number of ticks, as varints.
+//  - For each tick, a byte of flags: 1 if the seed differs from that of the
+//    previous tick (or zero, for the first), in which case the seed follows
+//    as 8 bytes, and 2 if the tick has a hash, which follows as 8 bytes.
+//    Then the number of events, as a varint, and the events: a tag byte
+//    (the index of the variant of `InputEvent`) and the fields. Keys and
+//    buttons are bytes (their index in `ALL`), identifiers are varints, and
+//    floats are 4 bytes.
+//
+// Varints are 7 bits per byte, least significant first, with the top bit
+// set on all but the last byte.
+
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Write};
+use std::time::Duration;
+use math::Vec2;
+use input::{GamepadAxis, GamepadButton, GamepadId, InputEvent, Key, MouseButton, TouchId};
+use super::{Replay, ReplayError, Tick};
+
+const MAGIC: &[u8] = b"FIZR";
+const VERSION: u8 = 1;
+
+const NEW_SEED: u8 = 1;
+const HASH: u8 = 2;
+
+fn write_varint<W: Write>(w: &mut W, mut n: u64) -> io::Result<()> {
+    while n >= 0x80 {
+        w.write_all(&[n as u8 | 0x80])?;
+        n >>= 7;
+    }
+    w.write_all(&[n as u8])
+}
+
+fn write_vec2<W: Write>(w: &mut W, v: Vec2<f32>) -> io::Result<()> {
+    w.write_all(&v.0.to_le_bytes())?;
+    w.write_all(&v.1.to_le_bytes())
+}
+
+fn write_event<W: Write>(w: &mut W, event: &InputEvent) -> io::Result<()> {
+    // Keys and buttons are written as their discriminants, which are their
+    // indices in ALL.
+    match *event {
+        InputEvent::KeyDown(key) => w.write_all(&[0, key as u8]),
+        InputEvent::KeyUp(key) => w.write_all(&[1, key as u8]),
+        InputEvent::MouseDown(button) => w.write_all(&[2, button as u8]),
+        InputEvent::MouseUp(button) => w.write_all(&[3, button as u8]),
+        InputEvent::MouseMove(p) => {
+            w.write_all(&[4])?;
+            write_vec2(w, p)
+        }
+        InputEvent::MouseMotion(d) => {
+            w.write_all(&[5])?;
+            write_vec2(w, d)
+        }
+        InputEvent::MouseWheel(d) => {
+            w.write_all(&[6])?;
+            write_vec2(w, d)
+        }
+        InputEvent::GamepadConnected(id) => {
+            w.write_all(&[7])?;
+            write_varint(w, id.0 as u64)
+        }
+        InputEvent::GamepadDisconnected(id) => {
+            w.write_all(&[8])?;
+            write_varint(w, id.0 as u64)
+        }
+        InputEvent::GamepadDown(id, button) => {
+            w.write_all(&[9])?;
+            write_varint(w, id.0 as u64)?;
+            w.write_all(&[button as u8])
+        }
+        InputEvent::GamepadUp(id, button) => {
+            w.write_all(&[10])?;
+            write_varint(w, id.0 as u64)?;
+            w.write_all(&[button as u8])
+        }
+        InputEvent::GamepadAxis(id, axis, value) => {
+            w.write_all(&[11])?;
+            write_varint(w, id.0 as u64)?;
+            w.write_all(&[axis as u8])?;
+            w.write_all(&value.to_le_bytes())
+        }
+        InputEvent::TouchStart(id, p) => {
+            w.write_all(&[12])?;
+            write_varint(w, id.0)?;
+            write_vec2(w, p)
+        }
+        InputEvent::TouchMove(id, p) => {
+            w.write_all(&[13])?;
+            write_varint(w, id.0)?;
+            write_vec2(w, p)
+        }
+        InputEvent::TouchEnd(id, p) => {
+            w.write_all(&[14])?;
+            write_varint(w, id.0)?;
+            write_vec2(w, p)
+        }
+        InputEvent::TouchCancel(id) => {
+            w.write_all(&[15])?;
+            write_varint(w, id.0)
+        }
+    }
+}
+
+pub fn write<W: Write>(w: &mut W, replay: &Replay) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&[VERSION])?;
+    write_varint(w, replay.timestep.as_nanos() as u64)?;
+    write_varint(w, replay.ticks.len() as u64)?;
+    let mut seed = 0;
+    for tick in &replay.ticks {
+        let flags = if tick.seed != seed { NEW_SEED } else { 0 } | if tick.hash.is_some() { HASH } else { 0 };
+        w.write_all(&[flags])?;
+        if tick.seed != seed {
+            w.write_all(&tick.seed.to_le_bytes())?;
+            seed = tick.seed;
+        }
+        if let Some(hash) = tick.hash {
+            w.write_all(&hash.to_le_bytes())?;
+        }
+        write_varint(w, tick.events.len() as u64)?;
+        for event in &tick.events {
+            write_event(w, event)?;
+        }
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    tick: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        if self.data.len() < n {
+            return Err(ReplayError::Truncated);
+        }
+        let (bytes, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, ReplayError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, ReplayError> {
+        Ok(f32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2<f32>, ReplayError> {
+        Ok(Vec2(self.f32()?, self.f32()?))
+    }
+
+    fn varint(&mut self) -> Result<u64, ReplayError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            n |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.invalid("varint is too long"))
+    }
+
+    fn u32_varint(&mut self) -> Result<u32, ReplayError> {
+        let n = self.varint()?;
+        u32::try_from(n).map_err(|_| self.invalid("gamepad identifier is too large"))
+    }
+
+    fn invalid(&self, message: &str) -> ReplayError {
+        ReplayError::Invalid {
+            tick: self.tick,
+            message: message.to_string(),
+        }
+    }
+
+    // named reads the index of a key or button.
+    fn named<T: Copy>(&mut self, all: &[T]) -> Result<T, ReplayError> {
+        let i = self.u8()?;
+        all.get(i as usize).copied().ok_or_else(|| self.invalid(&format!("unknown key or button {}", i)))
+    }
+
+    fn event(&mut self) -> Result<InputEvent, ReplayError> {
+        Ok(match self.u8()? {
+            0 => InputEvent::KeyDown(self.named(Key::ALL)?),
+            1 => InputEvent::KeyUp(self.named(Key::ALL)?),
+            2 => InputEvent::MouseDown(self.named(MouseButton::ALL)?),
+            3 => InputEvent::MouseUp(self.named(MouseButton::ALL)?),
+            4 => InputEvent::MouseMove(self.vec2()?),
+            5 => InputEvent::MouseMotion(self.vec2()?),
+            6 => InputEvent::MouseWheel(self.vec2()?),
+            7 => InputEvent::GamepadConnected(GamepadId(self.u32_varint()?)),
+            8 => InputEvent::GamepadDisconnected(GamepadId(self.u32_varint()?)),
+            9 => InputEvent::GamepadDown(GamepadId(self.u32_varint()?), self.named(GamepadButton::ALL)?),
+            10 => InputEvent::GamepadUp(GamepadId(self.u32_varint()?), self.named(GamepadButton::ALL)?),
+            11 => InputEvent::GamepadAxis(GamepadId(self.u32_varint()?), self.named(GamepadAxis::ALL)?, self.f32()?),
+            12 => InputEvent::TouchStart(TouchId(self.varint()?), self.vec2()?),
+            13 => InputEvent::TouchMove(TouchId(self.varint()?), self.vec2()?),
+            14 => InputEvent::TouchEnd(TouchId(self.varint()?), self.vec2()?),
+            15 => InputEvent::TouchCancel(TouchId(self.varint()?)),
+            tag => return Err(self.invalid(&format!("unknown event {}", tag))),
+        })
+    }
+}
+
+pub fn parse(data: &[u8]) -> Result<Replay, ReplayError> {
+    if !data.starts_with(MAGIC) {
+        return Err(ReplayError::NotReplay);
+    }
+    let mut r = Reader {
+        data: &data[MAGIC.len()..],
+        tick: 0,
+    };
+    let version = r.u8()?;
+    if version != VERSION {
+        return Err(ReplayError::Version(version));
+    }
+    let timestep = Duration::from_nanos(r.varint()?);
+    let count = r.varint()?;
+    // Each tick takes at least two bytes, which bounds the allocation.
+    let mut ticks = Vec::with_capacity((count as usize).min(r.data.len() / 2));
+    let mut seed = 0;
+    for tick in 0..count as usize {
+        r.tick = tick;
+        let flags = r.u8()?;
+        if flags & !(NEW_SEED | HASH) != 0 {
+            return Err(r.invalid(&format!("unknown flags {:#x}", flags)));
+        }
+        if flags & NEW_SEED != 0 {
+            seed = r.u64()?;
+        }
+        let hash = if flags & HASH != 0 { Some(r.u64()?) } else { None };
+        let n = r.varint()?;
+        let mut events = Vec::with_capacity((n as usize).min(r.data.len() / 2));
+        for _ in 0..n {
+            events.push(r.event()?);
+        }
+        ticks.push(Tick { events, seed, hash });
+    }
+    if !r.data.is_empty() {
+        return Err(r.invalid("trailing data"));
+    }
+    Ok(Replay { timestep, ticks })
+}
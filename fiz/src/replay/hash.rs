@@ -0,0 +1,303 @@
+use math::{Quat, Transform, Vec2, Vec3, Vec4};
+use ecs::{Component, Entity, Velocity, World};
+
+/// StateHasher is a hasher (64-bit FNV-1a) which gives the same hash for
+/// the same data on every machine and with every version of Rust, unlike
+/// the hashers of the standard library. Numbers are hashed as their
+/// little-endian bytes, and floats as their bits.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::replay::{HashState, StateHasher};
+///
+/// let mut h = StateHasher::new();
+/// (1u32, 2.5f32).hash_state(&mut h);
+/// assert_eq!(h.finish(), StateHasher::hash(&(1u32, 2.5f32)));
+/// assert_ne!(h.finish(), StateHasher::hash(&(1u32, -2.5f32)));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StateHasher {
+    state: u64,
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        StateHasher::new()
+    }
+}
+
+impl StateHasher {
+    /// new returns a hasher which has hashed nothing.
+    pub fn new() -> Self {
+        StateHasher { state: 0xcbf2_9ce4_8422_2325 }
+    }
+
+    /// hash returns the hash of the value.
+    pub fn hash<T: HashState + ?Sized>(value: &T) -> u64 {
+        let mut h = StateHasher::new();
+        value.hash_state(&mut h);
+        h.finish()
+    }
+
+    /// write hashes the bytes.
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state = (self.state ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// write_u64 hashes the number.
+    pub fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    /// finish returns the hash of what was hashed.
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// HashState is implemented by the types whose values can be hashed into a
+/// hash of the state of a simulation with `StateHasher`.
+pub trait HashState {
+    /// hash_state hashes the value.
+    fn hash_state(&self, h: &mut StateHasher);
+}
+
+macro_rules! hash_int {
+    ($($t:ty)*) => {
+        $(impl HashState for $t {
+            fn hash_state(&self, h: &mut StateHasher) {
+                h.write(&self.to_le_bytes());
+            }
+        })*
+    };
+}
+
+hash_int!(u8 u16 u32 u64 i8 i16 i32 i64);
+
+impl HashState for usize {
+    fn hash_state(&self, h: &mut StateHasher) {
+        // The same on 32 and 64-bit machines.
+        h.write_u64(*self as u64);
+    }
+}
+
+impl HashState for isize {
+    fn hash_state(&self, h: &mut StateHasher) {
+        h.write_u64(*self as i64 as u64);
+    }
+}
+
+impl HashState for bool {
+    fn hash_state(&self, h: &mut StateHasher) {
+        h.write(&[*self as u8]);
+    }
+}
+
+impl HashState for f32 {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.to_bits().hash_state(h);
+    }
+}
+
+impl HashState for f64 {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.to_bits().hash_state(h);
+    }
+}
+
+impl HashState for str {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.len().hash_state(h);
+        h.write(self.as_bytes());
+    }
+}
+
+impl HashState for String {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.as_str().hash_state(h);
+    }
+}
+
+impl<T: HashState + ?Sized> HashState for &T {
+    fn hash_state(&self, h: &mut StateHasher) {
+        (**self).hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for [T] {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.len().hash_state(h);
+        for x in self {
+            x.hash_state(h);
+        }
+    }
+}
+
+impl<T: HashState> HashState for Vec<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self[..].hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for Option<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        match *self {
+            None => false.hash_state(h),
+            Some(ref x) => {
+                true.hash_state(h);
+                x.hash_state(h);
+            }
+        }
+    }
+}
+
+macro_rules! hash_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: HashState),*> HashState for ($($t,)*) {
+            fn hash_state(&self, h: &mut StateHasher) {
+                $(self.$i.hash_state(h);)*
+            }
+        }
+    };
+}
+
+hash_tuple!(A 0);
+hash_tuple!(A 0, B 1);
+hash_tuple!(A 0, B 1, C 2);
+hash_tuple!(A 0, B 1, C 2, D 3);
+
+impl<T: HashState> HashState for Vec2<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.0.hash_state(h);
+        self.1.hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for Vec3<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.0.hash_state(h);
+        self.1.hash_state(h);
+        self.2.hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for Vec4<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.0.hash_state(h);
+        self.1.hash_state(h);
+        self.2.hash_state(h);
+        self.3.hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for Quat<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.0.hash_state(h);
+        self.1.hash_state(h);
+        self.2.hash_state(h);
+        self.3.hash_state(h);
+    }
+}
+
+impl<T: HashState> HashState for Transform<T> {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.translation.hash_state(h);
+        self.rotation.hash_state(h);
+        self.scale.hash_state(h);
+    }
+}
+
+impl HashState for Entity {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.index().hash_state(h);
+        self.generation().hash_state(h);
+    }
+}
+
+impl HashState for Velocity {
+    fn hash_state(&self, h: &mut StateHasher) {
+        self.linear.hash_state(h);
+        self.angular.hash_state(h);
+    }
+}
+
+// Hash hashes part of the state of a world.
+type Hash = Box<dyn Fn(&World, &mut StateHasher) + Send + Sync>;
+
+/// Validator hashes the state of a world, from the components and resources
+/// of the types it tracks, to tell when a replay diverges from the
+/// recording. Only state which affects the simulation needs tracking.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::replay::{HashState, StateHasher, Validator};
+/// use fiz::ecs::World;
+///
+/// struct Health(u32);
+///
+/// impl HashState for Health {
+///     fn hash_state(&self, h: &mut StateHasher) {
+///         self.0.hash_state(h);
+///     }
+/// }
+///
+/// let validator = Validator::new().component::<Health>().resource::<u64>();
+/// let mut world = World::new();
+/// let hero = world.spawn((Health(10),));
+/// world.insert_resource(7u64);
+/// let before = validator.hash(&world);
+///
+/// world.get_mut::<Health>(hero).unwrap().0 -= 1;
+/// assert_ne!(validator.hash(&world), before);
+/// ```
+#[derive(Default)]
+pub struct Validator {
+    hashes: Vec<Hash>,
+}
+
+impl std::fmt::Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Validator").field("tracked", &self.hashes.len()).finish()
+    }
+}
+
+impl Validator {
+    /// new returns a validator which tracks nothing.
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    /// component tracks the components of type T, in the order of the
+    /// entities of the world.
+    pub fn component<T: Component + HashState>(mut self) -> Self {
+        self.hashes.push(Box::new(|world, h| {
+            world.query::<&T, ()>().count().hash_state(h);
+            for &entity in world.entities() {
+                if let Some(c) = world.get::<T>(entity) {
+                    entity.hash_state(h);
+                    (*c).hash_state(h);
+                }
+            }
+        }));
+        self
+    }
+
+    /// resource tracks the resource of type R.
+    pub fn resource<R: Component + HashState>(mut self) -> Self {
+        self.hashes.push(Box::new(|world, h| world.resource::<R>().as_deref().hash_state(h)));
+        self
+    }
+
+    /// hash returns the hash of the tracked state of the world.
+    pub fn hash(&self, world: &World) -> u64 {
+        let mut h = StateHasher::new();
+        for hash in &self.hashes {
+            hash(world, &mut h);
+        }
+        h.finish()
+    }
+}
@@ -0,0 +1,532 @@
+//! Input recording and deterministic replay.
+//!
+//! A simulation which runs in fixed timesteps, takes all of its input
+//! through an `EventSource` and all of its randomness from an `Rng` seeded
+//! each tick, does the same thing every time it's given the same events and
+//! seeds. A `Recorder` records them, per tick, into a `Replay`, which is
+//! saved in a compact binary format, and a `Player` plays it back to
+//! reproduce a run exactly, on any machine.
+//!
+//! To find where a replay stops reproducing the recording, as when the
+//! simulation depends on something it shouldn't, such as the order of a
+//! `HashMap`, a `Validator` hashes the state of the world each tick. The
+//! recorder stores the hashes, and the player reports the first tick whose
+//! hash differs.
+
+mod format;
+mod hash;
+mod rng;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use input::{EventSource, InputEvent};
+
+pub use self::hash::{HashState, StateHasher, Validator};
+pub use self::rng::Rng;
+
+/// Tick is what a simulation was given in one fixed update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tick {
+    /// The input events.
+    pub events: Vec<InputEvent>,
+
+    /// The seed of the random numbers.
+    pub seed: u64,
+
+    /// The hash of the state of the simulation after the tick, if it was
+    /// recorded.
+    pub hash: Option<u64>,
+}
+
+/// Replay is a recording of the ticks of a simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    /// The timestep of the simulation.
+    pub timestep: Duration,
+
+    /// The ticks, in order.
+    pub ticks: Vec<Tick>,
+}
+
+impl Replay {
+    /// new returns an empty recording of a simulation with the timestep.
+    pub fn new(timestep: Duration) -> Self {
+        Replay {
+            timestep,
+            ticks: Vec::new(),
+        }
+    }
+
+    /// first_divergence returns the first tick at which both recordings
+    /// have hashes, and they differ.
+    pub fn first_divergence(&self, other: &Replay) -> Option<Divergence> {
+        self.ticks.iter().zip(&other.ticks).enumerate().find_map(|(tick, (a, b))| match (a.hash, b.hash) {
+            (Some(expected), Some(actual)) if expected != actual => Some(Divergence {
+                tick,
+                expected,
+                actual,
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// ReplayError describes a failure to load or save a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// A file couldn't be read or written.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// The data doesn't start with the magic bytes of a replay.
+    NotReplay,
+
+    /// The replay is of a version of the format which isn't supported.
+    Version(u8),
+
+    /// The data ends before the replay does.
+    Truncated,
+
+    /// The data of a tick is invalid.
+    Invalid {
+        /// The tick, starting at zero.
+        tick: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            ReplayError::NotReplay => write!(f, "not a replay"),
+            ReplayError::Version(v) => write!(f, "unsupported replay version {}", v),
+            ReplayError::Truncated => write!(f, "truncated replay"),
+            ReplayError::Invalid { tick, ref message } => write!(f, "tick {}: {}", tick, message),
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ReplayError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// load loads the replay file at the given path.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|error| {
+        ReplayError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    parse_replay(&data)
+}
+
+/// save saves the replay to a file at the given path.
+pub fn save<P: AsRef<Path>>(path: P, replay: &Replay) -> Result<(), ReplayError> {
+    let path = path.as_ref();
+    let mut data = Vec::new();
+    write_replay(&mut data, replay).unwrap();
+    fs::write(path, data).map_err(|error| {
+        ReplayError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })
+}
+
+/// parse_replay parses the contents of a replay file.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use fiz::input::{InputEvent, Key};
+/// use fiz::replay::{parse_replay, write_replay, Replay, ReplayError, Tick};
+///
+/// let mut replay = Replay::new(Duration::from_millis(10));
+/// for _ in 0..100 {
+///     replay.ticks.push(Tick { events: vec![InputEvent::KeyDown(Key::W)], seed: 42, hash: None });
+/// }
+/// let mut data = Vec::new();
+/// write_replay(&mut data, &replay).unwrap();
+/// assert_eq!(data.len(), 10 + 8 + 100 * 4);
+/// assert_eq!(parse_replay(&data).unwrap(), replay);
+///
+/// match parse_replay(&data[..data.len() - 1]) {
+///     Err(ReplayError::Truncated) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse_replay(data: &[u8]) -> Result<Replay, ReplayError> {
+    format::parse(data)
+}
+
+/// write_replay writes the replay in the binary format of replay files.
+pub fn write_replay<W: Write>(w: &mut W, replay: &Replay) -> io::Result<()> {
+    format::write(w, replay)
+}
+
+/// Divergence is a tick at which the state of a replay differs from that of
+/// the recording.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The tick, starting at zero.
+    pub tick: usize,
+
+    /// The hash of the recorded state.
+    pub expected: u64,
+
+    /// The hash of the replayed state.
+    pub actual: u64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "replay diverged at tick {}: expected state {:016x}, got {:016x}", self.tick, self.expected,
+               self.actual)
+    }
+}
+
+impl Error for Divergence {}
+
+/// Recorder is an event source which records the events of another, one
+/// tick per poll, along with the seed and state hash of each tick.
+#[derive(Debug)]
+pub struct Recorder<S> {
+    source: S,
+    replay: Replay,
+}
+
+impl<S: EventSource> Recorder<S> {
+    /// new returns a recorder of the events of the source, for a simulation
+    /// with the timestep.
+    pub fn new(source: S, timestep: Duration) -> Self {
+        Recorder {
+            source,
+            replay: Replay::new(timestep),
+        }
+    }
+
+    /// source returns the source of the events.
+    pub fn source(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// replay returns the recording so far.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// finish returns the recording.
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+
+    fn current(&mut self) -> &mut Tick {
+        self.replay.ticks.last_mut().expect("Recorder: no tick has been polled")
+    }
+
+    /// record_seed records the seed of the random numbers of the current
+    /// tick, and returns it.
+    ///
+    /// # Panics
+    ///
+    /// record_seed panics if no tick has been polled.
+    pub fn record_seed(&mut self, seed: u64) -> u64 {
+        self.current().seed = seed;
+        seed
+    }
+
+    /// record_hash records the hash of the state after the current tick.
+    ///
+    /// # Panics
+    ///
+    /// record_hash panics if no tick has been polled.
+    pub fn record_hash(&mut self, hash: u64) {
+        self.current().hash = Some(hash);
+    }
+}
+
+impl<S: EventSource> EventSource for Recorder<S> {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        let start = events.len();
+        self.source.poll(events);
+        self.replay.ticks.push(Tick {
+            events: events[start..].to_vec(),
+            seed: 0,
+            hash: None,
+        });
+    }
+}
+
+/// Player is an event source which plays back the events of a replay, one
+/// tick per poll, and checks the state of each tick against the recording.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use fiz::ecs::World;
+/// use fiz::input::{Input, InputEvent, Key, ScriptedEvents};
+/// use fiz::replay::{Player, Recorder, Rng, Validator};
+///
+/// // A simulation counting, with some randomness, the ticks space is held.
+/// fn tick(world: &mut World, input: &Input, seed: u64) {
+///     if input.keys.is_held(Key::Space) {
+///         *world.resource_mut::<u64>().unwrap() += 1 + Rng::new(seed).below(3);
+///     }
+/// }
+///
+/// let validator = Validator::new().resource::<u64>();
+/// let mut script = ScriptedEvents::new();
+/// script.frame(vec![InputEvent::KeyDown(Key::Space)]).frame(vec![]).frame(vec![InputEvent::KeyUp(Key::Space)]);
+///
+/// let mut recorder = Recorder::new(script, Duration::from_millis(10));
+/// let (mut world, mut input) = (World::new(), Input::new());
+/// world.insert_resource(0u64);
+/// for i in 0..3 {
+///     input.update(&mut recorder);
+///     let seed = recorder.record_seed(1000 + i);
+///     tick(&mut world, &input, seed);
+///     recorder.record_hash(validator.hash(&world));
+/// }
+/// let replay = recorder.finish();
+///
+/// let mut player = Player::new(replay);
+/// let (mut world, mut input) = (World::new(), Input::new());
+/// world.insert_resource(0u64);
+/// while !player.is_finished() {
+///     input.update(&mut player);
+///     tick(&mut world, &input, player.seed());
+///     player.check(validator.hash(&world)).unwrap();
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Player {
+    replay: Replay,
+    next: usize,
+}
+
+impl Player {
+    /// new returns a player of the replay, before its first tick.
+    pub fn new(replay: Replay) -> Self {
+        Player { replay, next: 0 }
+    }
+
+    /// replay returns the replay.
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// tick returns the index of the current tick, which is the last one
+    /// polled, or None before the first poll.
+    pub fn tick(&self) -> Option<usize> {
+        self.next.checked_sub(1)
+    }
+
+    /// is_finished tells if every tick has been polled. Polls then return
+    /// no events.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.ticks.len()
+    }
+
+    fn current(&self) -> &Tick {
+        self.tick()
+            .and_then(|i| self.replay.ticks.get(i))
+            .expect("Player: no tick has been polled, or the replay is finished")
+    }
+
+    /// seed returns the recorded seed of the random numbers of the current
+    /// tick.
+    ///
+    /// # Panics
+    ///
+    /// seed panics if no tick has been polled, or polls went past the end
+    /// of the replay.
+    pub fn seed(&self) -> u64 {
+        self.current().seed
+    }
+
+    /// check compares the hash of the state after the current tick with the
+    /// recorded one, if there is one.
+    ///
+    /// # Panics
+    ///
+    /// check panics if no tick has been polled, or polls went past the end
+    /// of the replay.
+    pub fn check(&self, hash: u64) -> Result<(), Divergence> {
+        match self.current().hash {
+            Some(expected) if expected != hash => Err(Divergence {
+                tick: self.next - 1,
+                expected,
+                actual: hash,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl EventSource for Player {
+    fn poll(&mut self, events: &mut Vec<InputEvent>) {
+        if let Some(tick) = self.replay.ticks.get(self.next) {
+            events.extend_from_slice(&tick.events);
+        }
+        self.next += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::time::Duration;
+    use math::{Transform, Vec2, Vec3};
+    use ecs::{Velocity, World};
+    use input::{GamepadAxis, GamepadButton, GamepadId, Input, InputEvent, Key, MouseButton, ScriptedEvents, TouchId};
+    use super::{load, parse_replay, save, write_replay, Divergence, Player, Recorder, ReplayError, Rng, Validator};
+
+    #[test]
+    fn format() {
+        let events = vec![
+            InputEvent::KeyDown(Key::Slash),
+            InputEvent::KeyUp(Key::A),
+            InputEvent::MouseDown(MouseButton::Forward),
+            InputEvent::MouseUp(MouseButton::Left),
+            InputEvent::MouseMove(Vec2(-1.5, 1e9)),
+            InputEvent::MouseMotion(Vec2(0.25, -0.0)),
+            InputEvent::MouseWheel(Vec2(0.0, 3.0)),
+            InputEvent::GamepadConnected(GamepadId(u32::MAX)),
+            InputEvent::GamepadDisconnected(GamepadId(0)),
+            InputEvent::GamepadDown(GamepadId(300), GamepadButton::DpadRight),
+            InputEvent::GamepadUp(GamepadId(1), GamepadButton::South),
+            InputEvent::GamepadAxis(GamepadId(2), GamepadAxis::RightTrigger, 0.75),
+            InputEvent::TouchStart(TouchId(u64::MAX), Vec2(1.0, 2.0)),
+            InputEvent::TouchMove(TouchId(128), Vec2(3.0, 4.0)),
+            InputEvent::TouchEnd(TouchId(7), Vec2(5.0, 6.0)),
+            InputEvent::TouchCancel(TouchId(0)),
+        ];
+        let mut script = ScriptedEvents::new();
+        script.frame(events.clone()).frame(vec![]).frame(vec![InputEvent::KeyDown(Key::Space)]);
+        let mut recorder = Recorder::new(script, Duration::from_micros(16_667));
+        let mut input = Input::new();
+        for (seed, hash) in [(0, None), (u64::MAX, Some(5)), (u64::MAX, Some(u64::MAX))] {
+            input.update(&mut recorder);
+            recorder.record_seed(seed);
+            if let Some(hash) = hash {
+                recorder.record_hash(hash);
+            }
+        }
+        let replay = recorder.finish();
+        assert_eq!(replay.ticks[0].events, events);
+
+        let path = env::temp_dir().join(format!("fiz-replay-{}.fizr", std::process::id()));
+        save(&path, &replay).unwrap();
+        assert_eq!(load(&path).unwrap(), replay);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut data = Vec::new();
+        write_replay(&mut data, &replay).unwrap();
+        for end in 0..data.len() {
+            assert!(parse_replay(&data[..end]).is_err());
+        }
+        assert!(matches!(parse_replay(b"FIZX\x01"), Err(ReplayError::NotReplay)));
+        data[4] = 2;
+        assert!(matches!(parse_replay(&data), Err(ReplayError::Version(2))));
+        data[4] = 1;
+        data.push(0);
+        assert!(matches!(parse_replay(&data), Err(ReplayError::Invalid { tick: 2, .. })));
+    }
+
+    // simulate runs a tick of a small simulation: a ship steered by input,
+    // and asteroids spawned at random.
+    fn simulate(world: &mut World, input: &Input, seed: u64, buggy: bool) {
+        let mut rng = Rng::new(seed);
+        let dt = 0.01;
+        if input.keys.is_pressed(Key::Space) || (buggy && input.keys.is_held(Key::Space)) {
+            let p = Vec3(rng.next_f32() * 100.0, rng.next_f32() * 100.0, 0.0);
+            world.spawn((Transform::from_translation(p), Velocity::default()));
+        }
+        let thrust = input.keys.is_held(Key::W) as i32 as f32;
+        let entities = world.entities().to_vec();
+        for e in entities {
+            let v = *world.get::<Velocity>(e).unwrap();
+            let mut t = world.get_mut::<Transform<f32>>(e).unwrap();
+            v.integrate(&mut t, dt);
+        }
+        let ship = world.entities()[0];
+        world.get_mut::<Velocity>(ship).unwrap().linear.1 += thrust;
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.spawn((Transform::<f32>::identity(), Velocity::default()));
+        world
+    }
+
+    #[test]
+    fn replays() {
+        let validator = Validator::new().component::<Transform<f32>>().component::<Velocity>();
+        let mut script = ScriptedEvents::new();
+        for i in 0..60 {
+            let mut events = Vec::new();
+            if i % 20 == 5 {
+                events.push(InputEvent::KeyDown(Key::Space));
+            }
+            if i % 20 == 8 {
+                events.push(InputEvent::KeyUp(Key::Space));
+            }
+            if i == 10 {
+                events.push(InputEvent::KeyDown(Key::W));
+            }
+            script.frame(events);
+        }
+        let mut recorder = Recorder::new(script, Duration::from_millis(10));
+        let mut world = world();
+        let mut input = Input::new();
+        let mut seeds = Rng::new(7);
+        for _ in 0..60 {
+            input.update(&mut recorder);
+            let seed = recorder.record_seed(seeds.next_u64());
+            simulate(&mut world, &input, seed, false);
+            recorder.record_hash(validator.hash(&world));
+        }
+        let mut data = Vec::new();
+        write_replay(&mut data, recorder.replay()).unwrap();
+        let replay = parse_replay(&data).unwrap();
+        assert_eq!(world.len(), 4);
+
+        // Replaying reproduces the recording, and a bug is caught at the
+        // first tick it changes the state.
+        for &buggy in &[false, true] {
+            let mut player = Player::new(replay.clone());
+            let mut world = self::world();
+            let mut input = Input::new();
+            let mut first = None;
+            while !player.is_finished() {
+                input.update(&mut player);
+                simulate(&mut world, &input, player.seed(), buggy);
+                if let Err(d) = player.check(validator.hash(&world)) {
+                    first = first.or(Some(d));
+                }
+            }
+            assert_eq!(first.map(|d: Divergence| d.tick), if buggy { Some(6) } else { None });
+        }
+    }
+}
@@ -0,0 +1,71 @@
+/// Rng is a small, fast pseudo-random number generator (SplitMix64) which
+/// gives the same numbers from the same seed on every machine, so that
+/// simulations seeded with it can be replayed. It isn't suitable for
+/// cryptography.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::replay::Rng;
+///
+/// let mut a = Rng::new(42);
+/// let mut b = Rng::new(42);
+/// assert_eq!(a.next_u64(), b.next_u64());
+///
+/// let x = a.next_f32();
+/// assert!((0.0..1.0).contains(&x));
+/// assert!(a.below(6) < 6);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// new returns a generator seeded with the seed.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// next_u64 returns a uniformly distributed 64-bit number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// next_u32 returns a uniformly distributed 32-bit number.
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// next_f32 returns a uniformly distributed number in the range [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+
+    /// next_f64 returns a uniformly distributed number in the range [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// below returns a uniformly distributed number less than n.
+    ///
+    /// # Panics
+    ///
+    /// below panics if n is zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Rng::below: n must be positive");
+        // Reject the numbers past the last whole multiple of n, which would
+        // favour small results.
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
@@ -0,0 +1,639 @@
+//! Scene graphs.
+//!
+//! A `Scene` is a forest of nodes, each holding a user value and a transform
+//! relative to its parent. World transforms are cached, and recomputed only
+//! for nodes whose transform or ancestors changed since the last `update`.
+
+mod traverse;
+
+use std::error::Error;
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use math::{Aabb, Mat4, Transform, Vec3};
+
+pub use self::traverse::{Ancestors, BreadthFirst, DepthFirst};
+
+/// NodeId identifies a node of a `Scene`. Identifiers of removed nodes are
+/// never equal to those of later ones, which may reuse their slot with a new
+/// generation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+/// SceneError describes why a node couldn't be moved to a new parent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SceneError {
+    /// The new parent is the node itself or one of its descendants.
+    Cycle,
+
+    /// The world transform of the node can't be kept, because the world
+    /// transform of the new parent isn't invertible.
+    Singular,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SceneError::Cycle => write!(f, "node can't be a descendant of itself"),
+            SceneError::Singular => write!(f, "world transform of the new parent is singular"),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+#[derive(Clone, Debug)]
+struct Node<N> {
+    value: N,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    local: Transform<f32>,
+    bounds: Option<Aabb<f32>>,
+
+    // The cached world transform, which is stale while the node is dirty.
+    // The descendants of a dirty node are always dirty too.
+    world: Mat4<f32>,
+    dirty: bool,
+}
+
+/// Scene is a hierarchy of nodes holding values of type N, each with a
+/// transform relative to its parent (its local transform) and optional
+/// bounds in its local space.
+///
+/// # Examples
+///
+/// ```
+/// use fiz::math::{Transform, Vec3};
+/// use fiz::scene::Scene;
+///
+/// let mut scene = Scene::new();
+/// let car = scene.insert("car", Transform::from_translation(Vec3(10.0, 0.0, 0.0)));
+/// let wheel = scene.insert_child(car, "wheel", Transform::from_translation(Vec3(1.0, -0.5, 0.0)));
+/// assert_eq!(scene.world(wheel).transform_point(Vec3(0.0, 0.0, 0.0)), Vec3(11.0, -0.5, 0.0));
+///
+/// // Moving the car moves its wheel.
+/// scene.set_local(car, Transform::from_translation(Vec3(20.0, 0.0, 0.0)));
+/// scene.update();
+/// assert_eq!(scene.world(wheel).transform_point(Vec3(0.0, 0.0, 0.0)), Vec3(21.0, -0.5, 0.0));
+///
+/// // The wheel comes off, staying where it was.
+/// scene.set_parent(wheel, None, true).unwrap();
+/// scene.set_local(car, Transform::identity());
+/// assert_eq!(scene.world(wheel).transform_point(Vec3(0.0, 0.0, 0.0)), Vec3(21.0, -0.5, 0.0));
+/// assert_eq!(scene[wheel], "wheel");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scene<N> {
+    nodes: Vec<Option<Node<N>>>,
+    // The number of times each slot of nodes was freed.
+    generations: Vec<u32>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+
+    // Nodes marked dirty since the last update, whose descendants are
+    // dirty too.
+    dirty: Vec<NodeId>,
+}
+
+impl<N> Default for Scene<N> {
+    fn default() -> Self {
+        Scene::new()
+    }
+}
+
+impl<N> Scene<N> {
+    /// new returns a new empty scene.
+    pub fn new() -> Self {
+        Scene {
+            nodes: Vec::new(),
+            generations: Vec::new(),
+            free: Vec::new(),
+            roots: Vec::new(),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// len returns the number of nodes in the scene.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    /// is_empty tells if the scene has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// contains tells if the node is in the scene.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.node(id).is_some()
+    }
+
+    // node returns the node, or None if it was removed.
+    fn node(&self, id: NodeId) -> Option<&Node<N>> {
+        if self.generations.get(id.index) != Some(&id.generation) {
+            return None;
+        }
+        self.nodes[id.index].as_ref()
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> Option<&mut Node<N>> {
+        if self.generations.get(id.index) != Some(&id.generation) {
+            return None;
+        }
+        self.nodes[id.index].as_mut()
+    }
+
+    // expect returns the node, panicking in the name of the method if it was
+    // removed.
+    fn expect(&self, id: NodeId, method: &str) -> &Node<N> {
+        self.node(id).unwrap_or_else(|| panic!("Scene::{}: node was removed", method))
+    }
+
+    fn expect_mut(&mut self, id: NodeId, method: &str) -> &mut Node<N> {
+        self.node_mut(id).unwrap_or_else(|| panic!("Scene::{}: node was removed", method))
+    }
+
+    /// insert adds a new root node with the given value and transform,
+    /// returning its identifier.
+    pub fn insert(&mut self, value: N, local: Transform<f32>) -> NodeId {
+        let id = self.alloc(Node {
+            value,
+            parent: None,
+            children: Vec::new(),
+            local,
+            bounds: None,
+            world: Mat4::identity(),
+            dirty: true,
+        });
+        self.roots.push(id);
+        self.dirty.push(id);
+        id
+    }
+
+    /// insert_child adds a new node with the given value and transform
+    /// relative to the parent, returning its identifier.
+    ///
+    /// # Panics
+    ///
+    /// insert_child panics if the parent was removed.
+    pub fn insert_child(&mut self, parent: NodeId, value: N, local: Transform<f32>) -> NodeId {
+        self.expect(parent, "insert_child");
+        let id = self.insert(value, local);
+        self.roots.pop();
+        self.link(id, Some(parent));
+        id
+    }
+
+    fn alloc(&mut self, node: Node<N>) -> NodeId {
+        let index = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.generations.push(0);
+                self.nodes.len() - 1
+            }
+        };
+        NodeId {
+            index,
+            generation: self.generations[index],
+        }
+    }
+
+    // link makes the unlinked node a child of the parent, or a root.
+    fn link(&mut self, id: NodeId, parent: Option<NodeId>) {
+        self.expect_mut(id, "link").parent = parent;
+        match parent {
+            Some(p) => self.expect_mut(p, "link").children.push(id),
+            None => self.roots.push(id),
+        }
+    }
+
+    // unlink removes the node from the children of its parent, or the
+    // roots.
+    fn unlink(&mut self, id: NodeId) {
+        let siblings = match self.expect(id, "unlink").parent {
+            Some(p) => &mut self.expect_mut(p, "unlink").children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|&c| c != id);
+    }
+
+    /// remove removes the node and all of its descendants from the scene,
+    /// returning the value of the node, or None if it was already removed.
+    pub fn remove(&mut self, id: NodeId) -> Option<N> {
+        self.node(id)?;
+        self.unlink(id);
+        let subtree: Vec<NodeId> = self.depth_first(id).collect();
+        let mut value = None;
+        for n in subtree {
+            let node = self.nodes[n.index].take().unwrap();
+            self.generations[n.index] += 1;
+            self.free.push(n.index);
+            if n == id {
+                value = Some(node.value);
+            }
+        }
+        value
+    }
+
+    /// get returns a reference to the value of the node, or None if it was
+    /// removed.
+    pub fn get(&self, id: NodeId) -> Option<&N> {
+        self.node(id).map(|n| &n.value)
+    }
+
+    /// get_mut returns a mutable reference to the value of the node, or None
+    /// if it was removed.
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut N> {
+        self.node_mut(id).map(|n| &mut n.value)
+    }
+
+    /// roots returns the nodes which have no parent, in the order they were
+    /// added.
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// parent returns the parent of the node, or None if it's a root.
+    ///
+    /// # Panics
+    ///
+    /// parent panics if the node was removed.
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.expect(id, "parent").parent
+    }
+
+    /// children returns the children of the node, in the order they were
+    /// added.
+    ///
+    /// # Panics
+    ///
+    /// children panics if the node was removed.
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.expect(id, "children").children
+    }
+
+    /// is_ancestor tells if a is b or one of its ancestors.
+    ///
+    /// # Panics
+    ///
+    /// is_ancestor panics if b was removed.
+    pub fn is_ancestor(&self, a: NodeId, b: NodeId) -> bool {
+        a == b || self.ancestors(b).any(|n| n == a)
+    }
+
+    /// set_parent moves the node and its descendants to a new parent, or to
+    /// the roots. If keep_world is true, the local transform of the node is
+    /// changed so that its world transform stays the same (less any shear,
+    /// which a transform can't represent); otherwise it moves with its new
+    /// parent.
+    ///
+    /// The node is added after the existing children of the parent.
+    ///
+    /// # Panics
+    ///
+    /// set_parent panics if the node or the parent was removed.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>, keep_world: bool) -> Result<(), SceneError> {
+        self.expect(id, "set_parent");
+        if let Some(p) = parent {
+            self.expect(p, "set_parent");
+            if self.is_ancestor(id, p) {
+                return Err(SceneError::Cycle);
+            }
+        }
+        if keep_world {
+            let parent_world = parent.map_or(Mat4::identity(), |p| self.world(p));
+            let local = parent_world.inverse()
+                .and_then(|inverse| Transform::from_mat4(&(inverse * self.world(id))))
+                .ok_or(SceneError::Singular)?;
+            self.expect_mut(id, "set_parent").local = local;
+        }
+        self.unlink(id);
+        self.link(id, parent);
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    /// local returns the transform of the node relative to its parent.
+    ///
+    /// # Panics
+    ///
+    /// local panics if the node was removed.
+    pub fn local(&self, id: NodeId) -> Transform<f32> {
+        self.expect(id, "local").local
+    }
+
+    /// set_local sets the transform of the node relative to its parent,
+    /// which changes the world transforms of it and its descendants.
+    ///
+    /// # Panics
+    ///
+    /// set_local panics if the node was removed.
+    pub fn set_local(&mut self, id: NodeId, local: Transform<f32>) {
+        self.expect_mut(id, "set_local").local = local;
+        self.mark_dirty(id);
+    }
+
+    // mark_dirty marks the node and its descendants as needing their world
+    // transforms recomputed.
+    fn mark_dirty(&mut self, id: NodeId) {
+        // The node may have moved away from the dirty ancestor it was
+        // listed under, so list it regardless.
+        self.dirty.push(id);
+        if self.expect(id, "mark_dirty").dirty {
+            // Its descendants are dirty already.
+            return;
+        }
+        let mut stack = vec![id];
+        while let Some(n) = stack.pop() {
+            let node = self.nodes[n.index].as_mut().unwrap();
+            if node.dirty && n != id {
+                continue;
+            }
+            node.dirty = true;
+            stack.extend_from_slice(&node.children);
+        }
+    }
+
+    /// is_dirty tells if the world transform of the node has changed since
+    /// the last update.
+    ///
+    /// # Panics
+    ///
+    /// is_dirty panics if the node was removed.
+    pub fn is_dirty(&self, id: NodeId) -> bool {
+        self.expect(id, "is_dirty").dirty
+    }
+
+    /// world returns the transform of the node relative to the scene, the
+    /// product of its local transform and those of its ancestors.
+    ///
+    /// World transforms are cached by `update`; those of nodes which have
+    /// changed since are computed from their nearest unchanged ancestor.
+    ///
+    /// # Panics
+    ///
+    /// world panics if the node was removed.
+    pub fn world(&self, id: NodeId) -> Mat4<f32> {
+        let node = self.expect(id, "world");
+        if !node.dirty {
+            return node.world;
+        }
+        let local = node.local.to_mat4();
+        match node.parent {
+            Some(p) => self.world(p) * local,
+            None => local,
+        }
+    }
+
+    /// update recomputes the cached world transforms of the nodes which
+    /// changed since the last update, that is, those whose local transform
+    /// or parent changed, their descendants, and new nodes.
+    pub fn update(&mut self) {
+        let dirty = ::std::mem::take(&mut self.dirty);
+        for id in dirty {
+            if !self.node(id).is_some_and(|n| n.dirty) {
+                continue;
+            }
+            // Start from the topmost dirty ancestor, as descendants depend
+            // on it.
+            let top = self.ancestors(id).take_while(|&a| self.nodes[a.index].as_ref().unwrap().dirty).last();
+            let top = top.unwrap_or(id);
+            let mut stack = vec![(top, self.node(top).unwrap().parent.map_or(Mat4::identity(), |p| self.world(p)))];
+            while let Some((n, parent_world)) = stack.pop() {
+                let node = self.nodes[n.index].as_mut().unwrap();
+                node.world = parent_world * node.local.to_mat4();
+                node.dirty = false;
+                let world = node.world;
+                stack.extend(node.children.iter().map(|&c| (c, world)));
+            }
+        }
+    }
+
+    /// bounds returns the bounds of the node in its local space, if it has
+    /// any.
+    ///
+    /// # Panics
+    ///
+    /// bounds panics if the node was removed.
+    pub fn bounds(&self, id: NodeId) -> Option<Aabb<f32>> {
+        self.expect(id, "bounds").bounds
+    }
+
+    /// set_bounds sets the bounds of the node in its local space, or None if
+    /// it has no extent of its own, like a group.
+    ///
+    /// # Panics
+    ///
+    /// set_bounds panics if the node was removed.
+    pub fn set_bounds(&mut self, id: NodeId, bounds: Option<Aabb<f32>>) {
+        self.expect_mut(id, "set_bounds").bounds = bounds;
+    }
+
+    /// world_bounds returns the box in world space enclosing the bounds of
+    /// the node and all of its descendants, or None if none of them has
+    /// bounds.
+    ///
+    /// # Panics
+    ///
+    /// world_bounds panics if the node was removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz::math::{Aabb, Quat, Transform, Vec3};
+    /// use fiz::math::unit::Deg;
+    /// use fiz::scene::Scene;
+    ///
+    /// let mut scene = Scene::new();
+    /// let group = scene.insert((), Transform::from_translation(Vec3(0.0, 5.0, 0.0)));
+    /// let unit = Aabb::new(Vec3(-0.5, -0.5, -0.5), Vec3(0.5, 0.5, 0.5));
+    /// let a = scene.insert_child(group, (), Transform::from_translation(Vec3(-2.0, 0.0, 0.0)));
+    /// let b = scene.insert_child(group, (), Transform {
+    ///     translation: Vec3(2.0, 0.0, 0.0),
+    ///     rotation: Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), Deg(90.0)),
+    ///     scale: Vec3(4.0, 1.0, 1.0),
+    /// });
+    /// scene.set_bounds(a, Some(unit));
+    /// scene.set_bounds(b, Some(unit));
+    ///
+    /// let bounds = scene.world_bounds(group).unwrap();
+    /// assert!(bounds.min.almost_equal(Vec3(-2.5, 4.5, -2.0), 1e-6));
+    /// assert!(bounds.max.almost_equal(Vec3(2.5, 5.5, 2.0), 1e-6));
+    /// ```
+    pub fn world_bounds(&self, id: NodeId) -> Option<Aabb<f32>> {
+        self.expect(id, "world_bounds");
+        let mut total: Option<Aabb<f32>> = None;
+        for n in self.depth_first(id) {
+            let local = match self.nodes[n.index].as_ref().unwrap().bounds {
+                Some(b) => b,
+                None => continue,
+            };
+            let world = self.world(n);
+            let corners: Vec<Vec3<f32>> = local.corners().iter().map(|&c| world.transform_point(c)).collect();
+            let b = Aabb::from_points(&corners).unwrap();
+            total = Some(total.map_or(b, |t| t.union(&b)));
+        }
+        total
+    }
+
+    /// depth_first returns an iterator over the node and its descendants,
+    /// each parent before its children (pre-order).
+    ///
+    /// # Panics
+    ///
+    /// depth_first panics if the node was removed.
+    pub fn depth_first(&self, id: NodeId) -> DepthFirst<'_, N> {
+        self.expect(id, "depth_first");
+        DepthFirst::new(self, vec![id])
+    }
+
+    /// iter returns an iterator over all nodes of the scene, depth first from
+    /// each root in turn.
+    pub fn iter(&self) -> DepthFirst<'_, N> {
+        DepthFirst::new(self, self.roots.iter().rev().cloned().collect())
+    }
+
+    /// breadth_first returns an iterator over the node and its descendants,
+    /// level by level.
+    ///
+    /// # Panics
+    ///
+    /// breadth_first panics if the node was removed.
+    pub fn breadth_first(&self, id: NodeId) -> BreadthFirst<'_, N> {
+        self.expect(id, "breadth_first");
+        BreadthFirst::new(self, id)
+    }
+
+    /// ancestors returns an iterator over the parent of the node, its
+    /// parent, and so on up to its root.
+    ///
+    /// # Panics
+    ///
+    /// ancestors panics if the node was removed.
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_, N> {
+        Ancestors::new(self, self.expect(id, "ancestors").parent)
+    }
+}
+
+impl<N> Index<NodeId> for Scene<N> {
+    type Output = N;
+
+    /// index returns a reference to the value of the node.
+    ///
+    /// # Panics
+    ///
+    /// index panics if the node was removed.
+    fn index(&self, id: NodeId) -> &N {
+        self.get(id).expect("Scene: node was removed")
+    }
+}
+
+impl<N> IndexMut<NodeId> for Scene<N> {
+    /// index_mut returns a mutable reference to the value of the node.
+    ///
+    /// # Panics
+    ///
+    /// index_mut panics if the node was removed.
+    fn index_mut(&mut self, id: NodeId) -> &mut N {
+        self.get_mut(id).expect("Scene: node was removed")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Quat, Transform, Vec3};
+    use math::unit::Deg;
+    use super::{NodeId, Scene, SceneError};
+
+    fn at(x: f32, y: f32, z: f32) -> Transform<f32> {
+        Transform::from_translation(Vec3(x, y, z))
+    }
+
+    fn origin(scene: &Scene<&str>, id: NodeId) -> Vec3<f32> {
+        scene.world(id).transform_point(Vec3(0.0, 0.0, 0.0))
+    }
+
+    fn names<'a, I: Iterator<Item = NodeId>>(scene: &Scene<&'a str>, it: I) -> Vec<&'a str> {
+        it.map(|n| scene[n]).collect()
+    }
+
+    #[test]
+    fn hierarchy() {
+        let mut scene = Scene::new();
+        let a = scene.insert("a", at(1.0, 0.0, 0.0));
+        let b = scene.insert_child(a, "b", Transform {
+            translation: Vec3(0.0, 1.0, 0.0),
+            rotation: Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), Deg(90.0)),
+            scale: Vec3(2.0, 2.0, 2.0),
+        });
+        let c = scene.insert_child(b, "c", at(1.0, 0.0, 0.0));
+        let d = scene.insert_child(a, "d", at(0.0, 0.0, 1.0));
+        let e = scene.insert("e", at(0.0, 0.0, 0.0));
+        assert_eq!(scene.len(), 5);
+        assert!(origin(&scene, c).almost_equal(Vec3(1.0, 3.0, 0.0), 1e-6));
+
+        // Traversal.
+        assert_eq!(names(&scene, scene.iter()), ["a", "b", "c", "d", "e"]);
+        assert_eq!(names(&scene, scene.breadth_first(a)), ["a", "b", "d", "c"]);
+        assert_eq!(names(&scene, scene.ancestors(c)), ["b", "a"]);
+        assert_eq!(scene.children(a), &[b, d]);
+        assert_eq!(scene.roots(), &[a, e]);
+
+        // Dirty propagation.
+        scene.update();
+        assert!(!scene.is_dirty(c));
+        scene.set_local(b, at(0.0, 2.0, 0.0));
+        assert!(scene.is_dirty(b) && scene.is_dirty(c) && !scene.is_dirty(a) && !scene.is_dirty(d));
+        assert_eq!(origin(&scene, c), Vec3(2.0, 2.0, 0.0));
+        scene.set_local(a, at(0.0, 0.0, 0.0));
+        scene.update();
+        assert!(scene.iter().all(|n| !scene.is_dirty(n)));
+        assert_eq!(origin(&scene, c), Vec3(1.0, 2.0, 0.0));
+        assert_eq!(origin(&scene, d), Vec3(0.0, 0.0, 1.0));
+
+        // Reparenting, with and without keeping the world transform.
+        assert_eq!(scene.set_parent(a, Some(c), true), Err(SceneError::Cycle));
+        scene.set_local(e, Transform {
+            translation: Vec3(5.0, 0.0, 0.0),
+            rotation: Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), Deg(45.0)),
+            scale: Vec3(0.5, 0.5, 0.5),
+        });
+        let before = scene.world(c);
+        scene.set_parent(b, Some(e), true).unwrap();
+        assert!(scene.world(c).almost_equal(&before, 1e-5));
+        assert_eq!(scene.children(a), &[d]);
+        assert_eq!(scene.parent(b), Some(e));
+        scene.set_parent(d, None, false).unwrap();
+        assert_eq!(origin(&scene, d), Vec3(0.0, 0.0, 1.0));
+        scene.set_parent(d, Some(e), false).unwrap();
+        assert!(origin(&scene, d).almost_equal(Vec3(5.0 + 0.5f32.sqrt() * 0.5, 0.0, 0.5f32.sqrt() * 0.5), 1e-6));
+        scene.update();
+        assert!(scene.world(c).almost_equal(&before, 1e-5));
+        let zero = scene.insert("zero", Transform { scale: Vec3(0.0, 1.0, 1.0), ..Transform::identity() });
+        assert_eq!(scene.set_parent(c, Some(zero), true), Err(SceneError::Singular));
+        assert_eq!(scene.parent(c), Some(b));
+
+        // Removal takes descendants with it.
+        assert_eq!(scene.remove(e), Some("e"));
+        assert_eq!(scene.remove(e), None);
+        assert!(!scene.contains(b) && !scene.contains(c) && !scene.contains(d));
+        assert_eq!(scene.roots(), &[a, zero]);
+        assert_eq!(scene.len(), 2);
+        let f = scene.insert_child(a, "f", at(0.0, 0.0, 0.0));
+        assert_eq!(scene.len(), 3);
+
+        // The new node reuses a slot, but not the identifiers of removed nodes.
+        for &n in &[b, c, d, e] {
+            assert!(n != f && !scene.contains(n) && scene.get(n).is_none());
+        }
+        assert_eq!(names(&scene, scene.iter()), ["a", "f", "zero"]);
+        scene.update();
+        assert_eq!(origin(&scene, f), Vec3(0.0, 0.0, 0.0));
+    }
+}
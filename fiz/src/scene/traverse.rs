@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+use super::{NodeId, Scene};
+
+/// DepthFirst is an iterator over nodes of a `Scene`, each parent before its
+/// children, returned by `Scene::depth_first` and `Scene::iter`.
+#[derive(Clone, Debug)]
+pub struct DepthFirst<'a, N: 'a> {
+    scene: &'a Scene<N>,
+    stack: Vec<NodeId>,
+}
+
+impl<'a, N> DepthFirst<'a, N> {
+    // new returns an iterator starting from the nodes on the stack, which
+    // are visited from the top.
+    pub(super) fn new(scene: &'a Scene<N>, stack: Vec<NodeId>) -> Self {
+        DepthFirst { scene, stack }
+    }
+}
+
+impl<'a, N> Iterator for DepthFirst<'a, N> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        self.stack.extend(self.scene.children(id).iter().rev());
+        Some(id)
+    }
+}
+
+/// BreadthFirst is an iterator over a node of a `Scene` and its
+/// descendants, level by level, returned by `Scene::breadth_first`.
+#[derive(Clone, Debug)]
+pub struct BreadthFirst<'a, N: 'a> {
+    scene: &'a Scene<N>,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'a, N> BreadthFirst<'a, N> {
+    pub(super) fn new(scene: &'a Scene<N>, id: NodeId) -> Self {
+        let mut queue = VecDeque::new();
+        queue.push_back(id);
+        BreadthFirst { scene, queue }
+    }
+}
+
+impl<'a, N> Iterator for BreadthFirst<'a, N> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.queue.pop_front()?;
+        self.queue.extend(self.scene.children(id));
+        Some(id)
+    }
+}
+
+/// Ancestors is an iterator over the ancestors of a node of a `Scene`,
+/// nearest first, returned by `Scene::ancestors`.
+#[derive(Clone, Debug)]
+pub struct Ancestors<'a, N: 'a> {
+    scene: &'a Scene<N>,
+    next: Option<NodeId>,
+}
+
+impl<'a, N> Ancestors<'a, N> {
+    pub(super) fn new(scene: &'a Scene<N>, next: Option<NodeId>) -> Self {
+        Ancestors { scene, next }
+    }
+}
+
+impl<'a, N> Iterator for Ancestors<'a, N> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.scene.parent(id);
+        Some(id)
+    }
+}
@@ -0,0 +1,102 @@
+use math::{Vec2, Vec3, Vec4};
+use super::super::image::{Image, PixelFormat};
+use super::{alpha, hammersley, sample_ggx, smith_ggx_visibility};
+
+/// env_brdf returns the scale and bias of the specular reflectance at normal
+/// incidence which give the directional albedo of GGX specular reflection:
+/// the fraction of uniform incoming light reflected towards a view direction
+/// at the given cosine to the normal. This is the second sum of the
+/// split-sum approximation of image based lighting, integrated with the given
+/// number of importance samples.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::brdf::env_brdf;
+///
+/// // Smooth surfaces reflect nearly all light at grazing angles.
+/// let head_on = env_brdf(1.0, 0.0, 64);
+/// let grazing = env_brdf(0.05, 0.0, 64);
+/// assert!(head_on.0 > 0.99 && head_on.1 < 0.01);
+/// assert!(grazing.0 + grazing.1 > 0.95 && grazing.1 > 0.5);
+/// ```
+pub fn env_brdf(n_dot_v: f32, roughness: f32, samples: u32) -> Vec2<f32> {
+    let n_dot_v = n_dot_v.clamp(1e-4, 1.0);
+    let v = Vec3((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+    let alpha = alpha(roughness);
+    let (mut scale, mut bias) = (0.0, 0.0);
+    for i in 0..samples {
+        let h = sample_ggx(hammersley(i, samples), alpha);
+        let v_dot_h = v.dot(h);
+        let l = h.mul_scalar(2.0 * v_dot_h) - v;
+        if l.2 <= 0.0 || v_dot_h <= 0.0 {
+            continue;
+        }
+        // The BRDF times the cosine over the density of the sample, without
+        // the Fresnel term.
+        let weight = smith_ggx_visibility(n_dot_v, l.2, alpha) * 4.0 * l.2 * v_dot_h / h.2;
+        let fc = (1.0 - v_dot_h).powi(5);
+        scale += (1.0 - fc) * weight;
+        bias += fc * weight;
+    }
+    Vec2(scale / samples as f32, bias / samples as f32)
+}
+
+/// env_brdf_lut returns a square table of `env_brdf`, with `n_dot_v`
+/// increasing along x and roughness increasing along y, from the top. Each
+/// texel holds the scale and bias in its red and green channels, sampled at
+/// its center.
+pub fn env_brdf_lut(size: u32, samples: u32) -> Image {
+    let mut lut = Image::new(size, size, PixelFormat::Rgba32F);
+    for y in 0..size {
+        for x in 0..size {
+            let center = |i: u32| (i as f32 + 0.5) / size as f32;
+            let e = env_brdf(center(x), center(y), samples);
+            lut.set_pixel(x, y, Vec4(e.0, e.1, 0.0, 1.0));
+        }
+    }
+    lut
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+    use math::Vec3;
+    use super::super::{alpha, ggx_ndf, smith_ggx_visibility};
+    use super::{env_brdf, env_brdf_lut};
+
+    #[test]
+    fn lut() {
+        let lut = env_brdf_lut(16, 256);
+        assert_eq!((lut.width(), lut.height()), (16, 16));
+        for y in 0..16 {
+            for x in 0..16 {
+                let e = lut.pixel(x, y);
+                assert!(e.0 >= 0.0 && e.1 >= 0.0 && e.0 + e.1 <= 1.0 + 1e-3, "{} {}: {:?}", x, y, e);
+            }
+        }
+        let e = env_brdf((5.0 + 0.5) / 16.0, (9.0 + 0.5) / 16.0, 256);
+        assert_eq!((lut.pixel(5, 9).0, lut.pixel(5, 9).1), (e.0, e.1));
+
+        // The importance sampled albedo matches quadrature of the BRDF, for a
+        // perfect reflector.
+        for &(n_dot_v, roughness) in &[(0.2, 0.4), (0.7, 0.7), (1.0, 1.0)] {
+            let v = Vec3((1.0f32 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+            let a = alpha(roughness);
+            let (nt, np) = (256, 256);
+            let (dt, dp) = (0.5 * PI / nt as f32, 2.0 * PI / np as f32);
+            let mut albedo = 0.0;
+            for i in 0..nt {
+                let theta = (i as f32 + 0.5) * dt;
+                for j in 0..np {
+                    let phi = (j as f32 + 0.5) * dp;
+                    let l = Vec3(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+                    let h = (v + l).normalize().unwrap();
+                    albedo += ggx_ndf(h.2, a) * smith_ggx_visibility(n_dot_v, l.2, a) * l.2 * theta.sin() * dt * dp;
+                }
+            }
+            let e = env_brdf(n_dot_v, roughness, 4096);
+            assert!((e.0 + e.1 - albedo).abs() < 0.01, "{} {}: {:?} {}", n_dot_v, roughness, e, albedo);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Physically based shading models.
+//!
+//! The terms of the Cook-Torrance microfacet model with the GGX
+//! (Trowbridge-Reitz) distribution, and diffuse models, as free functions of
+//! the cosines between the normal, view, light and half vectors, for use by
+//! renderers and baking tools alike. `Material` combines them into a
+//! metallic-roughness BRDF.
+//!
+//! Roughness is perceptual roughness, in [0, 1]; the GGX `alpha` is its
+//! square (see `alpha`).
+//!
+//! The `sample` functions importance sample the models, and `env_brdf_lut`
+//! generates the split-sum lookup table used for image based lighting.
+
+mod lut;
+mod sample;
+
+use std::f32::consts::PI;
+use math::Vec3;
+
+pub use self::lut::{env_brdf, env_brdf_lut};
+pub use self::sample::{ggx_pdf, hammersley, sample_cosine, sample_ggx, to_world};
+
+// The least GGX alpha, below which highlights are too small to be sampled
+// or represented in floats.
+const MIN_ALPHA: f32 = 1e-3;
+
+/// alpha returns the GGX alpha of a perceptual roughness.
+pub fn alpha(roughness: f32) -> f32 {
+    (roughness * roughness).clamp(MIN_ALPHA, 1.0)
+}
+
+/// lambert returns the Lambertian diffuse BRDF, which reflects light equally
+/// in all directions.
+pub fn lambert(albedo: Vec3<f32>) -> Vec3<f32> {
+    albedo.mul_scalar(1.0 / PI)
+}
+
+/// burley returns the Disney (Burley) diffuse BRDF, which darkens smooth
+/// surfaces and brightens rough ones at grazing angles.
+pub fn burley(albedo: Vec3<f32>, roughness: f32, n_dot_v: f32, n_dot_l: f32, l_dot_h: f32) -> Vec3<f32> {
+    let fd90 = 0.5 + 2.0 * roughness * l_dot_h * l_dot_h;
+    let scatter = |cos: f32| 1.0 + (fd90 - 1.0) * (1.0 - cos).powi(5);
+    albedo.mul_scalar(scatter(n_dot_l) * scatter(n_dot_v) / PI)
+}
+
+/// ggx_ndf returns the GGX (Trowbridge-Reitz) normal distribution function:
+/// the density of microfacets facing the half vector.
+pub fn ggx_ndf(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// smith_ggx_visibility returns the height-correlated Smith masking and
+/// shadowing of GGX microfacets, divided by `4 * n_dot_v * n_dot_l`.
+pub fn smith_ggx_visibility(n_dot_v: f32, n_dot_l: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let v = n_dot_l * (n_dot_v * n_dot_v * (1.0 - a2) + a2).sqrt();
+    let l = n_dot_v * (n_dot_l * n_dot_l * (1.0 - a2) + a2).sqrt();
+    0.5 / (v + l)
+}
+
+/// fresnel_schlick returns Schlick's approximation of the Fresnel
+/// reflectance, given the reflectance at normal incidence.
+pub fn fresnel_schlick(f0: Vec3<f32>, v_dot_h: f32) -> Vec3<f32> {
+    let f = (1.0 - v_dot_h).clamp(0.0, 1.0).powi(5);
+    f0 + (Vec3(1.0, 1.0, 1.0) - f0).mul_scalar(f)
+}
+
+/// Diffuse is a diffuse model.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Diffuse {
+    /// The Lambertian model, see `lambert`.
+    Lambert,
+
+    /// The Disney (Burley) model, see `burley`.
+    Burley,
+}
+
+/// Material is a metallic-roughness surface: GGX specular reflection over a
+/// diffuse base, which metals lack.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    /// The diffuse color of dielectrics, or the specular color of metals.
+    pub base_color: Vec3<f32>,
+
+    /// How metallic the surface is, from zero for dielectrics to one.
+    pub metallic: f32,
+
+    /// The perceptual roughness, from zero for mirrors to one.
+    pub roughness: f32,
+
+    /// The specular reflectance of dielectrics, where 0.5 is the 4% of most
+    /// materials.
+    pub reflectance: f32,
+
+    /// The diffuse model.
+    pub diffuse: Diffuse,
+}
+
+impl Default for Material {
+    /// default returns a white, half-rough dielectric.
+    fn default() -> Self {
+        Material {
+            base_color: Vec3(1.0, 1.0, 1.0),
+            metallic: 0.0,
+            roughness: 0.5,
+            reflectance: 0.5,
+            diffuse: Diffuse::Lambert,
+        }
+    }
+}
+
+impl Material {
+    /// f0 returns the specular reflectance at normal incidence.
+    pub fn f0(&self) -> Vec3<f32> {
+        let dielectric = 0.16 * self.reflectance * self.reflectance * (1.0 - self.metallic);
+        Vec3(dielectric, dielectric, dielectric) + self.base_color.mul_scalar(self.metallic)
+    }
+
+    /// eval returns the BRDF for light arriving from direction l and leaving
+    /// towards v, about the normal n, which are all normalized. The reflected
+    /// radiance is the incoming radiance times the BRDF times `n.dot(l)`.
+    /// The BRDF is zero for light or view directions below the surface.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::brdf::Material;
+    ///
+    /// let m = Material::default();
+    /// let n = Vec3(0.0, 0.0, 1.0);
+    /// let v = Vec3(0.6, 0.0, 0.8);
+    /// let mirror = Vec3(-0.6, 0.0, 0.8);
+    /// assert!(m.eval(n, v, mirror).0 > m.eval(n, v, v).0);
+    /// assert_eq!(m.eval(n, v, Vec3(0.0, 0.6, -0.8)), Vec3(0.0, 0.0, 0.0));
+    /// ```
+    pub fn eval(&self, n: Vec3<f32>, v: Vec3<f32>, l: Vec3<f32>) -> Vec3<f32> {
+        let (n_dot_v, n_dot_l) = (n.dot(v), n.dot(l));
+        let h = match (v + l).normalize() {
+            Some(h) if n_dot_v > 0.0 && n_dot_l > 0.0 => h,
+            _ => return Vec3(0.0, 0.0, 0.0),
+        };
+        let (n_dot_h, l_dot_h) = (n.dot(h).clamp(0.0, 1.0), l.dot(h).clamp(0.0, 1.0));
+        let alpha = alpha(self.roughness);
+        let f0 = self.f0();
+        let f = fresnel_schlick(f0, l_dot_h);
+        let specular = f.mul_scalar(ggx_ndf(n_dot_h, alpha) * smith_ggx_visibility(n_dot_v, n_dot_l, alpha));
+        // Light reflected at the surface, on the way in or out, isn't
+        // diffused.
+        let one = Vec3(1.0, 1.0, 1.0);
+        let transmitted = (one - fresnel_schlick(f0, n_dot_l)) * (one - fresnel_schlick(f0, n_dot_v));
+        let albedo = self.base_color.mul_scalar(1.0 - self.metallic) * transmitted;
+        let diffuse = match self.diffuse {
+            Diffuse::Lambert => lambert(albedo),
+            Diffuse::Burley => burley(albedo, self.roughness, n_dot_v, n_dot_l, l_dot_h),
+        };
+        specular + diffuse
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+    use math::Vec3;
+    use super::*;
+
+    // albedo returns the fraction of light from all directions reflected
+    // towards the view direction v by the BRDF, about the normal +Z, by
+    // midpoint quadrature.
+    fn albedo<F: Fn(Vec3<f32>) -> f32>(brdf: F) -> f32 {
+        let (nt, np) = (256, 128);
+        let (dt, dp) = (0.5 * PI / nt as f32, 2.0 * PI / np as f32);
+        let mut sum = 0.0;
+        for i in 0..nt {
+            let theta = (i as f32 + 0.5) * dt;
+            // Only half of the azimuths are evaluated, as the BRDFs are
+            // symmetric about the plane of incidence.
+            for j in 0..np / 2 {
+                let phi = (j as f32 + 0.5) * dp;
+                let l = Vec3(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos());
+                sum += 2.0 * brdf(l) * theta.cos() * theta.sin() * dt * dp;
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn ndf_normalized() {
+        // The projected area of the microfacets is that of the surface.
+        for &alpha in &[0.02, 0.1, 0.5, 1.0] {
+            let n = 100000;
+            let dt = 0.5 * PI / n as f32;
+            let area: f32 = (0..n)
+                .map(|i| {
+                    let theta = (i as f32 + 0.5) * dt;
+                    2.0 * PI * ggx_ndf(theta.cos(), alpha) * theta.cos() * theta.sin() * dt
+                })
+                .sum();
+            assert!((area - 1.0).abs() < 1e-3, "alpha {}: {}", alpha, area);
+        }
+    }
+
+    #[test]
+    fn energy_conservation() {
+        let n = Vec3(0.0, 0.0, 1.0);
+        let white = Vec3(1.0, 1.0, 1.0);
+        assert!((albedo(|_| lambert(white).0) - 1.0).abs() < 1e-3);
+        for &n_dot_v in &[0.1f32, 0.5, 1.0] {
+            let v = Vec3((1.0 - n_dot_v * n_dot_v).sqrt(), 0.0, n_dot_v);
+            for &roughness in &[0.3f32, 0.6, 1.0] {
+                // Microfacets reflecting all light lose some to masking, but
+                // never gain any.
+                let alpha = alpha(roughness);
+                let specular = albedo(|l| {
+                    let h = (v + l).normalize().unwrap();
+                    ggx_ndf(n.dot(h), alpha) * smith_ggx_visibility(n_dot_v, l.2, alpha)
+                });
+                assert!(specular <= 1.0 && specular > 0.25, "{} {}: {}", n_dot_v, roughness, specular);
+
+                // Burley diffuse isn't energy conserving, as it's fit to
+                // measurements: smooth surfaces lose light, and rough ones
+                // gain some at grazing angles.
+                let burley = albedo(|l| {
+                    let h = (v + l).normalize().unwrap();
+                    super::burley(white, roughness, n_dot_v, l.2, l.dot(h)).0
+                });
+                assert!(burley > 0.7 && burley < 1.5, "{} {}: {}", n_dot_v, roughness, burley);
+
+                for &metallic in &[0.0, 0.5, 1.0] {
+                    for &diffuse in &[Diffuse::Lambert, Diffuse::Burley] {
+                        let m = Material {
+                            base_color: white,
+                            metallic,
+                            roughness,
+                            reflectance: 1.0,
+                            diffuse,
+                        };
+                        let a = albedo(|l| m.eval(n, v, l).0);
+                        let limit = if diffuse == Diffuse::Lambert { 1.0 } else { burley.max(1.0) };
+                        assert!(a <= limit + 1e-3, "{:?}: {}", m, a);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fresnel() {
+        let f0 = Vec3(0.04, 0.5, 1.0);
+        assert_eq!(fresnel_schlick(f0, 1.0), f0);
+        assert_eq!(fresnel_schlick(f0, 0.0), Vec3(1.0, 1.0, 1.0));
+        let m = Material {
+            base_color: Vec3(1.0, 0.5, 0.25),
+            metallic: 1.0,
+            ..Material::default()
+        };
+        assert_eq!(m.f0(), m.base_color);
+        assert!(Material::default().f0().almost_equal(Vec3(0.04, 0.04, 0.04), 1e-6));
+    }
+}
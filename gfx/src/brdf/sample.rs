@@ -0,0 +1,105 @@
+use std::f32::consts::PI;
+use math::{Vec2, Vec3};
+use super::ggx_ndf;
+
+/// hammersley returns the i-th of n points of the Hammersley set, which
+/// cover the unit square evenly, for quasi-Monte Carlo integration.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec2;
+/// use fiz_gfx::brdf::hammersley;
+///
+/// assert_eq!(hammersley(0, 4), Vec2(0.0, 0.0));
+/// assert_eq!(hammersley(1, 4), Vec2(0.25, 0.5));
+/// assert_eq!(hammersley(3, 4), Vec2(0.75, 0.75));
+/// ```
+pub fn hammersley(i: u32, n: u32) -> Vec2<f32> {
+    Vec2(i as f32 / n as f32, (i.reverse_bits() as f64 / 4294967296.0) as f32)
+}
+
+/// sample_cosine maps a point of the unit square to a direction on the
+/// hemisphere about +Z, distributed by the cosine of its angle to +Z, as
+/// light is by a Lambertian surface. The density of the direction is
+/// `z / PI`.
+pub fn sample_cosine(u: Vec2<f32>) -> Vec3<f32> {
+    let (r, phi) = (u.0.sqrt(), 2.0 * PI * u.1);
+    Vec3(r * phi.cos(), r * phi.sin(), (1.0 - u.0).max(0.0).sqrt())
+}
+
+/// sample_ggx maps a point of the unit square to a half vector on the
+/// hemisphere about +Z, distributed by the GGX normal distribution times the
+/// cosine of its angle to +Z. Reflecting the view direction about it gives
+/// a light direction with the density `ggx_pdf`.
+pub fn sample_ggx(u: Vec2<f32>, alpha: f32) -> Vec3<f32> {
+    let cos_theta = ((1.0 - u.0) / (1.0 + (alpha * alpha - 1.0) * u.0)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// ggx_pdf returns the density of the light direction given by reflecting the
+/// view direction about a half vector from `sample_ggx`.
+pub fn ggx_pdf(n_dot_h: f32, v_dot_h: f32, alpha: f32) -> f32 {
+    ggx_ndf(n_dot_h, alpha) * n_dot_h / (4.0 * v_dot_h)
+}
+
+/// to_world returns a direction in the tangent space about +Z, such as one
+/// from `sample_cosine`, in the tangent space about the normalized vector
+/// n instead.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::brdf::to_world;
+///
+/// let n = Vec3(0.0, -1.0, 0.0);
+/// assert_eq!(to_world(Vec3(0.0, 0.0, 1.0), n), n);
+/// assert_eq!(to_world(Vec3(1.0, 0.0, 0.0), n).dot(n), 0.0);
+/// ```
+pub fn to_world(v: Vec3<f32>, n: Vec3<f32>) -> Vec3<f32> {
+    // Duff et al., "Building an Orthonormal Basis, Revisited" (2017).
+    let sign = 1.0f32.copysign(n.2);
+    let a = -1.0 / (sign + n.2);
+    let b = n.0 * n.1 * a;
+    let t = Vec3(1.0 + sign * n.0 * n.0 * a, sign * b, -sign * n.0);
+    let bt = Vec3(b, sign + n.1 * n.1 * a, -n.1);
+    t.mul_scalar(v.0) + bt.mul_scalar(v.1) + n.mul_scalar(v.2)
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+    use math::Vec3;
+    use super::*;
+
+    #[test]
+    fn distributions() {
+        let n = 4096;
+        // The mean cosine of a cosine distribution is 2/3.
+        let mean: f32 = (0..n).map(|i| sample_cosine(hammersley(i, n)).2).sum::<f32>() / n as f32;
+        assert!((mean - 2.0 / 3.0).abs() < 1e-3, "{}", mean);
+
+        // The mean cosine of GGX half vectors matches the distribution.
+        let alpha = 0.3;
+        let mean: f32 = (0..n).map(|i| sample_ggx(hammersley(i, n), alpha).2).sum::<f32>() / n as f32;
+        let steps = 100000;
+        let dt = 0.5 * PI / steps as f32;
+        let expected: f32 = (0..steps)
+            .map(|i| {
+                let theta = (i as f32 + 0.5) * dt;
+                2.0 * PI * ggx_ndf(theta.cos(), alpha) * theta.cos() * theta.cos() * theta.sin() * dt
+            })
+            .sum();
+        assert!((mean - expected).abs() < 1e-3, "{} {}", mean, expected);
+
+        for &n in &[Vec3(0.0, 0.0, -1.0), Vec3(0.6, 0.0, 0.8), Vec3(0.48, -0.6, -0.64)] {
+            let basis = |v| to_world(v, n);
+            let (x, y, z) = (basis(Vec3(1.0, 0.0, 0.0)), basis(Vec3(0.0, 1.0, 0.0)), basis(Vec3(0.0, 0.0, 1.0)));
+            assert!(x.dot(y).abs() < 1e-6 && x.dot(n).abs() < 1e-6 && y.dot(n).abs() < 1e-6);
+            assert!((x.length() - 1.0).abs() < 1e-6 && (y.length() - 1.0).abs() < 1e-6 && z == n);
+        }
+    }
+}
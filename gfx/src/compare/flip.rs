@@ -0,0 +1,167 @@
+//! A FLIP-style perceptual error, after Andersson et al., "FLIP: A Difference
+//! Evaluator for Alternating Images" (2020).
+//!
+//! Both images are filtered by models of the contrast sensitivity of the eye
+//! in an opponent color space, then compared by color difference. The color
+//! error is amplified where edges and points differ, which the eye is drawn
+//! to.
+
+use std::f32::consts::{PI, SQRT_2};
+use math::Vec4;
+use super::super::image::srgb_to_linear;
+use super::{convolve, gaussian};
+
+// The pixels per degree of visual angle, of a 0.7m wide 4K monitor viewed
+// from 0.7m.
+const PPD: f32 = 67.0;
+
+// The exponents of the color and feature errors, and the color error below
+// which errors are compressed into [0, PT).
+const QC: f32 = 0.7;
+const QF: f32 = 0.5;
+const PC: f32 = 0.4;
+const PT: f32 = 0.95;
+
+// The D65 reference white.
+const WHITE: [f32; 3] = [0.9504285, 1.0, 1.0889004];
+
+// The contrast sensitivity of the achromatic, red-green and blue-yellow
+// channels, as sums of two Gaussians (a1, b1, a2, b2).
+const CSF: [[f32; 4]; 3] = [[1.0, 0.0047, 0.0, 1e-5], [1.0, 0.0053, 0.0, 1e-5], [34.1, 0.04, 13.5, 0.025]];
+
+// flip returns the perceptual error of each pixel of two sRGB encoded images.
+pub fn flip(reference: &[Vec4<f32>], test: &[Vec4<f32>], width: usize, height: usize) -> Vec<f32> {
+    let (a, b) = (opponent(reference), opponent(test));
+    let (fa, fb) = (csf_filter(&a, width, height), csf_filter(&b, width, height));
+    let cmax = hyab(hunt(lab([0.0, 1.0, 0.0])), hunt(lab([0.0, 0.0, 1.0]))).powf(QC);
+    let (ea, eb) = (features(&a[0], width, height), features(&b[0], width, height));
+    (0..reference.len())
+        .map(|i| {
+            let ca = hunt(lab(to_rgb([fa[0][i], fa[1][i], fa[2][i]])));
+            let cb = hunt(lab(to_rgb([fb[0][i], fb[1][i], fb[2][i]])));
+            let e = hyab(ca, cb).powf(QC);
+            let color = if e < PC * cmax {
+                PT / (PC * cmax) * e
+            } else {
+                PT + (e - PC * cmax) / (cmax - PC * cmax) * (1.0 - PT)
+            };
+            let edge = (ea.0[i] - eb.0[i]).abs();
+            let point = (ea.1[i] - eb.1[i]).abs();
+            let feature = (edge.max(point) / SQRT_2).powf(QF);
+            color.min(1.0).powf(1.0 - feature.min(1.0))
+        })
+        .collect()
+}
+
+// opponent returns the channels of the YyCxCz color space (linearized
+// CIELAB) of the pixels.
+fn opponent(pixels: &[Vec4<f32>]) -> [Vec<f32>; 3] {
+    let mut channels = [Vec::new(), Vec::new(), Vec::new()];
+    let linear = |c: f32| srgb_to_linear(c.clamp(0.0, 1.0));
+    for p in pixels {
+        let [x, y, z] = xyz([linear(p.0), linear(p.1), linear(p.2)]);
+        channels[0].push(116.0 * y - 16.0);
+        channels[1].push(500.0 * (x - y));
+        channels[2].push(200.0 * (y - z));
+    }
+    channels
+}
+
+// xyz returns linear RGB as XYZ relative to the reference white.
+fn xyz(c: [f32; 3]) -> [f32; 3] {
+    [(0.4124564 * c[0] + 0.3575761 * c[1] + 0.1804375 * c[2]) / WHITE[0],
+     (0.2126729 * c[0] + 0.7151522 * c[1] + 0.0721750 * c[2]) / WHITE[1],
+     (0.0193339 * c[0] + 0.119192 * c[1] + 0.9503041 * c[2]) / WHITE[2]]
+}
+
+// to_rgb returns YyCxCz as linear RGB, clamped to [0, 1].
+fn to_rgb(c: [f32; 3]) -> [f32; 3] {
+    let y = (c[0] + 16.0) / 116.0;
+    let (x, z) = ((c[1] / 500.0 + y) * WHITE[0], (y - c[2] / 200.0) * WHITE[2]);
+    let y = y * WHITE[1];
+    [(3.2404542 * x - 1.5371385 * y - 0.4985314 * z).clamp(0.0, 1.0),
+     (-0.969266 * x + 1.8760108 * y + 0.0415560 * z).clamp(0.0, 1.0),
+     (0.0556434 * x - 0.2040259 * y + 1.0572252 * z).clamp(0.0, 1.0)]
+}
+
+// lab returns linear RGB as CIELAB.
+fn lab(c: [f32; 3]) -> [f32; 3] {
+    const DELTA: f32 = 6.0 / 29.0;
+    let f = |t: f32| if t > DELTA * DELTA * DELTA { t.cbrt() } else { t / (3.0 * DELTA * DELTA) + 4.0 / 29.0 };
+    let [x, y, z] = xyz(c);
+    [116.0 * f(y) - 16.0, 500.0 * (f(x) - f(y)), 200.0 * (f(y) - f(z))]
+}
+
+// hunt scales the chromatic channels of a CIELAB color by its lightness,
+// after the Hunt effect: colors are less colorful when dark.
+fn hunt(c: [f32; 3]) -> [f32; 3] {
+    [c[0], 0.01 * c[0] * c[1], 0.01 * c[0] * c[2]]
+}
+
+// hyab returns the HyAB distance of two colors, which suits large
+// differences better than Euclidean distance.
+fn hyab(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).abs() + ((a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+// csf_filter filters each channel by its contrast sensitivity.
+fn csf_filter(channels: &[Vec<f32>; 3], width: usize, height: usize) -> [Vec<f32>; 3] {
+    let b_max = CSF.iter().map(|c| c[1].max(c[3])).fold(0.0, f32::max);
+    let radius = (3.0 * (b_max / (2.0 * PI * PI)).sqrt() * PPD).ceil() as usize;
+    let filter = |c: &Vec<f32>, csf: &[f32; 4]| {
+        // Each Gaussian is separable, so the channel is filtered by each,
+        // then they're mixed by their weight in the sum.
+        let mut out = vec![0.0; c.len()];
+        let mut total = 0.0;
+        for &(a, b) in &[(csf[0], csf[1]), (csf[2], csf[3])] {
+            if a == 0.0 {
+                continue;
+            }
+            let k: Vec<f32> = (0..2 * radius + 1)
+                .map(|i| {
+                    let d = (i as f32 - radius as f32) / PPD;
+                    (-PI * PI * d * d / b).exp()
+                })
+                .collect();
+            let sum: f32 = k.iter().sum();
+            let weight = a * (PI / b).sqrt() * sum * sum;
+            let k: Vec<f32> = k.iter().map(|v| v / sum).collect();
+            for (o, v) in out.iter_mut().zip(convolve(c, width, height, &k, &k)) {
+                *o += weight * v;
+            }
+            total += weight;
+        }
+        out.into_iter().map(|v| v / total).collect()
+    };
+    [filter(&channels[0], &CSF[0]), filter(&channels[1], &CSF[1]), filter(&channels[2], &CSF[2])]
+}
+
+// features returns the edge and point strength of each pixel of the YyCxCz
+// achromatic channel, as the magnitudes of the first and second derivatives
+// of a Gaussian.
+fn features(yy: &[f32], width: usize, height: usize) -> (Vec<f32>, Vec<f32>) {
+    let sigma = 0.5 * 0.082 * PPD;
+    let radius = (3.0 * sigma).ceil() as usize;
+    let y: Vec<f32> = yy.iter().map(|&v| (v + 16.0) / 116.0).collect();
+    let smooth = gaussian(sigma, radius);
+    // The derivative kernels are normalized so that their positive and
+    // negative weights each sum to one.
+    let normalize = |k: Vec<f32>| {
+        let pos: f32 = k.iter().filter(|&&v| v > 0.0).sum();
+        let neg: f32 = -k.iter().filter(|&&v| v < 0.0).sum::<f32>();
+        k.into_iter().map(|v| if v > 0.0 { v / pos } else { v / neg }).collect::<Vec<_>>()
+    };
+    let first = normalize(smooth.iter().enumerate().map(|(i, g)| -(i as f32 - radius as f32) * g).collect());
+    let second = normalize(smooth.iter()
+        .enumerate()
+        .map(|(i, g)| {
+            let x = i as f32 - radius as f32;
+            (x * x / (sigma * sigma) - 1.0) * g
+        })
+        .collect());
+    let magnitude = |k: &[f32]| {
+        let (dx, dy) = (convolve(&y, width, height, k, &smooth), convolve(&y, width, height, &smooth, k));
+        dx.iter().zip(&dy).map(|(x, y)| (x * x + y * y).sqrt()).collect::<Vec<_>>()
+    };
+    (magnitude(&first), magnitude(&second))
+}
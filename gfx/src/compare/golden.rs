@@ -0,0 +1,205 @@
+//! Golden image tests: rendering headlessly and asserting the result matches
+//! a stored reference image.
+//!
+//! References are PNG files, usually kept under `testdata`. They're written
+//! from the rendered images, new or not, only when the `FIZ_UPDATE_GOLDEN`
+//! environment variable is set, so after adding a test or an intended change
+//! to rendering they're updated by running the tests with it, then reviewing
+//! the new images. Otherwise a missing reference fails the test.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use super::super::device::{Device, DeviceError, SoftDevice, Texture, TextureDesc, TextureFormat};
+use super::super::image::{self, Image, PixelFormat};
+use super::{compare, Thresholds};
+
+/// UPDATE_VAR is the environment variable which, when set, makes
+/// `assert_golden` write references rather than compare with them.
+pub const UPDATE_VAR: &str = "FIZ_UPDATE_GOLDEN";
+
+/// render renders headlessly through a `SoftDevice`, into an sRGB color
+/// target of the given size and number of samples, and returns the resolved
+/// target as an Rgba8 image. draw records and submits the commands drawing
+/// into the target.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec4;
+/// use fiz_gfx::device::{CommandBuffer, Device, RenderPass};
+/// use fiz_gfx::compare::golden;
+///
+/// let image = golden::render(4, 4, 1, |device, target| {
+///         let mut commands = CommandBuffer::new();
+///         commands.begin_pass(RenderPass {
+///             color: target,
+///             depth: None,
+///             clear_color: Some(Vec4(1.0, 0.5, 0.0, 1.0)),
+///             clear_depth: None,
+///         });
+///         commands.end_pass();
+///         device.submit(&commands)
+///     })
+///     .unwrap();
+/// assert_eq!(image.row(0)[..4], [255, 188, 0, 255]);
+/// ```
+pub fn render<F>(width: u32, height: u32, samples: u32, draw: F) -> Result<Image, DeviceError>
+    where F: FnOnce(&mut SoftDevice, Texture) -> Result<(), DeviceError>
+{
+    let mut device = SoftDevice::new();
+    let target = device.create_texture(&TextureDesc {
+            width,
+            height,
+            format: TextureFormat::Rgba8Srgb,
+            samples,
+        })?;
+    draw(&mut device, target)?;
+    let data = device.read_texture(target)?;
+    Ok(Image::from_data(width, height, PixelFormat::Rgba8, width as usize * 4, data).unwrap())
+}
+
+/// assert_golden asserts that the image matches the reference PNG file at
+/// the given path within the thresholds, after converting it to Rgba8 as
+/// the reference is stored.
+///
+/// The reference is written instead if `UPDATE_VAR` is set, in which case
+/// its path is returned so the caller can report it. When the image doesn't
+/// match, it's saved beside the reference with
+/// the extension `actual.png`, along with a heatmap of the differences with
+/// the extension `diff.png`.
+///
+/// # Panics
+///
+/// assert_golden panics if the image doesn't match, the reference is missing
+/// and `UPDATE_VAR` isn't set, or the files can't be read or written.
+pub fn assert_golden<P: AsRef<Path>>(path: P, image: &Image, thresholds: &Thresholds) -> Option<PathBuf> {
+    check(path.as_ref(), image, thresholds, env::var_os(UPDATE_VAR).is_some())
+}
+
+// check is assert_golden, writing the reference if update is true.
+fn check(path: &Path, image: &Image, thresholds: &Thresholds, update: bool) -> Option<PathBuf> {
+    let image = image.convert(PixelFormat::Rgba8);
+    if !update && !path.exists() {
+        panic!("golden image {} is missing; run the tests with {} set to write it", path.display(), UPDATE_VAR);
+    }
+    if update {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("creating {}: {}", dir.display(), e));
+        }
+        image::save(path, &image).unwrap_or_else(|e| panic!("{}", e));
+        return Some(path.to_path_buf());
+    }
+    let reference = image::load(path).unwrap_or_else(|e| panic!("{}", e));
+    let result = compare(&reference, &image).map_err(|e| e.to_string()).and_then(|c| {
+        let failures = c.failures(thresholds);
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let diff = path.with_extension("diff.png");
+        image::save(&diff, &c.heatmap()).unwrap_or_else(|e| panic!("{}", e));
+        Err(format!("{} ({}); heatmap written to {}", failures.join(", "), c, diff.display()))
+    });
+    if let Err(message) = result {
+        let actual = path.with_extension("actual.png");
+        image::save(&actual, &image).unwrap_or_else(|e| panic!("{}", e));
+        panic!("{} doesn't match {}: {}", actual.display(), path.display(), message);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+    use std::rc::Rc;
+    use math::Vec4;
+    use super::super::super::device::*;
+    use super::super::super::raster::Cull;
+    use super::super::Thresholds;
+    use super::{assert_golden, check, render};
+
+    // Colored interpolates vertex colors, given positions in clip space.
+    struct Colored;
+
+    impl CpuShader for Colored {
+        fn vertex(&self, a: &[Vec4<f32>], _: &ShaderContext) -> (Vec4<f32>, Vec<f32>) {
+            (a[0], vec![a[1].0, a[1].1, a[1].2])
+        }
+
+        fn fragment(&self, v: &[f32], _: &ShaderContext) -> Option<Vec4<f32>> {
+            Some(Vec4(v[0], v[1], v[2], 1.0))
+        }
+    }
+
+    // triangle draws a triangle of red, green and blue corners.
+    fn triangle(device: &mut SoftDevice, target: Texture) -> Result<(), DeviceError> {
+        let vertices: [f32; 15] = [0.0, 0.8, 1.0, 0.0, 0.0, -0.8, -0.7, 0.0, 1.0, 0.0, 0.9, -0.5, 0.0, 0.0, 1.0];
+        let bytes: Vec<u8> = vertices.iter().flat_map(|f| f.to_le_bytes()).collect();
+        let buffer = device.create_buffer(&BufferDesc {
+                usage: BufferUsage::Vertex,
+                size: bytes.len(),
+            })?;
+        device.write_buffer(buffer, 0, &bytes)?;
+        let pipeline = device.create_pipeline(&PipelineDesc {
+                vertex_stride: 20,
+                attributes: vec![VertexAttribute {
+                                     format: VertexFormat::Float2,
+                                     offset: 0,
+                                 },
+                                 VertexAttribute {
+                                     format: VertexFormat::Float3,
+                                     offset: 8,
+                                 }],
+                source: None,
+                cpu_shader: Some(Rc::new(Colored)),
+                cull: Cull::None,
+                depth_test: false,
+                depth_write: false,
+            })?;
+        let mut commands = CommandBuffer::new();
+        commands.begin_pass(RenderPass {
+            color: target,
+            depth: None,
+            clear_color: Some(Vec4(0.1, 0.1, 0.1, 1.0)),
+            clear_depth: None,
+        });
+        commands.set_pipeline(pipeline);
+        commands.set_vertex_buffer(buffer);
+        commands.draw(0..3);
+        commands.end_pass();
+        device.submit(&commands)
+    }
+
+    #[test]
+    fn triangle_matches() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/golden/triangle.png");
+        let image = render(32, 32, 4, triangle).unwrap();
+        assert_golden(path, &image, &Thresholds::exact());
+
+        // Rendering without multisampling changes the edges, which is seen
+        // with exact thresholds, but not the default ones.
+        let aliased = render(32, 32, 1, triangle).unwrap();
+        let reference = super::super::super::image::load(path).unwrap();
+        let c = super::super::compare(&reference, &aliased).unwrap();
+        assert!(!c.passes(&Thresholds::exact()));
+        assert!(c.passes(&Thresholds { min_psnr: 20.0, ..Thresholds::default() }), "{}", c);
+    }
+
+    #[test]
+    #[should_panic(expected = "run the tests with FIZ_UPDATE_GOLDEN set")]
+    fn missing_reference() {
+        let path = env::temp_dir().join(format!("fiz-golden-missing-{}.png", process::id()));
+        let image = render(8, 8, 1, triangle).unwrap();
+        check(&path, &image, &Thresholds::exact(), false);
+    }
+
+    #[test]
+    fn update_reference() {
+        // Updating writes the reference and returns its path, after which
+        // it's compared with.
+        let path = env::temp_dir().join(format!("fiz-golden-update-{}.png", process::id()));
+        let image = render(8, 8, 1, triangle).unwrap();
+        assert_eq!(check(&path, &image, &Thresholds::exact(), true), Some(path.clone()));
+        assert_eq!(check(&path, &image, &Thresholds::exact(), false), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
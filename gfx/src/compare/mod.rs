@@ -0,0 +1,367 @@
+//! Image comparison, for regression testing rendered output against golden
+//! (reference) images.
+//!
+//! `compare` measures how two images differ with several metrics: the
+//! per-pixel absolute difference, PSNR, SSIM and a FLIP-style perceptual
+//! error. A `Comparison` can be checked against `Thresholds`, and renders a
+//! heatmap showing where the images differ.
+//!
+//! ```
+//! use fiz_gfx::math::Vec4;
+//! use fiz_gfx::image::{Image, PixelFormat};
+//! use fiz_gfx::compare::{compare, Thresholds};
+//!
+//! let mut reference = Image::new(16, 16, PixelFormat::Rgba8);
+//! let mut test = reference.clone();
+//! test.set_pixel(3, 4, Vec4(1.0, 1.0, 1.0, 1.0));
+//!
+//! let c = compare(&reference, &test).unwrap();
+//! assert_eq!((c.max_abs_diff, c.differing_pixels), (1.0, 1));
+//! assert!(!c.passes(&Thresholds::exact()));
+//! assert!(c.heatmap().pixel(3, 4).0 > 0.5);
+//! ```
+//!
+//! The `golden` module has helpers to render through the software device
+//! and assert the result matches a stored reference.
+
+mod flip;
+pub mod golden;
+
+use std::error::Error;
+use std::f64;
+use std::fmt;
+use math::Vec4;
+use super::image::{Image, PixelFormat};
+
+/// SizeMismatch is the error comparing images of different sizes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SizeMismatch {
+    /// The width and height of the reference image.
+    pub reference: (u32, u32),
+
+    /// The width and height of the test image.
+    pub test: (u32, u32),
+}
+
+impl fmt::Display for SizeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "image is {}x{}, but the reference is {}x{}",
+               self.test.0,
+               self.test.1,
+               self.reference.0,
+               self.reference.1)
+    }
+}
+
+impl Error for SizeMismatch {}
+
+/// Thresholds are the limits of each metric for images to match.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Thresholds {
+    /// The greatest absolute difference of any channel of any pixel.
+    pub max_abs_diff: f32,
+
+    /// The least peak signal-to-noise ratio, in decibels.
+    pub min_psnr: f64,
+
+    /// The least mean structural similarity.
+    pub min_ssim: f64,
+
+    /// The greatest mean FLIP-style perceptual error.
+    pub max_flip: f64,
+}
+
+impl Default for Thresholds {
+    /// default returns thresholds allowing differences which are hard to
+    /// see, such as from rounding, but not missing or misplaced features.
+    fn default() -> Self {
+        Thresholds {
+            max_abs_diff: 1.0,
+            min_psnr: 35.0,
+            min_ssim: 0.95,
+            max_flip: 0.05,
+        }
+    }
+}
+
+impl Thresholds {
+    /// exact returns thresholds which only identical images match.
+    pub fn exact() -> Self {
+        Thresholds { max_abs_diff: 0.0, ..Thresholds::default() }
+    }
+}
+
+/// Comparison is the result of comparing a test image with a reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// The greatest absolute difference of any channel of any pixel.
+    pub max_abs_diff: f32,
+
+    /// The mean absolute difference of all channels of all pixels.
+    pub mean_abs_diff: f64,
+
+    /// The number of pixels which differ at all.
+    pub differing_pixels: usize,
+
+    /// The peak signal-to-noise ratio of the colors, in decibels, which is
+    /// infinite for identical images.
+    pub psnr: f64,
+
+    /// The mean structural similarity of the luminance, one for identical
+    /// images.
+    pub ssim: f64,
+
+    /// The mean FLIP-style perceptual error, from zero for identical images
+    /// to one.
+    pub flip: f64,
+
+    width: u32,
+    height: u32,
+    abs_diff: Vec<f32>,
+    flip_map: Vec<f32>,
+}
+
+impl Comparison {
+    /// abs_diff_at returns the greatest absolute difference of the channels
+    /// of a pixel.
+    ///
+    /// # Panics
+    ///
+    /// abs_diff_at panics if the pixel is out of range.
+    pub fn abs_diff_at(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) out of range", x, y);
+        self.abs_diff[(y * self.width + x) as usize]
+    }
+
+    /// flip_at returns the perceptual error of a pixel.
+    ///
+    /// # Panics
+    ///
+    /// flip_at panics if the pixel is out of range.
+    pub fn flip_at(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.width && y < self.height, "pixel ({}, {}) out of range", x, y);
+        self.flip_map[(y * self.width + x) as usize]
+    }
+
+    /// failures returns a description of each metric outside the
+    /// thresholds.
+    pub fn failures(&self, t: &Thresholds) -> Vec<String> {
+        let mut failures = Vec::new();
+        if self.max_abs_diff > t.max_abs_diff {
+            failures.push(format!("max abs diff {} > {}", self.max_abs_diff, t.max_abs_diff));
+        }
+        if self.psnr < t.min_psnr {
+            failures.push(format!("PSNR {:.2} dB < {} dB", self.psnr, t.min_psnr));
+        }
+        if self.ssim < t.min_ssim {
+            failures.push(format!("SSIM {:.4} < {}", self.ssim, t.min_ssim));
+        }
+        if self.flip > t.max_flip {
+            failures.push(format!("FLIP {:.4} > {}", self.flip, t.max_flip));
+        }
+        failures
+    }
+
+    /// passes reports whether every metric is within the thresholds.
+    pub fn passes(&self, t: &Thresholds) -> bool {
+        self.failures(t).is_empty()
+    }
+
+    /// heatmap returns an image of the perceptual error of each pixel, from
+    /// black for none through purple, red and yellow to white for the most.
+    pub fn heatmap(&self) -> Image {
+        // Stops of the magma color map.
+        const STOPS: [(f32, f32, f32); 5] = [(0.0, 0.0, 0.016), (0.316, 0.071, 0.486), (0.718, 0.216, 0.475),
+                                             (0.988, 0.537, 0.380), (0.988, 0.992, 0.749)];
+        let mut image = Image::new(self.width, self.height, PixelFormat::Rgba8);
+        for (i, &e) in self.flip_map.iter().enumerate() {
+            let t = e.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+            let k = (t as usize).min(STOPS.len() - 2);
+            let (a, b, f) = (STOPS[k], STOPS[k + 1], t - k as f32);
+            let c = Vec4(a.0 + (b.0 - a.0) * f, a.1 + (b.1 - a.1) * f, a.2 + (b.2 - a.2) * f, 1.0);
+            image.set_pixel(i as u32 % self.width, i as u32 / self.width, c);
+        }
+        image
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "max abs diff {}, mean abs diff {:.6}, {} differing pixels, PSNR {:.2} dB, SSIM {:.4}, FLIP {:.4}",
+               self.max_abs_diff,
+               self.mean_abs_diff,
+               self.differing_pixels,
+               self.psnr,
+               self.ssim,
+               self.flip)
+    }
+}
+
+/// compare compares a test image with a reference of the same size. The
+/// images may be in any pixel format, and are taken to hold sRGB encoded
+/// colors, as they would be displayed. Alpha only counts towards the
+/// absolute differences.
+pub fn compare(reference: &Image, test: &Image) -> Result<Comparison, SizeMismatch> {
+    let (width, height) = (reference.width(), reference.height());
+    if (test.width(), test.height()) != (width, height) {
+        return Err(SizeMismatch {
+            reference: (width, height),
+            test: (test.width(), test.height()),
+        });
+    }
+    let pixels = |image: &Image| {
+        let mut p = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                p.push(image.pixel(x, y));
+            }
+        }
+        p
+    };
+    let (a, b) = (pixels(reference), pixels(test));
+
+    let mut abs_diff = Vec::with_capacity(a.len());
+    let (mut sum_abs, mut sum_sq) = (0.0, 0.0);
+    for (p, q) in a.iter().zip(&b) {
+        let d = [(p.0 - q.0).abs(), (p.1 - q.1).abs(), (p.2 - q.2).abs(), (p.3 - q.3).abs()];
+        abs_diff.push(d.iter().cloned().fold(0.0, f32::max));
+        sum_abs += d.iter().map(|&d| d as f64).sum::<f64>();
+        sum_sq += d[..3].iter().map(|&d| (d as f64) * (d as f64)).sum::<f64>();
+    }
+    let n = a.len().max(1) as f64;
+    let mse = sum_sq / (3.0 * n);
+    let luminance = |p: &[Vec4<f32>]| p.iter().map(|c| 0.2126 * c.0 + 0.7152 * c.1 + 0.0722 * c.2).collect::<Vec<_>>();
+    let flip_map = flip::flip(&a, &b, width as usize, height as usize);
+    Ok(Comparison {
+        max_abs_diff: abs_diff.iter().cloned().fold(0.0, f32::max),
+        mean_abs_diff: sum_abs / (4.0 * n),
+        differing_pixels: abs_diff.iter().filter(|&&d| d > 0.0).count(),
+        psnr: if mse == 0.0 { f64::INFINITY } else { 10.0 * (1.0 / mse).log10() },
+        ssim: ssim(&luminance(&a), &luminance(&b), width as usize, height as usize),
+        flip: flip_map.iter().map(|&e| e as f64).sum::<f64>() / n,
+        width,
+        height,
+        abs_diff,
+        flip_map,
+    })
+}
+
+// gaussian returns a normalized Gaussian kernel with the given standard
+// deviation and radius.
+fn gaussian(sigma: f32, radius: usize) -> Vec<f32> {
+    let k: Vec<f32> = (0..2 * radius + 1)
+        .map(|i| {
+            let x = i as f32 - radius as f32;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let sum: f32 = k.iter().sum();
+    k.into_iter().map(|v| v / sum).collect()
+}
+
+// convolve filters an image of one channel separably with the horizontal
+// and vertical kernels, which have odd lengths, clamping at the edges.
+fn convolve(src: &[f32], width: usize, height: usize, kx: &[f32], ky: &[f32]) -> Vec<f32> {
+    let clamp = |i: isize, n: usize| i.clamp(0, n as isize - 1) as usize;
+    let (rx, ry) = ((kx.len() / 2) as isize, (ky.len() / 2) as isize);
+    let mut rows = vec![0.0; src.len()];
+    for y in 0..height {
+        for x in 0..width {
+            rows[y * width + x] = kx.iter()
+                .enumerate()
+                .map(|(i, &k)| k * src[y * width + clamp(x as isize + i as isize - rx, width)])
+                .sum();
+        }
+    }
+    let mut out = vec![0.0; src.len()];
+    for y in 0..height {
+        for x in 0..width {
+            out[y * width + x] = ky.iter()
+                .enumerate()
+                .map(|(i, &k)| k * rows[clamp(y as isize + i as isize - ry, height) * width + x])
+                .sum();
+        }
+    }
+    out
+}
+
+// ssim returns the mean structural similarity of two images of one channel,
+// with the usual 11x11 Gaussian window.
+fn ssim(a: &[f32], b: &[f32], width: usize, height: usize) -> f64 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+    let k = gaussian(1.5, 5);
+    let blur = |v: &[f32]| convolve(v, width, height, &k, &k);
+    let product = |x: &[f32], y: &[f32]| x.iter().zip(y).map(|(x, y)| x * y).collect::<Vec<_>>();
+    let (mu_a, mu_b) = (blur(a), blur(b));
+    let (aa, bb, ab) = (blur(&product(a, a)), blur(&product(b, b)), blur(&product(a, b)));
+    let sum: f64 = (0..a.len())
+        .map(|i| {
+            let (ma, mb) = (mu_a[i], mu_b[i]);
+            let (va, vb, cov) = (aa[i] - ma * ma, bb[i] - mb * mb, ab[i] - ma * mb);
+            (((2.0 * ma * mb + C1) * (2.0 * cov + C2)) / ((ma * ma + mb * mb + C1) * (va + vb + C2))) as f64
+        })
+        .sum();
+    sum / a.len().max(1) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec4;
+    use super::super::image::{Image, PixelFormat};
+    use super::{compare, SizeMismatch, Thresholds};
+
+    // pattern returns an image of smooth gradients, with a sharp-edged light
+    // box if boxed.
+    fn pattern(boxed: bool) -> Image {
+        let mut image = Image::new(48, 32, PixelFormat::Rgba8);
+        for y in 0..32 {
+            for x in 0..48 {
+                let c = if boxed && (10..30).contains(&x) && (8..20).contains(&y) {
+                    Vec4(0.9, 0.9, 0.9, 1.0)
+                } else {
+                    Vec4(x as f32 / 48.0, y as f32 / 32.0, 0.2, 1.0)
+                };
+                image.set_pixel(x, y, c);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn metrics() {
+        let a = pattern(true);
+        let same = compare(&a, &a).unwrap();
+        assert_eq!((same.max_abs_diff, same.differing_pixels, same.flip), (0.0, 0, 0.0));
+        assert!(same.psnr.is_infinite() && (same.ssim - 1.0).abs() < 1e-6);
+        assert!(same.passes(&Thresholds::exact()));
+
+        // Small noise is within the default thresholds.
+        let mut noisy = a.clone();
+        for y in 0..32 {
+            for x in 0..48 {
+                let p = noisy.pixel(x, y);
+                let n = if (x * 7 + y * 13) % 5 == 0 { 1.0 / 255.0 } else { 0.0 };
+                noisy.set_pixel(x, y, Vec4(p.0 + n, p.1, p.2 - n, 1.0));
+            }
+        }
+        let small = compare(&a, &noisy).unwrap();
+        assert!(small.passes(&Thresholds::default()), "{}", small);
+        assert!(!small.passes(&Thresholds::exact()));
+
+        // A missing feature isn't.
+        let big = compare(&a, &pattern(false)).unwrap();
+        assert_eq!(big.failures(&Thresholds::default()).len(), 3, "{}", big);
+        assert!(big.flip > small.flip && big.ssim < small.ssim && big.psnr < small.psnr);
+        assert!(big.flip_at(20, 14) > 0.1 && big.flip_at(45, 30) < 0.01);
+        assert_eq!((big.abs_diff_at(10, 8), big.abs_diff_at(9, 8)), ((230.0 - 51.0) / 255.0, 0.0));
+
+        assert_eq!(compare(&a, &Image::new(2, 2, PixelFormat::R8)),
+                   Err(SizeMismatch {
+                       reference: (48, 32),
+                       test: (2, 2),
+                   }));
+    }
+}
@@ -0,0 +1,616 @@
+//! The rendering device abstraction.
+//!
+//! A `Device` owns GPU resources (buffers, textures, samplers and
+//! pipelines), which are referred to by typed handles, and executes
+//! `CommandBuffer`s recording render passes and draw calls. Engine code
+//! written against the trait runs unchanged on any backend:
+//!
+//! - `NullDevice` validates and records everything it's given, for
+//!   inspection by tests.
+//! - `SoftDevice` renders with the software rasterizer (see the `raster`
+//!   module), for headless reference rendering.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+use math::Vec4;
+use super::raster::Cull;
+
+mod null;
+mod soft;
+
+pub use self::null::NullDevice;
+pub use self::soft::{SoftDevice, CpuShader, ShaderContext};
+
+macro_rules! handle {
+    ($name:ident, $what:expr) => {
+        #[doc = concat!(stringify!($name), " is a handle to a ", $what, " created by a `Device`.")]
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(u32);
+    };
+}
+
+handle!(Buffer, "buffer");
+handle!(Texture, "texture");
+handle!(Sampler, "sampler");
+handle!(Pipeline, "pipeline");
+
+/// DeviceError describes a failure of a device operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceError {
+    /// A handle doesn't refer to an existing resource of the device.
+    InvalidHandle,
+
+    /// A write or draw reaches outside of a buffer or texture.
+    OutOfBounds,
+
+    /// A descriptor or command is invalid.
+    Invalid(String),
+
+    /// The device doesn't support a requested feature.
+    Unsupported(String),
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeviceError::InvalidHandle => write!(f, "invalid resource handle"),
+            DeviceError::OutOfBounds => write!(f, "access out of bounds"),
+            DeviceError::Invalid(ref message) => write!(f, "invalid: {}", message),
+            DeviceError::Unsupported(ref what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl Error for DeviceError {}
+
+/// BufferUsage is the way a buffer is used by draw calls.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Vertex attributes.
+    Vertex,
+
+    /// Vertex indices.
+    Index,
+
+    /// Uniform values given to shaders.
+    Uniform,
+}
+
+/// BufferDesc describes a buffer to create.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BufferDesc {
+    /// The way the buffer is used.
+    pub usage: BufferUsage,
+
+    /// The size of the buffer in bytes. Buffers are initially zeroed.
+    pub size: usize,
+}
+
+/// TextureFormat is the format of the texels of a texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// Four bytes per texel, mapped linearly onto [0, 1].
+    Rgba8Unorm,
+
+    /// Four bytes per texel, with sRGB encoded color channels.
+    Rgba8Srgb,
+
+    /// Four 32-bit floats per texel.
+    Rgba32Float,
+
+    /// A 32-bit float depth per texel.
+    Depth32Float,
+}
+
+impl TextureFormat {
+    /// texel_size returns the size of a texel in bytes.
+    pub fn texel_size(self) -> usize {
+        match self {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8Srgb | TextureFormat::Depth32Float => 4,
+            TextureFormat::Rgba32Float => 16,
+        }
+    }
+
+    /// is_depth tells if the format holds depth rather than color.
+    pub fn is_depth(self) -> bool {
+        self == TextureFormat::Depth32Float
+    }
+}
+
+/// TextureDesc describes a texture to create.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TextureDesc {
+    /// The width in texels.
+    pub width: u32,
+
+    /// The height in texels.
+    pub height: u32,
+
+    /// The format of the texels.
+    pub format: TextureFormat,
+
+    /// The number of samples per texel, for multisampled render targets.
+    pub samples: u32,
+}
+
+/// Filter is the filtering used when sampling a texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// The nearest texel is used.
+    Nearest,
+
+    /// The four nearest texels are blended.
+    Linear,
+}
+
+/// AddressMode is the way texture coordinates outside of [0, 1] are mapped
+/// onto a texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AddressMode {
+    /// The texture repeats.
+    Repeat,
+
+    /// The texture repeats, mirrored every other time.
+    MirroredRepeat,
+
+    /// Coordinates are clamped to the edge of the texture.
+    ClampToEdge,
+}
+
+/// SamplerDesc describes a sampler to create.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SamplerDesc {
+    /// The filter used when the texture is magnified.
+    pub mag_filter: Filter,
+
+    /// The filter used when the texture is minified.
+    pub min_filter: Filter,
+
+    /// The address mode along U.
+    pub address_u: AddressMode,
+
+    /// The address mode along V.
+    pub address_v: AddressMode,
+}
+
+/// VertexFormat is the format of a vertex attribute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VertexFormat {
+    /// One 32-bit float.
+    Float,
+
+    /// Two 32-bit floats.
+    Float2,
+
+    /// Three 32-bit floats.
+    Float3,
+
+    /// Four 32-bit floats.
+    Float4,
+}
+
+impl VertexFormat {
+    /// components returns the number of components of the format.
+    pub fn components(self) -> usize {
+        match self {
+            VertexFormat::Float => 1,
+            VertexFormat::Float2 => 2,
+            VertexFormat::Float3 => 3,
+            VertexFormat::Float4 => 4,
+        }
+    }
+}
+
+/// VertexAttribute describes an attribute of the vertices in a vertex
+/// buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// The format of the attribute.
+    pub format: VertexFormat,
+
+    /// The offset of the attribute from the start of a vertex, in bytes.
+    pub offset: usize,
+}
+
+/// IndexFormat is the type of the indices in an index buffer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// 16-bit indices.
+    U16,
+
+    /// 32-bit indices.
+    U32,
+}
+
+/// PipelineDesc describes a pipeline to create: the shaders and
+/// fixed-function state used by draw calls.
+#[derive(Clone)]
+pub struct PipelineDesc {
+    /// The distance between vertices in the vertex buffer, in bytes.
+    pub vertex_stride: usize,
+
+    /// The vertex attributes, in order of shader location.
+    pub attributes: Vec<VertexAttribute>,
+
+    /// The shader source code, for backends which compile shaders.
+    pub source: Option<String>,
+
+    /// The shader implementation, for backends which run on the CPU.
+    pub cpu_shader: Option<Rc<dyn CpuShader>>,
+
+    /// Which triangles are culled.
+    pub cull: Cull,
+
+    /// Whether fragments are only drawn when nearer than the depth buffer.
+    pub depth_test: bool,
+
+    /// Whether drawn fragments update the depth buffer.
+    pub depth_write: bool,
+}
+
+impl fmt::Debug for PipelineDesc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PipelineDesc")
+            .field("vertex_stride", &self.vertex_stride)
+            .field("attributes", &self.attributes)
+            .field("source", &self.source)
+            .field("cpu_shader", &self.cpu_shader.as_ref().map(|_| ".."))
+            .field("cull", &self.cull)
+            .field("depth_test", &self.depth_test)
+            .field("depth_write", &self.depth_write)
+            .finish()
+    }
+}
+
+/// RenderPass describes the targets of a render pass and how they're
+/// cleared.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RenderPass {
+    /// The color texture drawn into.
+    pub color: Texture,
+
+    /// The depth texture, if any.
+    pub depth: Option<Texture>,
+
+    /// The color the color texture is cleared to, if it's cleared.
+    pub clear_color: Option<Vec4<f32>>,
+
+    /// The depth the depth texture is cleared to, if it's cleared.
+    pub clear_depth: Option<f32>,
+}
+
+/// Command is a command recorded in a `CommandBuffer`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Begins a render pass.
+    BeginPass(RenderPass),
+
+    /// Sets the pipeline used by draw calls.
+    SetPipeline(Pipeline),
+
+    /// Sets the vertex buffer used by draw calls.
+    SetVertexBuffer(Buffer),
+
+    /// Sets the index buffer used by indexed draw calls.
+    SetIndexBuffer(Buffer, IndexFormat),
+
+    /// Sets the uniform buffer given to shaders.
+    SetUniformBuffer(Buffer),
+
+    /// Binds a texture and sampler to a slot, for shaders to sample.
+    SetTexture {
+        /// The slot of the texture.
+        slot: u32,
+
+        /// The texture.
+        texture: Texture,
+
+        /// The sampler.
+        sampler: Sampler,
+    },
+
+    /// Draws triangles from a range of vertices.
+    Draw(Range<u32>),
+
+    /// Draws triangles from a range of indices.
+    DrawIndexed(Range<u32>),
+
+    /// Ends the render pass.
+    EndPass,
+}
+
+/// CommandBuffer records commands to be submitted to a device.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::device::{Device, NullDevice, CommandBuffer, Command, BufferDesc, BufferUsage, TextureDesc,
+///                       TextureFormat, RenderPass};
+///
+/// let mut device = NullDevice::new();
+/// let target = device.create_texture(&TextureDesc {
+///     width: 64,
+///     height: 64,
+///     format: TextureFormat::Rgba8Unorm,
+///     samples: 1,
+/// }).unwrap();
+///
+/// let mut commands = CommandBuffer::new();
+/// commands.begin_pass(RenderPass { color: target, depth: None, clear_color: None, clear_depth: None });
+/// commands.end_pass();
+/// device.submit(&commands).unwrap();
+/// assert_eq!(device.submissions()[0].commands().len(), 2);
+///
+/// // Drawing requires a pipeline.
+/// let mut commands = CommandBuffer::new();
+/// commands.begin_pass(RenderPass { color: target, depth: None, clear_color: None, clear_depth: None });
+/// commands.draw(0..3);
+/// commands.end_pass();
+/// assert!(device.submit(&commands).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandBuffer {
+    commands: Vec<Command>,
+}
+
+impl CommandBuffer {
+    /// new returns a new empty command buffer.
+    pub fn new() -> Self {
+        CommandBuffer::default()
+    }
+
+    /// commands returns the recorded commands.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// clear removes all of the recorded commands.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    /// begin_pass records the beginning of a render pass.
+    pub fn begin_pass(&mut self, pass: RenderPass) {
+        self.commands.push(Command::BeginPass(pass));
+    }
+
+    /// set_pipeline records setting the pipeline.
+    pub fn set_pipeline(&mut self, pipeline: Pipeline) {
+        self.commands.push(Command::SetPipeline(pipeline));
+    }
+
+    /// set_vertex_buffer records setting the vertex buffer.
+    pub fn set_vertex_buffer(&mut self, buffer: Buffer) {
+        self.commands.push(Command::SetVertexBuffer(buffer));
+    }
+
+    /// set_index_buffer records setting the index buffer.
+    pub fn set_index_buffer(&mut self, buffer: Buffer, format: IndexFormat) {
+        self.commands.push(Command::SetIndexBuffer(buffer, format));
+    }
+
+    /// set_uniform_buffer records setting the uniform buffer.
+    pub fn set_uniform_buffer(&mut self, buffer: Buffer) {
+        self.commands.push(Command::SetUniformBuffer(buffer));
+    }
+
+    /// set_texture records binding a texture and sampler to a slot.
+    pub fn set_texture(&mut self, slot: u32, texture: Texture, sampler: Sampler) {
+        self.commands.push(Command::SetTexture {
+            slot,
+            texture,
+            sampler,
+        });
+    }
+
+    /// draw records drawing triangles from a range of vertices.
+    pub fn draw(&mut self, vertices: Range<u32>) {
+        self.commands.push(Command::Draw(vertices));
+    }
+
+    /// draw_indexed records drawing triangles from a range of indices.
+    pub fn draw_indexed(&mut self, indices: Range<u32>) {
+        self.commands.push(Command::DrawIndexed(indices));
+    }
+
+    /// end_pass records the end of the render pass.
+    pub fn end_pass(&mut self) {
+        self.commands.push(Command::EndPass);
+    }
+}
+
+/// Device is a rendering backend: it creates resources and executes
+/// commands.
+pub trait Device {
+    /// name returns the name of the backend, e.g. `"null"`.
+    fn name(&self) -> &str;
+
+    /// create_buffer creates a zeroed buffer.
+    fn create_buffer(&mut self, desc: &BufferDesc) -> Result<Buffer, DeviceError>;
+
+    /// write_buffer writes data into a buffer, starting at the given offset
+    /// in bytes.
+    fn write_buffer(&mut self, buffer: Buffer, offset: usize, data: &[u8]) -> Result<(), DeviceError>;
+
+    /// destroy_buffer destroys a buffer.
+    fn destroy_buffer(&mut self, buffer: Buffer) -> Result<(), DeviceError>;
+
+    /// create_texture creates a texture, cleared to zero (or a depth of one).
+    fn create_texture(&mut self, desc: &TextureDesc) -> Result<Texture, DeviceError>;
+
+    /// write_texture replaces the contents of a single-sampled texture with
+    /// tightly packed rows of texels, from the top.
+    fn write_texture(&mut self, texture: Texture, data: &[u8]) -> Result<(), DeviceError>;
+
+    /// read_texture returns the contents of a texture in the layout taken by
+    /// write_texture. Multisampled textures are resolved.
+    fn read_texture(&mut self, texture: Texture) -> Result<Vec<u8>, DeviceError>;
+
+    /// destroy_texture destroys a texture.
+    fn destroy_texture(&mut self, texture: Texture) -> Result<(), DeviceError>;
+
+    /// create_sampler creates a sampler.
+    fn create_sampler(&mut self, desc: &SamplerDesc) -> Result<Sampler, DeviceError>;
+
+    /// destroy_sampler destroys a sampler.
+    fn destroy_sampler(&mut self, sampler: Sampler) -> Result<(), DeviceError>;
+
+    /// create_pipeline creates a pipeline.
+    fn create_pipeline(&mut self, desc: &PipelineDesc) -> Result<Pipeline, DeviceError>;
+
+    /// destroy_pipeline destroys a pipeline.
+    fn destroy_pipeline(&mut self, pipeline: Pipeline) -> Result<(), DeviceError>;
+
+    /// submit executes the commands, in order. Nothing is executed if the
+    /// commands are invalid.
+    fn submit(&mut self, commands: &CommandBuffer) -> Result<(), DeviceError>;
+}
+
+// Slots stores the resources of one kind, indexed by their handles.
+#[derive(Clone, Debug)]
+struct Slots<T> {
+    slots: Vec<Option<T>>,
+}
+
+impl<T> Default for Slots<T> {
+    fn default() -> Self {
+        Slots { slots: Vec::new() }
+    }
+}
+
+impl<T> Slots<T> {
+    fn insert(&mut self, value: T) -> u32 {
+        self.slots.push(Some(value));
+        (self.slots.len() - 1) as u32
+    }
+
+    fn get(&self, id: u32) -> Result<&T, DeviceError> {
+        self.slots.get(id as usize).and_then(|s| s.as_ref()).ok_or(DeviceError::InvalidHandle)
+    }
+
+    fn get_mut(&mut self, id: u32) -> Result<&mut T, DeviceError> {
+        self.slots.get_mut(id as usize).and_then(|s| s.as_mut()).ok_or(DeviceError::InvalidHandle)
+    }
+
+    fn remove(&mut self, id: u32) -> Result<T, DeviceError> {
+        self.slots.get_mut(id as usize).and_then(|s| s.take()).ok_or(DeviceError::InvalidHandle)
+    }
+}
+
+// Resources are the descriptions of the resources of a device, which
+// commands are validated against.
+#[derive(Clone, Debug, Default)]
+struct Resources {
+    buffers: Slots<BufferDesc>,
+    textures: Slots<TextureDesc>,
+    samplers: Slots<SamplerDesc>,
+    pipelines: Slots<PipelineDesc>,
+}
+
+fn invalid<T>(message: &str) -> Result<T, DeviceError> {
+    Err(DeviceError::Invalid(message.to_string()))
+}
+
+impl Resources {
+    fn check_texture(desc: &TextureDesc) -> Result<(), DeviceError> {
+        if desc.width == 0 || desc.height == 0 {
+            return invalid("texture has no texels");
+        }
+        if ![1, 2, 4, 8].contains(&desc.samples) {
+            return Err(DeviceError::Unsupported(format!("{} samples", desc.samples)));
+        }
+        Ok(())
+    }
+
+    fn check_pipeline(desc: &PipelineDesc) -> Result<(), DeviceError> {
+        for a in &desc.attributes {
+            if a.offset + 4 * a.format.components() > desc.vertex_stride {
+                return invalid("vertex attribute outside of the vertex stride");
+            }
+        }
+        Ok(())
+    }
+
+    fn check_buffer(&self, buffer: Buffer, usage: BufferUsage) -> Result<(), DeviceError> {
+        if self.buffers.get(buffer.0)?.usage != usage {
+            return invalid("buffer bound with the wrong usage");
+        }
+        Ok(())
+    }
+
+    // validate checks that the commands refer to existing resources and
+    // form a valid sequence of render passes.
+    fn validate(&self, commands: &[Command]) -> Result<(), DeviceError> {
+        let mut pass: Option<&RenderPass> = None;
+        let mut pipeline = None;
+        let mut vertices = None;
+        let mut indices = None;
+        for c in commands {
+            if let Command::BeginPass(_) = *c {
+            } else if pass.is_none() {
+                return invalid("command outside of a render pass");
+            }
+            match *c {
+                Command::BeginPass(ref p) => {
+                    if pass.is_some() {
+                        return invalid("render pass begun within another");
+                    }
+                    let color = self.textures.get(p.color.0)?;
+                    if color.format.is_depth() {
+                        return invalid("color target has a depth format");
+                    }
+                    if let Some(depth) = p.depth {
+                        let depth = self.textures.get(depth.0)?;
+                        if !depth.format.is_depth() {
+                            return invalid("depth target has a color format");
+                        }
+                        if (depth.width, depth.height, depth.samples) != (color.width, color.height, color.samples) {
+                            return invalid("depth target doesn't match the color target");
+                        }
+                    }
+                    pass = Some(p);
+                    pipeline = None;
+                    vertices = None;
+                    indices = None;
+                }
+                Command::SetPipeline(p) => pipeline = Some(self.pipelines.get(p.0)?),
+                Command::SetVertexBuffer(b) => {
+                    self.check_buffer(b, BufferUsage::Vertex)?;
+                    vertices = Some(b);
+                }
+                Command::SetIndexBuffer(b, _) => {
+                    self.check_buffer(b, BufferUsage::Index)?;
+                    indices = Some(b);
+                }
+                Command::SetUniformBuffer(b) => self.check_buffer(b, BufferUsage::Uniform)?,
+                Command::SetTexture { texture, sampler, .. } => {
+                    let t = self.textures.get(texture.0)?;
+                    self.samplers.get(sampler.0)?;
+                    if t.samples != 1 || pass.is_some_and(|p| p.color == texture || p.depth == Some(texture)) {
+                        return invalid("texture can't be sampled");
+                    }
+                }
+                Command::Draw(ref range) | Command::DrawIndexed(ref range) => {
+                    if pipeline.is_none() || vertices.is_none() {
+                        return invalid("draw without a pipeline and vertex buffer");
+                    }
+                    if range.start > range.end || (range.end - range.start) % 3 != 0 {
+                        return invalid("draw range isn't a whole number of triangles");
+                    }
+                    if let Command::DrawIndexed(_) = *c {
+                        if indices.is_none() {
+                            return invalid("indexed draw without an index buffer");
+                        }
+                    }
+                }
+                Command::EndPass => pass = None,
+            }
+        }
+        if pass.is_some() {
+            return invalid("render pass isn't ended");
+        }
+        Ok(())
+    }
+}
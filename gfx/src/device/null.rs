@@ -0,0 +1,112 @@
+use super::{Device, DeviceError, Resources, Slots, Buffer, BufferDesc, Texture, TextureDesc, TextureFormat,
+            Sampler, SamplerDesc, Pipeline, PipelineDesc, CommandBuffer};
+
+/// NullDevice is a device which renders nothing. It validates resources and
+/// commands like any other device, keeps the contents written to buffers and
+/// textures, and records every submitted command buffer for inspection.
+#[derive(Clone, Debug, Default)]
+pub struct NullDevice {
+    resources: Resources,
+    buffers: Slots<Vec<u8>>,
+    textures: Slots<Vec<u8>>,
+    submissions: Vec<CommandBuffer>,
+}
+
+impl NullDevice {
+    /// new returns a new device with no resources.
+    pub fn new() -> Self {
+        NullDevice::default()
+    }
+
+    /// submissions returns the command buffers submitted so far, in order.
+    pub fn submissions(&self) -> &[CommandBuffer] {
+        &self.submissions
+    }
+
+    /// buffer_data returns the contents of a buffer.
+    pub fn buffer_data(&self, buffer: Buffer) -> Result<&[u8], DeviceError> {
+        self.buffers.get(buffer.0).map(|b| &b[..])
+    }
+}
+
+// initial_texture returns the initial contents of a texture.
+fn initial_texture(desc: &TextureDesc) -> Vec<u8> {
+    let n = (desc.width * desc.height) as usize;
+    match desc.format {
+        TextureFormat::Depth32Float => (0..n).flat_map(|_| 1f32.to_le_bytes()).collect(),
+        f => vec![0; n * f.texel_size()],
+    }
+}
+
+impl Device for NullDevice {
+    fn name(&self) -> &str {
+        "null"
+    }
+
+    fn create_buffer(&mut self, desc: &BufferDesc) -> Result<Buffer, DeviceError> {
+        self.buffers.insert(vec![0; desc.size]);
+        Ok(Buffer(self.resources.buffers.insert(*desc)))
+    }
+
+    fn write_buffer(&mut self, buffer: Buffer, offset: usize, data: &[u8]) -> Result<(), DeviceError> {
+        let b = self.buffers.get_mut(buffer.0)?;
+        b.get_mut(offset..offset + data.len()).ok_or(DeviceError::OutOfBounds)?.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn destroy_buffer(&mut self, buffer: Buffer) -> Result<(), DeviceError> {
+        self.buffers.remove(buffer.0)?;
+        self.resources.buffers.remove(buffer.0).map(|_| ())
+    }
+
+    fn create_texture(&mut self, desc: &TextureDesc) -> Result<Texture, DeviceError> {
+        Resources::check_texture(desc)?;
+        self.textures.insert(initial_texture(desc));
+        Ok(Texture(self.resources.textures.insert(*desc)))
+    }
+
+    fn write_texture(&mut self, texture: Texture, data: &[u8]) -> Result<(), DeviceError> {
+        let desc = self.resources.textures.get(texture.0)?;
+        if desc.samples != 1 {
+            return Err(DeviceError::Invalid("write to a multisampled texture".to_string()));
+        }
+        let t = self.textures.get_mut(texture.0)?;
+        if data.len() != t.len() {
+            return Err(DeviceError::OutOfBounds);
+        }
+        t.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn read_texture(&mut self, texture: Texture) -> Result<Vec<u8>, DeviceError> {
+        self.textures.get(texture.0).cloned()
+    }
+
+    fn destroy_texture(&mut self, texture: Texture) -> Result<(), DeviceError> {
+        self.textures.remove(texture.0)?;
+        self.resources.textures.remove(texture.0).map(|_| ())
+    }
+
+    fn create_sampler(&mut self, desc: &SamplerDesc) -> Result<Sampler, DeviceError> {
+        Ok(Sampler(self.resources.samplers.insert(*desc)))
+    }
+
+    fn destroy_sampler(&mut self, sampler: Sampler) -> Result<(), DeviceError> {
+        self.resources.samplers.remove(sampler.0).map(|_| ())
+    }
+
+    fn create_pipeline(&mut self, desc: &PipelineDesc) -> Result<Pipeline, DeviceError> {
+        Resources::check_pipeline(desc)?;
+        Ok(Pipeline(self.resources.pipelines.insert(desc.clone())))
+    }
+
+    fn destroy_pipeline(&mut self, pipeline: Pipeline) -> Result<(), DeviceError> {
+        self.resources.pipelines.remove(pipeline.0).map(|_| ())
+    }
+
+    fn submit(&mut self, commands: &CommandBuffer) -> Result<(), DeviceError> {
+        self.resources.validate(commands.commands())?;
+        self.submissions.push(commands.clone());
+        Ok(())
+    }
+}
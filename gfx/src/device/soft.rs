@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+use std::mem;
+use math::{Vec2, Vec4};
+use super::super::image::{linear_to_srgb, srgb_to_linear};
+use super::super::raster::{Framebuffer, Rasterizer};
+use super::super::sampler::lerp;
+use super::{Device, DeviceError, Resources, Slots, Buffer, BufferDesc, Texture, TextureDesc, TextureFormat, Sampler,
+            SamplerDesc, Pipeline, PipelineDesc, CommandBuffer, Command, RenderPass, IndexFormat, Filter, AddressMode};
+
+/// CpuShader is the implementation of a pipeline's shaders for backends
+/// running on the CPU, such as `SoftDevice`.
+pub trait CpuShader {
+    /// vertex returns the clip-space position and the varyings of a vertex,
+    /// given its attributes in the order of the pipeline's attributes.
+    /// Attributes with fewer than four components are extended with zeros
+    /// and a `w` of one.
+    fn vertex(&self, attributes: &[Vec4<f32>], context: &ShaderContext) -> (Vec4<f32>, Vec<f32>);
+
+    /// fragment returns the linear RGBA color of a fragment given its
+    /// interpolated varyings, or None to discard it.
+    fn fragment(&self, varyings: &[f32], context: &ShaderContext) -> Option<Vec4<f32>>;
+}
+
+/// ShaderContext gives CPU shaders access to the bound uniforms and
+/// textures.
+pub struct ShaderContext<'a> {
+    uniforms: &'a [u8],
+    textures: Vec<Option<(&'a Framebuffer, SamplerDesc)>>,
+}
+
+impl<'a> ShaderContext<'a> {
+    /// uniforms returns the contents of the bound uniform buffer, or an
+    /// empty slice if none is bound.
+    pub fn uniforms(&self) -> &[u8] {
+        self.uniforms
+    }
+
+    /// uniform returns the 32-bit float at the given index in the uniform
+    /// buffer, or zero if it's out of range.
+    pub fn uniform(&self, i: usize) -> f32 {
+        match self.uniforms.get(4 * i..4 * i + 4) {
+            Some(b) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            None => 0.0,
+        }
+    }
+
+    /// sample returns the color of the texture bound to the slot at the
+    /// given texture coordinates, whose origin is the top-left corner, or
+    /// transparent black if no texture is bound. Textures have no mipmaps,
+    /// so the magnification filter is always used.
+    pub fn sample(&self, slot: u32, uv: Vec2<f32>) -> Vec4<f32> {
+        let (t, sampler) = match self.textures.get(slot as usize) {
+            Some(&Some(t)) => t,
+            _ => return Vec4(0.0, 0.0, 0.0, 0.0),
+        };
+        let (w, h) = (t.width() as i64, t.height() as i64);
+        let texel = |x: i64, y: i64| {
+            t.color(address(x, w, sampler.address_u) as u32, address(y, h, sampler.address_v) as u32, 0)
+        };
+        let (x, y) = (uv.0 * w as f32, uv.1 * h as f32);
+        match sampler.mag_filter {
+            Filter::Nearest => texel(x.floor() as i64, y.floor() as i64),
+            Filter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = lerp(texel(x0, y0), texel(x0 + 1, y0), fx);
+                let bottom = lerp(texel(x0, y0 + 1), texel(x0 + 1, y0 + 1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+}
+
+// address maps a texel coordinate onto a texture of size n.
+fn address(i: i64, n: i64, mode: AddressMode) -> i64 {
+    match mode {
+        AddressMode::Repeat => i.rem_euclid(n),
+        AddressMode::ClampToEdge => i.max(0).min(n - 1),
+        AddressMode::MirroredRepeat => {
+            let p = i.rem_euclid(2 * n);
+            if p < n { p } else { 2 * n - 1 - p }
+        }
+    }
+}
+
+fn float(b: &[u8], i: usize) -> f32 {
+    f32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]])
+}
+
+/// SoftDevice is a device which renders with the software rasterizer, giving
+/// deterministic results on any machine. Pipelines must have a CPU shader.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use fiz_gfx::math::Vec4;
+/// use fiz_gfx::raster::Cull;
+/// use fiz_gfx::device::*;
+///
+/// // A shader drawing positions given in clip space in a solid color.
+/// struct Solid;
+///
+/// impl CpuShader for Solid {
+///     fn vertex(&self, attributes: &[Vec4<f32>], _: &ShaderContext) -> (Vec4<f32>, Vec<f32>) {
+///         (attributes[0], Vec::new())
+///     }
+///
+///     fn fragment(&self, _: &[f32], _: &ShaderContext) -> Option<Vec4<f32>> {
+///         Some(Vec4(1.0, 0.0, 0.0, 1.0))
+///     }
+/// }
+///
+/// let mut device = SoftDevice::new();
+/// let target = device.create_texture(&TextureDesc {
+///     width: 2,
+///     height: 2,
+///     format: TextureFormat::Rgba8Unorm,
+///     samples: 1,
+/// }).unwrap();
+/// let vertices = device.create_buffer(&BufferDesc { usage: BufferUsage::Vertex, size: 24 }).unwrap();
+/// let data: Vec<u8> = [-1.0f32, -1.0, 1.0, -1.0, -1.0, 1.0].iter().flat_map(|f| f.to_le_bytes()).collect();
+/// device.write_buffer(vertices, 0, &data).unwrap();
+/// let pipeline = device.create_pipeline(&PipelineDesc {
+///     vertex_stride: 8,
+///     attributes: vec![VertexAttribute { format: VertexFormat::Float2, offset: 0 }],
+///     source: None,
+///     cpu_shader: Some(Rc::new(Solid)),
+///     cull: Cull::Back,
+///     depth_test: false,
+///     depth_write: false,
+/// }).unwrap();
+///
+/// let mut commands = CommandBuffer::new();
+/// commands.begin_pass(RenderPass {
+///     color: target,
+///     depth: None,
+///     clear_color: Some(Vec4(0.0, 0.0, 1.0, 1.0)),
+///     clear_depth: None,
+/// });
+/// commands.set_pipeline(pipeline);
+/// commands.set_vertex_buffer(vertices);
+/// commands.draw(0..3);
+/// commands.end_pass();
+/// device.submit(&commands).unwrap();
+///
+/// // The bottom-left pixel is red, and the top-right one blue.
+/// let pixels = device.read_texture(target).unwrap();
+/// assert_eq!(&pixels[8..12], &[255, 0, 0, 255]);
+/// assert_eq!(&pixels[4..8], &[0, 0, 255, 255]);
+/// ```
+#[derive(Debug, Default)]
+pub struct SoftDevice {
+    resources: Resources,
+    buffers: Slots<Vec<u8>>,
+    textures: Slots<Framebuffer>,
+}
+
+// State is the state of the command being executed.
+#[derive(Default)]
+struct State {
+    pass: Option<(RenderPass, Framebuffer)>,
+    pipeline: Option<Pipeline>,
+    vertices: Option<Buffer>,
+    indices: Option<(Buffer, IndexFormat)>,
+    uniforms: Option<Buffer>,
+    textures: Vec<Option<(Texture, Sampler)>>,
+}
+
+impl SoftDevice {
+    /// new returns a new device with no resources.
+    pub fn new() -> Self {
+        SoftDevice::default()
+    }
+
+    // begin_pass takes the framebuffer of the pass's targets out of their
+    // textures, and clears it.
+    fn begin_pass(&mut self, pass: &RenderPass) -> Result<Framebuffer, DeviceError> {
+        let mut fb = mem::replace(self.textures.get_mut(pass.color.0)?, Framebuffer::new(0, 0, 1));
+        if let Some(depth) = pass.depth {
+            mem::swap(fb.depth_data_mut(), self.textures.get_mut(depth.0)?.depth_data_mut());
+        }
+        if let Some(color) = pass.clear_color {
+            for c in fb.color_data_mut() {
+                *c = color;
+            }
+        }
+        if let Some(depth) = pass.clear_depth {
+            for d in fb.depth_data_mut().iter_mut() {
+                *d = depth;
+            }
+        }
+        Ok(fb)
+    }
+
+    // end_pass returns the framebuffer of a pass to its textures.
+    fn end_pass(&mut self, pass: &RenderPass, mut fb: Framebuffer) -> Result<(), DeviceError> {
+        if let Some(depth) = pass.depth {
+            mem::swap(fb.depth_data_mut(), self.textures.get_mut(depth.0)?.depth_data_mut());
+        }
+        *self.textures.get_mut(pass.color.0)? = fb;
+        Ok(())
+    }
+
+    // draw executes a draw call of the given indices (or vertices, if not
+    // indexed).
+    fn draw(&self, state: &mut State, first: u32, count: u32, indexed: bool) -> Result<(), DeviceError> {
+        let pipeline = self.resources.pipelines.get(state.pipeline.unwrap().0)?;
+        let shader = pipeline.cpu_shader
+            .as_ref()
+            .ok_or_else(|| DeviceError::Unsupported("pipeline without a CPU shader".to_string()))?;
+        let vertex_data = self.buffers.get(state.vertices.unwrap().0)?;
+
+        let mut indices = Vec::with_capacity(count as usize);
+        if indexed {
+            let (buffer, format) = state.indices.unwrap();
+            let data = self.buffers.get(buffer.0)?;
+            let size = if format == IndexFormat::U16 { 2 } else { 4 };
+            for i in first as usize..(first + count) as usize {
+                let b = data.get(i * size..(i + 1) * size).ok_or(DeviceError::OutOfBounds)?;
+                indices.push(match format {
+                    IndexFormat::U16 => u16::from_le_bytes([b[0], b[1]]) as u32,
+                    IndexFormat::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                });
+            }
+        } else {
+            indices.extend(first..first + count);
+        }
+
+        let uniforms = match state.uniforms {
+            Some(b) => &self.buffers.get(b.0)?[..],
+            None => &[],
+        };
+        let mut textures = Vec::with_capacity(state.textures.len());
+        for t in &state.textures {
+            textures.push(match *t {
+                Some((texture, sampler)) => {
+                    Some((self.textures.get(texture.0)?, *self.resources.samplers.get(sampler.0)?))
+                }
+                None => None,
+            });
+        }
+        let context = ShaderContext { uniforms, textures };
+
+        // Run the vertex shader once for each distinct vertex.
+        let mut vertices = Vec::new();
+        let mut remap: HashMap<u32, u32> = HashMap::new();
+        let mut triangles = Vec::with_capacity(indices.len() / 3);
+        let mut attributes = vec![Vec4(0.0, 0.0, 0.0, 1.0); pipeline.attributes.len()];
+        for t in indices.chunks(3) {
+            let mut triangle = [0; 3];
+            for (k, &index) in t.iter().enumerate() {
+                if let Some(&v) = remap.get(&index) {
+                    triangle[k] = v;
+                    continue;
+                }
+                let base = index as usize * pipeline.vertex_stride;
+                for (a, desc) in attributes.iter_mut().zip(&pipeline.attributes) {
+                    let n = desc.format.components();
+                    let start = base + desc.offset;
+                    let b = vertex_data.get(start..start + 4 * n).ok_or(DeviceError::OutOfBounds)?;
+                    let c = |i: usize| if i < n { float(b, 4 * i) } else if i == 3 { 1.0 } else { 0.0 };
+                    *a = Vec4(c(0), c(1), c(2), c(3));
+                }
+                vertices.push(shader.vertex(&attributes, &context));
+                triangle[k] = (vertices.len() - 1) as u32;
+                remap.insert(index, triangle[k]);
+            }
+            triangles.push(triangle);
+        }
+
+        let (ref pass, ref mut fb) = *state.pass.as_mut().unwrap();
+        let rasterizer = Rasterizer {
+            cull: pipeline.cull,
+            depth_test: pipeline.depth_test && pass.depth.is_some(),
+            depth_write: pipeline.depth_write && pass.depth.is_some(),
+        };
+        rasterizer.draw(fb, &vertices, &triangles, |f| shader.fragment(&f.varyings, &context));
+        Ok(())
+    }
+}
+
+impl Device for SoftDevice {
+    fn name(&self) -> &str {
+        "soft"
+    }
+
+    fn create_buffer(&mut self, desc: &BufferDesc) -> Result<Buffer, DeviceError> {
+        self.buffers.insert(vec![0; desc.size]);
+        Ok(Buffer(self.resources.buffers.insert(*desc)))
+    }
+
+    fn write_buffer(&mut self, buffer: Buffer, offset: usize, data: &[u8]) -> Result<(), DeviceError> {
+        let b = self.buffers.get_mut(buffer.0)?;
+        b.get_mut(offset..offset + data.len()).ok_or(DeviceError::OutOfBounds)?.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn destroy_buffer(&mut self, buffer: Buffer) -> Result<(), DeviceError> {
+        self.buffers.remove(buffer.0)?;
+        self.resources.buffers.remove(buffer.0).map(|_| ())
+    }
+
+    fn create_texture(&mut self, desc: &TextureDesc) -> Result<Texture, DeviceError> {
+        Resources::check_texture(desc)?;
+        self.textures.insert(Framebuffer::new(desc.width, desc.height, desc.samples));
+        Ok(Texture(self.resources.textures.insert(*desc)))
+    }
+
+    fn write_texture(&mut self, texture: Texture, data: &[u8]) -> Result<(), DeviceError> {
+        let desc = *self.resources.textures.get(texture.0)?;
+        if desc.samples != 1 {
+            return Err(DeviceError::Invalid("write to a multisampled texture".to_string()));
+        }
+        let size = desc.format.texel_size();
+        if data.len() != (desc.width * desc.height) as usize * size {
+            return Err(DeviceError::OutOfBounds);
+        }
+        let fb = self.textures.get_mut(texture.0)?;
+        if desc.format.is_depth() {
+            for (i, d) in fb.depth_data_mut().iter_mut().enumerate() {
+                *d = float(data, 4 * i);
+            }
+            return Ok(());
+        }
+        for (i, c) in fb.color_data_mut().iter_mut().enumerate() {
+            let b = &data[i * size..(i + 1) * size];
+            let unorm = |k: usize| b[k] as f32 / 255.0;
+            *c = match desc.format {
+                TextureFormat::Rgba8Unorm => Vec4(unorm(0), unorm(1), unorm(2), unorm(3)),
+                TextureFormat::Rgba8Srgb => {
+                    Vec4(srgb_to_linear(unorm(0)), srgb_to_linear(unorm(1)), srgb_to_linear(unorm(2)), unorm(3))
+                }
+                _ => Vec4(float(b, 0), float(b, 4), float(b, 8), float(b, 12)),
+            };
+        }
+        Ok(())
+    }
+
+    fn read_texture(&mut self, texture: Texture) -> Result<Vec<u8>, DeviceError> {
+        let desc = *self.resources.textures.get(texture.0)?;
+        let fb = self.textures.get(texture.0)?;
+        let mut out = Vec::with_capacity((desc.width * desc.height) as usize * desc.format.texel_size());
+        if desc.format.is_depth() {
+            for y in 0..desc.height {
+                for x in 0..desc.width {
+                    out.extend_from_slice(&fb.depth(x, y, 0).to_le_bytes());
+                }
+            }
+            return Ok(out);
+        }
+        let unorm = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for c in fb.resolve() {
+            match desc.format {
+                TextureFormat::Rgba8Unorm => out.extend_from_slice(&[unorm(c.0), unorm(c.1), unorm(c.2), unorm(c.3)]),
+                TextureFormat::Rgba8Srgb => {
+                    out.extend_from_slice(&[unorm(linear_to_srgb(c.0)),
+                                            unorm(linear_to_srgb(c.1)),
+                                            unorm(linear_to_srgb(c.2)),
+                                            unorm(c.3)])
+                }
+                _ => {
+                    for x in &[c.0, c.1, c.2, c.3] {
+                        out.extend_from_slice(&x.to_le_bytes());
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn destroy_texture(&mut self, texture: Texture) -> Result<(), DeviceError> {
+        self.textures.remove(texture.0)?;
+        self.resources.textures.remove(texture.0).map(|_| ())
+    }
+
+    fn create_sampler(&mut self, desc: &SamplerDesc) -> Result<Sampler, DeviceError> {
+        Ok(Sampler(self.resources.samplers.insert(*desc)))
+    }
+
+    fn destroy_sampler(&mut self, sampler: Sampler) -> Result<(), DeviceError> {
+        self.resources.samplers.remove(sampler.0).map(|_| ())
+    }
+
+    fn create_pipeline(&mut self, desc: &PipelineDesc) -> Result<Pipeline, DeviceError> {
+        Resources::check_pipeline(desc)?;
+        Ok(Pipeline(self.resources.pipelines.insert(desc.clone())))
+    }
+
+    fn destroy_pipeline(&mut self, pipeline: Pipeline) -> Result<(), DeviceError> {
+        self.resources.pipelines.remove(pipeline.0).map(|_| ())
+    }
+
+    fn submit(&mut self, commands: &CommandBuffer) -> Result<(), DeviceError> {
+        self.resources.validate(commands.commands())?;
+        let mut state = State::default();
+        let mut result = Ok(());
+        for c in commands.commands() {
+            match *c {
+                Command::BeginPass(ref pass) => {
+                    result = self.begin_pass(pass).map(|fb| {
+                        state = State::default();
+                        state.pass = Some((*pass, fb));
+                    })
+                }
+                Command::SetPipeline(p) => state.pipeline = Some(p),
+                Command::SetVertexBuffer(b) => state.vertices = Some(b),
+                Command::SetIndexBuffer(b, format) => state.indices = Some((b, format)),
+                Command::SetUniformBuffer(b) => state.uniforms = Some(b),
+                Command::SetTexture { slot, texture, sampler } => {
+                    let slot = slot as usize;
+                    if state.textures.len() <= slot {
+                        state.textures.resize(slot + 1, None);
+                    }
+                    state.textures[slot] = Some((texture, sampler));
+                }
+                Command::Draw(ref r) => result = self.draw(&mut state, r.start, r.end - r.start, false),
+                Command::DrawIndexed(ref r) => result = self.draw(&mut state, r.start, r.end - r.start, true),
+                Command::EndPass => {
+                    let (pass, fb) = state.pass.take().unwrap();
+                    result = self.end_pass(&pass, fb);
+                }
+            }
+            if result.is_err() {
+                break;
+            }
+        }
+        // Return the targets of an interrupted pass to their textures.
+        if let Some((pass, fb)) = state.pass.take() {
+            self.end_pass(&pass, fb)?;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use math::{Vec2, Vec4};
+    use super::super::super::raster::Cull;
+    use super::super::*;
+
+    // Textured draws a textured quad, given positions in clip space and
+    // texture coordinates, tinted by the uniform color.
+    struct Textured;
+
+    impl CpuShader for Textured {
+        fn vertex(&self, a: &[Vec4<f32>], _: &ShaderContext) -> (Vec4<f32>, Vec<f32>) {
+            (a[0], vec![a[1].0, a[1].1])
+        }
+
+        fn fragment(&self, v: &[f32], c: &ShaderContext) -> Option<Vec4<f32>> {
+            let tint = Vec4(c.uniform(0), c.uniform(1), c.uniform(2), c.uniform(3));
+            Some(c.sample(0, Vec2(v[0], v[1])) * tint)
+        }
+    }
+
+    // render renders a 2x2 checkerboard texture onto a 4x4 target through
+    // any device.
+    fn render<D: Device>(device: &mut D) -> Result<Texture, DeviceError> {
+        let target = device.create_texture(&TextureDesc {
+                width: 4,
+                height: 4,
+                format: TextureFormat::Rgba8Unorm,
+                samples: 4,
+            })?;
+        let depth = device.create_texture(&TextureDesc {
+                width: 4,
+                height: 4,
+                format: TextureFormat::Depth32Float,
+                samples: 4,
+            })?;
+        let texture = device.create_texture(&TextureDesc {
+                width: 2,
+                height: 2,
+                format: TextureFormat::Rgba8Unorm,
+                samples: 1,
+            })?;
+        device.write_texture(texture, &[255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255, 255])?;
+        let sampler = device.create_sampler(&SamplerDesc {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                address_u: AddressMode::ClampToEdge,
+                address_v: AddressMode::ClampToEdge,
+            })?;
+
+        // Interleaved positions and texture coordinates.
+        let vertices: [f32; 16] = [-1.0, 1.0, 0.0, 0.0, -1.0, -1.0, 0.0, 1.0, 1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.0];
+        let bytes = |v: &[f32]| v.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>();
+        let vertex_buffer = device.create_buffer(&BufferDesc {
+                usage: BufferUsage::Vertex,
+                size: 64,
+            })?;
+        device.write_buffer(vertex_buffer, 0, &bytes(&vertices))?;
+        let index_buffer = device.create_buffer(&BufferDesc {
+                usage: BufferUsage::Index,
+                size: 12,
+            })?;
+        let indices: Vec<u8> = [0u16, 1, 2, 0, 2, 3].iter().flat_map(|i| i.to_le_bytes()).collect();
+        device.write_buffer(index_buffer, 0, &indices)?;
+        let uniforms = device.create_buffer(&BufferDesc {
+                usage: BufferUsage::Uniform,
+                size: 16,
+            })?;
+        device.write_buffer(uniforms, 0, &bytes(&[1.0, 0.0, 1.0, 1.0]))?;
+
+        let pipeline = device.create_pipeline(&PipelineDesc {
+                vertex_stride: 16,
+                attributes: vec![VertexAttribute {
+                                     format: VertexFormat::Float2,
+                                     offset: 0,
+                                 },
+                                 VertexAttribute {
+                                     format: VertexFormat::Float2,
+                                     offset: 8,
+                                 }],
+                source: None,
+                cpu_shader: Some(Rc::new(Textured)),
+                cull: Cull::Back,
+                depth_test: true,
+                depth_write: true,
+            })?;
+
+        let mut commands = CommandBuffer::new();
+        commands.begin_pass(RenderPass {
+            color: target,
+            depth: Some(depth),
+            clear_color: Some(Vec4(0.0, 0.0, 0.0, 0.0)),
+            clear_depth: Some(1.0),
+        });
+        commands.set_pipeline(pipeline);
+        commands.set_vertex_buffer(vertex_buffer);
+        commands.set_index_buffer(index_buffer, IndexFormat::U16);
+        commands.set_uniform_buffer(uniforms);
+        commands.set_texture(0, texture, sampler);
+        commands.draw_indexed(0..6);
+        commands.end_pass();
+        device.submit(&commands)?;
+        Ok(target)
+    }
+
+    #[test]
+    fn backends() {
+        let mut null = NullDevice::new();
+        render(&mut null).unwrap();
+        let commands = null.submissions()[0].commands();
+        assert_eq!(commands.len(), 8);
+        assert_eq!(commands[6], Command::DrawIndexed(0..6));
+
+        let mut soft = SoftDevice::new();
+        let target = render(&mut soft).unwrap();
+        let pixels = soft.read_texture(target).unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), &[255, 0, 255, 255]);
+        assert_eq!(pixel(3, 0), &[0, 0, 0, 255]);
+        assert_eq!(pixel(0, 3), &[0, 0, 0, 255]);
+        assert_eq!(pixel(2, 3), &[255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut soft = SoftDevice::new();
+        let target = soft.create_texture(&TextureDesc {
+                width: 4,
+                height: 4,
+                format: TextureFormat::Rgba8Unorm,
+                samples: 1,
+            })
+            .unwrap();
+        let buffer = soft.create_buffer(&BufferDesc {
+                usage: BufferUsage::Vertex,
+                size: 8,
+            })
+            .unwrap();
+        assert_eq!(soft.write_buffer(buffer, 4, &[0; 8]), Err(DeviceError::OutOfBounds));
+
+        // A pass sampling its own target.
+        let sampler = soft.create_sampler(&SamplerDesc {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_u: AddressMode::Repeat,
+                address_v: AddressMode::Repeat,
+            })
+            .unwrap();
+        let mut commands = CommandBuffer::new();
+        commands.begin_pass(RenderPass {
+            color: target,
+            depth: None,
+            clear_color: None,
+            clear_depth: None,
+        });
+        commands.set_texture(0, target, sampler);
+        commands.end_pass();
+        assert!(soft.submit(&commands).is_err());
+
+        soft.destroy_texture(target).unwrap();
+        assert_eq!(soft.read_texture(target), Err(DeviceError::InvalidHandle));
+    }
+}
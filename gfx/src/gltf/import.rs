@@ -0,0 +1,1031 @@
+use std::fs;
+use std::path::Path;
+use math::{Vec2, Vec3, Vec4};
+use super::super::{Mesh, SkinWeights};
+use super::json::{self, Json};
+use super::{Gltf, GltfError, Scene, Node, Transform, Model, Primitive, Material, AlphaMode, TextureRef,
+            TextureTransform, Texture, Filter, Wrap, Image, Skin, Animation, Channel, Keyframes, Interpolation};
+
+// The extensions which may appear in extensionsRequired.
+const SUPPORTED_EXTENSIONS: [&str; 3] = ["KHR_texture_transform", "KHR_materials_emissive_strength",
+                                         "KHR_mesh_quantization"];
+
+/// load loads the `.gltf` or `.glb` file at the given path. Buffers stored in
+/// external files are read relative to it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use fiz_gfx::gltf;
+///
+/// let doc = gltf::load("assets/Fox.glb").unwrap();
+/// for model in &doc.models {
+///     for p in &model.primitives {
+///         println!("{} triangles", p.mesh.triangle_count());
+///     }
+/// }
+/// ```
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Gltf, GltfError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|error| {
+        GltfError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    parse(&data, path.parent())
+}
+
+/// parse parses a `.gltf` or `.glb` document held in memory. Buffers stored
+/// in external files are read relative to base_dir, and are an error if it
+/// is None.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::gltf::{self, GltfError};
+///
+/// let src = r#"{
+///     "asset": {"version": "2.0"},
+///     "nodes": [{"name": "Lamp", "translation": [1, 2, 3]}],
+///     "scenes": [{"nodes": [0]}]
+/// }"#;
+/// let doc = gltf::parse(src.as_bytes(), None).unwrap();
+/// assert_eq!(doc.nodes[0].name, Some("Lamp".to_string()));
+/// match doc.nodes[0].transform {
+///     gltf::Transform::Decomposed { translation, .. } => assert_eq!(translation, Vec3(1.0, 2.0, 3.0)),
+///     _ => unreachable!(),
+/// }
+///
+/// match gltf::parse(br#"{"asset": {"version": "1.0"}}"#, None) {
+///     Err(GltfError::Unsupported(_)) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse(data: &[u8], base_dir: Option<&Path>) -> Result<Gltf, GltfError> {
+    let (text, bin) = if data.starts_with(b"glTF") {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+    let text = ::std::str::from_utf8(text).map_err(|_| {
+        GltfError::Json {
+            line: 1,
+            message: "invalid UTF-8".to_string(),
+        }
+    })?;
+    let doc = json::parse(text).map_err(|(line, message)| GltfError::Json { line, message })?;
+
+    let version = doc.get("asset").get("version").as_str().unwrap_or("");
+    if !version.starts_with("2.") {
+        return Err(GltfError::Unsupported(format!("glTF version {:?}", version)));
+    }
+    for e in array(&doc, "extensionsRequired") {
+        let name = e.as_str().unwrap_or("");
+        if !SUPPORTED_EXTENSIONS.contains(&name) {
+            return Err(GltfError::Unsupported(format!("extension {}", name)));
+        }
+    }
+
+    let mut buffers = Vec::new();
+    for (i, b) in array(&doc, "buffers").iter().enumerate() {
+        let path = format!("buffers[{}]", i);
+        let bytes = match b.get("uri").as_str() {
+            Some(uri) => read_uri(uri, base_dir, &path)?.1,
+            None if i == 0 && bin.is_some() => bin.unwrap().to_vec(),
+            None => return Err(invalid(&path, "missing uri")),
+        };
+        if bytes.len() < integer(b, "byteLength", &path)?.unwrap_or(0) {
+            return Err(invalid(&path, "shorter than byteLength"));
+        }
+        buffers.push(bytes);
+    }
+
+    let r = Reader {
+        doc: &doc,
+        buffers,
+    };
+    Ok(Gltf {
+        scenes: r.scenes()?,
+        scene: r.index(&doc, "scene", "", "scenes")?,
+        nodes: r.nodes()?,
+        models: r.models()?,
+        materials: r.materials()?,
+        textures: r.textures()?,
+        images: r.images()?,
+        skins: r.skins()?,
+        animations: r.animations()?,
+    })
+}
+
+// split_glb returns the JSON and binary chunks of a .glb file.
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    let word = |i: usize| data.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    if word(4) != Some(2) {
+        return Err(GltfError::Glb("unsupported container version"));
+    }
+    let length = word(8).ok_or(GltfError::Glb("truncated header"))?;
+    if length > data.len() {
+        return Err(GltfError::Glb("truncated file"));
+    }
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset < length {
+        let (size, kind) = match (word(offset), word(offset + 4)) {
+            (Some(s), Some(k)) => (s, k),
+            _ => return Err(GltfError::Glb("truncated chunk header")),
+        };
+        let chunk = data.get(offset + 8..offset + 8 + size).ok_or(GltfError::Glb("truncated chunk"))?;
+        chunks.push((kind, chunk));
+        offset += 8 + size;
+    }
+    match chunks.first() {
+        Some(&(0x4e4f_534a, text)) => {
+            let bin = chunks.get(1).filter(|c| c.0 == 0x004e_4942).map(|c| c.1);
+            Ok((text, bin))
+        }
+        _ => Err(GltfError::Glb("first chunk isn't JSON")),
+    }
+}
+
+// read_uri returns the MIME type (if known) and contents of a data URI or of
+// a file relative to base_dir.
+fn read_uri(uri: &str, base_dir: Option<&Path>, path: &str) -> Result<(Option<String>, Vec<u8>), GltfError> {
+    if let Some(rest) = uri.strip_prefix("data:") {
+        let comma = rest.find(',').ok_or_else(|| invalid(path, "malformed data URI"))?;
+        let (header, payload) = (&rest[..comma], &rest[comma + 1..]);
+        let header = header.strip_suffix(";base64").ok_or_else(|| invalid(path, "data URI isn't base64"))?;
+        let bytes = decode_base64(payload).ok_or_else(|| invalid(path, "invalid base64 in data URI"))?;
+        let mime = if header.is_empty() { None } else { Some(header.to_string()) };
+        return Ok((mime, bytes));
+    }
+    let dir = base_dir.ok_or_else(|| invalid(path, "external buffer without a base directory"))?;
+    let file = dir.join(decode_percent(uri));
+    let bytes = fs::read(&file).map_err(|error| GltfError::Io { path: file, error })?;
+    Ok((None, bytes))
+}
+
+// decode_base64 decodes standard base64, with or without padding.
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in s.trim_end_matches('=').bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// decode_percent decodes the percent-encoded characters of a relative URI.
+fn decode_percent(uri: &str) -> String {
+    let b = uri.as_bytes();
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        let hex = b.get(i + 1..i + 3).and_then(|h| ::std::str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(c) if b[i] == b'%' => {
+                out.push(c);
+                i += 3;
+            }
+            _ => {
+                out.push(b[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn invalid(path: &str, message: &str) -> GltfError {
+    GltfError::Invalid {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+// array returns the elements of the array member with the given key, or none
+// if it's missing.
+fn array<'a>(j: &'a Json, key: &str) -> &'a [Json] {
+    j.get(key).as_array().unwrap_or(&[])
+}
+
+fn name(j: &Json) -> Option<String> {
+    j.get("name").as_str().map(|s| s.to_string())
+}
+
+fn number(j: &Json, key: &str, default: f32) -> f32 {
+    j.get(key).as_f64().map_or(default, |n| n as f32)
+}
+
+// numbers returns the elements of the number array member with the given
+// key, or the default if it's missing.
+fn numbers(j: &Json, key: &str, path: &str, default: &[f32]) -> Result<Vec<f32>, GltfError> {
+    let v = j.get(key);
+    if v.is_null() {
+        return Ok(default.to_vec());
+    }
+    let values = v.as_array()
+        .and_then(|a| a.iter().map(|n| n.as_f64().map(|n| n as f32)).collect::<Option<Vec<_>>>())
+        .filter(|a| default.is_empty() || a.len() == default.len())
+        .ok_or_else(|| invalid(&format!("{}.{}", path, key), "invalid number array"))?;
+    Ok(values)
+}
+
+// integer returns the non-negative integer member with the given key.
+fn integer(j: &Json, key: &str, path: &str) -> Result<Option<usize>, GltfError> {
+    match *j.get(key) {
+        Json::Null => Ok(None),
+        Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n as usize)),
+        _ => Err(invalid(&format!("{}.{}", path, key), "expected a non-negative integer")),
+    }
+}
+
+fn vec3(v: &[f32]) -> Vec3<f32> {
+    Vec3(v[0], v[1], v[2])
+}
+
+fn vec4(v: &[f32]) -> Vec4<f32> {
+    Vec4(v[0], v[1], v[2], v[3])
+}
+
+fn mat4(v: &[f32]) -> [Vec4<f32>; 4] {
+    [vec4(&v[0..4]), vec4(&v[4..8]), vec4(&v[8..12]), vec4(&v[12..16])]
+}
+
+// Accessor is the decoded contents of an accessor.
+struct Accessor {
+    values: Vec<f32>,
+    components: usize,
+    count: usize,
+}
+
+struct Reader<'a> {
+    doc: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Reader<'a> {
+    // index returns the index member with the given key, checking that it
+    // refers to an element of the named top-level array.
+    fn index(&self, j: &Json, key: &str, path: &str, target: &str) -> Result<Option<usize>, GltfError> {
+        match integer(j, key, path)? {
+            Some(i) if i >= array(self.doc, target).len() => {
+                Err(invalid(&format!("{}.{}", path, key), &format!("no {} element {}", target, i)))
+            }
+            i => Ok(i),
+        }
+    }
+
+    // indices returns the elements of the index array member with the given
+    // key.
+    fn indices(&self, j: &Json, key: &str, path: &str, target: &str) -> Result<Vec<usize>, GltfError> {
+        let len = array(self.doc, target).len();
+        array(j, key)
+            .iter()
+            .map(|v| match v.as_f64() {
+                Some(n) if n >= 0.0 && n.fract() == 0.0 && (n as usize) < len => Ok(n as usize),
+                _ => Err(invalid(&format!("{}.{}", path, key), &format!("invalid {} index", target))),
+            })
+            .collect()
+    }
+
+    // view returns the bytes of a buffer view, and its stride if given.
+    fn view(&self, i: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let path = format!("bufferViews[{}]", i);
+        let v = &array(self.doc, "bufferViews")[i];
+        let buffer = self.index(v, "buffer", &path, "buffers")?.ok_or_else(|| invalid(&path, "missing buffer"))?;
+        let offset = integer(v, "byteOffset", &path)?.unwrap_or(0);
+        let length = integer(v, "byteLength", &path)?.ok_or_else(|| invalid(&path, "missing byteLength"))?;
+        let bytes = offset.checked_add(length)
+            .and_then(|end| self.buffers[buffer].get(offset..end))
+            .ok_or_else(|| invalid(&path, "outside of its buffer"))?;
+        Ok((bytes, integer(v, "byteStride", &path)?))
+    }
+
+    // accessor decodes the accessor with the given index, converting its
+    // values to floats and normalizing them if required.
+    fn accessor(&self, i: usize) -> Result<Accessor, GltfError> {
+        let path = format!("accessors[{}]", i);
+        let a = &array(self.doc, "accessors")[i];
+        let component_type = integer(a, "componentType", &path)?.unwrap_or(0);
+        let normalized = a.get("normalized").as_bool().unwrap_or(false);
+        let count = integer(a, "count", &path)?.ok_or_else(|| invalid(&path, "missing count"))?;
+        let components = match a.get("type").as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(invalid(&path, "invalid type")),
+        };
+        let size = component_size(component_type).ok_or_else(|| invalid(&path, "invalid componentType"))?;
+
+        let len = count.checked_mul(components).ok_or_else(|| invalid(&path, "count is too large"))?;
+        let view = match self.index(a, "bufferView", &path, "bufferViews")? {
+            Some(view) => {
+                let (bytes, stride) = self.view(view)?;
+                let offset = integer(a, "byteOffset", &path)?.unwrap_or(0);
+                let stride = stride.unwrap_or(components * size);
+                if stride < components * size {
+                    return Err(invalid(&path, "byteStride is smaller than an element"));
+                }
+                if !fits(bytes.len(), offset, stride, components * size, count) {
+                    return Err(invalid(&path, "outside of its buffer view"));
+                }
+                Some((bytes, offset, stride))
+            }
+            None => None,
+        };
+        let mut values = vec![0.0; len];
+        if let Some((bytes, offset, stride)) = view {
+            read(bytes, offset, stride, component_type, normalized, components, &mut values)
+                .ok_or_else(|| invalid(&path, "outside of its buffer view"))?;
+        }
+
+        let sparse = a.get("sparse");
+        if !sparse.is_null() {
+            let path = format!("{}.sparse", path);
+            let n = integer(sparse, "count", &path)?.unwrap_or(0);
+            let read_part = |key: &str, component_type: usize, components: usize| {
+                let path = format!("{}.{}", path, key);
+                let part = sparse.get(key);
+                let view = self.index(part, "bufferView", &path, "bufferViews")?
+                    .ok_or_else(|| invalid(&path, "missing bufferView"))?;
+                let (bytes, _) = self.view(view)?;
+                let offset = integer(part, "byteOffset", &path)?.unwrap_or(0);
+                let size = component_size(component_type).ok_or_else(|| invalid(&path, "invalid componentType"))?;
+                if !fits(bytes.len(), offset, components * size, components * size, n) {
+                    return Err(invalid(&path, "outside of its buffer view"));
+                }
+                let mut out = vec![0.0; n * components];
+                read(bytes, offset, components * size, component_type, normalized && key == "values", components,
+                     &mut out)
+                    .ok_or_else(|| invalid(&path, "outside of its buffer view"))?;
+                Ok(out)
+            };
+            let index_type = integer(sparse.get("indices"), "componentType", &path)?.unwrap_or(0);
+            let indices = read_part("indices", index_type, 1)?;
+            let replacements = read_part("values", component_type, components)?;
+            for (k, &index) in indices.iter().enumerate() {
+                let index = index as usize;
+                if index >= count {
+                    return Err(invalid(&path, "index out of range"));
+                }
+                values[index * components..(index + 1) * components]
+                    .copy_from_slice(&replacements[k * components..(k + 1) * components]);
+            }
+        }
+        Ok(Accessor {
+            values,
+            components,
+            count,
+        })
+    }
+
+    // attribute decodes the accessor with the given index, checking that it
+    // has one of the allowed numbers of components and the expected count.
+    fn attribute(&self, i: usize, allowed: &[usize], count: Option<usize>, path: &str)
+                 -> Result<Accessor, GltfError> {
+        let a = self.accessor(i)?;
+        if !allowed.contains(&a.components) {
+            return Err(invalid(path, "unexpected accessor type"));
+        }
+        if count.is_some_and(|c| c != a.count) {
+            return Err(invalid(path, "accessor count doesn't match"));
+        }
+        Ok(a)
+    }
+
+    fn scenes(&self) -> Result<Vec<Scene>, GltfError> {
+        array(self.doc, "scenes")
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                Ok(Scene {
+                    name: name(s),
+                    nodes: self.indices(s, "nodes", &format!("scenes[{}]", i), "nodes")?,
+                })
+            })
+            .collect()
+    }
+
+    fn nodes(&self) -> Result<Vec<Node>, GltfError> {
+        array(self.doc, "nodes")
+            .iter()
+            .enumerate()
+            .map(|(i, n)| {
+                let path = format!("nodes[{}]", i);
+                let transform = if n.get("matrix").is_null() {
+                    Transform::Decomposed {
+                        translation: vec3(&numbers(n, "translation", &path, &[0.0; 3])?),
+                        rotation: vec4(&numbers(n, "rotation", &path, &[0.0, 0.0, 0.0, 1.0])?),
+                        scale: vec3(&numbers(n, "scale", &path, &[1.0; 3])?),
+                    }
+                } else {
+                    Transform::Matrix(mat4(&numbers(n, "matrix", &path, &[0.0; 16])?))
+                };
+                Ok(Node {
+                    name: name(n),
+                    children: self.indices(n, "children", &path, "nodes")?,
+                    transform,
+                    model: self.index(n, "mesh", &path, "meshes")?,
+                    skin: self.index(n, "skin", &path, "skins")?,
+                    weights: numbers(n, "weights", &path, &[])?,
+                })
+            })
+            .collect()
+    }
+
+    fn models(&self) -> Result<Vec<Model>, GltfError> {
+        let mut models = Vec::new();
+        for (i, m) in array(self.doc, "meshes").iter().enumerate() {
+            let path = format!("meshes[{}]", i);
+            let mut primitives = Vec::new();
+            for (j, p) in array(m, "primitives").iter().enumerate() {
+                if let Some(p) = self.primitive(p, &format!("{}.primitives[{}]", path, j))? {
+                    primitives.push(p);
+                }
+            }
+            models.push(Model {
+                name: name(m),
+                primitives,
+                weights: numbers(m, "weights", &path, &[])?,
+            });
+        }
+        Ok(models)
+    }
+
+    // primitive converts a primitive into a triangle mesh, or returns None
+    // if it's made of points or lines.
+    fn primitive(&self, p: &Json, path: &str) -> Result<Option<Primitive>, GltfError> {
+        let mode = integer(p, "mode", path)?.unwrap_or(4);
+        if mode < 4 {
+            return Ok(None);
+        } else if mode > 6 {
+            return Err(invalid(path, "invalid mode"));
+        }
+        let attributes = p.get("attributes");
+        let path = &format!("{}.attributes", path);
+        let get = |key: &str| self.index(attributes, key, path, "accessors");
+
+        let position = get("POSITION")?.ok_or_else(|| invalid(path, "missing POSITION"))?;
+        let position = self.attribute(position, &[3], None, path)?;
+        let n = Some(position.count);
+        let mut mesh = Mesh::new(position.values.chunks(3).map(vec3).collect(), Vec::new());
+        if let Some(a) = get("NORMAL")? {
+            mesh.normals = Some(self.attribute(a, &[3], n, path)?.values.chunks(3).map(vec3).collect());
+        }
+        if let Some(a) = get("TANGENT")? {
+            mesh.tangents = Some(self.attribute(a, &[4], n, path)?.values.chunks(4).map(vec4).collect());
+        }
+        if let Some(a) = get("TEXCOORD_0")? {
+            let uvs = self.attribute(a, &[2], n, path)?.values;
+            mesh.uvs = Some(uvs.chunks(2).map(|v| Vec2(v[0], v[1])).collect());
+        }
+        if let Some(a) = get("COLOR_0")? {
+            let colors = self.attribute(a, &[3, 4], n, path)?;
+            mesh.colors = Some(match colors.components {
+                3 => colors.values.chunks(3).map(|v| Vec4(v[0], v[1], v[2], 1.0)).collect(),
+                _ => colors.values.chunks(4).map(vec4).collect(),
+            });
+        }
+        if let (Some(j), Some(w)) = (get("JOINTS_0")?, get("WEIGHTS_0")?) {
+            let joints = self.attribute(j, &[4], n, path)?.values;
+            let weights = self.attribute(w, &[4], n, path)?.values;
+            mesh.skin = Some(joints.chunks(4)
+                .zip(weights.chunks(4))
+                .map(|(j, w)| {
+                    SkinWeights {
+                        joints: [j[0] as u16, j[1] as u16, j[2] as u16, j[3] as u16],
+                        weights: [w[0], w[1], w[2], w[3]],
+                    }
+                })
+                .collect());
+        }
+
+        let vertices: Vec<u32> = match self.index(p, "indices", path, "accessors")? {
+            Some(a) => self.attribute(a, &[1], None, path)?.values.iter().map(|&i| i as u32).collect(),
+            None => (0..position.count as u32).collect(),
+        };
+        if vertices.iter().any(|&v| v as usize >= position.count) {
+            return Err(invalid(path, "vertex index out of range"));
+        }
+        let v = &vertices;
+        mesh.indices = match mode {
+            4 => v.chunks(3).filter(|t| t.len() == 3).map(|t| [t[0], t[1], t[2]]).collect(),
+            5 => {
+                (0..v.len().saturating_sub(2))
+                    .map(|i| if i % 2 == 0 { [v[i], v[i + 1], v[i + 2]] } else { [v[i + 1], v[i], v[i + 2]] })
+                    .collect()
+            }
+            _ => (1..v.len().saturating_sub(1)).map(|i| [v[0], v[i], v[i + 1]]).collect(),
+        };
+        Ok(Some(Primitive {
+            mesh,
+            material: self.index(p, "material", path, "materials")?,
+        }))
+    }
+
+    // texture_ref reads the texture info member with the given key.
+    fn texture_ref(&self, j: &Json, key: &str, path: &str) -> Result<Option<TextureRef>, GltfError> {
+        let t = j.get(key);
+        if t.is_null() {
+            return Ok(None);
+        }
+        let path = &format!("{}.{}", path, key);
+        let texture = self.index(t, "index", path, "textures")?.ok_or_else(|| invalid(path, "missing index"))?;
+        let mut tex_coord = integer(t, "texCoord", path)?.unwrap_or(0) as u32;
+        let transform = t.get("extensions").get("KHR_texture_transform");
+        let transform = if transform.is_null() {
+            None
+        } else {
+            if let Some(n) = integer(transform, "texCoord", path)? {
+                tex_coord = n as u32;
+            }
+            let offset = numbers(transform, "offset", path, &[0.0, 0.0])?;
+            let scale = numbers(transform, "scale", path, &[1.0, 1.0])?;
+            Some(TextureTransform {
+                offset: Vec2(offset[0], offset[1]),
+                rotation: number(transform, "rotation", 0.0),
+                scale: Vec2(scale[0], scale[1]),
+            })
+        };
+        Ok(Some(TextureRef {
+            texture,
+            tex_coord,
+            transform,
+        }))
+    }
+
+    fn materials(&self) -> Result<Vec<Material>, GltfError> {
+        array(self.doc, "materials")
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let path = &format!("materials[{}]", i);
+                let pbr = m.get("pbrMetallicRoughness");
+                let pbr_path = &format!("{}.pbrMetallicRoughness", path);
+                let strength = number(m.get("extensions").get("KHR_materials_emissive_strength"),
+                                      "emissiveStrength",
+                                      1.0);
+                let alpha_mode = match m.get("alphaMode").as_str() {
+                    None | Some("OPAQUE") => AlphaMode::Opaque,
+                    Some("MASK") => AlphaMode::Mask(number(m, "alphaCutoff", 0.5)),
+                    Some("BLEND") => AlphaMode::Blend,
+                    Some(_) => return Err(invalid(path, "invalid alphaMode")),
+                };
+                Ok(Material {
+                    name: name(m),
+                    base_color: vec4(&numbers(pbr, "baseColorFactor", pbr_path, &[1.0; 4])?),
+                    base_color_texture: self.texture_ref(pbr, "baseColorTexture", pbr_path)?,
+                    metallic: number(pbr, "metallicFactor", 1.0),
+                    roughness: number(pbr, "roughnessFactor", 1.0),
+                    metallic_roughness_texture: self.texture_ref(pbr, "metallicRoughnessTexture", pbr_path)?,
+                    normal_texture: self.texture_ref(m, "normalTexture", path)?,
+                    normal_scale: number(m.get("normalTexture"), "scale", 1.0),
+                    occlusion_texture: self.texture_ref(m, "occlusionTexture", path)?,
+                    occlusion_strength: number(m.get("occlusionTexture"), "strength", 1.0),
+                    emissive: vec3(&numbers(m, "emissiveFactor", path, &[0.0; 3])?).mul_scalar(strength),
+                    emissive_texture: self.texture_ref(m, "emissiveTexture", path)?,
+                    alpha_mode,
+                    double_sided: m.get("doubleSided").as_bool().unwrap_or(false),
+                })
+            })
+            .collect()
+    }
+
+    fn textures(&self) -> Result<Vec<Texture>, GltfError> {
+        array(self.doc, "textures")
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let path = &format!("textures[{}]", i);
+                let sampler = match self.index(t, "sampler", path, "samplers")? {
+                    Some(s) => &array(self.doc, "samplers")[s],
+                    None => &Json::Null,
+                };
+                let path = &format!("{}.sampler", path);
+                let filter = |key: &str| {
+                    Ok(match integer(sampler, key, path)? {
+                        None => None,
+                        Some(9728) => Some(Filter::Nearest),
+                        Some(9729) => Some(Filter::Linear),
+                        Some(9984) if key == "minFilter" => Some(Filter::NearestMipmapNearest),
+                        Some(9985) if key == "minFilter" => Some(Filter::LinearMipmapNearest),
+                        Some(9986) if key == "minFilter" => Some(Filter::NearestMipmapLinear),
+                        Some(9987) if key == "minFilter" => Some(Filter::LinearMipmapLinear),
+                        Some(_) => return Err(invalid(path, &format!("invalid {}", key))),
+                    })
+                };
+                let wrap = |key: &str| {
+                    Ok(match integer(sampler, key, path)? {
+                        None | Some(10497) => Wrap::Repeat,
+                        Some(33071) => Wrap::ClampToEdge,
+                        Some(33648) => Wrap::MirroredRepeat,
+                        Some(_) => return Err(invalid(path, &format!("invalid {}", key))),
+                    })
+                };
+                Ok(Texture {
+                    name: name(t),
+                    image: self.index(t, "source", path, "images")?,
+                    mag_filter: filter("magFilter")?,
+                    min_filter: filter("minFilter")?,
+                    wrap_s: wrap("wrapS")?,
+                    wrap_t: wrap("wrapT")?,
+                })
+            })
+            .collect()
+    }
+
+    fn images(&self) -> Result<Vec<Image>, GltfError> {
+        array(self.doc, "images")
+            .iter()
+            .enumerate()
+            .map(|(i, im)| {
+                let path = &format!("images[{}]", i);
+                let mut image = Image {
+                    name: name(im),
+                    mime_type: im.get("mimeType").as_str().map(|s| s.to_string()),
+                    uri: None,
+                    data: None,
+                };
+                if let Some(view) = self.index(im, "bufferView", path, "bufferViews")? {
+                    image.data = Some(self.view(view)?.0.to_vec());
+                } else if let Some(uri) = im.get("uri").as_str() {
+                    if uri.starts_with("data:") {
+                        let (mime, data) = read_uri(uri, None, path)?;
+                        image.mime_type = image.mime_type.or(mime);
+                        image.data = Some(data);
+                    } else {
+                        image.uri = Some(decode_percent(uri));
+                    }
+                }
+                Ok(image)
+            })
+            .collect()
+    }
+
+    fn skins(&self) -> Result<Vec<Skin>, GltfError> {
+        array(self.doc, "skins")
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let path = &format!("skins[{}]", i);
+                let joints = self.indices(s, "joints", path, "nodes")?;
+                let inverse_bind_matrices = match self.index(s, "inverseBindMatrices", path, "accessors")? {
+                    Some(a) => {
+                        self.attribute(a, &[16], Some(joints.len()), path)?.values.chunks(16).map(mat4).collect()
+                    }
+                    None => {
+                        let identity = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+                        vec![mat4(&identity); joints.len()]
+                    }
+                };
+                Ok(Skin {
+                    name: name(s),
+                    joints,
+                    inverse_bind_matrices,
+                    skeleton: self.index(s, "skeleton", path, "nodes")?,
+                })
+            })
+            .collect()
+    }
+
+    fn animations(&self) -> Result<Vec<Animation>, GltfError> {
+        let mut animations = Vec::new();
+        for (i, a) in array(self.doc, "animations").iter().enumerate() {
+            let samplers = array(a, "samplers");
+            let mut channels = Vec::new();
+            for (j, c) in array(a, "channels").iter().enumerate() {
+                let path = &format!("animations[{}].channels[{}]", i, j);
+                let target = c.get("target");
+                let node = match self.index(target, "node", path, "nodes")? {
+                    Some(n) => n,
+                    // The target is defined by an extension.
+                    None => continue,
+                };
+                let sampler = integer(c, "sampler", path)?
+                    .and_then(|s| samplers.get(s))
+                    .ok_or_else(|| invalid(path, "invalid sampler"))?;
+                let path = &format!("animations[{}].samplers[{}]", i, integer(c, "sampler", path)?.unwrap());
+                let interpolation = match sampler.get("interpolation").as_str() {
+                    None | Some("LINEAR") => Interpolation::Linear,
+                    Some("STEP") => Interpolation::Step,
+                    Some("CUBICSPLINE") => Interpolation::CubicSpline,
+                    Some(_) => return Err(invalid(path, "invalid interpolation")),
+                };
+                let input = self.index(sampler, "input", path, "accessors")?
+                    .ok_or_else(|| invalid(path, "missing input"))?;
+                let output = self.index(sampler, "output", path, "accessors")?
+                    .ok_or_else(|| invalid(path, "missing output"))?;
+                let times = self.attribute(input, &[1], None, path)?.values;
+                let per_key = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+                let count = Some(times.len() * per_key);
+                let values = |components| self.attribute(output, &[components], count, path).map(|a| a.values);
+                let keyframes = match target.get("path").as_str() {
+                    Some("translation") => Keyframes::Translation(values(3)?.chunks(3).map(vec3).collect()),
+                    Some("rotation") => Keyframes::Rotation(values(4)?.chunks(4).map(vec4).collect()),
+                    Some("scale") => Keyframes::Scale(values(3)?.chunks(3).map(vec3).collect()),
+                    Some("weights") => Keyframes::Weights(self.attribute(output, &[1], None, path)?.values),
+                    _ => return Err(invalid(path, "invalid target path")),
+                };
+                channels.push(Channel {
+                    node,
+                    times,
+                    keyframes,
+                    interpolation,
+                });
+            }
+            animations.push(Animation {
+                name: name(a),
+                channels,
+            });
+        }
+        Ok(animations)
+    }
+}
+
+fn component_size(component_type: usize) -> Option<usize> {
+    match component_type {
+        5120 | 5121 => Some(1),
+        5122 | 5123 => Some(2),
+        5125 | 5126 => Some(4),
+        _ => None,
+    }
+}
+
+// fits reports whether count elements of the given size, stride bytes
+// apart from offset, fit in len bytes.
+fn fits(len: usize, offset: usize, stride: usize, size: usize, count: usize) -> bool {
+    count == 0 ||
+    (count - 1)
+        .checked_mul(stride)
+        .and_then(|n| n.checked_add(offset))
+        .and_then(|n| n.checked_add(size))
+        .is_some_and(|end| end <= len)
+}
+
+// read decodes out.len() / components elements of the given component type
+// from bytes, starting at offset and stride bytes apart. It returns None if
+// they don't all fit.
+fn read(bytes: &[u8], offset: usize, stride: usize, component_type: usize, normalized: bool, components: usize,
+        out: &mut [f32])
+        -> Option<()> {
+    let size = component_size(component_type)?;
+    for (i, element) in out.chunks_mut(components).enumerate() {
+        for (k, v) in element.iter_mut().enumerate() {
+            let start = offset + i * stride + k * size;
+            let b = bytes.get(start..start + size)?;
+            *v = match (component_type, normalized) {
+                (5120, false) => b[0] as i8 as f32,
+                (5120, true) => (b[0] as i8 as f32 / 127.0).max(-1.0),
+                (5121, false) => b[0] as f32,
+                (5121, true) => b[0] as f32 / 255.0,
+                (5122, false) => i16::from_le_bytes([b[0], b[1]]) as f32,
+                (5122, true) => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+                (5123, false) => u16::from_le_bytes([b[0], b[1]]) as f32,
+                (5123, true) => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+                (5125, _) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            };
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+    use math::{Vec2, Vec3, Vec4};
+    use super::super::{load, parse, GltfError, Transform, AlphaMode, Filter, Wrap, Keyframes, Interpolation};
+    use super::decode_base64;
+
+    fn model(name: &str, ext: &str) -> String {
+        format!("{}/testdata/gltf/{}/{}.{}", env!("CARGO_MANIFEST_DIR"), name, name, ext)
+    }
+
+    // khronos returns the path of a Khronos sample model, see
+    // testdata/gltf/README.md.
+    fn khronos(name: &str) -> String {
+        format!("{}/testdata/gltf/khronos/{}/glTF/{}.gltf", env!("CARGO_MANIFEST_DIR"), name, name)
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVsbG8").unwrap(), b"hello");
+        assert!(decode_base64("a$b").is_none());
+    }
+
+    #[test]
+    fn triangle() {
+        let doc = load(model("Triangle", "gltf")).unwrap();
+        assert_eq!(doc.scene, Some(0));
+        assert_eq!(doc.scenes[0].nodes, vec![0]);
+        assert_eq!(doc.nodes[0].model, Some(0));
+        let mesh = &doc.models[0].primitives[0].mesh;
+        assert_eq!(mesh.positions, vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)]);
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn oversized_accessors() {
+        // Counts, strides and sparse counts which don't fit in the buffer
+        // view are rejected before anything is allocated for them.
+        let doc = |accessor: &str| {
+            format!(r#"{{"asset": {{"version": "2.0"}},
+                "buffers": [{{"byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA"}}],
+                "bufferViews": [{{"buffer": 0, "byteLength": 12}}],
+                "accessors": [{}],
+                "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}]}}"#,
+                    accessor)
+        };
+        for accessor in &[r#"{"bufferView": 0, "componentType": 5126, "count": 1e18, "type": "VEC3"}"#,
+                          r#"{"bufferView": 0, "componentType": 5126, "count": 1e30, "type": "MAT4"}"#,
+                          r#"{"bufferView": 0, "byteOffset": 1e30, "componentType": 5126, "count": 1, "type": "VEC3"}"#,
+                          r#"{"componentType": 5126, "count": 1, "type": "VEC3",
+                              "sparse": {"count": 1e18, "indices": {"bufferView": 0, "componentType": 5125},
+                                         "values": {"bufferView": 0}}}"#] {
+            match parse(doc(accessor).as_bytes(), None) {
+                Err(GltfError::Invalid { ref path, .. }) if path.starts_with("accessors[0]") => {}
+                other => panic!("{}: {:?}", accessor, other),
+            }
+        }
+    }
+
+    #[test]
+    fn sparse_accessor() {
+        let doc = load(model("SimpleSparseAccessor", "gltf")).unwrap();
+        let mesh = &doc.models[0].primitives[0].mesh;
+        assert!(mesh.validate().is_ok());
+        assert_eq!(mesh.triangle_count(), 12);
+        for (i, &p) in mesh.positions.iter().enumerate() {
+            let expected = match i {
+                8 => Vec3(1.0, 2.0, 0.0),
+                10 => Vec3(3.0, 3.0, 0.0),
+                12 => Vec3(5.0, 4.0, 0.0),
+                _ => Vec3((i % 7) as f32, (i / 7) as f32, 0.0),
+            };
+            assert_eq!(p, expected);
+        }
+    }
+
+    #[test]
+    fn skin_and_animation() {
+        let doc = load(model("SimpleSkin", "gltf")).unwrap();
+        assert_eq!(doc.nodes[0].skin, Some(0));
+        assert_eq!(doc.nodes[1].children, vec![2]);
+        let skin = &doc.skins[0];
+        assert_eq!(skin.joints, vec![1, 2]);
+        assert_eq!(skin.inverse_bind_matrices[0][0], Vec4(1.0, 0.0, 0.0, 0.0));
+        assert_eq!(skin.inverse_bind_matrices[1][3], Vec4(-0.5, -1.0, 0.0, 1.0));
+
+        let mesh = &doc.models[0].primitives[0].mesh;
+        let weights = mesh.skin.as_ref().unwrap();
+        assert_eq!(weights.len(), mesh.vertex_count());
+        for (p, w) in mesh.positions.iter().zip(weights) {
+            assert_eq!(w.joints, [0, 1, 0, 0]);
+            let y = p.1 * 2.0;
+            assert!((w.weights[0] - (1.0 - y / 4.0)).abs() < 1e-6 && (w.weights[1] - y / 4.0).abs() < 1e-6);
+        }
+
+        let channel = &doc.animations[0].channels[0];
+        assert_eq!(channel.node, 2);
+        assert_eq!(channel.interpolation, Interpolation::Linear);
+        assert_eq!(channel.times, vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+        match channel.keyframes {
+            Keyframes::Rotation(ref r) => {
+                let half = PI / 8.0;
+                assert!(r[1].almost_equal(Vec4(0.0, 0.0, half.sin(), half.cos()), 1e-6));
+            }
+            ref other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn glb_material() {
+        let doc = load(model("TexturedBox", "glb")).unwrap();
+        assert_eq!(doc.nodes[0].name, Some("Root".to_string()));
+        match doc.nodes[0].transform {
+            Transform::Matrix(m) => assert_eq!(m[3], Vec4(0.0, 0.0, 2.0, 1.0)),
+            other => panic!("{:?}", other),
+        }
+        match doc.nodes[1].transform {
+            Transform::Decomposed { scale, .. } => assert_eq!(scale, Vec3(2.0, 2.0, 2.0)),
+            other => panic!("{:?}", other),
+        }
+
+        // Interleaved attributes.
+        let mesh = &doc.models[0].primitives[0].mesh;
+        assert!(mesh.validate().is_ok());
+        assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (24, 12));
+        for (p, n) in mesh.positions.iter().zip(mesh.normals.as_ref().unwrap()) {
+            assert_eq!(p.length_sq(), 0.75);
+            assert_eq!(n.dot(*p), 0.5);
+        }
+
+        let m = &doc.materials[doc.models[0].primitives[0].material.unwrap()];
+        assert_eq!(m.base_color, Vec4(0.8, 0.0, 0.0, 1.0));
+        assert_eq!((m.metallic, m.roughness), (0.25, 0.5));
+        assert_eq!(m.emissive, Vec3(4.0, 2.0, 0.0));
+        assert_eq!(m.alpha_mode, AlphaMode::Mask(0.25));
+        assert!(m.double_sided);
+        let base = m.base_color_texture.unwrap();
+        let t = base.transform.unwrap();
+        assert_eq!((t.offset, t.scale), (Vec2(0.5, 0.0), Vec2(0.5, 2.0)));
+        assert!(t.apply(Vec2(1.0, 0.0)).almost_equal(Vec2(0.5, -0.5), 1e-3));
+
+        let texture = &doc.textures[base.texture];
+        assert_eq!((texture.mag_filter, texture.min_filter), (Some(Filter::Nearest), Some(Filter::LinearMipmapLinear)));
+        assert_eq!((texture.wrap_s, texture.wrap_t), (Wrap::ClampToEdge, Wrap::MirroredRepeat));
+        let image = &doc.images[texture.image.unwrap()];
+        assert_eq!(image.mime_type, Some("image/png".to_string()));
+        assert!(image.data.as_ref().unwrap().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    #[ignore = "needs the Khronos sample models, see testdata/gltf/README.md"]
+    fn khronos_box_textured() {
+        let doc = load(khronos("BoxTextured")).unwrap();
+        let primitive = &doc.models[0].primitives[0];
+        let mesh = &primitive.mesh;
+        assert!(mesh.validate().is_ok());
+        assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (24, 12));
+        assert!(mesh.normals.is_some() && mesh.uvs.is_some());
+
+        let base = doc.materials[primitive.material.unwrap()].base_color_texture.unwrap();
+        let image = &doc.images[doc.textures[base.texture].image.unwrap()];
+        assert_eq!(image.uri, Some("CesiumLogoFlat.png".to_string()));
+    }
+
+    #[test]
+    #[ignore = "needs the Khronos sample models, see testdata/gltf/README.md"]
+    fn khronos_simple_skin() {
+        let doc = load(khronos("SimpleSkin")).unwrap();
+        assert_eq!(doc.nodes[0].skin, Some(0));
+        assert_eq!(doc.nodes[1].children, vec![2]);
+        assert_eq!(doc.skins[0].joints, vec![1, 2]);
+
+        let mesh = &doc.models[0].primitives[0].mesh;
+        assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (10, 8));
+        let weights = mesh.skin.as_ref().unwrap();
+        assert_eq!(weights.len(), mesh.vertex_count());
+        for w in weights {
+            assert_eq!(w.joints[..2], [0, 1]);
+            assert!((w.weights.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        }
+
+        let channel = &doc.animations[0].channels[0];
+        assert_eq!(channel.node, 2);
+        match channel.keyframes {
+            Keyframes::Rotation(ref r) => assert_eq!(r.len(), channel.times.len()),
+            ref other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the Khronos sample models, see testdata/gltf/README.md"]
+    fn khronos_simple_sparse_accessor() {
+        let doc = load(khronos("SimpleSparseAccessor")).unwrap();
+        let mesh = &doc.models[0].primitives[0].mesh;
+        assert!(mesh.validate().is_ok());
+        assert_eq!((mesh.vertex_count(), mesh.triangle_count()), (14, 12));
+        for (i, &p) in mesh.positions.iter().enumerate() {
+            let expected = match i {
+                8 => Vec3(1.0, 2.0, 0.0),
+                10 => Vec3(3.0, 3.0, 0.0),
+                12 => Vec3(5.0, 4.0, 0.0),
+                _ => Vec3((i % 7) as f32, (i / 7) as f32, 0.0),
+            };
+            assert_eq!(p, expected);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the Khronos sample models, see testdata/gltf/README.md"]
+    fn khronos_texture_transform() {
+        // The model's materials offset, rotate and scale their textures.
+        let doc = load(khronos("TextureTransformTest")).unwrap();
+        let transforms: Vec<_> =
+            doc.materials.iter().filter_map(|m| m.base_color_texture.and_then(|t| t.transform)).collect();
+        assert!(transforms.iter().any(|t| t.offset != Vec2(0.0, 0.0)));
+        assert!(transforms.iter().any(|t| t.rotation != 0.0));
+        assert!(transforms.iter().any(|t| t.scale != Vec2(1.0, 1.0)));
+    }
+}
@@ -0,0 +1,244 @@
+// A minimal JSON parser, sufficient for reading glTF documents.
+
+use std::char;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+static NULL: Json = Json::Null;
+
+impl Json {
+    // get returns the member of the object with the given key, or null.
+    pub fn get(&self, key: &str) -> &Json {
+        match *self {
+            Json::Object(ref members) => members.iter().find(|m| m.0 == key).map_or(&NULL, |m| &m.1),
+            _ => &NULL,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Json::Null
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match *self {
+            Json::Array(ref a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+// parse parses the JSON text, returning the line number and a description
+// of the first error.
+pub fn parse(src: &str) -> Result<Json, (usize, String)> {
+    let mut p = Parser {
+        src: src.as_bytes(),
+        pos: 0,
+    };
+    let v = p.value().map_err(|e| (p.line(), e))?;
+    p.space();
+    if p.pos != p.src.len() {
+        return Err((p.line(), "trailing characters after value".to_string()));
+    }
+    Ok(v)
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        1 + self.src[..self.pos.min(self.src.len())].iter().filter(|&&c| c == b'\n').count()
+    }
+
+    fn space(&mut self) {
+        while self.pos < self.src.len() && (self.src[self.pos] as char).is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).cloned()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.space();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", c as char))
+        }
+    }
+
+    fn literal(&mut self, word: &str, v: Json) -> Result<Json, String> {
+        if self.src[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            Ok(v)
+        } else {
+            Err("invalid literal".to_string())
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.space();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.space();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.space();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+                    self.space();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(members));
+                        }
+                        _ => return Err("expected ',' or '}'".to_string()),
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.space();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    self.space();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array(items));
+                        }
+                        _ => return Err("expected ',' or ']'".to_string()),
+                    }
+                }
+            }
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err("unexpected character".to_string()),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || b"+-.eE".contains(&c)) {
+            self.pos += 1;
+        }
+        let text = ::std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+        text.parse().map(Json::Number).map_err(|_| format!("invalid number {:?}", text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.peek() != Some(b'"') {
+            return Err("expected string".to_string());
+        }
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let c = self.peek().ok_or("unterminated string")?;
+            self.pos += 1;
+            match c {
+                b'"' => return String::from_utf8(out).map_err(|_| "invalid UTF-8 in string".to_string()),
+                b'\\' => {
+                    let e = self.peek().ok_or("unterminated string")?;
+                    self.pos += 1;
+                    let decoded = match e {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            if (0xd800..0xdc00).contains(&code) && self.src[self.pos..].starts_with(b"\\u") {
+                                // A surrogate pair.
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            char::from_u32(code).ok_or("invalid unicode escape")?
+                        }
+                        _ => return Err("invalid escape".to_string()),
+                    };
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(decoded.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => out.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.src.get(self.pos..self.pos + 4).ok_or("invalid unicode escape")?;
+        self.pos += 4;
+        let text = ::std::str::from_utf8(digits).map_err(|_| "invalid unicode escape")?;
+        u32::from_str_radix(text, 16).map_err(|_| "invalid unicode escape".to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Json};
+
+    #[test]
+    fn values() {
+        let v = parse(r#" {"a": [1, -2.5e1, true, null], "b": {"c": "x\"é😀"}, "d": []} "#).unwrap();
+        assert_eq!(v.get("a").as_array().unwrap()[1], Json::Number(-25.0));
+        assert_eq!(v.get("b").get("c").as_str(), Some("x\"é😀"));
+        assert!(v.get("missing").is_null());
+        assert_eq!(parse("{\n\"a\": 1,\n}").unwrap_err().0, 3);
+        assert!(parse("[1] 2").is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! glTF 2.0 import.
+//!
+//! `load` reads `.gltf` files (with external or data URI buffers) and binary
+//! `.glb` files into a `Gltf` document: the node hierarchy, triangle meshes
+//! with all of their attributes, metallic-roughness materials, textures,
+//! skins and keyframe animations. Sparse accessors are supported, as are the
+//! `KHR_texture_transform`, `KHR_materials_emissive_strength` and
+//! `KHR_mesh_quantization` extensions.
+//!
+//! Objects refer to each other by their index in the document, as in the
+//! glTF format itself. Coordinates are in glTF's right-handed, Y-up system,
+//! in meters.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use math::{Vec2, Vec3, Vec4};
+use super::Mesh;
+
+mod json;
+mod import;
+
+pub use self::import::{load, parse};
+
+/// GltfError describes a failure to load a glTF document.
+#[derive(Debug)]
+pub enum GltfError {
+    /// A file couldn't be read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// The JSON of the document is malformed.
+    Json {
+        /// The line number of the error, starting at one.
+        line: usize,
+
+        /// A description of the error.
+        message: String,
+    },
+
+    /// The binary container of a `.glb` file is malformed.
+    Glb(&'static str),
+
+    /// A property of the document is invalid.
+    Invalid {
+        /// The location of the property, e.g. `accessors[2].count`.
+        path: String,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// The document requires a feature which isn't supported, such as an
+    /// extension.
+    Unsupported(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GltfError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            GltfError::Json { line, ref message } => write!(f, "line {}: {}", line, message),
+            GltfError::Glb(message) => write!(f, "invalid glb: {}", message),
+            GltfError::Invalid { ref path, ref message } => write!(f, "{}: {}", path, message),
+            GltfError::Unsupported(ref what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GltfError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Gltf is an imported glTF document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Gltf {
+    /// The scenes of the document.
+    pub scenes: Vec<Scene>,
+
+    /// The index of the scene to display by default, if any.
+    pub scene: Option<usize>,
+
+    /// The nodes of the document.
+    pub nodes: Vec<Node>,
+
+    /// The meshes of the document.
+    pub models: Vec<Model>,
+
+    /// The materials of the document.
+    pub materials: Vec<Material>,
+
+    /// The textures of the document.
+    pub textures: Vec<Texture>,
+
+    /// The images of the document.
+    pub images: Vec<Image>,
+
+    /// The skins of the document.
+    pub skins: Vec<Skin>,
+
+    /// The animations of the document.
+    pub animations: Vec<Animation>,
+}
+
+/// Scene is a set of root nodes to display together.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scene {
+    /// The name of the scene, if any.
+    pub name: Option<String>,
+
+    /// The indices of the root nodes of the scene.
+    pub nodes: Vec<usize>,
+}
+
+/// Transform is the local transform of a node, relative to its parent.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Transform {
+    /// A column-major affine matrix.
+    Matrix([Vec4<f32>; 4]),
+
+    /// A translation, rotation and scale, applied in reverse order.
+    Decomposed {
+        /// The translation.
+        translation: Vec3<f32>,
+
+        /// The rotation, as a unit quaternion `(x, y, z, w)`.
+        rotation: Vec4<f32>,
+
+        /// The scale along each axis.
+        scale: Vec3<f32>,
+    },
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::Decomposed {
+            translation: Vec3(0.0, 0.0, 0.0),
+            rotation: Vec4(0.0, 0.0, 0.0, 1.0),
+            scale: Vec3(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Node is an element of the node hierarchy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Node {
+    /// The name of the node, if any.
+    pub name: Option<String>,
+
+    /// The indices of the children of the node.
+    pub children: Vec<usize>,
+
+    /// The transform of the node relative to its parent.
+    pub transform: Transform,
+
+    /// The index of the model instantiated by the node, if any.
+    pub model: Option<usize>,
+
+    /// The index of the skin deforming the node's model, if any.
+    pub skin: Option<usize>,
+
+    /// The morph target weights of the node's model.
+    pub weights: Vec<f32>,
+}
+
+/// Model is a glTF mesh: a set of triangle meshes which are drawn together,
+/// each with its own material.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Model {
+    /// The name of the model, if any.
+    pub name: Option<String>,
+
+    /// The primitives of the model. Primitives which aren't made of
+    /// triangles (points and lines) are omitted.
+    pub primitives: Vec<Primitive>,
+
+    /// The default morph target weights of the model.
+    pub weights: Vec<f32>,
+}
+
+/// Primitive is a triangle mesh of a model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Primitive {
+    /// The triangles and vertex attributes. Only the first set of texture
+    /// coordinates, colors and skin weights is imported.
+    pub mesh: Mesh,
+
+    /// The index of the material of the primitive, if any.
+    pub material: Option<usize>,
+}
+
+/// AlphaMode describes how the alpha of a material's base color is used.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Alpha is ignored and the surface is opaque.
+    Opaque,
+
+    /// The surface is opaque where alpha is at least the given cutoff, and
+    /// invisible elsewhere.
+    Mask(f32),
+
+    /// The surface is blended with what is behind it.
+    Blend,
+}
+
+/// TextureTransform is the `KHR_texture_transform` transformation of the
+/// texture coordinates used to sample a texture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureTransform {
+    /// The offset of the coordinates.
+    pub offset: Vec2<f32>,
+
+    /// The counter-clockwise rotation of the coordinates, in radians.
+    pub rotation: f32,
+
+    /// The scale of the coordinates.
+    pub scale: Vec2<f32>,
+}
+
+impl TextureTransform {
+    /// apply returns the transformed texture coordinates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec2;
+    /// use fiz_gfx::gltf::TextureTransform;
+    ///
+    /// let t = TextureTransform { offset: Vec2(0.5, 0.0), rotation: 0.0, scale: Vec2(0.5, 0.5) };
+    /// assert_eq!(t.apply(Vec2(1.0, 1.0)), Vec2(1.0, 0.5));
+    /// ```
+    pub fn apply(&self, uv: Vec2<f32>) -> Vec2<f32> {
+        let (s, c) = self.rotation.sin_cos();
+        let (u, v) = (uv.0 * self.scale.0, uv.1 * self.scale.1);
+        Vec2(c * u + s * v + self.offset.0, c * v - s * u + self.offset.1)
+    }
+}
+
+/// TextureRef is a reference from a material to a texture.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextureRef {
+    /// The index of the texture.
+    pub texture: usize,
+
+    /// The index of the set of texture coordinates used to sample it.
+    pub tex_coord: u32,
+
+    /// The transformation of the texture coordinates, if any.
+    pub transform: Option<TextureTransform>,
+}
+
+/// Material is a physically based metallic-roughness material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    /// The name of the material, if any.
+    pub name: Option<String>,
+
+    /// The linear RGBA base color, multiplied with the base color texture.
+    pub base_color: Vec4<f32>,
+
+    /// The base color texture, in sRGB.
+    pub base_color_texture: Option<TextureRef>,
+
+    /// The metalness, multiplied with the blue channel of the
+    /// metallic-roughness texture.
+    pub metallic: f32,
+
+    /// The perceptual roughness, multiplied with the green channel of the
+    /// metallic-roughness texture.
+    pub roughness: f32,
+
+    /// The metallic-roughness texture.
+    pub metallic_roughness_texture: Option<TextureRef>,
+
+    /// The tangent space normal texture.
+    pub normal_texture: Option<TextureRef>,
+
+    /// The scale of the X and Y components of the normal texture.
+    pub normal_scale: f32,
+
+    /// The ambient occlusion texture, sampled from its red channel.
+    pub occlusion_texture: Option<TextureRef>,
+
+    /// The strength of the ambient occlusion.
+    pub occlusion_strength: f32,
+
+    /// The linear emissive color, including any
+    /// `KHR_materials_emissive_strength` multiplier, so it may exceed one.
+    pub emissive: Vec3<f32>,
+
+    /// The emissive texture, in sRGB.
+    pub emissive_texture: Option<TextureRef>,
+
+    /// How the alpha of the base color is used.
+    pub alpha_mode: AlphaMode,
+
+    /// Whether back faces are visible.
+    pub double_sided: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: None,
+            base_color: Vec4(1.0, 1.0, 1.0, 1.0),
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive: Vec3(0.0, 0.0, 0.0),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+/// Filter is a texture filtering mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// The nearest texel of the nearest mipmap (if any).
+    Nearest,
+
+    /// Bilinear filtering of the nearest mipmap (if any).
+    Linear,
+
+    /// The nearest texel, of the nearest mipmap.
+    NearestMipmapNearest,
+
+    /// Bilinear filtering of the nearest mipmap.
+    LinearMipmapNearest,
+
+    /// The nearest texel, interpolated between mipmaps.
+    NearestMipmapLinear,
+
+    /// Trilinear filtering.
+    LinearMipmapLinear,
+}
+
+/// Wrap is a texture wrapping mode.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    /// The texture repeats.
+    Repeat,
+
+    /// The texture repeats, mirrored every other time.
+    MirroredRepeat,
+
+    /// Coordinates are clamped to the edge of the texture.
+    ClampToEdge,
+}
+
+/// Texture is an image and the sampler used to read it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    /// The name of the texture, if any.
+    pub name: Option<String>,
+
+    /// The index of the image, if any.
+    pub image: Option<usize>,
+
+    /// The magnification filter, if specified.
+    pub mag_filter: Option<Filter>,
+
+    /// The minification filter, if specified.
+    pub min_filter: Option<Filter>,
+
+    /// The wrapping mode along U.
+    pub wrap_s: Wrap,
+
+    /// The wrapping mode along V.
+    pub wrap_t: Wrap,
+}
+
+/// Image is an encoded image, such as a PNG or JPEG file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Image {
+    /// The name of the image, if any.
+    pub name: Option<String>,
+
+    /// The MIME type of the image, if known.
+    pub mime_type: Option<String>,
+
+    /// The URI of an external image file, relative to the document. External
+    /// images aren't loaded.
+    pub uri: Option<String>,
+
+    /// The encoded contents of an image embedded in the document.
+    pub data: Option<Vec<u8>>,
+}
+
+/// Skin is a set of joints which deform a skinned mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Skin {
+    /// The name of the skin, if any.
+    pub name: Option<String>,
+
+    /// The indices of the joint nodes. Skin weights of vertices refer to
+    /// joints by their position in this list.
+    pub joints: Vec<usize>,
+
+    /// The column-major inverse bind matrix of each joint, which transforms
+    /// the mesh into the joint's space.
+    pub inverse_bind_matrices: Vec<[Vec4<f32>; 4]>,
+
+    /// The index of the node at the root of the joint hierarchy, if given.
+    pub skeleton: Option<usize>,
+}
+
+/// Interpolation is the way an animated value is interpolated between
+/// keyframes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Linear interpolation (spherical linear interpolation for rotations).
+    Linear,
+
+    /// The value of the previous keyframe is held.
+    Step,
+
+    /// Cubic Hermite spline interpolation. Each keyframe has three values:
+    /// an in-tangent, the value and an out-tangent.
+    CubicSpline,
+}
+
+/// Keyframes are the values of an animated property at each keyframe.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    /// Translations of the node.
+    Translation(Vec<Vec3<f32>>),
+
+    /// Rotations of the node, as unit quaternions `(x, y, z, w)`.
+    Rotation(Vec<Vec4<f32>>),
+
+    /// Scales of the node.
+    Scale(Vec<Vec3<f32>>),
+
+    /// Morph target weights of the node's model, one set per keyframe.
+    Weights(Vec<f32>),
+}
+
+/// Channel animates one property of a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    /// The index of the animated node.
+    pub node: usize,
+
+    /// The time of each keyframe, in seconds.
+    pub times: Vec<f32>,
+
+    /// The values of the property at each keyframe.
+    pub keyframes: Keyframes,
+
+    /// The interpolation between keyframes.
+    pub interpolation: Interpolation,
+}
+
+/// Animation is a set of channels which play together.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Animation {
+    /// The name of the animation, if any.
+    pub name: Option<String>,
+
+    /// The channels of the animation.
+    pub channels: Vec<Channel>,
+}
@@ -0,0 +1,220 @@
+use std::io::{self, Write};
+use math::Vec4;
+use super::{decode_error, new_image, FileFormat, Image, ImageError, PixelFormat};
+
+fn error(message: &str) -> ImageError {
+    decode_error(FileFormat::Hdr, message)
+}
+
+// rgbe_to_rgb decodes a pixel of 8-bit mantissas sharing an exponent, as
+// Radiance does.
+fn rgbe_to_rgb(p: &[u8]) -> Vec4<f32> {
+    if p[3] == 0 {
+        return Vec4(0.0, 0.0, 0.0, 1.0);
+    }
+    let f = 2f32.powi(p[3] as i32 - 136);
+    Vec4((p[0] as f32 + 0.5) * f, (p[1] as f32 + 0.5) * f, (p[2] as f32 + 0.5) * f, 1.0)
+}
+
+// rgb_to_rgbe encodes a pixel, clamping negative values to zero.
+fn rgb_to_rgbe(c: Vec4<f32>) -> [u8; 4] {
+    let (r, g, b) = (c.0.max(0.0), c.1.max(0.0), c.2.max(0.0));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // Split v into a mantissa in [0.5, 1) and an exponent.
+    let mut e = v.log2().floor() as i32 + 1;
+    let mut m = v * 2f32.powi(-e);
+    if m >= 1.0 {
+        m *= 0.5;
+        e += 1;
+    } else if m < 0.5 {
+        m *= 2.0;
+        e -= 1;
+    }
+    let d = m * 255.9999 / v;
+    [(r * d) as u8, (g * d) as u8, (b * d) as u8, (e + 128).clamp(0, 255) as u8]
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    // The header is lines of text ending with an empty line, followed by
+    // the resolution line.
+    let mut lines = Vec::new();
+    let mut pos = 0;
+    while let Some(end) = data[pos..].iter().position(|&c| c == b'\n') {
+        let line = String::from_utf8_lossy(&data[pos..pos + end]).into_owned();
+        pos += end + 1;
+        let done = lines.last().is_some_and(|l: &String| l.is_empty());
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+    if !lines.first().is_some_and(|l| l.starts_with("#?")) {
+        return Err(error("missing signature"));
+    }
+    if let Some(format) = lines.iter().find_map(|l| l.strip_prefix("FORMAT=")) {
+        if format != "32-bit_rle_rgbe" {
+            return Err(ImageError::Unsupported(format!("HDR format {}", format)));
+        }
+    }
+    let resolution = lines.last().filter(|_| lines.len() >= 3).ok_or_else(|| error("missing resolution"))?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (width, height) = match fields[..] {
+        ["-Y", h, "+X", w] => {
+            (w.parse::<u32>().map_err(|_| error("invalid width"))?,
+             h.parse::<u32>().map_err(|_| error("invalid height"))?)
+        }
+        _ => return Err(ImageError::Unsupported(format!("HDR orientation {:?}", resolution))),
+    };
+
+    // Each row takes at least four bytes, and four bytes hold at most 255
+    // pixels, except in chains of old style runs, which nothing still writes.
+    let rest = (data.len() - pos) / 4;
+    if height as usize > rest || width as u64 * height as u64 > rest as u64 * 255 {
+        return Err(error("truncated data"));
+    }
+    let mut image = new_image(FileFormat::Hdr, width, height, PixelFormat::Rgba32F)?;
+    let w = width as usize;
+    let mut row = vec![0u8; 4 * w];
+    for y in 0..height {
+        pos = read_row(data, pos, &mut row)?;
+        for x in 0..width {
+            let i = 4 * x as usize;
+            image.set_pixel(x, y, rgbe_to_rgb(&row[i..i + 4]));
+        }
+    }
+    Ok(image)
+}
+
+// read_row reads a row of RGBE pixels starting at pos, returning the
+// position after it.
+fn read_row(data: &[u8], mut pos: usize, row: &mut [u8]) -> Result<usize, ImageError> {
+    let w = row.len() / 4;
+    let start = data.get(pos..pos + 4).ok_or_else(|| error("truncated data"))?;
+    let mut byte = || {
+        let b = *data.get(pos).ok_or_else(|| error("truncated data"))?;
+        pos += 1;
+        Ok(b)
+    };
+    if (8..32768).contains(&w) && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0 {
+        // Each channel is run-length encoded separately.
+        if ((start[2] as usize) << 8 | start[3] as usize) != w {
+            return Err(error("scanline width mismatch"));
+        }
+        for _ in 0..4 {
+            byte()?;
+        }
+        for c in 0..4 {
+            let mut x = 0;
+            while x < w {
+                let count = byte()? as usize;
+                let (n, run) = if count > 128 { (count - 128, true) } else { (count, false) };
+                if n == 0 || x + n > w {
+                    return Err(error("invalid run length"));
+                }
+                let value = if run { byte()? } else { 0 };
+                for _ in 0..n {
+                    row[4 * x + c] = if run { value } else { byte()? };
+                    x += 1;
+                }
+            }
+        }
+        return Ok(pos);
+    }
+    // Flat pixels, possibly with old style runs of (1, 1, 1, count) which
+    // repeat the previous pixel.
+    let (mut x, mut shift) = (0, 0);
+    while x < w {
+        let p = [byte()?, byte()?, byte()?, byte()?];
+        if p[..3] == [1, 1, 1] && x > 0 {
+            let n = (p[3] as usize).checked_shl(shift).unwrap_or(usize::MAX);
+            if n > w - x {
+                return Err(error("invalid run length"));
+            }
+            for _ in 0..n {
+                row.copy_within(4 * (x - 1)..4 * x, 4 * x);
+                x += 1;
+            }
+            shift += 8;
+            continue;
+        }
+        row[4 * x..4 * x + 4].copy_from_slice(&p);
+        x += 1;
+        shift = 0;
+    }
+    Ok(pos)
+}
+
+pub fn encode<W: Write>(w: &mut W, image: &Image) -> io::Result<()> {
+    let (width, height) = (image.width(), image.height());
+    write!(w, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+    let rle = (8..32768).contains(&width);
+    let mut row = Vec::with_capacity(4 * width as usize);
+    let mut channel = Vec::with_capacity(width as usize);
+    for y in 0..height {
+        row.clear();
+        for x in 0..width {
+            row.extend_from_slice(&rgb_to_rgbe(image.pixel(x, y)));
+        }
+        if !rle {
+            w.write_all(&row)?;
+            continue;
+        }
+        w.write_all(&[2, 2, (width >> 8) as u8, width as u8])?;
+        for c in 0..4 {
+            channel.clear();
+            channel.extend(row.iter().skip(c).step_by(4));
+            write_channel(w, &channel)?;
+        }
+    }
+    Ok(())
+}
+
+// write_channel writes the values of one channel of a row as runs of equal
+// values, and literal values between them.
+fn write_channel<W: Write>(w: &mut W, values: &[u8]) -> io::Result<()> {
+    let run = |i: usize| values[i..].iter().take(127).take_while(|&&v| v == values[i]).count();
+    let mut i = 0;
+    while i < values.len() {
+        let n = run(i);
+        if n >= 3 {
+            w.write_all(&[128 + n as u8, values[i]])?;
+            i += n;
+            continue;
+        }
+        let mut n = 1;
+        while i + n < values.len() && n < 128 && run(i + n) < 3 {
+            n += 1;
+        }
+        w.write_all(&[n as u8])?;
+        w.write_all(&values[i..i + n])?;
+        i += n;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec4;
+    use super::{decode, rgb_to_rgbe, rgbe_to_rgb};
+
+    #[test]
+    fn rgbe() {
+        assert_eq!(rgb_to_rgbe(Vec4(1.0, 0.5, 0.0, 1.0)), [127, 63, 0, 129]);
+        assert_eq!(rgbe_to_rgb(&[127, 63, 0, 129]), Vec4(0.99609375, 0.49609375, 0.00390625, 1.0));
+        assert_eq!(rgb_to_rgbe(Vec4(-1.0, 0.0, 0.0, 1.0)), [0; 4]);
+    }
+
+    #[test]
+    fn flat() {
+        // A flat row, with an old style run.
+        let mut data = b"#?RGBE\n# comment\n\n-Y 1 +X 4\n".to_vec();
+        data.extend_from_slice(&[128, 0, 0, 129, 0, 128, 0, 129, 1, 1, 1, 2]);
+        let image = decode(&data).unwrap();
+        assert_eq!(image.pixel(0, 0).0, 1.0039062);
+        assert_eq!(image.pixel(3, 0).1, 1.0039062);
+        assert!(decode(&data[..data.len() - 1]).is_err());
+    }
+}
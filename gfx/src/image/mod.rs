@@ -0,0 +1,718 @@
+//! Images and image file codecs.
+//!
+//! An `Image` is a two-dimensional array of pixels in one of a few pixel
+//! formats, stored in rows from the top which may be padded to a row pitch.
+//! Images can be decoded from and encoded to these file formats:
+//!
+//! - PNG, with all color types and bit depths, and interlacing.
+//! - PPM and PGM (binary and ASCII), and PFM for floating point images.
+//! - TGA, uncompressed and run-length encoded.
+//! - Radiance HDR (RGBE).
+//!
+//! Pixel values are stored as they are in files, with no gamma or color
+//! space conversion: an 8-bit PNG is usually sRGB encoded, while PFM and HDR
+//! files hold linear values.
+//!
+//! ```no_run
+//! use fiz_gfx::image;
+//!
+//! let texture = image::load("bricks.png").unwrap();
+//! let grey = texture.convert(image::PixelFormat::R8);
+//! image::save("bricks.pgm", &grey).unwrap();
+//! ```
+
+mod zlib;
+mod png;
+mod pnm;
+mod tga;
+mod hdr;
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use math::Vec4;
+
+/// ImageError describes a failure to load, decode or save an image.
+#[derive(Debug)]
+pub enum ImageError {
+    /// A file couldn't be read or written.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// The data of a file is invalid.
+    Decode {
+        /// The format of the file.
+        format: FileFormat,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// A file uses a feature, or is of a format, which isn't supported.
+    Unsupported(String),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImageError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            ImageError::Decode { format, ref message } => write!(f, "invalid {} file: {}", format.name(), message),
+            ImageError::Unsupported(ref what) => write!(f, "unsupported {}", what),
+        }
+    }
+}
+
+impl Error for ImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ImageError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// PixelFormat is the layout of the pixels of an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    /// One 8-bit normalized channel.
+    R8,
+
+    /// Four 8-bit normalized channels.
+    Rgba8,
+
+    /// Four 16-bit (half precision) floating point channels.
+    Rgba16F,
+
+    /// Four 32-bit floating point channels.
+    Rgba32F,
+
+    /// One 32-bit floating point depth value.
+    Depth32F,
+}
+
+impl PixelFormat {
+    /// pixel_size returns the size of a pixel in bytes.
+    pub fn pixel_size(self) -> usize {
+        match self {
+            PixelFormat::R8 => 1,
+            PixelFormat::Rgba8 | PixelFormat::Depth32F => 4,
+            PixelFormat::Rgba16F => 8,
+            PixelFormat::Rgba32F => 16,
+        }
+    }
+
+    /// channels returns the number of channels of a pixel.
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::Depth32F => 1,
+            _ => 4,
+        }
+    }
+
+    /// is_float reports whether the channels are floating point, and so not
+    /// limited to the range zero to one.
+    pub fn is_float(self) -> bool {
+        !matches!(self, PixelFormat::R8 | PixelFormat::Rgba8)
+    }
+}
+
+/// FileFormat is an image file format.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// Portable Network Graphics.
+    Png,
+
+    /// The binary and ASCII Netpbm pixmap (PPM) and graymap (PGM) formats.
+    Pnm,
+
+    /// The Portable Float Map, the floating point variant of PPM.
+    Pfm,
+
+    /// Truevision TGA.
+    Tga,
+
+    /// The Radiance RGBE format.
+    Hdr,
+}
+
+impl FileFormat {
+    /// from_extension returns the format of files with the given extension,
+    /// ignoring case, or None if it isn't known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::image::FileFormat;
+    ///
+    /// assert_eq!(FileFormat::from_extension("PGM"), Some(FileFormat::Pnm));
+    /// assert_eq!(FileFormat::from_extension("jpg"), None);
+    /// ```
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(FileFormat::Png),
+            "ppm" | "pgm" | "pnm" => Some(FileFormat::Pnm),
+            "pfm" => Some(FileFormat::Pfm),
+            "tga" => Some(FileFormat::Tga),
+            "hdr" | "pic" => Some(FileFormat::Hdr),
+            _ => None,
+        }
+    }
+
+    /// detect returns the format of a file from its first bytes, or None if
+    /// it isn't recognized. TGA files have no signature and aren't detected.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data {
+            _ if data.starts_with(b"\x89PNG\r\n\x1a\n") => Some(FileFormat::Png),
+            [b'P', b'2'..=b'6', ..] => Some(FileFormat::Pnm),
+            [b'P', b'F', ..] | [b'P', b'f', ..] => Some(FileFormat::Pfm),
+            _ if data.starts_with(b"#?") => Some(FileFormat::Hdr),
+            _ => None,
+        }
+    }
+
+    /// name returns the common name of the format.
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Png => "PNG",
+            FileFormat::Pnm => "PNM",
+            FileFormat::Pfm => "PFM",
+            FileFormat::Tga => "TGA",
+            FileFormat::Hdr => "HDR",
+        }
+    }
+}
+
+/// Image is a two-dimensional array of pixels, stored in rows from the top.
+/// Each row starts `row_pitch` bytes after the previous one, which may leave
+/// padding after the pixels of a row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    row_pitch: usize,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// new returns a new image of the given size and format, with tightly
+    /// packed rows and all bytes zero.
+    ///
+    /// # Panics
+    ///
+    /// new panics if the size of the image in bytes overflows usize.
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        Image::try_new(width, height, format).expect("image too large")
+    }
+
+    /// try_new returns a new image like `new`, or None if the size of the
+    /// image in bytes overflows usize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::image::{Image, PixelFormat};
+    ///
+    /// assert_eq!(Image::try_new(3, 2, PixelFormat::Rgba8).unwrap().data().len(), 24);
+    /// assert!(Image::try_new(u32::MAX, u32::MAX, PixelFormat::Rgba32F).is_none());
+    /// ```
+    pub fn try_new(width: u32, height: u32, format: PixelFormat) -> Option<Self> {
+        let row_pitch = (width as usize).checked_mul(format.pixel_size())?;
+        let size = row_pitch.checked_mul(height as usize)?;
+        Some(Image {
+            width,
+            height,
+            format,
+            row_pitch,
+            data: vec![0; size],
+        })
+    }
+
+    /// from_data returns an image of the given size and format holding the
+    /// data, whose rows are row_pitch bytes apart. It returns None if the
+    /// pitch is smaller than a row of pixels, or the data isn't the size of
+    /// all the rows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::image::{Image, PixelFormat};
+    ///
+    /// // Two rows of three pixels, each padded to four bytes.
+    /// let image = Image::from_data(3, 2, PixelFormat::R8, 4, vec![1, 2, 3, 0, 4, 5, 6, 0]).unwrap();
+    /// assert_eq!(image.row(1), &[4, 5, 6]);
+    /// assert!(Image::from_data(3, 2, PixelFormat::R8, 2, vec![0; 4]).is_none());
+    /// ```
+    pub fn from_data(width: u32,
+                     height: u32,
+                     format: PixelFormat,
+                     row_pitch: usize,
+                     data: Vec<u8>)
+                     -> Option<Self> {
+        let size = row_pitch.checked_mul(height as usize);
+        if row_pitch < width as usize * format.pixel_size() || Some(data.len()) != size {
+            return None;
+        }
+        Some(Image {
+            width,
+            height,
+            format,
+            row_pitch,
+            data,
+        })
+    }
+
+    /// width returns the width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// height returns the height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// format returns the format of the pixels.
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// row_pitch returns the distance between the starts of rows in bytes.
+    pub fn row_pitch(&self) -> usize {
+        self.row_pitch
+    }
+
+    /// data returns the bytes of the image, including any row padding.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// data_mut returns the bytes of the image, including any row padding.
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    /// into_data returns the bytes of the image, including any row padding.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// row returns the bytes of the pixels of a row, without padding.
+    ///
+    /// # Panics
+    ///
+    /// row panics if y is out of range.
+    pub fn row(&self, y: u32) -> &[u8] {
+        assert!(y < self.height, "row {} out of range", y);
+        let start = y as usize * self.row_pitch;
+        &self.data[start..start + self.width as usize * self.format.pixel_size()]
+    }
+
+    /// row_mut returns the bytes of the pixels of a row, without padding.
+    ///
+    /// # Panics
+    ///
+    /// row_mut panics if y is out of range.
+    pub fn row_mut(&mut self, y: u32) -> &mut [u8] {
+        assert!(y < self.height, "row {} out of range", y);
+        let start = y as usize * self.row_pitch;
+        let len = self.width as usize * self.format.pixel_size();
+        &mut self.data[start..start + len]
+    }
+
+    /// pixel returns the value of a pixel as RGBA. Normalized channels are
+    /// mapped to the range zero to one, and single channel formats return
+    /// the value as red, with zero green and blue and an alpha of one.
+    ///
+    /// # Panics
+    ///
+    /// pixel panics if the pixel is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec4;
+    /// use fiz_gfx::image::{Image, PixelFormat};
+    ///
+    /// let mut image = Image::new(2, 2, PixelFormat::Rgba16F);
+    /// image.set_pixel(1, 0, Vec4(0.5, 2.0, -1.0, 1.0));
+    /// assert_eq!(image.pixel(1, 0), Vec4(0.5, 2.0, -1.0, 1.0));
+    /// ```
+    pub fn pixel(&self, x: u32, y: u32) -> Vec4<f32> {
+        assert!(x < self.width, "column {} out of range", x);
+        let size = self.format.pixel_size();
+        let p = &self.row(y)[x as usize * size..(x as usize + 1) * size];
+        let unorm = |i: usize| p[i] as f32 / 255.0;
+        let half = |i: usize| f16_to_f32(u16::from_le_bytes([p[2 * i], p[2 * i + 1]]));
+        let float = |i: usize| f32::from_le_bytes([p[4 * i], p[4 * i + 1], p[4 * i + 2], p[4 * i + 3]]);
+        match self.format {
+            PixelFormat::R8 => Vec4(unorm(0), 0.0, 0.0, 1.0),
+            PixelFormat::Rgba8 => Vec4(unorm(0), unorm(1), unorm(2), unorm(3)),
+            PixelFormat::Rgba16F => Vec4(half(0), half(1), half(2), half(3)),
+            PixelFormat::Rgba32F => Vec4(float(0), float(1), float(2), float(3)),
+            PixelFormat::Depth32F => Vec4(float(0), 0.0, 0.0, 1.0),
+        }
+    }
+
+    /// set_pixel sets the value of a pixel from RGBA. Normalized channels
+    /// are clamped and rounded, and single channel formats keep only red.
+    ///
+    /// # Panics
+    ///
+    /// set_pixel panics if the pixel is out of range.
+    pub fn set_pixel(&mut self, x: u32, y: u32, value: Vec4<f32>) {
+        assert!(x < self.width, "column {} out of range", x);
+        let format = self.format;
+        let size = format.pixel_size();
+        let p = &mut self.row_mut(y)[x as usize * size..(x as usize + 1) * size];
+        let c = [value.0, value.1, value.2, value.3];
+        match format {
+            PixelFormat::R8 => p[0] = unorm8(c[0]),
+            PixelFormat::Rgba8 => {
+                for (b, &c) in p.iter_mut().zip(&c) {
+                    *b = unorm8(c);
+                }
+            }
+            PixelFormat::Rgba16F => {
+                for (b, &c) in p.chunks_mut(2).zip(&c) {
+                    b.copy_from_slice(&f32_to_f16(c).to_le_bytes());
+                }
+            }
+            PixelFormat::Rgba32F => {
+                for (b, &c) in p.chunks_mut(4).zip(&c) {
+                    b.copy_from_slice(&c.to_le_bytes());
+                }
+            }
+            PixelFormat::Depth32F => p.copy_from_slice(&c[0].to_le_bytes()),
+        }
+    }
+
+    /// convert returns a copy of the image in another pixel format, with
+    /// tightly packed rows, converting each pixel as `pixel` and `set_pixel`
+    /// do.
+    pub fn convert(&self, format: PixelFormat) -> Image {
+        let mut out = Image::new(self.width, self.height, format);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                out.set_pixel(x, y, self.pixel(x, y));
+            }
+        }
+        out
+    }
+}
+
+/// srgb_to_linear converts a color channel from the sRGB transfer function
+/// to linear.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::image::{linear_to_srgb, srgb_to_linear};
+///
+/// assert!((srgb_to_linear(0.5) - 0.21404).abs() < 1e-5);
+/// assert!((linear_to_srgb(srgb_to_linear(0.8)) - 0.8).abs() < 1e-6);
+/// ```
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// linear_to_srgb converts a linear color channel to the sRGB transfer
+/// function.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+// unorm8 converts a normalized value to a byte.
+fn unorm8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// f16_to_f32 converts a half precision float to single precision.
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h >> 15) as u32) << 31;
+    let exp = ((h >> 10) & 0x1f) as u32;
+    let mant = (h & 0x3ff) as u32;
+    let bits = match exp {
+        0 if mant == 0 => sign,
+        0 => {
+            // Subnormal values are normalized.
+            let (mut e, mut m) = (113, mant);
+            while m & 0x400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+            sign | (e << 23) | ((m & 0x3ff) << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mant << 13),
+        _ => sign | ((exp + 112) << 23) | (mant << 13),
+    };
+    f32::from_bits(bits)
+}
+
+// f32_to_f16 converts a single precision float to half precision, rounding
+// to the nearest value.
+fn f32_to_f16(f: f32) -> u16 {
+    let bits = f.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mant = bits & 0x7f_ffff;
+    if exp == 0xff {
+        return sign | 0x7c00 | if mant != 0 { 0x200 } else { 0 };
+    }
+    let e = exp - 112;
+    if e >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let (value, shift) = if e <= 0 {
+        if e < -10 {
+            return sign;
+        }
+        (mant | 0x80_0000, (14 - e) as u32)
+    } else {
+        (((e as u32) << 23) | mant, 13)
+    };
+    // Round to nearest, with ties to even. Rounding up may carry into the
+    // exponent, which is correct.
+    let (q, r, half) = (value >> shift, value & ((1 << shift) - 1), 1 << (shift - 1));
+    let q = if r > half || (r == half && q & 1 == 1) { q + 1 } else { q };
+    sign | q as u16
+}
+
+fn decode_error(format: FileFormat, message: &str) -> ImageError {
+    ImageError::Decode {
+        format,
+        message: message.to_string(),
+    }
+}
+
+// new_image returns a new image for a decoder, failing rather than panicking
+// if it's too large.
+fn new_image(file: FileFormat, width: u32, height: u32, format: PixelFormat) -> Result<Image, ImageError> {
+    Image::try_new(width, height, format).ok_or_else(|| decode_error(file, "image too large"))
+}
+
+/// decode decodes an image file of the given format. Images are decoded
+/// into the format best holding their pixels:
+///
+/// - 8-bit grayscale as `R8`, and other 8-bit and palette images as `Rgba8`.
+/// - 16-bit images, PFM and HDR files as `Rgba32F`.
+///
+/// Grayscale pixels are repeated in the red, green and blue channels of
+/// four channel formats.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec4;
+/// use fiz_gfx::image::{decode, FileFormat, PixelFormat};
+///
+/// let image = decode(b"P3 2 1 255  255 0 0  0 0 255", FileFormat::Pnm).unwrap();
+/// assert_eq!(image.format(), PixelFormat::Rgba8);
+/// assert_eq!(image.pixel(1, 0), Vec4(0.0, 0.0, 1.0, 1.0));
+/// ```
+pub fn decode(data: &[u8], format: FileFormat) -> Result<Image, ImageError> {
+    match format {
+        FileFormat::Png => png::decode(data),
+        FileFormat::Pnm | FileFormat::Pfm => pnm::decode(data),
+        FileFormat::Tga => tga::decode(data),
+        FileFormat::Hdr => hdr::decode(data),
+    }
+}
+
+/// encode encodes an image in the given file format. Images are converted
+/// to a pixel format the file format holds:
+///
+/// - PNG stores `R8` as 8-bit grayscale, `Rgba8` as 8-bit RGBA and other
+///   formats as 16-bit RGBA.
+/// - PPM and PGM store `R8` as an 8-bit graymap, and other formats as an
+///   8-bit pixmap without alpha.
+/// - PFM stores single channel formats as grayscale, and other formats as
+///   RGB.
+/// - TGA stores `R8` as 8-bit grayscale and other formats as 8-bit RGBA.
+/// - HDR stores RGB.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec4;
+/// use fiz_gfx::image::{decode, encode, FileFormat, Image, PixelFormat};
+///
+/// let mut image = Image::new(3, 2, PixelFormat::Rgba8);
+/// image.set_pixel(2, 1, Vec4(1.0, 0.0, 1.0, 0.0));
+/// let mut png = Vec::new();
+/// encode(&mut png, &image, FileFormat::Png).unwrap();
+/// assert_eq!(FileFormat::detect(&png), Some(FileFormat::Png));
+/// assert_eq!(decode(&png, FileFormat::Png).unwrap(), image);
+/// ```
+pub fn encode<W: Write>(w: &mut W, image: &Image, format: FileFormat) -> io::Result<()> {
+    match format {
+        FileFormat::Png => png::encode(w, image),
+        FileFormat::Pnm => pnm::encode_pnm(w, image),
+        FileFormat::Pfm => pnm::encode_pfm(w, image),
+        FileFormat::Tga => tga::encode(w, image),
+        FileFormat::Hdr => hdr::encode(w, image),
+    }
+}
+
+/// load loads the image file at the given path, whose format is detected
+/// from its contents, or its extension for TGA files.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, ImageError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|error| {
+        ImageError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    let format = FileFormat::detect(&data)
+        .or_else(|| path.extension().and_then(|e| e.to_str()).and_then(FileFormat::from_extension))
+        .ok_or_else(|| ImageError::Unsupported(format!("image file {}", path.display())))?;
+    decode(&data, format)
+}
+
+/// save saves the image to the given path, in the format of its extension.
+pub fn save<P: AsRef<Path>>(path: P, image: &Image) -> Result<(), ImageError> {
+    let path = path.as_ref();
+    let format = path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(FileFormat::from_extension)
+        .ok_or_else(|| ImageError::Unsupported(format!("image file extension of {}", path.display())))?;
+    let mut out = Vec::new();
+    encode(&mut out, image, format)
+        .and_then(|_| fs::write(path, out))
+        .map_err(|error| {
+            ImageError::Io {
+                path: path.to_path_buf(),
+                error,
+            }
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec4;
+    use super::{decode, encode, f16_to_f32, f32_to_f16, zlib, FileFormat, Image, ImageError, PixelFormat};
+
+    #[test]
+    fn half() {
+        for &f in &[0.0, -0.0, 1.0, -2.5, 65504.0, 6.1035156e-5, 5.9604645e-8, 0.33325195] {
+            assert_eq!(f16_to_f32(f32_to_f16(f)), f);
+        }
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f32_to_f16(1e-9), 0);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        // 1 + 2^-11 is halfway between two halves, and rounds to even.
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 2048.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 2048.0), 0x3c02);
+    }
+
+    // gradient returns an image with distinct values in each pixel.
+    fn gradient(width: u32, height: u32, format: PixelFormat) -> Image {
+        let mut image = Image::new(width, height, format);
+        for y in 0..height {
+            for x in 0..width {
+                let (u, v) = (x as f32 / width as f32, y as f32 / height as f32);
+                image.set_pixel(x, y, Vec4(u, v, 1.0 - u, if (x + y) % 3 == 0 { 1.0 } else { 0.5 }));
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn round_trip() {
+        let rgba = gradient(37, 23, PixelFormat::Rgba8);
+        let grey = gradient(37, 23, PixelFormat::R8);
+        let float = gradient(37, 23, PixelFormat::Rgba32F);
+        let opaque = |image: &Image| {
+            let mut image = image.clone();
+            for y in 0..image.height() {
+                for x in 0..image.width() {
+                    let p = image.pixel(x, y);
+                    image.set_pixel(x, y, Vec4(p.0, p.1, p.2, 1.0));
+                }
+            }
+            image
+        };
+        let cases = [(FileFormat::Png, &rgba, rgba.clone()),
+                     (FileFormat::Png, &grey, grey.clone()),
+                     (FileFormat::Tga, &rgba, rgba.clone()),
+                     (FileFormat::Tga, &grey, grey.clone()),
+                     (FileFormat::Pnm, &rgba, opaque(&rgba)),
+                     (FileFormat::Pnm, &grey, grey.clone()),
+                     (FileFormat::Pfm, &float, opaque(&float))];
+        for &(format, image, ref want) in &cases {
+            let mut data = Vec::new();
+            encode(&mut data, image, format).unwrap();
+            assert_eq!(&decode(&data, format).unwrap(), want, "{:?}", format);
+        }
+
+        // 16-bit PNG files hold floats within a 65535th.
+        let mut data = Vec::new();
+        encode(&mut data, &float, FileFormat::Png).unwrap();
+        let got = decode(&data, FileFormat::Png).unwrap();
+        assert_eq!(got.format(), PixelFormat::Rgba32F);
+        for y in 0..float.height() {
+            for x in 0..float.width() {
+                assert!(got.pixel(x, y).almost_equal(float.pixel(x, y), 1.0 / 65535.0));
+            }
+        }
+
+        // HDR files hold RGB with 8-bit mantissas sharing an exponent.
+        let mut data = Vec::new();
+        encode(&mut data, &float, FileFormat::Hdr).unwrap();
+        let got = decode(&data, FileFormat::Hdr).unwrap();
+        for y in 0..float.height() {
+            for x in 0..float.width() {
+                let (g, f) = (got.pixel(x, y), float.pixel(x, y));
+                assert!(Vec4(g.0, g.1, g.2, 1.0).almost_equal(Vec4(f.0, f.1, f.2, 1.0), 1.0 / 128.0));
+            }
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        // Headers claiming more pixels than the data could hold fail before
+        // the image is allocated.
+        let tga = [0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 32, 0, 0xff, 1, 2, 3, 4];
+        let mut png = Vec::new();
+        encode(&mut png, &gradient(2, 2, PixelFormat::Rgba8), FileFormat::Png).unwrap();
+        png[16..24].copy_from_slice(&[0x7f, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff]);
+        let crc = zlib::crc32(0, &png[12..29]);
+        png[29..33].copy_from_slice(&crc.to_be_bytes());
+        let cases: [(FileFormat, &[u8]); 6] = [(FileFormat::Tga, &tga),
+                                               (FileFormat::Png, &png),
+                                               (FileFormat::Pnm, b"P6 4294967295 4294967295 255 abc"),
+                                               (FileFormat::Pnm, b"P3 65536 65536 255 1 2 3"),
+                                               (FileFormat::Pfm, b"PF 4294967295 4294967295 -1.0 abcd"),
+                                               (FileFormat::Hdr, b"#?RADIANCE\n\n-Y 100000 +X 100000\n\x02\x02")];
+        for &(format, data) in &cases {
+            match decode(data, format) {
+                Err(ImageError::Decode { .. }) => {}
+                other => panic!("{:?}: {:?}", format, other),
+            }
+        }
+    }
+
+    #[test]
+    fn row_pitch() {
+        let tight = gradient(5, 3, PixelFormat::Rgba8);
+        let mut data = vec![0xff; 32 * 3];
+        for y in 0..3 {
+            data[y * 32..y * 32 + 20].copy_from_slice(tight.row(y as u32));
+        }
+        let padded = Image::from_data(5, 3, PixelFormat::Rgba8, 32, data).unwrap();
+        assert_eq!(padded.pixel(4, 2), tight.pixel(4, 2));
+        assert_eq!(padded.convert(PixelFormat::Rgba8), tight);
+        let mut png = Vec::new();
+        encode(&mut png, &padded, FileFormat::Png).unwrap();
+        assert_eq!(decode(&png, FileFormat::Png).unwrap(), tight);
+    }
+}
@@ -0,0 +1,337 @@
+use std::io::{self, Write};
+use super::{decode_error, new_image, zlib, FileFormat, Image, ImageError, PixelFormat};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// The origin and spacing of the pixels of each pass of Adam7 interlacing.
+const ADAM7: [(u32, u32, u32, u32); 7] = [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4),
+                                          (1, 0, 2, 2), (0, 1, 1, 2)];
+
+fn error(message: &str) -> ImageError {
+    decode_error(FileFormat::Png, message)
+}
+
+// Header is the contents of the IHDR chunk.
+struct Header {
+    width: u32,
+    height: u32,
+    depth: u8,
+    color: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    // row_size returns the size of a row of the given width in bytes.
+    fn row_size(&self, width: u32) -> usize {
+        (width as usize * self.channels() * self.depth as usize).div_ceil(8)
+    }
+
+    // data_size returns the size in bytes of the filtered rows of the
+    // passes, or None if it overflows.
+    fn data_size(&self, passes: &[(u32, u32, u32, u32)]) -> Option<usize> {
+        let mut size = 0usize;
+        for &(x0, y0, dx, dy) in passes {
+            if x0 < self.width && y0 < self.height {
+                let (width, height) = ((self.width - x0).div_ceil(dx), (self.height - y0).div_ceil(dy));
+                size = size.checked_add((self.row_size(width) + 1).checked_mul(height as usize)?)?;
+            }
+        }
+        Some(size)
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(SIGNATURE) {
+        return Err(error("missing signature"));
+    }
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let len = data.get(pos..pos + 4).ok_or_else(|| error("missing IEND chunk"))?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let chunk = data.get(pos + 4..pos + 8 + len).ok_or_else(|| error("truncated chunk"))?;
+        let crc = data.get(pos + 8 + len..pos + 12 + len).ok_or_else(|| error("truncated chunk"))?;
+        if zlib::crc32(0, chunk) != u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) {
+            return Err(error("chunk CRC mismatch"));
+        }
+        pos += 12 + len;
+        let (kind, body) = chunk.split_at(4);
+        match kind {
+            b"IHDR" => {
+                if body.len() != 13 {
+                    return Err(error("invalid IHDR chunk"));
+                }
+                let h = Header {
+                    width: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                    height: u32::from_be_bytes([body[4], body[5], body[6], body[7]]),
+                    depth: body[8],
+                    color: body[9],
+                    interlaced: body[12] == 1,
+                };
+                let valid = match h.color {
+                    0 => [1, 2, 4, 8, 16].contains(&h.depth),
+                    3 => [1, 2, 4, 8].contains(&h.depth),
+                    2 | 4 | 6 => [8, 16].contains(&h.depth),
+                    _ => false,
+                };
+                if !valid || h.width == 0 || h.height == 0 || body[10] != 0 || body[11] != 0 || body[12] > 1 {
+                    return Err(error("invalid IHDR chunk"));
+                }
+                header = Some(h);
+            }
+            b"PLTE" => palette = body,
+            b"tRNS" => transparency = body,
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // Critical chunks have an uppercase first letter.
+            _ if kind[0].is_ascii_uppercase() => {
+                return Err(ImageError::Unsupported(format!("PNG chunk {}", String::from_utf8_lossy(kind))))
+            }
+            _ => {}
+        }
+    }
+    let h = header.ok_or_else(|| error("missing IHDR chunk"))?;
+    if h.color == 3 && palette.is_empty() {
+        return Err(error("missing PLTE chunk"));
+    }
+    let passes = if h.interlaced { &ADAM7[..] } else { &[(0, 0, 1, 1)][..] };
+    let raw = zlib::inflate(&compressed).map_err(|e| error(&e))?;
+    if h.data_size(passes).is_none_or(|size| raw.len() < size) {
+        return Err(error("truncated image data"));
+    }
+
+    let format = match (h.depth, h.color) {
+        (16, _) => PixelFormat::Rgba32F,
+        (_, 0) if transparency.is_empty() => PixelFormat::R8,
+        _ => PixelFormat::Rgba8,
+    };
+    let mut image = new_image(FileFormat::Png, h.width, h.height, format)?;
+    let mut pos = 0;
+    for &(x0, y0, dx, dy) in passes {
+        if x0 >= h.width || y0 >= h.height {
+            continue;
+        }
+        let (width, height) = ((h.width - x0).div_ceil(dx), (h.height - y0).div_ceil(dy));
+        let size = h.row_size(width);
+        let bpp = (h.channels() * h.depth as usize).div_ceil(8);
+        let mut prev = vec![0; size];
+        for y in 0..height {
+            let filter = *raw.get(pos).ok_or_else(|| error("truncated image data"))?;
+            let mut row = raw.get(pos + 1..pos + 1 + size).ok_or_else(|| error("truncated image data"))?.to_vec();
+            pos += 1 + size;
+            unfilter(filter, &mut row, &prev, bpp)?;
+            for x in 0..width {
+                let rgba = pixel(&h, &row, x as usize, palette, transparency)?;
+                set_pixel(&mut image, x0 + x * dx, y0 + y * dy, rgba);
+            }
+            prev = row;
+        }
+    }
+    Ok(image)
+}
+
+// set_pixel sets a pixel of the image from RGBA samples of 8 bits, or 16
+// bits for floating point images.
+fn set_pixel(image: &mut Image, x: u32, y: u32, rgba: [u16; 4]) {
+    let format = image.format();
+    let size = format.pixel_size();
+    let p = &mut image.row_mut(y)[x as usize * size..(x as usize + 1) * size];
+    match format {
+        PixelFormat::R8 => p[0] = rgba[0] as u8,
+        PixelFormat::Rgba8 => {
+            for (b, &c) in p.iter_mut().zip(&rgba) {
+                *b = c as u8;
+            }
+        }
+        _ => {
+            for (b, &c) in p.chunks_mut(4).zip(&rgba) {
+                b.copy_from_slice(&(c as f32 / 65535.0).to_le_bytes());
+            }
+        }
+    }
+}
+
+// pixel returns the RGBA samples of a pixel of a row, scaled to 8 bits
+// unless they're 16 bits.
+fn pixel(h: &Header, row: &[u8], x: usize, palette: &[u8], transparency: &[u8]) -> Result<[u16; 4], ImageError> {
+    let depth = h.depth as usize;
+    let sample = |i: usize| {
+        let i = x * h.channels() + i;
+        match depth {
+            16 => u16::from_be_bytes([row[2 * i], row[2 * i + 1]]),
+            8 => row[i] as u16,
+            _ => ((row[i * depth / 8] >> (8 - depth - i * depth % 8)) & ((1 << depth) - 1) as u8) as u16,
+        }
+    };
+    // Samples of fewer than 8 bits are scaled up, except palette indices.
+    let scale = |v: u16| if depth < 8 { v * 255 / ((1 << depth) - 1) } else { v };
+    let max = if depth == 16 { 65535 } else { 255 };
+    // key returns the i'th 16-bit value of the tRNS chunk.
+    let key = |i: usize| transparency.get(2 * i..2 * i + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    Ok(match h.color {
+        0 => {
+            let g = sample(0);
+            let alpha = if key(0) == Some(g) { 0 } else { max };
+            [scale(g), scale(g), scale(g), alpha]
+        }
+        2 => {
+            let (r, g, b) = (sample(0), sample(1), sample(2));
+            let alpha = if (key(0), key(1), key(2)) == (Some(r), Some(g), Some(b)) { 0 } else { max };
+            [r, g, b, alpha]
+        }
+        3 => {
+            let i = sample(0) as usize;
+            let c = palette.get(3 * i..3 * i + 3).ok_or_else(|| error("palette index out of range"))?;
+            let alpha = transparency.get(i).map_or(255, |&a| a as u16);
+            [c[0] as u16, c[1] as u16, c[2] as u16, alpha]
+        }
+        4 => [sample(0), sample(0), sample(0), sample(1)],
+        _ => [sample(0), sample(1), sample(2), sample(3)],
+    })
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// unfilter reverses the filter of a row, given the previous unfiltered row
+// and the number of bytes per pixel.
+fn unfilter(filter: u8, row: &mut [u8], prev: &[u8], bpp: usize) -> Result<(), ImageError> {
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let b = prev[i];
+        row[i] = row[i].wrapping_add(match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return Err(error("invalid filter type")),
+        });
+    }
+    Ok(())
+}
+
+// filter returns the row filtered with the given filter type, preceded by
+// the type.
+fn filter(filter: u8, row: &[u8], prev: &[u8], bpp: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(row.len() + 1);
+    out.push(filter);
+    for i in 0..row.len() {
+        let a = if i >= bpp { row[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+        let b = prev[i];
+        out.push(row[i].wrapping_sub(match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            _ => paeth(a, b, c),
+        }));
+    }
+    out
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8], body: &[u8]) -> io::Result<()> {
+    w.write_all(&(body.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(body)?;
+    w.write_all(&zlib::crc32(zlib::crc32(0, kind), body).to_be_bytes())
+}
+
+pub fn encode<W: Write>(w: &mut W, image: &Image) -> io::Result<()> {
+    let (depth, color, bpp) = match image.format() {
+        PixelFormat::R8 => (8u8, 0u8, 1),
+        PixelFormat::Rgba8 => (8, 6, 4),
+        _ => (16, 6, 8),
+    };
+    let mut raw = Vec::new();
+    let mut prev = vec![0; image.width() as usize * bpp];
+    for y in 0..image.height() {
+        let row = if depth == 16 {
+            (0..image.width())
+                .flat_map(|x| {
+                    let p = image.pixel(x, y);
+                    let unorm16 = |c: f32| ((c.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes();
+                    [unorm16(p.0), unorm16(p.1), unorm16(p.2), unorm16(p.3)]
+                })
+                .flatten()
+                .collect()
+        } else {
+            image.row(y).to_vec()
+        };
+        // Choose the filter with the smallest sum of absolute differences,
+        // the usual heuristic.
+        let filtered = (0..5)
+            .map(|f| filter(f, &row, &prev, bpp))
+            .min_by_key(|f| f[1..].iter().map(|&b| (b as i8).unsigned_abs() as u64).sum::<u64>())
+            .unwrap();
+        raw.extend_from_slice(&filtered);
+        prev = row;
+    }
+
+    w.write_all(SIGNATURE)?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    header.extend_from_slice(&[depth, color, 0, 0, 0]);
+    write_chunk(w, b"IHDR", &header)?;
+    write_chunk(w, b"IDAT", &zlib::deflate(&raw))?;
+    write_chunk(w, b"IEND", &[])
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use math::Vec4;
+    use super::super::PixelFormat;
+    use super::decode;
+
+    #[test]
+    fn decode_files() {
+        let read = |name: &str| decode(&fs::read(format!("testdata/image/{}", name)).unwrap()).unwrap();
+
+        // The same 4x4 image in every color type, interlaced or not.
+        let rgba = read("rgba.png");
+        assert_eq!(rgba.format(), PixelFormat::Rgba8);
+        for name in &["rgb.png", "palette.png", "interlaced.png"] {
+            let image = read(name);
+            for y in 0..4 {
+                for x in 0..4 {
+                    let p = rgba.pixel(x, y);
+                    assert_eq!(image.pixel(x, y), Vec4(p.0, p.1, p.2, 1.0), "{} ({}, {})", name, x, y);
+                }
+            }
+        }
+        assert_ne!(rgba.pixel(1, 1).3, 1.0);
+
+        let grey = read("grey2.png");
+        assert_eq!(grey.format(), PixelFormat::R8);
+        assert_eq!(grey.row(0), &[0, 85, 170, 255]);
+
+        let deep = read("rgba16.png");
+        assert_eq!(deep.format(), PixelFormat::Rgba32F);
+        assert!(deep.pixel(0, 0).almost_equal(Vec4(1.0, 0.5, 0.0, 1.0), 1e-4));
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::{self, Write};
+use math::Vec4;
+use super::{decode_error, new_image, unorm8, FileFormat, Image, ImageError, PixelFormat};
+
+// Header is a parser of the whitespace separated fields of a Netpbm header,
+// which may contain comments.
+struct Header<'a> {
+    data: &'a [u8],
+    pos: usize,
+    format: FileFormat,
+}
+
+impl<'a> Header<'a> {
+    fn token(&mut self) -> Result<&'a str, ImageError> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(|c| !c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(decode_error(self.format, "truncated header"));
+        }
+        ::std::str::from_utf8(&self.data[start..self.pos]).map_err(|_| decode_error(self.format, "invalid header"))
+    }
+
+    fn number<T: ::std::str::FromStr>(&mut self) -> Result<T, ImageError> {
+        let token = self.token()?;
+        token.parse().map_err(|_| decode_error(self.format, &format!("invalid number {:?}", token)))
+    }
+
+    // body returns the data after the single whitespace byte ending the
+    // header.
+    fn body(&self) -> &'a [u8] {
+        &self.data[(self.pos + 1).min(self.data.len())..]
+    }
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    let format = if data.starts_with(b"Pf") || data.starts_with(b"PF") { FileFormat::Pfm } else { FileFormat::Pnm };
+    let mut header = Header {
+        data,
+        pos: 0,
+        format,
+    };
+    let magic = header.token()?;
+    let width: u32 = header.number()?;
+    let height: u32 = header.number()?;
+    let (channels, binary) = match magic {
+        "P2" => (1, false),
+        "P3" => (3, false),
+        "P5" => (1, true),
+        "P6" => (3, true),
+        "Pf" => return decode_pfm(&mut header, width, height, 1),
+        "PF" => return decode_pfm(&mut header, width, height, 3),
+        _ => return Err(ImageError::Unsupported(format!("Netpbm format {}", magic))),
+    };
+    let max: u32 = header.number()?;
+    if max == 0 || max > 65535 {
+        return Err(decode_error(format, "invalid maximum value"));
+    }
+    let truncated = || decode_error(format, "truncated data");
+    let n = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(channels)).ok_or_else(truncated)?;
+    let mut samples;
+    if binary {
+        let size = if max > 255 { 2 } else { 1 };
+        let body = n.checked_mul(size).and_then(|len| header.body().get(..len)).ok_or_else(truncated)?;
+        samples = Vec::with_capacity(n);
+        if size == 2 {
+            samples.extend(body.chunks(2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32));
+        } else {
+            samples.extend(body.iter().map(|&b| b as u32));
+        }
+    } else {
+        // Each sample takes at least a digit and a space.
+        samples = Vec::with_capacity(n.min(data.len() / 2));
+        for _ in 0..n {
+            samples.push(header.number()?);
+        }
+    }
+    if samples.iter().any(|&s| s > max) {
+        return Err(decode_error(format, "sample exceeds the maximum value"));
+    }
+
+    let pixel_format = match (max > 255, channels) {
+        (true, _) => PixelFormat::Rgba32F,
+        (false, 1) => PixelFormat::R8,
+        _ => PixelFormat::Rgba8,
+    };
+    let mut image = new_image(format, width, height, pixel_format)?;
+    for (i, p) in samples.chunks(channels).enumerate() {
+        let c = |k: usize| p[k.min(channels - 1)] as f32 / max as f32;
+        image.set_pixel(i as u32 % width, i as u32 / width, Vec4(c(0), c(1), c(2), 1.0));
+    }
+    Ok(image)
+}
+
+fn decode_pfm(header: &mut Header, width: u32, height: u32, channels: usize) -> Result<Image, ImageError> {
+    let scale: f32 = header.number()?;
+    let body = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(4 * channels))
+        .and_then(|len| header.body().get(..len))
+        .ok_or_else(|| decode_error(FileFormat::Pfm, "truncated data"))?;
+    let sample = |i: usize| {
+        let b = [body[4 * i], body[4 * i + 1], body[4 * i + 2], body[4 * i + 3]];
+        if scale < 0.0 { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+    };
+    let mut image = new_image(FileFormat::Pfm, width, height, PixelFormat::Rgba32F)?;
+    for y in 0..height {
+        for x in 0..width {
+            // Rows are stored from the bottom.
+            let i = ((height - 1 - y) as usize * width as usize + x as usize) * channels;
+            let c = |k: usize| sample(i + k.min(channels - 1));
+            image.set_pixel(x, y, Vec4(c(0), c(1), c(2), 1.0));
+        }
+    }
+    Ok(image)
+}
+
+pub fn encode_pnm<W: Write>(w: &mut W, image: &Image) -> io::Result<()> {
+    let (width, height) = (image.width(), image.height());
+    if image.format() == PixelFormat::R8 {
+        write!(w, "P5\n{} {}\n255\n", width, height)?;
+        for y in 0..height {
+            w.write_all(image.row(y))?;
+        }
+        return Ok(());
+    }
+    write!(w, "P6\n{} {}\n255\n", width, height)?;
+    let mut row = Vec::with_capacity(3 * width as usize);
+    for y in 0..height {
+        row.clear();
+        for x in 0..width {
+            let p = image.pixel(x, y);
+            row.extend_from_slice(&[unorm8(p.0), unorm8(p.1), unorm8(p.2)]);
+        }
+        w.write_all(&row)?;
+    }
+    Ok(())
+}
+
+pub fn encode_pfm<W: Write>(w: &mut W, image: &Image) -> io::Result<()> {
+    let (width, height) = (image.width(), image.height());
+    let grey = image.format().channels() == 1;
+    // A negative scale means little endian.
+    write!(w, "{}\n{} {}\n-1.0\n", if grey { "Pf" } else { "PF" }, width, height)?;
+    let mut row = Vec::new();
+    for y in (0..height).rev() {
+        row.clear();
+        for x in 0..width {
+            let p = image.pixel(x, y);
+            let channels = if grey { &[p.0][..] } else { &[p.0, p.1, p.2][..] };
+            for c in channels {
+                row.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        w.write_all(&row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec4;
+    use super::super::{ImageError, PixelFormat};
+    use super::decode;
+
+    #[test]
+    fn variants() {
+        // A comment, and a 16-bit graymap.
+        let mut data = b"P5 # grey\n2 1\n# max\n1000\n".to_vec();
+        data.extend_from_slice(&[0x01, 0xf4, 0x03, 0xe8]);
+        let image = decode(&data).unwrap();
+        assert_eq!(image.format(), PixelFormat::Rgba32F);
+        assert_eq!(image.pixel(0, 0), Vec4(0.5, 0.5, 0.5, 1.0));
+
+        let image = decode(b"P2 2 2 4 0 1 2 4").unwrap();
+        assert_eq!(image.format(), PixelFormat::R8);
+        assert_eq!(image.data(), &[0, 64, 128, 255]);
+
+        // A big endian float map, with its bottom row first.
+        let mut data = b"Pf\n1 2\n1.0\n".to_vec();
+        data.extend_from_slice(&2f32.to_be_bytes());
+        data.extend_from_slice(&0.25f32.to_be_bytes());
+        let image = decode(&data).unwrap();
+        assert_eq!(image.pixel(0, 0), Vec4(0.25, 0.25, 0.25, 1.0));
+        assert_eq!(image.pixel(0, 1), Vec4(2.0, 2.0, 2.0, 1.0));
+
+        match decode(b"P6 2 2 255 abc") {
+            Err(ImageError::Decode { .. }) => {}
+            other => panic!("{:?}", other),
+        }
+        match decode(b"P3 1 1 255 0 300 0") {
+            Err(ImageError::Decode { .. }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
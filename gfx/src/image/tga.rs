@@ -0,0 +1,165 @@
+use std::io::{self, Write};
+use super::{decode_error, new_image, unorm8, FileFormat, Image, ImageError, PixelFormat};
+
+fn error(message: &str) -> ImageError {
+    decode_error(FileFormat::Tga, message)
+}
+
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    let h = data.get(..18).ok_or_else(|| error("truncated header"))?;
+    let u16_at = |i: usize| u16::from_le_bytes([h[i], h[i + 1]]) as usize;
+    let (id_len, color_map, kind) = (h[0] as usize, h[1], h[2]);
+    let (map_len, map_entry_bits) = (u16_at(5), h[7] as usize);
+    let (width, height, bits, descriptor) = (u16_at(12), u16_at(14), h[16], h[17]);
+    let (grey, rle) = match kind {
+        2 => (false, false),
+        3 => (true, false),
+        10 => (false, true),
+        11 => (true, true),
+        _ => return Err(ImageError::Unsupported(format!("TGA image type {}", kind))),
+    };
+    let size = match (grey, bits) {
+        (true, 8) => 1,
+        (false, 16) => 2,
+        (false, 24) => 3,
+        (false, 32) => 4,
+        _ => return Err(ImageError::Unsupported(format!("TGA pixel depth {}", bits))),
+    };
+    // Skip the image ID and any (unused) color map.
+    let map_size = if color_map == 1 { map_len * map_entry_bits.div_ceil(8) } else { 0 };
+    let mut pos = 18 + id_len + map_size;
+
+    // A run-length packet of 1 + size bytes holds at most 128 pixels, so the
+    // data bounds the size of the image.
+    let n = width * height;
+    let rest = data.len().saturating_sub(pos);
+    if n > if rle { rest.div_ceil(1 + size) * 128 } else { rest / size } {
+        return Err(error("truncated data"));
+    }
+    let mut pixels = Vec::with_capacity(n * size);
+    while pixels.len() < n * size {
+        let (count, repeat) = if rle {
+            let packet = *data.get(pos).ok_or_else(|| error("truncated data"))?;
+            pos += 1;
+            ((packet & 0x7f) as usize + 1, packet & 0x80 != 0)
+        } else {
+            (n, false)
+        };
+        let len = if repeat { size } else { count * size };
+        let src = data.get(pos..pos + len).ok_or_else(|| error("truncated data"))?;
+        pos += len;
+        if repeat {
+            for _ in 0..count {
+                pixels.extend_from_slice(src);
+            }
+        } else {
+            pixels.extend_from_slice(src);
+        }
+    }
+    pixels.truncate(n * size);
+
+    let format = if grey { PixelFormat::R8 } else { PixelFormat::Rgba8 };
+    let mut image = new_image(FileFormat::Tga, width as u32, height as u32, format)?;
+    let out_size = image.format().pixel_size();
+    for (i, p) in pixels.chunks(size).enumerate() {
+        // Rows are stored from the bottom, and columns from the left,
+        // unless the descriptor says otherwise.
+        let (mut x, mut y) = (i % width, i / width);
+        if descriptor & 0x10 != 0 {
+            x = width - 1 - x;
+        }
+        if descriptor & 0x20 == 0 {
+            y = height - 1 - y;
+        }
+        let rgba = match size {
+            1 => [p[0], 0, 0, 0],
+            2 => {
+                let v = u16::from_le_bytes([p[0], p[1]]);
+                let c = |shift: u16| (((v >> shift) & 0x1f) as u32 * 255 / 31) as u8;
+                [c(10), c(5), c(0), if v & 0x8000 != 0 { 255 } else { 0 }]
+            }
+            3 => [p[2], p[1], p[0], 255],
+            _ => [p[2], p[1], p[0], p[3]],
+        };
+        let row = image.row_mut(y as u32);
+        row[x * out_size..(x + 1) * out_size].copy_from_slice(&rgba[..out_size]);
+    }
+    Ok(image)
+}
+
+pub fn encode<W: Write>(w: &mut W, image: &Image) -> io::Result<()> {
+    let grey = image.format() == PixelFormat::R8;
+    let (kind, bits, descriptor) = if grey { (11, 8, 0x20) } else { (10, 32, 0x28) };
+    let mut header = [0u8; 18];
+    header[2] = kind;
+    header[12..14].copy_from_slice(&(image.width() as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(image.height() as u16).to_le_bytes());
+    header[16] = bits;
+    header[17] = descriptor;
+    w.write_all(&header)?;
+
+    let size = if grey { 1 } else { 4 };
+    let mut row = Vec::with_capacity(image.width() as usize * size);
+    for y in 0..image.height() {
+        row.clear();
+        if grey {
+            row.extend_from_slice(image.row(y));
+        } else {
+            for x in 0..image.width() {
+                let p = image.pixel(x, y);
+                row.extend_from_slice(&[unorm8(p.2), unorm8(p.1), unorm8(p.0), unorm8(p.3)]);
+            }
+        }
+        write_rle(w, &row, size)?;
+    }
+    Ok(())
+}
+
+// write_rle writes a row of pixels as run-length encoded packets.
+fn write_rle<W: Write>(w: &mut W, row: &[u8], size: usize) -> io::Result<()> {
+    let pixels: Vec<&[u8]> = row.chunks(size).collect();
+    let run = |i: usize| pixels[i..].iter().take(128).take_while(|&&p| p == pixels[i]).count();
+    let mut i = 0;
+    while i < pixels.len() {
+        let n = run(i);
+        if n > 1 {
+            w.write_all(&[0x80 | (n - 1) as u8])?;
+            w.write_all(pixels[i])?;
+            i += n;
+            continue;
+        }
+        // Collect pixels up to the next run.
+        let mut n = 1;
+        while i + n < pixels.len() && n < 128 && run(i + n) == 1 {
+            n += 1;
+        }
+        w.write_all(&[(n - 1) as u8])?;
+        w.write_all(&row[i * size..(i + n) * size])?;
+        i += n;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::PixelFormat;
+    use super::decode;
+
+    #[test]
+    fn bottom_up() {
+        // An uncompressed 24-bit image with an ID, stored from the bottom.
+        let mut data = vec![2, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 24, 0, b'i', b'd'];
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        let image = decode(&data).unwrap();
+        assert_eq!(image.format(), PixelFormat::Rgba8);
+        assert_eq!(image.row(0), &[9, 8, 7, 255, 12, 11, 10, 255]);
+        assert_eq!(image.row(1), &[3, 2, 1, 255, 6, 5, 4, 255]);
+
+        // A run-length encoded greymap, with a run and raw packet, and
+        // packets crossing rows.
+        let data = [0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 2, 0, 8, 0x20, 0x83, 7, 0x01, 1, 2];
+        let image = decode(&data).unwrap();
+        assert_eq!(image.data(), &[7, 7, 7, 7, 1, 2]);
+        assert!(decode(&data[..21]).is_err());
+    }
+}
@@ -0,0 +1,400 @@
+// The zlib stream format (RFC 1950) with DEFLATE compression (RFC 1951),
+// and the CRC-32 checksum, as used by PNG.
+
+// The base lengths and extra bits of the length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99,
+                                115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+// The base distances and extra bits of the distance codes 0 to 29.
+const DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025,
+                              1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12,
+                              12, 13, 13];
+
+// The order in which code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// crc32 returns the CRC-32 of the data, continuing from a previous CRC.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in data {
+        c ^= b as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+    }
+    !c
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &x in chunk {
+            a += x as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// BitReader reads bits from the least significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, n: u32) -> Result<u32, String> {
+        let mut v = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.pos).ok_or("truncated compressed data")?;
+            v |= (((byte >> self.bit) & 1) as u32) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(v)
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+// Huffman is a canonical Huffman code, as the number of codes of each
+// length and the symbols in order of their codes.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0u16; 16];
+        for &l in lengths {
+            counts[l as usize] += 1;
+        }
+        counts[0] = 0;
+        // Reject over-subscribed codes.
+        let mut left = 1i32;
+        for &c in &counts[1..] {
+            left = 2 * left - c as i32;
+            if left < 0 {
+                return Err("invalid Huffman code".to_string());
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for l in 1..15 {
+            offsets[l + 1] = offsets[l] + counts[l];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (s, &l) in lengths.iter().enumerate() {
+            if l != 0 {
+                symbols[offsets[l as usize] as usize] = s as u16;
+                offsets[l as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, r: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= r.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+// fixed_lengths returns the code lengths of the fixed literal/length code.
+fn fixed_lengths() -> [u8; 288] {
+    let mut lengths = [0u8; 288];
+    for (i, l) in lengths.iter_mut().enumerate() {
+        *l = match i {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    lengths
+}
+
+// inflate decompresses a zlib stream.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 || data[0] & 0x0f != 8 || !((data[0] as u16) << 8 | data[1] as u16).is_multiple_of(31) {
+        return Err("invalid zlib header".to_string());
+    }
+    if data[1] & 0x20 != 0 {
+        return Err("zlib preset dictionaries are unsupported".to_string());
+    }
+    let mut r = BitReader {
+        data: &data[2..],
+        pos: 0,
+        bit: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.align();
+                let header = r.data.get(r.pos..r.pos + 4).ok_or("truncated compressed data")?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                if len != !u16::from_le_bytes([header[2], header[3]]) as usize {
+                    return Err("invalid stored block length".to_string());
+                }
+                r.pos += 4;
+                out.extend_from_slice(r.data.get(r.pos..r.pos + len).ok_or("truncated compressed data")?);
+                r.pos += len;
+            }
+            1 => {
+                let lit = Huffman::new(&fixed_lengths())?;
+                let dist = Huffman::new(&[5; 30])?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_codes(&mut r)?;
+                inflate_block(&mut r, &mut out, &lit, &dist)?;
+            }
+            _ => return Err("invalid block type".to_string()),
+        }
+        if last {
+            break;
+        }
+    }
+    r.align();
+    let sum = r.data.get(r.pos..r.pos + 4).ok_or("missing zlib checksum")?;
+    if u32::from_be_bytes([sum[0], sum[1], sum[2], sum[3]]) != adler32(&out) {
+        return Err("zlib checksum mismatch".to_string());
+    }
+    Ok(out)
+}
+
+fn dynamic_codes(r: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let nlen = r.bits(5)? as usize + 257;
+    let ndist = r.bits(5)? as usize + 1;
+    let ncode = r.bits(4)? as usize + 4;
+    let mut lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..ncode] {
+        lengths[i] = r.bits(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+    let mut lengths = Vec::with_capacity(nlen + ndist);
+    while lengths.len() < nlen + ndist {
+        let (value, repeat) = match code.decode(r)? {
+            s @ 0..=15 => (s as u8, 1),
+            16 => (*lengths.last().ok_or("repeat of no code length")?, 3 + r.bits(2)?),
+            17 => (0, 3 + r.bits(3)?),
+            _ => (0, 11 + r.bits(7)?),
+        };
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+    if lengths.len() > nlen + ndist {
+        return Err("too many code lengths".to_string());
+    }
+    Ok((Huffman::new(&lengths[..nlen])?, Huffman::new(&lengths[nlen..])?))
+}
+
+fn inflate_block(r: &mut BitReader, out: &mut Vec<u8>, lit: &Huffman, dist: &Huffman) -> Result<(), String> {
+    loop {
+        let s = lit.decode(r)? as usize;
+        match s {
+            0..=255 => out.push(s as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = s - 257;
+                if i >= 29 {
+                    return Err("invalid length code".to_string());
+                }
+                let len = LENGTH_BASE[i] as usize + r.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                let d = dist.decode(r)? as usize;
+                if d >= 30 {
+                    return Err("invalid distance code".to_string());
+                }
+                let distance = DIST_BASE[d] as usize + r.bits(DIST_EXTRA[d] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("distance too far back".to_string());
+                }
+                let start = out.len() - distance;
+                for k in 0..len {
+                    let b = out[start + k];
+                    out.push(b);
+                }
+            }
+        }
+    }
+}
+
+// BitWriter writes bits from the least significant first.
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    n: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, v: u32, n: u32) {
+        self.acc |= v << self.n;
+        self.n += n;
+        while self.n >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.n -= 8;
+        }
+    }
+
+    // code writes a Huffman code, which is stored most significant bit
+    // first.
+    fn code(&mut self, code: u32, len: u32) {
+        let reversed = code.reverse_bits() >> (32 - len);
+        self.bits(reversed, len);
+    }
+
+    // literal writes a symbol of the fixed literal/length code.
+    fn literal(&mut self, s: u32) {
+        match s {
+            0..=143 => self.code(0x30 + s, 8),
+            144..=255 => self.code(0x190 + s - 144, 9),
+            256..=279 => self.code(s - 256, 7),
+            _ => self.code(0xc0 + s - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.n > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+// The size of the window searched for matches, and of the hash table.
+const WINDOW: usize = 32768;
+const HASH_BITS: u32 = 15;
+
+// hash returns the hash of the three bytes at i.
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as u32) | (data[i + 1] as u32) << 8 | (data[i + 2] as u32) << 16;
+    (v.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+// insert adds position i to the hash chains.
+fn insert(data: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + 3 <= data.len() {
+        let h = hash(data, i);
+        prev[i % WINDOW] = head[h];
+        head[h] = i;
+    }
+}
+
+// deflate compresses the data into a zlib stream, using a single block with
+// the fixed Huffman code and greedy matching.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter {
+        out: vec![0x78, 0x01],
+        acc: 0,
+        n: 0,
+    };
+    w.bits(1, 1);
+    w.bits(1, 2);
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let mut i = 0;
+    while i < data.len() {
+        // Find the longest match in the last few candidates.
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + 3 <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let mut tries = 32;
+            while candidate != usize::MAX && i - candidate <= WINDOW && tries > 0 {
+                let max = (data.len() - i).min(258);
+                let len = (0..max).take_while(|&k| data[candidate + k] == data[i + k]).count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                }
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                tries -= 1;
+            }
+        }
+        if best_len >= 3 {
+            let l = LENGTH_BASE.iter().rposition(|&b| b as usize <= best_len).unwrap();
+            w.literal(257 + l as u32);
+            w.bits((best_len - LENGTH_BASE[l] as usize) as u32, LENGTH_EXTRA[l] as u32);
+            let d = DIST_BASE.iter().rposition(|&b| b as usize <= best_dist).unwrap();
+            w.code(d as u32, 5);
+            w.bits((best_dist - DIST_BASE[d] as usize) as u32, DIST_EXTRA[d] as u32);
+            for k in i..i + best_len {
+                insert(data, k, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            w.literal(data[i] as u32);
+            insert(data, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    w.literal(256);
+    let mut out = w.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32, deflate, inflate};
+
+    #[test]
+    fn round_trip() {
+        let mut text = b"the quick brown fox jumps over the lazy dog; ".repeat(40);
+        text.extend((0..3000u32).map(|i| (i * i % 251) as u8));
+        text.extend(vec![7; 1000]);
+        for data in &[&b""[..], &b"a"[..], &text[..]] {
+            let compressed = deflate(data);
+            assert_eq!(&inflate(&compressed).unwrap()[..], *data);
+        }
+        assert!(deflate(&text).len() < text.len() / 2);
+    }
+
+    #[test]
+    fn stored_and_dynamic() {
+        // Data in a stored block, and in a dynamic Huffman block, as written
+        // by zlib.
+        let stored = [0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2c, 0x02, 0x15];
+        assert_eq!(inflate(&stored).unwrap(), b"hello");
+        let dynamic = [0x78, 0xda, 0x95, 0xca, 0xc1, 0x0d, 0x80, 0x30, 0x08, 0x00, 0xc0, 0x55, 0x98, 0xc0, 0x9d, 0x5a,
+                       0x4a, 0x04, 0x45, 0xa8, 0x01, 0xad, 0x3a, 0xbd, 0xae, 0xe0, 0xf7, 0x72, 0xc9, 0x04, 0xed, 0xb6,
+                       0xb2, 0x09, 0x42, 0x55, 0xc7, 0x15, 0xd8, 0xb5, 0x05, 0x24, 0x4b, 0x40, 0x90, 0x25, 0x19, 0x12,
+                       0xe4, 0x10, 0xa4, 0xe9, 0xc3, 0x1f, 0xb9, 0xb0, 0x9f, 0xb8, 0xec, 0x17, 0x69, 0x3c, 0xb3, 0x1d,
+                       0x55, 0xfa, 0x68, 0x2f, 0xda, 0x52, 0x29, 0x87];
+        let mut want = b"the dynamic block holds this sentence twice. ".repeat(2);
+        want.extend((0..20u8).map(|i| i * 7 % 26 + 97));
+        assert_eq!(inflate(&dynamic).unwrap(), want);
+        assert!(inflate(&stored[..10]).is_err());
+        assert_eq!(crc32(0, b"IEND"), 0xae42_6082);
+    }
+}
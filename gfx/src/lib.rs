@@ -1,3 +1,22 @@
-#[test]
-fn it_works() {
-}
+#[macro_use]
+extern crate fiz_math;
+
+pub use fiz_math as math;
+
+pub mod mesh;
+pub mod obj;
+pub mod gltf;
+pub mod stl;
+pub mod ply;
+pub mod raster;
+pub mod device;
+pub mod image;
+pub mod sampler;
+pub mod compare;
+pub mod brdf;
+pub mod light;
+pub mod shadow;
+mod units;
+
+pub use self::mesh::{Mesh, MeshError, SkinWeights};
+pub use self::units::Units;
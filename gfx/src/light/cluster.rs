@@ -0,0 +1,402 @@
+use math::{Aabb, Frustum, Vec2, Vec3};
+use math::unit::{M, Rad};
+use super::{meters, Light};
+
+/// Perspective describes a perspective camera, whose view is divided into
+/// clusters by a `FroxelGrid`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Perspective {
+    /// The position of the camera.
+    pub eye: Vec3<M<f32>>,
+
+    /// The direction the camera looks in.
+    pub forward: Vec3<f32>,
+
+    /// The upwards direction of the view, which mustn't be parallel to
+    /// forward.
+    pub up: Vec3<f32>,
+
+    /// The vertical field of view.
+    pub fov_y: Rad<f32>,
+
+    /// The aspect ratio of the view (width / height).
+    pub aspect: f32,
+
+    /// The distance to the near clipping plane.
+    pub near: M<f32>,
+
+    /// The distance to the far clipping plane.
+    pub far: M<f32>,
+}
+
+impl Perspective {
+    /// frustum returns the frustum of the view.
+    pub fn frustum(&self) -> Frustum<f32> {
+        Frustum::perspective(meters(self.eye),
+                             self.forward,
+                             self.up,
+                             self.fov_y,
+                             self.aspect,
+                             self.near.0,
+                             self.far.0)
+    }
+}
+
+/// FroxelGrid divides the frustum of a camera into a grid of clusters
+/// ("froxels", frustum voxels): screen-space tiles, sliced by depth. Slices
+/// are spaced exponentially, so that clusters are roughly cubic at all
+/// depths.
+///
+/// Clusters are numbered from the top left of the screen, nearest to the
+/// camera, and stored with x varying fastest, then y, then depth.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::{Vec2, Vec3};
+/// use fiz_gfx::math::unit::{Deg, M, ToRad};
+/// use fiz_gfx::light::{FroxelGrid, Light, Lumens, Perspective, PointLight};
+///
+/// let view = Perspective {
+///     eye: Vec3(M(0.0), M(0.0), M(0.0)),
+///     forward: Vec3(0.0, 0.0, -1.0),
+///     up: Vec3(0.0, 1.0, 0.0),
+///     fov_y: Deg(60.0).to_rad(),
+///     aspect: 16.0 / 9.0,
+///     near: M(0.1),
+///     far: M(100.0),
+/// };
+/// let grid = FroxelGrid::new(&view, 16, 9, 24);
+///
+/// let lamp = PointLight {
+///     position: Vec3(M(0.0), M(0.0), M(-10.0)),
+///     color: Vec3(1.0, 1.0, 1.0),
+///     power: Lumens(800.0),
+///     range: M(1.0),
+/// };
+/// let clusters = grid.assign(&[Light::Point(lamp)]);
+///
+/// // The lamp lights the center of the screen around its depth, only.
+/// let (x, y, z) = grid.cluster_at(Vec2(0.5, 0.5), M(10.0)).unwrap();
+/// assert_eq!(clusters.lights(x, y, z), &[0]);
+/// assert!(clusters.lights(x, y, z + 2).is_empty());
+/// assert!(clusters.lights(0, 0, z).is_empty());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FroxelGrid {
+    width: u32,
+    height: u32,
+    depth: u32,
+    eye: Vec3<f32>,
+    right: Vec3<f32>,
+    up: Vec3<f32>,
+    forward: Vec3<f32>,
+    tan_x: f32,
+    tan_y: f32,
+    near: f32,
+    far: f32,
+}
+
+impl FroxelGrid {
+    /// new returns a grid dividing the view into width by height tiles, and
+    /// depth slices.
+    ///
+    /// # Panics
+    ///
+    /// new panics if a dimension is zero, the near distance isn't positive
+    /// and less than the far distance, or the forward or up vectors are
+    /// zero or parallel.
+    pub fn new(view: &Perspective, width: u32, height: u32, depth: u32) -> Self {
+        assert!(width > 0 && height > 0 && depth > 0, "FroxelGrid::new: empty grid");
+        assert!(view.near.0 > 0.0 && view.near.0 < view.far.0,
+                "FroxelGrid::new: invalid near and far distances");
+        let forward = view.forward.normalize().expect("FroxelGrid::new: zero forward vector");
+        let right = forward.cross(view.up).normalize().expect("FroxelGrid::new: up is parallel to forward");
+        let tan_y = (0.5 * view.fov_y.0).tan();
+        FroxelGrid {
+            width,
+            height,
+            depth,
+            eye: meters(view.eye),
+            right,
+            up: right.cross(forward),
+            forward,
+            tan_x: tan_y * view.aspect,
+            tan_y,
+            near: view.near.0,
+            far: view.far.0,
+        }
+    }
+
+    /// dimensions returns the number of tiles across and down, and of depth
+    /// slices.
+    pub fn dimensions(&self) -> (u32, u32, u32) {
+        (self.width, self.height, self.depth)
+    }
+
+    /// slice_depth returns the distance from the camera of the near plane of
+    /// a depth slice, where the slice after the last is the far plane.
+    pub fn slice_depth(&self, slice: u32) -> M<f32> {
+        M(self.near * (self.far / self.near).powf(slice as f32 / self.depth as f32))
+    }
+
+    /// slice returns the depth slice at the given distance from the camera,
+    /// or None outside the near and far planes.
+    pub fn slice(&self, depth: M<f32>) -> Option<u32> {
+        if !(depth.0 >= self.near && depth.0 < self.far) {
+            return None;
+        }
+        let s = (depth.0 / self.near).ln() / (self.far / self.near).ln() * self.depth as f32;
+        Some((s as u32).min(self.depth - 1))
+    }
+
+    /// cluster_at returns the cluster at a position on the screen, given in
+    /// [0, 1] from the top left, and at a distance from the camera, as shaders
+    /// find it for a fragment. It returns None off the screen, and outside
+    /// the near and far planes.
+    pub fn cluster_at(&self, uv: Vec2<f32>, depth: M<f32>) -> Option<(u32, u32, u32)> {
+        if !(0.0..=1.0).contains(&uv.0) || !(0.0..=1.0).contains(&uv.1) {
+            return None;
+        }
+        let tile = |t: f32, n: u32| ((t * n as f32) as u32).min(n - 1);
+        Some((tile(uv.0, self.width), tile(uv.1, self.height), self.slice(depth)?))
+    }
+
+    /// cluster_of returns the cluster containing a point, or None if it's
+    /// outside the frustum.
+    pub fn cluster_of(&self, p: Vec3<M<f32>>) -> Option<(u32, u32, u32)> {
+        let v = self.to_view(meters(p));
+        if v.2 <= 0.0 {
+            return None;
+        }
+        let uv = Vec2(0.5 + 0.5 * v.0 / (v.2 * self.tan_x), 0.5 - 0.5 * v.1 / (v.2 * self.tan_y));
+        self.cluster_at(uv, M(v.2))
+    }
+
+    // to_view returns a point relative to the camera: along its right, up
+    // and forward directions.
+    fn to_view(&self, p: Vec3<f32>) -> Vec3<f32> {
+        let d = p - self.eye;
+        Vec3(d.dot(self.right), d.dot(self.up), d.dot(self.forward))
+    }
+
+    /// bounds returns a box enclosing a cluster, relative to the camera:
+    /// along its right, up and forward directions.
+    pub fn bounds(&self, x: u32, y: u32, z: u32) -> Aabb<f32> {
+        let (z0, z1) = (self.slice_depth(z).0, self.slice_depth(z + 1).0);
+        let ndc_x = |i: u32| 2.0 * i as f32 / self.width as f32 - 1.0;
+        let ndc_y = |j: u32| 1.0 - 2.0 * j as f32 / self.height as f32;
+        let mut corners = Vec::with_capacity(8);
+        for &d in &[z0, z1] {
+            for &sx in &[ndc_x(x), ndc_x(x + 1)] {
+                for &sy in &[ndc_y(y), ndc_y(y + 1)] {
+                    corners.push(Vec3(sx * self.tan_x * d, sy * self.tan_y * d, d));
+                }
+            }
+        }
+        Aabb::from_points(&corners).unwrap()
+    }
+
+    /// assign returns the lights reaching each cluster, by the indices of the
+    /// lights. The test is conservative: lights are assigned to every
+    /// cluster their bounds (see `Light::bounds`) overlap the box of.
+    /// Directional lights reach every cluster, so they're listed once,
+    /// separately.
+    pub fn assign(&self, lights: &[Light]) -> LightClusters {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut clusters = vec![Vec::new(); w * h * self.depth as usize];
+        let mut directional = Vec::new();
+        for (i, light) in lights.iter().enumerate() {
+            let (center, radius) = match light.bounds() {
+                Some(b) => b,
+                None => {
+                    directional.push(i as u32);
+                    continue;
+                }
+            };
+            let (c, r) = (self.to_view(meters(center)), radius.0);
+            if c.2 + r < self.near || c.2 - r >= self.far {
+                continue;
+            }
+            let first = self.slice(M((c.2 - r).max(self.near))).unwrap();
+            let last = self.slice(M(c.2 + r)).unwrap_or(self.depth - 1);
+            for z in first..=last {
+                for y in 0..self.height {
+                    for x in 0..self.width {
+                        if self.bounds(x, y, z).distance_sq(c) <= r * r {
+                            clusters[(z as usize * h + y as usize) * w + x as usize].push(i as u32);
+                        }
+                    }
+                }
+            }
+        }
+        let mut ranges = Vec::with_capacity(clusters.len());
+        let mut indices = Vec::new();
+        for c in clusters {
+            ranges.push((indices.len() as u32, c.len() as u32));
+            indices.extend(c);
+        }
+        LightClusters {
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            ranges,
+            indices,
+            directional,
+        }
+    }
+}
+
+/// LightClusters are the lights assigned to each cluster of a `FroxelGrid`,
+/// as a flat list of light indices and the range of it of each cluster, to
+/// be uploaded to GPU buffers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightClusters {
+    width: u32,
+    height: u32,
+    depth: u32,
+    ranges: Vec<(u32, u32)>,
+    indices: Vec<u32>,
+    directional: Vec<u32>,
+}
+
+impl LightClusters {
+    /// lights returns the indices of the lights of a cluster.
+    ///
+    /// # Panics
+    ///
+    /// lights panics if the cluster is out of range.
+    pub fn lights(&self, x: u32, y: u32, z: u32) -> &[u32] {
+        assert!(x < self.width && y < self.height && z < self.depth,
+                "cluster ({}, {}, {}) out of range",
+                x,
+                y,
+                z);
+        let (offset, count) = self.ranges[((z * self.height + y) * self.width + x) as usize];
+        &self.indices[offset as usize..(offset + count) as usize]
+    }
+
+    /// ranges returns the offset into `indices` and number of lights of each
+    /// cluster, in the order of the clusters.
+    pub fn ranges(&self) -> &[(u32, u32)] {
+        &self.ranges
+    }
+
+    /// indices returns the light indices of all clusters.
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// directional returns the indices of the directional lights, which
+    /// reach every cluster.
+    pub fn directional(&self) -> &[u32] {
+        &self.directional
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec3;
+    use math::unit::{Deg, M, ToRad};
+    use super::super::*;
+
+    fn view() -> Perspective {
+        Perspective {
+            eye: Vec3(M(1.0), M(2.0), M(3.0)),
+            forward: Vec3(1.0, 0.0, -1.0),
+            up: Vec3(0.0, 1.0, 0.0),
+            fov_y: Deg(70.0).to_rad(),
+            aspect: 1.5,
+            near: M(0.5),
+            far: M(50.0),
+        }
+    }
+
+    fn point(x: f32, y: f32, z: f32, range: f32) -> Light {
+        Light::Point(PointLight {
+            position: Vec3(M(x), M(y), M(z)),
+            color: Vec3(1.0, 1.0, 1.0),
+            power: Lumens(100.0),
+            range: M(range),
+        })
+    }
+
+    #[test]
+    fn slices() {
+        let grid = FroxelGrid::new(&view(), 8, 6, 10);
+        assert_eq!(grid.slice_depth(0), M(0.5));
+        assert!((grid.slice_depth(10).0 - 50.0).abs() < 1e-4);
+        for z in 0..10 {
+            let mid = M((grid.slice_depth(z).0 * grid.slice_depth(z + 1).0).sqrt());
+            assert_eq!(grid.slice(mid), Some(z));
+        }
+        assert_eq!(grid.slice(M(0.4)), None);
+        assert_eq!(grid.slice(M(50.0)), None);
+
+        // Points along the view direction are in the center tiles.
+        let p = Vec3(M(1.0 + 4.0), M(2.0), M(3.0 - 4.0));
+        let (x, y, z) = grid.cluster_of(p).unwrap();
+        assert_eq!((x, y, grid.slice(M(32.0f32.sqrt()))), (4, 3, Some(z)));
+        assert!(grid.bounds(x, y, z).contains(Vec3(0.0, 0.0, 32.0f32.sqrt())));
+        assert_eq!(grid.cluster_of(Vec3(M(0.0), M(2.0), M(4.0))), None);
+    }
+
+    #[test]
+    fn assignment() {
+        let v = view();
+        let grid = FroxelGrid::new(&v, 12, 8, 16);
+        let sun = Light::Directional(DirectionalLight {
+            direction: Vec3(0.0, -1.0, 0.0),
+            color: Vec3(1.0, 1.0, 1.0),
+            illuminance: Lux(100000.0),
+        });
+        let spot = SpotLight::new(Vec3(M(8.0), M(6.0), M(-4.0)), Vec3(0.0, -1.0, 0.0), Lumens(500.0), Deg(20.0),
+                                  Deg(35.0));
+        let lights = [point(6.0, 2.0, -2.0, 2.0),
+                      sun,
+                      point(1.0, 2.0, 10.0, 3.0), // Behind the camera.
+                      point(15.0, 0.0, -10.0, 6.0),
+                      Light::Spot(SpotLight { range: M(7.0), ..spot }),
+                      point(40.0, 2.0, -36.0, 1.0)]; // Beyond the far plane.
+        let clusters = grid.assign(&lights);
+        assert_eq!(clusters.directional(), &[1]);
+        assert!(!clusters.indices().contains(&2) && !clusters.indices().contains(&5));
+        assert_eq!(clusters.ranges().len(), 12 * 8 * 16);
+
+        // Every light is assigned to the clusters of every point it reaches
+        // in the frustum.
+        let frustum = v.frustum();
+        let steps = 24;
+        for i in 0..steps {
+            for j in 0..steps {
+                for k in 0..steps {
+                    let t = |n: u32| (n as f32 + 0.5) / steps as f32;
+                    let p = Vec3(-10.0 + 60.0 * t(i), -10.0 + 30.0 * t(j), -45.0 + 60.0 * t(k));
+                    let cluster = match grid.cluster_of(Vec3(M(p.0), M(p.1), M(p.2))) {
+                        Some(c) => c,
+                        None => continue,
+                    };
+                    assert!(frustum.contains(p));
+                    for (l, light) in lights.iter().enumerate() {
+                        if let Some((center, radius)) = light.bounds() {
+                            if (super::meters(center) - p).length() < radius.0 {
+                                assert!(clusters.lights(cluster.0, cluster.1, cluster.2).contains(&(l as u32)),
+                                        "light {} at {:?}",
+                                        l,
+                                        p);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Lights aren't assigned to far away clusters.
+        let (x, y, z) = grid.cluster_of(Vec3(M(6.0), M(2.0), M(-2.0))).unwrap();
+        assert!(clusters.lights(x, y, z).contains(&0));
+        let (x, y, z) = grid.cluster_of(Vec3(M(20.0), M(2.0), M(-16.0))).unwrap();
+        assert!(!clusters.lights(x, y, z).contains(&0));
+        let assigned = clusters.indices().iter().filter(|&&l| l == 0).count();
+        assert!(assigned < 12 * 8 * 16 / 10, "{}", assigned);
+    }
+}
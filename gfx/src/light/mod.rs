@@ -0,0 +1,378 @@
+//! Light sources, in physical units, and clustered light culling.
+//!
+//! Lights are measured photometrically: the total light emitted by point,
+//! spot and area lights is given in `Lumens`, and the light falling on
+//! surfaces from directional lights, such as the sun, in `Lux`. Positions
+//! and ranges are in meters.
+//!
+//! `FroxelGrid` divides the view of a camera into clusters, and assigns each
+//! cluster the lights reaching it, so that shading only considers those.
+
+mod cluster;
+
+use std::f32::consts::PI;
+use math::Vec3;
+use math::unit::{M, Rad, ToRad};
+
+pub use self::cluster::{FroxelGrid, LightClusters, Perspective};
+
+unit! {
+    /// Lumens measure luminous flux: the total light emitted by a source, as
+    /// perceived by the eye. A 60 watt incandescent bulb emits about 800 lumens.
+    Lumens
+}
+
+unit! {
+    /// Lux measure illuminance: the light falling on a surface, in lumens per
+    /// square meter. Direct sunlight is about 100,000 lux, an office about 500.
+    Lux
+}
+
+unit! {
+    /// Candela measure luminous intensity: the light emitted by a source in a
+    /// direction, in lumens per steradian.
+    Candela
+}
+
+// meters returns a position in meters as plain floats.
+fn meters(p: Vec3<M<f32>>) -> Vec3<f32> {
+    Vec3(p.0 .0, p.1 .0, p.2 .0)
+}
+
+/// distance_attenuation returns the inverse square falloff of light at the
+/// given distance from a light, smoothly windowed to reach zero at its
+/// range. Distances under a centimeter are treated as a centimeter.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::unit::M;
+/// use fiz_gfx::light::distance_attenuation;
+///
+/// assert!((distance_attenuation(M(2.0), M(100.0)) - 0.25).abs() < 1e-6);
+/// assert_eq!(distance_attenuation(M(100.0), M(100.0)), 0.0);
+/// ```
+pub fn distance_attenuation(distance: M<f32>, range: M<f32>) -> f32 {
+    let (d, r) = (distance.0.max(0.01), range.0);
+    let window = (1.0 - (d / r).powi(4)).clamp(0.0, 1.0);
+    window * window / (d * d)
+}
+
+/// DirectionalLight is light from a source so distant its rays are
+/// parallel, such as the sun.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// The normalized direction the light travels in.
+    pub direction: Vec3<f32>,
+
+    /// The linear RGB color of the light.
+    pub color: Vec3<f32>,
+
+    /// The illuminance of surfaces facing the light.
+    pub illuminance: Lux<f32>,
+}
+
+/// PointLight is light emitted equally in all directions from a point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointLight {
+    /// The position of the light.
+    pub position: Vec3<M<f32>>,
+
+    /// The linear RGB color of the light.
+    pub color: Vec3<f32>,
+
+    /// The total light emitted.
+    pub power: Lumens<f32>,
+
+    /// The distance beyond which the light is ignored.
+    pub range: M<f32>,
+}
+
+impl PointLight {
+    /// intensity returns the luminous intensity of the light in every
+    /// direction.
+    pub fn intensity(&self) -> Candela<f32> {
+        Candela(self.power.0 / (4.0 * PI))
+    }
+
+    /// illuminance_at returns the illuminance of a surface at p facing the
+    /// light.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::math::unit::M;
+    /// use fiz_gfx::light::{Lumens, PointLight};
+    ///
+    /// let bulb = PointLight {
+    ///     position: Vec3(M(0.0), M(2.0), M(0.0)),
+    ///     color: Vec3(1.0, 1.0, 1.0),
+    ///     power: Lumens(800.0),
+    ///     range: M(10.0),
+    /// };
+    /// // 800 / (4 pi) candela, 2 meters away.
+    /// let e = bulb.illuminance_at(Vec3(M(0.0), M(0.0), M(0.0)));
+    /// assert!((e.0 - 15.9).abs() < 0.1);
+    /// ```
+    pub fn illuminance_at(&self, p: Vec3<M<f32>>) -> Lux<f32> {
+        let d = M((meters(p) - meters(self.position)).length());
+        Lux(self.intensity().0 * distance_attenuation(d, self.range))
+    }
+}
+
+/// SpotLight is light emitted from a point in a cone.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpotLight {
+    /// The position of the light.
+    pub position: Vec3<M<f32>>,
+
+    /// The normalized direction of the axis of the cone.
+    pub direction: Vec3<f32>,
+
+    /// The linear RGB color of the light.
+    pub color: Vec3<f32>,
+
+    /// The total light emitted.
+    pub power: Lumens<f32>,
+
+    /// The distance beyond which the light is ignored.
+    pub range: M<f32>,
+
+    /// The angle from the axis within which the light is at full intensity.
+    pub inner_angle: Rad<f32>,
+
+    /// The angle from the axis of the edge of the cone, at most a right
+    /// angle. The light fades out between the inner and outer angles.
+    pub outer_angle: Rad<f32>,
+}
+
+impl SpotLight {
+    /// new returns a white spot light with the given inner and outer angles,
+    /// and the range at which the light falls to a hundredth of a lux, a
+    /// fraction of full moonlight.
+    pub fn new<A: ToRad<Output = f32>>(position: Vec3<M<f32>>,
+                                        direction: Vec3<f32>,
+                                        power: Lumens<f32>,
+                                        inner_angle: A,
+                                        outer_angle: A)
+                                        -> Self {
+        let mut light = SpotLight {
+            position,
+            direction,
+            color: Vec3(1.0, 1.0, 1.0),
+            power,
+            range: M(0.0),
+            inner_angle: inner_angle.to_rad(),
+            outer_angle: outer_angle.to_rad(),
+        };
+        light.range = M((light.intensity().0 / 0.01).sqrt());
+        light
+    }
+
+    // outer returns the outer angle, limited to a right angle.
+    fn outer(&self) -> f32 {
+        self.outer_angle.0.clamp(1e-4, 0.5 * PI)
+    }
+
+    /// intensity returns the luminous intensity of the light within the
+    /// inner cone, where the power is spread over the outer cone.
+    pub fn intensity(&self) -> Candela<f32> {
+        Candela(self.power.0 / (2.0 * PI * (1.0 - self.outer().cos())))
+    }
+
+    /// angle_attenuation returns how much of the intensity of the light is
+    /// emitted in the given normalized direction from it: one within the
+    /// inner cone, zero outside the outer cone, and fading smoothly between.
+    pub fn angle_attenuation(&self, dir: Vec3<f32>) -> f32 {
+        let (cos_outer, cos_inner) = (self.outer().cos(), self.inner_angle.0.min(self.outer()).cos());
+        let t = ((dir.dot(self.direction) - cos_outer) / (cos_inner - cos_outer).max(1e-4)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// illuminance_at returns the illuminance of a surface at p facing the
+    /// light.
+    pub fn illuminance_at(&self, p: Vec3<M<f32>>) -> Lux<f32> {
+        let to = meters(p) - meters(self.position);
+        let attenuation = match to.normalize() {
+            Some(dir) => self.angle_attenuation(dir) * distance_attenuation(M(to.length()), self.range),
+            None => 0.0,
+        };
+        Lux(self.intensity().0 * attenuation)
+    }
+}
+
+/// AreaShape is the shape of an area light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AreaShape {
+    /// A rectangle, of the given width along the tangent and height along
+    /// the bitangent.
+    Rectangle {
+        /// The width of the rectangle.
+        width: M<f32>,
+
+        /// The height of the rectangle.
+        height: M<f32>,
+    },
+
+    /// A disc of the given radius.
+    Disc {
+        /// The radius of the disc.
+        radius: M<f32>,
+    },
+}
+
+impl AreaShape {
+    /// area returns the area of the shape, in square meters.
+    pub fn area(&self) -> f32 {
+        match *self {
+            AreaShape::Rectangle { width, height } => width.0 * height.0,
+            AreaShape::Disc { radius } => PI * radius.0 * radius.0,
+        }
+    }
+
+    /// extent returns the greatest distance of a point of the shape from its
+    /// center.
+    pub fn extent(&self) -> M<f32> {
+        match *self {
+            AreaShape::Rectangle { width, height } => M(0.5 * width.0.hypot(height.0)),
+            AreaShape::Disc { radius } => radius,
+        }
+    }
+}
+
+/// AreaLight is light emitted evenly from a flat shape, such as a window or
+/// a panel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AreaLight {
+    /// The position of the center of the shape.
+    pub position: Vec3<M<f32>>,
+
+    /// The normalized direction the shape faces.
+    pub normal: Vec3<f32>,
+
+    /// The normalized direction of the width of rectangles, perpendicular
+    /// to the normal.
+    pub tangent: Vec3<f32>,
+
+    /// The shape of the light.
+    pub shape: AreaShape,
+
+    /// The linear RGB color of the light.
+    pub color: Vec3<f32>,
+
+    /// The total light emitted.
+    pub power: Lumens<f32>,
+
+    /// Whether both sides of the shape emit light, rather than only the
+    /// side it faces.
+    pub two_sided: bool,
+
+    /// The distance from the shape beyond which the light is ignored.
+    pub range: M<f32>,
+}
+
+impl AreaLight {
+    /// luminance returns the luminance of the surface of the light, in
+    /// candela per square meter (nits).
+    pub fn luminance(&self) -> f32 {
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+        self.power.0 / (PI * self.shape.area() * sides)
+    }
+}
+
+/// Light is a light source of any type.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Light {
+    /// A directional light.
+    Directional(DirectionalLight),
+
+    /// A point light.
+    Point(PointLight),
+
+    /// A spot light.
+    Spot(SpotLight),
+
+    /// An area light.
+    Area(AreaLight),
+}
+
+impl Light {
+    /// bounds returns the center and radius of a sphere enclosing the space
+    /// the light reaches, or None for directional lights, which reach
+    /// everywhere.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::math::unit::{Deg, M};
+    /// use fiz_gfx::light::{Light, Lumens, SpotLight};
+    ///
+    /// // A narrow cone is bounded by a sphere half its range across.
+    /// let mut spot = SpotLight::new(Vec3(M(0.0), M(0.0), M(0.0)), Vec3(0.0, 0.0, -1.0), Lumens(100.0),
+    ///                               Deg(5.0), Deg(10.0));
+    /// spot.range = M(10.0);
+    /// let (center, radius) = Light::Spot(spot).bounds().unwrap();
+    /// assert!((center.2 .0 + 5.08).abs() < 0.01 && (radius.0 - 5.08).abs() < 0.01);
+    /// ```
+    pub fn bounds(&self) -> Option<(Vec3<M<f32>>, M<f32>)> {
+        match *self {
+            Light::Directional(_) => None,
+            Light::Point(ref l) => Some((l.position, l.range)),
+            Light::Spot(ref l) => {
+                // The smallest sphere around the cone: wide cones are
+                // bounded by their cap, narrow ones by a sphere touching
+                // the apex and the rim of the cap.
+                let (angle, range) = (l.outer(), l.range.0);
+                let (distance, radius) = if angle > 0.25 * PI {
+                    (angle.cos() * range, angle.sin() * range)
+                } else {
+                    let r = range / (2.0 * angle.cos());
+                    (r, r)
+                };
+                let center = meters(l.position) + l.direction.mul_scalar(distance);
+                Some((Vec3(M(center.0), M(center.1), M(center.2)), M(radius)))
+            }
+            Light::Area(ref l) => Some((l.position, M(l.range.0 + l.shape.extent().0))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+    use math::Vec3;
+    use math::unit::{Deg, M};
+    use super::*;
+
+    #[test]
+    fn photometry() {
+        let origin = Vec3(M(0.0), M(0.0), M(0.0));
+        let down = Vec3(0.0, -1.0, 0.0);
+        let spot = SpotLight::new(origin, down, Lumens(1000.0), Deg(20.0), Deg(30.0));
+        // The power is spread over the solid angle of the outer cone.
+        let solid_angle = 2.0 * PI * (1.0 - (PI / 6.0).cos());
+        assert!((spot.intensity().0 * solid_angle - 1000.0).abs() < 0.1);
+        assert!((spot.illuminance_at(Vec3(M(0.0), M(-1.0), M(0.0))).0 - spot.intensity().0).abs() < 0.1);
+        assert!((spot.illuminance_at(Vec3(M(0.0), M(-1.0), M(1.0))).0).abs() < 1e-6);
+        let edge = Vec3(0.0, -(25.0f32.to_radians().cos()), 25.0f32.to_radians().sin());
+        assert!((0.4..0.7).contains(&spot.angle_attenuation(edge)));
+        assert!((spot.illuminance_at(Vec3(M(0.0), M(-spot.range.0), M(0.0))).0).abs() < 1e-6);
+
+        let panel = AreaLight {
+            position: origin,
+            normal: down,
+            tangent: Vec3(1.0, 0.0, 0.0),
+            shape: AreaShape::Rectangle { width: M(2.0), height: M(0.5) },
+            color: Vec3(1.0, 1.0, 1.0),
+            power: Lumens(PI * 100.0),
+            two_sided: false,
+            range: M(5.0),
+        };
+        assert!((panel.luminance() - 100.0).abs() < 1e-4);
+        let (center, radius) = Light::Area(panel).bounds().unwrap();
+        assert_eq!(center, origin);
+        assert!((radius.0 - 5.0 - 17.0f32.sqrt() / 4.0).abs() < 1e-5);
+    }
+}
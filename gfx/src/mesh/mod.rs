@@ -0,0 +1,478 @@
+//! Indexed triangle meshes.
+//!
+//! A `Mesh` stores per-vertex attributes in separate arrays (positions and
+//! any of normals, tangents, texture coordinates, colors and skin weights)
+//! along with triangles indexing them. Meshes can be validated, welded, and
+//! have their normals and tangents generated.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use math::{Vec2, Vec3, Vec4, Aabb};
+
+mod tangents;
+
+/// SkinWeights are the joints influencing a skinned vertex and their weights,
+/// which normally sum to one. Unused influences have a weight of zero.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SkinWeights {
+    /// The indices of the joints influencing the vertex.
+    pub joints: [u16; 4],
+
+    /// The weight of each joint's influence.
+    pub weights: [f32; 4],
+}
+
+/// MeshError describes an invalid mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshError {
+    /// A vertex attribute doesn't have one element per vertex.
+    AttributeLength {
+        /// The name of the attribute.
+        attribute: &'static str,
+
+        /// The number of elements of the attribute.
+        len: usize,
+
+        /// The number of vertices of the mesh.
+        expected: usize,
+    },
+
+    /// A triangle refers to a vertex that doesn't exist.
+    IndexOutOfRange {
+        /// The index of the triangle.
+        triangle: usize,
+
+        /// The offending vertex index.
+        index: u32,
+    },
+
+    /// A triangle has repeated vertices or zero area.
+    DegenerateTriangle {
+        /// The index of the triangle.
+        triangle: usize,
+    },
+
+    /// An operation requires an attribute the mesh doesn't have.
+    MissingAttribute(&'static str),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshError::AttributeLength { attribute, len, expected } => {
+                write!(f, "mesh has {} {} for {} vertices", len, attribute, expected)
+            }
+            MeshError::IndexOutOfRange { triangle, index } => {
+                write!(f, "triangle {} refers to missing vertex {}", triangle, index)
+            }
+            MeshError::DegenerateTriangle { triangle } => write!(f, "triangle {} is degenerate", triangle),
+            MeshError::MissingAttribute(name) => write!(f, "mesh has no {}", name),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// Mesh is an indexed triangle mesh. Each attribute that is present holds
+/// one element per vertex, and triangles are wound counter-clockwise when
+/// seen from their front.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::{Vec3, Aabb};
+/// use fiz_gfx::Mesh;
+///
+/// // A unit quad in the XY plane, facing +Z.
+/// let mut quad = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0)],
+///                          vec![[0, 1, 2], [0, 2, 3]]);
+/// assert!(quad.validate().is_ok());
+/// assert_eq!(quad.bounds(), Some(Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0))));
+///
+/// quad.compute_smooth_normals().unwrap();
+/// assert_eq!(quad.normals.unwrap()[0], Vec3(0.0, 0.0, 1.0));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    /// The position of each vertex.
+    pub positions: Vec<Vec3<f32>>,
+
+    /// The unit normal of each vertex.
+    pub normals: Option<Vec<Vec3<f32>>>,
+
+    /// The unit tangent of each vertex, pointing along increasing U, with
+    /// the handedness of the tangent frame in W (either 1 or -1) such that
+    /// the bitangent is `w * normal.cross(tangent)`.
+    pub tangents: Option<Vec<Vec4<f32>>>,
+
+    /// The texture coordinates of each vertex.
+    pub uvs: Option<Vec<Vec2<f32>>>,
+
+    /// The linear RGBA color of each vertex.
+    pub colors: Option<Vec<Vec4<f32>>>,
+
+    /// The skin weights of each vertex.
+    pub skin: Option<Vec<SkinWeights>>,
+
+    /// The triangles, as indices of their three vertices.
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// new returns a new mesh with the given positions and triangles, and no
+    /// other attributes.
+    pub fn new(positions: Vec<Vec3<f32>>, indices: Vec<[u32; 3]>) -> Self {
+        Mesh {
+            positions,
+            indices,
+            ..Mesh::default()
+        }
+    }
+
+    /// vertex_count returns the number of vertices of the mesh.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// triangle_count returns the number of triangles of the mesh.
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// bounds returns the bounds of the vertices of the mesh, or None if it
+    /// has none.
+    pub fn bounds(&self) -> Option<Aabb<f32>> {
+        Aabb::from_points(&self.positions)
+    }
+
+    /// triangle returns the positions of the vertices of the given triangle.
+    ///
+    /// # Panics
+    ///
+    /// triangle panics if the triangle or its vertices don't exist.
+    pub fn triangle(&self, i: usize) -> [Vec3<f32>; 3] {
+        let t = self.indices[i];
+        [self.positions[t[0] as usize], self.positions[t[1] as usize], self.positions[t[2] as usize]]
+    }
+
+    /// validate checks that each attribute has one element per vertex, and
+    /// that the triangles refer to existing vertices and aren't degenerate,
+    /// returning the first problem found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::{Mesh, MeshError};
+    ///
+    /// let p = vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(2.0, 0.0, 0.0)];
+    /// let m = Mesh::new(p.clone(), vec![[0, 1, 3]]);
+    /// assert_eq!(m.validate(), Err(MeshError::IndexOutOfRange { triangle: 0, index: 3 }));
+    ///
+    /// // Collinear vertices.
+    /// let m = Mesh::new(p, vec![[0, 1, 2]]);
+    /// assert_eq!(m.validate(), Err(MeshError::DegenerateTriangle { triangle: 0 }));
+    /// ```
+    pub fn validate(&self) -> Result<(), MeshError> {
+        self.validate_references()?;
+        for i in 0..self.indices.len() {
+            if self.is_degenerate(i) {
+                return Err(MeshError::DegenerateTriangle { triangle: i });
+            }
+        }
+        Ok(())
+    }
+
+    // validate_references does the checks of validate which make indexing
+    // the attributes by the triangles safe, allowing degenerate triangles.
+    fn validate_references(&self) -> Result<(), MeshError> {
+        let n = self.positions.len();
+        let lengths = [("normals", self.normals.as_ref().map(|a| a.len())),
+                       ("tangents", self.tangents.as_ref().map(|a| a.len())),
+                       ("uvs", self.uvs.as_ref().map(|a| a.len())),
+                       ("colors", self.colors.as_ref().map(|a| a.len())),
+                       ("skin weights", self.skin.as_ref().map(|a| a.len()))];
+        for &(attribute, len) in &lengths {
+            match len {
+                Some(len) if len != n => {
+                    return Err(MeshError::AttributeLength {
+                        attribute,
+                        len,
+                        expected: n,
+                    })
+                }
+                _ => {}
+            }
+        }
+        for (i, t) in self.indices.iter().enumerate() {
+            if let Some(&index) = t.iter().find(|&&v| v as usize >= n) {
+                return Err(MeshError::IndexOutOfRange { triangle: i, index });
+            }
+        }
+        Ok(())
+    }
+
+    /// remove_degenerate removes the degenerate triangles of the mesh,
+    /// returning how many were removed.
+    pub fn remove_degenerate(&mut self) -> usize {
+        let before = self.indices.len();
+        let keep: Vec<bool> = (0..before).map(|i| !self.is_degenerate(i)).collect();
+        let mut k = keep.iter();
+        self.indices.retain(|_| *k.next().unwrap());
+        before - self.indices.len()
+    }
+
+    // is_degenerate tells if the triangle has repeated vertices or zero area.
+    fn is_degenerate(&self, i: usize) -> bool {
+        let t = self.indices[i];
+        if t[0] == t[1] || t[1] == t[2] || t[2] == t[0] {
+            return true;
+        }
+        let [a, b, c] = self.triangle(i);
+        (b - a).cross(c - a).length_sq() == 0.0
+    }
+
+    /// weld merges vertices whose positions, and all other attributes, differ
+    /// by at most the given tolerance in each component, returning the
+    /// number of vertices removed. The first of each set of merged vertices
+    /// is kept, and the order of the remaining vertices is preserved.
+    /// Welding may leave degenerate triangles, see remove_degenerate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::Mesh;
+    ///
+    /// // Two triangles sharing an edge, stored with separate vertices.
+    /// let mut m = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0),
+    ///                            Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0001)],
+    ///                       vec![[0, 1, 2], [3, 4, 5]]);
+    /// assert_eq!(m.weld(0.001), 2);
+    /// assert_eq!(m.vertex_count(), 4);
+    /// assert_eq!(m.indices, vec![[0, 1, 2], [1, 3, 2]]);
+    /// ```
+    pub fn weld(&mut self, tolerance: f32) -> usize {
+        // Bucket the kept vertices by position into cells of the tolerance's
+        // size, so candidates for merging lie in neighboring cells.
+        let size = if tolerance > 0.0 { tolerance } else { 1.0 };
+        let cell = |p: Vec3<f32>| ((p.0 / size).floor() as i64, (p.1 / size).floor() as i64, (p.2 / size).floor() as i64);
+        let mut cells: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+        let mut kept: Vec<usize> = Vec::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        for v in 0..self.positions.len() {
+            let c = cell(self.positions[v]);
+            let mut found = None;
+            'search: for x in c.0 - 1..c.0 + 2 {
+                for y in c.1 - 1..c.1 + 2 {
+                    for z in c.2 - 1..c.2 + 2 {
+                        if let Some(&k) = cells.get(&(x, y, z))
+                            .and_then(|ks| ks.iter().find(|&&k| self.same_vertex(kept[k], v, tolerance))) {
+                            found = Some(k);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+            remap.push(match found {
+                Some(k) => k as u32,
+                None => {
+                    cells.entry(c).or_default().push(kept.len());
+                    kept.push(v);
+                    (kept.len() - 1) as u32
+                }
+            });
+        }
+
+        let removed = self.positions.len() - kept.len();
+        self.select_vertices(&kept);
+        for t in &mut self.indices {
+            for v in t.iter_mut() {
+                *v = remap[*v as usize];
+            }
+        }
+        removed
+    }
+
+    // same_vertex tells if the attributes of the vertices a and b are all
+    // within the tolerance of each other.
+    fn same_vertex(&self, a: usize, b: usize, tolerance: f32) -> bool {
+        fn close(a: &[f32], b: &[f32], tolerance: f32) -> bool {
+            a.iter().zip(b).all(|(x, y)| (x - y).abs() <= tolerance)
+        }
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+        close(&[pa.0, pa.1, pa.2], &[pb.0, pb.1, pb.2], tolerance) &&
+        self.normals.as_ref().is_none_or(|n| close(&[n[a].0, n[a].1, n[a].2], &[n[b].0, n[b].1, n[b].2], tolerance)) &&
+        self.tangents.as_ref().is_none_or(|t| {
+            close(&[t[a].0, t[a].1, t[a].2, t[a].3], &[t[b].0, t[b].1, t[b].2, t[b].3], tolerance)
+        }) &&
+        self.uvs.as_ref().is_none_or(|uv| close(&[uv[a].0, uv[a].1], &[uv[b].0, uv[b].1], tolerance)) &&
+        self.colors.as_ref().is_none_or(|c| {
+            close(&[c[a].0, c[a].1, c[a].2, c[a].3], &[c[b].0, c[b].1, c[b].2, c[b].3], tolerance)
+        }) &&
+        self.skin.as_ref().is_none_or(|s| s[a].joints == s[b].joints && close(&s[a].weights, &s[b].weights, tolerance))
+    }
+
+    // select_vertices replaces the attributes of the mesh with those of the
+    // given vertices, in order, without changing the indices.
+    fn select_vertices(&mut self, vertices: &[usize]) {
+        fn select<T: Copy>(a: &mut Vec<T>, vertices: &[usize]) {
+            *a = vertices.iter().map(|&v| a[v]).collect();
+        }
+        select(&mut self.positions, vertices);
+        if let Some(ref mut a) = self.normals {
+            select(a, vertices);
+        }
+        if let Some(ref mut a) = self.tangents {
+            select(a, vertices);
+        }
+        if let Some(ref mut a) = self.uvs {
+            select(a, vertices);
+        }
+        if let Some(ref mut a) = self.colors {
+            select(a, vertices);
+        }
+        if let Some(ref mut a) = self.skin {
+            select(a, vertices);
+        }
+    }
+
+    /// scale multiplies the positions of the vertices by the given factor,
+    /// for example to convert them between units (see `Units::scale`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::{Mesh, Units};
+    ///
+    /// let mut m = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(250.0, 0.0, 0.0), Vec3(0.0, 50.0, 0.0)],
+    ///                       vec![[0, 1, 2]]);
+    /// m.scale(Units::MM.scale(Units::CM));
+    /// assert_eq!(m.positions[1], Vec3(25.0, 0.0, 0.0));
+    /// ```
+    pub fn scale(&mut self, factor: f32) {
+        for p in &mut self.positions {
+            *p = p.mul_scalar(factor);
+        }
+    }
+
+    /// compute_flat_normals gives each triangle its own vertices, whose
+    /// normals are the triangle's normal, for a faceted appearance. Any
+    /// tangents are removed, as they no longer match the normals.
+    ///
+    /// compute_flat_normals fails, leaving the mesh unchanged, if its
+    /// attributes don't all have one element per vertex or a triangle
+    /// refers to a vertex which doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec3;
+    /// use fiz_gfx::Mesh;
+    ///
+    /// let mut m = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0), Vec3(0.0, 0.0, 1.0)],
+    ///                       vec![[0, 1, 2], [0, 3, 1]]);
+    /// m.compute_flat_normals().unwrap();
+    /// assert_eq!(m.vertex_count(), 6);
+    /// assert_eq!(m.normals.unwrap(), vec![Vec3(0.0, 0.0, 1.0), Vec3(0.0, 0.0, 1.0), Vec3(0.0, 0.0, 1.0),
+    ///                                     Vec3(0.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0)]);
+    ///
+    /// let mut bad = Mesh::new(vec![Vec3(0.0, 0.0, 0.0)], vec![[0, 1, 2]]);
+    /// assert!(bad.compute_flat_normals().is_err());
+    /// assert!(bad.compute_smooth_normals().is_err());
+    /// ```
+    pub fn compute_flat_normals(&mut self) -> Result<(), MeshError> {
+        self.validate_references()?;
+        let corners: Vec<usize> = self.indices.iter().flat_map(|t| t.iter().map(|&v| v as usize)).collect();
+        let normals = (0..self.indices.len())
+            .flat_map(|i| {
+                let [a, b, c] = self.triangle(i);
+                let n = (b - a).cross(c - a).normalize().unwrap_or(Vec3(0.0, 0.0, 0.0));
+                vec![n, n, n]
+            })
+            .collect();
+        self.select_vertices(&corners);
+        self.indices = (0..self.indices.len() as u32).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+        self.normals = Some(normals);
+        self.tangents = None;
+        Ok(())
+    }
+
+    /// compute_smooth_normals gives each vertex the average of the normals of
+    /// the triangles around it, weighted by the angle of each triangle at the
+    /// vertex. Vertices at the same position are smoothed together even if
+    /// they aren't shared, so seams in other attributes (such as texture
+    /// coordinates) don't show. Any tangents are removed, as they no longer
+    /// match the normals.
+    ///
+    /// compute_smooth_normals fails like `compute_flat_normals`.
+    pub fn compute_smooth_normals(&mut self) -> Result<(), MeshError> {
+        self.validate_references()?;
+        let key = |p: Vec3<f32>| (p.0.to_bits(), p.1.to_bits(), p.2.to_bits());
+        let mut sums: HashMap<(u32, u32, u32), Vec3<f32>> = HashMap::new();
+        for i in 0..self.indices.len() {
+            let p = self.triangle(i);
+            let n = match (p[1] - p[0]).cross(p[2] - p[0]).normalize() {
+                Some(n) => n,
+                None => continue,
+            };
+            for k in 0..3 {
+                let angle = corner_angle(p[k], p[(k + 1) % 3], p[(k + 2) % 3]);
+                let sum = sums.entry(key(p[k])).or_insert(Vec3(0.0, 0.0, 0.0));
+                *sum = *sum + n.mul_scalar(angle);
+            }
+        }
+        self.normals = Some(self.positions
+            .iter()
+            .map(|&p| sums.get(&key(p)).and_then(|n| n.normalize()).unwrap_or(Vec3(0.0, 0.0, 0.0)))
+            .collect());
+        self.tangents = None;
+        Ok(())
+    }
+
+    /// compute_tangents generates tangents from the normals and texture
+    /// coordinates of the mesh.
+    ///
+    /// Each triangle's tangent, the direction of increasing U, is derived
+    /// from its texture coordinates and projected onto the tangent plane of
+    /// each of its vertices, and the results are averaged per vertex,
+    /// weighted by corner angle. The sign in W gives the handedness of the
+    /// frame, and vertices shared by triangles of opposite handedness (e.g.
+    /// at mirrored texture seams) are split in two. This is close to, but
+    /// not the same as, MikkTSpace, so normal maps baked against MikkTSpace
+    /// tangents may show small differences.
+    ///
+    /// compute_tangents fails if the mesh has no normals or texture
+    /// coordinates, or if it fails the checks of `validate` other than for
+    /// degenerate triangles, which are allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::{Vec2, Vec3, Vec4};
+    /// use fiz_gfx::Mesh;
+    ///
+    /// let mut quad = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0)],
+    ///                          vec![[0, 1, 2], [0, 2, 3]]);
+    /// assert!(quad.compute_tangents().is_err());
+    ///
+    /// quad.uvs = Some(vec![Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(1.0, 1.0), Vec2(0.0, 1.0)]);
+    /// quad.compute_smooth_normals().unwrap();
+    /// quad.compute_tangents().unwrap();
+    /// assert_eq!(quad.tangents.unwrap()[0], Vec4(1.0, 0.0, 0.0, 1.0));
+    /// ```
+    pub fn compute_tangents(&mut self) -> Result<(), MeshError> {
+        tangents::compute(self)
+    }
+}
+
+// corner_angle returns the angle of the triangle at the vertex p, whose
+// neighbors are a and b.
+pub(crate) fn corner_angle(p: Vec3<f32>, a: Vec3<f32>, b: Vec3<f32>) -> f32 {
+    match ((a - p).normalize(), (b - p).normalize()) {
+        (Some(u), Some(v)) => u.dot(v).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use math::{Vec3, Vec4};
+use super::{Mesh, MeshError, corner_angle};
+
+// compute generates the tangents of the mesh, see Mesh::compute_tangents.
+pub fn compute(mesh: &mut Mesh) -> Result<(), MeshError> {
+    mesh.validate_references()?;
+    let n = mesh.positions.len();
+    let mut sums: HashMap<(u32, bool), Vec3<f32>> = HashMap::new();
+    let mut primary: Vec<Option<bool>> = vec![None; n];
+    let mut signs = Vec::with_capacity(mesh.indices.len());
+    {
+        let normals = mesh.normals.as_ref().ok_or(MeshError::MissingAttribute("normals"))?;
+        let uvs = mesh.uvs.as_ref().ok_or(MeshError::MissingAttribute("uvs"))?;
+        for (i, t) in mesh.indices.iter().enumerate() {
+            let p = mesh.triangle(i);
+            let uv = [uvs[t[0] as usize], uvs[t[1] as usize], uvs[t[2] as usize]];
+            let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+            let (d1, d2) = (uv[1] - uv[0], uv[2] - uv[0]);
+            let r = d1.0 * d2.1 - d2.0 * d1.1;
+            if r == 0.0 {
+                // Degenerate texture coordinates say nothing of the frame.
+                signs.push(None);
+                continue;
+            }
+            let tangent = (e1.mul_scalar(d2.1) - e2.mul_scalar(d1.1)).div_scalar(r);
+            let bitangent = (e2.mul_scalar(d1.0) - e1.mul_scalar(d2.0)).div_scalar(r);
+            let positive = e1.cross(e2).cross(tangent).dot(bitangent) >= 0.0;
+            signs.push(Some(positive));
+
+            // Accumulate the tangent, projected onto each vertex's tangent
+            // plane, separately for each handedness.
+            for k in 0..3 {
+                let v = t[k];
+                let normal = normals[v as usize];
+                let projected = match (tangent - normal.mul_scalar(normal.dot(tangent))).normalize() {
+                    Some(x) => x,
+                    None => continue,
+                };
+                let weight = corner_angle(p[k], p[(k + 1) % 3], p[(k + 2) % 3]);
+                let sum = sums.entry((v, positive)).or_insert(Vec3(0.0, 0.0, 0.0));
+                *sum = *sum + projected.mul_scalar(weight);
+                primary[v as usize].get_or_insert(positive);
+            }
+        }
+    }
+
+    // Split the vertices used with both handednesses, giving the triangles
+    // with the vertex's secondary handedness a copy of it.
+    let mut vertices: Vec<usize> = (0..n).collect();
+    let mut copies: HashMap<u32, u32> = HashMap::new();
+    for (t, sign) in mesh.indices.iter_mut().zip(&signs) {
+        let sign = match *sign {
+            Some(s) => s,
+            None => continue,
+        };
+        for v in t.iter_mut() {
+            if primary[*v as usize] == Some(sign) || !sums.contains_key(&(*v, sign)) {
+                continue;
+            }
+            let original = *v;
+            *v = *copies.entry(original).or_insert_with(|| {
+                vertices.push(original as usize);
+                (vertices.len() - 1) as u32
+            });
+        }
+    }
+    mesh.select_vertices(&vertices);
+
+    let normals = mesh.normals.as_ref().unwrap();
+    let tangents = vertices.iter()
+        .enumerate()
+        .map(|(v, &original)| {
+            let positive = if v < n { primary[v].unwrap_or(true) } else { !primary[original].unwrap() };
+            let normal = normals[v];
+            let t = sums.get(&(original as u32, positive))
+                .and_then(|t| t.normalize())
+                .unwrap_or_else(|| perpendicular(normal));
+            Vec4(t.0, t.1, t.2, if positive { 1.0 } else { -1.0 })
+        })
+        .collect();
+    mesh.tangents = Some(tangents);
+    Ok(())
+}
+
+// perpendicular returns a unit vector perpendicular to n, or the X axis if n
+// is zero.
+fn perpendicular(n: Vec3<f32>) -> Vec3<f32> {
+    let axis = if n.0.abs() < 0.9 { Vec3(1.0, 0.0, 0.0) } else { Vec3(0.0, 1.0, 0.0) };
+    (axis - n.mul_scalar(n.dot(axis))).normalize().unwrap_or(Vec3(1.0, 0.0, 0.0))
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec2, Vec3, Vec4};
+    use super::super::{Mesh, MeshError};
+
+    #[test]
+    fn mirrored_seam() {
+        // Two quads sharing the edge x = 1, the second with its texture
+        // mirrored horizontally.
+        let mut m = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(1.0, 1.0, 0.0), Vec3(0.0, 1.0, 0.0),
+                                   Vec3(2.0, 0.0, 0.0), Vec3(2.0, 1.0, 0.0)],
+                              vec![[0, 1, 2], [0, 2, 3], [1, 4, 5], [1, 5, 2]]);
+        m.uvs = Some(vec![Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(1.0, 1.0), Vec2(0.0, 1.0), Vec2(0.0, 0.0), Vec2(0.0, 1.0)]);
+        m.compute_smooth_normals().unwrap();
+        m.compute_tangents().unwrap();
+        assert!(m.validate().is_ok());
+
+        let mut bad = m.clone();
+        bad.indices.push([0, 1, 8]);
+        assert_eq!(bad.compute_tangents(), Err(MeshError::IndexOutOfRange { triangle: 4, index: 8 }));
+        let mut bad = m.clone();
+        bad.uvs.as_mut().unwrap().pop();
+        assert!(bad.compute_tangents().is_err());
+
+        // The vertices of the shared edge are split.
+        assert_eq!(m.vertex_count(), 8);
+        let tangents = m.tangents.as_ref().unwrap();
+        for t in &m.indices[..2] {
+            for &v in t {
+                assert!(tangents[v as usize].almost_equal(Vec4(1.0, 0.0, 0.0, 1.0), 1e-6));
+            }
+        }
+        for t in &m.indices[2..] {
+            for &v in t {
+                assert!(tangents[v as usize].almost_equal(Vec4(-1.0, 0.0, 0.0, -1.0), 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn orthogonal() {
+        // A bumpy grid with skewed texture coordinates.
+        let size = 8;
+        let mut positions = Vec::new();
+        let mut uvs = Vec::new();
+        for y in 0..size + 1 {
+            for x in 0..size + 1 {
+                let (fx, fy) = (x as f32, y as f32);
+                positions.push(Vec3(fx, fy, (fx * 0.7).sin() + (fy * 0.3).cos()));
+                uvs.push(Vec2(fx * 0.1 + fy * 0.02, fy * 0.1));
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..size {
+            for x in 0..size {
+                let i = y * (size + 1) + x;
+                indices.push([i, i + 1, i + size + 2]);
+                indices.push([i, i + size + 2, i + size + 1]);
+            }
+        }
+        let mut m = Mesh::new(positions, indices);
+        m.uvs = Some(uvs);
+        m.compute_smooth_normals().unwrap();
+        m.compute_tangents().unwrap();
+        let normals = m.normals.as_ref().unwrap();
+        for (t, n) in m.tangents.as_ref().unwrap().iter().zip(normals) {
+            let t = t.xyz();
+            assert!((t.length() - 1.0).abs() < 1e-5);
+            assert!(t.dot(*n).abs() < 1e-5);
+            assert!(t.0 > 0.5);
+        }
+    }
+}
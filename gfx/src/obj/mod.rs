@@ -0,0 +1,678 @@
+//! Wavefront OBJ and MTL import and export.
+//!
+//! OBJ files are parsed into an `Obj`, which holds one `Object` for each
+//! distinct pair of group (or object) name and material in the file, each
+//! with its own `Mesh`. Polygons are triangulated on load, and normals are
+//! generated for vertices without them according to the smoothing groups of
+//! their faces.
+//!
+//! Errors carry the number of the offending line, so that problems in files
+//! can be found easily.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::SplitWhitespace;
+use math::{Vec2, Vec3, Vec4};
+use math::polygon::{Polygon, triangulate};
+use super::Mesh;
+
+mod mtl;
+
+pub use self::mtl::{Material, parse_mtl, write_mtl};
+
+/// ObjError describes a failure to load an OBJ or MTL file.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file couldn't be read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// A number couldn't be parsed.
+    InvalidNumber {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The text which isn't a number.
+        text: String,
+    },
+
+    /// A statement is missing some of its arguments.
+    MissingArgument {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The keyword of the statement.
+        statement: String,
+    },
+
+    /// A face refers to a vertex, texture coordinate or normal that doesn't
+    /// exist.
+    InvalidIndex {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The index as written in the file.
+        index: i64,
+    },
+
+    /// A face has fewer than three vertices.
+    InvalidFace {
+        /// The line number, starting at one.
+        line: usize,
+    },
+
+    /// A material statement appears before any `newmtl` statement.
+    NoMaterial {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// The keyword of the statement.
+        statement: String,
+    },
+
+    /// A material library referenced by an OBJ file is invalid.
+    Material {
+        /// The path of the material library.
+        path: PathBuf,
+
+        /// The error in the material library.
+        error: Box<ObjError>,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ObjError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::InvalidNumber { line, ref text } => write!(f, "line {}: invalid number {:?}", line, text),
+            ObjError::MissingArgument { line, ref statement } => {
+                write!(f, "line {}: missing argument to {:?}", line, statement)
+            }
+            ObjError::InvalidIndex { line, index } => write!(f, "line {}: index {} is out of range", line, index),
+            ObjError::InvalidFace { line } => write!(f, "line {}: face has fewer than three vertices", line),
+            ObjError::NoMaterial { line, ref statement } => {
+                write!(f, "line {}: {:?} appears before any newmtl statement", line, statement)
+            }
+            ObjError::Material { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ObjError::Io { ref error, .. } => Some(error),
+            ObjError::Material { ref error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
+
+/// Object is the part of an OBJ file with one group (or object) name and
+/// material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Object {
+    /// The name of the group or object, which is empty if none was given.
+    pub name: String,
+
+    /// The name of the material, if any.
+    pub material: Option<String>,
+
+    /// The triangles of the object. Normals are always present, and texture
+    /// coordinates and colors are present if any vertex has them.
+    pub mesh: Mesh,
+}
+
+/// Obj is the contents of an OBJ file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Obj {
+    /// The objects of the file, in order of first appearance.
+    pub objects: Vec<Object>,
+
+    /// The materials of the file, loaded from its material libraries.
+    pub materials: Vec<Material>,
+
+    /// The paths of the material libraries (`mtllib`) of the file.
+    pub material_libraries: Vec<String>,
+}
+
+impl Obj {
+    /// material returns the material with the given name, if any.
+    pub fn material(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+}
+
+/// load loads the OBJ file at the given path, along with the material
+/// libraries it refers to, which are found relative to it.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Obj, ObjError> {
+    let path = path.as_ref();
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|error| {
+            ObjError::Io {
+                path: path.to_path_buf(),
+                error,
+            }
+        })
+    };
+    let mut obj = parse_obj(&read(path)?)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for lib in &obj.material_libraries {
+        let lib_path = dir.join(lib);
+        let materials = parse_mtl(&read(&lib_path)?).map_err(|error| {
+            ObjError::Material {
+                path: lib_path,
+                error: Box::new(error),
+            }
+        })?;
+        obj.materials.extend(materials);
+    }
+    Ok(obj)
+}
+
+/// parse_obj parses the contents of an OBJ file, without loading its
+/// material libraries. Unsupported statements (such as curves) are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::obj::{parse_obj, ObjError};
+///
+/// let obj = parse_obj("
+///     v 0 0 0
+///     v 1 0 0
+///     v 1 1 0
+///     v 0 1 0
+///     g square
+///     f 1 2 3 4
+///     g tri
+///     f -4 -3 -2
+/// ").unwrap();
+/// assert_eq!(obj.objects.len(), 2);
+/// assert_eq!(obj.objects[0].name, "square");
+/// assert_eq!(obj.objects[0].mesh.triangle_count(), 2);
+/// assert_eq!(obj.objects[1].mesh.triangle_count(), 1);
+///
+/// match parse_obj("v 0 0 0\nf 1 2 3\n") {
+///     Err(ObjError::InvalidIndex { line: 2, index: 2 }) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse_obj(src: &str) -> Result<Obj, ObjError> {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut obj = Obj::default();
+    let mut builders: Vec<Builder> = Vec::new();
+    let mut name = String::new();
+    let mut material = None;
+    let mut smoothing = 0;
+    let mut current = None;
+    let mut faces = 0;
+
+    for (i, text) in src.lines().enumerate() {
+        let line = i + 1;
+        let mut args = match Args::new(text, line) {
+            Some(a) => a,
+            None => continue,
+        };
+        match args.keyword {
+            "v" => {
+                positions.push(args.vec3()?);
+                // Some tools append a vertex color to positions.
+                colors.push(match args.next() {
+                    Some(r) => Some(Vec3(parse(r, line)?, args.float()?, args.float()?)),
+                    None => None,
+                });
+            }
+            "vt" => uvs.push(Vec2(args.float()?, args.next().map_or(Ok(0.0), |v| parse(v, line))?)),
+            "vn" => normals.push(args.vec3()?),
+            "g" | "o" => {
+                name = args.rest().unwrap_or("").to_string();
+                current = None;
+            }
+            "usemtl" => {
+                material = Some(args.rest()?.to_string());
+                current = None;
+            }
+            "mtllib" => obj.material_libraries.extend(args.tokens.by_ref().map(|s| s.to_string())),
+            "s" => {
+                let s = args.next().ok_or_else(|| args.missing())?;
+                smoothing = if s == "off" { 0 } else { parse::<f32>(s, line)? as u32 };
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for token in args.tokens.by_ref() {
+                    corners.push(corner(token, line, positions.len(), uvs.len(), normals.len())?);
+                }
+                if corners.len() < 3 {
+                    return Err(ObjError::InvalidFace { line });
+                }
+                let b = match current {
+                    Some(b) => b,
+                    None => {
+                        let found = builders.iter().position(|b| b.name == name && b.material == material);
+                        let b = found.unwrap_or_else(|| {
+                            builders.push(Builder::new(name.clone(), material.clone()));
+                            builders.len() - 1
+                        });
+                        current = Some(b);
+                        b
+                    }
+                };
+
+                // Vertices without normals are shared only within their
+                // smoothing group, and not at all outside of one.
+                faces += 1;
+                let smooth = if smoothing > 0 { smoothing as u64 } else { (1 << 32) + faces };
+                let ps: Vec<Vec3<f32>> = corners.iter().map(|c| positions[c.0]).collect();
+                for t in triangulate_face(&ps) {
+                    let tri = [corners[t[0]], corners[t[1]], corners[t[2]]];
+                    let mut indices = [0; 3];
+                    for k in 0..3 {
+                        let (v, vt, vn) = tri[k];
+                        indices[k] = builders[b].vertex(v, vt, vn, smooth, &positions, &colors, &uvs, &normals);
+                    }
+                    builders[b].indices.push(indices);
+                }
+            }
+            _ => {}
+        }
+    }
+    obj.objects = builders.into_iter().map(|b| b.finish()).collect();
+    Ok(obj)
+}
+
+/// write_obj writes the objects in the OBJ format, referring to the
+/// material libraries of the given `Obj` (but not writing them, see
+/// write_mtl).
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::Mesh;
+/// use fiz_gfx::obj::{Obj, Object, parse_obj, write_obj};
+///
+/// let mut mesh = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)], vec![[0, 1, 2]]);
+/// mesh.compute_flat_normals().unwrap();
+/// let mut obj = Obj::default();
+/// obj.objects.push(Object { name: "tri".to_string(), material: None, mesh });
+///
+/// let mut out = Vec::new();
+/// write_obj(&mut out, &obj).unwrap();
+/// assert_eq!(parse_obj(&String::from_utf8(out).unwrap()).unwrap(), obj);
+/// ```
+pub fn write_obj<W: Write>(w: &mut W, obj: &Obj) -> io::Result<()> {
+    for lib in &obj.material_libraries {
+        writeln!(w, "mtllib {}", lib)?;
+    }
+    let (mut v_base, mut vt_base, mut vn_base) = (1, 1, 1);
+    for o in &obj.objects {
+        let m = &o.mesh;
+        writeln!(w, "g {}", o.name)?;
+        if let Some(ref material) = o.material {
+            writeln!(w, "usemtl {}", material)?;
+        }
+        for (i, p) in m.positions.iter().enumerate() {
+            match m.colors {
+                Some(ref c) => writeln!(w, "v {} {} {} {} {} {}", p.0, p.1, p.2, c[i].0, c[i].1, c[i].2)?,
+                None => writeln!(w, "v {} {} {}", p.0, p.1, p.2)?,
+            }
+        }
+        for uv in m.uvs.iter().flatten() {
+            writeln!(w, "vt {} {}", uv.0, uv.1)?;
+        }
+        for n in m.normals.iter().flatten() {
+            writeln!(w, "vn {} {} {}", n.0, n.1, n.2)?;
+        }
+        for t in &m.indices {
+            write!(w, "f")?;
+            for &v in t {
+                match (m.uvs.is_some(), m.normals.is_some()) {
+                    (true, true) => write!(w, " {}/{}/{}", v_base + v, vt_base + v, vn_base + v)?,
+                    (true, false) => write!(w, " {}/{}", v_base + v, vt_base + v)?,
+                    (false, true) => write!(w, " {}//{}", v_base + v, vn_base + v)?,
+                    (false, false) => write!(w, " {}", v_base + v)?,
+                }
+            }
+            writeln!(w)?;
+        }
+        let n = m.positions.len() as u32;
+        v_base += n;
+        vt_base += if m.uvs.is_some() { n } else { 0 };
+        vn_base += if m.normals.is_some() { n } else { 0 };
+    }
+    Ok(())
+}
+
+// A corner of a face: the indices of its position, texture coordinate and
+// normal.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// corner parses a face corner of the form v, v/vt, v//vn or v/vt/vn.
+fn corner(token: &str, line: usize, nv: usize, nvt: usize, nvn: usize) -> Result<Corner, ObjError> {
+    let mut parts = token.split('/');
+    let v = index(parts.next().unwrap_or(""), line, nv)?;
+    let vt = match parts.next() {
+        Some(s) if !s.is_empty() => Some(index(s, line, nvt)?),
+        _ => None,
+    };
+    let vn = match parts.next() {
+        Some(s) if !s.is_empty() => Some(index(s, line, nvn)?),
+        _ => None,
+    };
+    Ok((v, vt, vn))
+}
+
+// index resolves a one-based (or negative, relative to the end) index into a
+// list of the given length.
+fn index(s: &str, line: usize, len: usize) -> Result<usize, ObjError> {
+    let i: i64 = parse(s, line)?;
+    let resolved = if i > 0 { i - 1 } else { len as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= len as i64 {
+        return Err(ObjError::InvalidIndex { line, index: i });
+    }
+    Ok(resolved as usize)
+}
+
+// triangulate_face returns the triangles of the polygon, as indices of its
+// vertices. The polygon is projected onto its plane and triangulated by ear
+// clipping, so concave faces are handled.
+fn triangulate_face(ps: &[Vec3<f32>]) -> Vec<[usize; 3]> {
+    let fan = || (1..ps.len() - 1).map(|i| [0, i, i + 1]).collect();
+    if ps.len() == 3 {
+        return fan();
+    }
+
+    // Newell's method gives a robust normal for nonplanar polygons.
+    let n = (0..ps.len()).fold(Vec3(0.0, 0.0, 0.0), |n, i| n + ps[i].cross(ps[(i + 1) % ps.len()]));
+    let n = match n.normalize() {
+        Some(n) => n,
+        None => return fan(),
+    };
+    let axis = if n.0.abs() < 0.9 { Vec3(1.0, 0.0, 0.0) } else { Vec3(0.0, 1.0, 0.0) };
+    let u = (axis - n.mul_scalar(n.dot(axis))).normalize().unwrap();
+    let v = n.cross(u);
+    let ring = ps.iter().map(|&p| Vec2(p.dot(u) as f64, p.dot(v) as f64)).collect();
+    let triangles = triangulate(&Polygon::new(ring));
+    if triangles.is_empty() { fan() } else { triangles }
+}
+
+// Builder accumulates the vertices and triangles of an object.
+struct Builder {
+    name: String,
+    material: Option<String>,
+    positions: Vec<Vec3<f32>>,
+    colors: Vec<Option<Vec3<f32>>>,
+    uvs: Vec<Option<Vec2<f32>>>,
+    normals: Vec<Option<Vec3<f32>>>,
+
+    // The position index and smoothing key of each vertex, to share
+    // generated normals.
+    smooth: Vec<(usize, u64)>,
+    vertices: HashMap<(usize, Option<usize>, Option<usize>, u64), u32>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl Builder {
+    fn new(name: String, material: Option<String>) -> Self {
+        Builder {
+            name,
+            material,
+            positions: Vec::new(),
+            colors: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            smooth: Vec::new(),
+            vertices: HashMap::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    // vertex returns the index of the vertex with the given attributes,
+    // adding it if needed.
+    #[allow(clippy::too_many_arguments)]
+    fn vertex(&mut self,
+              v: usize,
+              vt: Option<usize>,
+              vn: Option<usize>,
+              smooth: u64,
+              positions: &[Vec3<f32>],
+              colors: &[Option<Vec3<f32>>],
+              uvs: &[Vec2<f32>],
+              normals: &[Vec3<f32>])
+              -> u32 {
+        let smooth = if vn.is_some() { 0 } else { smooth };
+        if let Some(&i) = self.vertices.get(&(v, vt, vn, smooth)) {
+            return i;
+        }
+        let i = self.positions.len() as u32;
+        self.positions.push(positions[v]);
+        self.colors.push(colors[v]);
+        self.uvs.push(vt.map(|t| uvs[t]));
+        self.normals.push(vn.map(|n| normals[n]));
+        self.smooth.push((v, smooth));
+        self.vertices.insert((v, vt, vn, smooth), i);
+        i
+    }
+
+    fn finish(self) -> Object {
+        let mut mesh = Mesh::new(self.positions, self.indices);
+        if self.uvs.iter().any(|uv| uv.is_some()) {
+            mesh.uvs = Some(self.uvs.iter().map(|uv| uv.unwrap_or(Vec2(0.0, 0.0))).collect());
+        }
+        if self.colors.iter().any(|c| c.is_some()) {
+            mesh.colors = Some(self.colors
+                .iter()
+                .map(|c| c.map_or(Vec4(1.0, 1.0, 1.0, 1.0), |c| Vec4(c.0, c.1, c.2, 1.0)))
+                .collect());
+        }
+
+        // Generate the missing normals, averaging the normals of the faces
+        // around each vertex which share its smoothing key.
+        let mut sums: HashMap<(usize, u64), Vec3<f32>> = HashMap::new();
+        for i in 0..mesh.indices.len() {
+            let t = mesh.indices[i];
+            let p = mesh.triangle(i);
+            let n = (p[1] - p[0]).cross(p[2] - p[0]).normalize().unwrap_or(Vec3(0.0, 0.0, 0.0));
+            for k in 0..3 {
+                let v = t[k] as usize;
+                if self.normals[v].is_none() {
+                    let angle = super::mesh::corner_angle(p[k], p[(k + 1) % 3], p[(k + 2) % 3]);
+                    let sum = sums.entry(self.smooth[v]).or_insert(Vec3(0.0, 0.0, 0.0));
+                    *sum = *sum + n.mul_scalar(angle);
+                }
+            }
+        }
+        mesh.normals = Some(self.normals
+            .iter()
+            .zip(&self.smooth)
+            .map(|(n, s)| {
+                n.unwrap_or_else(|| sums.get(s).and_then(|n| n.normalize()).unwrap_or(Vec3(0.0, 0.0, 0.0)))
+            })
+            .collect());
+        Object {
+            name: self.name,
+            material: self.material,
+            mesh,
+        }
+    }
+}
+
+// Args splits a line of an OBJ or MTL file into its keyword and arguments.
+struct Args<'a> {
+    keyword: &'a str,
+    tokens: SplitWhitespace<'a>,
+    rest: &'a str,
+    line: usize,
+}
+
+impl<'a> Args<'a> {
+    // new returns the arguments of the line, or None if it's blank or a
+    // comment.
+    fn new(text: &'a str, line: usize) -> Option<Self> {
+        let text = text.split('#').next().unwrap_or("").trim();
+        let mut tokens = text.split_whitespace();
+        let keyword = tokens.next()?;
+        Some(Args {
+            keyword,
+            tokens,
+            rest: text[keyword.len()..].trim(),
+            line,
+        })
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    fn missing(&self) -> ObjError {
+        ObjError::MissingArgument {
+            line: self.line,
+            statement: self.keyword.to_string(),
+        }
+    }
+
+    fn float(&mut self) -> Result<f32, ObjError> {
+        let s = self.next().ok_or_else(|| self.missing())?;
+        parse(s, self.line)
+    }
+
+    fn vec3(&mut self) -> Result<Vec3<f32>, ObjError> {
+        Ok(Vec3(self.float()?, self.float()?, self.float()?))
+    }
+
+    // rest returns all of the arguments, as names may contain spaces.
+    fn rest(&self) -> Result<&'a str, ObjError> {
+        if self.rest.is_empty() { Err(self.missing()) } else { Ok(self.rest) }
+    }
+
+    fn last(&mut self) -> Result<&'a str, ObjError> {
+        self.tokens.clone().last().ok_or_else(|| self.missing())
+    }
+}
+
+// parse parses a number, reporting the line number if it's invalid.
+fn parse<T: ::std::str::FromStr>(s: &str, line: usize) -> Result<T, ObjError> {
+    s.parse().map_err(|_| {
+        ObjError::InvalidNumber {
+            line,
+            text: s.to_string(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use math::Vec3;
+    use super::{load, parse_obj, write_obj, ObjError};
+
+    #[test]
+    fn concave_and_smoothing() {
+        // An L-shaped hexagon, which a triangle fan would get wrong, and a
+        // smoothed roof whose two slopes meet at a ridge.
+        let obj = parse_obj("
+            v 0 0 0
+            v 2 0 0
+            v 2 1 0
+            v 1 1 0
+            v 1 2 0
+            v 0 2 0
+            f 1 2 3 4 5 6
+            v 0 0 0
+            v 1 0 1
+            v 1 1 1
+            v 0 1 0
+            v 2 0 0
+            v 2 1 0
+            o ridge
+            s 1
+            f 7 8 9
+            f 7 9 10
+            f 8 11 12
+            f -5 -1 -4
+        ").unwrap();
+        let l = &obj.objects[0].mesh;
+        assert_eq!(l.triangle_count(), 4);
+        let area: f32 = (0..4).map(|i| {
+            let [a, b, c] = l.triangle(i);
+            (b - a).cross(c - a).2 / 2.0
+        }).sum();
+        assert_eq!(area, 3.0);
+        assert!(l.normals.as_ref().unwrap().iter().all(|&n| n == Vec3(0.0, 0.0, 1.0)));
+
+        // The ridge vertices shared by both slopes point straight up.
+        let ridge = &obj.objects[1].mesh;
+        assert_eq!(ridge.vertex_count(), 6);
+        let n = ridge.normals.as_ref().unwrap()[1];
+        assert!(n.almost_equal(Vec3(0.0, 0.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src: &str| parse_obj(src).unwrap_err();
+        assert!(match err("v 1 2\n") {
+            ObjError::MissingArgument { line: 1, ref statement } => statement == "v",
+            _ => false,
+        });
+        assert!(match err("v 0 0 0\nv 0 0 0\n\nv 1 1 x\n") {
+            ObjError::InvalidNumber { line: 4, ref text } => text == "x",
+            _ => false,
+        });
+        assert!(matches!(err("v 0 0 0\nf 1 1\n"), ObjError::InvalidFace { line: 2 }));
+        assert!(matches!(err("v 0 0 0\nf 1 0 1\n"), ObjError::InvalidIndex { line: 2, index: 0 }));
+        assert_eq!(err("v 0 0 0\n# comment\nf 1/2 1 1\n").to_string(), "line 3: index 2 is out of range");
+    }
+
+    #[test]
+    fn load_with_materials() {
+        let dir = env::temp_dir().join(format!("fiz-gfx-obj-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("scene.mtl"), "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n").unwrap();
+        fs::write(dir.join("scene.obj"), "
+            mtllib scene.mtl
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vn 0 0 1
+            usemtl red
+            f 1/1/1 2/2/1 3/3/1
+            usemtl blue
+            f 1//1 3//1 4//1
+            usemtl red
+            f 2/2/1 1/1/1 4/1/1
+        ").unwrap();
+        let obj = load(dir.join("scene.obj")).unwrap();
+        assert_eq!(obj.objects.len(), 2);
+        assert_eq!(obj.objects[0].mesh.triangle_count(), 2);
+        assert_eq!(obj.material(obj.objects[1].material.as_ref().unwrap()).unwrap().diffuse, Vec3(0.0, 0.0, 1.0));
+
+        // Round trip through the exporter.
+        let mut out = Vec::new();
+        write_obj(&mut out, &obj).unwrap();
+        let again = parse_obj(&String::from_utf8(out).unwrap()).unwrap();
+        assert_eq!(again.objects, obj.objects);
+
+        fs::write(dir.join("scene.mtl"), "newmtl red\nKd 1 0\n").unwrap();
+        let err = load(dir.join("scene.obj")).unwrap_err();
+        assert!(err.to_string().ends_with("scene.mtl: line 2: missing argument to \"Kd\""));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::{self, Write};
+use math::Vec3;
+use super::{ObjError, Args};
+
+/// Material is a material described by an MTL file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    /// The name of the material, by which objects refer to it.
+    pub name: String,
+
+    /// The ambient color (`Ka`).
+    pub ambient: Vec3<f32>,
+
+    /// The diffuse color (`Kd`).
+    pub diffuse: Vec3<f32>,
+
+    /// The specular color (`Ks`).
+    pub specular: Vec3<f32>,
+
+    /// The emissive color (`Ke`).
+    pub emissive: Vec3<f32>,
+
+    /// The specular exponent (`Ns`).
+    pub shininess: f32,
+
+    /// The opacity (`d`, or one minus `Tr`), where one is fully opaque.
+    pub opacity: f32,
+
+    /// The index of refraction (`Ni`).
+    pub ior: f32,
+
+    /// The illumination model (`illum`).
+    pub illum: u32,
+
+    /// The diffuse texture (`map_Kd`).
+    pub diffuse_map: Option<String>,
+
+    /// The specular texture (`map_Ks`).
+    pub specular_map: Option<String>,
+
+    /// The emissive texture (`map_Ke`).
+    pub emissive_map: Option<String>,
+
+    /// The opacity texture (`map_d`).
+    pub opacity_map: Option<String>,
+
+    /// The bump or normal texture (`map_Bump`, `bump` or `norm`).
+    pub normal_map: Option<String>,
+}
+
+impl Material {
+    /// new returns a new material with the given name and default
+    /// properties: a white diffuse color, no specular highlights and full
+    /// opacity.
+    pub fn new(name: &str) -> Self {
+        Material {
+            name: name.to_string(),
+            ambient: Vec3(0.0, 0.0, 0.0),
+            diffuse: Vec3(1.0, 1.0, 1.0),
+            specular: Vec3(0.0, 0.0, 0.0),
+            emissive: Vec3(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            opacity: 1.0,
+            ior: 1.0,
+            illum: 2,
+            diffuse_map: None,
+            specular_map: None,
+            emissive_map: None,
+            opacity_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+/// parse_mtl parses the materials of an MTL file. Unsupported statements are
+/// ignored.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::obj::{parse_mtl, ObjError};
+///
+/// let materials = parse_mtl("newmtl brick\nKd 0.8 0.3 0.2\nmap_Kd -bm 1.0 brick.png\n").unwrap();
+/// assert_eq!(materials[0].name, "brick");
+/// assert_eq!(materials[0].diffuse, Vec3(0.8, 0.3, 0.2));
+/// assert_eq!(materials[0].diffuse_map, Some("brick.png".to_string()));
+///
+/// match parse_mtl("newmtl a\nNs high\n") {
+///     Err(ObjError::InvalidNumber { line: 2, .. }) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse_mtl(src: &str) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+    for (i, text) in src.lines().enumerate() {
+        let line = i + 1;
+        let mut args = match Args::new(text, line) {
+            Some(a) => a,
+            None => continue,
+        };
+        let keyword = args.keyword;
+        if keyword == "newmtl" {
+            materials.push(Material::new(args.rest()?));
+            continue;
+        }
+        let known = ["Ka", "Kd", "Ks", "Ke", "Ns", "d", "Tr", "Ni", "illum", "map_Kd", "map_Ks", "map_Ke", "map_d",
+                     "map_Bump", "map_bump", "bump", "norm"];
+        if !known.contains(&keyword) {
+            continue;
+        }
+        let m = materials.last_mut().ok_or_else(|| ObjError::NoMaterial {
+            line,
+            statement: keyword.to_string(),
+        })?;
+        match keyword {
+            "Ka" => m.ambient = args.vec3()?,
+            "Kd" => m.diffuse = args.vec3()?,
+            "Ks" => m.specular = args.vec3()?,
+            "Ke" => m.emissive = args.vec3()?,
+            "Ns" => m.shininess = args.float()?,
+            "d" => m.opacity = args.float()?,
+            "Tr" => m.opacity = 1.0 - args.float()?,
+            "Ni" => m.ior = args.float()?,
+            "illum" => m.illum = args.float()? as u32,
+            _ => {
+                // Texture options precede the file name.
+                let file = Some(args.last()?.to_string());
+                match keyword {
+                    "map_Kd" => m.diffuse_map = file,
+                    "map_Ks" => m.specular_map = file,
+                    "map_Ke" => m.emissive_map = file,
+                    "map_d" => m.opacity_map = file,
+                    _ => m.normal_map = file,
+                }
+            }
+        }
+    }
+    Ok(materials)
+}
+
+/// write_mtl writes the materials in the MTL format.
+pub fn write_mtl<W: Write>(w: &mut W, materials: &[Material]) -> io::Result<()> {
+    for m in materials {
+        writeln!(w, "newmtl {}", m.name)?;
+        let colors = [("Ka", m.ambient), ("Kd", m.diffuse), ("Ks", m.specular), ("Ke", m.emissive)];
+        for &(keyword, c) in &colors {
+            writeln!(w, "{} {} {} {}", keyword, c.0, c.1, c.2)?;
+        }
+        writeln!(w, "Ns {}", m.shininess)?;
+        writeln!(w, "d {}", m.opacity)?;
+        writeln!(w, "Ni {}", m.ior)?;
+        writeln!(w, "illum {}", m.illum)?;
+        let maps = [("map_Kd", &m.diffuse_map),
+                    ("map_Ks", &m.specular_map),
+                    ("map_Ke", &m.emissive_map),
+                    ("map_d", &m.opacity_map),
+                    ("map_Bump", &m.normal_map)];
+        for &(keyword, file) in &maps {
+            if let Some(ref file) = *file {
+                writeln!(w, "{} {}", keyword, file)?;
+            }
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
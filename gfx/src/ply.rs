@@ -0,0 +1,787 @@
+//! PLY (Stanford polygon file) import and export.
+//!
+//! ASCII, binary little-endian and binary big-endian files are supported.
+//! The standard vertex properties (positions, normals, texture coordinates
+//! and colors) are mapped onto the `Mesh`, and any other scalar vertex
+//! properties, such as the confidence or intensity values written by
+//! scanners, are kept alongside it. Faces are split into triangle fans.
+//! Elements other than vertices and faces are skipped.
+//!
+//! The units of the coordinates are read from a comment of the form
+//! `comment units mm`, which is also written on export.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use math::{Vec2, Vec3, Vec4};
+use super::{Mesh, Units};
+
+/// PlyError describes a failure to load a PLY file.
+#[derive(Debug)]
+pub enum PlyError {
+    /// A file couldn't be read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// A line of the header is invalid.
+    Header {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// An element in the body of the file is invalid.
+    Data {
+        /// The name of the element, e.g. `vertex`.
+        element: String,
+
+        /// The index of the element, starting at zero.
+        index: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlyError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            PlyError::Header { line, ref message } => write!(f, "line {}: {}", line, message),
+            PlyError::Data { ref element, index, ref message } => write!(f, "{} {}: {}", element, index, message),
+        }
+    }
+}
+
+impl Error for PlyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PlyError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// PlyFormat is the encoding of the body of a PLY file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// Whitespace separated numbers.
+    Ascii,
+
+    /// Little-endian binary numbers.
+    BinaryLittleEndian,
+
+    /// Big-endian binary numbers.
+    BinaryBigEndian,
+}
+
+/// ScalarType is the type of a PLY property.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalarType {
+    /// A signed 8-bit integer (`char` or `int8`).
+    Char,
+
+    /// An unsigned 8-bit integer (`uchar` or `uint8`).
+    UChar,
+
+    /// A signed 16-bit integer (`short` or `int16`).
+    Short,
+
+    /// An unsigned 16-bit integer (`ushort` or `uint16`).
+    UShort,
+
+    /// A signed 32-bit integer (`int` or `int32`).
+    Int,
+
+    /// An unsigned 32-bit integer (`uint` or `uint32`).
+    UInt,
+
+    /// A 32-bit float (`float` or `float32`).
+    Float,
+
+    /// A 64-bit float (`double` or `float64`).
+    Double,
+}
+
+impl ScalarType {
+    /// from_name returns the type with the given name, e.g. `uchar` or
+    /// `uint8`.
+    pub fn from_name(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Char),
+            "uchar" | "uint8" => Some(ScalarType::UChar),
+            "short" | "int16" => Some(ScalarType::Short),
+            "ushort" | "uint16" => Some(ScalarType::UShort),
+            "int" | "int32" => Some(ScalarType::Int),
+            "uint" | "uint32" => Some(ScalarType::UInt),
+            "float" | "float32" => Some(ScalarType::Float),
+            "double" | "float64" => Some(ScalarType::Double),
+            _ => None,
+        }
+    }
+
+    /// name returns the original PLY name of the type, e.g. `uchar`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarType::Char => "char",
+            ScalarType::UChar => "uchar",
+            ScalarType::Short => "short",
+            ScalarType::UShort => "ushort",
+            ScalarType::Int => "int",
+            ScalarType::UInt => "uint",
+            ScalarType::Float => "float",
+            ScalarType::Double => "double",
+        }
+    }
+
+    // size returns the size of the type in bytes.
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Char | ScalarType::UChar => 1,
+            ScalarType::Short | ScalarType::UShort => 2,
+            ScalarType::Int | ScalarType::UInt | ScalarType::Float => 4,
+            ScalarType::Double => 8,
+        }
+    }
+
+    // max returns the value which integer colors of the type are divided by
+    // to map them onto [0, 1], or one for floats.
+    fn max(self) -> f64 {
+        match self {
+            ScalarType::Char => 127.0,
+            ScalarType::UChar => 255.0,
+            ScalarType::Short => 32767.0,
+            ScalarType::UShort => 65535.0,
+            ScalarType::Int => 2147483647.0,
+            ScalarType::UInt => 4294967295.0,
+            ScalarType::Float | ScalarType::Double => 1.0,
+        }
+    }
+}
+
+/// Property is a vertex property with no counterpart in `Mesh`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    /// The name of the property.
+    pub name: String,
+
+    /// The type of the property in the file.
+    pub scalar_type: ScalarType,
+
+    /// The value of the property for each vertex.
+    pub values: Vec<f64>,
+}
+
+/// Ply is the contents of a PLY file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ply {
+    /// The encoding of the file.
+    pub format: PlyFormat,
+
+    /// The comments of the header, except for the one giving the units.
+    pub comments: Vec<String>,
+
+    /// The units of the coordinates, if given.
+    pub units: Option<Units>,
+
+    /// The triangles and standard vertex attributes. Colors with integer
+    /// types are mapped onto [0, 1].
+    pub mesh: Mesh,
+
+    /// The other scalar vertex properties, in order of declaration.
+    pub properties: Vec<Property>,
+}
+
+impl Ply {
+    /// new returns a new PLY model of the mesh, in the given format, with no
+    /// comments, units or extra properties.
+    pub fn new(format: PlyFormat, mesh: Mesh) -> Self {
+        Ply {
+            format,
+            comments: Vec::new(),
+            units: None,
+            mesh,
+            properties: Vec::new(),
+        }
+    }
+}
+
+// PropertyDef is the declaration of a property in the header.
+struct PropertyDef {
+    name: String,
+    scalar_type: ScalarType,
+
+    // The type of the length of a list property, or None for scalars.
+    list: Option<ScalarType>,
+}
+
+// ElementDef is the declaration of an element in the header.
+struct ElementDef {
+    name: String,
+    count: usize,
+    properties: Vec<PropertyDef>,
+}
+
+/// load loads the PLY file at the given path.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Ply, PlyError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|error| {
+        PlyError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    parse_ply(&data)
+}
+
+/// parse_ply parses the contents of a PLY file.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::Units;
+/// use fiz_gfx::ply::{parse_ply, PlyError};
+///
+/// let ply = parse_ply(b"ply
+/// format ascii 1.0
+/// comment units m
+/// element vertex 4
+/// property float x
+/// property float y
+/// property float z
+/// property float confidence
+/// element face 1
+/// property list uchar int vertex_indices
+/// end_header
+/// 0 0 0 0.5
+/// 1 0 0 0.5
+/// 1 1 0 0.75
+/// 0 1 0 1
+/// 4 0 1 2 3
+/// ").unwrap();
+/// assert_eq!(ply.units, Some(Units::M));
+/// assert_eq!(ply.mesh.positions[2], Vec3(1.0, 1.0, 0.0));
+/// assert_eq!(ply.mesh.indices, vec![[0, 1, 2], [0, 2, 3]]);
+/// assert_eq!(ply.properties[0].name, "confidence");
+/// assert_eq!(ply.properties[0].values, vec![0.5, 0.5, 0.75, 1.0]);
+///
+/// match parse_ply(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\nend_header\n") {
+///     Err(PlyError::Header { line: 4, .. }) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse_ply(data: &[u8]) -> Result<Ply, PlyError> {
+    let (format, comments, units, elements, body) = parse_header(data)?;
+    let mut ply = Ply {
+        format,
+        comments,
+        units,
+        mesh: Mesh::default(),
+        properties: Vec::new(),
+    };
+    let mut source = match format {
+        PlyFormat::Ascii => {
+            let text = ::std::str::from_utf8(body).map_err(|_| {
+                PlyError::Data {
+                    element: elements.first().map_or("", |e| &e.name).to_string(),
+                    index: 0,
+                    message: "invalid UTF-8".to_string(),
+                }
+            })?;
+            Source::Ascii(text.split_whitespace())
+        }
+        _ => {
+            Source::Binary {
+                data: body,
+                big_endian: format == PlyFormat::BinaryBigEndian,
+            }
+        }
+    };
+
+    let mut faces = Vec::new();
+    let mut row = Vec::new();
+    for e in &elements {
+        // Each value takes at least a byte, which bounds the allocations.
+        let rows = e.count.min(body.len());
+        let layout = if e.name == "vertex" { Some(VertexLayout::new(e, rows, &mut ply)) } else { None };
+        for index in 0..e.count {
+            let error = |message: &str| {
+                PlyError::Data {
+                    element: e.name.clone(),
+                    index,
+                    message: message.to_string(),
+                }
+            };
+            row.clear();
+            for p in &e.properties {
+                let value = match p.list {
+                    None => source.read(p.scalar_type).ok_or_else(|| error("missing data"))?,
+                    Some(length_type) => {
+                        let len = source.read(length_type).ok_or_else(|| error("missing data"))?;
+                        let mut items = Vec::with_capacity((len as usize).min(body.len()));
+                        for _ in 0..len as usize {
+                            items.push(source.read(p.scalar_type).ok_or_else(|| error("missing data"))?);
+                        }
+                        if e.name == "face" && (p.name == "vertex_indices" || p.name == "vertex_index") {
+                            faces.push((index, items));
+                        }
+                        continue;
+                    }
+                };
+                row.push(value);
+            }
+            if let Some(ref layout) = layout {
+                layout.add(&mut ply, &row);
+            }
+        }
+    }
+
+    let n = ply.mesh.positions.len();
+    for (index, face) in faces {
+        if face.iter().any(|&v| v < 0.0 || v as usize >= n) {
+            return Err(PlyError::Data {
+                element: "face".to_string(),
+                index,
+                message: "vertex index out of range".to_string(),
+            });
+        }
+        for k in 1..face.len().saturating_sub(1) {
+            ply.mesh.indices.push([face[0] as u32, face[k] as u32, face[k + 1] as u32]);
+        }
+    }
+    Ok(ply)
+}
+
+// parse_header parses the header, returning the format, comments, units,
+// element declarations and body of the file.
+#[allow(clippy::type_complexity)]
+fn parse_header(data: &[u8])
+                -> Result<(PlyFormat, Vec<String>, Option<Units>, Vec<ElementDef>, &[u8]), PlyError> {
+    let header_error = |line, message: &str| {
+        PlyError::Header {
+            line,
+            message: message.to_string(),
+        }
+    };
+    let end = data.windows(10)
+        .position(|w| w == b"end_header")
+        .ok_or_else(|| header_error(1, "missing end_header"))?;
+    let mut body = &data[end + 10..];
+    if body.starts_with(b"\r") {
+        body = &body[1..];
+    }
+    if body.starts_with(b"\n") {
+        body = &body[1..];
+    }
+    let header = String::from_utf8_lossy(&data[..end]);
+
+    let mut format = None;
+    let mut comments = Vec::new();
+    let mut units = None;
+    let mut elements: Vec<ElementDef> = Vec::new();
+    for (i, text) in header.lines().enumerate() {
+        let line = i + 1;
+        let mut tokens = text.split_whitespace();
+        let keyword = tokens.next();
+        if line == 1 {
+            if keyword != Some("ply") {
+                return Err(header_error(line, "not a PLY file"));
+            }
+            continue;
+        }
+        match keyword {
+            Some("format") => {
+                format = Some(match tokens.next() {
+                    Some("ascii") => PlyFormat::Ascii,
+                    Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                    Some("binary_big_endian") => PlyFormat::BinaryBigEndian,
+                    _ => return Err(header_error(line, "unknown format")),
+                });
+            }
+            Some("comment") | Some("obj_info") => {
+                let rest = text.trim_start()[keyword.unwrap().len()..].trim();
+                match parse_units(rest) {
+                    Some(u) => units = Some(u),
+                    None if keyword == Some("comment") => comments.push(rest.to_string()),
+                    None => {}
+                }
+            }
+            Some("element") => {
+                let name = tokens.next().ok_or_else(|| header_error(line, "missing element name"))?;
+                let count = tokens.next()
+                    .and_then(|c| c.parse().ok())
+                    .ok_or_else(|| header_error(line, "invalid element count"))?;
+                elements.push(ElementDef {
+                    name: name.to_string(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let e = elements.last_mut().ok_or_else(|| header_error(line, "property outside of an element"))?;
+                let scalar = |t: Option<&str>| {
+                    t.and_then(ScalarType::from_name).ok_or_else(|| header_error(line, "unknown property type"))
+                };
+                let (list, scalar_type) = match tokens.next() {
+                    Some("list") => (Some(scalar(tokens.next())?), scalar(tokens.next())?),
+                    t => (None, scalar(t)?),
+                };
+                let name = tokens.next().ok_or_else(|| header_error(line, "missing property name"))?;
+                e.properties.push(PropertyDef {
+                    name: name.to_string(),
+                    scalar_type,
+                    list,
+                });
+            }
+            None => {}
+            Some(_) => return Err(header_error(line, "unknown header statement")),
+        }
+    }
+    let format = format.ok_or_else(|| header_error(2, "missing format"))?;
+    Ok((format, comments, units, elements, body))
+}
+
+// parse_units returns the units given by a comment such as "units: mm".
+fn parse_units(comment: &str) -> Option<Units> {
+    let mut words = comment.split(|c: char| c.is_whitespace() || c == ':' || c == '=').filter(|w| !w.is_empty());
+    match words.next().map(|w| w.to_lowercase()) {
+        Some(ref w) if w == "unit" || w == "units" => words.next().and_then(Units::from_name),
+        _ => None,
+    }
+}
+
+// VertexLayout gives the positions of the scalar properties of the vertex
+// element which map onto the attributes of a mesh.
+struct VertexLayout {
+    position: [Option<usize>; 3],
+    normal: Option<[usize; 3]>,
+    uv: Option<[usize; 2]>,
+    color: Option<[usize; 3]>,
+    alpha: Option<usize>,
+
+    // The divisor of each color channel.
+    color_max: [f64; 4],
+
+    // The other properties.
+    extra: Vec<usize>,
+}
+
+impl VertexLayout {
+    // new returns the layout of the element, adding its other properties to
+    // the model with room for rows values.
+    fn new(e: &ElementDef, rows: usize, ply: &mut Ply) -> Self {
+        let scalars: Vec<&PropertyDef> = e.properties.iter().filter(|p| p.list.is_none()).collect();
+        let find = |names: &[&str]| scalars.iter().position(|p| names.contains(&p.name.as_str()));
+        let max = |i: Option<usize>| i.map_or(1.0, |i| scalars[i].scalar_type.max());
+        let (x, y, z) = (find(&["x"]), find(&["y"]), find(&["z"]));
+        let normal = (find(&["nx"]), find(&["ny"]), find(&["nz"]));
+        let uv = (find(&["u", "s", "texture_u"]), find(&["v", "t", "texture_v"]));
+        let color = (find(&["red"]), find(&["green"]), find(&["blue"]));
+        let alpha = find(&["alpha"]);
+        let mut known: Vec<usize> = [x, y, z, normal.0, normal.1, normal.2, uv.0, uv.1, color.0, color.1, color.2, alpha]
+            .iter()
+            .filter_map(|&i| i)
+            .collect();
+        known.sort();
+
+        let mut extra = Vec::new();
+        for (i, p) in scalars.iter().enumerate() {
+            if known.binary_search(&i).is_err() {
+                extra.push(i);
+                ply.properties.push(Property {
+                    name: p.name.clone(),
+                    scalar_type: p.scalar_type,
+                    values: Vec::with_capacity(rows),
+                });
+            }
+        }
+        VertexLayout {
+            position: [x, y, z],
+            normal: match normal {
+                (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+                _ => None,
+            },
+            uv: match uv {
+                (Some(u), Some(v)) => Some([u, v]),
+                _ => None,
+            },
+            color: match color {
+                (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+                _ => None,
+            },
+            alpha,
+            color_max: [max(color.0), max(color.1), max(color.2), max(alpha)],
+            extra,
+        }
+    }
+
+    // add adds a vertex to the model, given the values of the scalar
+    // properties of the element.
+    fn add(&self, ply: &mut Ply, row: &[f64]) {
+        let mesh = &mut ply.mesh;
+        let p = |i: Option<usize>| i.map_or(0.0, |i| row[i] as f32);
+        mesh.positions.push(Vec3(p(self.position[0]), p(self.position[1]), p(self.position[2])));
+        if let Some(n) = self.normal {
+            mesh.normals.get_or_insert_with(Vec::new).push(Vec3(row[n[0]] as f32, row[n[1]] as f32, row[n[2]] as f32));
+        }
+        if let Some(uv) = self.uv {
+            mesh.uvs.get_or_insert_with(Vec::new).push(Vec2(row[uv[0]] as f32, row[uv[1]] as f32));
+        }
+        if let Some(c) = self.color {
+            let channel = |k: usize, i: usize| (row[i] / self.color_max[k]) as f32;
+            let alpha = self.alpha.map_or(1.0, |i| channel(3, i));
+            mesh.colors.get_or_insert_with(Vec::new).push(Vec4(channel(0, c[0]), channel(1, c[1]), channel(2, c[2]), alpha));
+        }
+        let first = ply.properties.len() - self.extra.len();
+        for (p, &i) in ply.properties[first..].iter_mut().zip(&self.extra) {
+            p.values.push(row[i]);
+        }
+    }
+}
+
+// Source reads the values of the body of a file.
+enum Source<'a> {
+    Ascii(::std::str::SplitWhitespace<'a>),
+    Binary { data: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Source<'a> {
+    fn read(&mut self, t: ScalarType) -> Option<f64> {
+        match *self {
+            Source::Ascii(ref mut tokens) => tokens.next().and_then(|s| s.parse().ok()),
+            Source::Binary { ref mut data, big_endian } => {
+                let size = t.size();
+                if data.len() < size {
+                    return None;
+                }
+                let mut b = [0u8; 8];
+                b[..size].copy_from_slice(&data[..size]);
+                *data = &data[size..];
+                if big_endian {
+                    b[..size].reverse();
+                }
+                Some(match t {
+                    ScalarType::Char => b[0] as i8 as f64,
+                    ScalarType::UChar => b[0] as f64,
+                    ScalarType::Short => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    ScalarType::UShort => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    ScalarType::Int => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    ScalarType::UInt => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    ScalarType::Float => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    ScalarType::Double => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+}
+
+/// write_ply writes the model in its format. Positions, normals and texture
+/// coordinates are written as floats and colors as bytes, followed by the
+/// extra properties with their own types.
+///
+/// # Panics
+///
+/// write_ply panics if an extra property doesn't have one value per vertex.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::{Mesh, Units};
+/// use fiz_gfx::ply::{parse_ply, write_ply, Ply, PlyFormat};
+///
+/// let mesh = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)], vec![[0, 1, 2]]);
+/// let mut ply = Ply::new(PlyFormat::BinaryLittleEndian, mesh.clone());
+/// ply.units = Some(Units::MM);
+///
+/// let mut out = Vec::new();
+/// write_ply(&mut out, &ply).unwrap();
+/// assert_eq!(parse_ply(&out).unwrap(), ply);
+/// ```
+pub fn write_ply<W: Write>(w: &mut W, ply: &Ply) -> io::Result<()> {
+    let mesh = &ply.mesh;
+    writeln!(w, "ply")?;
+    writeln!(w,
+             "format {} 1.0",
+             match ply.format {
+                 PlyFormat::Ascii => "ascii",
+                 PlyFormat::BinaryLittleEndian => "binary_little_endian",
+                 PlyFormat::BinaryBigEndian => "binary_big_endian",
+             })?;
+    for c in &ply.comments {
+        writeln!(w, "comment {}", c)?;
+    }
+    if let Some(units) = ply.units {
+        writeln!(w, "comment units {}", units.name())?;
+    }
+
+    // The columns of the vertex element.
+    let mut columns: Vec<(&str, ScalarType)> = vec![("x", ScalarType::Float), ("y", ScalarType::Float),
+                                                    ("z", ScalarType::Float)];
+    if mesh.normals.is_some() {
+        columns.extend_from_slice(&[("nx", ScalarType::Float), ("ny", ScalarType::Float), ("nz", ScalarType::Float)]);
+    }
+    if mesh.uvs.is_some() {
+        columns.extend_from_slice(&[("s", ScalarType::Float), ("t", ScalarType::Float)]);
+    }
+    if mesh.colors.is_some() {
+        columns.extend_from_slice(&[("red", ScalarType::UChar), ("green", ScalarType::UChar),
+                                    ("blue", ScalarType::UChar), ("alpha", ScalarType::UChar)]);
+    }
+    for p in &ply.properties {
+        assert_eq!(p.values.len(), mesh.vertex_count(), "values of property {}", p.name);
+        columns.push((&p.name, p.scalar_type));
+    }
+    writeln!(w, "element vertex {}", mesh.vertex_count())?;
+    for &(name, t) in &columns {
+        writeln!(w, "property {} {}", t.name(), name)?;
+    }
+    writeln!(w, "element face {}", mesh.triangle_count())?;
+    writeln!(w, "property list uchar int vertex_indices")?;
+    writeln!(w, "end_header")?;
+
+    let mut row = Vec::with_capacity(columns.len());
+    for v in 0..mesh.vertex_count() {
+        row.clear();
+        let p = mesh.positions[v];
+        row.extend_from_slice(&[p.0 as f64, p.1 as f64, p.2 as f64]);
+        if let Some(ref n) = mesh.normals {
+            row.extend_from_slice(&[n[v].0 as f64, n[v].1 as f64, n[v].2 as f64]);
+        }
+        if let Some(ref uv) = mesh.uvs {
+            row.extend_from_slice(&[uv[v].0 as f64, uv[v].1 as f64]);
+        }
+        if let Some(ref c) = mesh.colors {
+            let byte = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as f64;
+            row.extend_from_slice(&[byte(c[v].0), byte(c[v].1), byte(c[v].2), byte(c[v].3)]);
+        }
+        for p in &ply.properties {
+            row.push(p.values[v]);
+        }
+        let types = columns.iter().map(|c| c.1);
+        write_row(w, ply.format, types.zip(row.iter().cloned()))?;
+    }
+    for t in &mesh.indices {
+        let types = [ScalarType::UChar, ScalarType::Int, ScalarType::Int, ScalarType::Int];
+        let values = [3.0, t[0] as f64, t[1] as f64, t[2] as f64];
+        write_row(w, ply.format, types.iter().cloned().zip(values.iter().cloned()))?;
+    }
+    Ok(())
+}
+
+// write_row writes the values of an element in the given format.
+fn write_row<W: Write, I: Iterator<Item = (ScalarType, f64)>>(w: &mut W, format: PlyFormat, values: I)
+                                                               -> io::Result<()> {
+    let mut first = true;
+    for (t, x) in values {
+        if format == PlyFormat::Ascii {
+            if !first {
+                write!(w, " ")?;
+            }
+            first = false;
+            match t {
+                ScalarType::Float => write!(w, "{}", x as f32)?,
+                _ => write!(w, "{}", x)?,
+            }
+            continue;
+        }
+        let mut b = [0u8; 8];
+        let size = t.size();
+        match t {
+            ScalarType::Char => b[0] = x as i8 as u8,
+            ScalarType::UChar => b[0] = x as u8,
+            ScalarType::Short => b[..2].copy_from_slice(&(x as i16).to_le_bytes()),
+            ScalarType::UShort => b[..2].copy_from_slice(&(x as u16).to_le_bytes()),
+            ScalarType::Int => b[..4].copy_from_slice(&(x as i32).to_le_bytes()),
+            ScalarType::UInt => b[..4].copy_from_slice(&(x as u32).to_le_bytes()),
+            ScalarType::Float => b[..4].copy_from_slice(&(x as f32).to_le_bytes()),
+            ScalarType::Double => b = x.to_le_bytes(),
+        }
+        if format == PlyFormat::BinaryBigEndian {
+            b[..size].reverse();
+        }
+        w.write_all(&b[..size])?;
+    }
+    if format == PlyFormat::Ascii {
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec2, Vec3, Vec4};
+    use super::super::{Mesh, Units};
+    use super::{parse_ply, write_ply, Ply, PlyFormat, Property, ScalarType};
+
+    #[test]
+    fn round_trip() {
+        let mut mesh = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.25)],
+                                 vec![[0, 1, 2]]);
+        mesh.normals = Some(vec![Vec3(0.0, 0.0, 1.0); 3]);
+        mesh.uvs = Some(vec![Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(0.0, 0.5)]);
+        mesh.colors = Some(vec![Vec4(1.0, 0.0, 0.0, 1.0), Vec4(0.0, 1.0, 0.0, 1.0), Vec4(0.0, 0.0, 1.0, 0.0)]);
+        for &format in &[PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+            let mut ply = Ply::new(format, mesh.clone());
+            ply.units = Some(Units::CM);
+            ply.comments.push("scanned".to_string());
+            ply.properties.push(Property {
+                name: "intensity".to_string(),
+                scalar_type: ScalarType::UShort,
+                values: vec![1.0, 2.0, 65535.0],
+            });
+            ply.properties.push(Property {
+                name: "confidence".to_string(),
+                scalar_type: ScalarType::Double,
+                values: vec![0.1, 0.2, 0.3],
+            });
+            let mut out = Vec::new();
+            write_ply(&mut out, &ply).unwrap();
+            assert_eq!(parse_ply(&out).unwrap(), ply);
+        }
+    }
+
+    #[test]
+    fn other_elements() {
+        // Integer colors, an unknown element and list property, and a face
+        // property which are skipped.
+        let mut data = b"ply\r\nformat binary_big_endian 1.0\r\nobj_info units: mm\r\nelement vertex 3\r
+property uchar red\r\nproperty uchar green\r\nproperty uchar blue\r\nproperty short x\r\nproperty short y\r
+property short z\r\nproperty list uchar float weights\r\nelement edge 1\r\nproperty int a\r\nproperty int b\r
+element face 1\r\nproperty uchar flags\r\nproperty list uchar uint vertex_index\r\nend_header\r\n"
+            .to_vec();
+        for v in 0..3u8 {
+            data.extend_from_slice(&[255, 0, 51 * v, 0, v, 0, 2, 0, 0, 1, 0, 0, 0, 0]);
+        }
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        data.extend_from_slice(&[7, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2]);
+        let ply = parse_ply(&data).unwrap();
+        assert_eq!(ply.units, Some(Units::MM));
+        assert!(ply.comments.is_empty() && ply.properties.is_empty());
+        assert_eq!(ply.mesh.positions, vec![Vec3(0.0, 2.0, 0.0), Vec3(1.0, 2.0, 0.0), Vec3(2.0, 2.0, 0.0)]);
+        assert_eq!(ply.mesh.colors.unwrap()[1], Vec4(1.0, 0.0, 0.2, 1.0));
+        assert_eq!(ply.mesh.indices, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn truncated() {
+        // Counts and list lengths far beyond the data fail without
+        // allocating for them.
+        let data = b"ply\nformat binary_little_endian 1.0\nelement vertex 4294967295\nproperty float x
+property float w\nelement face 1\nproperty list uint uint vertex_indices\nend_header\n\xff\xff\xff\xff";
+        assert!(parse_ply(data).is_err());
+        let data = b"ply\nformat binary_little_endian 1.0\nelement face 1\nproperty list uint uint vertex_indices
+end_header\n\xff\xff\xff\xff";
+        assert!(parse_ply(data).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use math::Vec4;
+use super::super::image::{Image, PixelFormat};
+
+// The sample positions within a pixel for each supported sample count, in
+// 1/256ths of a pixel from its top-left corner. These are the standard
+// Direct3D patterns.
+const PATTERN_1: [(i64, i64); 1] = [(128, 128)];
+const PATTERN_2: [(i64, i64); 2] = [(192, 192), (64, 64)];
+const PATTERN_4: [(i64, i64); 4] = [(96, 32), (224, 96), (32, 160), (160, 224)];
+const PATTERN_8: [(i64, i64); 8] = [(144, 80), (112, 176), (208, 144), (80, 48), (48, 208), (16, 112), (176, 240),
+                                    (240, 16)];
+
+/// Framebuffer is an in-memory multisampled color and depth buffer, which
+/// the `Rasterizer` draws into.
+///
+/// Pixels are addressed from the top-left corner of the image. Each pixel
+/// has one or more samples, each with its own RGBA color and depth.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    samples: u32,
+    color: Vec<Vec4<f32>>,
+    depth: Vec<f32>,
+}
+
+impl Framebuffer {
+    /// new returns a new framebuffer of the given size and number of samples
+    /// per pixel, cleared to transparent black and the far depth of one.
+    ///
+    /// # Panics
+    ///
+    /// new panics if samples isn't 1, 2, 4 or 8.
+    pub fn new(width: u32, height: u32, samples: u32) -> Self {
+        assert!([1, 2, 4, 8].contains(&samples), "unsupported sample count {}", samples);
+        let n = (width * height * samples) as usize;
+        Framebuffer {
+            width,
+            height,
+            samples,
+            color: vec![Vec4(0.0, 0.0, 0.0, 0.0); n],
+            depth: vec![1.0; n],
+        }
+    }
+
+    /// width returns the width of the framebuffer in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// height returns the height of the framebuffer in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// samples returns the number of samples per pixel.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// clear sets the color and depth of every sample.
+    pub fn clear(&mut self, color: Vec4<f32>, depth: f32) {
+        for c in &mut self.color {
+            *c = color;
+        }
+        for d in &mut self.depth {
+            *d = depth;
+        }
+    }
+
+    /// color returns the color of a sample of a pixel.
+    ///
+    /// # Panics
+    ///
+    /// color panics if the pixel or sample is out of range.
+    pub fn color(&self, x: u32, y: u32, sample: u32) -> Vec4<f32> {
+        self.color[self.index(x, y, sample)]
+    }
+
+    /// depth returns the depth of a sample of a pixel.
+    ///
+    /// # Panics
+    ///
+    /// depth panics if the pixel or sample is out of range.
+    pub fn depth(&self, x: u32, y: u32, sample: u32) -> f32 {
+        self.depth[self.index(x, y, sample)]
+    }
+
+    /// resolve returns the color of each pixel, in rows from the top, as the
+    /// average of the colors of its samples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec4;
+    /// use fiz_gfx::raster::Framebuffer;
+    ///
+    /// let mut fb = Framebuffer::new(2, 1, 4);
+    /// fb.clear(Vec4(1.0, 0.5, 0.0, 1.0), 1.0);
+    /// assert_eq!(fb.resolve(), vec![Vec4(1.0, 0.5, 0.0, 1.0); 2]);
+    /// ```
+    pub fn resolve(&self) -> Vec<Vec4<f32>> {
+        let scale = 1.0 / self.samples as f32;
+        self.color
+            .chunks(self.samples as usize)
+            .map(|samples| {
+                let sum = samples.iter().fold(Vec4(0.0, 0.0, 0.0, 0.0), |sum, &c| sum + c);
+                sum.mul_scalar(scale)
+            })
+            .collect()
+    }
+
+    /// to_image returns the resolved colors of the framebuffer as an image
+    /// of 32-bit floats, for example to save it to a file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec4;
+    /// use fiz_gfx::image::PixelFormat;
+    /// use fiz_gfx::raster::Framebuffer;
+    ///
+    /// let mut fb = Framebuffer::new(4, 2, 1);
+    /// fb.clear(Vec4(0.25, 0.5, 1.0, 1.0), 1.0);
+    /// let image = fb.to_image().convert(PixelFormat::Rgba8);
+    /// assert_eq!(image.row(1)[..4], [64, 128, 255, 255]);
+    /// ```
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height, PixelFormat::Rgba32F);
+        for (i, c) in self.resolve().into_iter().enumerate() {
+            image.set_pixel(i as u32 % self.width, i as u32 / self.width, c);
+        }
+        image
+    }
+
+    // sample_positions returns the positions of the samples within a pixel.
+    pub(crate) fn sample_positions(&self) -> &'static [(i64, i64)] {
+        match self.samples {
+            1 => &PATTERN_1,
+            2 => &PATTERN_2,
+            4 => &PATTERN_4,
+            _ => &PATTERN_8,
+        }
+    }
+
+    pub(crate) fn index(&self, x: u32, y: u32, sample: u32) -> usize {
+        assert!(x < self.width && y < self.height && sample < self.samples);
+        ((y * self.width + x) * self.samples + sample) as usize
+    }
+
+    pub(crate) fn color_data_mut(&mut self) -> &mut [Vec4<f32>] {
+        &mut self.color
+    }
+
+    pub(crate) fn depth_data_mut(&mut self) -> &mut Vec<f32> {
+        &mut self.depth
+    }
+
+    pub(crate) fn set_color(&mut self, i: usize, color: Vec4<f32>) {
+        self.color[i] = color;
+    }
+
+    pub(crate) fn depth_mut(&mut self, i: usize) -> &mut f32 {
+        &mut self.depth[i]
+    }
+}
@@ -0,0 +1,444 @@
+//! A headless software rasterizer.
+//!
+//! The rasterizer draws triangles given in clip space into an in-memory
+//! `Framebuffer`, entirely on the CPU. It exists to give tests a
+//! deterministic reference renderer, so its rules are exact rather than
+//! fast:
+//!
+//! - Triangles are clipped against the near plane (and a guard band far
+//!   outside of the viewport), then snapped to 1/256th of a pixel and
+//!   rasterized with integer edge functions using the top-left fill rule, so
+//!   triangles sharing an edge never both cover, or both miss, a sample.
+//! - Depth is interpolated linearly in screen space and tested per sample;
+//!   varyings are interpolated with perspective correction.
+//! - Fragments are shaded once per pixel, at its center, and written to the
+//!   samples they cover (multisampling), which `Framebuffer::resolve`
+//!   averages.
+//!
+//! Clip space follows the OpenGL conventions: a vertex is visible when each
+//! of `x`, `y` and `z` is between `-w` and `w`, depth `-w` maps to zero and
+//! `w` to one, and front faces are counter-clockwise.
+
+use math::{Vec3, Vec4};
+
+mod framebuffer;
+mod varyings;
+
+pub use self::framebuffer::Framebuffer;
+pub use self::varyings::Varyings;
+
+// The extent of the guard band, as a multiple of the viewport, beyond which
+// triangles are clipped. It keeps the fixed-point coordinates small enough
+// that edge functions can't overflow.
+const GUARD_BAND: f32 = 16.0;
+
+// The number of sub-pixel steps per pixel.
+const SUBPIXELS: f64 = 256.0;
+
+/// Cull selects the triangles which are discarded based on their facing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Cull {
+    /// No triangles are culled.
+    None,
+
+    /// Back-facing (clockwise) triangles are culled.
+    Back,
+
+    /// Front-facing (counter-clockwise) triangles are culled.
+    Front,
+}
+
+/// Fragment is the input of a fragment shader: a pixel covered by a
+/// triangle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment<V> {
+    /// The column of the pixel, from the left.
+    pub x: u32,
+
+    /// The row of the pixel, from the top.
+    pub y: u32,
+
+    /// The depth of the triangle at the center of the pixel.
+    pub depth: f32,
+
+    /// Whether the triangle is front-facing.
+    pub front_facing: bool,
+
+    /// The varyings of the triangle, interpolated at the center of the
+    /// pixel.
+    pub varyings: V,
+}
+
+/// Rasterizer holds the fixed-function state used to draw triangles.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rasterizer {
+    /// Which triangles are culled.
+    pub cull: Cull,
+
+    /// Whether samples are only drawn when nearer than the depth buffer.
+    pub depth_test: bool,
+
+    /// Whether drawn samples update the depth buffer.
+    pub depth_write: bool,
+}
+
+impl Default for Rasterizer {
+    fn default() -> Self {
+        Rasterizer::new()
+    }
+}
+
+// ScreenVertex is a vertex after clipping and the viewport transform.
+struct ScreenVertex<V> {
+    // The fixed-point position, in sub-pixels from the top-left corner.
+    x: i64,
+    y: i64,
+
+    depth: f32,
+    inv_w: f32,
+    varyings: V,
+}
+
+impl Rasterizer {
+    /// new returns a rasterizer which culls back faces and tests and writes
+    /// depth.
+    pub fn new() -> Self {
+        Rasterizer {
+            cull: Cull::Back,
+            depth_test: true,
+            depth_write: true,
+        }
+    }
+
+    /// draw draws triangles, given by the indices of their vertices, each of
+    /// which is a clip-space position and the varyings to interpolate.
+    ///
+    /// The shader is called for each pixel covered by a triangle and
+    /// returns its color, or None to discard it. Colors replace those in the
+    /// framebuffer.
+    ///
+    /// # Panics
+    ///
+    /// draw panics if a triangle refers to a vertex which doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::{Vec3, Vec4};
+    /// use fiz_gfx::raster::{Framebuffer, Rasterizer};
+    ///
+    /// let mut fb = Framebuffer::new(4, 4, 1);
+    /// let vertices = [(Vec4(-1.0, -1.0, 0.0, 1.0), Vec3(1.0, 0.0, 0.0)),
+    ///                 (Vec4(1.0, -1.0, 0.0, 1.0), Vec3(0.0, 1.0, 0.0)),
+    ///                 (Vec4(-1.0, 1.0, 0.0, 1.0), Vec3(0.0, 0.0, 1.0))];
+    /// Rasterizer::new().draw(&mut fb, &vertices, &[[0, 1, 2]], |f| {
+    ///     let c = f.varyings;
+    ///     Some(Vec4(c.0, c.1, c.2, 1.0))
+    /// });
+    ///
+    /// // The lower-left half of the framebuffer is covered. The centers of
+    /// // the pixels on the diagonal lie on the triangle's right edge, so the
+    /// // top-left rule leaves them uncovered.
+    /// let covered = fb.resolve().iter().filter(|c| c.3 == 1.0).count();
+    /// assert_eq!(covered, 6);
+    /// assert_eq!(fb.color(0, 3, 0), Vec4(0.75, 0.125, 0.125, 1.0));
+    ///
+    /// // Clockwise triangles are back faces, and culled.
+    /// let mut fb = Framebuffer::new(4, 4, 1);
+    /// Rasterizer::new().draw(&mut fb, &vertices, &[[0, 2, 1]], |_| Some(Vec4(1.0, 1.0, 1.0, 1.0)));
+    /// assert!(fb.resolve().iter().all(|c| c.3 == 0.0));
+    /// ```
+    pub fn draw<V, F>(&self, fb: &mut Framebuffer, vertices: &[(Vec4<f32>, V)], triangles: &[[u32; 3]], mut shader: F)
+        where V: Varyings,
+              F: FnMut(&Fragment<V>) -> Option<Vec4<f32>>
+    {
+        for t in triangles {
+            let polygon: Vec<(Vec4<f32>, V)> = t.iter().map(|&i| vertices[i as usize].clone()).collect();
+            let polygon = clip(polygon);
+            if polygon.len() < 3 {
+                continue;
+            }
+            let screen: Vec<ScreenVertex<V>> = polygon.into_iter().map(|v| to_screen(fb, v)).collect();
+            for k in 1..screen.len() - 1 {
+                self.triangle(fb, [&screen[0], &screen[k], &screen[k + 1]], &mut shader);
+            }
+        }
+    }
+
+    // triangle rasterizes a clipped triangle.
+    fn triangle<V, F>(&self, fb: &mut Framebuffer, v: [&ScreenVertex<V>; 3], shader: &mut F)
+        where V: Varyings,
+              F: FnMut(&Fragment<V>) -> Option<Vec4<f32>>
+    {
+        let area = edge(v[0], v[1], (v[2].x, v[2].y));
+        if area == 0 {
+            return;
+        }
+        // Counter-clockwise triangles in clip space are clockwise on screen,
+        // where Y points down.
+        let front_facing = area < 0;
+        match self.cull {
+            Cull::Back if !front_facing => return,
+            Cull::Front if front_facing => return,
+            _ => {}
+        }
+        let (v, area) = if area < 0 { ([v[0], v[2], v[1]], -area) } else { (v, area) };
+
+        // The edges, opposite to each vertex, and whether samples exactly on
+        // them are covered.
+        let edges = [(v[1], v[2]), (v[2], v[0]), (v[0], v[1])];
+        let inclusive = [is_top_left(v[1], v[2]), is_top_left(v[2], v[0]), is_top_left(v[0], v[1])];
+
+        let sub = SUBPIXELS as i64;
+        let min_x = v.iter().map(|p| p.x).min().unwrap().div_euclid(sub).max(0);
+        let min_y = v.iter().map(|p| p.y).min().unwrap().div_euclid(sub).max(0);
+        let max_x = v.iter().map(|p| p.x).max().unwrap().div_euclid(sub).min(fb.width() as i64 - 1);
+        let max_y = v.iter().map(|p| p.y).max().unwrap().div_euclid(sub).min(fb.height() as i64 - 1);
+        let positions = fb.sample_positions();
+        let area = area as f64;
+        let mut passed = Vec::with_capacity(positions.len());
+        for y in min_y..max_y + 1 {
+            for x in min_x..max_x + 1 {
+                passed.clear();
+                for (s, &(sx, sy)) in positions.iter().enumerate() {
+                    let p = (x * sub + sx, y * sub + sy);
+                    let e = [edge(edges[0].0, edges[0].1, p), edge(edges[1].0, edges[1].1, p),
+                             edge(edges[2].0, edges[2].1, p)];
+                    if (0..3).any(|k| e[k] < 0 || (e[k] == 0 && !inclusive[k])) {
+                        continue;
+                    }
+                    let depth = ((e[0] as f64 * v[0].depth as f64 + e[1] as f64 * v[1].depth as f64 +
+                                  e[2] as f64 * v[2].depth as f64) / area) as f32;
+                    if !(0.0..=1.0).contains(&depth) {
+                        continue;
+                    }
+                    let i = fb.index(x as u32, y as u32, s as u32);
+                    if self.depth_test && depth >= *fb.depth_mut(i) {
+                        continue;
+                    }
+                    passed.push((i, depth));
+                }
+                if passed.is_empty() {
+                    continue;
+                }
+
+                // Shade the pixel at its center.
+                let p = (x * sub + sub / 2, y * sub + sub / 2);
+                let l = [edge(edges[0].0, edges[0].1, p) as f64 / area,
+                         edge(edges[1].0, edges[1].1, p) as f64 / area,
+                         edge(edges[2].0, edges[2].1, p) as f64 / area];
+                let depth = (l[0] * v[0].depth as f64 + l[1] * v[1].depth as f64 + l[2] * v[2].depth as f64) as f32;
+                let b = Vec3(l[0] * v[0].inv_w as f64, l[1] * v[1].inv_w as f64, l[2] * v[2].inv_w as f64);
+                let sum = b.0 + b.1 + b.2;
+                let weights = [(b.0 / sum) as f32, (b.1 / sum) as f32, (b.2 / sum) as f32];
+                let fragment = Fragment {
+                    x: x as u32,
+                    y: y as u32,
+                    depth,
+                    front_facing,
+                    varyings: V::weighted([&v[0].varyings, &v[1].varyings, &v[2].varyings], weights),
+                };
+                let color = match shader(&fragment) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                for &(i, depth) in &passed {
+                    fb.set_color(i, color);
+                    if self.depth_write {
+                        *fb.depth_mut(i) = depth;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// edge returns twice the signed area of the triangle (a, b, p), which is
+// positive when p is clockwise from a to b on screen.
+fn edge<V>(a: &ScreenVertex<V>, b: &ScreenVertex<V>, p: (i64, i64)) -> i64 {
+    (b.x - a.x) * (p.1 - a.y) - (b.y - a.y) * (p.0 - a.x)
+}
+
+// is_top_left tells if the edge from a to b of a clockwise triangle is a top
+// edge (horizontal, with the triangle below it) or a left edge.
+fn is_top_left<V>(a: &ScreenVertex<V>, b: &ScreenVertex<V>) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    (dy == 0 && dx > 0) || dy < 0
+}
+
+// clip clips a convex polygon against the near plane and the guard band.
+fn clip<V: Varyings>(mut polygon: Vec<(Vec4<f32>, V)>) -> Vec<(Vec4<f32>, V)> {
+    let planes: [fn(Vec4<f32>) -> f32; 5] = [|p| p.2 + p.3,
+                                             |p| GUARD_BAND * p.3 + p.0,
+                                             |p| GUARD_BAND * p.3 - p.0,
+                                             |p| GUARD_BAND * p.3 + p.1,
+                                             |p| GUARD_BAND * p.3 - p.1];
+    for distance in &planes {
+        if polygon.iter().all(|v| distance(v.0) >= 0.0) {
+            continue;
+        }
+        let mut out = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let (a, b) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+            let (da, db) = (distance(a.0), distance(b.0));
+            if da >= 0.0 {
+                out.push(a.clone());
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                // Interpolate from the inside vertex, so that both triangles
+                // sharing an edge find the same point on it.
+                let (inside, outside, t) = if da >= 0.0 { (a, b, da / (da - db)) } else { (b, a, db / (db - da)) };
+                let position = inside.0 + (outside.0 - inside.0).mul_scalar(t);
+                let varyings = V::weighted([&inside.1, &outside.1, &outside.1], [1.0 - t, t, 0.0]);
+                out.push((position, varyings));
+            }
+        }
+        polygon = out;
+        if polygon.len() < 3 {
+            break;
+        }
+    }
+    polygon
+}
+
+// to_screen applies the perspective divide and viewport transform to a
+// clipped vertex.
+fn to_screen<V>(fb: &Framebuffer, v: (Vec4<f32>, V)) -> ScreenVertex<V> {
+    let (p, varyings) = v;
+    let inv_w = 1.0 / p.3 as f64;
+    let x = (p.0 as f64 * inv_w + 1.0) * 0.5 * fb.width() as f64;
+    let y = (1.0 - p.1 as f64 * inv_w) * 0.5 * fb.height() as f64;
+    ScreenVertex {
+        x: (x * SUBPIXELS).round() as i64,
+        y: (y * SUBPIXELS).round() as i64,
+        depth: ((p.2 as f64 * inv_w + 1.0) * 0.5) as f32,
+        inv_w: inv_w as f32,
+        varyings,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec3, Vec4};
+    use super::{Framebuffer, Rasterizer, Cull};
+
+    const WHITE: Vec4<f32> = Vec4(1.0, 1.0, 1.0, 1.0);
+
+    // clip returns the clip-space position of a point on the near plane
+    // given in pixels.
+    fn clip(fb: &Framebuffer, x: f32, y: f32) -> Vec4<f32> {
+        Vec4(2.0 * x / fb.width() as f32 - 1.0, 1.0 - 2.0 * y / fb.height() as f32, 0.0, 1.0)
+    }
+
+    // project returns the clip-space position of a point in view space,
+    // with a 90 degree field of view and near and far planes at 1 and 100.
+    fn project(p: Vec3<f32>) -> Vec4<f32> {
+        let (n, f) = (1.0, 100.0);
+        Vec4(p.0, p.1, (p.2 * (f + n) + 2.0 * f * n) / (n - f), -p.2)
+    }
+
+    #[test]
+    fn shared_edges() {
+        // A grid of quads covering the framebuffer, whose inner vertices lie
+        // on pixel centers so that many samples are exactly on edges.
+        let mut fb = Framebuffer::new(8, 8, 1);
+        let mut vertices = Vec::new();
+        for j in 0..5 {
+            for i in 0..5 {
+                let coord = |k: i32| if k == 0 || k == 4 { k as f32 * 2.0 } else { k as f32 * 2.0 + 0.5 };
+                vertices.push((clip(&fb, coord(i), coord(j)), ()));
+            }
+        }
+        let mut triangles = Vec::new();
+        for j in 0..4 {
+            for i in 0..4 {
+                let v = j * 5 + i;
+                triangles.push([v, v + 1, v + 6]);
+                triangles.push([v, v + 6, v + 5]);
+            }
+        }
+        let r = Rasterizer {
+            cull: Cull::None,
+            depth_test: false,
+            depth_write: false,
+        };
+        let mut counts = vec![0; 64];
+        r.draw(&mut fb, &vertices, &triangles, |f| {
+            counts[(f.y * 8 + f.x) as usize] += 1;
+            None
+        });
+        assert_eq!(counts, vec![1; 64]);
+    }
+
+    #[test]
+    fn perspective_correct() {
+        // A floor receding into the distance. The interpolated view-space
+        // position must project onto the center of each pixel.
+        let mut fb = Framebuffer::new(16, 16, 1);
+        let floor = [Vec3(-4.0, -1.0, -1.5), Vec3(4.0, -1.0, -1.5), Vec3(4.0, -1.0, -20.0), Vec3(-4.0, -1.0, -20.0)];
+        let vertices: Vec<_> = floor.iter().map(|&p| (project(p), p)).collect();
+        let mut fragments = 0;
+        Rasterizer::new().draw(&mut fb, &vertices, &[[0, 1, 2], [0, 2, 3]], |f| {
+            let p = f.varyings;
+            let center = ((f.x as f32 + 0.5) / 8.0 - 1.0, 1.0 - (f.y as f32 + 0.5) / 8.0);
+            assert!((p.0 / -p.2 - center.0).abs() < 1e-3 && (p.1 / -p.2 - center.1).abs() < 1e-3);
+            assert!((p.1 + 1.0).abs() < 1e-5);
+            fragments += 1;
+            Some(WHITE)
+        });
+        assert!(fragments > 0);
+    }
+
+    #[test]
+    fn near_clipping() {
+        // A triangle extending behind the camera.
+        let mut fb = Framebuffer::new(16, 16, 1);
+        let points = [Vec3(1.0, -1.0, -3.0), Vec3(-1.0, -1.0, -3.0), Vec3(0.0, -1.0, 2.0)];
+        let vertices: Vec<_> = points.iter().map(|&p| (project(p), p.2)).collect();
+        let mut fragments = 0;
+        Rasterizer::new().draw(&mut fb, &vertices, &[[0, 1, 2]], |f| {
+            assert!(f.varyings <= -1.0 + 1e-3 && f.depth >= 0.0);
+            fragments += 1;
+            Some(WHITE)
+        });
+        // The clipped triangle reaches the bottom of the screen.
+        assert!(fragments > 0);
+        assert_eq!(fb.color(8, 15, 0), WHITE);
+    }
+
+    #[test]
+    fn depth() {
+        let mut fb = Framebuffer::new(4, 4, 1);
+        let quad = |z: f32, color: Vec4<f32>| {
+            vec![(Vec4(-1.0, -1.0, z, 1.0), color),
+                 (Vec4(1.0, -1.0, z, 1.0), color),
+                 (Vec4(1.0, 1.0, z, 1.0), color),
+                 (Vec4(-1.0, 1.0, z, 1.0), color)]
+        };
+        let red = Vec4(1.0, 0.0, 0.0, 1.0);
+        let r = Rasterizer::new();
+        r.draw(&mut fb, &quad(-0.5, red), &[[0, 1, 2], [0, 2, 3]], |f| Some(f.varyings));
+        r.draw(&mut fb, &quad(0.5, WHITE), &[[0, 1, 2], [0, 2, 3]], |f| Some(f.varyings));
+        assert_eq!(fb.resolve(), vec![red; 16]);
+        assert_eq!(fb.depth(2, 2, 0), 0.25);
+    }
+
+    #[test]
+    fn multisampling() {
+        // A rectangle covering the left half of the middle pixel.
+        let mut fb = Framebuffer::new(3, 1, 4);
+        let vertices = [(clip(&fb, 0.0, 0.0), ()), (clip(&fb, 0.0, 1.0), ()), (clip(&fb, 1.5, 1.0), ()),
+                        (clip(&fb, 1.5, 0.0), ())];
+        let mut shaded = 0;
+        Rasterizer::new().draw(&mut fb, &vertices, &[[0, 1, 2], [0, 2, 3]], |_| {
+            shaded += 1;
+            Some(WHITE)
+        });
+        let resolved = fb.resolve();
+        assert_eq!(resolved, vec![WHITE, WHITE.mul_scalar(0.5), Vec4(0.0, 0.0, 0.0, 0.0)]);
+
+        // Pixels are shaded once for each triangle covering them, rather than
+        // for each sample.
+        assert_eq!(shaded, 3);
+    }
+}
@@ -0,0 +1,62 @@
+use math::{Vec2, Vec3, Vec4};
+
+/// Varyings are the per-vertex values which are interpolated across a
+/// triangle and given to the fragment shader, such as colors, normals and
+/// texture coordinates.
+///
+/// Varyings is implemented for `f32`, the vector types, vectors of floats and
+/// tuples of up to four varyings.
+pub trait Varyings: Clone {
+    /// weighted returns the sum of the values, each multiplied by its
+    /// weight.
+    fn weighted(values: [&Self; 3], weights: [f32; 3]) -> Self;
+}
+
+impl Varyings for () {
+    fn weighted(_: [&Self; 3], _: [f32; 3]) -> Self {}
+}
+
+impl Varyings for f32 {
+    fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+        v[0] * w[0] + v[1] * w[1] + v[2] * w[2]
+    }
+}
+
+impl Varyings for Vec2<f32> {
+    fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+        v[0].mul_scalar(w[0]) + v[1].mul_scalar(w[1]) + v[2].mul_scalar(w[2])
+    }
+}
+
+impl Varyings for Vec3<f32> {
+    fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+        v[0].mul_scalar(w[0]) + v[1].mul_scalar(w[1]) + v[2].mul_scalar(w[2])
+    }
+}
+
+impl Varyings for Vec4<f32> {
+    fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+        v[0].mul_scalar(w[0]) + v[1].mul_scalar(w[1]) + v[2].mul_scalar(w[2])
+    }
+}
+
+impl Varyings for Vec<f32> {
+    fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+        (0..v[0].len()).map(|i| v[0][i] * w[0] + v[1][i] * w[1] + v[2][i] * w[2]).collect()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: Varyings),*> Varyings for ($($name,)*) {
+            fn weighted(v: [&Self; 3], w: [f32; 3]) -> Self {
+                ($($name::weighted([&v[0].$index, &v[1].$index, &v[2].$index], w),)*)
+            }
+        }
+    };
+}
+
+impl_tuple!(A: 0);
+impl_tuple!(A: 0, B: 1);
+impl_tuple!(A: 0, B: 1, C: 2);
+impl_tuple!(A: 0, B: 1, C: 2, D: 3);
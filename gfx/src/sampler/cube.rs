@@ -0,0 +1,158 @@
+use math::{Vec2, Vec3, Vec4};
+use super::{Mipmaps, Sampler, Wrap};
+
+/// CubeFace is a face of a cube map, named by the axis it faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    /// The face towards +X.
+    PositiveX,
+
+    /// The face towards -X.
+    NegativeX,
+
+    /// The face towards +Y.
+    PositiveY,
+
+    /// The face towards -Y.
+    NegativeY,
+
+    /// The face towards +Z.
+    PositiveZ,
+
+    /// The face towards -Z.
+    NegativeZ,
+}
+
+impl CubeFace {
+    /// ALL lists the faces in the order cube maps store them.
+    pub const ALL: [CubeFace; 6] = [CubeFace::PositiveX,
+                                    CubeFace::NegativeX,
+                                    CubeFace::PositiveY,
+                                    CubeFace::NegativeY,
+                                    CubeFace::PositiveZ,
+                                    CubeFace::NegativeZ];
+
+    /// project returns the face a direction points at, and the texture
+    /// coordinates of the direction on that face, following the usual
+    /// (OpenGL and Direct3D) cube map conventions. It returns None for the
+    /// zero vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::{Vec2, Vec3};
+    /// use fiz_gfx::sampler::CubeFace;
+    ///
+    /// assert_eq!(CubeFace::project(Vec3(2.0, 0.0, -1.0)), Some((CubeFace::PositiveX, Vec2(0.75, 0.5))));
+    /// assert_eq!(CubeFace::project(Vec3(0.0, 0.0, 0.0)), None);
+    /// ```
+    pub fn project(dir: Vec3<f32>) -> Option<(CubeFace, Vec2<f32>)> {
+        let (ax, ay, az) = (dir.0.abs(), dir.1.abs(), dir.2.abs());
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            if dir.0 >= 0.0 {
+                (CubeFace::PositiveX, -dir.2, -dir.1, ax)
+            } else {
+                (CubeFace::NegativeX, dir.2, -dir.1, ax)
+            }
+        } else if ay >= az {
+            if dir.1 >= 0.0 {
+                (CubeFace::PositiveY, dir.0, dir.2, ay)
+            } else {
+                (CubeFace::NegativeY, dir.0, -dir.2, ay)
+            }
+        } else if dir.2 >= 0.0 {
+            (CubeFace::PositiveZ, dir.0, -dir.1, az)
+        } else {
+            (CubeFace::NegativeZ, -dir.0, -dir.1, az)
+        };
+        if ma == 0.0 || ma.is_nan() {
+            return None;
+        }
+        Some((face, Vec2(0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0))))
+    }
+}
+
+/// CubeMap is a texture of six square faces, sampled by direction, such as
+/// an environment map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeMap {
+    faces: [Mipmaps; 6],
+}
+
+impl CubeMap {
+    /// new returns a cube map of the faces, in the order of `CubeFace::ALL`.
+    /// It returns None unless the faces are square, and of the same size and
+    /// number of levels.
+    pub fn new(faces: [Mipmaps; 6]) -> Option<Self> {
+        let first = &faces[0];
+        let same = |f: &Mipmaps| {
+            f.width() == first.width() && f.height() == first.height() && f.levels().len() == first.levels().len()
+        };
+        if first.width() != first.height() || !faces.iter().all(same) {
+            return None;
+        }
+        Some(CubeMap { faces })
+    }
+
+    /// face returns the texture of a face.
+    pub fn face(&self, face: CubeFace) -> &Mipmaps {
+        &self.faces[face as usize]
+    }
+
+    /// sample samples the cube map in the given direction, at the given
+    /// level of detail, returning transparent black for the zero vector.
+    /// Faces are clamped to their edges, so seams aren't filtered across
+    /// faces.
+    pub fn sample(&self, sampler: &Sampler, dir: Vec3<f32>, lod: f32) -> Vec4<f32> {
+        let (face, uv) = match CubeFace::project(dir) {
+            Some(p) => p,
+            None => return Vec4(0.0, 0.0, 0.0, 0.0),
+        };
+        let sampler = Sampler {
+            wrap_u: Wrap::Clamp,
+            wrap_v: Wrap::Clamp,
+            ..*sampler
+        };
+        sampler.sample_lod(self.face(face), uv, lod)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec3, Vec4};
+    use super::super::super::image::{Image, PixelFormat};
+    use super::super::{ColorSpace, Mipmaps, Sampler};
+    use super::{CubeFace, CubeMap};
+
+    #[test]
+    fn faces() {
+        // Each face is a solid color identifying it.
+        let faces: Vec<Mipmaps> = (0..6)
+            .map(|i| {
+                let mut image = Image::new(2, 2, PixelFormat::Rgba32F);
+                for y in 0..2 {
+                    for x in 0..2 {
+                        image.set_pixel(x, y, Vec4(i as f32, 0.0, 0.0, 1.0));
+                    }
+                }
+                Mipmaps::new(&image, ColorSpace::Linear)
+            })
+            .collect();
+        let faces = [faces[0].clone(), faces[1].clone(), faces[2].clone(), faces[3].clone(), faces[4].clone(),
+                     faces[5].clone()];
+        let cube = CubeMap::new(faces).unwrap();
+        let s = Sampler::new();
+        let dirs = [Vec3(1.0, 0.2, 0.3), Vec3(-1.0, 0.9, 0.0), Vec3(0.1, 2.0, -1.0), Vec3(0.0, -1.0, 0.0),
+                    Vec3(0.5, -0.5, 0.6), Vec3(0.0, 0.0, -1.0)];
+        for (i, &dir) in dirs.iter().enumerate() {
+            assert_eq!(CubeFace::project(dir).unwrap().0, CubeFace::ALL[i]);
+            assert_eq!(cube.sample(&s, dir, 0.0).0, i as f32);
+        }
+
+        // The corners of the faces meet at the corners of the cube.
+        let (_, uv) = CubeFace::project(Vec3(1.0, 1.0, 0.999)).unwrap();
+        assert!(uv.0 < 0.001 && uv.1 < 0.001);
+        let (_, uv) = CubeFace::project(Vec3(-0.999, -1.0, -1.0)).unwrap();
+        assert!(uv.0 < 0.001 && uv.1 > 0.999);
+    }
+}
@@ -0,0 +1,231 @@
+use std::f32::consts::PI;
+use math::Vec4;
+use super::super::image::{srgb_to_linear, Image, PixelFormat};
+
+/// MipFilter is the filter used to downsample mipmap levels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MipFilter {
+    /// Each texel is the average of the texels it covers. This is fast and
+    /// never rings, but blurs more than necessary.
+    Box,
+
+    /// A Kaiser-windowed sinc filter, which keeps levels sharper, at the
+    /// cost of some ringing near sharp edges.
+    Kaiser,
+}
+
+/// ColorSpace is the color space of the texels of an image.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Texels are linear.
+    Linear,
+
+    /// The color (but not alpha) of texels is sRGB encoded.
+    Srgb,
+}
+
+/// Mipmaps is a texture as a chain of levels, each half the size of the
+/// previous one (rounded down), down to one texel. Levels hold linear
+/// 32-bit floating point texels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mipmaps {
+    levels: Vec<Image>,
+}
+
+impl Mipmaps {
+    /// new returns a texture of only the image, without mipmaps.
+    pub fn new(image: &Image, color_space: ColorSpace) -> Self {
+        Mipmaps { levels: vec![linearize(image, color_space)] }
+    }
+
+    /// generate returns the image with its full mipmap chain. Images in the
+    /// sRGB color space are filtered in linear space, so that levels keep
+    /// their brightness.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::Vec4;
+    /// use fiz_gfx::image::{linear_to_srgb, Image, PixelFormat};
+    /// use fiz_gfx::sampler::{ColorSpace, MipFilter, Mipmaps};
+    ///
+    /// let mut image = Image::new(2, 1, PixelFormat::Rgba8);
+    /// image.set_pixel(1, 0, Vec4(1.0, 1.0, 1.0, 1.0));
+    /// let mips = Mipmaps::generate(&image, MipFilter::Box, ColorSpace::Srgb);
+    ///
+    /// // Half of the light of a white texel is brighter than sRGB 0.5.
+    /// let grey = mips.levels()[1].pixel(0, 0);
+    /// assert_eq!(grey.0, 0.5);
+    /// assert!((linear_to_srgb(grey.0) - 0.735).abs() < 0.001);
+    /// ```
+    pub fn generate(image: &Image, filter: MipFilter, color_space: ColorSpace) -> Self {
+        let mut levels = vec![linearize(image, color_space)];
+        loop {
+            let last = levels.last().unwrap();
+            let (w, h) = (last.width(), last.height());
+            if w <= 1 && h <= 1 {
+                break;
+            }
+            let next = resample(last, (w / 2).max(1), (h / 2).max(1), filter);
+            levels.push(next);
+        }
+        Mipmaps { levels }
+    }
+
+    /// from_levels returns a texture of the given levels, converted to
+    /// linear floating point, or None if their sizes don't form a mipmap
+    /// chain (which may be incomplete).
+    pub fn from_levels(levels: &[Image], color_space: ColorSpace) -> Option<Self> {
+        let first = levels.first()?;
+        for (i, l) in levels.iter().enumerate() {
+            let size = |n: u32| (n >> i).max(1);
+            if l.width() != size(first.width()) || l.height() != size(first.height()) {
+                return None;
+            }
+        }
+        Some(Mipmaps { levels: levels.iter().map(|l| linearize(l, color_space)).collect() })
+    }
+
+    /// levels returns the levels, from the largest.
+    pub fn levels(&self) -> &[Image] {
+        &self.levels
+    }
+
+    /// width returns the width of the first level.
+    pub fn width(&self) -> u32 {
+        self.levels[0].width()
+    }
+
+    /// height returns the height of the first level.
+    pub fn height(&self) -> u32 {
+        self.levels[0].height()
+    }
+}
+
+// linearize returns the image as linear floats.
+fn linearize(image: &Image, color_space: ColorSpace) -> Image {
+    let mut out = image.convert(PixelFormat::Rgba32F);
+    if color_space == ColorSpace::Srgb {
+        for y in 0..out.height() {
+            for x in 0..out.width() {
+                let c = out.pixel(x, y);
+                out.set_pixel(x, y, Vec4(srgb_to_linear(c.0), srgb_to_linear(c.1), srgb_to_linear(c.2), c.3));
+            }
+        }
+    }
+    out
+}
+
+fn bessel_i0(x: f32) -> f32 {
+    let (mut sum, mut term, mut k) = (1.0, 1.0, 1.0);
+    while term > sum * 1e-8 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+// The half-width of the Kaiser filter in destination texels, and the shape
+// of its window.
+const KAISER_RADIUS: f32 = 1.5;
+const KAISER_ALPHA: f32 = 4.0;
+
+fn kaiser(t: f32) -> f32 {
+    let x = t / KAISER_RADIUS;
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+    sinc * bessel_i0(KAISER_ALPHA * (1.0 - x * x).sqrt()) / bessel_i0(KAISER_ALPHA)
+}
+
+// weights returns the source texels and weights of each destination texel
+// when resampling n texels to m.
+fn weights(n: u32, m: u32, filter: MipFilter) -> Vec<Vec<(u32, f32)>> {
+    let ratio = n as f32 / m as f32;
+    (0..m)
+        .map(|x| {
+            let mut w: Vec<(u32, f32)> = match filter {
+                MipFilter::Box => {
+                    // The coverage of each source texel.
+                    let (a, b) = (x as f32 * ratio, (x + 1) as f32 * ratio);
+                    (a.floor() as u32..(b.ceil() as u32).min(n))
+                        .map(|i| (i, (b.min(i as f32 + 1.0) - a.max(i as f32)).max(0.0)))
+                        .collect()
+                }
+                MipFilter::Kaiser => {
+                    let center = (x as f32 + 0.5) * ratio;
+                    let radius = KAISER_RADIUS * ratio;
+                    let first = (center - radius).floor() as i64;
+                    let last = (center + radius).ceil() as i64;
+                    (first..=last)
+                        .map(|i| {
+                            let t = (i as f32 + 0.5 - center) / ratio;
+                            (i.clamp(0, n as i64 - 1) as u32, kaiser(t))
+                        })
+                        .collect()
+                }
+            };
+            let sum: f32 = w.iter().map(|&(_, w)| w).sum();
+            for e in &mut w {
+                e.1 /= sum;
+            }
+            w
+        })
+        .collect()
+}
+
+// resample returns the image resized with the filter, separably.
+fn resample(image: &Image, width: u32, height: u32, filter: MipFilter) -> Image {
+    let (wx, wy) = (weights(image.width(), width, filter), weights(image.height(), height, filter));
+    let zero = Vec4(0.0, 0.0, 0.0, 0.0);
+    let mut rows = Image::new(width, image.height(), PixelFormat::Rgba32F);
+    for y in 0..image.height() {
+        for (x, w) in wx.iter().enumerate() {
+            let c = w.iter().fold(zero, |c, &(i, w)| c + image.pixel(i, y).mul_scalar(w));
+            rows.set_pixel(x as u32, y, c);
+        }
+    }
+    let mut out = Image::new(width, height, PixelFormat::Rgba32F);
+    for (y, w) in wy.iter().enumerate() {
+        for x in 0..width {
+            let c = w.iter().fold(zero, |c, &(i, w)| c + rows.pixel(x, i).mul_scalar(w));
+            // Negative lobes may ring below zero.
+            out.set_pixel(x, y as u32, Vec4(c.0.max(0.0), c.1.max(0.0), c.2.max(0.0), c.3.clamp(0.0, 1.0)));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec4;
+    use super::super::super::image::{Image, PixelFormat};
+    use super::{ColorSpace, MipFilter, Mipmaps};
+
+    #[test]
+    fn kaiser() {
+        // A 7x5 image, with odd sizes, of a bright dot in a constant field.
+        let mut image = Image::new(7, 5, PixelFormat::Rgba32F);
+        for y in 0..5 {
+            for x in 0..7 {
+                image.set_pixel(x, y, Vec4(0.25, 0.25, 0.25, 1.0));
+            }
+        }
+        image.set_pixel(3, 2, Vec4(4.0, 4.0, 4.0, 1.0));
+        for &filter in &[MipFilter::Box, MipFilter::Kaiser] {
+            let mips = Mipmaps::generate(&image, filter, ColorSpace::Linear);
+            let sizes: Vec<_> = mips.levels().iter().map(|l| (l.width(), l.height())).collect();
+            assert_eq!(sizes, vec![(7, 5), (3, 2), (1, 1)]);
+            // Filters keep constant regions constant, and the light of the
+            // dot.
+            assert!(mips.levels()[1].pixel(0, 0).almost_equal(Vec4(0.25, 0.25, 0.25, 1.0), 0.05));
+            let mean = mips.levels()[2].pixel(0, 0);
+            assert!((mean.0 - (0.25 + 3.75 / 35.0)).abs() < 0.05, "{:?} {:?}", filter, mean);
+        }
+        assert!(Mipmaps::from_levels(&[image.clone(), Image::new(3, 2, PixelFormat::R8)], ColorSpace::Linear)
+            .is_some());
+        assert!(Mipmaps::from_levels(&[image, Image::new(4, 2, PixelFormat::R8)], ColorSpace::Linear).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+//! Texture sampling on the CPU.
+//!
+//! A `Sampler` reads filtered texels from the levels of a `Mipmaps` chain
+//! or a `CubeMap`, much like a GPU does, for software rendering and texture
+//! baking tools.
+//!
+//! Texture coordinates have their origin at the top-left corner of the
+//! texture, with texel centers at half-texel offsets, and sampled values are
+//! linear RGBA.
+//!
+//! ```
+//! use fiz_gfx::math::{Vec2, Vec4};
+//! use fiz_gfx::image::{Image, PixelFormat};
+//! use fiz_gfx::sampler::{ColorSpace, MipFilter, Mipmaps, Sampler};
+//!
+//! let mut image = Image::new(8, 8, PixelFormat::Rgba8);
+//! for y in 0..8 {
+//!     for x in 0..8 {
+//!         let c = ((x + y) % 2) as f32;
+//!         image.set_pixel(x, y, Vec4(c, c, c, 1.0));
+//!     }
+//! }
+//! let mips = Mipmaps::generate(&image, MipFilter::Box, ColorSpace::Srgb);
+//! assert_eq!(mips.levels().len(), 4);
+//!
+//! // Far away, the checkerboard is an even (linear) grey.
+//! let grey = Sampler::new().sample_lod(&mips, Vec2(0.3, 0.6), 3.0);
+//! assert!(grey.almost_equal(Vec4(0.5, 0.5, 0.5, 1.0), 1e-6));
+//! ```
+
+mod mipmaps;
+mod cube;
+
+pub use self::mipmaps::{ColorSpace, MipFilter, Mipmaps};
+pub use self::cube::{CubeFace, CubeMap};
+
+use math::{Vec2, Vec4};
+use super::image::Image;
+
+/// Filter is how texels are filtered within a mipmap level.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// The nearest texel is used.
+    Nearest,
+
+    /// The four nearest texels are blended (bilinear filtering).
+    Linear,
+}
+
+/// MipmapMode is how mipmap levels are chosen when minifying.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MipmapMode {
+    /// Only the first level is used.
+    None,
+
+    /// The nearest level is used.
+    Nearest,
+
+    /// The two nearest levels are blended (trilinear filtering, with linear
+    /// filters).
+    Linear,
+}
+
+/// Wrap is how texture coordinates outside a texture are handled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    /// The texture repeats.
+    Repeat,
+
+    /// The texture repeats, mirrored every other time.
+    Mirror,
+
+    /// The edge texels extend outwards.
+    Clamp,
+
+    /// Texels outside the texture are the border color.
+    Border,
+}
+
+/// Sampler holds the state used to sample textures.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sampler {
+    /// The filter used when magnifying, at a level of detail of zero or
+    /// less.
+    pub mag_filter: Filter,
+
+    /// The filter used within levels when minifying.
+    pub min_filter: Filter,
+
+    /// How levels are chosen when minifying.
+    pub mipmap_mode: MipmapMode,
+
+    /// How horizontal texture coordinates are wrapped.
+    pub wrap_u: Wrap,
+
+    /// How vertical texture coordinates are wrapped.
+    pub wrap_v: Wrap,
+
+    /// The color of texels outside the texture, with `Wrap::Border`.
+    pub border: Vec4<f32>,
+
+    /// The greatest number of samples taken along the major axis of an
+    /// anisotropic footprint, or one to disable anisotropic filtering.
+    pub max_anisotropy: u32,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler::new()
+    }
+}
+
+impl Sampler {
+    /// new returns a trilinear sampler which repeats textures, without
+    /// anisotropic filtering.
+    pub fn new() -> Self {
+        Sampler {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_mode: MipmapMode::Linear,
+            wrap_u: Wrap::Repeat,
+            wrap_v: Wrap::Repeat,
+            border: Vec4(0.0, 0.0, 0.0, 0.0),
+            max_anisotropy: 1,
+        }
+    }
+
+    /// sample samples the first level of the texture.
+    pub fn sample(&self, mips: &Mipmaps, uv: Vec2<f32>) -> Vec4<f32> {
+        self.sample_lod(mips, uv, 0.0)
+    }
+
+    /// sample_lod samples the texture at the given level of detail, the
+    /// base-two logarithm of the number of texels per pixel.
+    pub fn sample_lod(&self, mips: &Mipmaps, uv: Vec2<f32>, lod: f32) -> Vec4<f32> {
+        let levels = mips.levels();
+        if lod <= 0.0 || lod.is_nan() {
+            return self.filtered(&levels[0], uv, self.mag_filter);
+        }
+        let last = levels.len() - 1;
+        let lod = lod.min(last as f32);
+        match self.mipmap_mode {
+            MipmapMode::None => self.filtered(&levels[0], uv, self.min_filter),
+            MipmapMode::Nearest => self.filtered(&levels[(lod + 0.5) as usize], uv, self.min_filter),
+            MipmapMode::Linear => {
+                let l = lod.floor() as usize;
+                let a = self.filtered(&levels[l], uv, self.min_filter);
+                if l == last {
+                    return a;
+                }
+                lerp(a, self.filtered(&levels[l + 1], uv, self.min_filter), lod - l as f32)
+            }
+        }
+    }
+
+    /// sample_grad samples the texture given the derivatives of the texture
+    /// coordinates across a pixel horizontally and vertically, which choose
+    /// the level of detail. With anisotropic filtering, several samples are
+    /// taken along the longer axis of the pixel's footprint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::{Vec2, Vec4};
+    /// use fiz_gfx::image::{Image, PixelFormat};
+    /// use fiz_gfx::sampler::{ColorSpace, Filter, Mipmaps, MipFilter, Sampler};
+    ///
+    /// // Vertical black and white stripes.
+    /// let mut image = Image::new(16, 16, PixelFormat::Rgba8);
+    /// for y in 0..16 {
+    ///     for x in 0..16 {
+    ///         let c = (x % 2) as f32;
+    ///         image.set_pixel(x, y, Vec4(c, c, c, 1.0));
+    ///     }
+    /// }
+    /// let mips = Mipmaps::generate(&image, MipFilter::Box, ColorSpace::Linear);
+    ///
+    /// // A pixel spanning eight texels across, and one down, sees grey.
+    /// let mut sampler = Sampler::new();
+    /// sampler.max_anisotropy = 8;
+    /// let (ddx, ddy) = (Vec2(0.5, 0.0), Vec2(0.0, 1.0 / 16.0));
+    /// let grey = sampler.sample_grad(&mips, Vec2(0.5, 0.5), ddx, ddy);
+    /// assert!((grey.0 - 0.5).abs() < 0.01);
+    /// ```
+    pub fn sample_grad(&self, mips: &Mipmaps, uv: Vec2<f32>, ddx: Vec2<f32>, ddy: Vec2<f32>) -> Vec4<f32> {
+        let (w, h) = (mips.width() as f32, mips.height() as f32);
+        let lx = Vec2(ddx.0 * w, ddx.1 * h).length();
+        let ly = Vec2(ddy.0 * w, ddy.1 * h).length();
+        let (major, minor, axis) = if lx >= ly { (lx, ly, ddx) } else { (ly, lx, ddy) };
+        let n = if self.max_anisotropy > 1 && minor > 0.0 {
+            ((major / minor).ceil() as u32).clamp(1, self.max_anisotropy)
+        } else {
+            1
+        };
+        let lod = (major / n as f32).log2();
+        if n == 1 {
+            return self.sample_lod(mips, uv, lod);
+        }
+        // Average samples spread evenly along the major axis.
+        let sum = (0..n).fold(Vec4(0.0, 0.0, 0.0, 0.0), |sum, i| {
+            let offset = axis.mul_scalar((i as f32 + 0.5) / n as f32 - 0.5);
+            sum + self.sample_lod(mips, uv + offset, lod)
+        });
+        sum.mul_scalar(1.0 / n as f32)
+    }
+
+    // texel returns the texel of a level at the given (possibly outside)
+    // coordinates.
+    fn texel(&self, level: &Image, x: i64, y: i64) -> Vec4<f32> {
+        match (wrap(x, level.width() as i64, self.wrap_u), wrap(y, level.height() as i64, self.wrap_v)) {
+            (Some(x), Some(y)) => level.pixel(x as u32, y as u32),
+            _ => self.border,
+        }
+    }
+
+    // filtered samples a level with the given filter.
+    fn filtered(&self, level: &Image, uv: Vec2<f32>, filter: Filter) -> Vec4<f32> {
+        let (x, y) = (uv.0 * level.width() as f32, uv.1 * level.height() as f32);
+        match filter {
+            Filter::Nearest => self.texel(level, x.floor() as i64, y.floor() as i64),
+            Filter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = lerp(self.texel(level, x0, y0), self.texel(level, x0 + 1, y0), fx);
+                let bottom = lerp(self.texel(level, x0, y0 + 1), self.texel(level, x0 + 1, y0 + 1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+}
+
+// lerp interpolates linearly between two colors. (Vec4::lerp scales the
+// first by the second instead.)
+pub(crate) fn lerp(a: Vec4<f32>, b: Vec4<f32>, t: f32) -> Vec4<f32> {
+    a + (b - a).mul_scalar(t)
+}
+
+// wrap maps a texel coordinate onto a texture of size n, or returns None if
+// it's on the border.
+fn wrap(i: i64, n: i64, mode: Wrap) -> Option<i64> {
+    match mode {
+        Wrap::Repeat => Some(i.rem_euclid(n)),
+        Wrap::Mirror => {
+            let p = i.rem_euclid(2 * n);
+            Some(if p < n { p } else { 2 * n - 1 - p })
+        }
+        Wrap::Clamp => Some(i.clamp(0, n - 1)),
+        Wrap::Border => if (0..n).contains(&i) { Some(i) } else { None },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec2, Vec4};
+    use super::super::image::{Image, PixelFormat};
+    use super::{ColorSpace, Filter, MipFilter, MipmapMode, Mipmaps, Sampler, Wrap};
+
+    // ramp returns a 4x2 image whose red channel increases to the right,
+    // and green is one on the bottom row.
+    fn ramp() -> Mipmaps {
+        let mut image = Image::new(4, 2, PixelFormat::Rgba32F);
+        for y in 0..2 {
+            for x in 0..4 {
+                image.set_pixel(x, y, Vec4(x as f32, y as f32, 0.0, 1.0));
+            }
+        }
+        Mipmaps::generate(&image, MipFilter::Box, ColorSpace::Linear)
+    }
+
+    #[test]
+    fn filters() {
+        let mips = ramp();
+        let mut s = Sampler::new();
+        s.mag_filter = Filter::Nearest;
+        assert_eq!(s.sample(&mips, Vec2(0.6, 0.9)), Vec4(2.0, 1.0, 0.0, 1.0));
+        s.mag_filter = Filter::Linear;
+        assert_eq!(s.sample(&mips, Vec2(0.5, 0.5)), Vec4(1.5, 0.5, 0.0, 1.0));
+
+        // Repeating blends the last column with the first.
+        assert_eq!(s.sample(&mips, Vec2(1.0, 0.25)).0, 1.5);
+        s.wrap_u = Wrap::Clamp;
+        assert_eq!(s.sample(&mips, Vec2(1.0, 0.25)).0, 3.0);
+        s.wrap_u = Wrap::Mirror;
+        assert_eq!(s.sample(&mips, Vec2(-0.125, 0.25)).0, 0.0);
+        assert_eq!(s.sample(&mips, Vec2(1.375, 0.25)).0, 2.0);
+        s.wrap_u = Wrap::Border;
+        s.border = Vec4(9.0, 9.0, 9.0, 9.0);
+        assert_eq!(s.sample(&mips, Vec2(1.0, 0.25)).0, 6.0);
+        assert_eq!(s.sample(&mips, Vec2(2.0, 0.25)), s.border);
+    }
+
+    #[test]
+    fn mip_levels() {
+        let mips = ramp();
+        let sizes: Vec<_> = mips.levels().iter().map(|l| (l.width(), l.height())).collect();
+        assert_eq!(sizes, vec![(4, 2), (2, 1), (1, 1)]);
+        assert_eq!(mips.levels()[1].pixel(1, 0), Vec4(2.5, 0.5, 0.0, 1.0));
+        assert_eq!(mips.levels()[2].pixel(0, 0), Vec4(1.5, 0.5, 0.0, 1.0));
+
+        let mut s = Sampler::new();
+        let uv = Vec2(0.75, 0.5);
+        let (l0, l1) = (s.sample_lod(&mips, uv, 0.0), s.sample_lod(&mips, uv, 1.0));
+        assert_eq!(s.sample_lod(&mips, uv, 0.5), l0.mul_scalar(0.5) + l1.mul_scalar(0.5));
+        assert_eq!(s.sample_lod(&mips, uv, 5.0), Vec4(1.5, 0.5, 0.0, 1.0));
+        s.mipmap_mode = MipmapMode::Nearest;
+        assert_eq!(s.sample_lod(&mips, uv, 0.75), l1);
+        s.mipmap_mode = MipmapMode::None;
+        assert_eq!(s.sample_lod(&mips, uv, 0.75), l0);
+
+        // Derivatives of one texel per pixel select the first level.
+        assert_eq!(s.sample_grad(&mips, uv, Vec2(0.25, 0.0), Vec2(0.0, 0.5)), l0);
+    }
+}
@@ -0,0 +1,80 @@
+use math::Vec2;
+
+/// pcf_tent returns the offsets in texels and weights of a percentage-closer
+/// filter with a tent (bilinear) falloff, which reaches zero one texel
+/// beyond the given radius. The weights sum to one.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::shadow::pcf_tent;
+///
+/// let kernel = pcf_tent(1);
+/// assert_eq!(kernel.len(), 9);
+/// assert!((kernel.iter().map(|k| k.1).sum::<f32>() - 1.0).abs() < 1e-6);
+///
+/// // The center is weighted most.
+/// let center = kernel.iter().find(|k| k.0 .0 == 0.0 && k.0 .1 == 0.0).unwrap();
+/// assert!(kernel.iter().all(|k| k.1 <= center.1));
+/// ```
+pub fn pcf_tent(radius: u32) -> Vec<(Vec2<f32>, f32)> {
+    let r = radius as i32;
+    let tent = |i: i32| (r + 1 - i.abs()) as f32;
+    let mut kernel = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+    for y in -r..=r {
+        for x in -r..=r {
+            kernel.push((Vec2(x as f32, y as f32), tent(x) * tent(y)));
+        }
+    }
+    let total: f32 = kernel.iter().map(|k| k.1).sum();
+    for k in &mut kernel {
+        k.1 /= total;
+    }
+    kernel
+}
+
+/// vogel_disk returns the given number of points spread evenly over the unit
+/// disc along a golden angle spiral, for sampling shadow maps with few
+/// samples and little visible pattern.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::shadow::vogel_disk;
+///
+/// let disk = vogel_disk(16);
+/// assert_eq!(disk.len(), 16);
+/// assert!(disk.iter().all(|p| p.length() <= 1.0));
+/// ```
+pub fn vogel_disk(count: u32) -> Vec<Vec2<f32>> {
+    // The golden angle, pi * (3 - sqrt(5)).
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    (0..count)
+        .map(|i| {
+            let r = ((i as f32 + 0.5) / count as f32).sqrt();
+            let theta = i as f32 * GOLDEN_ANGLE;
+            Vec2(r * theta.cos(), r * theta.sin())
+        })
+        .collect()
+}
+
+/// pcss_penumbra returns the width of the penumbra at a receiver, for an
+/// area light of the given width and an average blocker between the two,
+/// all measured along the direction of the light. This estimate from similar
+/// triangles is the filter width of percentage-closer soft shadows.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::shadow::pcss_penumbra;
+///
+/// // Shadows are sharp near the blocker and soften with distance.
+/// assert_eq!(pcss_penumbra(2.0, 2.0, 0.5), 0.0);
+/// assert_eq!(pcss_penumbra(4.0, 2.0, 0.5), 0.5);
+/// ```
+pub fn pcss_penumbra(receiver: f32, blocker: f32, light_size: f32) -> f32 {
+    if blocker <= 0.0 || receiver <= blocker {
+        return 0.0;
+    }
+    light_size * (receiver - blocker) / blocker
+}
@@ -0,0 +1,223 @@
+use math::{Mat4, Vec2, Vec3, Vec4};
+use super::super::mesh::Mesh;
+use super::super::raster::{Cull, Framebuffer, Rasterizer};
+use super::{pcss_penumbra, vogel_disk};
+
+/// ShadowMap is a square depth image of a scene as seen by a light, rendered
+/// with the software rasterizer.
+///
+/// Shadow lookups take points in world space. Depths are those of the depth
+/// buffer, from zero at the near plane to one at the far plane.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowMap {
+    view_proj: Mat4<f32>,
+    size: u32,
+    depth: Vec<f32>,
+}
+
+impl ShadowMap {
+    /// render renders the depth of the given meshes with a light's
+    /// view-projection matrix into a new shadow map of the given width and
+    /// height. Triangles aren't culled, so either side casts shadows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::{Mat4, Vec3};
+    /// use fiz_gfx::mesh::Mesh;
+    /// use fiz_gfx::shadow::ShadowMap;
+    ///
+    /// // A light above a unit quad, looking down.
+    /// let quad = Mesh::new(vec![Vec3(-0.5, 0.0, -0.5), Vec3(0.5, 0.0, -0.5), Vec3(0.5, 0.0, 0.5),
+    ///                           Vec3(-0.5, 0.0, 0.5)],
+    ///                      vec![[0, 1, 2], [0, 2, 3]]);
+    /// let view = Mat4::look_at(Vec3(0.0, 2.0, 0.0), Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0));
+    /// let map = ShadowMap::render(Mat4::orthographic(-1.0, 1.0, -1.0, 1.0, 1.0, 3.0) * view, 8, &[&quad]);
+    ///
+    /// // The quad covers the center of the map, halfway between the planes.
+    /// assert_eq!(map.depth(4, 4), 0.5);
+    /// assert_eq!(map.depth(0, 0), 1.0);
+    ///
+    /// // Points below the quad are in shadow.
+    /// assert_eq!(map.visibility(Vec3(0.0, -1.0, 0.0), 0.001), 0.0);
+    /// assert_eq!(map.visibility(Vec3(0.0, 0.0, 0.0), 0.001), 1.0);
+    /// ```
+    pub fn render(view_proj: Mat4<f32>, size: u32, meshes: &[&Mesh]) -> Self {
+        let mut fb = Framebuffer::new(size, size, 1);
+        let rasterizer = Rasterizer {
+            cull: Cull::None,
+            depth_test: true,
+            depth_write: true,
+        };
+        for mesh in meshes {
+            let vertices: Vec<(Vec4<f32>, ())> = mesh.positions
+                .iter()
+                .map(|p| (view_proj.mul_vec4(Vec4(p.0, p.1, p.2, 1.0)), ()))
+                .collect();
+            rasterizer.draw(&mut fb, &vertices, &mesh.indices, |_| Some(Vec4(0.0, 0.0, 0.0, 1.0)));
+        }
+        let mut depth = Vec::with_capacity((size * size) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                depth.push(fb.depth(x, y, 0));
+            }
+        }
+        ShadowMap {
+            view_proj,
+            size,
+            depth,
+        }
+    }
+
+    /// size returns the width and height of the shadow map in texels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// view_proj returns the view-projection matrix the shadow map was
+    /// rendered with.
+    pub fn view_proj(&self) -> Mat4<f32> {
+        self.view_proj
+    }
+
+    /// depth returns the depth of the nearest surface at a texel, counted
+    /// from the top-left corner, or one where there's none.
+    ///
+    /// # Panics
+    ///
+    /// depth panics if the texel is out of bounds.
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        assert!(x < self.size && y < self.size, "texel ({}, {}) out of bounds", x, y);
+        self.depth[(y * self.size + x) as usize]
+    }
+
+    /// project returns the position of a point in the shadow map, in texels
+    /// from its top-left corner, and its depth, or None if it's in front of
+    /// the light's projection.
+    pub fn project(&self, p: Vec3<f32>) -> Option<Vec3<f32>> {
+        let clip = self.view_proj.mul_vec4(Vec4(p.0, p.1, p.2, 1.0));
+        if clip.3 <= 0.0 {
+            return None;
+        }
+        let size = self.size as f32;
+        Some(Vec3((clip.0 / clip.3 + 1.0) * 0.5 * size,
+                  (1.0 - clip.1 / clip.3) * 0.5 * size,
+                  (clip.2 / clip.3 + 1.0) * 0.5))
+    }
+
+    // stored returns the depth of the texel containing a position in texels,
+    // or one outside the map, where nothing casts shadows.
+    fn stored(&self, texel: Vec2<f32>) -> f32 {
+        let size = self.size as f32;
+        if !(texel.0 >= 0.0 && texel.1 >= 0.0 && texel.0 < size && texel.1 < size) {
+            return 1.0;
+        }
+        self.depth(texel.0 as u32, texel.1 as u32)
+    }
+
+    /// visibility returns one if a point is lit and zero if it's in shadow,
+    /// that is, if a surface is nearer to the light by more than the bias.
+    /// Points outside the map are lit.
+    pub fn visibility(&self, p: Vec3<f32>, bias: f32) -> f32 {
+        self.pcf(p, bias, &[(Vec2(0.0, 0.0), 1.0)])
+    }
+
+    /// pcf returns the fraction of a point which is lit, filtering
+    /// shadow tests at the weighted offsets in texels of a kernel, such as
+    /// those of `pcf_tent`.
+    pub fn pcf(&self, p: Vec3<f32>, bias: f32, kernel: &[(Vec2<f32>, f32)]) -> f32 {
+        let q = match self.project(p) {
+            Some(q) => q,
+            None => return 1.0,
+        };
+        let center = Vec2(q.0, q.1);
+        kernel.iter()
+            .filter(|k| self.stored(center + k.0) >= q.2 - bias)
+            .map(|k| k.1)
+            .sum()
+    }
+
+    /// pcss returns the fraction of a point which is lit by percentage-closer
+    /// soft shadows of an area light, whose width is given in texels: the
+    /// average depth of blockers near the point gives the width of the
+    /// penumbra, over which shadow tests are filtered with the given number
+    /// of samples.
+    ///
+    /// The penumbra is estimated from depths, so it's only proportional to
+    /// distance for orthographic projections whose near plane is at the
+    /// light.
+    pub fn pcss(&self, p: Vec3<f32>, bias: f32, light_size: f32, samples: u32) -> f32 {
+        let q = match self.project(p) {
+            Some(q) => q,
+            None => return 1.0,
+        };
+        let center = Vec2(q.0, q.1);
+        let disk = vogel_disk(samples);
+        let (mut total, mut blockers) = (0.0, 0);
+        for &d in &disk {
+            let depth = self.stored(center + d.mul_scalar(light_size));
+            if depth < q.2 - bias {
+                total += depth;
+                blockers += 1;
+            }
+        }
+        if blockers == 0 {
+            return 1.0;
+        }
+        let radius = pcss_penumbra(q.2, total / blockers as f32, light_size).max(0.5);
+        let lit = disk.iter()
+            .filter(|&&d| self.stored(center + d.mul_scalar(radius)) >= q.2 - bias)
+            .count();
+        lit as f32 / samples as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Mat4, Vec3};
+    use super::super::super::mesh::Mesh;
+    use super::super::pcf_tent;
+    use super::ShadowMap;
+
+    fn quad(y: f32, half: f32) -> Mesh {
+        Mesh::new(vec![Vec3(-half, y, -half), Vec3(half, y, -half), Vec3(half, y, half), Vec3(-half, y, half)],
+                  vec![[0, 1, 2], [0, 2, 3]])
+    }
+
+    // scene returns the shadow map of a sun shining straight down on a
+    // ground with a 2x2 meter blocker above it at the given height.
+    fn scene(height: f32) -> ShadowMap {
+        let view = Mat4::look_at(Vec3(0.0, 10.0, 0.0), Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0));
+        let projection = Mat4::orthographic(-5.0, 5.0, -5.0, 5.0, 0.0, 20.0);
+        ShadowMap::render(projection * view, 64, &[&quad(0.0, 5.0), &quad(height, 1.0)])
+    }
+
+    #[test]
+    fn shadows() {
+        let map = scene(5.0);
+        let bias = 0.005;
+        assert_eq!(map.size(), 64);
+        assert_eq!(map.depth(0, 0), 0.5);
+        assert_eq!(map.depth(32, 32), 0.25);
+        let p = map.project(Vec3(-5.0, 0.0, -5.0)).unwrap();
+        assert!(p.almost_equal(Vec3(0.0, 0.0, 0.5), 1e-5), "{:?}", p);
+
+        // The blocker doesn't shadow itself, but shadows the ground, with a
+        // soft edge when filtered.
+        assert_eq!(map.visibility(Vec3(0.0, 5.0, 0.0), bias), 1.0);
+        assert_eq!(map.visibility(Vec3(0.0, 0.0, 0.0), bias), 0.0);
+        assert_eq!(map.visibility(Vec3(3.0, 0.0, 3.0), bias), 1.0);
+        let kernel = pcf_tent(2);
+        assert_eq!(map.pcf(Vec3(0.0, 0.0, 0.0), bias, &kernel), 0.0);
+        assert!((map.pcf(Vec3(3.0, 0.0, 3.0), bias, &kernel) - 1.0).abs() < 1e-6);
+        let edge = map.pcf(Vec3(1.0, 0.0, 0.0), bias, &kernel);
+        assert!(edge > 0.2 && edge < 0.8, "{}", edge);
+
+        // Soft shadows are softer for blockers nearer the light.
+        assert_eq!(map.pcss(Vec3(3.0, 0.0, 3.0), bias, 4.0, 32), 1.0);
+        assert_eq!(map.pcss(Vec3(0.0, 0.0, 0.0), bias, 4.0, 32), 0.0);
+        let near = map.pcss(Vec3(0.7, 0.0, 0.0), bias, 4.0, 64);
+        let far = scene(8.0).pcss(Vec3(0.7, 0.0, 0.0), bias, 4.0, 64);
+        assert!(near > 0.0 && near < far && far < 1.0, "{} {}", near, far);
+    }
+}
@@ -0,0 +1,363 @@
+//! Shadow mapping.
+//!
+//! The functions here compute the view-projection matrices shadow maps are
+//! rendered with: one for a spot light, six (a cube) for a point light, and
+//! cascades for directional lights, which split the view of the camera by
+//! depth so that nearby shadows get more texels. `ShadowMap` renders the
+//! depth pass with the software rasterizer and looks up shadows with the
+//! filters of the `filter` functions, for tests and offline rendering.
+//!
+//! Matrices follow the clip-space conventions of the `raster` module.
+
+mod filter;
+mod map;
+
+use math::{Mat4, Vec3, Vec4};
+use math::unit::{M, Rad};
+use super::light::{Perspective, PointLight, SpotLight};
+use super::sampler::CubeFace;
+
+pub use self::filter::{pcf_tent, pcss_penumbra, vogel_disk};
+pub use self::map::ShadowMap;
+
+// meters returns a position in meters as plain floats.
+fn meters(p: Vec3<M<f32>>) -> Vec3<f32> {
+    Vec3(p.0 .0, p.1 .0, p.2 .0)
+}
+
+// any_up returns a unit vector which isn't parallel to the direction.
+fn any_up(dir: Vec3<f32>) -> Vec3<f32> {
+    if dir.1.abs() < 0.99 {
+        Vec3(0.0, 1.0, 0.0)
+    } else {
+        Vec3(1.0, 0.0, 0.0)
+    }
+}
+
+/// spot_view_proj returns the view-projection matrix of the shadow map of a
+/// spot light, covering its cone out to its range, from the given near
+/// distance.
+pub fn spot_view_proj(light: &SpotLight, near: M<f32>) -> Mat4<f32> {
+    let eye = meters(light.position);
+    let view = Mat4::look_at(eye, eye + light.direction, any_up(light.direction));
+    let fov = Rad(2.0 * light.outer_angle.0.clamp(1e-3, 0.499 * ::std::f32::consts::PI));
+    Mat4::perspective(fov, 1.0, near.0, light.range.0) * view
+}
+
+/// point_view_projs returns the view-projection matrices of the six faces
+/// of the cube shadow map of a point light, in the order of `CubeFace::ALL`,
+/// from the given near distance to its range.
+///
+/// Rendering a face gives the image `CubeMap` expects, so rows start from
+/// the top as usual. As cube maps are mirrored, the faces flip the winding
+/// of triangles.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::math::unit::M;
+/// use fiz_gfx::light::{Lumens, PointLight};
+/// use fiz_gfx::sampler::CubeFace;
+/// use fiz_gfx::shadow::point_view_projs;
+///
+/// let light = PointLight {
+///     position: Vec3(M(0.0), M(0.0), M(0.0)),
+///     color: Vec3(1.0, 1.0, 1.0),
+///     power: Lumens(100.0),
+///     range: M(10.0),
+/// };
+/// let faces = point_view_projs(&light, M(0.1));
+///
+/// // Points along +X are at the center of the +X face.
+/// let p = faces[CubeFace::PositiveX as usize].transform_point(Vec3(5.0, 0.0, 0.0));
+/// assert!(p.0.abs() < 1e-6 && p.1.abs() < 1e-6);
+/// ```
+pub fn point_view_projs(light: &PointLight, near: M<f32>) -> [Mat4<f32>; 6] {
+    let eye = meters(light.position);
+    let projection = Mat4::perspective(Rad(0.5 * ::std::f32::consts::PI), 1.0, near.0, light.range.0);
+    let face = |face: CubeFace| {
+        // The directions the face looks in, and in which its texture
+        // coordinates increase (see CubeFace::project).
+        let (forward, right, down) = match face {
+            CubeFace::PositiveX => (Vec3(1.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0), Vec3(0.0, -1.0, 0.0)),
+            CubeFace::NegativeX => (Vec3(-1.0, 0.0, 0.0), Vec3(0.0, 0.0, 1.0), Vec3(0.0, -1.0, 0.0)),
+            CubeFace::PositiveY => (Vec3(0.0, 1.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 0.0, 1.0)),
+            CubeFace::NegativeY => (Vec3(0.0, -1.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0)),
+            CubeFace::PositiveZ => (Vec3(0.0, 0.0, 1.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, -1.0, 0.0)),
+            CubeFace::NegativeZ => (Vec3(0.0, 0.0, -1.0), Vec3(-1.0, 0.0, 0.0), Vec3(0.0, -1.0, 0.0)),
+        };
+        let up: Vec3<f32> = -down;
+        let view = Mat4(Vec4(right.0, up.0, -forward.0, 0.0),
+                        Vec4(right.1, up.1, -forward.1, 0.0),
+                        Vec4(right.2, up.2, -forward.2, 0.0),
+                        Vec4(-right.dot(eye), -up.dot(eye), forward.dot(eye), 1.0));
+        projection * view
+    };
+    [face(CubeFace::PositiveX),
+     face(CubeFace::NegativeX),
+     face(CubeFace::PositiveY),
+     face(CubeFace::NegativeY),
+     face(CubeFace::PositiveZ),
+     face(CubeFace::NegativeZ)]
+}
+
+/// CascadeFit is the way cascades are fit around their part of the view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CascadeFit {
+    /// Cascades enclose a sphere around their part of the view, so their
+    /// size doesn't change as the camera turns. Combined with texel
+    /// snapping, shadows don't shimmer as the camera moves or turns.
+    Stable,
+
+    /// Cascades tightly enclose their part of the view as seen from the
+    /// light, which uses the shadow map better, but shadow edges shimmer as
+    /// the camera turns.
+    Tight,
+}
+
+/// Cascade is a shadow map covering a range of depths of the view.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cascade {
+    /// The distance from the camera where the cascade starts.
+    pub near: M<f32>,
+
+    /// The distance from the camera where the cascade ends.
+    pub far: M<f32>,
+
+    /// The view-projection matrix the cascade is rendered with.
+    pub view_proj: Mat4<f32>,
+
+    /// The width of a texel of the shadow map.
+    pub texel_size: M<f32>,
+}
+
+/// ShadowCascades are the settings of the cascaded shadow map of a
+/// directional light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ShadowCascades {
+    /// The number of cascades.
+    pub count: u32,
+
+    /// The blend between uniform (zero) and logarithmic (one) split
+    /// distances of the practical split scheme.
+    pub lambda: f32,
+
+    /// The width and height of each shadow map in texels.
+    pub resolution: u32,
+
+    /// How cascades are fit around the view.
+    pub fit: CascadeFit,
+
+    /// How far towards the light beyond the view shadow casters are
+    /// included.
+    pub caster_margin: M<f32>,
+}
+
+impl Default for ShadowCascades {
+    /// default returns four stable 2048x2048 cascades, with splits mostly
+    /// logarithmic.
+    fn default() -> Self {
+        ShadowCascades {
+            count: 4,
+            lambda: 0.75,
+            resolution: 2048,
+            fit: CascadeFit::Stable,
+            caster_margin: M(50.0),
+        }
+    }
+}
+
+impl ShadowCascades {
+    /// splits returns the distances from the camera at which cascades
+    /// start, followed by the far distance of the view, by the practical
+    /// split scheme: a blend of logarithmic splits, which keep the texel
+    /// density even, and uniform ones, which don't crowd the near plane.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::math::unit::M;
+    /// use fiz_gfx::shadow::ShadowCascades;
+    ///
+    /// let uniform = ShadowCascades { count: 4, lambda: 0.0, ..ShadowCascades::default() };
+    /// assert_eq!(uniform.splits(M(1.0), M(1000.0)), vec![M(1.0), M(250.75), M(500.5), M(750.25), M(1000.0)]);
+    /// let log = ShadowCascades { count: 3, lambda: 1.0, ..ShadowCascades::default() };
+    /// let splits: Vec<_> = log.splits(M(1.0), M(1000.0)).iter().map(|s| s.0.round()).collect();
+    /// assert_eq!(splits, vec![1.0, 10.0, 100.0, 1000.0]);
+    /// ```
+    pub fn splits(&self, near: M<f32>, far: M<f32>) -> Vec<M<f32>> {
+        let (n, f) = (near.0, far.0);
+        (0..=self.count)
+            .map(|i| {
+                let t = i as f32 / self.count as f32;
+                let log = n * (f / n).powf(t);
+                let uniform = n + (f - n) * t;
+                M(self.lambda * log + (1.0 - self.lambda) * uniform)
+            })
+            .collect()
+    }
+
+    /// fit returns the cascades of a view, for a directional light shining
+    /// in the given direction.
+    ///
+    /// Cascades are snapped to whole texels as seen by the light, so shadow
+    /// edges don't shimmer as the camera moves.
+    pub fn fit(&self, view: &Perspective, direction: Vec3<f32>) -> Vec<Cascade> {
+        let dir = direction.normalize().expect("ShadowCascades::fit: zero direction");
+        // The rotation into light space, which must not depend on the view
+        // so that snapping is stable.
+        let rotation = Mat4::look_at(Vec3(0.0, 0.0, 0.0), dir, any_up(dir));
+        let forward = view.forward.normalize().expect("ShadowCascades::fit: zero forward vector");
+        let right = forward.cross(view.up).normalize().expect("ShadowCascades::fit: up is parallel to forward");
+        let up = right.cross(forward);
+        let tan_y = (0.5 * view.fov_y.0).tan();
+        let tan_x = tan_y * view.aspect;
+        let eye = meters(view.eye);
+        let splits = self.splits(view.near, view.far);
+        splits.windows(2)
+            .map(|s| {
+                let (d0, d1) = (s[0].0, s[1].0);
+                let (center, half_width) = match self.fit {
+                    CascadeFit::Stable => {
+                        // The smallest sphere around the slice of the frustum,
+                        // whose center is on its axis.
+                        let k = tan_x * tan_x + tan_y * tan_y;
+                        let c = (0.5 * (d0 + d1) * (1.0 + k)).min(d1);
+                        let near = ((c - d0) * (c - d0) + k * d0 * d0).sqrt();
+                        let far = ((d1 - c) * (d1 - c) + k * d1 * d1).sqrt();
+                        let radius = near.max(far);
+                        (rotation.transform_point(eye + forward.mul_scalar(c)), radius)
+                    }
+                    CascadeFit::Tight => {
+                        let mut lo = Vec3(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+                        let mut hi = -lo;
+                        for &d in &[d0, d1] {
+                            for &sx in &[-1.0, 1.0] {
+                                for &sy in &[-1.0, 1.0] {
+                                    let p = eye + forward.mul_scalar(d) + right.mul_scalar(sx * tan_x * d) +
+                                            up.mul_scalar(sy * tan_y * d);
+                                    let p = rotation.transform_point(p);
+                                    lo = lo.min(p);
+                                    hi = hi.max(p);
+                                }
+                            }
+                        }
+                        let size = hi - lo;
+                        (lo + size.mul_scalar(0.5), 0.5 * size.0.max(size.1).max(size.2))
+                    }
+                };
+                let texel = 2.0 * half_width / self.resolution as f32;
+                let snap = |v: f32| (v / texel).floor() * texel;
+                let (x, y) = (snap(center.0), snap(center.1));
+                // Light space looks down -Z, so distances are negated.
+                let (near, far) = (-center.2 - half_width - self.caster_margin.0, -center.2 + half_width);
+                let projection = Mat4::orthographic(x - half_width,
+                                                    x + half_width,
+                                                    y - half_width,
+                                                    y + half_width,
+                                                    near,
+                                                    far);
+                Cascade {
+                    near: s[0],
+                    far: s[1],
+                    view_proj: projection * rotation,
+                    texel_size: M(texel),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::{Vec2, Vec3};
+    use math::unit::{Deg, M, ToRad};
+    use super::super::light::{Lumens, Perspective, PointLight, SpotLight};
+    use super::super::sampler::CubeFace;
+    use super::*;
+
+    fn view(eye: Vec3<f32>, forward: Vec3<f32>) -> Perspective {
+        Perspective {
+            eye: Vec3(M(eye.0), M(eye.1), M(eye.2)),
+            forward,
+            up: Vec3(0.0, 1.0, 0.0),
+            fov_y: Deg(60.0).to_rad(),
+            aspect: 16.0 / 9.0,
+            near: M(0.1),
+            far: M(200.0),
+        }
+    }
+
+    // texel returns the position of a point in the shadow map of a cascade,
+    // in texels.
+    fn texel(c: &Cascade, resolution: u32, p: Vec3<f32>) -> Vec2<f32> {
+        let p = c.view_proj.transform_point(p);
+        Vec2((p.0 + 1.0) * 0.5 * resolution as f32, (p.1 + 1.0) * 0.5 * resolution as f32)
+    }
+
+    #[test]
+    fn cascades() {
+        let settings = ShadowCascades { resolution: 1024, ..ShadowCascades::default() };
+        let sun = Vec3(0.3, -1.0, 0.2);
+        let a = view(Vec3(0.0, 2.0, 0.0), Vec3(0.0, 0.0, -1.0));
+        let cascades = settings.fit(&a, sun);
+        assert_eq!(cascades.len(), 4);
+        assert_eq!((cascades[0].near, cascades[3].far), (M(0.1), M(200.0)));
+        for c in &cascades {
+            assert!(c.texel_size.0 > 0.0);
+            // The slice of the view is within the cascade.
+            for &d in &[c.near.0, c.far.0] {
+                let p = c.view_proj.transform_point(Vec3(0.0, 2.0, -d));
+                assert!(p.0.abs() <= 1.0 && p.1.abs() <= 1.0 && p.2.abs() <= 1.0, "{:?}", p);
+            }
+            // Casters towards the light are too.
+            let p = c.view_proj.transform_point(Vec3(0.0, 2.0, -c.near.0) - sun.mul_scalar(20.0));
+            assert!(p.2.abs() <= 1.0, "{:?}", p);
+        }
+        assert!(cascades.windows(2).all(|c| c[0].texel_size.0 < c[1].texel_size.0));
+
+        // Moving the camera moves the cascades by whole texels, and turning
+        // it doesn't resize stable cascades.
+        let b = view(Vec3(0.37, 2.05, -0.21), Vec3(0.1, 0.0, -1.0));
+        for (ca, cb) in cascades.iter().zip(settings.fit(&b, sun)) {
+            assert!((ca.texel_size.0 - cb.texel_size.0).abs() < 1e-6 * ca.texel_size.0);
+            let p = Vec3(3.0, 0.0, -7.0);
+            let d = texel(ca, 1024, p) - texel(&cb, 1024, p);
+            assert!((d.0 - d.0.round()).abs() < 0.01 && (d.1 - d.1.round()).abs() < 0.01, "{:?}", d);
+        }
+
+        // Tight cascades are smaller.
+        let tight = ShadowCascades { fit: CascadeFit::Tight, ..settings }.fit(&a, sun);
+        assert!(tight.iter().zip(&cascades).all(|(t, s)| t.texel_size.0 <= s.texel_size.0));
+    }
+
+    #[test]
+    fn light_matrices() {
+        let light = PointLight {
+            position: Vec3(M(1.0), M(-2.0), M(0.5)),
+            color: Vec3(1.0, 1.0, 1.0),
+            power: Lumens(100.0),
+            range: M(20.0),
+        };
+        let faces = point_view_projs(&light, M(0.05));
+        let dirs = [Vec3(1.0, 0.3, -0.4), Vec3(-0.2, 0.9, 0.5), Vec3(0.3, -0.2, -1.0), Vec3(-1.0, -0.6, 0.1),
+                    Vec3(0.5, -1.0, 0.7), Vec3(-0.1, 0.4, 0.8)];
+        for &d in &dirs {
+            // Each face renders its directions where the cube map samples
+            // them.
+            let (face, uv) = CubeFace::project(d).unwrap();
+            let p = faces[face as usize].transform_point(Vec3(1.0, -2.0, 0.5) + d.mul_scalar(3.0));
+            assert!(Vec2(0.5 + 0.5 * p.0, 0.5 - 0.5 * p.1).almost_equal(uv, 1e-5), "{:?} {:?}", p, uv);
+            assert!(p.2 > -1.0 && p.2 < 1.0);
+        }
+
+        let spot = SpotLight::new(Vec3(M(0.0), M(5.0), M(0.0)), Vec3(0.0, -1.0, 0.0), Lumens(500.0), Deg(20.0),
+                                  Deg(30.0));
+        let m = spot_view_proj(&spot, M(0.1));
+        let inside = m.transform_point(Vec3(0.0, 0.0, 2.8));
+        assert!(inside.0.abs() < 1.0 && inside.1.abs() < 1.0);
+        let outside = m.transform_point(Vec3(0.0, 0.0, 3.0));
+        assert!(outside.0.abs().max(outside.1.abs()) > 1.0);
+    }
+}
@@ -0,0 +1,345 @@
+//! STL import and export.
+//!
+//! Both the ASCII and binary variants of STL are supported. STL files store
+//! each triangle with its own three vertices and a facet normal, so imported
+//! meshes have three vertices per triangle whose normals are the facet
+//! normals. To share vertices between triangles, remove the normals and
+//! weld the mesh:
+//!
+//! ```no_run
+//! use fiz_gfx::stl;
+//!
+//! let mut part = stl::load("bracket.stl").unwrap();
+//! part.mesh.normals = None;
+//! part.mesh.weld(0.0);
+//! part.mesh.compute_smooth_normals().unwrap();
+//! ```
+//!
+//! STL files have no notion of units, but are almost always written in
+//! millimeters, which is what `Stl::units` defaults to.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use math::Vec3;
+use super::{Mesh, Units};
+
+/// StlError describes a failure to load an STL file.
+#[derive(Debug)]
+pub enum StlError {
+    /// A file couldn't be read.
+    Io {
+        /// The path of the file.
+        path: PathBuf,
+
+        /// The underlying error.
+        error: io::Error,
+    },
+
+    /// A line of an ASCII file is invalid.
+    Syntax {
+        /// The line number, starting at one.
+        line: usize,
+
+        /// A description of the problem.
+        message: String,
+    },
+
+    /// A binary file is shorter than its triangle count implies.
+    Truncated,
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StlError::Io { ref path, ref error } => write!(f, "{}: {}", path.display(), error),
+            StlError::Syntax { line, ref message } => write!(f, "line {}: {}", line, message),
+            StlError::Truncated => write!(f, "truncated binary STL file"),
+        }
+    }
+}
+
+impl Error for StlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            StlError::Io { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// StlFormat is the variant of an STL file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StlFormat {
+    /// The human readable variant, starting with `solid`.
+    Ascii,
+
+    /// The compact binary variant.
+    Binary,
+}
+
+/// Stl is the contents of an STL file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stl {
+    /// The name of the solid (ASCII), or the text of the header (binary).
+    pub name: String,
+
+    /// The variant of the file.
+    pub format: StlFormat,
+
+    /// The units of the coordinates, which STL files don't record.
+    pub units: Units,
+
+    /// The triangles, with three vertices each and facet normals.
+    pub mesh: Mesh,
+}
+
+impl Stl {
+    /// new returns a new STL model of the mesh, in the given format and in
+    /// millimeters.
+    pub fn new(name: &str, format: StlFormat, mesh: Mesh) -> Self {
+        Stl {
+            name: name.to_string(),
+            format,
+            units: Units::MM,
+            mesh,
+        }
+    }
+}
+
+/// load loads the STL file at the given path.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Stl, StlError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|error| {
+        StlError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })?;
+    parse_stl(&data)
+}
+
+/// parse_stl parses the contents of an ASCII or binary STL file. Facet
+/// normals which are zero (as some exporters write) are computed from the
+/// triangles instead.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::stl::{parse_stl, StlError, StlFormat};
+///
+/// let stl = parse_stl(b"solid wedge
+///   facet normal 0 0 0
+///     outer loop
+///       vertex 0 0 0
+///       vertex 10 0 0
+///       vertex 0 10 0
+///     endloop
+///   endfacet
+/// endsolid wedge
+/// ").unwrap();
+/// assert_eq!((stl.name.as_str(), stl.format), ("wedge", StlFormat::Ascii));
+/// assert_eq!(stl.mesh.positions[1], Vec3(10.0, 0.0, 0.0));
+/// assert_eq!(stl.mesh.normals.unwrap()[0], Vec3(0.0, 0.0, 1.0));
+///
+/// match parse_stl(b"solid x\nfacet normal 0 0 1\nouter loop\nvertex 0 0\n") {
+///     Err(StlError::Syntax { line: 4, .. }) => {}
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn parse_stl(data: &[u8]) -> Result<Stl, StlError> {
+    // Some binary files start with "solid" too, so they're recognized by
+    // their size first.
+    let binary_size = data.get(80..84).map(|b| 84 + 50 * u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let text = ::std::str::from_utf8(data).ok().filter(|s| s.trim_start().starts_with("solid"));
+    match text {
+        Some(text) if binary_size != Some(data.len()) => parse_ascii(text),
+        _ => parse_binary(data),
+    }
+}
+
+// Facet is the normal and vertices of a triangle.
+type Facet = (Vec3<f32>, [Vec3<f32>; 3]);
+
+// build returns the mesh of the facets, computing zero normals.
+fn build(facets: Vec<Facet>) -> Mesh {
+    let mut mesh = Mesh::new(Vec::with_capacity(facets.len() * 3), Vec::with_capacity(facets.len()));
+    let mut normals = Vec::with_capacity(facets.len() * 3);
+    for (i, (n, v)) in facets.into_iter().enumerate() {
+        let n = n.normalize().unwrap_or_else(|| facet_normal(&v));
+        let base = 3 * i as u32;
+        mesh.positions.extend_from_slice(&v);
+        normals.extend_from_slice(&[n, n, n]);
+        mesh.indices.push([base, base + 1, base + 2]);
+    }
+    mesh.normals = Some(normals);
+    mesh
+}
+
+// facet_normal returns the unit normal of the triangle, or zero if it's
+// degenerate.
+fn facet_normal(v: &[Vec3<f32>; 3]) -> Vec3<f32> {
+    (v[1] - v[0]).cross(v[2] - v[0]).normalize().unwrap_or(Vec3(0.0, 0.0, 0.0))
+}
+
+fn parse_ascii(src: &str) -> Result<Stl, StlError> {
+    let mut name = None;
+    let mut facets = Vec::new();
+    let mut normal = Vec3(0.0, 0.0, 0.0);
+    let mut vertices = Vec::new();
+    for (i, text) in src.lines().enumerate() {
+        let line = i + 1;
+        let syntax = |message: &str| {
+            StlError::Syntax {
+                line,
+                message: message.to_string(),
+            }
+        };
+        let mut tokens = text.split_whitespace();
+        let vec3 = |tokens: &mut ::std::str::SplitWhitespace| {
+            let mut v = [0.0; 3];
+            for x in &mut v {
+                *x = tokens.next()
+                    .and_then(|t| t.parse().ok())
+                    .ok_or_else(|| syntax("expected three numbers"))?;
+            }
+            Ok(Vec3(v[0], v[1], v[2]))
+        };
+        match tokens.next() {
+            Some("solid") => {
+                name.get_or_insert_with(|| text.trim()["solid".len()..].trim().to_string());
+            }
+            Some("facet") => {
+                if tokens.next() != Some("normal") {
+                    return Err(syntax("expected \"facet normal\""));
+                }
+                normal = vec3(&mut tokens)?;
+                vertices.clear();
+            }
+            Some("vertex") => vertices.push(vec3(&mut tokens)?),
+            Some("endfacet") => {
+                if vertices.len() < 3 {
+                    return Err(syntax("facet has fewer than three vertices"));
+                }
+                // Some exporters write polygons, which are split into fans.
+                for k in 1..vertices.len() - 1 {
+                    facets.push((normal, [vertices[0], vertices[k], vertices[k + 1]]));
+                }
+                vertices.clear();
+            }
+            Some("outer") | Some("endloop") | Some("endsolid") | None => {}
+            Some(other) => return Err(syntax(&format!("unexpected {:?}", other))),
+        }
+    }
+    Ok(Stl {
+        name: name.unwrap_or_default(),
+        format: StlFormat::Ascii,
+        units: Units::MM,
+        mesh: build(facets),
+    })
+}
+
+fn parse_binary(data: &[u8]) -> Result<Stl, StlError> {
+    let count = data.get(80..84).ok_or(StlError::Truncated)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    let body = data.get(84..84 + count.checked_mul(50).ok_or(StlError::Truncated)?).ok_or(StlError::Truncated)?;
+    let float = |b: &[u8], i: usize| f32::from_le_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+    let vec3 = |b: &[u8], i: usize| Vec3(float(b, i), float(b, i + 4), float(b, i + 8));
+    let facets = body.chunks(50)
+        .map(|b| (vec3(b, 0), [vec3(b, 12), vec3(b, 24), vec3(b, 36)]))
+        .collect();
+    let header = String::from_utf8_lossy(&data[..80]);
+    Ok(Stl {
+        name: header.trim_end_matches(|c: char| c == '\0' || c.is_whitespace()).to_string(),
+        format: StlFormat::Binary,
+        units: Units::MM,
+        mesh: build(facets),
+    })
+}
+
+/// write_stl writes the triangles of the model in its format, with normals
+/// computed from their vertices. Other vertex attributes are discarded.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_gfx::math::Vec3;
+/// use fiz_gfx::Mesh;
+/// use fiz_gfx::stl::{parse_stl, write_stl, Stl, StlFormat};
+///
+/// let mesh = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)], vec![[0, 1, 2]]);
+/// let mut out = Vec::new();
+/// write_stl(&mut out, &Stl::new("tri", StlFormat::Binary, mesh)).unwrap();
+/// assert_eq!(out.len(), 84 + 50);
+/// assert_eq!(parse_stl(&out).unwrap().mesh.triangle(0)[1], Vec3(1.0, 0.0, 0.0));
+/// ```
+pub fn write_stl<W: Write>(w: &mut W, stl: &Stl) -> io::Result<()> {
+    let mesh = &stl.mesh;
+    match stl.format {
+        StlFormat::Ascii => {
+            writeln!(w, "solid {}", stl.name)?;
+            for i in 0..mesh.triangle_count() {
+                let v = mesh.triangle(i);
+                let n = facet_normal(&v);
+                writeln!(w, "  facet normal {} {} {}", n.0, n.1, n.2)?;
+                writeln!(w, "    outer loop")?;
+                for p in &v {
+                    writeln!(w, "      vertex {} {} {}", p.0, p.1, p.2)?;
+                }
+                writeln!(w, "    endloop")?;
+                writeln!(w, "  endfacet")?;
+            }
+            writeln!(w, "endsolid {}", stl.name)
+        }
+        StlFormat::Binary => {
+            let mut header = [0u8; 80];
+            let name = stl.name.as_bytes();
+            let len = name.len().min(80);
+            header[..len].copy_from_slice(&name[..len]);
+            w.write_all(&header)?;
+            w.write_all(&(mesh.triangle_count() as u32).to_le_bytes())?;
+            for i in 0..mesh.triangle_count() {
+                let v = mesh.triangle(i);
+                for p in [facet_normal(&v), v[0], v[1], v[2]].iter() {
+                    for x in &[p.0, p.1, p.2] {
+                        w.write_all(&x.to_le_bytes())?;
+                    }
+                }
+                w.write_all(&[0, 0])?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use math::Vec3;
+    use super::super::Mesh;
+    use super::{parse_stl, write_stl, Stl, StlFormat};
+
+    #[test]
+    fn round_trip() {
+        let mesh = Mesh::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.5, 0.0, 0.0), Vec3(0.0, 2.0, 0.0), Vec3(0.0, 0.0, -3.25)],
+                             vec![[0, 1, 2], [0, 3, 1]]);
+        for &format in &[StlFormat::Ascii, StlFormat::Binary] {
+            // A binary header starting with "solid" mustn't be mistaken for
+            // an ASCII file.
+            let stl = Stl::new("solid part", format, mesh.clone());
+            let mut out = Vec::new();
+            write_stl(&mut out, &stl).unwrap();
+            let got = parse_stl(&out).unwrap();
+            assert_eq!((got.name.as_str(), got.format), ("solid part", format));
+            assert_eq!(got.mesh.triangle_count(), 2);
+            for i in 0..2 {
+                assert_eq!(got.mesh.triangle(i), mesh.triangle(i));
+            }
+            let normals = got.mesh.normals.unwrap();
+            assert_eq!(normals[0], Vec3(0.0, 0.0, 1.0));
+            assert_eq!(normals[3], Vec3(0.0, -1.0, 0.0));
+        }
+    }
+}
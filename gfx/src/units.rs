@@ -0,0 +1,93 @@
+use math::unit::{MM, CM, M, KM, ToM};
+
+/// Units are the units of length of the coordinates of a model file, which
+/// correspond to the length types of `fiz_math::unit`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Units {
+    /// Millimeters, see `unit::MM`.
+    MM,
+
+    /// Centimeters, see `unit::CM`.
+    CM,
+
+    /// Meters, see `unit::M`.
+    M,
+
+    /// Kilometers, see `unit::KM`.
+    KM,
+}
+
+impl Units {
+    /// from_name returns the units with the given name or symbol, such as
+    /// `mm` or `meters`, ignoring case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::Units;
+    ///
+    /// assert_eq!(Units::from_name("Millimeters"), Some(Units::MM));
+    /// assert_eq!(Units::from_name("m"), Some(Units::M));
+    /// assert_eq!(Units::from_name("furlongs"), None);
+    /// assert_eq!(Units::from_name("ms"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Units> {
+        match name.to_lowercase().as_str() {
+            "mm" | "millimeter" | "millimeters" | "millimetre" | "millimetres" => Some(Units::MM),
+            "cm" | "centimeter" | "centimeters" | "centimetre" | "centimetres" => Some(Units::CM),
+            "m" | "meter" | "meters" | "metre" | "metres" => Some(Units::M),
+            "km" | "kilometer" | "kilometers" | "kilometre" | "kilometres" => Some(Units::KM),
+            _ => None,
+        }
+    }
+
+    /// name returns the symbol of the units, e.g. `mm`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Units::MM => "mm",
+            Units::CM => "cm",
+            Units::M => "m",
+            Units::KM => "km",
+        }
+    }
+
+    /// to_m returns the length of one of these units in meters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::Units;
+    /// use fiz_gfx::math::unit::M;
+    ///
+    /// assert_eq!(Units::CM.to_m(), M(0.01));
+    /// ```
+    pub fn to_m(self) -> M<f32> {
+        M(self.meters() as f32)
+    }
+
+    // meters returns the length of one of these units in meters, in double
+    // precision so that ratios of units round well.
+    fn meters(self) -> f64 {
+        match self {
+            Units::MM => MM(1.0).to_m().0,
+            Units::CM => CM(1.0).to_m().0,
+            Units::M => 1.0,
+            Units::KM => KM(1.0).to_m().0,
+        }
+    }
+
+    /// scale returns the factor which converts lengths in these units to the
+    /// given units.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_gfx::Units;
+    ///
+    /// assert_eq!(Units::MM.scale(Units::M), 0.001);
+    /// assert_eq!(Units::KM.scale(Units::CM), 100000.0);
+    /// ```
+    pub fn scale(self, to: Units) -> f32 {
+        (self.meters() / to.meters()) as f32
+    }
+}
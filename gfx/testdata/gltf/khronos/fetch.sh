@@ -0,0 +1,25 @@
+#!/bin/sh
+# fetch.sh downloads the Khronos sample models tested by the gltf module,
+# with their license files, from https://github.com/KhronosGroup/glTF-Sample-Assets
+# into this directory, laid out as in that repository.
+#
+# Usage: ./fetch.sh [ref]
+set -eu
+
+ref=${1:-main}
+base="https://raw.githubusercontent.com/KhronosGroup/glTF-Sample-Assets/$ref/Models"
+cd "$(dirname "$0")"
+
+for model in BoxTextured SimpleSkin SimpleSparseAccessor TextureTransformTest; do
+    mkdir -p "$model/glTF"
+    curl -fsSL -o "$model/LICENSE.md" "$base/$model/LICENSE.md"
+    curl -fsSL -o "$model/glTF/$model.gltf" "$base/$model/glTF/$model.gltf"
+
+    # Fetch the buffers and images the model refers to, other than those
+    # embedded as data URIs.
+    grep -o '"uri" *: *"[^"]*"' "$model/glTF/$model.gltf" | sed 's/.*"\([^"]*\)"$/\1/' | grep -v '^data:' |
+        while read -r uri; do
+            mkdir -p "$(dirname "$model/glTF/$uri")"
+            curl -fsSL -o "$model/glTF/$uri" "$base/$model/glTF/$uri"
+        done
+done
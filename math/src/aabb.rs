@@ -0,0 +1,201 @@
+use super::float::Float;
+use super::Vec3;
+
+/// Aabb is a three-dimensional axis-aligned bounding box, described by its
+/// minimum and maximum corners.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Aabb, Vec3};
+///
+/// let a = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(2.0, 2.0, 2.0));
+/// let b = Aabb::new(Vec3(1.0, 1.0, 1.0), Vec3(3.0, 3.0, 3.0));
+/// assert!(a.intersects(&b));
+/// assert_eq!(a.union(&b), Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(3.0, 3.0, 3.0)));
+/// assert_eq!(a.intersection(&b), Some(Aabb::new(Vec3(1.0, 1.0, 1.0), Vec3(2.0, 2.0, 2.0))));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb<T> {
+    /// The minimum corner of the box.
+    pub min: Vec3<T>,
+
+    /// The maximum corner of the box.
+    pub max: Vec3<T>,
+}
+
+impl<T: PartialOrd + Copy> Aabb<T> {
+    /// new returns a new box with the given corners, which may be given in
+    /// any order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::new(Vec3(1, 5, 0), Vec3(2, 0, 3));
+    /// assert_eq!(a.min, Vec3(1, 0, 0));
+    /// assert_eq!(a.max, Vec3(2, 5, 3));
+    /// ```
+    pub fn new(a: Vec3<T>, b: Vec3<T>) -> Self {
+        Aabb {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// from_points returns the smallest box containing all of the points, or
+    /// None if there are none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::from_points(&[Vec3(1, 2, 3), Vec3(-1, 5, 0), Vec3(0, 0, 0)]).unwrap();
+    /// assert_eq!(a, Aabb::new(Vec3(-1, 0, 0), Vec3(1, 5, 3)));
+    /// ```
+    pub fn from_points(points: &[Vec3<T>]) -> Option<Self> {
+        let first = points.first()?;
+        Some(points[1..].iter().fold(Aabb::new(*first, *first), |a, &p| a.include(p)))
+    }
+
+    /// include returns the smallest box containing both this box and p.
+    pub fn include(&self, p: Vec3<T>) -> Self {
+        Aabb {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+
+    /// union returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// intersection returns the box where both boxes overlap, or None if they
+    /// don't.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        if self.intersects(other) {
+            Some(Aabb {
+                min: self.min.max(other.min),
+                max: self.max.min(other.max),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// intersects tells if the boxes overlap. Boxes which only touch are
+    /// considered to overlap.
+    pub fn intersects(&self, other: &Self) -> bool {
+        !self.max.any_less(other.min) && !other.max.any_less(self.min)
+    }
+
+    /// contains tells if the point lies within the box (or on its boundary).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::new(Vec3(0, 0, 0), Vec3(2, 2, 2));
+    /// assert!(a.contains(Vec3(1, 2, 0)));
+    /// assert!(!a.contains(Vec3(1, 3, 0)));
+    /// ```
+    pub fn contains(&self, p: Vec3<T>) -> bool {
+        !p.any_less(self.min) && !p.any_greater(self.max)
+    }
+
+    /// contains_aabb tells if the other box lies entirely within this one.
+    pub fn contains_aabb(&self, other: &Self) -> bool {
+        self.contains(other.min) && self.contains(other.max)
+    }
+}
+
+impl<T: Float> Aabb<T> {
+    /// center returns the point at the center of the box.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(2.0, 4.0, 6.0));
+    /// assert_eq!(a.center(), Vec3(1.0, 2.0, 3.0));
+    /// ```
+    pub fn center(&self) -> Vec3<T> {
+        (self.min + self.max).div_scalar(T::one() + T::one())
+    }
+
+    /// size returns the size of the box along each axis.
+    pub fn size(&self) -> Vec3<T> {
+        self.max - self.min
+    }
+
+    /// surface_area returns the surface area of the box.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 2.0, 3.0));
+    /// assert_eq!(a.surface_area(), 22.0);
+    /// ```
+    pub fn surface_area(&self) -> T {
+        let s = self.size();
+        (s.0 * s.1 + s.1 * s.2 + s.2 * s.0) * (T::one() + T::one())
+    }
+
+    /// volume returns the volume of the box.
+    pub fn volume(&self) -> T {
+        let s = self.size();
+        s.0 * s.1 * s.2
+    }
+
+    /// expand returns the box grown by the given margin on every side.
+    pub fn expand(&self, margin: T) -> Self {
+        Aabb {
+            min: self.min.sub_scalar(margin),
+            max: self.max.add_scalar(margin),
+        }
+    }
+
+    /// closest_point returns the point of the box closest to p.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    ///
+    /// let a = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+    /// assert_eq!(a.closest_point(Vec3(2.0, 0.5, -1.0)), Vec3(1.0, 0.5, 0.0));
+    /// assert_eq!(a.distance_sq(Vec3(2.0, 0.5, -1.0)), 2.0);
+    /// ```
+    pub fn closest_point(&self, p: Vec3<T>) -> Vec3<T> {
+        p.max(self.min).min(self.max)
+    }
+
+    /// distance_sq returns the squared distance from p to the box, which is
+    /// zero if p lies inside it.
+    pub fn distance_sq(&self, p: Vec3<T>) -> T {
+        (self.closest_point(p) - p).length_sq()
+    }
+
+    /// corners returns the eight corners of the box.
+    pub fn corners(&self) -> [Vec3<T>; 8] {
+        let (a, b) = (self.min, self.max);
+        [Vec3(a.0, a.1, a.2),
+         Vec3(b.0, a.1, a.2),
+         Vec3(a.0, b.1, a.2),
+         Vec3(b.0, b.1, a.2),
+         Vec3(a.0, a.1, b.2),
+         Vec3(b.0, a.1, b.2),
+         Vec3(a.0, b.1, b.2),
+         Vec3(b.0, b.1, b.2)]
+    }
+}
@@ -0,0 +1,889 @@
+//! Bounding volume hierarchies.
+//!
+//! A `Bvh` is a binary tree of axis-aligned bounding boxes over a set of user
+//! items, which accelerates ray casts, overlap tests, frustum culling and
+//! nearest neighbor searches. Trees built up front use the surface area
+//! heuristic (SAH) to choose their splits, and items may then be inserted,
+//! removed or moved incrementally.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use num::NumCast;
+use super::float::Float;
+use super::{Vec3, Aabb, Ray, Frustum, Containment};
+
+// The number of bins used when searching for the best SAH split.
+const BINS: usize = 12;
+
+/// ItemId identifies an item stored in a `Bvh`, or in one of the structures
+/// of the `spatial` module. Identifiers remain valid until their item is
+/// removed, after which they may be reused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ItemId(pub(crate) usize);
+
+#[derive(Copy, Clone, Debug)]
+enum Kind {
+    // A leaf node, holding the index of a single entry.
+    Leaf(usize),
+
+    // A branch node, holding the indices of its two children.
+    Branch(usize, usize),
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    bounds: Aabb<T>,
+    parent: Option<usize>,
+    kind: Kind,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T, I> {
+    bounds: Aabb<T>,
+    item: I,
+    leaf: usize,
+}
+
+/// Bvh is a bounding volume hierarchy holding items of type I, each with an
+/// axis-aligned bounding box.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Aabb, Ray, Vec3};
+/// use fiz_math::bvh::Bvh;
+///
+/// let cube = |x: f64| Aabb::new(Vec3(x, 0.0, 0.0), Vec3(x + 1.0, 1.0, 1.0));
+/// let mut bvh = Bvh::build(vec![(cube(0.0), "a"), (cube(2.0), "b"), (cube(4.0), "c")]);
+///
+/// // Cast a ray along +x through the cubes, using their boxes as the exact
+/// // shapes.
+/// let ray = Ray::new(Vec3(-1.0, 0.5, 0.5), Vec3(1.0, 0.0, 0.0));
+/// let (id, t) = bvh.raycast(&ray, 100.0, |_, &name| ray.intersect_aabb(&cube(match name {
+///     "a" => 0.0,
+///     "b" => 2.0,
+///     _ => 4.0,
+/// }), 100.0)).unwrap();
+/// assert_eq!(bvh[id], "a");
+/// assert_eq!(t, 1.0);
+///
+/// // Move "a" out of the way.
+/// bvh.set_bounds(id, Aabb::new(Vec3(0.0, 5.0, 0.0), Vec3(1.0, 6.0, 1.0)));
+/// let hits = bvh.query_aabb(&Aabb::new(Vec3(-10.0, 0.0, 0.0), Vec3(10.0, 1.0, 1.0)));
+/// assert_eq!(hits.len(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Bvh<T, I> {
+    nodes: Vec<Node<T>>,
+    free_nodes: Vec<usize>,
+    entries: Vec<Option<Entry<T, I>>>,
+    free_entries: Vec<usize>,
+    root: Option<usize>,
+    len: usize,
+}
+
+impl<T: Float, I> Default for Bvh<T, I> {
+    fn default() -> Self {
+        Bvh::new()
+    }
+}
+
+impl<T: Float, I> Bvh<T, I> {
+    /// new returns a new empty tree.
+    pub fn new() -> Self {
+        Bvh {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            entries: Vec::new(),
+            free_entries: Vec::new(),
+            root: None,
+            len: 0,
+        }
+    }
+
+    /// build returns a tree holding the given items, built top-down using the
+    /// surface area heuristic. The items are given identifiers in order,
+    /// i.e. the first item's identifier is the smallest.
+    pub fn build(items: Vec<(Aabb<T>, I)>) -> Self {
+        let mut bvh = Bvh::new();
+        bvh.len = items.len();
+        bvh.entries = items.into_iter()
+            .map(|(bounds, item)| {
+                Some(Entry {
+                    bounds,
+                    item,
+                    leaf: 0,
+                })
+            })
+            .collect();
+        bvh.rebuild();
+        bvh
+    }
+
+    /// rebuild rebuilds the entire tree using the surface area heuristic,
+    /// which may improve query performance after many incremental updates.
+    /// Item identifiers are unchanged.
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        self.free_nodes.clear();
+        let mut ids: Vec<usize> = (0..self.entries.len()).filter(|&i| self.entries[i].is_some()).collect();
+        self.root = if ids.is_empty() {
+            None
+        } else {
+            Some(self.build_range(&mut ids, None))
+        };
+    }
+
+    /// len returns the number of items in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// is_empty tells if the tree holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// bounds returns the bounds of the whole tree, or None if it is empty.
+    pub fn bounds(&self) -> Option<Aabb<T>> {
+        self.root.map(|r| self.nodes[r].bounds)
+    }
+
+    /// get returns a reference to the item, or None if it was removed.
+    pub fn get(&self, id: ItemId) -> Option<&I> {
+        self.entry(id).map(|e| &e.item)
+    }
+
+    /// get_mut returns a mutable reference to the item, or None if it was
+    /// removed.
+    pub fn get_mut(&mut self, id: ItemId) -> Option<&mut I> {
+        self.entries.get_mut(id.0).and_then(|e| e.as_mut()).map(|e| &mut e.item)
+    }
+
+    /// item_bounds returns the bounds of the item, or None if it was removed.
+    pub fn item_bounds(&self, id: ItemId) -> Option<Aabb<T>> {
+        self.entry(id).map(|e| e.bounds)
+    }
+
+    /// iter returns an iterator over the items in the tree, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &I)> {
+        self.entries.iter().enumerate().filter_map(|(i, e)| e.as_ref().map(|e| (ItemId(i), &e.item)))
+    }
+
+    /// insert adds an item with the given bounds to the tree, returning its
+    /// identifier. The item is placed next to the existing node which
+    /// minimizes the increase in surface area of the tree.
+    pub fn insert(&mut self, bounds: Aabb<T>, item: I) -> ItemId {
+        let entry = Entry {
+            bounds,
+            item,
+            leaf: 0,
+        };
+        let index = match self.free_entries.pop() {
+            Some(i) => {
+                self.entries[i] = Some(entry);
+                i
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+        self.len += 1;
+        let leaf = self.alloc(Node {
+            bounds,
+            parent: None,
+            kind: Kind::Leaf(index),
+        });
+        self.entries[index].as_mut().unwrap().leaf = leaf;
+        self.insert_leaf(leaf);
+        ItemId(index)
+    }
+
+    /// remove removes the item from the tree, returning it, or None if it was
+    /// already removed.
+    pub fn remove(&mut self, id: ItemId) -> Option<I> {
+        let entry = self.entries.get_mut(id.0)?.take()?;
+        self.free_entries.push(id.0);
+        self.len -= 1;
+        self.remove_leaf(entry.leaf);
+        self.free_nodes.push(entry.leaf);
+        Some(entry.item)
+    }
+
+    /// set_bounds changes the bounds of the item, refitting only the nodes
+    /// above it. Large movements can leave the tree poorly structured, in
+    /// which case reinsert moves the item to a better place.
+    ///
+    /// # Panics
+    ///
+    /// set_bounds panics if the item was removed.
+    pub fn set_bounds(&mut self, id: ItemId, bounds: Aabb<T>) {
+        let leaf = {
+            let e = self.entries[id.0].as_mut().expect("Bvh::set_bounds: item was removed");
+            e.bounds = bounds;
+            e.leaf
+        };
+        self.nodes[leaf].bounds = bounds;
+        if let Some(p) = self.nodes[leaf].parent {
+            self.refit_up(p);
+        }
+    }
+
+    /// reinsert changes the bounds of the item, and moves it to the best
+    /// place in the tree for its new bounds.
+    ///
+    /// # Panics
+    ///
+    /// reinsert panics if the item was removed.
+    pub fn reinsert(&mut self, id: ItemId, bounds: Aabb<T>) {
+        let leaf = {
+            let e = self.entries[id.0].as_mut().expect("Bvh::reinsert: item was removed");
+            e.bounds = bounds;
+            e.leaf
+        };
+        self.remove_leaf(leaf);
+        self.nodes[leaf].bounds = bounds;
+        self.nodes[leaf].parent = None;
+        self.insert_leaf(leaf);
+    }
+
+    /// refit recomputes the bounds of every item using the given function,
+    /// and then the bounds of every node, without changing the structure of
+    /// the tree. This is cheaper than rebuilding, and works well when items
+    /// move coherently (e.g. an animated mesh).
+    pub fn refit<F>(&mut self, mut bounds: F)
+        where F: FnMut(ItemId, &I) -> Aabb<T>
+    {
+        for (i, e) in self.entries.iter_mut().enumerate() {
+            if let Some(ref mut e) = *e {
+                e.bounds = bounds(ItemId(i), &e.item);
+            }
+        }
+
+        // Visit nodes in depth-first order, then fix them up in reverse so
+        // that children are always handled before their parents.
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(n) = stack.pop() {
+            order.push(n);
+            if let Kind::Branch(l, r) = self.nodes[n].kind {
+                stack.push(l);
+                stack.push(r);
+            }
+        }
+        for &n in order.iter().rev() {
+            self.nodes[n].bounds = match self.nodes[n].kind {
+                Kind::Leaf(e) => self.entries[e].as_ref().unwrap().bounds,
+                Kind::Branch(l, r) => self.nodes[l].bounds.union(&self.nodes[r].bounds),
+            };
+        }
+    }
+
+    /// raycast returns the closest item hit by the ray within the parametric
+    /// distance t_max, along with the distance at which it was hit.
+    ///
+    /// The hit function performs the exact intersection test against an item
+    /// whose bounds the ray passes through, returning the distance along the
+    /// ray of the hit (if any). Items are visited roughly front to back, and
+    /// subtrees beyond the closest hit found so far are skipped.
+    pub fn raycast<F>(&self, ray: &Ray<T>, t_max: T, mut hit: F) -> Option<(ItemId, T)>
+        where F: FnMut(ItemId, &I) -> Option<T>
+    {
+        let mut best: Option<(ItemId, T)> = None;
+        let mut limit = t_max;
+        let mut stack: Vec<(usize, T)> = Vec::new();
+        if let Some(r) = self.root {
+            if let Some(t) = ray.intersect_aabb(&self.nodes[r].bounds, limit) {
+                stack.push((r, t));
+            }
+        }
+        while let Some((n, t)) = stack.pop() {
+            if t > limit {
+                continue;
+            }
+            match self.nodes[n].kind {
+                Kind::Leaf(e) => {
+                    if let Some(t) = hit(ItemId(e), &self.entries[e].as_ref().unwrap().item) {
+                        if t <= limit {
+                            limit = t;
+                            best = Some((ItemId(e), t));
+                        }
+                    }
+                }
+                Kind::Branch(l, r) => {
+                    let tl = ray.intersect_aabb(&self.nodes[l].bounds, limit);
+                    let tr = ray.intersect_aabb(&self.nodes[r].bounds, limit);
+                    // Push the nearer child last, so that it is visited first.
+                    match (tl, tr) {
+                        (Some(a), Some(b)) if a <= b => stack.extend_from_slice(&[(r, b), (l, a)]),
+                        (Some(a), Some(b)) => stack.extend_from_slice(&[(l, a), (r, b)]),
+                        (Some(a), None) => stack.push((l, a)),
+                        (None, Some(b)) => stack.push((r, b)),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// raycast_any returns any item hit by the ray within the parametric
+    /// distance t_max, stopping at the first hit found. It is cheaper than
+    /// raycast when only the existence of a hit matters (e.g. for shadow or
+    /// line-of-sight tests). The hit function is as described for raycast.
+    pub fn raycast_any<F>(&self, ray: &Ray<T>, t_max: T, mut hit: F) -> Option<(ItemId, T)>
+        where F: FnMut(ItemId, &I) -> Option<T>
+    {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(n) = stack.pop() {
+            if ray.intersect_aabb(&self.nodes[n].bounds, t_max).is_none() {
+                continue;
+            }
+            match self.nodes[n].kind {
+                Kind::Leaf(e) => {
+                    if let Some(t) = hit(ItemId(e), &self.entries[e].as_ref().unwrap().item) {
+                        if t <= t_max {
+                            return Some((ItemId(e), t));
+                        }
+                    }
+                }
+                Kind::Branch(l, r) => stack.extend_from_slice(&[l, r]),
+            }
+        }
+        None
+    }
+
+    /// query_aabb returns the items whose bounds overlap the given box.
+    pub fn query_aabb(&self, b: &Aabb<T>) -> Vec<ItemId> {
+        let mut out = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if !node.bounds.intersects(b) {
+                continue;
+            }
+            match node.kind {
+                Kind::Leaf(e) => out.push(ItemId(e)),
+                Kind::Branch(l, r) => stack.extend_from_slice(&[l, r]),
+            }
+        }
+        out
+    }
+
+    /// cull returns the items whose bounds lie at least partially within the
+    /// frustum. Subtrees entirely within the frustum are accepted without
+    /// further tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Frustum, Vec3};
+    /// use fiz_math::bvh::Bvh;
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let items = (0..10).map(|i| {
+    ///     let x = i as f32 * 4.0 - 20.0;
+    ///     (Aabb::new(Vec3(x, -1.0, -10.5), Vec3(x + 1.0, 1.0, -9.5)), i)
+    /// }).collect();
+    /// let bvh = Bvh::build(items);
+    /// let camera = Frustum::perspective(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0),
+    ///                                   Vec3(0.0, 1.0, 0.0), Deg(90.0), 1.0, 0.1, 100.0);
+    /// let mut visible: Vec<i32> = bvh.cull(&camera).into_iter().map(|id| bvh[id]).collect();
+    /// visible.sort();
+    /// assert_eq!(visible, vec![3, 4, 5, 6, 7]);
+    /// ```
+    pub fn cull(&self, frustum: &Frustum<T>) -> Vec<ItemId> {
+        let mut out = Vec::new();
+        let mut stack: Vec<(usize, bool)> = self.root.into_iter().map(|r| (r, false)).collect();
+        while let Some((n, inside)) = stack.pop() {
+            let node = &self.nodes[n];
+            let inside = inside ||
+                         match frustum.classify(&node.bounds) {
+                Containment::Outside => continue,
+                Containment::Intersecting => false,
+                Containment::Inside => true,
+            };
+            match node.kind {
+                Kind::Leaf(e) => out.push(ItemId(e)),
+                Kind::Branch(l, r) => stack.extend_from_slice(&[(l, inside), (r, inside)]),
+            }
+        }
+        out
+    }
+
+    /// nearest returns the item nearest to the point p, along with its
+    /// squared distance.
+    ///
+    /// The distance_sq function returns the exact squared distance from p to
+    /// an item; it must never be less than the squared distance from p to the
+    /// item's bounds. Nodes are searched in order of the distance to their
+    /// bounds, so only items near p are tested.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Vec3};
+    /// use fiz_math::bvh::Bvh;
+    ///
+    /// let points = vec![Vec3(0.0, 0.0, 0.0), Vec3(5.0, 5.0, 5.0), Vec3(1.0, 2.0, 2.0)];
+    /// let bvh = Bvh::build(points.iter().map(|&p| (Aabb::new(p, p), p)).collect());
+    /// let (id, d) = bvh.nearest(Vec3(1.0, 1.0, 1.0), |_, &q| (q - Vec3(1.0, 1.0, 1.0)).length_sq()).unwrap();
+    /// assert_eq!(bvh[id], Vec3(1.0, 2.0, 2.0));
+    /// assert_eq!(d, 2.0);
+    /// ```
+    pub fn nearest<F>(&self, p: Vec3<T>, mut distance_sq: F) -> Option<(ItemId, T)>
+        where F: FnMut(ItemId, &I) -> T
+    {
+        let mut best: Option<(ItemId, T)> = None;
+        let mut heap = BinaryHeap::new();
+        if let Some(r) = self.root {
+            heap.push(Candidate {
+                distance: self.nodes[r].bounds.distance_sq(p),
+                node: r,
+            });
+        }
+        while let Some(Candidate { distance, node }) = heap.pop() {
+            if best.is_some_and(|b| distance >= b.1) {
+                break;
+            }
+            match self.nodes[node].kind {
+                Kind::Leaf(e) => {
+                    let d = distance_sq(ItemId(e), &self.entries[e].as_ref().unwrap().item);
+                    if best.is_none_or(|b| d < b.1) {
+                        best = Some((ItemId(e), d));
+                    }
+                }
+                Kind::Branch(l, r) => {
+                    for &c in &[l, r] {
+                        heap.push(Candidate {
+                            distance: self.nodes[c].bounds.distance_sq(p),
+                            node: c,
+                        });
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    // entry returns the entry of the item, if it exists.
+    fn entry(&self, id: ItemId) -> Option<&Entry<T, I>> {
+        self.entries.get(id.0).and_then(|e| e.as_ref())
+    }
+
+    // alloc stores the node, reusing a free slot if possible.
+    fn alloc(&mut self, node: Node<T>) -> usize {
+        match self.free_nodes.pop() {
+            Some(i) => {
+                self.nodes[i] = node;
+                i
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    // build_range builds a subtree over the given entries, returning its root.
+    fn build_range(&mut self, ids: &mut [usize], parent: Option<usize>) -> usize {
+        if ids.len() == 1 {
+            let e = ids[0];
+            let bounds = self.entries[e].as_ref().unwrap().bounds;
+            let n = self.alloc(Node {
+                bounds,
+                parent,
+                kind: Kind::Leaf(e),
+            });
+            self.entries[e].as_mut().unwrap().leaf = n;
+            return n;
+        }
+
+        let mid = self.split(ids);
+        let n = self.alloc(Node {
+            bounds: self.entries[ids[0]].as_ref().unwrap().bounds,
+            parent,
+            kind: Kind::Leaf(ids[0]),
+        });
+        let (left, right) = ids.split_at_mut(mid);
+        let l = self.build_range(left, Some(n));
+        let r = self.build_range(right, Some(n));
+        self.nodes[n].bounds = self.nodes[l].bounds.union(&self.nodes[r].bounds);
+        self.nodes[n].kind = Kind::Branch(l, r);
+        n
+    }
+
+    // split partitions the entries (of which there are at least two) into
+    // two non-empty groups, returning the size of the first. The split plane
+    // is chosen by binning the entries' centroids along the axis of largest
+    // extent and minimizing the surface area heuristic.
+    fn split(&self, ids: &mut [usize]) -> usize {
+        let bounds = |e: usize| self.entries[e].as_ref().unwrap().bounds;
+        let centroid = |e: usize, axis: usize| {
+            let c = bounds(e).center();
+            match axis {
+                0 => c.0,
+                1 => c.1,
+                _ => c.2,
+            }
+        };
+        let c0 = bounds(ids[0]).center();
+        let cb = ids.iter().fold(Aabb::new(c0, c0), |b, &e| b.include(bounds(e).center()));
+        let extent = cb.size();
+        let axis = if extent.0 >= extent.1 && extent.0 >= extent.2 {
+            0
+        } else if extent.1 >= extent.2 {
+            1
+        } else {
+            2
+        };
+        let (lo, width) = match axis {
+            0 => (cb.min.0, extent.0),
+            1 => (cb.min.1, extent.1),
+            _ => (cb.min.2, extent.2),
+        };
+
+        let mut split = None;
+        if width > T::zero() {
+            let scale = <T as NumCast>::from(BINS).unwrap() / width;
+            let bin = |e: usize| {
+                let b = ((centroid(e, axis) - lo) * scale).to_usize().unwrap_or(0);
+                if b >= BINS { BINS - 1 } else { b }
+            };
+            let mut counts = [0usize; BINS];
+            let mut boxes: [Option<Aabb<T>>; BINS] = [None; BINS];
+            for &e in ids.iter() {
+                let b = bin(e);
+                counts[b] += 1;
+                boxes[b] = Some(boxes[b].map_or(bounds(e), |x| x.union(&bounds(e))));
+            }
+
+            // Sweep from the right to get the cost of every right side, then
+            // from the left to find the cheapest split.
+            let merge = |a: Option<Aabb<T>>, b: Option<Aabb<T>>| match (a, b) {
+                (Some(a), Some(b)) => Some(a.union(&b)),
+                (a, None) => a,
+                (None, b) => b,
+            };
+            let mut right_cost = [T::zero(); BINS];
+            let (mut acc, mut count) = (None, 0);
+            for k in (1..BINS).rev() {
+                acc = merge(acc, boxes[k]);
+                count += counts[k];
+                right_cost[k] = acc.map_or(T::zero(), |b: Aabb<T>| b.surface_area()) *
+                                <T as NumCast>::from(count).unwrap();
+            }
+            let (mut acc, mut count) = (None, 0);
+            let mut best: Option<(T, usize)> = None;
+            for k in 1..BINS {
+                acc = merge(acc, boxes[k - 1]);
+                count += counts[k - 1];
+                if count == 0 || count == ids.len() {
+                    continue;
+                }
+                let cost = acc.map_or(T::zero(), |b: Aabb<T>| b.surface_area()) *
+                           <T as NumCast>::from(count).unwrap() + right_cost[k];
+                if best.is_none_or(|b| cost < b.0) {
+                    best = Some((cost, k));
+                }
+            }
+            if let Some((_, k)) = best {
+                // Partition in place by bin.
+                let mut i = 0;
+                for j in 0..ids.len() {
+                    if bin(ids[j]) < k {
+                        ids.swap(i, j);
+                        i += 1;
+                    }
+                }
+                split = Some(i);
+            }
+        }
+
+        split.unwrap_or_else(|| {
+            // All centroids coincide (or binning failed to separate them), so
+            // split evenly.
+            let mid = ids.len() / 2;
+            ids.sort_by(|&a, &b| centroid(a, axis).partial_cmp(&centroid(b, axis)).unwrap_or(Ordering::Equal));
+            mid
+        })
+    }
+
+    // insert_leaf links the (detached) leaf node into the tree.
+    fn insert_leaf(&mut self, leaf: usize) {
+        let root = match self.root {
+            Some(r) => r,
+            None => {
+                self.root = Some(leaf);
+                return;
+            }
+        };
+        let bounds = self.nodes[leaf].bounds;
+
+        // Descend toward the sibling which minimizes the total increase in
+        // surface area, including that inherited by the ancestors.
+        let two = T::one() + T::one();
+        let mut sibling = root;
+        while let Kind::Branch(l, r) = self.nodes[sibling].kind {
+            let area = self.nodes[sibling].bounds.surface_area();
+            let combined = self.nodes[sibling].bounds.union(&bounds).surface_area();
+            let cost = two * combined;
+            let inherited = two * (combined - area);
+            let child_cost = |c: usize| {
+                let merged = self.nodes[c].bounds.union(&bounds).surface_area();
+                match self.nodes[c].kind {
+                    Kind::Leaf(_) => merged + inherited,
+                    Kind::Branch(..) => merged - self.nodes[c].bounds.surface_area() + inherited,
+                }
+            };
+            let (cl, cr) = (child_cost(l), child_cost(r));
+            if cost < cl && cost < cr {
+                break;
+            }
+            sibling = if cl < cr { l } else { r };
+        }
+
+        // Replace the sibling with a new branch holding it and the leaf.
+        let old_parent = self.nodes[sibling].parent;
+        let branch = self.alloc(Node {
+            bounds: self.nodes[sibling].bounds.union(&bounds),
+            parent: old_parent,
+            kind: Kind::Branch(sibling, leaf),
+        });
+        self.nodes[sibling].parent = Some(branch);
+        self.nodes[leaf].parent = Some(branch);
+        match old_parent {
+            None => self.root = Some(branch),
+            Some(p) => {
+                self.replace_child(p, sibling, branch);
+                self.refit_up(p);
+            }
+        }
+    }
+
+    // remove_leaf unlinks the leaf node from the tree, freeing its parent.
+    fn remove_leaf(&mut self, leaf: usize) {
+        let parent = match self.nodes[leaf].parent {
+            Some(p) => p,
+            None => {
+                self.root = None;
+                return;
+            }
+        };
+        let sibling = match self.nodes[parent].kind {
+            Kind::Branch(l, r) => if l == leaf { r } else { l },
+            Kind::Leaf(_) => unreachable!(),
+        };
+        let grandparent = self.nodes[parent].parent;
+        self.nodes[sibling].parent = grandparent;
+        self.free_nodes.push(parent);
+        match grandparent {
+            None => self.root = Some(sibling),
+            Some(g) => {
+                self.replace_child(g, parent, sibling);
+                self.refit_up(g);
+            }
+        }
+    }
+
+    // replace_child replaces the child old of the branch node n with new.
+    fn replace_child(&mut self, n: usize, old: usize, new: usize) {
+        if let Kind::Branch(ref mut l, ref mut r) = self.nodes[n].kind {
+            if *l == old {
+                *l = new;
+            } else {
+                *r = new;
+            }
+        }
+    }
+
+    // refit_up recomputes the bounds of the branch node n and its ancestors,
+    // stopping early once a node's bounds are unchanged.
+    fn refit_up(&mut self, mut n: usize) {
+        loop {
+            if let Kind::Branch(l, r) = self.nodes[n].kind {
+                let bounds = self.nodes[l].bounds.union(&self.nodes[r].bounds);
+                if bounds == self.nodes[n].bounds {
+                    return;
+                }
+                self.nodes[n].bounds = bounds;
+            }
+            match self.nodes[n].parent {
+                Some(p) => n = p,
+                None => return,
+            }
+        }
+    }
+}
+
+impl<T: Float, I> ::std::ops::Index<ItemId> for Bvh<T, I> {
+    type Output = I;
+
+    /// index returns a reference to the item.
+    ///
+    /// # Panics
+    ///
+    /// index panics if the item was removed.
+    fn index(&self, id: ItemId) -> &I {
+        self.get(id).expect("Bvh: item was removed")
+    }
+}
+
+// A node waiting to be searched by nearest, ordered such that the node
+// closest to the query point is greatest.
+struct Candidate<T> {
+    distance: T,
+    node: usize,
+}
+
+impl<T: Float> PartialEq for Candidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Float> Eq for Candidate<T> {}
+
+impl<T: Float> PartialOrd for Candidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Float> Ord for Candidate<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.partial_cmp(&self.distance).unwrap_or(Ordering::Equal)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bvh, Kind, ItemId};
+    use {Aabb, Ray, Vec3};
+    use testing::lcg;
+
+    fn random_box(s: &mut u64) -> Aabb<f64> {
+        let p = Vec3(lcg(s) * 100.0, lcg(s) * 100.0, lcg(s) * 100.0);
+        let size = Vec3(lcg(s) * 5.0, lcg(s) * 5.0, lcg(s) * 5.0);
+        Aabb::new(p, p + size)
+    }
+
+    // check validates the structure of the tree: parent links, bounds and
+    // the leaves of every item.
+    fn check<I>(bvh: &Bvh<f64, I>) {
+        let mut count = 0;
+        let mut stack: Vec<usize> = bvh.root.into_iter().collect();
+        if let Some(r) = bvh.root {
+            assert!(bvh.nodes[r].parent.is_none());
+        }
+        while let Some(n) = stack.pop() {
+            match bvh.nodes[n].kind {
+                Kind::Leaf(e) => {
+                    let entry = bvh.entries[e].as_ref().unwrap();
+                    assert_eq!(entry.leaf, n);
+                    assert_eq!(entry.bounds, bvh.nodes[n].bounds);
+                    count += 1;
+                }
+                Kind::Branch(l, r) => {
+                    assert_eq!(bvh.nodes[l].parent, Some(n));
+                    assert_eq!(bvh.nodes[r].parent, Some(n));
+                    assert_eq!(bvh.nodes[n].bounds, bvh.nodes[l].bounds.union(&bvh.nodes[r].bounds));
+                    stack.push(l);
+                    stack.push(r);
+                }
+            }
+        }
+        assert_eq!(count, bvh.len());
+    }
+
+    #[test]
+    fn against_brute_force() {
+        let mut s = 7;
+        let boxes: Vec<Aabb<f64>> = (0..300).map(|_| random_box(&mut s)).collect();
+        let mut bvh = Bvh::build(boxes.iter().cloned().enumerate().map(|(i, b)| (b, i)).collect());
+        check(&bvh);
+
+        // Remove a third of the items, insert some more and move others.
+        let mut live: Vec<(ItemId, Aabb<f64>)> = (0..boxes.len()).map(|i| (ItemId(i), boxes[i])).collect();
+        for i in (0..boxes.len()).filter(|i| i % 3 == 0).rev() {
+            assert_eq!(bvh.remove(live[i].0), Some(i));
+            live.remove(i);
+        }
+        assert_eq!(bvh.remove(ItemId(0)), None);
+        for i in 0..50 {
+            let b = random_box(&mut s);
+            live.push((bvh.insert(b, 1000 + i), b));
+        }
+        for k in 0..40 {
+            let b = random_box(&mut s);
+            live[k * 3].1 = b;
+            if k % 2 == 0 {
+                bvh.set_bounds(live[k * 3].0, b);
+            } else {
+                bvh.reinsert(live[k * 3].0, b);
+            }
+        }
+        check(&bvh);
+        assert_eq!(bvh.len(), live.len());
+
+        for _ in 0..100 {
+            let q = random_box(&mut s).expand(10.0);
+            let mut got = bvh.query_aabb(&q);
+            got.sort();
+            let mut want: Vec<ItemId> = live.iter().filter(|l| l.1.intersects(&q)).map(|l| l.0).collect();
+            want.sort();
+            assert_eq!(got, want);
+
+            let origin = Vec3(lcg(&mut s) * 100.0, lcg(&mut s) * 100.0, -10.0);
+            let ray = Ray::new(origin, Vec3(lcg(&mut s) - 0.5, lcg(&mut s) - 0.5, 1.0));
+            let exact = |id: ItemId| ray.intersect_aabb(&bvh.item_bounds(id).unwrap(), 1000.0);
+            let got = bvh.raycast(&ray, 1000.0, |id, _| exact(id)).map(|h| h.1);
+            let want = live.iter().filter_map(|l| exact(l.0)).fold(None, |m: Option<f64>, t| {
+                Some(m.map_or(t, |m| m.min(t)))
+            });
+            assert_eq!(got, want);
+            assert_eq!(bvh.raycast_any(&ray, 1000.0, |id, _| exact(id)).is_some(), want.is_some());
+
+            let p = Vec3(lcg(&mut s) * 120.0, lcg(&mut s) * 120.0, lcg(&mut s) * 120.0);
+            let got = bvh.nearest(p, |id, _| bvh.item_bounds(id).unwrap().distance_sq(p)).unwrap().1;
+            let want = live.iter().map(|l| l.1.distance_sq(p)).fold(f64::INFINITY, f64::min);
+            assert_eq!(got, want);
+        }
+
+        // A full refit after moving every item.
+        let offset = Vec3(1.0, 2.0, 3.0);
+        bvh.refit(|id, _| {
+            let b = live.iter().find(|l| l.0 == id).unwrap().1;
+            Aabb::new(b.min + offset, b.max + offset)
+        });
+        check(&bvh);
+        bvh.rebuild();
+        check(&bvh);
+    }
+
+    #[test]
+    fn sah_separates_clusters() {
+        // Two distant clusters end up in separate subtrees of the root.
+        let mut s = 3;
+        let mut items = Vec::new();
+        for i in 0..64 {
+            let mut b = random_box(&mut s);
+            if i % 2 == 0 {
+                b = Aabb::new(b.min + Vec3(1000.0, 0.0, 0.0), b.max + Vec3(1000.0, 0.0, 0.0));
+            }
+            items.push((b, i));
+        }
+        let bvh = Bvh::build(items);
+        let root = bvh.root.unwrap();
+        if let Kind::Branch(l, r) = bvh.nodes[root].kind {
+            for &c in &[l, r] {
+                let size = bvh.nodes[c].bounds.size();
+                assert!(size.0 < 200.0);
+            }
+        } else {
+            panic!("root is a leaf");
+        }
+    }
+}
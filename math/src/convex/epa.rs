@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use super::super::float::Float;
+use super::super::{Vec3, Zero};
+use super::Support;
+use super::gjk::{Vertex, MAX_ITERATIONS, gjk, minkowski, tolerance, combine};
+
+/// Penetration describes how deeply two convex shapes overlap.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Penetration<T> {
+    /// The penetration depth, i.e. the shortest distance either shape must be
+    /// moved to separate them.
+    pub depth: T,
+
+    /// The contact normal, a unit vector pointing from the first shape toward
+    /// the second. Translating the second shape by `normal * depth` separates
+    /// the shapes.
+    pub normal: Vec3<T>,
+
+    /// The deepest point of the first shape within the second.
+    pub point_a: Vec3<T>,
+
+    /// The deepest point of the second shape within the first.
+    pub point_b: Vec3<T>,
+}
+
+/// penetration returns the penetration depth and contact normal of the two
+/// convex shapes using the expanding polytope algorithm (EPA), or None if they
+/// don't overlap.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Vec3, Float};
+/// use fiz_math::convex::{penetration, Cuboid};
+///
+/// let a = Cuboid::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+/// let b = Cuboid::new(Vec3(0.5, 1.75, 0.0), Vec3(1.0, 1.0, 1.0));
+/// let p = penetration(&a, &b).unwrap();
+/// assert!(p.depth.almost_equal(0.25, 1e-9));
+/// assert!(p.normal.almost_equal(Vec3(0.0, 1.0, 0.0), 1e-9));
+/// ```
+pub fn penetration<T, A, B>(a: &A, b: &B) -> Option<Penetration<T>>
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    let simplex = match gjk(a, b) {
+        Ok(_) => return None,
+        Err(s) => s,
+    };
+    let mut verts = enclose(a, b, simplex);
+    if verts.len() < 4 {
+        // The Minkowski difference is flat, so the shapes merely touch.
+        let normal = any_normal(&verts);
+        let p = verts[0];
+        return Some(Penetration {
+            depth: T::zero(),
+            normal,
+            point_a: p.a,
+            point_b: p.b,
+        });
+    }
+
+    // Orient the initial tetrahedron's faces outward.
+    let mut faces: Vec<Face<T>> = Vec::new();
+    for f in [[0, 1, 2, 3], [0, 3, 1, 2], [0, 2, 3, 1], [1, 3, 2, 0]].iter() {
+        let (i, j, k) = if (verts[f[3]].w - verts[f[0]].w)
+            .dot((verts[f[1]].w - verts[f[0]].w).cross(verts[f[2]].w - verts[f[0]].w)) > T::zero() {
+            (f[0], f[2], f[1])
+        } else {
+            (f[0], f[1], f[2])
+        };
+        if let Some(face) = Face::new(&verts, [i, j, k]) {
+            faces.push(face);
+        }
+    }
+
+    let tol = tolerance::<T>();
+    let mut closest = None;
+    for _ in 0..MAX_ITERATIONS {
+        let best = match faces.iter().enumerate().min_by(|x, y| {
+            x.1.distance.partial_cmp(&y.1.distance).unwrap_or(::std::cmp::Ordering::Equal)
+        }) {
+            Some((i, _)) => i,
+            None => break,
+        };
+        let (n, d) = (faces[best].normal, faces[best].distance);
+        closest = Some(faces[best].clone());
+
+        // Stop once the polytope can't be expanded meaningfully any further
+        // in the direction of its closest face.
+        let w = minkowski(a, b, n);
+        if w.w.dot(n) - d <= tol * (T::one() + d.abs()) {
+            break;
+        }
+
+        // Remove the faces visible from w, and patch the hole with faces
+        // connecting its boundary to w.
+        let mut edges = HashSet::new();
+        let mut kept = Vec::with_capacity(faces.len());
+        for f in faces.drain(..) {
+            if f.normal.dot(w.w - verts[f.v[0]].w) > T::zero() {
+                for k in 0..3 {
+                    edges.insert((f.v[k], f.v[(k + 1) % 3]));
+                }
+            } else {
+                kept.push(f);
+            }
+        }
+        faces = kept;
+        verts.push(w);
+        let wi = verts.len() - 1;
+        for &(u, v) in &edges {
+            if !edges.contains(&(v, u)) {
+                if let Some(f) = Face::new(&verts, [u, v, wi]) {
+                    faces.push(f);
+                }
+            }
+        }
+    }
+
+    let f = closest?;
+    let (n, d) = (f.normal, f.distance.max(T::zero()));
+
+    // Express the projection of the origin onto the face in barycentric
+    // coordinates, to interpolate the contact points.
+    let simplex = [verts[f.v[0]], verts[f.v[1]], verts[f.v[2]]];
+    let weights = barycentric(n.mul_scalar(d), simplex[0].w, simplex[1].w, simplex[2].w);
+    Some(Penetration {
+        depth: d,
+        normal: n,
+        point_a: combine(&simplex, &weights, |s| s.a),
+        point_b: combine(&simplex, &weights, |s| s.b),
+    })
+}
+
+// A triangular face of the expanding polytope, with its outward unit normal
+// and distance from the origin.
+#[derive(Clone, Debug)]
+struct Face<T> {
+    v: [usize; 3],
+    normal: Vec3<T>,
+    distance: T,
+}
+
+impl<T: Float> Face<T> {
+    // new returns the face, or None if it is degenerate.
+    fn new(verts: &[Vertex<T>], v: [usize; 3]) -> Option<Self> {
+        let (a, b, c) = (verts[v[0]].w, verts[v[1]].w, verts[v[2]].w);
+        let normal = (b - a).cross(c - a).normalize()?;
+        Some(Face {
+            v,
+            normal,
+            distance: normal.dot(a),
+        })
+    }
+}
+
+// enclose grows the GJK simplex, which contains the origin, into a
+// tetrahedron. Fewer than four vertices are returned if the Minkowski
+// difference is flat.
+fn enclose<T, A, B>(a: &A, b: &B, mut simplex: Vec<Vertex<T>>) -> Vec<Vertex<T>>
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    let (zero, one) = (T::zero(), T::one());
+    let axes = [Vec3(one, zero, zero), Vec3(zero, one, zero), Vec3(zero, zero, one)];
+    let tol = tolerance::<T>();
+    let distinct = |s: &[Vertex<T>], w: &Vertex<T>| s.iter().all(|x| (x.w - w.w).length_sq() > tol * tol);
+
+    while simplex.len() < 4 {
+        // Search along directions perpendicular to the current simplex (or
+        // along the axes for a single point).
+        let dirs: Vec<Vec3<T>> = match simplex.len() {
+            1 => axes.to_vec(),
+            2 => {
+                let d = simplex[1].w - simplex[0].w;
+                axes.iter().map(|&x| d.cross(x)).filter(|x| !x.is_zero()).collect()
+            }
+            _ => {
+                let n = (simplex[1].w - simplex[0].w).cross(simplex[2].w - simplex[0].w);
+                if n.is_zero() {
+                    // Collinear, drop a vertex and retry.
+                    simplex.pop();
+                    axes.iter().map(|&x| (simplex[1].w - simplex[0].w).cross(x)).collect()
+                } else {
+                    vec![n]
+                }
+            }
+        };
+        let found = dirs.iter()
+            .flat_map(|&d| vec![d, -d])
+            .map(|d| minkowski(a, b, d))
+            .find(|w| distinct(&simplex, w) && !flat(&simplex, w.w, tol));
+        match found {
+            Some(w) => simplex.push(w),
+            None => break,
+        }
+    }
+    simplex
+}
+
+// flat tells if adding p to the simplex leaves it without volume (or area,
+// or length).
+fn flat<T: Float>(simplex: &[Vertex<T>], p: Vec3<T>, tol: T) -> bool {
+    let o = simplex[0].w;
+    match simplex.len() {
+        1 => false,
+        2 => {
+            let d = simplex[1].w - o;
+            d.cross(p - o).length_sq() <= tol * tol * d.length_sq() * (p - o).length_sq()
+        }
+        _ => {
+            let n = (simplex[1].w - o).cross(simplex[2].w - o);
+            let v = n.dot(p - o);
+            v * v <= tol * tol * n.length_sq() * (p - o).length_sq()
+        }
+    }
+}
+
+// any_normal returns a unit normal for a degenerate (flat) Minkowski
+// difference.
+fn any_normal<T: Float>(verts: &[Vertex<T>]) -> Vec3<T> {
+    let (zero, one) = (T::zero(), T::one());
+    let fallback = Vec3(one, zero, zero);
+    let d = match verts.len() {
+        3 => (verts[1].w - verts[0].w).cross(verts[2].w - verts[0].w),
+        2 => (verts[1].w - verts[0].w).cross(Vec3(zero, zero, one)),
+        _ => fallback,
+    };
+    d.normalize().unwrap_or(fallback)
+}
+
+// barycentric returns the barycentric coordinates of p (which lies in the
+// plane of the triangle) with respect to the triangle a, b, c.
+fn barycentric<T: Float>(p: Vec3<T>, a: Vec3<T>, b: Vec3<T>, c: Vec3<T>) -> [T; 3] {
+    let (v0, v1, v2) = (b - a, c - a, p - a);
+    let (d00, d01, d11) = (v0.dot(v0), v0.dot(v1), v1.dot(v1));
+    let (d20, d21) = (v2.dot(v0), v2.dot(v1));
+    let denom = d00 * d11 - d01 * d01;
+    if denom == T::zero() {
+        return [T::one(), T::zero(), T::zero()];
+    }
+    let v = (d11 * d20 - d01 * d21) / denom;
+    let w = (d00 * d21 - d01 * d20) / denom;
+    [T::one() - v - w, v, w]
+}
+
+#[cfg(test)]
+mod test {
+    use super::penetration;
+    use super::super::{Sphere, Cuboid, Capsule, Cylinder, PointCloud, closest_points};
+    use {Vec3, Float};
+
+    #[test]
+    fn depth_and_normal() {
+        let a = Sphere::new(Vec3(0.0, 0.0, 0.0), 1.0);
+        let b = Sphere::new(Vec3(1.5, 0.0, 0.0), 1.0);
+        let p = penetration(&a, &b).unwrap();
+        assert!(p.depth.almost_equal(0.5, 1e-3));
+        assert!(p.normal.almost_equal(Vec3(1.0, 0.0, 0.0), 1e-2));
+        assert!(p.point_a.almost_equal(Vec3(1.0, 0.0, 0.0), 1e-2));
+        assert!(p.point_b.almost_equal(Vec3(0.5, 0.0, 0.0), 1e-2));
+
+        // Moving b along the normal by the depth separates the shapes.
+        let cube = Cuboid::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+        let cap = Capsule::new(Vec3(-3.0, 0.0, -0.7), Vec3(3.0, 0.0, -0.7), 0.5);
+        let p = penetration(&cube, &cap).unwrap();
+        assert!(p.depth.almost_equal(0.8, 1e-6));
+        assert!(p.normal.almost_equal(Vec3(0.0, 0.0, -1.0), 1e-6));
+        let moved = Capsule::new(cap.a + p.normal.mul_scalar(p.depth + 1e-6),
+                                 cap.b + p.normal.mul_scalar(p.depth + 1e-6),
+                                 0.5);
+        assert!(closest_points(&cube, &moved).is_some());
+
+        // Deep, fully contained overlap.
+        let cyl = Cylinder::new(Vec3(0.0, -0.25, 0.0), Vec3(0.0, 0.25, 0.0), 0.25);
+        let p = penetration(&cube, &cyl).unwrap();
+        assert!(p.depth.almost_equal(1.25, 1e-6));
+
+        // Touching shapes have zero depth, and separated ones none.
+        let tip = PointCloud::new(vec![Vec3(1.0, 0.0, 0.0)]);
+        assert!(penetration(&cube, &tip).unwrap().depth.almost_equal(0.0, 1e-9));
+        let far = PointCloud::new(vec![Vec3(1.1, 0.0, 0.0)]);
+        assert!(penetration(&cube, &far).is_none());
+    }
+}
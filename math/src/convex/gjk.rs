@@ -0,0 +1,357 @@
+use num::NumCast;
+use super::super::float::Float;
+use super::super::{Vec3, Zero};
+use super::Support;
+
+// The maximum number of iterations GJK and EPA perform. Both converge in a
+// handful of iterations for polytopes; curved shapes are approached linearly
+// and are cut off here.
+pub(super) const MAX_ITERATIONS: usize = 128;
+
+// A point of the Minkowski difference A - B, along with the support points of
+// A and B it was made from.
+#[derive(Copy, Clone, Debug)]
+pub(super) struct Vertex<T> {
+    pub w: Vec3<T>,
+    pub a: Vec3<T>,
+    pub b: Vec3<T>,
+}
+
+// minkowski returns the support point of A - B in the given direction.
+pub(super) fn minkowski<T, A, B>(a: &A, b: &B, direction: Vec3<T>) -> Vertex<T>
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    let (pa, pb) = (a.support(direction), b.support(-direction));
+    Vertex {
+        w: pa - pb,
+        a: pa,
+        b: pb,
+    }
+}
+
+// tolerance returns the relative tolerance used for convergence tests.
+pub(super) fn tolerance<T: Float>() -> T {
+    T::epsilon() * <T as NumCast>::from(128).unwrap()
+}
+
+/// Closest describes the closest points between two separated shapes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Closest<T> {
+    /// The distance between the shapes.
+    pub distance: T,
+
+    /// The point on the first shape closest to the second.
+    pub point_a: Vec3<T>,
+
+    /// The point on the second shape closest to the first.
+    pub point_b: Vec3<T>,
+}
+
+/// closest_points returns the closest points between the two convex shapes
+/// using the GJK algorithm, or None if the shapes overlap.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Vec3, Float};
+/// use fiz_math::convex::{closest_points, Sphere, Cuboid};
+///
+/// let a = Cuboid::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+/// let b = Sphere::new(Vec3(4.0, 0.5, 0.0), 1.0);
+/// let c = closest_points(&a, &b).unwrap();
+/// assert!(c.distance.almost_equal(2.0, 1e-9));
+/// assert!(c.point_a.almost_equal(Vec3(1.0, 0.5, 0.0), 1e-6));
+/// assert!(c.point_b.almost_equal(Vec3(3.0, 0.5, 0.0), 1e-6));
+/// ```
+pub fn closest_points<T, A, B>(a: &A, b: &B) -> Option<Closest<T>>
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    gjk(a, b).ok()
+}
+
+/// distance returns the distance between the two convex shapes, or zero if
+/// they overlap.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Vec3, Float};
+/// use fiz_math::convex::{distance, Sphere};
+///
+/// let a = Sphere::new(Vec3(0.0, 0.0, 0.0), 1.0);
+/// let b = Sphere::new(Vec3(0.0, 3.0, 4.0), 2.0);
+/// assert!(distance(&a, &b).almost_equal(2.0, 1e-9));
+/// assert_eq!(distance(&a, &a), 0.0);
+/// ```
+pub fn distance<T, A, B>(a: &A, b: &B) -> T
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    gjk(a, b).map(|c| c.distance).unwrap_or_else(|_| T::zero())
+}
+
+/// intersects tells if the two convex shapes overlap (or touch).
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{intersects, Capsule, Cone};
+///
+/// let a = Capsule::new(Vec3(-2.0, 0.0, 0.0), Vec3(2.0, 0.0, 0.0), 0.5);
+/// let b = Cone::new(Vec3(0.0, 0.25, 0.0), Vec3(0.0, 2.0, 0.0), 1.0);
+/// assert!(intersects(&a, &b));
+///
+/// let c = Cone::new(Vec3(0.0, 0.75, 0.0), Vec3(0.0, 2.0, 0.0), 1.0);
+/// assert!(!intersects(&a, &c));
+/// ```
+pub fn intersects<T, A, B>(a: &A, b: &B) -> bool
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    gjk(a, b).is_err()
+}
+
+// gjk runs the GJK distance algorithm. On overlap, the final simplex (which
+// contains the origin, or has it on its boundary) is returned as the error.
+pub(super) fn gjk<T, A, B>(a: &A, b: &B) -> Result<Closest<T>, Vec<Vertex<T>>>
+    where T: Float,
+          A: Support<T> + ?Sized,
+          B: Support<T> + ?Sized
+{
+    let tol = tolerance::<T>();
+    let mut dir = a.center() - b.center();
+    if dir.is_zero() {
+        dir = Vec3(T::one(), T::zero(), T::zero());
+    }
+    let mut simplex = vec![minkowski(a, b, dir)];
+    let mut weights = vec![T::one()];
+    let mut v = simplex[0].w;
+
+    for _ in 0..MAX_ITERATIONS {
+        let vv = v.length_sq();
+        let scale = simplex.iter().fold(T::zero(), |m, s| m.max(s.w.length_sq()));
+        if vv <= tol * tol * scale {
+            return Err(simplex);
+        }
+
+        // Stop once the support point in the search direction brings the
+        // lower bound on the distance close enough to the upper bound |v|.
+        let w = minkowski(a, b, -v);
+        if vv - v.dot(w.w) <= tol * vv || simplex.iter().any(|s| s.w == w.w) {
+            break;
+        }
+        simplex.push(w);
+
+        match reduce(&simplex) {
+            None => return Err(simplex),
+            Some((keep, lambda)) => {
+                simplex = keep.iter().map(|&i| simplex[i]).collect();
+                weights = lambda;
+            }
+        }
+        let next = combine(&simplex, &weights, |s| s.w);
+        if next.length_sq() >= vv {
+            // No progress was made, which only happens due to roundoff
+            // once v is as close as it can be.
+            break;
+        }
+        v = next;
+    }
+
+    Ok(Closest {
+        distance: v.length(),
+        point_a: combine(&simplex, &weights, |s| s.a),
+        point_b: combine(&simplex, &weights, |s| s.b),
+    })
+}
+
+// combine returns the weighted sum of a component of the simplex vertices.
+pub(super) fn combine<T: Float, F>(simplex: &[Vertex<T>], weights: &[T], f: F) -> Vec3<T>
+    where F: Fn(&Vertex<T>) -> Vec3<T>
+{
+    simplex.iter().zip(weights).fold(Vec3::zero(), |sum, (s, &l)| sum + f(s).mul_scalar(l))
+}
+
+// Reduced holds the indices of a subset of simplex vertices and barycentric
+// weights for them.
+type Reduced<T> = (Vec<usize>, Vec<T>);
+
+// reduce finds the point of the simplex closest to the origin. It returns the
+// indices of the vertices of the smallest face containing that point and its
+// barycentric coordinates with respect to them, or None if the simplex is a
+// tetrahedron containing the origin.
+fn reduce<T: Float>(simplex: &[Vertex<T>]) -> Option<Reduced<T>> {
+    let w: Vec<Vec3<T>> = simplex.iter().map(|s| s.w).collect();
+    match w.len() {
+        1 => Some((vec![0], vec![T::one()])),
+        2 => Some(segment(w[0], w[1], [0, 1])),
+        3 => Some(triangle(w[0], w[1], w[2], [0, 1, 2])),
+        _ => tetrahedron(&w),
+    }
+}
+
+// segment returns the closest point of the segment a-b to the origin, as in
+// reduce. The indices of a and b are given by idx.
+fn segment<T: Float>(a: Vec3<T>, b: Vec3<T>, idx: [usize; 2]) -> Reduced<T> {
+    let ab = b - a;
+    let len = ab.length_sq();
+    let t = -a.dot(ab);
+    if t <= T::zero() || len == T::zero() {
+        (vec![idx[0]], vec![T::one()])
+    } else if t >= len {
+        (vec![idx[1]], vec![T::one()])
+    } else {
+        let t = t / len;
+        (vec![idx[0], idx[1]], vec![T::one() - t, t])
+    }
+}
+
+// triangle returns the closest point of the triangle a, b, c to the origin,
+// as in reduce, following "Real-Time Collision Detection" by Christer Ericson
+// (section 5.1.5).
+fn triangle<T: Float>(a: Vec3<T>, b: Vec3<T>, c: Vec3<T>, idx: [usize; 3]) -> Reduced<T> {
+    let zero = T::zero();
+    let (ab, ac) = (b - a, c - a);
+    let (d1, d2) = (-ab.dot(a), -ac.dot(a));
+    if d1 <= zero && d2 <= zero {
+        return (vec![idx[0]], vec![T::one()]);
+    }
+    let (d3, d4) = (-ab.dot(b), -ac.dot(b));
+    if d3 >= zero && d4 <= d3 {
+        return (vec![idx[1]], vec![T::one()]);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= zero && d1 >= zero && d3 <= zero && d1 - d3 > zero {
+        return segment(a, b, [idx[0], idx[1]]);
+    }
+    let (d5, d6) = (-ab.dot(c), -ac.dot(c));
+    if d6 >= zero && d5 <= d6 {
+        return (vec![idx[2]], vec![T::one()]);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= zero && d2 >= zero && d6 <= zero && d2 - d6 > zero {
+        return segment(a, c, [idx[0], idx[2]]);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= zero && d4 - d3 >= zero && d5 - d6 >= zero && (d4 - d3) + (d5 - d6) > zero {
+        return segment(b, c, [idx[1], idx[2]]);
+    }
+
+    let denom = va + vb + vc;
+    if denom <= zero {
+        // The triangle is degenerate (its points are collinear), so the
+        // closest point lies on one of its edges.
+        let candidates = [segment(a, b, [idx[0], idx[1]]), segment(a, c, [idx[0], idx[2]]),
+                          segment(b, c, [idx[1], idx[2]])];
+        let pos = |i: usize| if i == idx[0] { a } else if i == idx[1] { b } else { c };
+        return candidates.iter()
+            .map(|(k, l)| {
+                let p = k.iter().zip(l).fold(Vec3::zero(), |s, (&i, &l)| s + pos(i).mul_scalar(l));
+                (p.length_sq(), (k.clone(), l.clone()))
+            })
+            .fold(None, |best: Option<(T, Reduced<T>)>, c| match best {
+                Some(ref b) if b.0 <= c.0 => best,
+                _ => Some(c),
+            })
+            .unwrap()
+            .1;
+    }
+    let (v, w) = (vb / denom, vc / denom);
+    (vec![idx[0], idx[1], idx[2]], vec![T::one() - v - w, v, w])
+}
+
+// tetrahedron returns the closest point of the tetrahedron to the origin, as
+// in reduce.
+fn tetrahedron<T: Float>(p: &[Vec3<T>]) -> Option<Reduced<T>> {
+    let zero = T::zero();
+    let faces = [[0, 1, 2, 3], [0, 2, 3, 1], [0, 3, 1, 2], [1, 3, 2, 0]];
+    let mut best: Option<(T, Reduced<T>)> = None;
+    for f in faces.iter() {
+        let (a, b, c, d) = (p[f[0]], p[f[1]], p[f[2]], p[f[3]]);
+        let n = (b - a).cross(c - a);
+        let (side_o, side_d) = (-a.dot(n), (d - a).dot(n));
+
+        // Only faces which separate the origin from the opposite vertex can
+        // hold the closest point. Should the tetrahedron be flat, every face
+        // is considered.
+        if side_o * side_d > zero {
+            continue;
+        }
+        let (k, l) = triangle(a, b, c, [f[0], f[1], f[2]]);
+        let q = k.iter().zip(&l).fold(Vec3::zero(), |s, (&i, &l)| s + p[i].mul_scalar(l));
+        let dist = q.length_sq();
+        match best {
+            Some((bd, _)) if bd <= dist => {}
+            _ => best = Some((dist, (k, l))),
+        }
+    }
+    best.map(|b| b.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{closest_points, distance, intersects};
+    use super::super::{Sphere, Cuboid, Capsule, Cylinder, Cone, PointCloud, convex_hull};
+    use {Vec3, Float};
+
+    #[test]
+    fn primitives() {
+        let cube = Cuboid::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0));
+
+        // Corner to corner.
+        let other = Cuboid::new(Vec3(3.0, 3.0, 3.0), Vec3(1.0, 1.0, 1.0));
+        let c = closest_points(&cube, &other).unwrap();
+        assert!(c.distance.almost_equal(3.0f64.sqrt(), 1e-12));
+        assert!(c.point_a.almost_equal(Vec3(1.0, 1.0, 1.0), 1e-12));
+        assert!(c.point_b.almost_equal(Vec3(2.0, 2.0, 2.0), 1e-12));
+
+        // Edge to face.
+        let cyl = Cylinder::new(Vec3(-5.0, 0.0, 3.0), Vec3(5.0, 0.0, 3.0), 0.5);
+        assert!(distance(&cube, &cyl).almost_equal(1.5, 1e-9));
+
+        // Capsule to sphere, along a diagonal.
+        let cap = Capsule::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, 10.0), 1.0);
+        let s = Sphere::new(Vec3(3.0, 4.0, 5.0), 2.0);
+        let c = closest_points(&cap, &s).unwrap();
+        assert!(c.distance.almost_equal(2.0, 1e-6));
+        assert!(c.point_a.almost_equal(Vec3(0.6, 0.8, 5.0), 1e-6));
+
+        // Cone apex just touching a sphere.
+        let cone = Cone::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, -2.0, 0.0), 1.0);
+        let s = Sphere::new(Vec3(0.0, 1.0, 0.0), 1.0);
+        assert!(intersects(&cone, &s));
+        let s = Sphere::new(Vec3(0.0, 1.001, 0.0), 1.0);
+        assert!(!intersects(&cone, &s));
+
+        // f32 shapes.
+        let a = Sphere::new(Vec3(0.0f32, 0.0, 0.0), 1.0);
+        let b = Cuboid::new(Vec3(0.0f32, 5.0, 0.0), Vec3(2.0, 1.0, 2.0));
+        assert!(distance(&a, &b).almost_equal(3.0, 1e-5));
+    }
+
+    #[test]
+    fn point_to_hull_distance() {
+        // The distances from single points to a tetrahedron's hull, closest
+        // to one of its faces, edges or vertices, match those worked out by
+        // hand.
+        let tetra = convex_hull(&[Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0),
+                                  Vec3(0.0, 0.0, 1.0)])
+            .unwrap();
+        for &(p, d) in &[(Vec3(1.0, 1.0, 1.0), (1.0 - 1.0 / 3.0) * 3.0f64.sqrt()),
+                         (Vec3(-1.0, -1.0, -1.0), 3.0f64.sqrt()),
+                         (Vec3(0.5, 0.5, -2.0), 2.0),
+                         (Vec3(2.0, 0.0, 0.0), 1.0)] {
+            let point = PointCloud::new(vec![p]);
+            assert!(distance(&tetra, &point).almost_equal(d, 1e-9), "{:?}", p);
+        }
+        let inside = PointCloud::new(vec![Vec3(0.1, 0.1, 0.1)]);
+        assert!(intersects(&tetra, &inside));
+    }
+}
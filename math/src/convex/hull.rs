@@ -0,0 +1,334 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use num::NumCast;
+use super::super::float::Float;
+use super::super::Vec3;
+use super::super::predicates::orient3d;
+use super::{Support, furthest, average};
+
+/// Hull is a convex polyhedron with triangular faces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hull<T> {
+    /// The vertices of the hull.
+    pub vertices: Vec<Vec3<T>>,
+
+    /// The faces of the hull, as indices into vertices. Each face is ordered
+    /// counterclockwise when viewed from outside the hull.
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl<T: Float> Hull<T> {
+    /// volume returns the volume enclosed by the hull.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec3;
+    /// use fiz_math::convex::convex_hull;
+    ///
+    /// let h = convex_hull(&[Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0),
+    ///                       Vec3(0.0, 1.0, 0.0), Vec3(0.0, 0.0, 1.0)]).unwrap();
+    /// assert_eq!(h.volume(), 1.0 / 6.0);
+    /// ```
+    pub fn volume(&self) -> T {
+        let o = self.vertices[0];
+        let sum = self.faces.iter().fold(T::zero(), |sum, f| {
+            let (a, b, c) = (self.vertices[f[0]] - o, self.vertices[f[1]] - o, self.vertices[f[2]] - o);
+            sum + a.dot(b.cross(c))
+        });
+        sum / <T as NumCast>::from(6).unwrap()
+    }
+}
+
+impl<T: Float> Support<T> for Hull<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        furthest(&self.vertices, direction)
+    }
+
+    fn center(&self) -> Vec3<T> {
+        average(&self.vertices)
+    }
+}
+
+// A face of the hull under construction, along with the points which lie
+// above it and haven't been assigned to any other face.
+struct Face {
+    v: [usize; 3],
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+/// convex_hull computes the convex hull of the points using the quickhull
+/// algorithm. Points lying on the surface of the hull (but not at its
+/// corners) are not included as vertices.
+///
+/// Exact predicates are used for all orientation tests, so the result is
+/// always a valid closed convex polyhedron. None is returned if the points
+/// are all coplanar (including when there are fewer than four of them).
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::convex_hull;
+///
+/// let mut points = Vec::new();
+/// for &x in &[-1.0, 0.0, 1.0] {
+///     for &y in &[-1.0, 0.0, 1.0] {
+///         for &z in &[-1.0, 0.0, 1.0] {
+///             points.push(Vec3(x, y, z));
+///         }
+///     }
+/// }
+/// let cube = convex_hull(&points).unwrap();
+/// assert_eq!(cube.vertices.len(), 8);
+/// assert_eq!(cube.faces.len(), 12);
+/// assert_eq!(cube.volume(), 8.0);
+/// ```
+pub fn convex_hull<T: Float>(points: &[Vec3<T>]) -> Option<Hull<T>> {
+    let pts: Vec<Vec3<f64>> = points.iter()
+        .map(|p| Vec3(p.0.to_f64().unwrap(), p.1.to_f64().unwrap(), p.2.to_f64().unwrap()))
+        .collect();
+    let faces = quickhull(&pts)?;
+
+    // Compact the vertices to those used by the faces.
+    let mut remap = vec![usize::MAX; pts.len()];
+    let mut hull = Hull {
+        vertices: Vec::new(),
+        faces: Vec::with_capacity(faces.len()),
+    };
+    for f in faces {
+        let mut face = [0; 3];
+        for k in 0..3 {
+            if remap[f[k]] == usize::MAX {
+                remap[f[k]] = hull.vertices.len();
+                hull.vertices.push(points[f[k]]);
+            }
+            face[k] = remap[f[k]];
+        }
+        hull.faces.push(face);
+    }
+    Some(hull)
+}
+
+// above tells if p lies strictly above (outside) the counterclockwise face.
+fn above(pts: &[Vec3<f64>], f: &[usize; 3], p: usize) -> bool {
+    orient3d(pts[f[0]], pts[f[1]], pts[f[2]], pts[p]) == Ordering::Less
+}
+
+// quickhull returns the faces of the hull of pts, as indices into pts.
+fn quickhull(pts: &[Vec3<f64>]) -> Option<Vec<[usize; 3]>> {
+    let simplex = initial_simplex(pts)?;
+    let mut faces: Vec<Face> = Vec::new();
+    let [a, b, c, d] = simplex;
+    for v in [[a, b, c], [a, c, d], [a, d, b], [b, d, c]].iter() {
+        faces.push(Face {
+            v: *v,
+            outside: Vec::new(),
+            alive: true,
+        });
+    }
+
+    // Assign every point to the first face it lies above.
+    for i in 0..pts.len() {
+        if simplex.contains(&i) {
+            continue;
+        }
+        if let Some(f) = faces.iter_mut().find(|f| above(pts, &f.v, i)) {
+            f.outside.push(i);
+        }
+    }
+
+    let mut pending: Vec<usize> = (0..faces.len()).collect();
+    while let Some(fi) = pending.pop() {
+        if !faces[fi].alive || faces[fi].outside.is_empty() {
+            continue;
+        }
+
+        // The eye point is the outside point furthest from the face.
+        let eye = {
+            let f = &faces[fi];
+            let (p0, p1, p2) = (pts[f.v[0]], pts[f.v[1]], pts[f.v[2]]);
+            let n = (p1 - p0).cross(p2 - p0);
+            *f.outside
+                .iter()
+                .max_by(|&&x, &&y| {
+                    (pts[x] - p0).dot(n).partial_cmp(&(pts[y] - p0).dot(n)).unwrap_or(Ordering::Equal)
+                })
+                .unwrap()
+        };
+
+        // Remove every face the eye can see. The region they form is
+        // connected, and its boundary is the horizon.
+        let mut visible = Vec::new();
+        let mut edges = HashSet::new();
+        for (i, f) in faces.iter_mut().enumerate() {
+            if f.alive && above(pts, &f.v, eye) {
+                f.alive = false;
+                visible.push(i);
+                for k in 0..3 {
+                    edges.insert((f.v[k], f.v[(k + 1) % 3]));
+                }
+            }
+        }
+        let mut orphans = Vec::new();
+        for &i in &visible {
+            orphans.append(&mut faces[i].outside);
+        }
+
+        // Connect each horizon edge to the eye.
+        let first = faces.len();
+        for &(u, v) in &edges {
+            if !edges.contains(&(v, u)) {
+                faces.push(Face {
+                    v: [u, v, eye],
+                    outside: Vec::new(),
+                    alive: true,
+                });
+            }
+        }
+        for p in orphans {
+            if p == eye {
+                continue;
+            }
+            if let Some(f) = faces[first..].iter_mut().find(|f| above(pts, &f.v, p)) {
+                f.outside.push(p);
+            }
+        }
+        pending.extend(first..faces.len());
+    }
+
+    Some(faces.into_iter().filter(|f| f.alive).map(|f| f.v).collect())
+}
+
+// initial_simplex returns four affinely independent points, ordered such that
+// the fourth lies below the (counterclockwise) plane of the first three.
+fn initial_simplex(pts: &[Vec3<f64>]) -> Option<[usize; 4]> {
+    if pts.len() < 4 {
+        return None;
+    }
+
+    // The extreme points along the axis of largest spread.
+    let mut best = (0, 0, -1.0);
+    for axis in 0..3 {
+        let get = |i: usize| match axis {
+            0 => pts[i].0,
+            1 => pts[i].1,
+            _ => pts[i].2,
+        };
+        let lo = (0..pts.len()).min_by(|&x, &y| get(x).partial_cmp(&get(y)).unwrap_or(Ordering::Equal)).unwrap();
+        let hi = (0..pts.len()).max_by(|&x, &y| get(x).partial_cmp(&get(y)).unwrap_or(Ordering::Equal)).unwrap();
+        if get(hi) - get(lo) > best.2 {
+            best = (lo, hi, get(hi) - get(lo));
+        }
+    }
+    let (a, b) = (best.0, best.1);
+    if pts[a] == pts[b] {
+        return None;
+    }
+
+    // The point furthest from the line a-b.
+    let ab = pts[b] - pts[a];
+    let c = (0..pts.len())
+        .max_by(|&x, &y| {
+            let dx = ab.cross(pts[x] - pts[a]).length_sq();
+            let dy = ab.cross(pts[y] - pts[a]).length_sq();
+            dx.partial_cmp(&dy).unwrap_or(Ordering::Equal)
+        })
+        .unwrap();
+
+    // The point furthest from the plane a, b, c. Distances are only used to
+    // pick a good candidate, coplanarity is decided exactly.
+    let n = ab.cross(pts[c] - pts[a]);
+    let d = (0..pts.len())
+        .max_by(|&x, &y| {
+            let dx = (pts[x] - pts[a]).dot(n).abs();
+            let dy = (pts[y] - pts[a]).dot(n).abs();
+            dx.partial_cmp(&dy).unwrap_or(Ordering::Equal)
+        })
+        .unwrap();
+    match orient3d(pts[a], pts[b], pts[c], pts[d]) {
+        Ordering::Greater => Some([a, b, c, d]),
+        Ordering::Less => Some([b, a, c, d]),
+        Ordering::Equal => {
+            // The candidates were poorly conditioned; fall back to an exact
+            // search for any point off the plane (if a, b, c aren't collinear
+            // there may be none, meaning all points are coplanar).
+            let c = (0..pts.len()).find(|&i| {
+                let (u, v) = (pts[b] - pts[a], pts[i] - pts[a]);
+                u.cross(v) != Vec3(0.0, 0.0, 0.0)
+            })?;
+            let d = (0..pts.len()).find(|&i| orient3d(pts[a], pts[b], pts[c], pts[i]) != Ordering::Equal)?;
+            if orient3d(pts[a], pts[b], pts[c], pts[d]) == Ordering::Greater {
+                Some([a, b, c, d])
+            } else {
+                Some([b, a, c, d])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use std::collections::HashSet;
+    use super::convex_hull;
+    use super::super::super::predicates::orient3d;
+    use Vec3;
+    use testing::lcg;
+
+    #[test]
+    fn valid_polyhedron() {
+        let mut s = 1;
+        let mut points: Vec<Vec3<f64>> = (0..500)
+            .map(|_| {
+                let v = Vec3(lcg(&mut s) - 0.5, lcg(&mut s) - 0.5, lcg(&mut s) - 0.5);
+                v.div_scalar(v.length()).mul_scalar(lcg(&mut s).sqrt())
+            })
+            .collect();
+        // Duplicates and coplanar points on a face of the final hull.
+        let dup = points[7];
+        points.push(dup);
+        points.extend_from_slice(&[Vec3(2.0, 2.0, 2.0), Vec3(2.0, -2.0, 2.0), Vec3(2.0, 2.0, -2.0),
+                                   Vec3(2.0, -2.0, -2.0), Vec3(2.0, 0.0, 0.0), Vec3(2.0, 1.0, 0.5)]);
+        let hull = convex_hull(&points).unwrap();
+
+        // Every input point lies on or below every face.
+        for f in &hull.faces {
+            let (a, b, c) = (hull.vertices[f[0]], hull.vertices[f[1]], hull.vertices[f[2]]);
+            for &p in &points {
+                assert!(orient3d(a, b, c, p) != Ordering::Less);
+            }
+        }
+
+        // The surface is closed: every edge is shared with exactly one
+        // oppositely oriented edge, and Euler's formula holds.
+        let mut edges = HashSet::new();
+        for f in &hull.faces {
+            for k in 0..3 {
+                assert!(edges.insert((f[k], f[(k + 1) % 3])));
+            }
+        }
+        for &(u, v) in &edges {
+            assert!(edges.contains(&(v, u)));
+        }
+        assert_eq!(hull.vertices.len() + hull.faces.len() - edges.len() / 2, 2);
+
+        // Points interior to the square face were not used.
+        assert!(!hull.vertices.contains(&Vec3(2.0, 0.0, 0.0)));
+        assert!(!hull.vertices.contains(&Vec3(2.0, 1.0, 0.5)));
+    }
+
+    #[test]
+    fn degenerate() {
+        let flat = [Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0), Vec3(1.0, 1.0, 0.0)];
+        assert!(convex_hull(&flat).is_none());
+        assert!(convex_hull(&flat[..3]).is_none());
+        assert!(convex_hull(&[Vec3(1.0f32, 1.0, 1.0); 5]).is_none());
+
+        // Nearly coplanar points still produce a closed hull.
+        let tilt = [Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0), Vec3(1.0, 1.0, 1e-300)];
+        let hull = convex_hull(&tilt).unwrap();
+        assert_eq!(hull.faces.len(), 4);
+    }
+}
@@ -0,0 +1,368 @@
+//! Queries on three-dimensional convex shapes.
+//!
+//! Convex shapes are described by their support mapping (see the `Support`
+//! trait), which is all the GJK and EPA algorithms in this module need to
+//! compute distances, closest points and penetration depths between any two
+//! shapes. Support maps are provided for the common primitives, point clouds
+//! and convex hulls built by `convex_hull`.
+
+use num::NumCast;
+use super::float::Float;
+use super::{Vec3, Zero};
+
+mod hull;
+mod gjk;
+mod epa;
+
+pub use self::hull::{Hull, convex_hull};
+pub use self::gjk::{Closest, closest_points, distance, intersects};
+pub use self::epa::{Penetration, penetration};
+
+/// Support is implemented by convex shapes, and describes them by their
+/// support mapping.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Cuboid};
+///
+/// let b = Cuboid::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 2.0, 3.0));
+/// assert_eq!(b.support(Vec3(1.0, -1.0, 1.0)), Vec3(1.0, -2.0, 3.0));
+/// ```
+pub trait Support<T> {
+    /// support returns the point of the shape which is furthest in the given
+    /// direction, i.e. the point p maximizing `p.dot(direction)`. The
+    /// direction need not be normalized, and may be zero (in which case any
+    /// point of the shape may be returned).
+    fn support(&self, direction: Vec3<T>) -> Vec3<T>;
+
+    /// center returns a point inside the shape, used as a starting point when
+    /// searching it.
+    fn center(&self) -> Vec3<T>;
+}
+
+impl<T, S: Support<T> + ?Sized> Support<T> for &S {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        (**self).support(direction)
+    }
+
+    fn center(&self) -> Vec3<T> {
+        (**self).center()
+    }
+}
+
+/// Sphere is a solid sphere.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Sphere};
+///
+/// let s = Sphere::new(Vec3(1.0, 0.0, 0.0), 2.0);
+/// assert_eq!(s.support(Vec3(0.0, 5.0, 0.0)), Vec3(1.0, 2.0, 0.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sphere<T> {
+    /// The center of the sphere.
+    pub center: Vec3<T>,
+
+    /// The radius of the sphere.
+    pub radius: T,
+}
+
+impl<T> Sphere<T> {
+    /// new returns a new sphere with the given center and radius.
+    pub fn new(center: Vec3<T>, radius: T) -> Self {
+        Sphere {
+            center,
+            radius,
+        }
+    }
+}
+
+impl<T: Float> Support<T> for Sphere<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        match direction.normalize() {
+            Some(n) => self.center + n.mul_scalar(self.radius),
+            None => self.center,
+        }
+    }
+
+    fn center(&self) -> Vec3<T> {
+        self.center
+    }
+}
+
+/// Cuboid is a solid axis-aligned box.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Cuboid};
+///
+/// let b = Cuboid::new(Vec3(1.0, 1.0, 1.0), Vec3(0.5, 0.5, 0.5));
+/// assert_eq!(b.support(Vec3(-1.0, 0.0, 1.0)), Vec3(0.5, 1.5, 1.5));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cuboid<T> {
+    /// The center of the box.
+    pub center: Vec3<T>,
+
+    /// The distance from the center to the faces of the box along each axis.
+    pub half_extents: Vec3<T>,
+}
+
+impl<T> Cuboid<T> {
+    /// new returns a new box with the given center and half extents.
+    pub fn new(center: Vec3<T>, half_extents: Vec3<T>) -> Self {
+        Cuboid {
+            center,
+            half_extents,
+        }
+    }
+}
+
+impl<T: Float> Support<T> for Cuboid<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        let pick = |d: T, h: T| if d < T::zero() { -h } else { h };
+        let h = self.half_extents;
+        self.center + Vec3(pick(direction.0, h.0), pick(direction.1, h.1), pick(direction.2, h.2))
+    }
+
+    fn center(&self) -> Vec3<T> {
+        self.center
+    }
+}
+
+/// Capsule is the set of points within a radius of the line segment between
+/// two points.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Capsule};
+///
+/// let c = Capsule::new(Vec3(0.0, -1.0, 0.0), Vec3(0.0, 1.0, 0.0), 0.5);
+/// assert_eq!(c.support(Vec3(0.0, 1.0, 0.0)), Vec3(0.0, 1.5, 0.0));
+/// assert_eq!(c.support(Vec3(1.0, 0.0, 0.0)).0, 0.5);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Capsule<T> {
+    /// The first end point of the capsule's core segment.
+    pub a: Vec3<T>,
+
+    /// The second end point of the capsule's core segment.
+    pub b: Vec3<T>,
+
+    /// The radius of the capsule.
+    pub radius: T,
+}
+
+impl<T> Capsule<T> {
+    /// new returns a new capsule around the segment a-b with the given radius.
+    pub fn new(a: Vec3<T>, b: Vec3<T>, radius: T) -> Self {
+        Capsule {
+            a,
+            b,
+            radius,
+        }
+    }
+}
+
+impl<T: Float> Support<T> for Capsule<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        let end = if direction.dot(self.b - self.a) > T::zero() { self.b } else { self.a };
+        match direction.normalize() {
+            Some(n) => end + n.mul_scalar(self.radius),
+            None => end,
+        }
+    }
+
+    fn center(&self) -> Vec3<T> {
+        midpoint(self.a, self.b)
+    }
+}
+
+/// Cylinder is a solid circular cylinder, whose axis runs between the centers
+/// of its two caps.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Cylinder};
+///
+/// let c = Cylinder::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, 2.0), 1.0);
+/// assert_eq!(c.support(Vec3(1.0, 0.0, 1.0)), Vec3(1.0, 0.0, 2.0));
+/// assert_eq!(c.support(Vec3(0.0, -1.0, -1.0)), Vec3(0.0, -1.0, 0.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cylinder<T> {
+    /// The center of the first cap.
+    pub a: Vec3<T>,
+
+    /// The center of the second cap.
+    pub b: Vec3<T>,
+
+    /// The radius of the cylinder.
+    pub radius: T,
+}
+
+impl<T> Cylinder<T> {
+    /// new returns a new cylinder with caps centered at a and b and the given
+    /// radius.
+    pub fn new(a: Vec3<T>, b: Vec3<T>, radius: T) -> Self {
+        Cylinder {
+            a,
+            b,
+            radius,
+        }
+    }
+}
+
+impl<T: Float> Support<T> for Cylinder<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        let end = if direction.dot(self.b - self.a) > T::zero() { self.b } else { self.a };
+        end + rim(self.b - self.a, direction, self.radius)
+    }
+
+    fn center(&self) -> Vec3<T> {
+        midpoint(self.a, self.b)
+    }
+}
+
+/// Cone is a solid circular cone, given by its apex and the center of its
+/// base.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, Cone};
+///
+/// let c = Cone::new(Vec3(0.0, 2.0, 0.0), Vec3(0.0, 0.0, 0.0), 1.0);
+/// assert_eq!(c.support(Vec3(0.0, 1.0, 0.0)), Vec3(0.0, 2.0, 0.0));
+/// assert_eq!(c.support(Vec3(1.0, -1.0, 0.0)), Vec3(1.0, 0.0, 0.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cone<T> {
+    /// The apex (tip) of the cone.
+    pub apex: Vec3<T>,
+
+    /// The center of the cone's base.
+    pub base: Vec3<T>,
+
+    /// The radius of the base.
+    pub radius: T,
+}
+
+impl<T> Cone<T> {
+    /// new returns a new cone with the given apex, base center and base
+    /// radius.
+    pub fn new(apex: Vec3<T>, base: Vec3<T>, radius: T) -> Self {
+        Cone {
+            apex,
+            base,
+            radius,
+        }
+    }
+}
+
+impl<T: Float> Support<T> for Cone<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        let edge = self.base + rim(self.apex - self.base, direction, self.radius);
+        if self.apex.dot(direction) >= edge.dot(direction) {
+            self.apex
+        } else {
+            edge
+        }
+    }
+
+    fn center(&self) -> Vec3<T> {
+        // The centroid of a cone lies a quarter of the way up its axis.
+        let quarter = <T as NumCast>::from(0.25).unwrap();
+        self.base + (self.apex - self.base).mul_scalar(quarter)
+    }
+}
+
+/// PointCloud is the convex hull of a set of points, described implicitly by
+/// the points themselves. Use `convex_hull` to compute the hull explicitly.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec3;
+/// use fiz_math::convex::{Support, PointCloud};
+///
+/// let p = PointCloud::new(vec![Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0)]);
+/// assert_eq!(p.support(Vec3(-1.0, 2.0, 0.0)), Vec3(0.0, 1.0, 0.0));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointCloud<T> {
+    /// The points, which must not be empty.
+    pub points: Vec<Vec3<T>>,
+}
+
+impl<T> PointCloud<T> {
+    /// new returns a new point cloud of the given points.
+    ///
+    /// # Panics
+    ///
+    /// new panics if there are no points.
+    pub fn new(points: Vec<Vec3<T>>) -> Self {
+        assert!(!points.is_empty(), "PointCloud::new: no points");
+        PointCloud { points }
+    }
+}
+
+impl<T: Float> Support<T> for PointCloud<T> {
+    fn support(&self, direction: Vec3<T>) -> Vec3<T> {
+        furthest(&self.points, direction)
+    }
+
+    fn center(&self) -> Vec3<T> {
+        average(&self.points)
+    }
+}
+
+// furthest returns the point furthest in the given direction.
+fn furthest<T: Float>(points: &[Vec3<T>], direction: Vec3<T>) -> Vec3<T> {
+    let mut best = points[0];
+    let mut best_dot = best.dot(direction);
+    for &p in &points[1..] {
+        let d = p.dot(direction);
+        if d > best_dot {
+            best = p;
+            best_dot = d;
+        }
+    }
+    best
+}
+
+// average returns the average of the points.
+fn average<T: Float>(points: &[Vec3<T>]) -> Vec3<T> {
+    let sum = points.iter().fold(Vec3::zero(), |s, &p| s + p);
+    sum.div_scalar(<T as NumCast>::from(points.len()).unwrap())
+}
+
+// midpoint returns the point halfway between a and b.
+fn midpoint<T: Float>(a: Vec3<T>, b: Vec3<T>) -> Vec3<T> {
+    (a + b).div_scalar(T::one() + T::one())
+}
+
+// rim returns the offset from the center of a disc of the given radius,
+// perpendicular to axis, to its point furthest in the given direction.
+fn rim<T: Float>(axis: Vec3<T>, direction: Vec3<T>, radius: T) -> Vec3<T> {
+    let along = match axis.normalize() {
+        Some(n) => n.mul_scalar(direction.dot(n)),
+        None => Vec3::zero(),
+    };
+    match (direction - along).normalize() {
+        Some(n) => n.mul_scalar(radius),
+        None => Vec3::zero(),
+    }
+}
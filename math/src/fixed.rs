@@ -0,0 +1,951 @@
+use std::ops::{Add, Sub, Neg, Mul, Div, Rem};
+use std::cmp::Ordering;
+use std::fmt;
+use num::traits::{Zero, One, Num, Bounded, Signed, ToPrimitive, NumCast};
+use super::{Vec2, Vec3};
+use clamp::Clamp;
+
+/// FixedInt is implemented by the primitive signed integer types that may be
+/// used as the underlying storage of a `Fixed` number.
+///
+/// All arithmetic on fixed-point numbers is carried out using `i128`
+/// intermediates and then narrowed (wrapping on overflow) back into the
+/// storage type, so results never depend on the host architecture.
+pub trait FixedInt: Copy + Eq + Ord + fmt::Debug {
+    /// BITS is the number of bits in the integer type.
+    const BITS: u32;
+
+    /// to_i128 widens the integer to an `i128`.
+    fn to_i128(self) -> i128;
+
+    /// from_i128 narrows the `i128` into the integer type, wrapping on
+    /// overflow.
+    fn from_i128(v: i128) -> Self;
+}
+
+macro_rules! impl_fixed_int {
+    ($($ty:ty),*) => ($(
+        impl FixedInt for $ty {
+            const BITS: u32 = <$ty>::BITS;
+
+            fn to_i128(self) -> i128 {
+                self as i128
+            }
+
+            fn from_i128(v: i128) -> Self {
+                v as $ty
+            }
+        }
+    )*);
+}
+
+impl_fixed_int! { i8, i16, i32, i64 }
+
+/// Fixed is a deterministic fixed-point number stored in the signed integer
+/// type `I` with `FRAC` fractional bits.
+///
+/// Unlike floating-point numbers, every operation on a fixed-point number
+/// (including `sqrt` and the trigonometric functions) is computed using only
+/// integer arithmetic, and as such produces bit-identical results on every
+/// platform. This makes it suitable for simulations which must stay in
+/// lockstep across machines.
+///
+/// Arithmetic wraps on overflow, multiplication rounds to nearest, and
+/// division truncates toward zero.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Fixed, Vec2};
+///
+/// type F = Fixed<i32, 16>;
+///
+/// let a = F::from_int(3);
+/// let b = F::from_f64(0.5);
+/// assert_eq!(a * b, F::from_f64(1.5));
+///
+/// let v = Vec2(F::from_int(3), F::from_int(4));
+/// assert_eq!(v.length(), F::from_int(5));
+/// ```
+///
+/// ```
+/// use fiz_math::Fixed32;
+/// use fiz_math::unit::{M, MM, ToMM};
+///
+/// let x = M(Fixed32::from_f64(1.5));
+/// assert_eq!(x.to_mm(), MM(Fixed32::from_int(1500)));
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed<I, const FRAC: u32>(I);
+
+/// Fixed32 is a 32-bit fixed-point number with 16 fractional bits.
+pub type Fixed32 = Fixed<i32, 16>;
+
+/// Fixed64 is a 64-bit fixed-point number with 32 fractional bits.
+pub type Fixed64 = Fixed<i64, 32>;
+
+// The trigonometric functions are evaluated using CORDIC in Q32.32 format,
+// these constants (and the arctangent table) are expressed in that format.
+const Q: u32 = 32;
+const PI_Q: i128 = 13493037705;
+const HALF_PI_Q: i128 = PI_Q / 2;
+const TWO_PI_Q: i128 = PI_Q * 2;
+const CORDIC_K: i64 = 2608131496;
+const CORDIC_ATAN: [i64; 32] = [3373259426, 1991351318, 1052175346, 534100635, 268086748,
+                                134174063, 67103403, 33553749, 16777131, 8388597, 4194303,
+                                2097152, 1048576, 524288, 262144, 131072, 65536, 32768, 16384,
+                                8192, 4096, 2048, 1024, 512, 256, 128, 64, 32, 16, 8, 4, 2];
+
+// shift_round shifts v left (positive n) or right (negative n), rounding to
+// nearest when bits are shifted out.
+fn shift_round(v: i128, n: i32) -> i128 {
+    if n >= 0 {
+        v << n
+    } else {
+        let n = -n as u32;
+        (v + (1 << (n - 1))) >> n
+    }
+}
+
+// isqrt returns the largest integer whose square is less than or equal to v.
+fn isqrt(v: u128) -> u128 {
+    let mut rem = v;
+    let mut res = 0u128;
+    let mut bit = 1u128 << 126;
+    while bit > v {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rem >= res + bit {
+            rem -= res + bit;
+            res = (res >> 1) + bit;
+        } else {
+            res >>= 1;
+        }
+        bit >>= 2;
+    }
+    res
+}
+
+// hypot returns the square root of the sum of the squares of the components,
+// saturating at the largest value of I. The squares are at most 2^126, so
+// the sum of up to three of them can't overflow a u128.
+fn hypot<I: FixedInt>(components: &[I]) -> I {
+    let sum: u128 = components.iter()
+        .map(|c| {
+            let a = c.to_i128().unsigned_abs();
+            a * a
+        })
+        .sum();
+    let max = (1u128 << (I::BITS - 1)) - 1;
+    I::from_i128(isqrt(sum).min(max) as i128)
+}
+
+// cordic_sin_cos returns the sine and cosine of the given Q32 angle (which
+// must lie within [-pi/2, pi/2]) in Q32 format.
+fn cordic_sin_cos(angle: i64) -> (i64, i64) {
+    let (mut x, mut y, mut z) = (CORDIC_K, 0i64, angle);
+    for (i, atan) in CORDIC_ATAN.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if z >= 0 {
+            x -= dx;
+            y += dy;
+            z -= atan;
+        } else {
+            x += dx;
+            y -= dy;
+            z += atan;
+        }
+    }
+    (y, x)
+}
+
+// cordic_atan returns the arctangent of y / x in Q32 format, x must be
+// positive and both x and y must be less than 2^60 in magnitude.
+fn cordic_atan(mut x: i64, mut y: i64) -> i64 {
+    let mut z = 0i64;
+    for (i, atan) in CORDIC_ATAN.iter().enumerate() {
+        let (dx, dy) = (y >> i, x >> i);
+        if y > 0 {
+            x += dx;
+            y -= dy;
+            z += atan;
+        } else {
+            x -= dx;
+            y += dy;
+            z -= atan;
+        }
+    }
+    z
+}
+
+impl<I: FixedInt, const FRAC: u32> Fixed<I, FRAC> {
+    /// from_bits returns the fixed-point number whose underlying integer
+    /// representation is `bits`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_bits(1 << 16), Fixed32::from_int(1));
+    /// ```
+    pub fn from_bits(bits: I) -> Self {
+        Fixed(bits)
+    }
+
+    /// to_bits returns the underlying integer representation of the number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_f64(0.5).to_bits(), 1 << 15);
+    /// ```
+    pub fn to_bits(self) -> I {
+        self.0
+    }
+
+    /// from_int returns the fixed-point number representing the integer `v`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_int(-2).to_f64(), -2.0);
+    /// ```
+    pub fn from_int(v: i64) -> Self {
+        Fixed(I::from_i128((v as i128) << FRAC))
+    }
+
+    /// from_f64 returns the fixed-point number nearest to `v`.
+    ///
+    /// The conversion is a single exact scaling by a power of two followed by
+    /// rounding, and is thus deterministic. It is intended for constants and
+    /// loading data, not for use inside a simulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_f64(0.25).to_f64(), 0.25);
+    /// ```
+    pub fn from_f64(v: f64) -> Self {
+        Fixed(I::from_i128((v * (1u128 << FRAC) as f64).round() as i128))
+    }
+
+    /// to_f64 returns the number as a 64-bit float, for display or rendering
+    /// purposes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_int(3).to_f64(), 3.0);
+    /// ```
+    pub fn to_f64(self) -> f64 {
+        self.0.to_i128() as f64 / (1u128 << FRAC) as f64
+    }
+
+    /// to_int returns the integer part of the number, rounding toward
+    /// negative infinity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_f64(1.75).to_int(), 1);
+    /// assert_eq!(Fixed32::from_f64(-1.75).to_int(), -2);
+    /// ```
+    pub fn to_int(self) -> i64 {
+        (self.0.to_i128() >> FRAC) as i64
+    }
+
+    /// epsilon returns the smallest positive number representable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::epsilon().to_f64(), 1.0 / 65536.0);
+    /// ```
+    pub fn epsilon() -> Self {
+        Fixed(I::from_i128(1))
+    }
+
+    /// pi returns the number nearest to π.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::pi(), Fixed32::from_f64(std::f64::consts::PI));
+    /// ```
+    pub fn pi() -> Self {
+        Self::from_q(PI_Q)
+    }
+
+    /// floor returns the largest integer less than or equal to the number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_f64(-1.5).floor(), Fixed32::from_int(-2));
+    /// ```
+    pub fn floor(self) -> Self {
+        Fixed(I::from_i128((self.0.to_i128() >> FRAC) << FRAC))
+    }
+
+    /// round returns the nearest integer to the number, rounding half-way
+    /// cases toward positive infinity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_f64(1.5).round(), Fixed32::from_int(2));
+    /// assert_eq!(Fixed32::from_f64(1.25).round(), Fixed32::from_int(1));
+    /// ```
+    pub fn round(self) -> Self {
+        Fixed(I::from_i128(shift_round(self.0.to_i128(), -(FRAC as i32)) << FRAC))
+    }
+
+    /// sqrt returns the square root of the number, rounded toward zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number is negative.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::from_int(16).sqrt(), Fixed32::from_int(4));
+    /// assert_eq!(Fixed32::from_f64(0.25).sqrt(), Fixed32::from_f64(0.5));
+    /// ```
+    pub fn sqrt(self) -> Self {
+        let v = self.0.to_i128();
+        assert!(v >= 0, "square root of negative fixed-point number");
+        Fixed(I::from_i128(isqrt((v as u128) << FRAC) as i128))
+    }
+
+    /// sin_cos returns both the sine and cosine of the number (in radians).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed64;
+    ///
+    /// let (s, c) = Fixed64::from_f64(0.5).sin_cos();
+    /// assert!((s.to_f64() - 0.5f64.sin()).abs() < 1e-8);
+    /// assert!((c.to_f64() - 0.5f64.cos()).abs() < 1e-8);
+    /// ```
+    pub fn sin_cos(self) -> (Self, Self) {
+        let (s, c) = self.sin_cos_q();
+        (Self::from_q(s), Self::from_q(c))
+    }
+
+    // sin_cos_q returns the sine and cosine of the number in Q format.
+    fn sin_cos_q(self) -> (i128, i128) {
+        // Reduce the angle into [-pi, pi), and then into [-pi/2, pi/2]
+        // negating the cosine when reflecting.
+        let mut a = shift_round(self.0.to_i128(), Q as i32 - FRAC as i32).rem_euclid(TWO_PI_Q);
+        if a >= PI_Q {
+            a -= TWO_PI_Q;
+        }
+        let mut flip = false;
+        if a > HALF_PI_Q {
+            a = PI_Q - a;
+            flip = true;
+        } else if a < -HALF_PI_Q {
+            a = -PI_Q - a;
+            flip = true;
+        }
+        let (s, c) = cordic_sin_cos(a as i64);
+        let c = if flip { -c } else { c };
+        (s as i128, c as i128)
+    }
+
+    /// sin returns the sine of the number (in radians).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::pi().sin(), Fixed32::from_int(0));
+    /// ```
+    pub fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+
+    /// cos returns the cosine of the number (in radians).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(Fixed32::pi().cos(), Fixed32::from_int(-1));
+    /// ```
+    pub fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+
+    /// tan returns the tangent of the number (in radians). Near odd multiples
+    /// of π/2, where the tangent is beyond the range of the type, it
+    /// saturates at the maximum or minimum, with the sign of the sine over
+    /// the cosine (or of the sine, if the cosine is zero).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed64;
+    ///
+    /// let t = Fixed64::from_f64(0.3).tan();
+    /// assert!((t.to_f64() - 0.3f64.tan()).abs() < 1e-8);
+    /// assert_eq!((Fixed64::pi() / Fixed64::from_int(2)).tan(), Fixed64::from_bits(i64::MAX));
+    /// ```
+    pub fn tan(self) -> Self {
+        // The quotient of the unrounded sine and cosine is accurate even
+        // where the cosine would round to zero. tan is odd, but the reduction
+        // of the angle isn't exactly symmetric, so negative angles are
+        // reflected for consistent signs at the poles.
+        let bits = self.0.to_i128();
+        let magnitude = Fixed::<I, FRAC>(I::from_i128(bits.abs()));
+        let (s, c) = if bits < 0 && magnitude.0.to_i128() > 0 {
+            let (s, c) = magnitude.sin_cos_q();
+            (-s, c)
+        } else {
+            self.sin_cos_q()
+        };
+        let max = (1 << (I::BITS - 1)) - 1;
+        if (s.abs() << FRAC) >= c.abs() * max {
+            return if (s < 0) == (c < 0) { Self::max_value() } else { Self::min_value() };
+        }
+        Fixed(I::from_i128((s << FRAC) / c))
+    }
+
+    /// atan2 returns the four quadrant arctangent of `self` (y) and `x` in
+    /// radians, within the range [-π, π].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// let one = Fixed32::from_int(1);
+    /// let a = one.atan2(-one);
+    /// assert!((a.to_f64() - 1.0f64.atan2(-1.0)).abs() < 1e-4);
+    /// ```
+    pub fn atan2(self, x: Self) -> Self {
+        let (mut y, mut x) = (self.0.to_i128(), x.0.to_i128());
+        if x == 0 && y == 0 {
+            return Self::zero();
+        }
+
+        // Reflect into the right half-plane.
+        let offset = if x >= 0 {
+            0
+        } else if y >= 0 {
+            PI_Q
+        } else {
+            -PI_Q
+        };
+        if x < 0 {
+            x = -x;
+            y = -y;
+        }
+
+        // Scale the pair (preserving their ratio) so that the largest has its
+        // most significant bit at 2^58, giving CORDIC headroom and precision.
+        let m = x.abs().max(y.abs());
+        let shift = 58 - (127 - m.leading_zeros() as i32);
+        let (x, y) = if shift >= 0 {
+            (x << shift, y << shift)
+        } else {
+            (x >> -shift, y >> -shift)
+        };
+        Self::from_q(offset + cordic_atan(x as i64, y as i64) as i128)
+    }
+
+    /// atan returns the arctangent of the number in radians, within the range
+    /// [-π/2, π/2].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed64;
+    ///
+    /// let a = Fixed64::from_int(2).atan();
+    /// assert!((a.to_f64() - 2.0f64.atan()).abs() < 1e-8);
+    /// ```
+    pub fn atan(self) -> Self {
+        self.atan2(Self::one())
+    }
+
+    // from_q returns the fixed-point number nearest to the Q32 value v.
+    fn from_q(v: i128) -> Self {
+        Fixed(I::from_i128(shift_round(v, FRAC as i32 - Q as i32)))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> fmt::Debug for Fixed<I, FRAC> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Fixed({})", Fixed::to_f64(*self))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> fmt::Display for Fixed<I, FRAC> {
+    /// fmt formats the number in decimal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// assert_eq!(format!("{}", Fixed32::from_f64(-2.5)), "-2.5");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&Fixed::to_f64(*self), f)
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Add for Fixed<I, FRAC> {
+    type Output = Self;
+
+    fn add(self, _rhs: Self) -> Self {
+        Fixed(I::from_i128(self.0.to_i128() + _rhs.0.to_i128()))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Sub for Fixed<I, FRAC> {
+    type Output = Self;
+
+    fn sub(self, _rhs: Self) -> Self {
+        Fixed(I::from_i128(self.0.to_i128() - _rhs.0.to_i128()))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Neg for Fixed<I, FRAC> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Fixed(I::from_i128(-self.0.to_i128()))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Mul for Fixed<I, FRAC> {
+    type Output = Self;
+
+    /// mul multiplies the two numbers, rounding the result to nearest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// let x = Fixed32::from_f64(1.5) * Fixed32::from_f64(-2.0);
+    /// assert_eq!(x, Fixed32::from_int(-3));
+    /// ```
+    fn mul(self, _rhs: Self) -> Self {
+        let v = self.0.to_i128() * _rhs.0.to_i128();
+        Fixed(I::from_i128(shift_round(v, -(FRAC as i32))))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Div for Fixed<I, FRAC> {
+    type Output = Self;
+
+    /// div divides the two numbers, truncating the result toward zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `_rhs` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    ///
+    /// let x = Fixed32::from_int(3) / Fixed32::from_int(4);
+    /// assert_eq!(x, Fixed32::from_f64(0.75));
+    /// ```
+    fn div(self, _rhs: Self) -> Self {
+        Fixed(I::from_i128((self.0.to_i128() << FRAC) / _rhs.0.to_i128()))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Rem for Fixed<I, FRAC> {
+    type Output = Self;
+
+    fn rem(self, _rhs: Self) -> Self {
+        Fixed(I::from_i128(self.0.to_i128() % _rhs.0.to_i128()))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Zero for Fixed<I, FRAC> {
+    fn zero() -> Self {
+        Fixed(I::from_i128(0))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.to_i128() == 0
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> One for Fixed<I, FRAC> {
+    fn one() -> Self {
+        Fixed(I::from_i128(1 << FRAC))
+    }
+}
+
+/// ParseFixedError is returned when parsing a fixed-point number fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseFixedError;
+
+impl fmt::Display for ParseFixedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid fixed-point number literal")
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Num for Fixed<I, FRAC> {
+    type FromStrRadixErr = ParseFixedError;
+
+    /// from_str_radix parses a number of the form `[-]digits[.digits]` in the
+    /// given radix, rounding the fractional part to nearest exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Fixed32, Num};
+    ///
+    /// assert_eq!(Fixed32::from_str_radix("-1.25", 10), Ok(Fixed32::from_f64(-1.25)));
+    /// assert_eq!(Fixed32::from_str_radix("1.8", 16), Ok(Fixed32::from_f64(1.5)));
+    /// assert!(Fixed32::from_str_radix("1.2.3", 10).is_err());
+    /// ```
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseFixedError> {
+        let (neg, s) = if let Some(rest) = s.strip_prefix('-') {
+            (true, rest)
+        } else {
+            (false, s)
+        };
+        let mut parts = s.splitn(2, '.');
+        let int = parts.next().unwrap_or("");
+        let frac = parts.next().unwrap_or("");
+        if int.is_empty() && frac.is_empty() {
+            return Err(ParseFixedError);
+        }
+
+        let digit = |c: char| c.to_digit(radix).map(|d| d as i128).ok_or(ParseFixedError);
+        let mut v: i128 = 0;
+        for c in int.chars() {
+            let d = digit(c)?;
+            v = v.checked_mul(radix as i128).and_then(|v| v.checked_add(d)).ok_or(ParseFixedError)?;
+        }
+        v = v.checked_mul(1 << FRAC).ok_or(ParseFixedError)?;
+
+        // Accumulate the fractional part as an exact rational num / den, only
+        // considering as many digits as can affect the rounded result.
+        let (mut num, mut den): (i128, i128) = (0, 1);
+        for c in frac.chars() {
+            let d = digit(c)?;
+            if den < (1 << 80) {
+                num = num * radix as i128 + d;
+                den *= radix as i128;
+            }
+        }
+        v += ((num << FRAC) + den / 2) / den;
+        Ok(Fixed(I::from_i128(if neg { -v } else { v })))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Bounded for Fixed<I, FRAC> {
+    fn min_value() -> Self {
+        Fixed(I::from_i128(-1 << (I::BITS - 1)))
+    }
+
+    fn max_value() -> Self {
+        Fixed(I::from_i128((1 << (I::BITS - 1)) - 1))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Signed for Fixed<I, FRAC> {
+    fn abs(&self) -> Self {
+        if self.is_negative() { -*self } else { *self }
+    }
+
+    fn abs_sub(&self, other: &Self) -> Self {
+        if *self <= *other { Self::zero() } else { *self - *other }
+    }
+
+    fn signum(&self) -> Self {
+        match self.0.to_i128().cmp(&0) {
+            Ordering::Less => -Self::one(),
+            Ordering::Equal => Self::zero(),
+            Ordering::Greater => Self::one(),
+        }
+    }
+
+    fn is_positive(&self) -> bool {
+        self.0.to_i128() > 0
+    }
+
+    fn is_negative(&self) -> bool {
+        self.0.to_i128() < 0
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> ToPrimitive for Fixed<I, FRAC> {
+    fn to_i64(&self) -> Option<i64> {
+        Some(self.to_int())
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        let v = self.to_int();
+        if v < 0 { None } else { Some(v as u64) }
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        Some(Fixed::to_f64(*self))
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> NumCast for Fixed<I, FRAC> {
+    /// from converts the primitive number into a fixed-point number. Integers
+    /// are converted exactly, other values as described by `from_f64`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Fixed32;
+    /// use fiz_math::num_export::traits::NumCast;
+    ///
+    /// assert_eq!(<Fixed32 as NumCast>::from(1000), Some(Fixed32::from_int(1000)));
+    /// assert_eq!(<Fixed32 as NumCast>::from(0.5), Some(Fixed32::from_f64(0.5)));
+    /// ```
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        let f = n.to_f64()?;
+        if f.fract() == 0.0 {
+            if let Some(i) = n.to_i64() {
+                return Some(Fixed::from_int(i));
+            }
+        }
+        if f.is_finite() { Some(Fixed::from_f64(f)) } else { None }
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Clamp for Fixed<I, FRAC> {
+    type Elem = Self;
+
+    fn clamp(self, min: Self, max: Self) -> Self {
+        Ord::clamp(self, min, max)
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Vec2<Fixed<I, FRAC>> {
+    /// length returns the magnitude of this vector. The squared components are
+    /// summed at full precision, so it does not overflow even when
+    /// `length_sq` would, and a length beyond the range of the type saturates
+    /// at its maximum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Vec2, Fixed32};
+    ///
+    /// let v = Vec2(Fixed32::from_int(-6), Fixed32::from_int(8));
+    /// assert_eq!(v.length(), Fixed32::from_int(10));
+    /// ```
+    pub fn length(self) -> Fixed<I, FRAC> {
+        Fixed(hypot(&[self.0.to_bits(), self.1.to_bits()]))
+    }
+
+    /// normalize returns the normalized (i.e. length/magnitude == 1) vector
+    /// representing self. If the vector's length is zero, then None is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Vec2, Fixed32};
+    ///
+    /// let v = Vec2(Fixed32::from_int(0), Fixed32::from_int(5));
+    /// assert_eq!(v.normalize(), Some(Vec2(Fixed32::from_int(0), Fixed32::from_int(1))));
+    /// ```
+    pub fn normalize(self) -> Option<Self> {
+        let length = self.length();
+        if length.is_zero() {
+            None
+        } else {
+            Some(self.div_scalar(length))
+        }
+    }
+}
+
+impl<I: FixedInt, const FRAC: u32> Vec3<Fixed<I, FRAC>> {
+    /// length returns the magnitude of this vector. The squared components are
+    /// summed at full precision, so it does not overflow even when
+    /// `length_sq` would, and a length beyond the range of the type saturates
+    /// at its maximum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Vec3, Fixed32};
+    ///
+    /// let v = Vec3(Fixed32::from_int(2), Fixed32::from_int(3), Fixed32::from_int(6));
+    /// assert_eq!(v.length(), Fixed32::from_int(7));
+    /// ```
+    pub fn length(self) -> Fixed<I, FRAC> {
+        Fixed(hypot(&[self.0.to_bits(), self.1.to_bits(), self.2.to_bits()]))
+    }
+
+    /// normalize returns the normalized (i.e. length/magnitude == 1) vector
+    /// representing self. If the vector's length is zero, then None is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Vec3, Fixed32};
+    ///
+    /// let v = Vec3(Fixed32::from_int(0), Fixed32::from_int(0), Fixed32::from_int(-3));
+    /// let n = v.normalize().unwrap();
+    /// assert_eq!(n, Vec3(Fixed32::from_int(0), Fixed32::from_int(0), Fixed32::from_int(-1)));
+    /// ```
+    pub fn normalize(self) -> Option<Self> {
+        let length = self.length();
+        if length.is_zero() {
+            None
+        } else {
+            Some(self.div_scalar(length))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f64::consts::FRAC_PI_2;
+    use super::{Fixed32, Fixed64};
+    use num::traits::Num;
+    use {Vec2, Vec3};
+
+    // The expected values below are raw bit patterns. Since all operations are
+    // carried out with integer arithmetic they must match exactly on every
+    // platform, any change to them breaks lockstep compatibility.
+
+    #[test]
+    fn golden_trig() {
+        let mut bits = Vec::new();
+        for i in -8..9 {
+            let (s, c) = Fixed32::from_f64(i as f64 * 0.8).sin_cos();
+            bits.push((s.to_bits(), c.to_bits()));
+        }
+        assert_eq!(bits, GOLDEN_TRIG.to_vec());
+
+        let a = Fixed64::from_int(3).atan2(Fixed64::from_int(-4));
+        assert_eq!(a.to_bits(), GOLDEN_ATAN2);
+    }
+
+    #[test]
+    fn golden_sqrt_length() {
+        assert_eq!(Fixed32::from_int(2).sqrt().to_bits(), 92681);
+        assert_eq!(Fixed64::from_int(2).sqrt().to_bits(), 6074000999);
+
+        let v = Vec3(Fixed32::from_f64(1.1), Fixed32::from_f64(-2.2), Fixed32::from_f64(3.3));
+        let n = v.normalize().unwrap();
+        assert_eq!((v.length().to_bits(), n.0.to_bits(), n.1.to_bits(), n.2.to_bits()),
+                   GOLDEN_NORMALIZE);
+    }
+
+    #[test]
+    fn tan_poles() {
+        // The nearest Fixed32 to π/2 is just past it, and the nearest Fixed64
+        // just before it. Tangents beyond the range saturate.
+        let half_pi = Fixed32::from_f64(FRAC_PI_2);
+        let ulp = Fixed32::from_bits(1);
+        let got: Vec<i32> = [half_pi - ulp, half_pi, half_pi + ulp, -half_pi, -half_pi - ulp, half_pi + Fixed32::from_bits(4)]
+            .iter()
+            .map(|a| a.tan().to_bits())
+            .collect();
+        assert_eq!(got, GOLDEN_TAN32.to_vec());
+
+        let half_pi = Fixed64::from_f64(FRAC_PI_2);
+        let ulp = Fixed64::from_bits(1);
+        let got: Vec<i64> = [half_pi - ulp, half_pi, half_pi + ulp, -half_pi, -half_pi - ulp]
+            .iter()
+            .map(|a| a.tan().to_bits())
+            .collect();
+        assert_eq!(got, GOLDEN_TAN64.to_vec());
+    }
+
+    #[test]
+    fn length_extremes() {
+        let (min, max, zero) = (Fixed64::from_bits(i64::MIN), Fixed64::from_bits(i64::MAX), Fixed64::from_int(0));
+        assert_eq!(Vec3(zero, max, zero).length(), max);
+        assert_eq!(Vec3(min, zero, zero).length(), max);
+        assert_eq!(Vec3(min, min, min).length(), max);
+        assert_eq!(Vec2(min, min).length(), max);
+
+        // floor(sqrt(3) * 2^62)
+        let half = Fixed64::from_bits(1 << 62);
+        assert_eq!(Vec3(half, -half, half).length().to_bits(), 7987674492471257550);
+    }
+
+    #[test]
+    fn trig_accuracy() {
+        for i in -1000..1000 {
+            let x = i as f64 * 0.0123;
+            let (s, c) = Fixed64::from_f64(x).sin_cos();
+            assert!((s.to_f64() - x.sin()).abs() < 1e-8, "sin({})", x);
+            assert!((c.to_f64() - x.cos()).abs() < 1e-8, "cos({})", x);
+
+            let a = Fixed64::from_f64(x.sin()).atan2(Fixed64::from_f64(x.cos()));
+            let expect = x.sin().atan2(x.cos());
+            assert!((a.to_f64() - expect).abs() < 1e-8, "atan2 at {}", x);
+        }
+    }
+
+    #[test]
+    fn lockstep_simulation() {
+        // A small particle simulation, its final state must never change.
+        let dt = Fixed32::from_str_radix("0.016", 10).unwrap();
+        let mut pos = Vec2(Fixed32::from_int(0), Fixed32::from_int(10));
+        let mut vel = Vec2(Fixed32::from_int(3), Fixed32::from_int(0));
+        let mut angle = Fixed32::from_int(0);
+        for _ in 0..1000 {
+            let (s, c) = angle.sin_cos();
+            vel = vel + Vec2(c, s).mul_scalar(dt);
+            pos = pos + vel.mul_scalar(dt);
+            if let Some(n) = pos.normalize() {
+                angle = angle + n.1 * dt;
+            }
+        }
+        assert_eq!((pos.0.to_bits(), pos.1.to_bits(), angle.to_bits()), GOLDEN_SIMULATION);
+    }
+
+    const GOLDEN_TRIG: [(i32, i32); 17] = [(-7638, 65089), (41370, 50828), (65285, 5735),
+                                          (49598, -42837), (3825, -65424), (-44267, -48326),
+                                          (-65508, -1914), (-47013, 45659), (0, 65536),
+                                          (47013, 45659), (65508, -1914), (44267, -48326),
+                                          (-3825, -65424), (-49598, -42837), (-65285, 5735),
+                                          (-41370, 50828), (7638, 65089)];
+    const GOLDEN_ATAN2: i64 = 10729221488;
+    const GOLDEN_TAN32: [i32; 6] = [i32::MAX, i32::MIN, i32::MIN, i32::MAX, i32::MAX, -1000728752];
+    const GOLDEN_TAN64: [i64; 5] = [i64::MAX, i64::MAX, i64::MIN, i64::MIN, i64::MAX];
+    const GOLDEN_NORMALIZE: (i32, i32, i32, i32) = (269734, 17515, -35030, 52545);
+    const GOLDEN_SIMULATION: (i32, i32, i32) = (3098212, 2191918, 693877);
+}
@@ -0,0 +1,160 @@
+use super::float::Float;
+use super::unit::ToRad;
+use super::{Vec3, Aabb};
+
+/// Plane is an infinite plane, described by its unit normal and its signed
+/// distance from the origin such that points p on the plane satisfy
+/// `normal.dot(p) + distance == 0`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Plane, Vec3};
+///
+/// let p = Plane::from_point(Vec3(0.0, 1.0, 0.0), Vec3(5.0, 2.0, 5.0));
+/// assert_eq!(p.signed_distance(Vec3(0.0, 3.0, 0.0)), 1.0);
+/// assert_eq!(p.signed_distance(Vec3(0.0, 0.0, 0.0)), -2.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane<T> {
+    /// The unit normal of the plane, pointing to its front side.
+    pub normal: Vec3<T>,
+
+    /// The signed distance of the plane from the origin, along its normal
+    /// (negated).
+    pub distance: T,
+}
+
+impl<T: Float> Plane<T> {
+    /// new returns a new plane with the given normal and distance.
+    pub fn new(normal: Vec3<T>, distance: T) -> Self {
+        Plane {
+            normal,
+            distance,
+        }
+    }
+
+    /// from_point returns the plane with the given unit normal that passes
+    /// through the given point.
+    pub fn from_point(normal: Vec3<T>, point: Vec3<T>) -> Self {
+        Plane {
+            normal,
+            distance: -normal.dot(point),
+        }
+    }
+
+    /// signed_distance returns the distance from the plane to p, which is
+    /// positive in front of the plane and negative behind it.
+    pub fn signed_distance(&self, p: Vec3<T>) -> T {
+        self.normal.dot(p) + self.distance
+    }
+}
+
+/// Containment describes how a volume relates to another containing volume.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Containment {
+    /// The volume lies entirely outside.
+    Outside,
+
+    /// The volume is partially inside.
+    Intersecting,
+
+    /// The volume lies entirely inside.
+    Inside,
+}
+
+/// Frustum is a convex volume bounded by six planes, typically the volume
+/// visible to a camera. The normals of the planes point into the frustum.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Frustum, Containment, Aabb, Vec3};
+/// use fiz_math::unit::Deg;
+///
+/// let f = Frustum::perspective(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, -1.0), Vec3(0.0, 1.0, 0.0),
+///                              Deg(90.0), 1.0, 0.1, 100.0);
+/// assert!(f.contains(Vec3(0.0, 0.0, -10.0)));
+/// assert!(!f.contains(Vec3(0.0, 0.0, 10.0)));
+///
+/// let b = Aabb::new(Vec3(-1.0, -1.0, -6.0), Vec3(1.0, 1.0, -4.0));
+/// assert_eq!(f.classify(&b), Containment::Inside);
+/// let b = Aabb::new(Vec3(-1.0, -1.0, -1.0), Vec3(1.0, 1.0, 1.0));
+/// assert_eq!(f.classify(&b), Containment::Intersecting);
+/// let b = Aabb::new(Vec3(10.0, -1.0, -6.0), Vec3(12.0, 1.0, -4.0));
+/// assert_eq!(f.classify(&b), Containment::Outside);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum<T> {
+    /// The planes bounding the frustum.
+    pub planes: [Plane<T>; 6],
+}
+
+impl<T: Float> Frustum<T> {
+    /// new returns a new frustum bounded by the given planes, whose normals
+    /// must point into the frustum.
+    pub fn new(planes: [Plane<T>; 6]) -> Self {
+        Frustum { planes }
+    }
+
+    /// perspective returns the frustum of a perspective camera at eye looking
+    /// along forward, with the given vertical field of view, aspect ratio
+    /// (width / height) and near and far clipping distances.
+    pub fn perspective<A>(eye: Vec3<T>,
+                          forward: Vec3<T>,
+                          up: Vec3<T>,
+                          fov_y: A,
+                          aspect: T,
+                          near: T,
+                          far: T)
+                          -> Self
+        where A: ToRad<Output = T>
+    {
+        let f = forward.normalize().expect("Frustum::perspective: zero forward vector");
+        let r = f.cross(up).normalize().expect("Frustum::perspective: up is parallel to forward");
+        let u = r.cross(f);
+        let tan_y = (fov_y.to_rad().0 / (T::one() + T::one())).tan();
+        let tan_x = tan_y * aspect;
+        let side = |n: Vec3<T>| Plane::from_point(n.normalize().unwrap(), eye);
+        Frustum {
+            planes: [Plane::from_point(f, eye + f.mul_scalar(near)),
+                     Plane::from_point(-f, eye + f.mul_scalar(far)),
+                     side(r + f.mul_scalar(tan_x)),
+                     side(-r + f.mul_scalar(tan_x)),
+                     side(u + f.mul_scalar(tan_y)),
+                     side(-u + f.mul_scalar(tan_y))],
+        }
+    }
+
+    /// contains tells if the point lies within the frustum (or on its
+    /// boundary).
+    pub fn contains(&self, p: Vec3<T>) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(p) >= T::zero())
+    }
+
+    /// classify tells whether the box lies inside, outside or partially
+    /// inside the frustum. The test is conservative: boxes near the corners
+    /// of the frustum may be reported as intersecting while actually lying
+    /// outside.
+    pub fn classify(&self, b: &Aabb<T>) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in &self.planes {
+            let n = plane.normal;
+            let pick = |n: T, lo: T, hi: T| if n >= T::zero() { (hi, lo) } else { (lo, hi) };
+            let (x, nx) = pick(n.0, b.min.0, b.max.0);
+            let (y, ny) = pick(n.1, b.min.1, b.max.1);
+            let (z, nz) = pick(n.2, b.min.2, b.max.2);
+
+            // The corner furthest along the normal decides if the box is
+            // entirely behind the plane, the nearest corner if it's entirely
+            // in front of it.
+            if plane.signed_distance(Vec3(x, y, z)) < T::zero() {
+                return Containment::Outside;
+            }
+            if plane.signed_distance(Vec3(nx, ny, nz)) < T::zero() {
+                result = Containment::Intersecting;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,343 @@
+//! k-d trees over static point sets.
+//!
+//! A `KdTree` is built once over a set of points, such as `Vec2` or `Vec3`
+//! (any `spatial::Point`), and answers nearest neighbor, radius and box
+//! queries over them. The tree is stored flat, as a balanced binary tree
+//! implicit in a single reordered array of points, so it uses no per-node
+//! allocations or pointers.
+//!
+//! Queries return the indices of the points in the slice the tree was built
+//! from, and squared distances, which allows the tree to work with any
+//! numeric component type, including integers.
+
+use std::cmp::Ordering;
+use num::{Num, Zero};
+use super::spatial::Point;
+
+/// KdTree is a k-d tree over a static set of points.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::kdtree::KdTree;
+///
+/// let points = [Vec2(0, 0), Vec2(5, 1), Vec2(2, 2), Vec2(-3, 4), Vec2(1, -1)];
+/// let tree = KdTree::build(&points);
+///
+/// // The two points nearest to (3, 1), and their squared distances.
+/// assert_eq!(tree.nearest(Vec2(3, 1), 2), vec![(2, 2), (1, 4)]);
+///
+/// let mut near = tree.query_radius(Vec2(0, 0), 2);
+/// near.sort();
+/// assert_eq!(near, vec![0, 4]);
+///
+/// let mut inside = tree.query_box(Vec2(0, 0), Vec2(5, 2));
+/// inside.sort();
+/// assert_eq!(inside, vec![0, 1, 2]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KdTree<P> {
+    // The points, ordered such that the point at the middle of any subtree's
+    // range splits it, with the points before it on one side of the
+    // splitting plane and the points after it on the other.
+    points: Vec<P>,
+
+    // The index of each point in the slice the tree was built from.
+    indices: Vec<usize>,
+
+    // The splitting axis of the subtree whose middle point is at each index.
+    axes: Vec<u8>,
+}
+
+impl<P: Point> KdTree<P> {
+    /// build returns a new tree over the given points, in O(n log n) time on
+    /// average.
+    pub fn build(points: &[P]) -> Self {
+        let mut entries: Vec<(P, usize)> = points.iter().cloned().zip(0..).collect();
+        let mut axes = vec![0; points.len()];
+        build(&mut entries, &mut axes);
+        KdTree {
+            points: entries.iter().map(|e| e.0).collect(),
+            indices: entries.iter().map(|e| e.1).collect(),
+            axes,
+        }
+    }
+
+    /// len returns the number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// is_empty tells if the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// nearest returns (up to) the k points nearest to p, as pairs of their
+    /// index and squared distance, ordered by increasing distance.
+    pub fn nearest(&self, p: P, k: usize) -> Vec<(usize, P::Scalar)> {
+        self.nearest_approx(p, k, usize::MAX)
+    }
+
+    /// nearest_approx is like nearest, but stops searching after examining
+    /// max_checks points. The results are then approximate: they may miss
+    /// some of the actual nearest points, which trades accuracy for speed on
+    /// large trees. Points are examined nearest region first, so even small
+    /// limits usually give good results.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec3;
+    /// use fiz_math::kdtree::KdTree;
+    ///
+    /// let points: Vec<_> = (0..1000).map(|i| Vec3((i % 10) as f64, (i / 10 % 10) as f64, (i / 100) as f64)).collect();
+    /// let tree = KdTree::build(&points);
+    /// let p = Vec3(4.2, 5.1, 6.0);
+    /// let exact = tree.nearest(p, 1);
+    /// assert_eq!(exact[0].0, 654);
+    ///
+    /// let approx = tree.nearest_approx(p, 1, 32);
+    /// assert!(approx[0].1 >= exact[0].1);
+    /// ```
+    pub fn nearest_approx(&self, p: P, k: usize, max_checks: usize) -> Vec<(usize, P::Scalar)> {
+        let mut search = Search {
+            p,
+            k,
+            checks: max_checks,
+            best: Vec::with_capacity(k + 1),
+        };
+        if k > 0 {
+            self.search(0, self.points.len(), &mut search);
+        }
+        search.best.into_iter().map(|(i, d)| (self.indices[i], d)).collect()
+    }
+
+    /// query_radius returns the indices of the points within distance r of
+    /// p, in no particular order.
+    pub fn query_radius(&self, p: P, r: P::Scalar) -> Vec<usize> {
+        let mut out = Vec::new();
+        self.radius(0, self.points.len(), p, r * r, &mut out);
+        out
+    }
+
+    /// query_box returns the indices of the points within the box with the
+    /// given minimum and maximum corners (inclusive), in no particular
+    /// order.
+    pub fn query_box(&self, min: P, max: P) -> Vec<usize> {
+        let mut out = Vec::new();
+        self.boxed(0, self.points.len(), &min, &max, &mut out);
+        out
+    }
+
+    fn search(&self, lo: usize, hi: usize, s: &mut Search<P>) {
+        if lo >= hi || s.checks == 0 {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let point = &self.points[mid];
+        s.checks -= 1;
+        s.offer(mid, distance_sq(&s.p, point));
+
+        // Search the side of the splitting plane containing p first, then
+        // the other side if the plane is closer than the k-th best point.
+        let axis = self.axes[mid] as usize;
+        let (v, split) = (s.p.axis(axis), point.axis(axis));
+        let near = if v < split { (lo, mid) } else { (mid + 1, hi) };
+        let far = if v < split { (mid + 1, hi) } else { (lo, mid) };
+        self.search(near.0, near.1, s);
+        let gap = difference(v, split);
+        if s.best.len() < s.k || gap * gap < s.best[s.k - 1].1 {
+            self.search(far.0, far.1, s);
+        }
+    }
+
+    fn radius(&self, lo: usize, hi: usize, p: P, r2: P::Scalar, out: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let point = &self.points[mid];
+        if distance_sq(&p, point) <= r2 {
+            out.push(self.indices[mid]);
+        }
+        let axis = self.axes[mid] as usize;
+        let (v, split) = (p.axis(axis), point.axis(axis));
+        let gap = difference(v, split);
+        if v <= split || gap * gap <= r2 {
+            self.radius(lo, mid, p, r2, out);
+        }
+        if v >= split || gap * gap <= r2 {
+            self.radius(mid + 1, hi, p, r2, out);
+        }
+    }
+
+    fn boxed(&self, lo: usize, hi: usize, min: &P, max: &P, out: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let point = &self.points[mid];
+        if (0..P::DIM).all(|i| min.axis(i) <= point.axis(i) && point.axis(i) <= max.axis(i)) {
+            out.push(self.indices[mid]);
+        }
+        let axis = self.axes[mid] as usize;
+        let split = point.axis(axis);
+        if min.axis(axis) <= split {
+            self.boxed(lo, mid, min, max, out);
+        }
+        if max.axis(axis) >= split {
+            self.boxed(mid + 1, hi, min, max, out);
+        }
+    }
+}
+
+// The state of a nearest neighbor search.
+struct Search<P: Point> {
+    p: P,
+    k: usize,
+
+    // The number of points which may still be examined.
+    checks: usize,
+
+    // The best points found so far, ordered by increasing squared distance.
+    best: Vec<(usize, P::Scalar)>,
+}
+
+impl<P: Point> Search<P> {
+    // offer adds the point to the best points, if it's one of the k nearest
+    // so far.
+    fn offer(&mut self, i: usize, d: P::Scalar) {
+        if self.best.len() == self.k && d >= self.best[self.k - 1].1 {
+            return;
+        }
+        let at = self.best.iter().position(|b| d < b.1).unwrap_or(self.best.len());
+        self.best.insert(at, (i, d));
+        self.best.truncate(self.k);
+    }
+}
+
+// build arranges the entries into an implicit balanced tree, by recursively
+// partitioning them around the median along the axis of largest spread.
+fn build<P: Point>(entries: &mut [(P, usize)], axes: &mut [u8]) {
+    if entries.len() <= 1 {
+        return;
+    }
+    let mut axis = 0;
+    let mut spread = None;
+    for i in 0..P::DIM {
+        let mut lo = entries[0].0.axis(i);
+        let mut hi = lo;
+        for e in entries.iter() {
+            let v = e.0.axis(i);
+            if v < lo {
+                lo = v;
+            }
+            if v > hi {
+                hi = v;
+            }
+        }
+        let s = hi - lo;
+        if spread.is_none_or(|x| s > x) {
+            spread = Some(s);
+            axis = i;
+        }
+    }
+
+    let mid = entries.len() / 2;
+    entries.select_nth_unstable_by(mid, |a, b| a.0.axis(axis).partial_cmp(&b.0.axis(axis)).unwrap_or(Ordering::Equal));
+    axes[mid] = axis as u8;
+    let (left, right) = entries.split_at_mut(mid);
+    let (left_axes, right_axes) = axes.split_at_mut(mid);
+    build(left, left_axes);
+    build(&mut right[1..], &mut right_axes[1..]);
+}
+
+// difference returns the absolute difference of a and b, which is safe for
+// unsigned types.
+fn difference<T: Num + PartialOrd>(a: T, b: T) -> T {
+    if a > b { a - b } else { b - a }
+}
+
+// distance_sq returns the squared distance between a and b.
+fn distance_sq<P: Point>(a: &P, b: &P) -> P::Scalar {
+    (0..P::DIM).fold(P::Scalar::zero(), |sum, i| {
+        let d = difference(a.axis(i), b.axis(i));
+        sum + d * d
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KdTree, Point, distance_sq};
+    use {Vec2, Vec3};
+    use testing::lcg;
+
+    // check compares the results of every query against a brute force search
+    // of the points.
+    fn check<P: Point>(tree: &KdTree<P>, points: &[P], p: P, r: P::Scalar, min: P, max: P, k: usize) {
+        let mut got = tree.query_radius(p, r);
+        got.sort();
+        let want: Vec<usize> = (0..points.len()).filter(|&i| distance_sq(&p, &points[i]) <= r * r).collect();
+        assert_eq!(got, want);
+
+        let mut got = tree.query_box(min, max);
+        got.sort();
+        let want: Vec<usize> = (0..points.len())
+            .filter(|&i| (0..P::DIM).all(|a| min.axis(a) <= points[i].axis(a) && points[i].axis(a) <= max.axis(a)))
+            .collect();
+        assert_eq!(got, want);
+
+        let got = tree.nearest(p, k);
+        for &(i, d) in &got {
+            assert_eq!(distance_sq(&p, &points[i]), d);
+        }
+        let got: Vec<P::Scalar> = got.into_iter().map(|n| n.1).collect();
+        let mut want: Vec<P::Scalar> = points.iter().map(|q| distance_sq(&p, q)).collect();
+        want.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        want.truncate(k);
+        assert_eq!(got, want);
+
+        // Approximate results are never better than the exact ones, and are
+        // exact when every point may be checked.
+        let approx = tree.nearest_approx(p, k, 8);
+        assert!(approx.iter().zip(want.iter()).all(|(a, &w)| a.1 >= w));
+        let approx: Vec<P::Scalar> = tree.nearest_approx(p, k, points.len()).into_iter().map(|n| n.1).collect();
+        assert_eq!(approx, want);
+    }
+
+    #[test]
+    fn against_brute_force_f64() {
+        let mut s = 3;
+        for &n in &[0, 1, 2, 7, 100, 1000] {
+            let points: Vec<Vec3<f64>> =
+                (0..n).map(|_| Vec3(lcg(&mut s) * 100.0, lcg(&mut s) * 10.0, lcg(&mut s) * 50.0)).collect();
+            let tree = KdTree::build(&points);
+            assert_eq!(tree.len(), n);
+            for _ in 0..30 {
+                let p = Vec3(lcg(&mut s) * 120.0 - 10.0, lcg(&mut s) * 20.0 - 5.0, lcg(&mut s) * 60.0 - 5.0);
+                let q = Vec3(lcg(&mut s) * 120.0 - 10.0, lcg(&mut s) * 20.0 - 5.0, lcg(&mut s) * 60.0 - 5.0);
+                let min = Vec3(p.0.min(q.0), p.1.min(q.1), p.2.min(q.2));
+                let max = Vec3(p.0.max(q.0), p.1.max(q.1), p.2.max(q.2));
+                let k = (lcg(&mut s) * 12.0) as usize;
+                check(&tree, &points, p, lcg(&mut s) * 20.0, min, max, k);
+            }
+        }
+    }
+
+    #[test]
+    fn against_brute_force_integer() {
+        // Small integer coordinates give many duplicate points and ties.
+        let mut s = 5;
+        let points: Vec<Vec2<u32>> = (0..500).map(|_| Vec2((lcg(&mut s) * 20.0) as u32, (lcg(&mut s) * 20.0) as u32)).collect();
+        let tree = KdTree::build(&points);
+        for _ in 0..50 {
+            let p = Vec2((lcg(&mut s) * 25.0) as u32, (lcg(&mut s) * 25.0) as u32);
+            let q = Vec2((lcg(&mut s) * 25.0) as u32, (lcg(&mut s) * 25.0) as u32);
+            let (min, max) = (Vec2(p.0.min(q.0), p.1.min(q.1)), Vec2(p.0.max(q.0), p.1.max(q.1)));
+            let k = (lcg(&mut s) * 30.0) as usize;
+            check(&tree, &points, p, (lcg(&mut s) * 6.0) as u32, min, max, k);
+        }
+    }
+}
@@ -12,7 +12,23 @@ mod vec3;
 mod vec4;
 mod float;
 mod clamp;
+mod fixed;
+mod aabb;
+mod ray;
+mod frustum;
+mod mat4;
+mod quat;
+mod transform;
 pub mod unit;
+pub mod predicates;
+pub mod polygon;
+pub mod convex;
+pub mod bvh;
+pub mod spatial;
+pub mod kdtree;
+
+#[cfg(test)]
+mod testing;
 
 pub use num::{Zero, One, Num};
 pub use self::vec2::Vec2;
@@ -20,3 +36,10 @@ pub use self::vec3::Vec3;
 pub use self::vec4::Vec4;
 pub use self::float::{EPSILON, Float};
 pub use self::clamp::Clamp;
+pub use self::fixed::{Fixed, FixedInt, Fixed32, Fixed64, ParseFixedError};
+pub use self::aabb::Aabb;
+pub use self::ray::Ray;
+pub use self::frustum::{Plane, Frustum, Containment};
+pub use self::mat4::Mat4;
+pub use self::quat::Quat;
+pub use self::transform::Transform;
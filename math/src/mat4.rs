@@ -0,0 +1,232 @@
+use std::ops::Mul;
+use super::float::Float;
+use super::unit::ToRad;
+use super::{Vec3, Vec4};
+
+/// Mat4 is a 4x4 matrix, stored as its four columns, for transforming
+/// homogeneous coordinates. Vectors are columns, multiplied on the right, so
+/// `(a * b).mul_vec4(v)` applies `b` to `v` and then `a`.
+///
+/// Projections follow the OpenGL conventions: the camera looks down -Z, and
+/// visible points have clip-space `x`, `y` and `z` between `-w` and `w`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Mat4, Vec3};
+///
+/// let m = Mat4::translation(Vec3(1.0, 2.0, 3.0)) * Mat4::scale(Vec3(2.0, 2.0, 2.0));
+/// assert_eq!(m.transform_point(Vec3(1.0, 1.0, 1.0)), Vec3(3.0, 4.0, 5.0));
+/// assert_eq!(m.transform_vector(Vec3(1.0, 1.0, 1.0)), Vec3(2.0, 2.0, 2.0));
+/// assert_eq!(m.inverse().unwrap().transform_point(Vec3(3.0, 4.0, 5.0)), Vec3(1.0, 1.0, 1.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Mat4<T>(pub Vec4<T>, pub Vec4<T>, pub Vec4<T>, pub Vec4<T>);
+
+impl<T: Float> Mat4<T> {
+    // from_rows returns the matrix with the given rows, which read as the
+    // matrix is written.
+    fn from_rows(r: [[T; 4]; 4]) -> Self {
+        let col = |c: usize| Vec4(r[0][c], r[1][c], r[2][c], r[3][c]);
+        Mat4(col(0), col(1), col(2), col(3))
+    }
+
+    // to_rows returns the rows of the matrix.
+    fn to_rows(self) -> [[T; 4]; 4] {
+        let (a, b, c, d) = (self.0, self.1, self.2, self.3);
+        [[a.0, b.0, c.0, d.0], [a.1, b.1, c.1, d.1], [a.2, b.2, c.2, d.2], [a.3, b.3, c.3, d.3]]
+    }
+
+    /// identity returns the identity matrix.
+    pub fn identity() -> Self {
+        let (o, z) = (T::one(), T::zero());
+        Mat4(Vec4(o, z, z, z), Vec4(z, o, z, z), Vec4(z, z, o, z), Vec4(z, z, z, o))
+    }
+
+    /// translation returns the matrix translating points by v.
+    pub fn translation(v: Vec3<T>) -> Self {
+        let mut m = Mat4::identity();
+        m.3 = Vec4(v.0, v.1, v.2, T::one());
+        m
+    }
+
+    /// scale returns the matrix scaling along each axis by the components of
+    /// v.
+    pub fn scale(v: Vec3<T>) -> Self {
+        let z = T::zero();
+        Mat4(Vec4(v.0, z, z, z), Vec4(z, v.1, z, z), Vec4(z, z, v.2, z), Vec4(z, z, z, T::one()))
+    }
+
+    /// look_at returns the view matrix of a camera at eye looking towards
+    /// target, with the given upwards direction: it maps eye to the origin,
+    /// target onto -Z, and up into the +Y half of the YZ plane.
+    ///
+    /// # Panics
+    ///
+    /// look_at panics if the eye and target are equal, or up is parallel to
+    /// the view direction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Mat4, Vec3};
+    ///
+    /// let view = Mat4::look_at(Vec3(0.0, 0.0, 5.0), Vec3(0.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+    /// assert_eq!(view.transform_point(Vec3(1.0, 0.0, 0.0)), Vec3(1.0, 0.0, -5.0));
+    /// ```
+    pub fn look_at(eye: Vec3<T>, target: Vec3<T>, up: Vec3<T>) -> Self {
+        let f = (target - eye).normalize().expect("Mat4::look_at: eye and target are equal");
+        let s = f.cross(up).normalize().expect("Mat4::look_at: up is parallel to the view direction");
+        let u = s.cross(f);
+        let (z, o) = (T::zero(), T::one());
+        Mat4::from_rows([[s.0, s.1, s.2, -s.dot(eye)],
+                         [u.0, u.1, u.2, -u.dot(eye)],
+                         [-f.0, -f.1, -f.2, f.dot(eye)],
+                         [z, z, z, o]])
+    }
+
+    /// perspective returns a perspective projection with the given vertical
+    /// field of view, aspect ratio (width / height) and near and far
+    /// clipping distances.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Mat4, Vec3};
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let p = Mat4::perspective(Deg(90.0), 2.0, 1.0, 10.0);
+    /// assert!(p.transform_point(Vec3(2.0, 1.0, -1.0)).almost_equal(Vec3(1.0, 1.0, -1.0), 1e-6));
+    /// assert!(p.transform_point(Vec3(0.0, 0.0, -10.0)).almost_equal(Vec3(0.0, 0.0, 1.0), 1e-6));
+    /// ```
+    pub fn perspective<A>(fov_y: A, aspect: T, near: T, far: T) -> Self
+        where A: ToRad<Output = T>
+    {
+        let two = T::one() + T::one();
+        let f = T::one() / (fov_y.to_rad().0 / two).tan();
+        let (z, o) = (T::zero(), T::one());
+        Mat4::from_rows([[f / aspect, z, z, z],
+                         [z, f, z, z],
+                         [z, z, (far + near) / (near - far), two * far * near / (near - far)],
+                         [z, z, -o, z]])
+    }
+
+    /// orthographic returns an orthographic projection of the box between
+    /// the given left, right, bottom and top planes, and near and far
+    /// distances along -Z.
+    pub fn orthographic(left: T, right: T, bottom: T, top: T, near: T, far: T) -> Self {
+        let two = T::one() + T::one();
+        let (z, o) = (T::zero(), T::one());
+        Mat4::from_rows([[two / (right - left), z, z, -(right + left) / (right - left)],
+                         [z, two / (top - bottom), z, -(top + bottom) / (top - bottom)],
+                         [z, z, -two / (far - near), -(far + near) / (far - near)],
+                         [z, z, z, o]])
+    }
+
+    /// transpose returns the matrix with its rows and columns swapped.
+    pub fn transpose(&self) -> Self {
+        let r = self.to_rows();
+        Mat4(Vec4(r[0][0], r[0][1], r[0][2], r[0][3]),
+             Vec4(r[1][0], r[1][1], r[1][2], r[1][3]),
+             Vec4(r[2][0], r[2][1], r[2][2], r[2][3]),
+             Vec4(r[3][0], r[3][1], r[3][2], r[3][3]))
+    }
+
+    /// inverse returns the inverse of the matrix, or None if it's singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let m = self.to_rows();
+        // The 2x2 determinants of the top two and bottom two rows.
+        let s = [m[0][0] * m[1][1] - m[1][0] * m[0][1],
+                 m[0][0] * m[1][2] - m[1][0] * m[0][2],
+                 m[0][0] * m[1][3] - m[1][0] * m[0][3],
+                 m[0][1] * m[1][2] - m[1][1] * m[0][2],
+                 m[0][1] * m[1][3] - m[1][1] * m[0][3],
+                 m[0][2] * m[1][3] - m[1][2] * m[0][3]];
+        let c = [m[2][0] * m[3][1] - m[3][0] * m[2][1],
+                 m[2][0] * m[3][2] - m[3][0] * m[2][2],
+                 m[2][0] * m[3][3] - m[3][0] * m[2][3],
+                 m[2][1] * m[3][2] - m[3][1] * m[2][2],
+                 m[2][1] * m[3][3] - m[3][1] * m[2][3],
+                 m[2][2] * m[3][3] - m[3][2] * m[2][3]];
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det == T::zero() || !det.is_finite() {
+            return None;
+        }
+        let i = T::one() / det;
+        Some(Mat4::from_rows([[(m[1][1] * c[5] - m[1][2] * c[4] + m[1][3] * c[3]) * i,
+                               (-m[0][1] * c[5] + m[0][2] * c[4] - m[0][3] * c[3]) * i,
+                               (m[3][1] * s[5] - m[3][2] * s[4] + m[3][3] * s[3]) * i,
+                               (-m[2][1] * s[5] + m[2][2] * s[4] - m[2][3] * s[3]) * i],
+                              [(-m[1][0] * c[5] + m[1][2] * c[2] - m[1][3] * c[1]) * i,
+                               (m[0][0] * c[5] - m[0][2] * c[2] + m[0][3] * c[1]) * i,
+                               (-m[3][0] * s[5] + m[3][2] * s[2] - m[3][3] * s[1]) * i,
+                               (m[2][0] * s[5] - m[2][2] * s[2] + m[2][3] * s[1]) * i],
+                              [(m[1][0] * c[4] - m[1][1] * c[2] + m[1][3] * c[0]) * i,
+                               (-m[0][0] * c[4] + m[0][1] * c[2] - m[0][3] * c[0]) * i,
+                               (m[3][0] * s[4] - m[3][1] * s[2] + m[3][3] * s[0]) * i,
+                               (-m[2][0] * s[4] + m[2][1] * s[2] - m[2][3] * s[0]) * i],
+                              [(-m[1][0] * c[3] + m[1][1] * c[1] - m[1][2] * c[0]) * i,
+                               (m[0][0] * c[3] - m[0][1] * c[1] + m[0][2] * c[0]) * i,
+                               (-m[3][0] * s[3] + m[3][1] * s[1] - m[3][2] * s[0]) * i,
+                               (m[2][0] * s[3] - m[2][1] * s[1] + m[2][2] * s[0]) * i]]))
+    }
+
+    /// mul_vec4 returns the product of the matrix and the column vector v.
+    pub fn mul_vec4(&self, v: Vec4<T>) -> Vec4<T> {
+        self.0.mul_scalar(v.0) + self.1.mul_scalar(v.1) + self.2.mul_scalar(v.2) + self.3.mul_scalar(v.3)
+    }
+
+    /// transform_point returns the point p transformed by the matrix,
+    /// including the perspective divide.
+    pub fn transform_point(&self, p: Vec3<T>) -> Vec3<T> {
+        let v = self.mul_vec4(Vec4(p.0, p.1, p.2, T::one()));
+        Vec3(v.0 / v.3, v.1 / v.3, v.2 / v.3)
+    }
+
+    /// transform_vector returns the direction v transformed by the matrix,
+    /// which ignores translation.
+    pub fn transform_vector(&self, v: Vec3<T>) -> Vec3<T> {
+        let v = self.mul_vec4(Vec4(v.0, v.1, v.2, T::zero()));
+        Vec3(v.0, v.1, v.2)
+    }
+
+    /// almost_equal tells if each element of the matrix is equal to that of
+    /// the other given an absolute tolerance (see `Float::almost_equal`).
+    pub fn almost_equal<N: Float>(&self, other: &Self, abs_tol: N) -> bool {
+        self.0.almost_equal(other.0, abs_tol) && self.1.almost_equal(other.1, abs_tol) &&
+        self.2.almost_equal(other.2, abs_tol) && self.3.almost_equal(other.3, abs_tol)
+    }
+}
+
+impl<T: Float> Mul for Mat4<T> {
+    type Output = Self;
+
+    /// mul returns the product of the matrices, which applies rhs first.
+    fn mul(self, rhs: Self) -> Self {
+        Mat4(self.mul_vec4(rhs.0), self.mul_vec4(rhs.1), self.mul_vec4(rhs.2), self.mul_vec4(rhs.3))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::unit::Deg;
+    use super::super::{Vec3, Vec4};
+    use super::Mat4;
+
+    #[test]
+    fn inverse() {
+        let m = Mat4::perspective(Deg(70.0), 1.5, 0.5, 40.0) *
+                Mat4::look_at(Vec3(1.0, 2.0, 3.0), Vec3(-2.0, 0.5, 0.0), Vec3(0.0, 1.0, 0.0)) *
+                Mat4::scale(Vec3(1.0, 2.0, 0.5));
+        let i = m.inverse().unwrap();
+        assert!((m * i).almost_equal(&Mat4::identity(), 1e-5));
+        assert!((i * m).almost_equal(&Mat4::identity(), 1e-5));
+        assert_eq!(m.transpose().transpose(), m);
+        assert_eq!(Mat4::scale(Vec3(1.0, 0.0, 1.0)).inverse(), None);
+
+        let o = Mat4::orthographic(-2.0, 2.0, -1.0, 3.0, 1.0, 5.0);
+        assert!(o.transform_point(Vec3(2.0, 3.0, -5.0)).almost_equal(Vec3(1.0, 1.0, 1.0), 1e-6));
+        assert!(o.transform_point(Vec3(-2.0, -1.0, -1.0)).almost_equal(Vec3(-1.0, -1.0, -1.0), 1e-6));
+        assert_eq!(o.mul_vec4(Vec4(0.0, 1.0, -3.0, 1.0)), Vec4(0.0, 0.0, 0.0, 1.0));
+    }
+}
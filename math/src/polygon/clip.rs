@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use super::super::float::Float;
+use super::super::Vec2;
+use super::super::predicates::orient2d;
+use super::{Polygon, area, crossing, on_boundary, winding_number, to_f64, from_f64};
+
+/// union returns the region covered by either `a` or `b`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, union};
+///
+/// let a = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+/// let b = Polygon::new(vec![Vec2(1.0, 1.0), Vec2(3.0, 1.0), Vec2(3.0, 3.0), Vec2(1.0, 3.0)]);
+/// let u = union(&a, &b);
+/// assert_eq!(u.len(), 1);
+/// assert_eq!(u[0].area(), 7.0);
+/// assert_eq!(u[0].exterior.len(), 8);
+/// ```
+pub fn union<T: Float>(a: &Polygon<T>, b: &Polygon<T>) -> Vec<Polygon<T>> {
+    boolean(a, b, |a, b| a > 0 || b > 0)
+}
+
+/// intersection returns the region covered by both `a` and `b`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, intersection};
+///
+/// let a = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+/// let b = Polygon::new(vec![Vec2(1.0, 1.0), Vec2(3.0, 1.0), Vec2(3.0, 3.0), Vec2(1.0, 3.0)]);
+/// let i = intersection(&a, &b);
+/// assert_eq!(i.len(), 1);
+/// assert_eq!(i[0].area(), 1.0);
+/// ```
+pub fn intersection<T: Float>(a: &Polygon<T>, b: &Polygon<T>) -> Vec<Polygon<T>> {
+    boolean(a, b, |a, b| a > 0 && b > 0)
+}
+
+/// difference returns the region covered by `a` but not by `b`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, difference};
+///
+/// let a = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 4.0), Vec2(0.0, 4.0)]);
+/// let b = Polygon::new(vec![Vec2(1.0, 1.0), Vec2(3.0, 1.0), Vec2(3.0, 3.0), Vec2(1.0, 3.0)]);
+/// let d = difference(&a, &b);
+/// assert_eq!(d.len(), 1);
+/// assert_eq!(d[0].holes.len(), 1);
+/// assert_eq!(d[0].area(), 12.0);
+/// ```
+pub fn difference<T: Float>(a: &Polygon<T>, b: &Polygon<T>) -> Vec<Polygon<T>> {
+    boolean(a, b, |a, b| a > 0 && b <= 0)
+}
+
+// boolean performs a boolean operation on the two polygons. The keep function
+// is given the winding numbers of a region with respect to each polygon, and
+// tells if that region is part of the result.
+fn boolean<T, F>(a: &Polygon<T>, b: &Polygon<T>, keep: F) -> Vec<Polygon<T>>
+    where T: Float,
+          F: Fn(i32, i32) -> bool
+{
+    let rings = |p: &Polygon<T>| -> Vec<Vec<Vec2<f64>>> {
+        p.normalized().rings().map(|r| r.iter().map(|&v| to_f64(v)).collect()).collect()
+    };
+    resolve(&rings(a), &rings(b), keep)
+        .into_iter()
+        .map(|p| {
+            Polygon {
+                exterior: p.exterior.into_iter().map(from_f64).collect(),
+                holes: p.holes.into_iter().map(|h| h.into_iter().map(from_f64).collect()).collect(),
+            }
+        })
+        .collect()
+}
+
+// An edge in the arrangement of all input edges, after splitting at every
+// intersection. Coincident edges are merged, with count holding the net
+// number of times each input traverses it from u to v.
+struct Group {
+    u: usize,
+    v: usize,
+    count: [i32; 2],
+}
+
+// resolve computes the boundary of the region described by keep, given the
+// winding numbers of the two sets of rings a and b. Rings in the result have
+// their interior to the left (exteriors counterclockwise, holes clockwise).
+pub(super) fn resolve<F>(a: &[Vec<Vec2<f64>>], b: &[Vec<Vec2<f64>>], keep: F) -> Vec<Polygon<f64>>
+    where F: Fn(i32, i32) -> bool
+{
+    // Gather all edges, tagged by the input they came from.
+    let mut edges: Vec<(Vec2<f64>, Vec2<f64>, usize)> = Vec::new();
+    for (src, rings) in [a, b].iter().enumerate() {
+        for ring in rings.iter() {
+            let n = ring.len();
+            for i in 0..n {
+                let (p, q) = (clean(ring[i]), clean(ring[(i + 1) % n]));
+                if p != q {
+                    edges.push((p, q, src));
+                }
+            }
+        }
+    }
+
+    let groups = split(&edges);
+    let (points, groups) = (groups.0, groups.1);
+
+    // Classify each group by the winding numbers on either side of it.
+    let mut out: Vec<(usize, usize)> = Vec::new();
+    for (gi, g) in groups.iter().enumerate() {
+        let (u, v) = (points[g.u], points[g.v]);
+        let m = Vec2((u.0 + v.0) / 2.0, (u.1 + v.1) / 2.0);
+
+        let mut w = [0i32; 2];
+        for (hi, h) in groups.iter().enumerate() {
+            if hi != gi {
+                let c = crossing(points[h.u], points[h.v], m);
+                w[0] += c * h.count[0];
+                w[1] += c * h.count[1];
+            }
+        }
+
+        let mut left = w;
+        let mut right = w;
+        for s in 0..2 {
+            let c = g.count[s];
+            if u.1 < v.1 || (u.1 == v.1 && u.0 > v.0) {
+                left[s] += c;
+            } else {
+                right[s] -= c;
+            }
+        }
+
+        let (l, r) = (keep(left[0], left[1]), keep(right[0], right[1]));
+        if l && !r {
+            out.push((g.u, g.v));
+        } else if r && !l {
+            out.push((g.v, g.u));
+        }
+    }
+
+    let rings = chain(&points, &out);
+    assemble(rings)
+}
+
+// clean replaces negative zero components, so that equal points have equal
+// bit patterns.
+fn clean(v: Vec2<f64>) -> Vec2<f64> {
+    Vec2(v.0 + 0.0, v.1 + 0.0)
+}
+
+// strictly_within tells if p (known to be collinear with a and b) lies within
+// the open segment a-b.
+fn strictly_within(a: Vec2<f64>, b: Vec2<f64>, p: Vec2<f64>) -> bool {
+    p != a && p != b && p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) &&
+    p.1 <= a.1.max(b.1)
+}
+
+// split splits every edge at each point where it touches or crosses another
+// edge, and merges coincident pieces. It returns the unique points and the
+// merged edge groups.
+fn split(edges: &[(Vec2<f64>, Vec2<f64>, usize)]) -> (Vec<Vec2<f64>>, Vec<Group>) {
+    let mut cuts: Vec<Vec<Vec2<f64>>> = edges.iter().map(|&(p, q, _)| vec![p, q]).collect();
+    for i in 0..edges.len() {
+        for j in i + 1..edges.len() {
+            let (a, b, _) = edges[i];
+            let (c, d, _) = edges[j];
+            let (o1, o2) = (orient2d(a, b, c), orient2d(a, b, d));
+            let (o3, o4) = (orient2d(c, d, a), orient2d(c, d, b));
+            if o1 != Ordering::Equal && o2 != Ordering::Equal && o3 != Ordering::Equal &&
+               o4 != Ordering::Equal {
+                if o1 != o2 && o3 != o4 {
+                    // A proper crossing, the same computed point is used to
+                    // split both edges so that they meet exactly.
+                    let (ab, cd) = (b - a, d - c);
+                    let t = ((c.0 - a.0) * cd.1 - (c.1 - a.1) * cd.0) / (ab.0 * cd.1 - ab.1 * cd.0);
+                    let x = clean(Vec2(a.0 + ab.0 * t, a.1 + ab.1 * t));
+                    cuts[i].push(x);
+                    cuts[j].push(x);
+                }
+                continue;
+            }
+            if o1 == Ordering::Equal && strictly_within(a, b, c) {
+                cuts[i].push(c);
+            }
+            if o2 == Ordering::Equal && strictly_within(a, b, d) {
+                cuts[i].push(d);
+            }
+            if o3 == Ordering::Equal && strictly_within(c, d, a) {
+                cuts[j].push(a);
+            }
+            if o4 == Ordering::Equal && strictly_within(c, d, b) {
+                cuts[j].push(b);
+            }
+        }
+    }
+
+    let mut points: Vec<Vec2<f64>> = Vec::new();
+    let mut ids: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut id = |p: Vec2<f64>| -> usize {
+        *ids.entry((p.0.to_bits(), p.1.to_bits())).or_insert_with(|| {
+            points.push(p);
+            points.len() - 1
+        })
+    };
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index: HashMap<(usize, usize), usize> = HashMap::new();
+    for (i, &(p, q, src)) in edges.iter().enumerate() {
+        let dir = q - p;
+        let mut pts = cuts[i].clone();
+        pts.sort_by(|x, y| (*x - p).dot(dir).partial_cmp(&(*y - p).dot(dir)).unwrap_or(Ordering::Equal));
+        pts.dedup();
+        for k in 0..pts.len() - 1 {
+            let (s, e) = (id(pts[k]), id(pts[k + 1]));
+            if s == e {
+                continue;
+            }
+            let (lo, hi, sign) = if s < e { (s, e, 1) } else { (e, s, -1) };
+            let gi = *index.entry((lo, hi)).or_insert_with(|| {
+                groups.push(Group {
+                    u: lo,
+                    v: hi,
+                    count: [0, 0],
+                });
+                groups.len() - 1
+            });
+            groups[gi].count[src] += sign;
+        }
+    }
+    (points, groups)
+}
+
+// chain links the directed edges into closed rings. Where several edges leave
+// the same vertex, the one turning furthest clockwise is taken, which keeps
+// rings that touch at a single vertex separate.
+fn chain(points: &[Vec2<f64>], edges: &[(usize, usize)]) -> Vec<Vec<Vec2<f64>>> {
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, &(u, _)) in edges.iter().enumerate() {
+        outgoing.entry(u).or_default().push(i);
+    }
+    let angle = |e: usize| {
+        let (u, v) = (points[edges[e].0], points[edges[e].1]);
+        (v.1 - u.1).atan2(v.0 - u.0)
+    };
+
+    let mut used = vec![false; edges.len()];
+    let mut rings = Vec::new();
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        let mut ring = Vec::new();
+        let mut cur = start;
+        loop {
+            used[cur] = true;
+            ring.push(points[edges[cur].0]);
+            let at = edges[cur].1;
+            let back = angle(cur) + PI;
+
+            // Among the unused outgoing edges (and the starting edge, which
+            // closes the ring) pick the smallest clockwise turn from the
+            // reversed incoming edge.
+            let mut best: Option<(f64, usize)> = None;
+            for &e in outgoing.get(&at).map(|v| v.as_slice()).unwrap_or(&[]) {
+                if used[e] && e != start {
+                    continue;
+                }
+                let mut turn = (back - angle(e)) % (2.0 * PI);
+                if turn <= 0.0 {
+                    turn += 2.0 * PI;
+                }
+                match best {
+                    Some((t, _)) if t <= turn => {}
+                    _ => best = Some((turn, e)),
+                }
+            }
+            match best {
+                Some((_, e)) if e == start => break,
+                Some((_, e)) => cur = e,
+                None => {
+                    // The arrangement is inconsistent (only possible when
+                    // computed intersection points were rounded badly), drop
+                    // the partial ring.
+                    ring.clear();
+                    break;
+                }
+            }
+        }
+        let ring = remove_collinear(ring);
+        if ring.len() >= 3 {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+// remove_collinear removes vertices which lie exactly on the straight line
+// between their neighbours.
+fn remove_collinear(mut ring: Vec<Vec2<f64>>) -> Vec<Vec2<f64>> {
+    let mut i = 0;
+    while ring.len() >= 3 && i < ring.len() {
+        let n = ring.len();
+        let (p, v, q) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+        if orient2d(p, v, q) == Ordering::Equal && (v - p).dot(q - v) > 0.0 {
+            ring.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+    ring
+}
+
+// assemble sorts the rings into polygons, assigning each hole (clockwise
+// ring) to the smallest exterior ring containing it.
+fn assemble(rings: Vec<Vec<Vec2<f64>>>) -> Vec<Polygon<f64>> {
+    let (mut exteriors, holes): (Vec<_>, Vec<_>) = rings.into_iter().partition(|r| area(r) > 0.0);
+    exteriors.sort_by(|a, b| area(a).partial_cmp(&area(b)).unwrap_or(Ordering::Equal));
+    let mut polygons: Vec<Polygon<f64>> = exteriors.into_iter().map(Polygon::new).collect();
+    for hole in holes {
+        // Test containment using a hole vertex that isn't on the boundary of
+        // the exterior, falling back to an edge midpoint.
+        let parent = polygons.iter_mut().find(|p| {
+            let probe = hole.iter()
+                .cloned()
+                .find(|&v| !on_boundary(&p.exterior, v))
+                .unwrap_or_else(|| (hole[0] + hole[1]).div_scalar(2.0));
+            winding_number(&p.exterior, probe) != 0
+        });
+        if let Some(p) = parent {
+            p.holes.push(hole);
+        }
+    }
+    polygons
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Polygon;
+    use super::{union, intersection, difference};
+    use Vec2;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polygon<f64> {
+        Polygon::new(vec![Vec2(x0, y0), Vec2(x1, y0), Vec2(x1, y1), Vec2(x0, y1)])
+    }
+
+    fn total_area(p: &[Polygon<f64>]) -> f64 {
+        p.iter().map(|p| p.area()).sum()
+    }
+
+    #[test]
+    fn shared_edges() {
+        // Two squares sharing a whole edge merge into one rectangle.
+        let (a, b) = (rect(0.0, 0.0, 1.0, 1.0), rect(1.0, 0.0, 2.0, 1.0));
+        let u = union(&a, &b);
+        assert_eq!(u.len(), 1);
+        assert_eq!(u[0].exterior.len(), 4);
+        assert_eq!(u[0].area(), 2.0);
+        assert!(intersection(&a, &b).is_empty());
+        let d = difference(&a, &b);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].exterior.len(), 4);
+        assert_eq!(d[0].area(), 1.0);
+
+        // Identical polygons.
+        assert_eq!(total_area(&union(&a, &a)), 1.0);
+        assert_eq!(total_area(&intersection(&a, &a)), 1.0);
+        assert!(difference(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn touching_vertex() {
+        // Squares touching at a corner produce two separate rings.
+        let (a, b) = (rect(0.0, 0.0, 1.0, 1.0), rect(1.0, 1.0, 2.0, 2.0));
+        let u = union(&a, &b);
+        assert_eq!(u.len(), 2);
+        assert_eq!(total_area(&u), 2.0);
+    }
+
+    #[test]
+    fn holes() {
+        let frame = rect(0.0, 0.0, 10.0, 10.0).with_hole(vec![Vec2(2.0, 2.0), Vec2(2.0, 8.0),
+                                                              Vec2(8.0, 8.0), Vec2(8.0, 2.0)]);
+        // A bar crossing the hole splits it in two.
+        let bar = rect(-1.0, 4.0, 11.0, 6.0);
+        let u = union(&frame, &bar);
+        assert_eq!(u.len(), 1);
+        assert_eq!(u[0].holes.len(), 2);
+        assert_eq!(total_area(&u), 64.0 + 2.0 * 2.0 + 6.0 * 2.0);
+
+        let i = intersection(&frame, &bar);
+        assert_eq!(i.len(), 2);
+        assert_eq!(total_area(&i), 8.0);
+
+        let d = difference(&frame, &bar);
+        assert_eq!(d.len(), 2);
+        assert_eq!(total_area(&d), 64.0 - 8.0);
+
+        // An island within the hole stays separate.
+        let island = rect(4.0, 4.0, 6.0, 6.0);
+        let u = union(&frame, &island);
+        assert_eq!(u.len(), 2);
+        assert_eq!(total_area(&u), 68.0);
+    }
+}
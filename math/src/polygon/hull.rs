@@ -0,0 +1,57 @@
+use std::cmp::Ordering;
+use super::super::float::Float;
+use super::super::Vec2;
+use super::super::predicates::orient2d;
+use super::to_f64;
+
+/// convex_hull returns the convex hull of the given points as a
+/// counterclockwise ring, using Andrew's monotone chain algorithm.
+///
+/// Collinear points along the hull's edges and duplicate points are not
+/// included in the result. If all of the points are collinear the result
+/// contains just the two extreme points (or one, if all points are equal).
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::convex_hull;
+///
+/// let points = [Vec2(0.0, 0.0), Vec2(1.0, 1.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0),
+///               Vec2(1.0, 0.0), Vec2(0.0, 2.0)];
+/// let hull = convex_hull(&points);
+/// assert_eq!(hull, vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+/// ```
+pub fn convex_hull<T: Float>(points: &[Vec2<T>]) -> Vec<Vec2<T>> {
+    let mut sorted: Vec<Vec2<T>> = points.to_vec();
+    sorted.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal).then(a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    });
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let mut hull: Vec<Vec2<T>> = Vec::with_capacity(sorted.len() * 2);
+
+    // Lower hull, then upper hull. The last point of each is omitted because
+    // it is the first point of the other.
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &Vec2<T>>> = if pass == 0 {
+            Box::new(sorted.iter())
+        } else {
+            Box::new(sorted.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2 &&
+                  orient2d(to_f64(hull[hull.len() - 2]), to_f64(hull[hull.len() - 1]), to_f64(p)) !=
+                  Ordering::Greater {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
@@ -0,0 +1,463 @@
+//! Two-dimensional polygon algorithms.
+//!
+//! A ring is a closed sequence of vertices given as a slice of `Vec2<T>`, the
+//! edge from the last vertex back to the first is implied. A `Polygon` is an
+//! exterior ring with zero or more holes.
+//!
+//! All orientation and containment decisions are made using the exact
+//! predicates in `fiz_math::predicates`, so results are consistent even for
+//! (nearly) degenerate input.
+
+use std::cmp::Ordering;
+use num::NumCast;
+use super::float::Float;
+use super::Vec2;
+use super::predicates::orient2d;
+
+mod hull;
+mod simplify;
+mod triangulate;
+mod clip;
+mod offset;
+
+pub use self::hull::convex_hull;
+pub use self::simplify::{simplify, simplify_ring};
+pub use self::triangulate::{triangulate, triangulate_delaunay};
+pub use self::clip::{union, intersection, difference};
+pub use self::offset::{offset, Join};
+
+/// Winding describes the orientation of a ring.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Winding {
+    /// The vertices are ordered counterclockwise (positive area).
+    CounterClockwise,
+
+    /// The vertices are ordered clockwise (negative area).
+    Clockwise,
+
+    /// The ring encloses no area (e.g. all its vertices are collinear).
+    Degenerate,
+}
+
+/// Polygon is a simple polygon described by an exterior ring and any number
+/// of holes.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::Polygon;
+///
+/// let square = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 4.0), Vec2(0.0, 4.0)]);
+/// let hole = vec![Vec2(1.0, 1.0), Vec2(3.0, 1.0), Vec2(3.0, 3.0), Vec2(1.0, 3.0)];
+/// let p = square.with_hole(hole);
+/// assert_eq!(p.area(), 12.0);
+/// assert!(p.contains(Vec2(0.5, 0.5)));
+/// assert!(!p.contains(Vec2(2.0, 2.0)));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon<T> {
+    /// The exterior ring of the polygon.
+    pub exterior: Vec<Vec2<T>>,
+
+    /// The holes of the polygon, each one lying within the exterior ring.
+    pub holes: Vec<Vec<Vec2<T>>>,
+}
+
+impl<T: Float> Polygon<T> {
+    /// new returns a polygon with the given exterior ring and no holes.
+    pub fn new(exterior: Vec<Vec2<T>>) -> Self {
+        Polygon {
+            exterior,
+            holes: Vec::new(),
+        }
+    }
+
+    /// with_hole returns the polygon with the given ring added as a hole.
+    pub fn with_hole(mut self, hole: Vec<Vec2<T>>) -> Self {
+        self.holes.push(hole);
+        self
+    }
+
+    /// rings returns an iterator over the exterior ring followed by each of
+    /// the holes.
+    pub fn rings(&self) -> impl Iterator<Item = &Vec<Vec2<T>>> {
+        ::std::iter::once(&self.exterior).chain(self.holes.iter())
+    }
+
+    /// vertices returns all of the vertices of the polygon, in the order of
+    /// the exterior ring followed by each of the holes. Triangle indices
+    /// returned by the triangulation functions refer to this order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec2;
+    /// use fiz_math::polygon::Polygon;
+    ///
+    /// let p = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(0.0, 1.0)]);
+    /// assert_eq!(p.vertices().len(), 3);
+    /// ```
+    pub fn vertices(&self) -> Vec<Vec2<T>> {
+        self.rings().flat_map(|r| r.iter().cloned()).collect()
+    }
+
+    /// normalized returns the polygon with its exterior ring ordered
+    /// counterclockwise and its holes ordered clockwise, such that the
+    /// interior of the polygon always lies to the left of each edge.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec2;
+    /// use fiz_math::polygon::{Polygon, Winding, winding};
+    ///
+    /// let p = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(0.0, 1.0), Vec2(1.0, 0.0)]);
+    /// assert_eq!(winding(&p.normalized().exterior), Winding::CounterClockwise);
+    /// ```
+    pub fn normalized(&self) -> Self {
+        let orient = |ring: &Vec<Vec2<T>>, want: Winding| {
+            let mut ring = ring.clone();
+            if winding(&ring) != want {
+                ring.reverse();
+            }
+            ring
+        };
+        Polygon {
+            exterior: orient(&self.exterior, Winding::CounterClockwise),
+            holes: self.holes.iter().map(|h| orient(h, Winding::Clockwise)).collect(),
+        }
+    }
+
+    /// area returns the area of the polygon (the exterior area less the area
+    /// of its holes), regardless of the orientation of its rings.
+    pub fn area(&self) -> T {
+        self.holes.iter().fold(area(&self.exterior).abs(), |a, h| a - area(h).abs())
+    }
+
+    /// centroid returns the center of mass of the polygon, or None if it has
+    /// no area.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec2;
+    /// use fiz_math::polygon::Polygon;
+    ///
+    /// let p = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 2.0), Vec2(0.0, 2.0)])
+    ///     .with_hole(vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+    /// assert_eq!(p.centroid(), Some(Vec2(3.0, 1.0)));
+    /// ```
+    pub fn centroid(&self) -> Option<Vec2<T>> {
+        let mut sum = Vec2(T::zero(), T::zero());
+        let mut total = T::zero();
+        for (i, ring) in self.rings().enumerate() {
+            let a = area(ring).abs();
+            if let Some(c) = centroid(ring) {
+                let a = if i == 0 { a } else { -a };
+                sum = sum + c.mul_scalar(a);
+                total = total + a;
+            }
+        }
+        if total == T::zero() {
+            None
+        } else {
+            Some(sum.div_scalar(total))
+        }
+    }
+
+    /// contains tells if the point lies within the polygon (i.e. within the
+    /// exterior ring and outside all holes). Points lying exactly on the
+    /// boundary of the polygon are considered to be contained.
+    pub fn contains(&self, p: Vec2<T>) -> bool {
+        if on_boundary(&self.exterior, p) || self.holes.iter().any(|h| on_boundary(h, p)) {
+            return true;
+        }
+        contains(&self.exterior, p) && !self.holes.iter().any(|h| contains(h, p))
+    }
+
+    /// is_simple tells if no two edges of the polygon (across all of its
+    /// rings) intersect, other than adjacent edges meeting at their shared
+    /// vertex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec2;
+    /// use fiz_math::polygon::Polygon;
+    ///
+    /// let square = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 4.0), Vec2(0.0, 4.0)]);
+    /// assert!(square.is_simple());
+    ///
+    /// let crossing = square.with_hole(vec![Vec2(1.0, 1.0), Vec2(5.0, 1.0), Vec2(1.0, 2.0)]);
+    /// assert!(!crossing.is_simple());
+    /// ```
+    pub fn is_simple(&self) -> bool {
+        let mut edges = Vec::new();
+        for ring in self.rings() {
+            let base = edges.len();
+            let n = ring.len();
+            for i in 0..n {
+                let next = if i + 1 == n { base } else { base + i + 1 };
+                edges.push((base + i, next, to_f64(ring[i]), to_f64(ring[(i + 1) % n])));
+            }
+        }
+        for i in 0..edges.len() {
+            for j in i + 1..edges.len() {
+                let (a0, a1, a, b) = edges[i];
+                let (b0, b1, c, d) = edges[j];
+                let adjacent = a1 == b0 || b1 == a0;
+                if segments_intersect(a, b, c, d, adjacent) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// area returns the signed area of the ring, which is positive if the ring is
+/// ordered counterclockwise and negative if it is ordered clockwise.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::area;
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 3.0), Vec2(0.0, 3.0)];
+/// assert_eq!(area(&ring), 6.0);
+/// ```
+pub fn area<T: Float>(ring: &[Vec2<T>]) -> T {
+    let n = ring.len();
+    let mut sum = T::zero();
+    for i in 0..n {
+        let (a, b) = (ring[i], ring[(i + 1) % n]);
+        sum = sum + (a.0 * b.1 - b.0 * a.1);
+    }
+    sum / (T::one() + T::one())
+}
+
+/// winding returns the orientation of the ring.
+///
+/// The orientation is determined exactly (using the lowest-leftmost vertex,
+/// which is always convex) rather than from the sign of the computed area.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{winding, Winding};
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(0.0, 1.0)];
+/// assert_eq!(winding(&ring), Winding::CounterClockwise);
+///
+/// let line = [Vec2(0.0, 0.0), Vec2(1.0, 1.0), Vec2(2.0, 2.0)];
+/// assert_eq!(winding(&line), Winding::Degenerate);
+/// ```
+pub fn winding<T: Float>(ring: &[Vec2<T>]) -> Winding {
+    let ring: Vec<Vec2<f64>> = ring.iter().map(|&v| to_f64(v)).collect();
+    let n = ring.len();
+    if n < 3 {
+        return Winding::Degenerate;
+    }
+    let lowest = (0..n)
+        .min_by(|&i, &j| {
+            let (a, b) = (ring[i], ring[j]);
+            a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal).then(a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+        })
+        .unwrap();
+
+    // Skip over neighbours which coincide with the chosen vertex.
+    let v = ring[lowest];
+    let prev = (1..n).map(|k| ring[(lowest + n - k) % n]).find(|&p| p != v);
+    let next = (1..n).map(|k| ring[(lowest + k) % n]).find(|&p| p != v);
+    let (prev, next) = match (prev, next) {
+        (Some(p), Some(q)) => (p, q),
+        _ => return Winding::Degenerate,
+    };
+    match orient2d(prev, v, next) {
+        Ordering::Greater => Winding::CounterClockwise,
+        Ordering::Less => Winding::Clockwise,
+        Ordering::Equal => {
+            // The extreme vertex is collinear with its neighbours, fall back to
+            // the sign of the area.
+            let a = area(&ring);
+            if a > 0.0 {
+                Winding::CounterClockwise
+            } else if a < 0.0 {
+                Winding::Clockwise
+            } else {
+                Winding::Degenerate
+            }
+        }
+    }
+}
+
+/// centroid returns the center of mass of the area enclosed by the ring, or
+/// None if the ring has no area.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::centroid;
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)];
+/// assert_eq!(centroid(&ring), Some(Vec2(1.0, 1.0)));
+/// ```
+pub fn centroid<T: Float>(ring: &[Vec2<T>]) -> Option<Vec2<T>> {
+    let n = ring.len();
+    let (mut cx, mut cy, mut a) = (T::zero(), T::zero(), T::zero());
+    for i in 0..n {
+        let (p, q) = (ring[i], ring[(i + 1) % n]);
+        let cross = p.0 * q.1 - q.0 * p.1;
+        cx = cx + (p.0 + q.0) * cross;
+        cy = cy + (p.1 + q.1) * cross;
+        a = a + cross;
+    }
+    if a == T::zero() {
+        return None;
+    }
+    let six = T::from(3).unwrap() * a;
+    Some(Vec2(cx / six, cy / six))
+}
+
+/// contains tells if the point lies within the ring, using the nonzero
+/// winding rule. Points exactly on the boundary are considered inside.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::contains;
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)];
+/// assert!(contains(&ring, Vec2(1.0, 1.0)));
+/// assert!(contains(&ring, Vec2(2.0, 1.0)));
+/// assert!(!contains(&ring, Vec2(3.0, 1.0)));
+/// ```
+pub fn contains<T: Float>(ring: &[Vec2<T>], p: Vec2<T>) -> bool {
+    on_boundary(ring, p) || winding_number(ring, p) != 0
+}
+
+/// winding_number returns the number of times the ring winds
+/// counterclockwise around the point. The result for points exactly on the
+/// boundary is unspecified.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::winding_number;
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(0.0, 2.0), Vec2(2.0, 2.0), Vec2(2.0, 0.0)];
+/// assert_eq!(winding_number(&ring, Vec2(1.0, 1.0)), -1);
+/// ```
+pub fn winding_number<T: Float>(ring: &[Vec2<T>], p: Vec2<T>) -> i32 {
+    let p = to_f64(p);
+    let n = ring.len();
+    let mut w = 0;
+    for i in 0..n {
+        let (a, b) = (to_f64(ring[i]), to_f64(ring[(i + 1) % n]));
+        w += crossing(a, b, p);
+    }
+    w
+}
+
+/// intersections returns the pairs of edge indices of the ring which
+/// intersect each other (edge `i` runs from vertex `i` to vertex `i + 1`).
+/// Adjacent edges are only reported if they overlap beyond their shared
+/// vertex. An empty result means the ring is simple.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::intersections;
+///
+/// let bowtie = [Vec2(0.0, 0.0), Vec2(2.0, 2.0), Vec2(2.0, 0.0), Vec2(0.0, 2.0)];
+/// assert_eq!(intersections(&bowtie), vec![(0, 2)]);
+/// ```
+pub fn intersections<T: Float>(ring: &[Vec2<T>]) -> Vec<(usize, usize)> {
+    let n = ring.len();
+    let mut out = Vec::new();
+    for i in 0..n {
+        for j in i + 1..n {
+            let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+            let (a, b) = (to_f64(ring[i]), to_f64(ring[(i + 1) % n]));
+            let (c, d) = (to_f64(ring[j]), to_f64(ring[(j + 1) % n]));
+            if segments_intersect(a, b, c, d, adjacent) {
+                out.push((i, j));
+            }
+        }
+    }
+    out
+}
+
+// to_f64 converts the vector into one of f64 components. This is exact for
+// f32 and f64 inputs, which lets the exact predicates be used.
+fn to_f64<T: Float>(v: Vec2<T>) -> Vec2<f64> {
+    Vec2(v.0.to_f64().unwrap(), v.1.to_f64().unwrap())
+}
+
+// from_f64 converts the vector back into one of T components.
+fn from_f64<T: Float>(v: Vec2<f64>) -> Vec2<T> {
+    Vec2(<T as NumCast>::from(v.0).unwrap(), <T as NumCast>::from(v.1).unwrap())
+}
+
+// crossing returns the contribution of the edge a -> b to the winding number
+// of p, counting upward crossings of the ray from p toward +x as +1 and
+// downward crossings as -1. The half-open rule used treats p as though it
+// lies infinitesimally above any vertex at the same height.
+fn crossing(a: Vec2<f64>, b: Vec2<f64>, p: Vec2<f64>) -> i32 {
+    if a.1 <= p.1 {
+        if b.1 > p.1 && orient2d(a, b, p) == Ordering::Greater {
+            return 1;
+        }
+    } else if b.1 <= p.1 && orient2d(a, b, p) == Ordering::Less {
+        return -1;
+    }
+    0
+}
+
+// on_segment tells if p (known to be collinear with a and b) lies within the
+// closed segment a-b.
+fn on_segment(a: Vec2<f64>, b: Vec2<f64>, p: Vec2<f64>) -> bool {
+    p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+}
+
+// on_boundary tells if p lies exactly on an edge of the ring.
+fn on_boundary<T: Float>(ring: &[Vec2<T>], p: Vec2<T>) -> bool {
+    let p = to_f64(p);
+    let n = ring.len();
+    (0..n).any(|i| {
+        let (a, b) = (to_f64(ring[i]), to_f64(ring[(i + 1) % n]));
+        orient2d(a, b, p) == Ordering::Equal && on_segment(a, b, p)
+    })
+}
+
+// segments_intersect tells if the closed segments a-b and c-d intersect. If
+// adjacent is true the segments are known to share an endpoint, and only an
+// overlap beyond that endpoint is considered an intersection.
+fn segments_intersect(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>, d: Vec2<f64>, adjacent: bool) -> bool {
+    let (o1, o2) = (orient2d(a, b, c), orient2d(a, b, d));
+    let (o3, o4) = (orient2d(c, d, a), orient2d(c, d, b));
+    if adjacent {
+        // Adjacent edges only intersect improperly if they are collinear and
+        // fold back over each other.
+        if o1 != Ordering::Equal || o2 != Ordering::Equal {
+            return false;
+        }
+        let shared = if a == c || a == d { a } else { b };
+        let (p, q) = (if a == shared { b } else { a }, if c == shared { d } else { c });
+        let (u, v) = (p - shared, q - shared);
+        return u.dot(v) > 0.0;
+    }
+    if o1 != o2 && o3 != o4 && o1 != Ordering::Equal && o2 != Ordering::Equal &&
+       o3 != Ordering::Equal && o4 != Ordering::Equal {
+        return true;
+    }
+    (o1 == Ordering::Equal && on_segment(a, b, c)) || (o2 == Ordering::Equal && on_segment(a, b, d)) ||
+    (o3 == Ordering::Equal && on_segment(c, d, a)) || (o4 == Ordering::Equal && on_segment(c, d, b))
+}
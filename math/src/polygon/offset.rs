@@ -0,0 +1,222 @@
+use std::f64::consts::PI;
+use super::super::float::Float;
+use super::super::Vec2;
+use super::{Polygon, clip, to_f64, from_f64};
+
+/// Join describes how offset edges are connected around convex corners.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Join<T> {
+    /// Miter extends both edges until they meet. The value is the miter limit,
+    /// as a multiple of the offset distance; corners sharper than the limit
+    /// are squared off instead.
+    Miter(T),
+
+    /// Round connects the edges with a circular arc. The value is the maximum
+    /// distance allowed between the arc and its polyline approximation.
+    Round(T),
+
+    /// Square cuts off the corner at the offset distance from the vertex.
+    Square,
+}
+
+/// offset grows the polygon outward by the given distance (or shrinks it when
+/// the distance is negative), returning the resulting polygons. Shrinking may
+/// split a polygon into several or remove it entirely.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, offset, Join};
+///
+/// let square = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+///
+/// let grown = offset(&square, 1.0, Join::Miter(2.0));
+/// assert_eq!(grown[0].area(), 16.0);
+///
+/// let shrunk = offset(&square, -0.5, Join::Square);
+/// assert_eq!(shrunk[0].area(), 1.0);
+///
+/// assert!(offset(&square, -1.5, Join::Square).is_empty());
+/// ```
+pub fn offset<T: Float>(polygon: &Polygon<T>, distance: T, join: Join<T>) -> Vec<Polygon<T>> {
+    let d = distance.to_f64().unwrap();
+    let join = match join {
+        Join::Miter(limit) => Join::Miter(limit.to_f64().unwrap()),
+        Join::Round(tolerance) => Join::Round(tolerance.to_f64().unwrap()),
+        Join::Square => Join::Square,
+    };
+    let rings: Vec<Vec<Vec2<f64>>> = polygon.normalized()
+        .rings()
+        .map(|r| r.iter().map(|&v| to_f64(v)).collect())
+        .collect();
+    if d == 0.0 {
+        return vec![polygon.normalized()];
+    }
+
+    // Each offset ring is a closed curve whose winding number is positive
+    // exactly where the offset polygon should be, so resolving them with the
+    // positive fill rule yields the result.
+    let offset: Vec<Vec<Vec2<f64>>> = rings.iter()
+        .map(|r| offset_ring(r, d, join))
+        .filter(|r| r.len() >= 3)
+        .collect();
+    clip::resolve(&offset, &[], |a, _| a > 0)
+        .into_iter()
+        .map(|p| {
+            Polygon {
+                exterior: p.exterior.into_iter().map(from_f64).collect(),
+                holes: p.holes.into_iter().map(|h| h.into_iter().map(from_f64).collect()).collect(),
+            }
+        })
+        .collect()
+}
+
+// normal returns the unit normal pointing to the right of the edge a-b.
+fn normal(a: Vec2<f64>, b: Vec2<f64>) -> Vec2<f64> {
+    let e = b - a;
+    let l = e.length();
+    Vec2(e.1 / l, -e.0 / l)
+}
+
+// offset_ring moves each edge of the ring to its right by d, adding joins at
+// corners which open up. The result is generally self-intersecting.
+fn offset_ring(ring: &[Vec2<f64>], d: f64, join: Join<f64>) -> Vec<Vec2<f64>> {
+    // Drop repeated vertices, which have no edge direction.
+    let mut pts: Vec<Vec2<f64>> = Vec::with_capacity(ring.len());
+    for &p in ring {
+        if pts.last() != Some(&p) {
+            pts.push(p);
+        }
+    }
+    while pts.len() > 1 && pts.first() == pts.last() {
+        pts.pop();
+    }
+    let n = pts.len();
+    if n < 3 {
+        return Vec::new();
+    }
+
+    let mut out = Vec::new();
+    for i in 0..n {
+        let (prev, v, next) = (pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n]);
+        let (n1, n2) = (normal(prev, v), normal(v, next));
+        let (p1, p2) = (v + n1.mul_scalar(d), v + n2.mul_scalar(d));
+        let e1 = v - prev;
+        let e2 = next - v;
+        let cross = e1.0 * e2.1 - e1.1 * e2.0;
+        let cos = n1.dot(n2);
+
+        // The corner opens up on the offset side when the ring turns away
+        // from it, i.e. a left turn for outward offsets.
+        if cross * d >= 0.0 && !(cross == 0.0 && cos > 0.0) {
+            let u1 = e1.div_scalar(e1.length());
+            let u2 = e2.div_scalar(e2.length());
+            match join {
+                Join::Miter(limit) if cos > -1.0 && (2.0 / (1.0 + cos)).sqrt() <= limit => {
+                    out.push(v + (n1 + n2).mul_scalar(d / (1.0 + cos)));
+                }
+                Join::Round(tolerance) => {
+                    out.push(p1);
+                    let r = d.abs();
+                    let step = if tolerance > 0.0 && tolerance < r {
+                        2.0 * (1.0 - tolerance / r).acos()
+                    } else {
+                        PI / 2.0
+                    };
+                    let a1 = n1.1.atan2(n1.0);
+                    let mut sweep = n2.1.atan2(n2.0) - a1;
+                    if cross == 0.0 {
+                        // A spike, turn halfway around.
+                        sweep = PI * d.signum();
+                    } else if d > 0.0 && sweep < 0.0 {
+                        sweep += 2.0 * PI;
+                    } else if d < 0.0 && sweep > 0.0 {
+                        sweep -= 2.0 * PI;
+                    }
+                    let steps = (sweep.abs() / step).ceil() as usize;
+                    for k in 1..steps {
+                        let a = a1 + sweep * k as f64 / steps as f64;
+                        out.push(v + Vec2(a.cos(), a.sin()).mul_scalar(d));
+                    }
+                    out.push(p2);
+                }
+                _ => {
+                    // Square, or a miter exceeding its limit: cut the corner
+                    // across the bisector at the offset distance.
+                    let sum = n1 + n2;
+                    let bisector = if sum.length() < 1e-12 {
+                        u1
+                    } else {
+                        sum.div_scalar(sum.length()).mul_scalar(d.signum())
+                    };
+                    let t1 = (d.abs() - (p1 - v).dot(bisector)) / u1.dot(bisector);
+                    let t2 = (d.abs() - (p2 - v).dot(bisector)) / -u2.dot(bisector);
+                    out.push(p1 + u1.mul_scalar(t1));
+                    out.push(p2 - u2.mul_scalar(t2));
+                }
+            }
+        } else if cross == 0.0 {
+            // Collinear continuation.
+            out.push(p1);
+        } else {
+            // The corner closes up; the overlap it leaves behind has the
+            // wrong winding and is removed when the ring is resolved.
+            out.push(p1);
+            out.push(v);
+            out.push(p2);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::Polygon;
+    use super::{offset, Join};
+    use Vec2;
+
+    #[test]
+    fn joins() {
+        let square = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(0.0, 2.0)]);
+
+        let square_join = offset(&square, 1.0, Join::Square);
+        assert_eq!(square_join.len(), 1);
+        assert_eq!(square_join[0].exterior.len(), 8);
+        let leg = 2.0 - 2.0f64.sqrt();
+        assert!((square_join[0].area() - (16.0 - 2.0 * leg * leg)).abs() < 1e-9);
+
+        // Round joins approach the area of the Minkowski sum with a disc.
+        let round = offset(&square, 1.0, Join::Round(0.001));
+        let exact = 4.0 + 4.0 * 2.0 + ::std::f64::consts::PI;
+        assert!((round[0].area() - exact).abs() < 0.01);
+        assert!(round[0].area() < exact);
+
+        // A miter limit below sqrt(2) squares off right angles.
+        let limited = offset(&square, 1.0, Join::Miter(1.2));
+        assert_eq!(limited[0].exterior.len(), 8);
+    }
+
+    #[test]
+    fn concave() {
+        // An L shape, whose inner corner is concave.
+        let l = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 2.0), Vec2(2.0, 2.0),
+                                  Vec2(2.0, 4.0), Vec2(0.0, 4.0)]);
+        let grown = offset(&l, 1.0, Join::Miter(2.0));
+        assert_eq!(grown.len(), 1);
+        assert_eq!(grown[0].exterior.len(), 6);
+        assert_eq!(grown[0].area(), 6.0 * 6.0 - 2.0 * 2.0);
+
+        let shrunk = offset(&l, -0.5, Join::Miter(2.0));
+        assert_eq!(shrunk.len(), 1);
+        assert_eq!(shrunk[0].area(), 3.0 * 3.0 - 2.0 * 2.0);
+
+        // Offsetting a polygon with a hole shrinks the hole.
+        let frame = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(6.0, 0.0), Vec2(6.0, 6.0), Vec2(0.0, 6.0)])
+            .with_hole(vec![Vec2(2.0, 2.0), Vec2(4.0, 2.0), Vec2(4.0, 4.0), Vec2(2.0, 4.0)]);
+        let grown = offset(&frame, 0.5, Join::Miter(2.0));
+        assert_eq!(grown[0].holes.len(), 1);
+        assert_eq!(grown[0].area(), 49.0 - 1.0);
+        assert!(offset(&frame, 1.0, Join::Miter(2.0))[0].holes.is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::cmp::Ordering;
+use super::super::float::Float;
+use super::super::Vec2;
+use super::{to_f64, from_f64};
+
+/// simplify reduces the number of vertices in the open polyline using the
+/// Ramer–Douglas–Peucker algorithm. Every removed vertex lies within
+/// `epsilon` of the resulting polyline, and the first and last vertices are
+/// always kept.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::simplify;
+///
+/// let line = [Vec2(0.0, 0.0), Vec2(1.0, 0.1), Vec2(2.0, -0.1), Vec2(3.0, 5.0), Vec2(4.0, 6.0)];
+/// assert_eq!(simplify(&line, 0.5), vec![Vec2(0.0, 0.0), Vec2(2.0, -0.1), Vec2(3.0, 5.0), Vec2(4.0, 6.0)]);
+/// ```
+pub fn simplify<T: Float>(line: &[Vec2<T>], epsilon: T) -> Vec<Vec2<T>> {
+    if line.len() < 3 {
+        return line.to_vec();
+    }
+    let pts: Vec<Vec2<f64>> = line.iter().map(|&v| to_f64(v)).collect();
+    let mut keep = vec![false; pts.len()];
+    keep[0] = true;
+    keep[pts.len() - 1] = true;
+    rdp(&pts, 0, pts.len() - 1, epsilon.to_f64().unwrap(), &mut keep);
+    line.iter().zip(keep).filter(|&(_, k)| k).map(|(&v, _)| v).collect()
+}
+
+/// simplify_ring reduces the number of vertices in the closed ring using the
+/// Ramer–Douglas–Peucker algorithm. The ring is split at its two mutually
+/// farthest vertices (approximately), which are always kept.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::simplify_ring;
+///
+/// let ring = [Vec2(0.0, 0.0), Vec2(2.0, 0.0), Vec2(2.0, 2.0), Vec2(1.0, 2.01), Vec2(0.0, 2.0)];
+/// assert_eq!(simplify_ring(&ring, 0.1).len(), 4);
+/// ```
+pub fn simplify_ring<T: Float>(ring: &[Vec2<T>], epsilon: T) -> Vec<Vec2<T>> {
+    let n = ring.len();
+    if n < 4 {
+        return ring.to_vec();
+    }
+    let pts: Vec<Vec2<f64>> = ring.iter().map(|&v| to_f64(v)).collect();
+
+    // Split the ring at vertex zero and the vertex farthest from it.
+    let far = (1..n)
+        .max_by(|&i, &j| {
+            let (a, b) = ((pts[i] - pts[0]).length_sq(), (pts[j] - pts[0]).length_sq());
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        })
+        .unwrap();
+    let mut closed = pts.clone();
+    closed.push(pts[0]);
+    let mut keep = vec![false; n + 1];
+    keep[0] = true;
+    keep[far] = true;
+    let eps = epsilon.to_f64().unwrap();
+    rdp(&closed, 0, far, eps, &mut keep);
+    rdp(&closed, far, n, eps, &mut keep);
+    (0..n).filter(|&i| keep[i]).map(|i| from_f64(closed[i])).collect()
+}
+
+// rdp marks the vertices between first and last (exclusive) that must be
+// kept to stay within epsilon of the line.
+fn rdp(pts: &[Vec2<f64>], first: usize, last: usize, epsilon: f64, keep: &mut [bool]) {
+    let mut stack = vec![(first, last)];
+    while let Some((first, last)) = stack.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+        let (a, b) = (pts[first], pts[last]);
+        let ab = b - a;
+        let len_sq = ab.length_sq();
+        let mut best = (0.0, first);
+        for (i, &p) in pts.iter().enumerate().take(last).skip(first + 1) {
+            let d = if len_sq == 0.0 {
+                (p - a).length()
+            } else {
+                let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+                (p - (a + ab.mul_scalar(t))).length()
+            };
+            if d > best.0 {
+                best = (d, i);
+            }
+        }
+        if best.0 > epsilon {
+            keep[best.1] = true;
+            stack.push((first, best.1));
+            stack.push((best.1, last));
+        }
+    }
+}
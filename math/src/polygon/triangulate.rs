@@ -0,0 +1,413 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use super::super::float::Float;
+use super::super::Vec2;
+use super::super::predicates::{orient2d, incircle};
+use super::{Polygon, Winding, winding, to_f64};
+
+/// triangulate triangulates the polygon (including its holes) using ear
+/// clipping.
+///
+/// The result is a list of counterclockwise triangles whose indices refer to
+/// the vertices of the polygon as returned by `Polygon::vertices`. Holes are
+/// joined to the exterior ring by bridge edges before clipping, and
+/// degenerate (zero area) triangles are omitted.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, triangulate};
+///
+/// let square = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(4.0, 0.0), Vec2(4.0, 4.0), Vec2(0.0, 4.0)])
+///     .with_hole(vec![Vec2(1.0, 1.0), Vec2(3.0, 1.0), Vec2(3.0, 3.0), Vec2(1.0, 3.0)]);
+/// let triangles = triangulate(&square);
+/// assert_eq!(triangles.len(), 8);
+///
+/// // The triangles cover exactly the area of the polygon.
+/// let v = square.vertices();
+/// let area: f64 = triangles.iter().map(|t| {
+///     let (a, b, c) = (v[t[0]], v[t[1]], v[t[2]]);
+///     ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)) / 2.0
+/// }).sum();
+/// assert_eq!(area, square.area());
+/// ```
+pub fn triangulate<T: Float>(polygon: &Polygon<T>) -> Vec<[usize; 3]> {
+    let pts: Vec<Vec2<f64>> = polygon.vertices().into_iter().map(to_f64).collect();
+
+    // Build index rings with the exterior counterclockwise and the holes
+    // clockwise.
+    let mut base = 0;
+    let mut rings = Vec::new();
+    for (i, ring) in polygon.rings().enumerate() {
+        let mut indices: Vec<usize> = (base..base + ring.len()).collect();
+        base += ring.len();
+        let w = winding(ring);
+        if w == Winding::Degenerate {
+            continue;
+        }
+        let want = if i == 0 { Winding::CounterClockwise } else { Winding::Clockwise };
+        if w != want {
+            indices.reverse();
+        }
+        rings.push((i, indices));
+    }
+    if rings.is_empty() || rings[0].0 != 0 {
+        return Vec::new();
+    }
+    let mut outer = rings.remove(0).1;
+
+    // Join holes from right to left, so that each bridge can't cross a hole
+    // which is yet to be joined.
+    let mut holes: Vec<Vec<usize>> = rings.into_iter().map(|r| r.1).collect();
+    holes.sort_by(|a, b| {
+        let max = |h: &Vec<usize>| h.iter().map(|&i| pts[i].0).fold(f64::MIN, f64::max);
+        max(b).partial_cmp(&max(a)).unwrap_or(Ordering::Equal)
+    });
+    for hole in holes {
+        bridge_hole(&pts, &mut outer, &hole);
+    }
+
+    ear_clip(&pts, &outer)
+}
+
+/// triangulate_delaunay returns the constrained Delaunay triangulation of the
+/// polygon (including its holes). Every edge of the polygon appears in the
+/// result, and all other edges satisfy the Delaunay (empty circumcircle)
+/// condition among the vertices visible to them. No vertices are added.
+///
+/// The result is a list of counterclockwise triangles whose indices refer to
+/// the vertices of the polygon as returned by `Polygon::vertices`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::polygon::{Polygon, triangulate_delaunay};
+///
+/// // A thin quad whose Delaunay diagonal is the short one.
+/// let quad = Polygon::new(vec![Vec2(0.0, 0.0), Vec2(2.0, -0.5), Vec2(4.0, 0.0), Vec2(2.0, 0.5)]);
+/// let triangles = triangulate_delaunay(&quad);
+/// assert_eq!(triangles.len(), 2);
+/// for t in triangles {
+///     assert!(t.contains(&1) && t.contains(&3));
+/// }
+/// ```
+pub fn triangulate_delaunay<T: Float>(polygon: &Polygon<T>) -> Vec<[usize; 3]> {
+    let pts: Vec<Vec2<f64>> = polygon.vertices().into_iter().map(to_f64).collect();
+    let mut tris = triangulate(polygon);
+
+    let key = |a: usize, b: usize| if a < b { (a, b) } else { (b, a) };
+    let mut constrained = HashSet::new();
+    let mut base = 0;
+    for ring in polygon.rings() {
+        let n = ring.len();
+        for i in 0..n {
+            constrained.insert(key(base + i, base + (i + 1) % n));
+        }
+        base += n;
+    }
+
+    // Map each directed edge to the triangle it belongs to.
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for (ti, t) in tris.iter().enumerate() {
+        for k in 0..3 {
+            edges.insert((t[k], t[(k + 1) % 3]), ti);
+        }
+    }
+
+    // Lawson's flip algorithm: flip illegal edges until none remain.
+    let mut stack: Vec<(usize, usize)> = edges.keys().filter(|&&(a, b)| a < b).cloned().collect();
+    while let Some((a, b)) = stack.pop() {
+        if constrained.contains(&key(a, b)) {
+            continue;
+        }
+        let (t1, t2) = match (edges.get(&(a, b)), edges.get(&(b, a))) {
+            (Some(&t1), Some(&t2)) => (t1, t2),
+            _ => continue,
+        };
+        let c = third(&tris[t1], a, b);
+        let d = third(&tris[t2], b, a);
+        if incircle(pts[a], pts[b], pts[c], pts[d]) != Ordering::Greater {
+            continue;
+        }
+        // Only flip if the quad a, d, b, c is strictly convex.
+        if orient2d(pts[c], pts[d], pts[a]) != Ordering::Less ||
+           orient2d(pts[c], pts[d], pts[b]) != Ordering::Greater {
+            continue;
+        }
+
+        for &t in &[t1, t2] {
+            for k in 0..3 {
+                edges.remove(&(tris[t][k], tris[t][(k + 1) % 3]));
+            }
+        }
+        tris[t1] = [a, d, c];
+        tris[t2] = [b, c, d];
+        for &t in &[t1, t2] {
+            for k in 0..3 {
+                edges.insert((tris[t][k], tris[t][(k + 1) % 3]), t);
+            }
+        }
+        stack.extend_from_slice(&[(a, d), (d, b), (b, c), (c, a)]);
+    }
+    tris
+}
+
+// third returns the vertex of the triangle which is not a or b.
+fn third(t: &[usize; 3], a: usize, b: usize) -> usize {
+    *t.iter().find(|&&v| v != a && v != b).unwrap()
+}
+
+// in_cone tells if the point m lies within the interior angle of the
+// counterclockwise ring at vertex p (with neighbours prev and next).
+fn in_cone(prev: Vec2<f64>, p: Vec2<f64>, next: Vec2<f64>, m: Vec2<f64>) -> bool {
+    let left_of_prev = orient2d(prev, p, m) != Ordering::Less;
+    let left_of_next = orient2d(p, next, m) != Ordering::Less;
+    if orient2d(prev, p, next) != Ordering::Less {
+        left_of_prev && left_of_next
+    } else {
+        left_of_prev || left_of_next
+    }
+}
+
+// in_triangle tells if p lies inside or on the counterclockwise triangle
+// a, b, c.
+fn in_triangle(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>, p: Vec2<f64>) -> bool {
+    orient2d(a, b, p) != Ordering::Less && orient2d(b, c, p) != Ordering::Less &&
+    orient2d(c, a, p) != Ordering::Less
+}
+
+// bridge_hole joins the clockwise hole into the counterclockwise outer ring
+// by a pair of coincident bridge edges, as described by David Eberly in
+// "Triangulation by Ear Clipping".
+fn bridge_hole(pts: &[Vec2<f64>], outer: &mut Vec<usize>, hole: &[usize]) {
+    // The rightmost hole vertex, M.
+    let mi = (0..hole.len())
+        .max_by(|&a, &b| {
+            let (pa, pb) = (pts[hole[a]], pts[hole[b]]);
+            pa.0.partial_cmp(&pb.0).unwrap_or(Ordering::Equal).then(pb.1.partial_cmp(&pa.1).unwrap_or(Ordering::Equal))
+        })
+        .unwrap();
+    let m = pts[hole[mi]];
+
+    // Cast a ray from M toward +x and find the closest outer edge it hits.
+    let n = outer.len();
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (pts[outer[i]], pts[outer[(i + 1) % n]]);
+        // The exterior is counterclockwise, so edges visible from the left
+        // run upward (or end/start at M's height).
+        if !(a.1 <= m.1 && b.1 >= m.1) || a.1 == b.1 {
+            continue;
+        }
+        let x = a.0 + (m.1 - a.1) * (b.0 - a.0) / (b.1 - a.1);
+        if x < m.0 {
+            continue;
+        }
+        // Choose the endpoint with the largest x as the candidate, unless the
+        // ray hits a vertex exactly.
+        let k = if a.1 == m.1 {
+            i
+        } else if b.1 == m.1 {
+            (i + 1) % n
+        } else if a.0 > b.0 {
+            i
+        } else {
+            (i + 1) % n
+        };
+        match best {
+            Some((bx, _)) if bx <= x => {}
+            _ => best = Some((x, k)),
+        }
+    }
+    let (ix, mut k) = match best {
+        Some(b) => b,
+        None => return,
+    };
+    let i_pt = Vec2(ix, m.1);
+    let p = pts[outer[k]];
+
+    // If any reflex vertex lies within the triangle M, I, P then the closest
+    // one (by angle to the ray) is visible from M instead.
+    if p != i_pt {
+        let (t0, t1, t2) = if orient2d(m, i_pt, p) == Ordering::Less {
+            (m, p, i_pt)
+        } else {
+            (m, i_pt, p)
+        };
+        let mut best_angle = f64::MAX;
+        let mut best_dist = f64::MAX;
+        for j in 0..n {
+            let (prev, r, next) = (pts[outer[(j + n - 1) % n]], pts[outer[j]], pts[outer[(j + 1) % n]]);
+            if r == p || orient2d(prev, r, next) == Ordering::Greater || !in_triangle(t0, t1, t2, r) {
+                continue;
+            }
+            let d = r - m;
+            let angle = d.1.abs() / d.0.max(0.0);
+            let dist = d.length_sq();
+            if angle < best_angle || (angle == best_angle && dist < best_dist) {
+                best_angle = angle;
+                best_dist = dist;
+                k = j;
+            }
+        }
+    }
+
+    // The chosen vertex may appear more than once (from previous bridges),
+    // pick the occurrence whose interior angle contains M.
+    let target = outer[k];
+    for j in 0..n {
+        if outer[j] == target &&
+           in_cone(pts[outer[(j + n - 1) % n]], pts[target], pts[outer[(j + 1) % n]], m) {
+            k = j;
+            break;
+        }
+    }
+
+    let mut merged = Vec::with_capacity(n + hole.len() + 2);
+    merged.extend_from_slice(&outer[..k + 1]);
+    merged.extend_from_slice(&hole[mi..]);
+    merged.extend_from_slice(&hole[..mi + 1]);
+    merged.extend_from_slice(&outer[k..]);
+    *outer = merged;
+}
+
+// ear_clip triangulates the counterclockwise (weakly simple) ring of vertex
+// indices.
+fn ear_clip(pts: &[Vec2<f64>], ring: &[usize]) -> Vec<[usize; 3]> {
+    let n = ring.len();
+    let mut tris = Vec::with_capacity(n.saturating_sub(2));
+    if n < 3 {
+        return tris;
+    }
+    let mut prev: Vec<usize> = (0..n).map(|i| (i + n - 1) % n).collect();
+    let mut next: Vec<usize> = (0..n).map(|i| (i + 1) % n).collect();
+    let mut remaining = n;
+    let mut cur = 0;
+    let mut stalled = 0;
+    let pos = |i: usize| pts[ring[i]];
+
+    while remaining > 3 {
+        let (p, q) = (prev[cur], next[cur]);
+        let (a, b, c) = (pos(p), pos(cur), pos(q));
+        let o = orient2d(a, b, c);
+
+        let mut ear = o == Ordering::Greater;
+        if ear {
+            // No reflex vertex (other than those coincident with a corner) may
+            // lie within the ear.
+            let mut r = next[q];
+            while r != p {
+                let v = pos(r);
+                if v != a && v != b && v != c &&
+                   orient2d(pos(prev[r]), v, pos(next[r])) != Ordering::Greater &&
+                   in_triangle(a, b, c, v) {
+                    ear = false;
+                    break;
+                }
+                r = next[r];
+            }
+        }
+
+        // A vertex that is collinear with its neighbours is kept until it
+        // becomes part of an ear, so that it isn't left as a T-junction. If no
+        // ear can be found such vertices (and zero-width spikes) are dropped,
+        // and failing that the input is not simple, so clip anyway to
+        // guarantee progress.
+        let force = stalled >= remaining;
+        if ear || (force && o != Ordering::Less) || stalled >= 2 * remaining {
+            if o == Ordering::Greater {
+                tris.push([ring[p], ring[cur], ring[q]]);
+            }
+            next[p] = q;
+            prev[q] = p;
+            remaining -= 1;
+            stalled = 0;
+            cur = p;
+        } else {
+            stalled += 1;
+            cur = q;
+        }
+    }
+    let (p, q) = (prev[cur], next[cur]);
+    if orient2d(pos(p), pos(cur), pos(q)) == Ordering::Greater {
+        tris.push([ring[p], ring[cur], ring[q]]);
+    }
+    tris
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use super::{triangulate, triangulate_delaunay};
+    use super::super::Polygon;
+    use super::super::super::predicates::incircle;
+    use Vec2;
+
+    fn tri_area(v: &[Vec2<f64>], t: &[usize; 3]) -> f64 {
+        let (a, b, c) = (v[t[0]], v[t[1]], v[t[2]]);
+        ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)) / 2.0
+    }
+
+    // comb returns a comb-shaped polygon with two rectangular holes, which has
+    // many reflex vertices and collinear edges.
+    fn comb() -> Polygon<f64> {
+        let mut ext = vec![Vec2(0.0, 0.0), Vec2(10.0, 0.0), Vec2(10.0, 5.0)];
+        for i in 0..4 {
+            let x = 9.0 - i as f64 * 2.0;
+            ext.push(Vec2(x, 5.0));
+            ext.push(Vec2(x, 3.0));
+            ext.push(Vec2(x - 1.0, 3.0));
+            ext.push(Vec2(x - 1.0, 5.0));
+        }
+        ext.push(Vec2(0.0, 5.0));
+        Polygon::new(ext)
+            .with_hole(vec![Vec2(1.0, 1.0), Vec2(1.0, 2.0), Vec2(4.0, 2.0), Vec2(4.0, 1.0)])
+            .with_hole(vec![Vec2(5.0, 1.0), Vec2(9.0, 1.0), Vec2(9.0, 2.0), Vec2(5.0, 2.0)])
+    }
+
+    #[test]
+    fn covers_area() {
+        let p = comb();
+        let v = p.vertices();
+        for tris in [triangulate(&p), triangulate_delaunay(&p)] {
+            assert_eq!(tris.len(), v.len() - 2 + 2 * p.holes.len());
+            let mut sum = 0.0;
+            for t in &tris {
+                let a = tri_area(&v, t);
+                assert!(a > 0.0);
+                sum += a;
+
+                // Every triangle's centroid lies within the polygon.
+                let c = (v[t[0]] + v[t[1]] + v[t[2]]).div_scalar(3.0);
+                assert!(p.contains(c), "triangle {:?} outside polygon", t);
+            }
+            assert_eq!(sum, p.area());
+        }
+    }
+
+    #[test]
+    fn delaunay_property() {
+        // A convex polygon's constrained Delaunay triangulation is its
+        // Delaunay triangulation, so no vertex lies inside any circumcircle.
+        let ring: Vec<Vec2<f64>> = (0..12)
+            .map(|i| {
+                let a = i as f64 * 0.5;
+                Vec2(a.cos() * (3.0 + (i % 3) as f64), a.sin() * 2.0)
+            })
+            .collect();
+        let hull = super::super::convex_hull(&ring);
+        let p = Polygon::new(hull.clone());
+        let tris = triangulate_delaunay(&p);
+        assert_eq!(tris.len(), hull.len() - 2);
+        for t in &tris {
+            for (i, &v) in hull.iter().enumerate() {
+                if t.contains(&i) {
+                    continue;
+                }
+                assert!(incircle(hull[t[0]], hull[t[1]], hull[t[2]], v) != Ordering::Greater);
+            }
+        }
+    }
+}
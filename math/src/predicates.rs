@@ -0,0 +1,478 @@
+//! Robust geometric predicates.
+//!
+//! The predicates in this module return the exact sign of their determinant,
+//! regardless of floating-point roundoff. They follow the approach described
+//! by Jonathan Richard Shewchuk in "Adaptive Precision Floating-Point
+//! Arithmetic and Fast Robust Geometric Predicates":
+//!
+//! http://www.cs.cmu.edu/~quake/robust.html
+//!
+//! Each predicate is first evaluated with ordinary floating-point arithmetic,
+//! and that result is used whenever a forward error bound proves its sign is
+//! correct. Only when the input is (nearly) degenerate is the determinant
+//! recomputed exactly using floating-point expansions, so the common case
+//! stays fast.
+
+use std::cmp::Ordering;
+use super::{Vec2, Vec3};
+
+// Half an ulp of 1.0, i.e. the maximum relative roundoff error of an f64
+// operation.
+const EPS: f64 = 1.1102230246251565e-16;
+
+const CCW_ERR_BOUND_A: f64 = (3.0 + 16.0 * EPS) * EPS;
+const O3D_ERR_BOUND_A: f64 = (7.0 + 56.0 * EPS) * EPS;
+const ICC_ERR_BOUND_A: f64 = (10.0 + 96.0 * EPS) * EPS;
+const ISP_ERR_BOUND_A: f64 = (16.0 + 224.0 * EPS) * EPS;
+
+// An expansion is a sum of non-overlapping f64 components, sorted by
+// increasing magnitude, which represents a real number exactly.
+type Expansion = Vec<f64>;
+
+// two_sum returns the rounded sum a + b along with its exact roundoff error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+// two_product returns the rounded product a * b along with its exact
+// roundoff error.
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+// diff returns the exact expansion of a - b.
+fn diff(a: f64, b: f64) -> Expansion {
+    let (x, y) = two_sum(a, -b);
+    compress(vec![y, x])
+}
+
+// compress removes zero components from the expansion.
+fn compress(mut e: Expansion) -> Expansion {
+    e.retain(|&c| c != 0.0);
+    e
+}
+
+// sum returns the exact expansion of e + f.
+fn sum(e: &[f64], f: &[f64]) -> Expansion {
+    let mut h: Expansion = e.to_vec();
+    for &b in f {
+        // Grow h by the component b.
+        let mut q = b;
+        for c in h.iter_mut() {
+            let (s, err) = two_sum(q, *c);
+            *c = err;
+            q = s;
+        }
+        h.push(q);
+        h = compress(h);
+    }
+    h
+}
+
+// neg returns the expansion of -e.
+fn neg(e: &[f64]) -> Expansion {
+    e.iter().map(|c| -c).collect()
+}
+
+// scale returns the exact expansion of e * b.
+fn scale(e: &[f64], b: f64) -> Expansion {
+    let mut h = Vec::with_capacity(e.len() * 2);
+    let mut q = 0.0;
+    for &c in e {
+        let (p1, p0) = two_product(c, b);
+        let (s, err) = two_sum(q, p0);
+        h.push(err);
+        let (s2, err2) = two_sum(p1, s);
+        h.push(err2);
+        q = s2;
+    }
+    h.push(q);
+    // The components above may overlap slightly in magnitude ordering, so
+    // normalize by summing into a fresh expansion.
+    sum(&[], &compress(h))
+}
+
+// mul returns the exact expansion of e * f.
+fn mul(e: &[f64], f: &[f64]) -> Expansion {
+    f.iter().fold(Vec::new(), |acc, &b| sum(&acc, &scale(e, b)))
+}
+
+// sign returns the sign of the expansion, which is the sign of its largest
+// component.
+fn sign(e: &[f64]) -> Ordering {
+    match e.iter().rev().find(|&&c| c != 0.0) {
+        Some(c) if *c > 0.0 => Ordering::Greater,
+        Some(_) => Ordering::Less,
+        None => Ordering::Equal,
+    }
+}
+
+// sign_of returns the sign of the floating-point value.
+fn sign_of(v: f64) -> Ordering {
+    v.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+
+// det2 returns the exact expansion of a * d - b * c.
+fn det2(a: &[f64], b: &[f64], c: &[f64], d: &[f64]) -> Expansion {
+    sum(&mul(a, d), &neg(&mul(b, c)))
+}
+
+/// orient2d tells on which side of the directed line through `a` and `b` the
+/// point `c` lies.
+///
+/// The result is `Greater` if `a`, `b` and `c` appear in counterclockwise
+/// order, `Less` if they appear in clockwise order, and `Equal` if they are
+/// exactly collinear.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use fiz_math::Vec2;
+/// use fiz_math::predicates::orient2d;
+///
+/// let (a, b) = (Vec2(0.0, 0.0), Vec2(1.0, 0.0));
+/// assert_eq!(orient2d(a, b, Vec2(0.5, 1.0)), Ordering::Greater);
+/// assert_eq!(orient2d(a, b, Vec2(0.5, -1.0)), Ordering::Less);
+/// assert_eq!(orient2d(a, b, Vec2(2.0, 0.0)), Ordering::Equal);
+/// ```
+pub fn orient2d(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>) -> Ordering {
+    let det_left = (a.0 - c.0) * (b.1 - c.1);
+    let det_right = (a.1 - c.1) * (b.0 - c.0);
+    let det = det_left - det_right;
+    let err_bound = CCW_ERR_BOUND_A * (det_left.abs() + det_right.abs());
+    if det > err_bound || -det > err_bound {
+        return sign_of(det);
+    }
+
+    let (acx, acy) = (diff(a.0, c.0), diff(a.1, c.1));
+    let (bcx, bcy) = (diff(b.0, c.0), diff(b.1, c.1));
+    sign(&det2(&acx, &acy, &bcx, &bcy))
+}
+
+/// orient3d tells on which side of the plane through `a`, `b` and `c` the
+/// point `d` lies.
+///
+/// The result is `Greater` if `d` lies below the plane, where "below" is
+/// defined such that `a`, `b` and `c` appear in counterclockwise order when
+/// viewed from above. It is `Less` if `d` lies above the plane, and `Equal`
+/// if the four points are exactly coplanar.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use fiz_math::Vec3;
+/// use fiz_math::predicates::orient3d;
+///
+/// let (a, b, c) = (Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+/// assert_eq!(orient3d(a, b, c, Vec3(0.0, 0.0, -1.0)), Ordering::Greater);
+/// assert_eq!(orient3d(a, b, c, Vec3(0.0, 0.0, 1.0)), Ordering::Less);
+/// assert_eq!(orient3d(a, b, c, Vec3(5.0, 5.0, 0.0)), Ordering::Equal);
+/// ```
+pub fn orient3d(a: Vec3<f64>, b: Vec3<f64>, c: Vec3<f64>, d: Vec3<f64>) -> Ordering {
+    let (adx, ady, adz) = (a.0 - d.0, a.1 - d.1, a.2 - d.2);
+    let (bdx, bdy, bdz) = (b.0 - d.0, b.1 - d.1, b.2 - d.2);
+    let (cdx, cdy, cdz) = (c.0 - d.0, c.1 - d.1, c.2 - d.2);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+
+    let det = adz * (bdxcdy - cdxbdy) + bdz * (cdxady - adxcdy) + cdz * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * adz.abs() +
+                    (cdxady.abs() + adxcdy.abs()) * bdz.abs() +
+                    (adxbdy.abs() + bdxady.abs()) * cdz.abs();
+    let err_bound = O3D_ERR_BOUND_A * permanent;
+    if det > err_bound || -det > err_bound {
+        return sign_of(det);
+    }
+
+    let (adx, ady, adz) = (diff(a.0, d.0), diff(a.1, d.1), diff(a.2, d.2));
+    let (bdx, bdy, bdz) = (diff(b.0, d.0), diff(b.1, d.1), diff(b.2, d.2));
+    let (cdx, cdy, cdz) = (diff(c.0, d.0), diff(c.1, d.1), diff(c.2, d.2));
+    let bc = det2(&bdx, &bdy, &cdx, &cdy);
+    let ca = det2(&cdx, &cdy, &adx, &ady);
+    let ab = det2(&adx, &ady, &bdx, &bdy);
+    let det = sum(&sum(&mul(&adz, &bc), &mul(&bdz, &ca)), &mul(&cdz, &ab));
+    sign(&det)
+}
+
+/// incircle tells whether the point `d` lies inside the circle passing
+/// through `a`, `b` and `c`.
+///
+/// Assuming `a`, `b` and `c` are in counterclockwise order (see `orient2d`),
+/// the result is `Greater` if `d` lies inside the circle, `Less` if it lies
+/// outside, and `Equal` if the four points are exactly cocircular. The sign
+/// is reversed when `a`, `b` and `c` are in clockwise order.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use fiz_math::Vec2;
+/// use fiz_math::predicates::incircle;
+///
+/// let (a, b, c) = (Vec2(1.0, 0.0), Vec2(0.0, 1.0), Vec2(-1.0, 0.0));
+/// assert_eq!(incircle(a, b, c, Vec2(0.0, 0.5)), Ordering::Greater);
+/// assert_eq!(incircle(a, b, c, Vec2(0.0, -2.0)), Ordering::Less);
+/// assert_eq!(incircle(a, b, c, Vec2(0.0, -1.0)), Ordering::Equal);
+/// ```
+pub fn incircle(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>, d: Vec2<f64>) -> Ordering {
+    let (adx, ady) = (a.0 - d.0, a.1 - d.1);
+    let (bdx, bdy) = (b.0 - d.0, b.1 - d.1);
+    let (cdx, cdy) = (c.0 - d.0, c.1 - d.1);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift +
+                    (cdxady.abs() + adxcdy.abs()) * blift +
+                    (adxbdy.abs() + bdxady.abs()) * clift;
+    let err_bound = ICC_ERR_BOUND_A * permanent;
+    if det > err_bound || -det > err_bound {
+        return sign_of(det);
+    }
+
+    let (adx, ady) = (diff(a.0, d.0), diff(a.1, d.1));
+    let (bdx, bdy) = (diff(b.0, d.0), diff(b.1, d.1));
+    let (cdx, cdy) = (diff(c.0, d.0), diff(c.1, d.1));
+    let alift = sum(&mul(&adx, &adx), &mul(&ady, &ady));
+    let blift = sum(&mul(&bdx, &bdx), &mul(&bdy, &bdy));
+    let clift = sum(&mul(&cdx, &cdx), &mul(&cdy, &cdy));
+    let bc = det2(&bdx, &bdy, &cdx, &cdy);
+    let ca = det2(&cdx, &cdy, &adx, &ady);
+    let ab = det2(&adx, &ady, &bdx, &bdy);
+    let det = sum(&sum(&mul(&alift, &bc), &mul(&blift, &ca)), &mul(&clift, &ab));
+    sign(&det)
+}
+
+/// insphere tells whether the point `e` lies inside the sphere passing
+/// through `a`, `b`, `c` and `d`.
+///
+/// Assuming `orient3d(a, b, c, d)` is `Greater`, the result is `Greater` if
+/// `e` lies inside the sphere, `Less` if it lies outside, and `Equal` if the
+/// five points are exactly cospherical. The sign is reversed when
+/// `orient3d(a, b, c, d)` is `Less`.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use fiz_math::Vec3;
+/// use fiz_math::predicates::{insphere, orient3d};
+///
+/// let a = Vec3(1.0, 0.0, 0.0);
+/// let b = Vec3(0.0, 1.0, 0.0);
+/// let c = Vec3(-1.0, 0.0, 0.0);
+/// let d = Vec3(0.0, 0.0, -1.0);
+/// assert_eq!(orient3d(a, b, c, d), Ordering::Greater);
+/// assert_eq!(insphere(a, b, c, d, Vec3(0.0, 0.0, 0.0)), Ordering::Greater);
+/// assert_eq!(insphere(a, b, c, d, Vec3(0.0, 0.0, 2.0)), Ordering::Less);
+/// assert_eq!(insphere(a, b, c, d, Vec3(0.0, -1.0, 0.0)), Ordering::Equal);
+/// ```
+pub fn insphere(a: Vec3<f64>,
+                b: Vec3<f64>,
+                c: Vec3<f64>,
+                d: Vec3<f64>,
+                e: Vec3<f64>)
+                -> Ordering {
+    let (aex, aey, aez) = (a.0 - e.0, a.1 - e.1, a.2 - e.2);
+    let (bex, bey, bez) = (b.0 - e.0, b.1 - e.1, b.2 - e.2);
+    let (cex, cey, cez) = (c.0 - e.0, c.1 - e.1, c.2 - e.2);
+    let (dex, dey, dez) = (d.0 - e.0, d.1 - e.1, d.2 - e.2);
+
+    let (aexbey, bexaey) = (aex * bey, bex * aey);
+    let (bexcey, cexbey) = (bex * cey, cex * bey);
+    let (cexdey, dexcey) = (cex * dey, dex * cey);
+    let (dexaey, aexdey) = (dex * aey, aex * dey);
+    let (aexcey, cexaey) = (aex * cey, cex * aey);
+    let (bexdey, dexbey) = (bex * dey, dex * bey);
+    let ab = aexbey - bexaey;
+    let bc = bexcey - cexbey;
+    let cd = cexdey - dexcey;
+    let da = dexaey - aexdey;
+    let ac = aexcey - cexaey;
+    let bd = bexdey - dexbey;
+
+    let abc = aez * bc - bez * ac + cez * ab;
+    let bcd = bez * cd - cez * bd + dez * bc;
+    let cda = cez * da + dez * ac + aez * cd;
+    let dab = dez * ab + aez * bd + bez * da;
+
+    let alift = aex * aex + aey * aey + aez * aez;
+    let blift = bex * bex + bey * bey + bez * bez;
+    let clift = cex * cex + cey * cey + cez * cez;
+    let dlift = dex * dex + dey * dey + dez * dez;
+
+    let det = (dlift * abc - clift * dab) + (blift * cda - alift * bcd);
+
+    let (aezp, bezp, cezp, dezp) = (aez.abs(), bez.abs(), cez.abs(), dez.abs());
+    let (aexbeyp, bexaeyp) = (aexbey.abs(), bexaey.abs());
+    let (bexceyp, cexbeyp) = (bexcey.abs(), cexbey.abs());
+    let (cexdeyp, dexceyp) = (cexdey.abs(), dexcey.abs());
+    let (dexaeyp, aexdeyp) = (dexaey.abs(), aexdey.abs());
+    let (aexceyp, cexaeyp) = (aexcey.abs(), cexaey.abs());
+    let (bexdeyp, dexbeyp) = (bexdey.abs(), dexbey.abs());
+    let permanent = ((cexdeyp + dexceyp) * bezp + (dexbeyp + bexdeyp) * cezp +
+                     (bexceyp + cexbeyp) * dezp) * alift +
+                    ((dexaeyp + aexdeyp) * cezp + (aexceyp + cexaeyp) * dezp +
+                     (cexdeyp + dexceyp) * aezp) * blift +
+                    ((aexbeyp + bexaeyp) * dezp + (bexdeyp + dexbeyp) * aezp +
+                     (dexaeyp + aexdeyp) * bezp) * clift +
+                    ((bexceyp + cexbeyp) * aezp + (cexaeyp + aexceyp) * bezp +
+                     (aexbeyp + bexaeyp) * cezp) * dlift;
+    let err_bound = ISP_ERR_BOUND_A * permanent;
+    if det > err_bound || -det > err_bound {
+        return sign_of(det);
+    }
+
+    let (aex, aey, aez) = (diff(a.0, e.0), diff(a.1, e.1), diff(a.2, e.2));
+    let (bex, bey, bez) = (diff(b.0, e.0), diff(b.1, e.1), diff(b.2, e.2));
+    let (cex, cey, cez) = (diff(c.0, e.0), diff(c.1, e.1), diff(c.2, e.2));
+    let (dex, dey, dez) = (diff(d.0, e.0), diff(d.1, e.1), diff(d.2, e.2));
+
+    let ab = det2(&aex, &aey, &bex, &bey);
+    let bc = det2(&bex, &bey, &cex, &cey);
+    let cd = det2(&cex, &cey, &dex, &dey);
+    let da = det2(&dex, &dey, &aex, &aey);
+    let ac = det2(&aex, &aey, &cex, &cey);
+    let bd = det2(&bex, &bey, &dex, &dey);
+
+    let abc = sum(&sum(&mul(&aez, &bc), &neg(&mul(&bez, &ac))), &mul(&cez, &ab));
+    let bcd = sum(&sum(&mul(&bez, &cd), &neg(&mul(&cez, &bd))), &mul(&dez, &bc));
+    let cda = sum(&sum(&mul(&cez, &da), &mul(&dez, &ac)), &mul(&aez, &cd));
+    let dab = sum(&sum(&mul(&dez, &ab), &mul(&aez, &bd)), &mul(&bez, &da));
+
+    let lift = |x: &[f64], y: &[f64], z: &[f64]| sum(&sum(&mul(x, x), &mul(y, y)), &mul(z, z));
+    let alift = lift(&aex, &aey, &aez);
+    let blift = lift(&bex, &bey, &bez);
+    let clift = lift(&cex, &cey, &cez);
+    let dlift = lift(&dex, &dey, &dez);
+
+    let det = sum(&sum(&mul(&dlift, &abc), &neg(&mul(&clift, &dab))),
+                  &sum(&mul(&blift, &cda), &neg(&mul(&alift, &bcd))));
+    sign(&det)
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use super::{orient2d, orient3d, incircle, insphere};
+    use {Vec2, Vec3};
+
+    // ULP is the spacing of f64 values in [0.5, 1).
+    const ULP: f64 = 1.1102230246251565e-16;
+
+    // nudge moves v by the given number of ulps away from zero.
+    fn nudge(v: f64, ulps: i64) -> f64 {
+        f64::from_bits((v.to_bits() as i64 + ulps) as u64)
+    }
+
+    // exact_orient2d computes the orientation of points whose coordinates are
+    // all multiples of ULP using 128-bit integers.
+    fn exact_orient2d(a: Vec2<f64>, b: Vec2<f64>, c: Vec2<f64>) -> Ordering {
+        let i = |v: f64| (v / ULP) as i128;
+        let (acx, acy) = (i(a.0) - i(c.0), i(a.1) - i(c.1));
+        let (bcx, bcy) = (i(b.0) - i(c.0), i(b.1) - i(c.1));
+        (acx * bcy - acy * bcx).cmp(&0)
+    }
+
+    #[test]
+    fn orient2d_near_collinear() {
+        // The classic example from Kettner et al. "Classroom Examples of
+        // Robustness Problems in Geometric Computations": a grid of points
+        // perturbed by single ulps around a line.
+        let (b, c) = (Vec2(12.0, 12.0), Vec2(24.0, 24.0));
+        let mut seen = [false; 3];
+        for x in 0..64 {
+            for y in 0..64 {
+                let a = Vec2(0.5 + x as f64 * ULP, 0.5 + y as f64 * ULP);
+                let o = orient2d(a, b, c);
+                assert_eq!(o, exact_orient2d(a, b, c), "at {:?}", a);
+                seen[(o as i32 + 1) as usize] = true;
+            }
+        }
+        assert_eq!(seen, [true, true, true]);
+    }
+
+    #[test]
+    fn orient2d_symmetry() {
+        let (a, b) = (Vec2(0.1, 0.1), Vec2(0.3, 0.3));
+        for i in 0..100 {
+            let c = Vec2(0.2 + i as f64 * 1e-17, 0.2);
+            let o = orient2d(a, b, c);
+            assert_eq!(orient2d(b, c, a), o);
+            assert_eq!(orient2d(c, a, b), o);
+            assert_eq!(orient2d(b, a, c), o.reverse());
+        }
+    }
+
+    #[test]
+    fn orient3d_degenerate() {
+        // Points on the plane x + y + z = 1, which is not exactly
+        // representable for most inputs.
+        let a = Vec3(1.0, 0.0, 0.0);
+        let b = Vec3(0.0, 1.0, 0.0);
+        let c = Vec3(0.0, 0.0, 1.0);
+        assert_eq!(orient3d(a, b, c, Vec3(0.5, 0.5, 0.0)), Ordering::Equal);
+        assert_eq!(orient3d(a, b, c, Vec3(0.25, 0.25, 0.5)), Ordering::Equal);
+
+        let d = Vec3(0.25, 0.25, 0.5 + ULP);
+        assert_eq!(orient3d(a, b, c, d), Ordering::Less);
+        assert_eq!(orient3d(b, a, c, d), Ordering::Greater);
+        let d = Vec3(0.25, 0.25, 0.5 - ULP);
+        assert_eq!(orient3d(a, b, c, d), Ordering::Greater);
+
+        // All four points collinear.
+        let (a, b, c) = (Vec3(0.0, 0.0, 0.0), Vec3(1.0, 1.0, 1.0), Vec3(2.0, 2.0, 2.0));
+        assert_eq!(orient3d(a, b, c, Vec3(3.0, 3.0, 3.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn incircle_degenerate() {
+        // Points on a circle of radius 5 with integer coordinates.
+        let (a, b, c) = (Vec2(5.0, 0.0), Vec2(3.0, 4.0), Vec2(-4.0, 3.0));
+        assert_eq!(incircle(a, b, c, Vec2(0.0, -5.0)), Ordering::Equal);
+        assert_eq!(incircle(a, b, c, Vec2(-3.0, -4.0)), Ordering::Equal);
+        assert_eq!(incircle(a, b, c, Vec2(0.0, nudge(-5.0, -1))), Ordering::Greater);
+        assert_eq!(incircle(a, b, c, Vec2(0.0, nudge(-5.0, 1))), Ordering::Less);
+        assert_eq!(incircle(c, b, a, Vec2(0.0, nudge(-5.0, -1))), Ordering::Less);
+
+        // Translated far from the origin, where the fast filter must fail.
+        let t = Vec2(1.0e6, 1.0e6);
+        assert_eq!(incircle(a + t, b + t, c + t, Vec2(-3.0, -4.0) + t), Ordering::Equal);
+
+        // Collinear a, b, c: every point not on the line is outside.
+        let (a, b, c) = (Vec2(0.0, 0.0), Vec2(1.0, 0.0), Vec2(2.0, 0.0));
+        assert_eq!(incircle(a, b, c, Vec2(3.0, 0.0)), Ordering::Equal);
+    }
+
+    #[test]
+    fn insphere_degenerate() {
+        // Points on a sphere of radius 3 with integer coordinates.
+        let a = Vec3(3.0, 0.0, 0.0);
+        let b = Vec3(0.0, 3.0, 0.0);
+        let c = Vec3(-3.0, 0.0, 0.0);
+        let d = Vec3(0.0, 0.0, -3.0);
+        assert_eq!(orient3d(a, b, c, d), Ordering::Greater);
+        assert_eq!(insphere(a, b, c, d, Vec3(1.0, 2.0, 2.0)), Ordering::Equal);
+        assert_eq!(insphere(a, b, c, d, Vec3(-2.0, -1.0, 2.0)), Ordering::Equal);
+        assert_eq!(insphere(a, b, c, d, Vec3(0.0, 0.0, nudge(3.0, -1))), Ordering::Greater);
+        assert_eq!(insphere(a, b, c, d, Vec3(0.0, 0.0, nudge(3.0, 1))), Ordering::Less);
+        assert_eq!(insphere(b, a, c, d, Vec3(0.0, 0.0, nudge(3.0, -1))), Ordering::Less);
+
+        let t = Vec3(1.0e5, -1.0e5, 1.0e5);
+        assert_eq!(insphere(a + t, b + t, c + t, d + t, Vec3(1.0, 2.0, 2.0) + t),
+                   Ordering::Equal);
+    }
+}
@@ -0,0 +1,227 @@
+use std::ops::{Mul, Neg};
+use super::float::Float;
+use super::unit::ToRad;
+use super::{Mat4, Vec3, Vec4};
+
+/// Quat is a quaternion `(x, y, z, w)`, which as a unit quaternion
+/// represents a rotation. Like matrices, `a * b` rotates by `b` and then by
+/// `a`.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Quat, Vec3};
+/// use fiz_math::unit::Deg;
+///
+/// let q = Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), Deg(90.0));
+/// assert!(q.rotate(Vec3(1.0, 0.0, 0.0)).almost_equal(Vec3(0.0, 1.0, 0.0), 1e-6));
+/// assert!((q * q).rotate(Vec3(1.0, 0.0, 0.0)).almost_equal(Vec3(-1.0, 0.0, 0.0), 1e-6));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quat<T>(pub T, pub T, pub T, pub T);
+
+impl<T: Float> Quat<T> {
+    /// identity returns the quaternion which doesn't rotate.
+    pub fn identity() -> Self {
+        Quat(T::zero(), T::zero(), T::zero(), T::one())
+    }
+
+    /// from_axis_angle returns the rotation counter-clockwise about the
+    /// given axis, looking down it, by the given angle.
+    ///
+    /// # Panics
+    ///
+    /// from_axis_angle panics if the axis is zero.
+    pub fn from_axis_angle<A>(axis: Vec3<T>, angle: A) -> Self
+        where A: ToRad<Output = T>
+    {
+        let axis = axis.normalize().expect("Quat::from_axis_angle: zero axis");
+        let half = angle.to_rad().0 / (T::one() + T::one());
+        let (s, c) = half.sin_cos();
+        Quat(axis.0 * s, axis.1 * s, axis.2 * s, c)
+    }
+
+    /// from_mat4 returns the rotation of a matrix whose upper 3x3 part is a
+    /// rotation, that is, orthonormal with a positive determinant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Quat, Vec3};
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let q = Quat::from_axis_angle(Vec3(1.0, 2.0, 3.0), Deg(150.0));
+    /// assert!(Quat::from_mat4(&q.to_mat4()).almost_equal(q, 1e-6));
+    /// ```
+    pub fn from_mat4(m: &Mat4<T>) -> Self {
+        let (m00, m01, m02) = (m.0 .0, m.1 .0, m.2 .0);
+        let (m10, m11, m12) = (m.0 .1, m.1 .1, m.2 .1);
+        let (m20, m21, m22) = (m.0 .2, m.1 .2, m.2 .2);
+        let (one, two) = (T::one(), T::one() + T::one());
+        let quarter = one / (two + two);
+        // Divide by the largest of the four components, for precision.
+        let trace = m00 + m11 + m22;
+        let q = if trace > T::zero() {
+            let s = (trace + one).sqrt() * two;
+            Quat((m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, s * quarter)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (one + m00 - m11 - m22).sqrt() * two;
+            Quat(s * quarter, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s)
+        } else if m11 > m22 {
+            let s = (one + m11 - m00 - m22).sqrt() * two;
+            Quat((m01 + m10) / s, s * quarter, (m12 + m21) / s, (m02 - m20) / s)
+        } else {
+            let s = (one + m22 - m00 - m11).sqrt() * two;
+            Quat((m02 + m20) / s, (m12 + m21) / s, s * quarter, (m10 - m01) / s)
+        };
+        // Prefer a positive w, so that equal rotations compare equal.
+        if q.3 < T::zero() { -q } else { q }
+    }
+
+    /// to_mat4 returns the rotation matrix of a unit quaternion.
+    pub fn to_mat4(self) -> Mat4<T> {
+        let Quat(x, y, z, w) = self;
+        let (z0, one, two) = (T::zero(), T::one(), T::one() + T::one());
+        Mat4(Vec4(one - two * (y * y + z * z), two * (x * y + w * z), two * (x * z - w * y), z0),
+             Vec4(two * (x * y - w * z), one - two * (x * x + z * z), two * (y * z + w * x), z0),
+             Vec4(two * (x * z + w * y), two * (y * z - w * x), one - two * (x * x + y * y), z0),
+             Vec4(z0, z0, z0, one))
+    }
+
+    /// dot returns the dot product of the quaternions.
+    pub fn dot(self, other: Self) -> T {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2 + self.3 * other.3
+    }
+
+    /// length returns the magnitude of the quaternion.
+    pub fn length(self) -> T {
+        self.dot(self).sqrt()
+    }
+
+    /// normalize returns the quaternion scaled to unit length, or None if
+    /// its length is zero.
+    pub fn normalize(self) -> Option<Self> {
+        let l = self.length();
+        if l == T::zero() || !l.is_finite() {
+            return None;
+        }
+        Some(Quat(self.0 / l, self.1 / l, self.2 / l, self.3 / l))
+    }
+
+    /// conjugate returns the quaternion with its vector part negated, which
+    /// for unit quaternions is the inverse rotation.
+    pub fn conjugate(self) -> Self {
+        Quat(-self.0, -self.1, -self.2, self.3)
+    }
+
+    /// rotate returns the vector v rotated by the unit quaternion.
+    pub fn rotate(self, v: Vec3<T>) -> Vec3<T> {
+        let u = Vec3(self.0, self.1, self.2);
+        let t = u.cross(v).mul_scalar(T::one() + T::one());
+        v + t.mul_scalar(self.3) + u.cross(t)
+    }
+
+    /// slerp returns the spherical linear interpolation between the unit
+    /// quaternions, along the shortest arc: the rotation a fraction t of the
+    /// way from self to other at a constant angular velocity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Quat, Vec3};
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let a = Quat::identity();
+    /// let b = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), Deg(90.0));
+    /// let c = Quat::from_axis_angle(Vec3(0.0, 1.0, 0.0), Deg(30.0));
+    /// assert!(a.slerp(b, 1.0 / 3.0).almost_equal(c, 1e-6));
+    /// ```
+    pub fn slerp(self, other: Self, t: T) -> Self {
+        let mut d = self.dot(other);
+        let mut other = other;
+        if d < T::zero() {
+            other = -other;
+            d = -d;
+        }
+        let one = T::one();
+        let (a, b) = if d > T::from(0.9995).unwrap() {
+            // Nearly equal, where the angle is imprecise: interpolate
+            // linearly and normalize.
+            (one - t, t)
+        } else {
+            let theta = d.acos();
+            let s = theta.sin();
+            (((one - t) * theta).sin() / s, (t * theta).sin() / s)
+        };
+        Quat(self.0 * a + other.0 * b,
+             self.1 * a + other.1 * b,
+             self.2 * a + other.2 * b,
+             self.3 * a + other.3 * b)
+            .normalize()
+            .unwrap_or(self)
+    }
+
+    /// almost_equal tells if each component of the quaternion is equal to
+    /// that of the other given an absolute tolerance (see
+    /// `Float::almost_equal`). Note that q and -q are the same rotation,
+    /// but aren't equal.
+    pub fn almost_equal<N: Float>(self, other: Self, abs_tol: N) -> bool {
+        Vec4(self.0, self.1, self.2, self.3).almost_equal(Vec4(other.0, other.1, other.2, other.3), abs_tol)
+    }
+}
+
+impl<T: Float> Neg for Quat<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Quat(-self.0, -self.1, -self.2, -self.3)
+    }
+}
+
+impl<T: Float> Mul for Quat<T> {
+    type Output = Self;
+
+    /// mul returns the Hamilton product of the quaternions, which rotates by
+    /// rhs and then by self.
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self, rhs);
+        Quat(a.3 * b.0 + a.0 * b.3 + a.1 * b.2 - a.2 * b.1,
+             a.3 * b.1 - a.0 * b.2 + a.1 * b.3 + a.2 * b.0,
+             a.3 * b.2 + a.0 * b.1 - a.1 * b.0 + a.2 * b.3,
+             a.3 * b.3 - a.0 * b.0 - a.1 * b.1 - a.2 * b.2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::unit::Deg;
+    use super::super::{Mat4, Vec3};
+    use super::Quat;
+
+    #[test]
+    fn rotations() {
+        // Each branch of from_mat4: small angles, and half turns about each
+        // axis, where the trace is negative.
+        let axes = [Vec3(1.0, 0.2, 0.1), Vec3(0.1, 1.0, -0.2), Vec3(-0.2, 0.1, 1.0), Vec3(1.0, 1.0, 1.0)];
+        for &axis in &axes {
+            for &angle in &[10.0, 90.0, 170.0, 179.0] {
+                let q = Quat::from_axis_angle(axis, Deg(angle));
+                let m = q.to_mat4();
+                assert!(Quat::from_mat4(&m).almost_equal(q, 1e-5), "{:?} {}", axis, angle);
+                let v = Vec3(0.3, -0.7, 1.1);
+                assert!(q.rotate(v).almost_equal(m.transform_vector(v), 1e-5));
+                assert!((q.conjugate() * q).almost_equal(Quat::identity(), 1e-6));
+            }
+        }
+
+        // Products compose like matrices.
+        let (a, b) = (Quat::from_axis_angle(axes[0], Deg(40.0)), Quat::from_axis_angle(axes[1], Deg(-75.0)));
+        assert!((a * b).to_mat4().almost_equal(&(a.to_mat4() * b.to_mat4()), 1e-5));
+        assert!(Mat4::<f64>::identity().almost_equal(&Quat::identity().to_mat4(), 0.0));
+
+        // Slerp takes the shortest arc.
+        let c = Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), Deg(350.0));
+        let half = Quat::identity().slerp(c, 0.5);
+        assert!(half.almost_equal(Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), Deg(-5.0)), 1e-6), "{:?}", half);
+    }
+}
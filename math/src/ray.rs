@@ -0,0 +1,78 @@
+use super::float::Float;
+use super::{Vec3, Aabb};
+
+/// Ray is a half-line starting at an origin and extending in a direction.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Ray, Vec3};
+///
+/// let r = Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 0.0, 2.0));
+/// assert_eq!(r.at(1.5), Vec3(0.0, 0.0, 3.0));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray<T> {
+    /// The origin of the ray.
+    pub origin: Vec3<T>,
+
+    /// The direction of the ray, which need not be normalized. Distances
+    /// along the ray are measured in multiples of its length.
+    pub direction: Vec3<T>,
+}
+
+impl<T: Float> Ray<T> {
+    /// new returns a new ray with the given origin and direction.
+    pub fn new(origin: Vec3<T>, direction: Vec3<T>) -> Self {
+        Ray {
+            origin,
+            direction,
+        }
+    }
+
+    /// at returns the point at the parametric distance t along the ray.
+    pub fn at(&self, t: T) -> Vec3<T> {
+        self.origin + self.direction.mul_scalar(t)
+    }
+
+    /// intersect_aabb returns the parametric distance along the ray at which
+    /// it enters the box, or zero if the origin lies inside the box. None is
+    /// returned if the ray misses the box or enters it beyond t_max.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Aabb, Ray, Vec3};
+    ///
+    /// let b = Aabb::new(Vec3(1.0, -1.0, -1.0), Vec3(3.0, 1.0, 1.0));
+    /// let r = Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 0.0, 0.0));
+    /// assert_eq!(r.intersect_aabb(&b, 10.0), Some(1.0));
+    /// assert_eq!(r.intersect_aabb(&b, 0.5), None);
+    ///
+    /// let up = Ray::new(Vec3(0.0, 0.0, 0.0), Vec3(0.0, 1.0, 0.0));
+    /// assert_eq!(up.intersect_aabb(&b, 10.0), None);
+    /// ```
+    pub fn intersect_aabb(&self, b: &Aabb<T>, t_max: T) -> Option<T> {
+        let (mut near, mut far) = (T::zero(), t_max);
+        for &(o, d, lo, hi) in &[(self.origin.0, self.direction.0, b.min.0, b.max.0),
+                                 (self.origin.1, self.direction.1, b.min.1, b.max.1),
+                                 (self.origin.2, self.direction.2, b.min.2, b.max.2)] {
+            if d == T::zero() {
+                // Parallel to the slab, either always or never within it.
+                if o < lo || o > hi {
+                    return None;
+                }
+                continue;
+            }
+            let inv = T::one() / d;
+            let (t0, t1) = ((lo - o) * inv, (hi - o) * inv);
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map;
+use std::ops::Index;
+use num::NumCast;
+use num::{Float, Zero};
+use super::{Point, FloatPoint, Bounds, ItemId, SpatialQuery, Items, floor_div};
+
+/// HashGrid is an unbounded uniform grid of square (or cubic) cells, storing
+/// items with axis-aligned bounds in every cell they overlap. Cells are
+/// identified by integer vectors (`Vec2<i32>` or `Vec3<i32>`) and stored in
+/// a hash map, so only occupied cells use memory.
+///
+/// Grids work best when items are of about the size of a cell, or smaller.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::spatial::{SpatialQuery, Bounds, HashGrid};
+///
+/// let mut grid = HashGrid::new(10.0);
+/// let a = grid.insert(Bounds::new(Vec2(5.0, 5.0), Vec2(15.0, 8.0)), "a");
+/// let b = grid.insert(Bounds::point(Vec2(-3.0, 4.0)), "b");
+///
+/// // The cells themselves can be inspected.
+/// assert_eq!(grid.cell_of(Vec2(-3.0, 4.0)), Vec2(-1, 0));
+/// assert_eq!(grid.cell(Vec2(1, 0)), &[a]);
+/// assert_eq!(grid.cells().count(), 3);
+///
+/// assert_eq!(grid.query_box(&Bounds::new(Vec2(-5.0, 0.0), Vec2(0.0, 5.0))), vec![b]);
+/// let near = grid.nearest(Vec2(0.0, 0.0), 2);
+/// assert_eq!(grid[near[0].0], "b");
+/// assert_eq!(near[0].1, 25.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HashGrid<P: FloatPoint, I> {
+    cell_size: P::Scalar,
+    cells: HashMap<P::Cell, Vec<ItemId>>,
+    items: Items<P, I, ()>,
+}
+
+impl<P: FloatPoint, I> HashGrid<P, I> {
+    /// new returns a new empty grid with cells of the given size.
+    ///
+    /// # Panics
+    ///
+    /// new panics if the cell size isn't positive.
+    pub fn new(cell_size: P::Scalar) -> Self {
+        assert!(cell_size > P::Scalar::zero(), "HashGrid::new: cell size must be positive");
+        HashGrid {
+            cell_size,
+            cells: HashMap::new(),
+            items: Items::new(),
+        }
+    }
+
+    /// cell_size returns the size of the cells of the grid.
+    pub fn cell_size(&self) -> P::Scalar {
+        self.cell_size
+    }
+
+    /// cell_of returns the cell containing the point.
+    pub fn cell_of(&self, p: P) -> P::Cell {
+        P::cell_from_axes(|i| floor_div(p.axis(i), self.cell_size))
+    }
+
+    /// cell_bounds returns the bounds of the cell.
+    pub fn cell_bounds(&self, cell: P::Cell) -> Bounds<P> {
+        let min = P::from_axes(|i| <P::Scalar as NumCast>::from(P::cell_axis(&cell, i)).unwrap() * self.cell_size);
+        Bounds {
+            min,
+            max: P::from_axes(|i| min.axis(i) + self.cell_size),
+        }
+    }
+
+    /// cell returns the items overlapping the cell, which is empty for
+    /// unoccupied cells.
+    pub fn cell(&self, cell: P::Cell) -> &[ItemId] {
+        self.cells.get(&cell).map_or(&[], |c| &c[..])
+    }
+
+    /// cells returns an iterator over the occupied cells of the grid and
+    /// their items, in no particular order.
+    pub fn cells(&self) -> Cells<'_, P> {
+        Cells { iter: self.cells.iter() }
+    }
+
+    /// len returns the number of items in the grid.
+    pub fn len(&self) -> usize {
+        self.items.len
+    }
+
+    /// is_empty tells if the grid holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.len == 0
+    }
+
+    /// get returns a reference to the item, or None if it was removed.
+    pub fn get(&self, id: ItemId) -> Option<&I> {
+        self.items.get(id).map(|e| &e.item)
+    }
+
+    /// get_mut returns a mutable reference to the item, or None if it was
+    /// removed.
+    pub fn get_mut(&mut self, id: ItemId) -> Option<&mut I> {
+        self.items.get_mut(id).map(|e| &mut e.item)
+    }
+
+    /// iter returns an iterator over the items in the grid, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &I)> {
+        self.items.iter().map(|(id, e)| (id, &e.item))
+    }
+
+    /// insert adds an item with the given bounds to the grid, returning its
+    /// identifier.
+    pub fn insert(&mut self, bounds: Bounds<P>, item: I) -> ItemId {
+        let id = self.items.insert(bounds, item, ());
+        self.attach(&bounds, id);
+        id
+    }
+
+    /// remove removes the item from the grid, returning it, or None if it was
+    /// already removed.
+    pub fn remove(&mut self, id: ItemId) -> Option<I> {
+        let entry = self.items.remove(id)?;
+        self.detach(&entry.bounds, id);
+        Some(entry.item)
+    }
+
+    /// update changes the bounds of the item, moving it to other cells if
+    /// needed.
+    ///
+    /// # Panics
+    ///
+    /// update panics if the item was removed.
+    pub fn update(&mut self, id: ItemId, bounds: Bounds<P>) {
+        let old = self.items.get(id).expect("HashGrid::update: item was removed").bounds;
+        self.detach(&old, id);
+        self.items.get_mut(id).unwrap().bounds = bounds;
+        self.attach(&bounds, id);
+    }
+
+    fn attach(&mut self, b: &Bounds<P>, id: ItemId) {
+        let (lo, hi) = self.cell_range(b);
+        for cell in cells_between::<P>(&lo, &hi) {
+            self.cells.entry(cell).or_default().push(id);
+        }
+    }
+
+    fn detach(&mut self, b: &Bounds<P>, id: ItemId) {
+        let (lo, hi) = self.cell_range(b);
+        for cell in cells_between::<P>(&lo, &hi) {
+            let empty = {
+                let ids = self.cells.get_mut(&cell).unwrap();
+                let i = ids.iter().position(|&x| x == id).unwrap();
+                ids.swap_remove(i);
+                ids.is_empty()
+            };
+            if empty {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    // cell_range returns the first and last cells overlapped by the box.
+    fn cell_range(&self, b: &Bounds<P>) -> (P::Cell, P::Cell) {
+        (self.cell_of(b.min), self.cell_of(b.max))
+    }
+}
+
+// count_cells returns the number of cells from lo to hi, inclusive.
+fn count_cells<P: Point>(lo: &P::Cell, hi: &P::Cell) -> u64 {
+    (0..P::DIM).fold(1, |n: u64, i| {
+        n.saturating_mul((P::cell_axis(hi, i) as i64 - P::cell_axis(lo, i) as i64 + 1) as u64)
+    })
+}
+
+// cells_between returns the cells from lo to hi, inclusive.
+fn cells_between<P: Point>(lo: &P::Cell, hi: &P::Cell) -> Vec<P::Cell> {
+    let mut cur: Vec<i32> = (0..P::DIM).map(|i| P::cell_axis(lo, i)).collect();
+    let mut cells = Vec::new();
+    loop {
+        cells.push(P::cell_from_axes(|i| cur[i]));
+
+        // Advance to the next cell, odometer style.
+        let mut i = 0;
+        loop {
+            if i == P::DIM {
+                return cells;
+            }
+            if cur[i] < P::cell_axis(hi, i) {
+                cur[i] += 1;
+                break;
+            }
+            cur[i] = P::cell_axis(lo, i);
+            i += 1;
+        }
+    }
+}
+
+impl<P: FloatPoint, I> SpatialQuery<P> for HashGrid<P, I> {
+    fn query_box(&self, b: &Bounds<P>) -> Vec<ItemId> {
+        // Large boxes are cheaper to search by visiting the occupied cells
+        // than every cell they cover.
+        let (lo, hi) = self.cell_range(b);
+        let cells: Vec<&Vec<ItemId>> = if count_cells::<P>(&lo, &hi) > self.cells.len() as u64 {
+            self.cells.values().collect()
+        } else {
+            cells_between::<P>(&lo, &hi).iter().filter_map(|c| self.cells.get(c)).collect()
+        };
+        let mut out: Vec<ItemId> = cells.into_iter()
+            .flat_map(|ids| ids.iter().cloned())
+            .filter(|&id| self.items.get(id).unwrap().bounds.intersects(b))
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    fn nearest(&self, p: P, k: usize) -> Vec<(ItemId, P::Scalar)> {
+        let mut found: Vec<(ItemId, P::Scalar)> = Vec::new();
+        let mut seen = HashSet::new();
+        let origin = self.cell_of(p);
+        let mut ring: i32 = 0;
+
+        // Search rings of cells of increasing Chebyshev distance from the
+        // cell of p, until k items are known to be closer than any item not
+        // yet seen.
+        while k > 0 && seen.len() < self.items.len {
+            let lo = P::cell_from_axes(|i| P::cell_axis(&origin, i).saturating_sub(ring));
+            let hi = P::cell_from_axes(|i| P::cell_axis(&origin, i).saturating_add(ring));
+            if count_cells::<P>(&lo, &hi) > self.cells.len() as u64 {
+                // The ring has outgrown the occupied cells, consider every
+                // remaining item.
+                for (id, e) in self.items.iter() {
+                    if seen.insert(id) {
+                        found.push((id, e.bounds.distance_sq(p)));
+                    }
+                }
+                break;
+            }
+            for cell in cells_between::<P>(&lo, &hi) {
+                let on_ring = (0..P::DIM)
+                    .any(|i| (P::cell_axis(&cell, i) - P::cell_axis(&origin, i)).abs() == ring);
+                if !on_ring {
+                    continue;
+                }
+                for &id in self.cell(cell) {
+                    if seen.insert(id) {
+                        found.push((id, self.items.get(id).unwrap().bounds.distance_sq(p)));
+                    }
+                }
+            }
+
+            // Items not yet seen lie outside of the block of searched cells.
+            if found.len() >= k {
+                let block = Bounds {
+                    min: self.cell_bounds(lo).min,
+                    max: self.cell_bounds(hi).max,
+                };
+                let reach = (0..P::DIM).fold(P::Scalar::infinity(), |d, i| {
+                    d.min(p.axis(i) - block.min.axis(i)).min(block.max.axis(i) - p.axis(i))
+                });
+                found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(::std::cmp::Ordering::Equal));
+                if found[k - 1].1 <= reach * reach {
+                    break;
+                }
+            }
+            ring += 1;
+        }
+        found.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(::std::cmp::Ordering::Equal));
+        found.truncate(k);
+        found
+    }
+
+    fn item_bounds(&self, id: ItemId) -> Option<Bounds<P>> {
+        self.items.get(id).map(|e| e.bounds)
+    }
+}
+
+impl<P: FloatPoint, I> Index<ItemId> for HashGrid<P, I> {
+    type Output = I;
+
+    fn index(&self, id: ItemId) -> &I {
+        self.get(id).expect("HashGrid::index: item was removed")
+    }
+}
+
+/// Cells is an iterator over the occupied cells of a `HashGrid` and their
+/// items.
+pub struct Cells<'a, P: Point + 'a> {
+    iter: hash_map::Iter<'a, P::Cell, Vec<ItemId>>,
+}
+
+impl<'a, P: Point> Iterator for Cells<'a, P> {
+    type Item = (P::Cell, &'a [ItemId]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(&c, ids)| (c, &ids[..]))
+    }
+}
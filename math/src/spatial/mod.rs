@@ -0,0 +1,458 @@
+//! Spatial partitioning structures for two- and three-dimensional items.
+//!
+//! Each structure stores items along with their axis-aligned bounds, and
+//! implements the `SpatialQuery` trait for box, radius and k-nearest neighbor
+//! searches:
+//!
+//! - `Quadtree` (a loose quadtree over `Vec2`) and `Octree` (a sparse octree
+//!   over `Vec3`), both backed by the generic `Tree`.
+//! - `HashGrid`, a uniform grid of cells keyed by `Vec2<i32>` or `Vec3<i32>`,
+//!   whose storage is proportional to the number of occupied cells.
+//!
+//! The structures are generic over the `FloatPoint` trait, which is
+//! implemented by the `Point`s of floating-point components: `Vec2` and `Vec3`
+//! of floats. Items are identified by the same `ItemId` as those of a `Bvh`,
+//! and nearest neighbor searches report squared distances, as `Bvh` and
+//! `KdTree` do.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+use num::NumCast;
+use num::{Float, Num, Zero, One};
+use super::{Vec2, Vec3, Aabb};
+
+mod tree;
+mod grid;
+
+pub use super::bvh::ItemId;
+pub use self::tree::{Tree, Quadtree, Octree};
+pub use self::grid::{HashGrid, Cells};
+
+/// Point is a position in two- or three-dimensional space, whose components
+/// are accessed by axis index.
+pub trait Point: Copy + PartialEq + Debug {
+    /// Scalar is the component type.
+    type Scalar: Num + PartialOrd + Copy + Debug;
+
+    /// Cell is the integer vector type identifying grid cells in this space.
+    type Cell: Copy + Eq + Hash + Debug;
+
+    /// DIM is the number of dimensions (and components).
+    const DIM: usize;
+
+    /// axis returns the component along the given axis.
+    fn axis(&self, i: usize) -> Self::Scalar;
+
+    /// from_axes returns the point whose components are given by f for each
+    /// axis.
+    fn from_axes<F: FnMut(usize) -> Self::Scalar>(f: F) -> Self;
+
+    /// cell_axis returns the component of the cell along the given axis.
+    fn cell_axis(cell: &Self::Cell, i: usize) -> i32;
+
+    /// cell_from_axes returns the cell whose components are given by f for
+    /// each axis.
+    fn cell_from_axes<F: FnMut(usize) -> i32>(f: F) -> Self::Cell;
+}
+
+impl<T: Num + PartialOrd + Copy + Debug> Point for Vec2<T> {
+    type Scalar = T;
+    type Cell = Vec2<i32>;
+    const DIM: usize = 2;
+
+    fn axis(&self, i: usize) -> T {
+        if i == 0 { self.0 } else { self.1 }
+    }
+
+    fn from_axes<F: FnMut(usize) -> T>(mut f: F) -> Self {
+        Vec2(f(0), f(1))
+    }
+
+    fn cell_axis(cell: &Vec2<i32>, i: usize) -> i32 {
+        if i == 0 { cell.0 } else { cell.1 }
+    }
+
+    fn cell_from_axes<F: FnMut(usize) -> i32>(mut f: F) -> Vec2<i32> {
+        Vec2(f(0), f(1))
+    }
+}
+
+impl<T: Num + PartialOrd + Copy + Debug> Point for Vec3<T> {
+    type Scalar = T;
+    type Cell = Vec3<i32>;
+    const DIM: usize = 3;
+
+    fn axis(&self, i: usize) -> T {
+        match i {
+            0 => self.0,
+            1 => self.1,
+            _ => self.2,
+        }
+    }
+
+    fn from_axes<F: FnMut(usize) -> T>(mut f: F) -> Self {
+        Vec3(f(0), f(1), f(2))
+    }
+
+    fn cell_axis(cell: &Vec3<i32>, i: usize) -> i32 {
+        match i {
+            0 => cell.0,
+            1 => cell.1,
+            _ => cell.2,
+        }
+    }
+
+    fn cell_from_axes<F: FnMut(usize) -> i32>(mut f: F) -> Vec3<i32> {
+        Vec3(f(0), f(1), f(2))
+    }
+}
+
+/// FloatPoint is a `Point` with floating-point components, as the structures
+/// of this module require. It's implemented for every such point.
+pub trait FloatPoint: Point<Scalar = <Self as FloatPoint>::Float> {
+    /// Float is the component type, the same as `Point::Scalar`.
+    type Float: super::Float;
+}
+
+impl<P: Point> FloatPoint for P
+    where P::Scalar: super::Float
+{
+    type Float = P::Scalar;
+}
+
+/// Bounds is an axis-aligned box in the space of the point type P.
+///
+/// `Bounds<Vec3<T>>` is the same box as `Aabb<T>`, and they convert into
+/// each other with `From`. Aabb isn't an alias of it because it also works
+/// with components which aren't floats, and its `contains` takes a point
+/// rather than a box.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Aabb, Vec2, Vec3};
+/// use fiz_math::spatial::Bounds;
+///
+/// let b = Bounds::new(Vec2(0.0, 0.0), Vec2(2.0, 1.0));
+/// assert!(b.contains(&Bounds::point(Vec2(1.0, 1.0))));
+/// assert_eq!(b.distance_sq(Vec2(4.0, 2.0)), 5.0);
+///
+/// let aabb = Aabb::new(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 2.0, 3.0));
+/// let b = Bounds::from(aabb);
+/// assert_eq!(b.distance_sq(Vec3(0.0, 0.0, 5.0)), aabb.distance_sq(Vec3(0.0, 0.0, 5.0)));
+/// assert_eq!(Aabb::from(b), aabb);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds<P> {
+    /// The minimum corner of the box.
+    pub min: P,
+
+    /// The maximum corner of the box.
+    pub max: P,
+}
+
+impl<P: FloatPoint> Bounds<P> {
+    /// new returns a new box with the given corners, which may be given in
+    /// any order.
+    pub fn new(a: P, b: P) -> Self {
+        Bounds {
+            min: P::from_axes(|i| a.axis(i).min(b.axis(i))),
+            max: P::from_axes(|i| a.axis(i).max(b.axis(i))),
+        }
+    }
+
+    /// point returns an empty box at the given point.
+    pub fn point(p: P) -> Self {
+        Bounds { min: p, max: p }
+    }
+
+    /// around returns the box of the given half size centered at p.
+    pub fn around(p: P, half_size: P::Scalar) -> Self {
+        Bounds {
+            min: P::from_axes(|i| p.axis(i) - half_size),
+            max: P::from_axes(|i| p.axis(i) + half_size),
+        }
+    }
+
+    /// intersects tells if the boxes overlap (or touch).
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..P::DIM).all(|i| self.min.axis(i) <= other.max.axis(i) && other.min.axis(i) <= self.max.axis(i))
+    }
+
+    /// contains tells if the other box lies entirely within this one.
+    pub fn contains(&self, other: &Self) -> bool {
+        (0..P::DIM).all(|i| self.min.axis(i) <= other.min.axis(i) && other.max.axis(i) <= self.max.axis(i))
+    }
+
+    /// center returns the center of the box.
+    pub fn center(&self) -> P {
+        let two = P::Scalar::one() + P::Scalar::one();
+        P::from_axes(|i| (self.min.axis(i) + self.max.axis(i)) / two)
+    }
+
+    /// distance_sq returns the squared distance from p to the box, which is
+    /// zero if p lies inside it.
+    pub fn distance_sq(&self, p: P) -> P::Scalar {
+        (0..P::DIM).fold(P::Scalar::zero(), |sum, i| {
+            let v = p.axis(i);
+            let d = if v < self.min.axis(i) {
+                self.min.axis(i) - v
+            } else if v > self.max.axis(i) {
+                v - self.max.axis(i)
+            } else {
+                P::Scalar::zero()
+            };
+            sum + d * d
+        })
+    }
+}
+
+impl<T> From<Aabb<T>> for Bounds<Vec3<T>> {
+    fn from(b: Aabb<T>) -> Self {
+        Bounds { min: b.min, max: b.max }
+    }
+}
+
+impl<T> From<Bounds<Vec3<T>>> for Aabb<T> {
+    fn from(b: Bounds<Vec3<T>>) -> Self {
+        Aabb { min: b.min, max: b.max }
+    }
+}
+
+/// SpatialQuery is the query interface shared by the structures of this
+/// module. An item matches a query if its bounds do; distances are measured
+/// to the nearest point of an item's bounds.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Vec2, Vec3};
+/// use fiz_math::spatial::{SpatialQuery, FloatPoint, Bounds, Quadtree, HashGrid};
+///
+/// // Generic code works with any of the structures.
+/// fn count_near<P: FloatPoint, S: SpatialQuery<P>>(s: &S, p: P, r: P::Scalar) -> usize {
+///     s.query_radius(p, r).len()
+/// }
+///
+/// let mut tree = Quadtree::loose(Bounds::new(Vec2(0.0, 0.0), Vec2(64.0, 64.0)), 6);
+/// let mut grid = HashGrid::new(4.0);
+/// for i in 0..10 {
+///     let p = Vec2(i as f64, 1.0);
+///     tree.insert(Bounds::point(p), i);
+///     grid.insert(Bounds::point(Vec3(p.0, p.1, 0.0)), i);
+/// }
+/// assert_eq!(count_near(&tree, Vec2(0.0, 0.0), 2.0), 2);
+/// assert_eq!(count_near(&grid, Vec3(0.0, 0.0, 0.0), 2.0), 2);
+/// ```
+pub trait SpatialQuery<P: FloatPoint> {
+    /// query_box returns the items whose bounds overlap the box.
+    fn query_box(&self, b: &Bounds<P>) -> Vec<ItemId>;
+
+    /// query_radius returns the items whose bounds are within the given
+    /// distance of the point.
+    fn query_radius(&self, center: P, radius: P::Scalar) -> Vec<ItemId> {
+        let r2 = radius * radius;
+        self.query_box(&Bounds::around(center, radius))
+            .into_iter()
+            .filter(|&id| self.item_bounds(id).is_some_and(|b| b.distance_sq(center) <= r2))
+            .collect()
+    }
+
+    /// nearest returns (up to) the k items nearest to the point, ordered by
+    /// increasing distance, along with their squared distances, as
+    /// `Bvh::nearest` and `KdTree::nearest` return.
+    fn nearest(&self, p: P, k: usize) -> Vec<(ItemId, P::Scalar)>;
+
+    /// item_bounds returns the bounds of the item, or None if it was removed.
+    fn item_bounds(&self, id: ItemId) -> Option<Bounds<P>>;
+}
+
+// Items is the storage for items shared by the structures of this module. L
+// is whatever the structure needs to remember to locate the item.
+#[derive(Clone, Debug)]
+struct Items<P, I, L> {
+    entries: Vec<Option<Entry<P, I, L>>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<P, I, L> {
+    bounds: Bounds<P>,
+    item: I,
+    location: L,
+}
+
+impl<P, I, L> Items<P, I, L> {
+    fn new() -> Self {
+        Items {
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn insert(&mut self, bounds: Bounds<P>, item: I, location: L) -> ItemId {
+        let entry = Some(Entry {
+            bounds,
+            item,
+            location,
+        });
+        self.len += 1;
+        match self.free.pop() {
+            Some(i) => {
+                self.entries[i] = entry;
+                ItemId(i)
+            }
+            None => {
+                self.entries.push(entry);
+                ItemId(self.entries.len() - 1)
+            }
+        }
+    }
+
+    fn remove(&mut self, id: ItemId) -> Option<Entry<P, I, L>> {
+        let e = self.entries.get_mut(id.0)?.take()?;
+        self.free.push(id.0);
+        self.len -= 1;
+        Some(e)
+    }
+
+    fn get(&self, id: ItemId) -> Option<&Entry<P, I, L>> {
+        self.entries.get(id.0).and_then(|e| e.as_ref())
+    }
+
+    fn get_mut(&mut self, id: ItemId) -> Option<&mut Entry<P, I, L>> {
+        self.entries.get_mut(id.0).and_then(|e| e.as_mut())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (ItemId, &Entry<P, I, L>)> {
+        self.entries.iter().enumerate().filter_map(|(i, e)| e.as_ref().map(|e| (ItemId(i), e)))
+    }
+}
+
+// Candidate is an entry of the priority queue used for nearest neighbor
+// searches, ordered such that the smallest distance is greatest. K is either
+// a node to expand or an item to report.
+struct Candidate<T, K> {
+    distance: T,
+    key: K,
+}
+
+impl<T: Float, K> PartialEq for Candidate<T, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl<T: Float, K> Eq for Candidate<T, K> {}
+
+impl<T: Float, K> PartialOrd for Candidate<T, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<::std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Float, K> Ord for Candidate<T, K> {
+    fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+        other.distance.partial_cmp(&self.distance).unwrap_or(::std::cmp::Ordering::Equal)
+    }
+}
+
+// floor_div returns the index of the cell of the given size containing v.
+fn floor_div<T: Float>(v: T, size: T) -> i32 {
+    <i32 as NumCast>::from((v / size).floor()).unwrap_or(if v < T::zero() { i32::MIN } else { i32::MAX })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SpatialQuery, Bounds, ItemId, Point, Quadtree, Octree, HashGrid};
+    use {Vec2, Vec3};
+    use testing::lcg;
+
+    // check compares the results of every query against a brute force search
+    // of the given live items.
+    fn check<P: Point<Scalar = f64>, S: SpatialQuery<P>>(s: &S,
+                                                          live: &[(ItemId, Bounds<P>)],
+                                                          p: P,
+                                                          r: f64,
+                                                          k: usize) {
+        let q = Bounds::around(p, r);
+        let mut got = s.query_box(&q);
+        got.sort();
+        let mut want: Vec<ItemId> = live.iter().filter(|l| l.1.intersects(&q)).map(|l| l.0).collect();
+        want.sort();
+        assert_eq!(got, want);
+
+        let mut got = s.query_radius(p, r);
+        got.sort();
+        let mut want: Vec<ItemId> = live.iter().filter(|l| l.1.distance_sq(p) <= r * r).map(|l| l.0).collect();
+        want.sort();
+        assert_eq!(got, want);
+
+        let got: Vec<f64> = s.nearest(p, k).into_iter().map(|n| n.1).collect();
+        let mut want: Vec<f64> = live.iter().map(|l| l.1.distance_sq(p)).collect();
+        want.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        want.truncate(k);
+        assert_eq!(got, want);
+    }
+
+    fn random_bounds<P: Point<Scalar = f64>>(s: &mut u64, world: f64, size: f64) -> Bounds<P> {
+        let min = P::from_axes(|_| lcg(s) * world - world / 4.0);
+        let max = P::from_axes(|i| min.axis(i) + lcg(s) * lcg(s) * size);
+        Bounds::new(min, max)
+    }
+
+    #[test]
+    fn against_brute_force() {
+        let mut s = 11;
+        let world = Bounds::new(Vec2(0.0, 0.0), Vec2(100.0, 100.0));
+        let mut quad = Quadtree::loose(world, 8);
+        let mut grid2 = HashGrid::new(5.0);
+        let mut oct = Octree::new(Bounds::new(Vec3(0.0, 0.0, 0.0), Vec3(100.0, 100.0, 100.0)), 6);
+        let mut grid3 = HashGrid::new(8.0);
+        let (mut live2, mut live3) = (Vec::new(), Vec::new());
+
+        // Items partly lie outside of the trees' bounds.
+        for i in 0..400 {
+            let b: Bounds<Vec2<f64>> = random_bounds(&mut s, 120.0, 20.0);
+            let id = quad.insert(b, i);
+            assert_eq!(grid2.insert(b, i), id);
+            live2.push((id, b));
+            let b: Bounds<Vec3<f64>> = random_bounds(&mut s, 120.0, 20.0);
+            let id = oct.insert(b, i);
+            assert_eq!(grid3.insert(b, i), id);
+            live3.push((id, b));
+        }
+        for i in (0..400).filter(|i| i % 4 == 1).rev() {
+            assert_eq!(quad.remove(live2[i].0), Some(i));
+            assert_eq!(grid2.remove(live2[i].0), Some(i));
+            live2.remove(i);
+            assert_eq!(oct.remove(live3[i].0), Some(i));
+            assert_eq!(grid3.remove(live3[i].0), Some(i));
+            live3.remove(i);
+        }
+        for i in 0..60 {
+            let b: Bounds<Vec2<f64>> = random_bounds(&mut s, 120.0, 20.0);
+            quad.update(live2[i * 3].0, b);
+            grid2.update(live2[i * 3].0, b);
+            live2[i * 3].1 = b;
+            let b: Bounds<Vec3<f64>> = random_bounds(&mut s, 120.0, 20.0);
+            oct.update(live3[i * 3].0, b);
+            grid3.update(live3[i * 3].0, b);
+            live3[i * 3].1 = b;
+        }
+
+        for _ in 0..50 {
+            let p = Vec2(lcg(&mut s) * 140.0 - 20.0, lcg(&mut s) * 140.0 - 20.0);
+            let (r, k) = (lcg(&mut s) * 30.0, (lcg(&mut s) * 20.0) as usize);
+            check(&quad, &live2, p, r, k);
+            check(&grid2, &live2, p, r, k);
+            let p = Vec3(lcg(&mut s) * 140.0 - 20.0, lcg(&mut s) * 140.0 - 20.0, lcg(&mut s) * 140.0 - 20.0);
+            check(&oct, &live3, p, r, k);
+            check(&grid3, &live3, p, r, k);
+        }
+        check(&grid3, &live3, Vec3(1e4, 1e4, 1e4), 1.0, 500);
+        check(&oct, &live3, Vec3(1e4, 1e4, 1e4), 1.0, 500);
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Index;
+use num::NumCast;
+use num::{Float, Zero, One};
+use super::super::{Vec2, Vec3};
+use super::{Point, FloatPoint, Bounds, ItemId, SpatialQuery, Items, Candidate, floor_div};
+
+// The key of a node: its depth and its cell among the nodes of that depth.
+type Key<P> = (u32, <P as Point>::Cell);
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    // The items stored at this node.
+    items: Vec<ItemId>,
+
+    // The number of items stored at this node and below it.
+    count: usize,
+}
+
+// The entries of the nearest neighbor priority queue.
+enum Visit<P: FloatPoint> {
+    Node(Key<P>),
+    Item(ItemId),
+}
+
+/// Tree is a sparse region tree over items with axis-aligned bounds: a
+/// quadtree in two dimensions and an octree in three. Each level of the tree
+/// splits the cells of the previous level in half along every axis, and
+/// only nodes holding items (or with descendants holding items) are
+/// allocated.
+///
+/// Each item is stored at the deepest node whose box contains it. In a loose
+/// tree, node boxes are enlarged to twice their size, so that small items
+/// straddling a split still sink to a node of about their size rather than
+/// remaining near the root.
+///
+/// Items may lie partly or entirely outside the bounds of the tree, in which
+/// case they are stored at the root.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::Vec2;
+/// use fiz_math::spatial::{SpatialQuery, Bounds, Quadtree};
+///
+/// let mut tree = Quadtree::loose(Bounds::new(Vec2(0.0, 0.0), Vec2(100.0, 100.0)), 5);
+/// let a = tree.insert(Bounds::around(Vec2(10.0, 10.0), 1.0), "a");
+/// let b = tree.insert(Bounds::around(Vec2(50.0, 50.0), 1.0), "b");
+/// assert_eq!(tree.query_radius(Vec2(12.0, 10.0), 2.0), vec![a]);
+///
+/// tree.update(a, Bounds::around(Vec2(90.0, 90.0), 1.0));
+/// let near = tree.nearest(Vec2(60.0, 50.0), 1);
+/// assert_eq!(tree[near[0].0], "b");
+/// assert_eq!(near[0].1, 81.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tree<P: FloatPoint, I> {
+    bounds: Bounds<P>,
+    looseness: P::Scalar,
+    max_depth: u32,
+    nodes: HashMap<Key<P>, Node>,
+    items: Items<P, I, Key<P>>,
+}
+
+/// Quadtree is a tree over two-dimensional items.
+pub type Quadtree<T, I> = Tree<Vec2<T>, I>;
+
+/// Octree is a tree over three-dimensional items.
+pub type Octree<T, I> = Tree<Vec3<T>, I>;
+
+impl<P: FloatPoint, I> Tree<P, I> {
+    /// new returns a new empty tree covering the given bounds, whose nodes
+    /// are nested at most max_depth levels below the root.
+    ///
+    /// # Panics
+    ///
+    /// new panics if max_depth is greater than 30.
+    pub fn new(bounds: Bounds<P>, max_depth: u32) -> Self {
+        Tree::with_looseness(bounds, max_depth, P::Scalar::one())
+    }
+
+    /// loose returns a new empty loose tree, whose node boxes are twice the
+    /// size of their cells, covering the given bounds with nodes nested at
+    /// most max_depth levels below the root.
+    ///
+    /// # Panics
+    ///
+    /// loose panics if max_depth is greater than 30.
+    pub fn loose(bounds: Bounds<P>, max_depth: u32) -> Self {
+        Tree::with_looseness(bounds, max_depth, P::Scalar::one() + P::Scalar::one())
+    }
+
+    fn with_looseness(bounds: Bounds<P>, max_depth: u32, looseness: P::Scalar) -> Self {
+        assert!(max_depth <= 30, "Tree::new: max_depth must be at most 30");
+        Tree {
+            bounds,
+            looseness,
+            max_depth,
+            nodes: HashMap::new(),
+            items: Items::new(),
+        }
+    }
+
+    /// bounds returns the bounds covered by the tree.
+    pub fn bounds(&self) -> Bounds<P> {
+        self.bounds
+    }
+
+    /// max_depth returns the maximum depth of the nodes of the tree.
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    /// len returns the number of items in the tree.
+    pub fn len(&self) -> usize {
+        self.items.len
+    }
+
+    /// is_empty tells if the tree holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.len == 0
+    }
+
+    /// get returns a reference to the item, or None if it was removed.
+    pub fn get(&self, id: ItemId) -> Option<&I> {
+        self.items.get(id).map(|e| &e.item)
+    }
+
+    /// get_mut returns a mutable reference to the item, or None if it was
+    /// removed.
+    pub fn get_mut(&mut self, id: ItemId) -> Option<&mut I> {
+        self.items.get_mut(id).map(|e| &mut e.item)
+    }
+
+    /// iter returns an iterator over the items in the tree, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &I)> {
+        self.items.iter().map(|(id, e)| (id, &e.item))
+    }
+
+    /// insert adds an item with the given bounds to the tree, returning its
+    /// identifier.
+    pub fn insert(&mut self, bounds: Bounds<P>, item: I) -> ItemId {
+        let key = self.place(&bounds);
+        let id = self.items.insert(bounds, item, key);
+        self.attach(key, id);
+        id
+    }
+
+    /// remove removes the item from the tree, returning it, or None if it was
+    /// already removed.
+    pub fn remove(&mut self, id: ItemId) -> Option<I> {
+        let entry = self.items.remove(id)?;
+        self.detach(entry.location, id);
+        Some(entry.item)
+    }
+
+    /// update changes the bounds of the item, moving it to another node if
+    /// needed.
+    ///
+    /// # Panics
+    ///
+    /// update panics if the item was removed.
+    pub fn update(&mut self, id: ItemId, bounds: Bounds<P>) {
+        let old = self.items.get(id).expect("Tree::update: item was removed").location;
+        let key = self.place(&bounds);
+        {
+            let e = self.items.get_mut(id).unwrap();
+            e.bounds = bounds;
+            e.location = key;
+        }
+        if key != old {
+            self.detach(old, id);
+            self.attach(key, id);
+        }
+    }
+
+    // root returns the key of the root node.
+    fn root() -> Key<P> {
+        (0, P::cell_from_axes(|_| 0))
+    }
+
+    // cell_size returns the size of the cells at the given depth along axis
+    // i.
+    fn cell_size(&self, depth: u32, i: usize) -> P::Scalar {
+        let two = P::Scalar::one() + P::Scalar::one();
+        (self.bounds.max.axis(i) - self.bounds.min.axis(i)) / two.powi(depth as i32)
+    }
+
+    // node_bounds returns the loose box of the node.
+    fn node_bounds(&self, key: &Key<P>) -> Bounds<P> {
+        let two = P::Scalar::one() + P::Scalar::one();
+        let min = P::from_axes(|i| {
+            let size = self.cell_size(key.0, i);
+            let lo = self.bounds.min.axis(i) + size * <P::Scalar as NumCast>::from(P::cell_axis(&key.1, i)).unwrap();
+            lo - size * (self.looseness - P::Scalar::one()) / two
+        });
+        let max = P::from_axes(|i| min.axis(i) + self.cell_size(key.0, i) * self.looseness);
+        Bounds { min, max }
+    }
+
+    // children returns the keys of the children of the node, which may not
+    // exist.
+    fn children(key: &Key<P>) -> Vec<Key<P>> {
+        (0..1 << P::DIM)
+            .map(|mask: usize| {
+                (key.0 + 1, P::cell_from_axes(|i| 2 * P::cell_axis(&key.1, i) + ((mask >> i) & 1) as i32))
+            })
+            .collect()
+    }
+
+    // place returns the key of the deepest node that should hold an item
+    // with the given bounds, descending toward the child containing the
+    // center of the item.
+    fn place(&self, bounds: &Bounds<P>) -> Key<P> {
+        let center = bounds.center();
+        let mut key = Tree::<P, I>::root();
+        while key.0 < self.max_depth {
+            let depth = key.0 + 1;
+            let child = (depth, P::cell_from_axes(|i| {
+                let lo = 2 * P::cell_axis(&key.1, i);
+                let c = floor_div(center.axis(i) - self.bounds.min.axis(i), self.cell_size(depth, i));
+                c.max(lo).min(lo + 1)
+            }));
+            if !self.node_bounds(&child).contains(bounds) {
+                break;
+            }
+            key = child;
+        }
+        key
+    }
+
+    // parent returns the key of the parent of the (non-root) node.
+    fn parent(key: &Key<P>) -> Key<P> {
+        (key.0 - 1, P::cell_from_axes(|i| P::cell_axis(&key.1, i) / 2))
+    }
+
+    // attach stores the item at the node, allocating the node and its
+    // ancestors as needed.
+    fn attach(&mut self, key: Key<P>, id: ItemId) {
+        self.nodes.entry(key).or_default().items.push(id);
+        let mut k = key;
+        loop {
+            self.nodes.entry(k).or_default().count += 1;
+            if k.0 == 0 {
+                break;
+            }
+            k = Tree::<P, I>::parent(&k);
+        }
+    }
+
+    // detach removes the item from the node, freeing the nodes left empty.
+    fn detach(&mut self, key: Key<P>, id: ItemId) {
+        {
+            let items = &mut self.nodes.get_mut(&key).unwrap().items;
+            let i = items.iter().position(|&x| x == id).unwrap();
+            items.swap_remove(i);
+        }
+        let mut k = key;
+        loop {
+            let empty = {
+                let node = self.nodes.get_mut(&k).unwrap();
+                node.count -= 1;
+                node.count == 0
+            };
+            if empty {
+                self.nodes.remove(&k);
+            }
+            if k.0 == 0 {
+                break;
+            }
+            k = Tree::<P, I>::parent(&k);
+        }
+    }
+
+    fn query_node(&self, key: &Key<P>, b: &Bounds<P>, out: &mut Vec<ItemId>) {
+        let node = &self.nodes[key];
+        out.extend(node.items.iter().filter(|&&id| self.items.get(id).unwrap().bounds.intersects(b)));
+        for child in Tree::<P, I>::children(key) {
+            if self.nodes.contains_key(&child) && self.node_bounds(&child).intersects(b) {
+                self.query_node(&child, b, out);
+            }
+        }
+    }
+}
+
+impl<P: FloatPoint, I> SpatialQuery<P> for Tree<P, I> {
+    fn query_box(&self, b: &Bounds<P>) -> Vec<ItemId> {
+        let mut out = Vec::new();
+        let root = Tree::<P, I>::root();
+        // The root is always searched, as it holds the items outside of the
+        // tree's bounds.
+        if self.nodes.contains_key(&root) {
+            self.query_node(&root, b, &mut out);
+        }
+        out
+    }
+
+    fn nearest(&self, p: P, k: usize) -> Vec<(ItemId, P::Scalar)> {
+        let mut out = Vec::new();
+        let mut heap = BinaryHeap::new();
+        let root = Tree::<P, I>::root();
+        if self.nodes.contains_key(&root) {
+            heap.push(Candidate {
+                distance: P::Scalar::zero(),
+                key: Visit::Node::<P>(root),
+            });
+        }
+
+        // Visit nodes and items best-first by their squared distance, so
+        // items are reported in order once no closer node remains.
+        while let Some(c) = heap.pop() {
+            if out.len() == k {
+                break;
+            }
+            match c.key {
+                Visit::Item(id) => out.push((id, c.distance)),
+                Visit::Node(key) => {
+                    for &id in &self.nodes[&key].items {
+                        heap.push(Candidate {
+                            distance: self.items.get(id).unwrap().bounds.distance_sq(p),
+                            key: Visit::Item(id),
+                        });
+                    }
+                    for child in Tree::<P, I>::children(&key) {
+                        if self.nodes.contains_key(&child) {
+                            heap.push(Candidate {
+                                distance: self.node_bounds(&child).distance_sq(p),
+                                key: Visit::Node(child),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn item_bounds(&self, id: ItemId) -> Option<Bounds<P>> {
+        self.items.get(id).map(|e| e.bounds)
+    }
+}
+
+impl<P: FloatPoint, I> Index<ItemId> for Tree<P, I> {
+    type Output = I;
+
+    fn index(&self, id: ItemId) -> &I {
+        self.get(id).expect("Tree::index: item was removed")
+    }
+}
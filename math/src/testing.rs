@@ -0,0 +1,8 @@
+// Helpers shared by the tests of the crate.
+
+// lcg returns a pseudo-random number in [0, 1) from a small deterministic
+// generator, so that the tests are repeatable.
+pub fn lcg(state: &mut u64) -> f64 {
+    *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*state >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,127 @@
+use super::float::Float;
+use super::{Mat4, Quat, Vec3, Vec4};
+
+/// Transform is a translation, rotation and scale, applied in reverse order:
+/// points are scaled along each axis, then rotated, then translated.
+///
+/// # Examples
+///
+/// ```
+/// use fiz_math::{Quat, Transform, Vec3};
+/// use fiz_math::unit::Deg;
+///
+/// let t = Transform {
+///     translation: Vec3(1.0, 0.0, 0.0),
+///     rotation: Quat::from_axis_angle(Vec3(0.0, 0.0, 1.0), Deg(90.0)),
+///     scale: Vec3(2.0, 2.0, 2.0),
+/// };
+/// assert!(t.transform_point(Vec3(1.0, 0.0, 0.0)).almost_equal(Vec3(1.0, 2.0, 0.0), 1e-6));
+/// assert!(t.to_mat4().transform_point(Vec3(1.0, 0.0, 0.0)).almost_equal(Vec3(1.0, 2.0, 0.0), 1e-6));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform<T> {
+    /// The translation, applied last.
+    pub translation: Vec3<T>,
+
+    /// The rotation, as a unit quaternion.
+    pub rotation: Quat<T>,
+
+    /// The scale along each axis, applied first.
+    pub scale: Vec3<T>,
+}
+
+impl<T: Float> Default for Transform<T> {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl<T: Float> Transform<T> {
+    /// identity returns the transform which leaves points unchanged.
+    pub fn identity() -> Self {
+        Transform {
+            translation: Vec3(T::zero(), T::zero(), T::zero()),
+            rotation: Quat::identity(),
+            scale: Vec3(T::one(), T::one(), T::one()),
+        }
+    }
+
+    /// from_translation returns the transform which only translates.
+    pub fn from_translation(translation: Vec3<T>) -> Self {
+        Transform { translation, ..Transform::identity() }
+    }
+
+    /// from_mat4 decomposes an affine matrix into a transform, or returns
+    /// None if it's singular. Shear, which a transform can't represent, is
+    /// lost, and mirroring is represented by a negative X scale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::{Quat, Transform, Vec3};
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let t = Transform {
+    ///     translation: Vec3(1.0, 2.0, 3.0),
+    ///     rotation: Quat::from_axis_angle(Vec3(1.0, 1.0, 0.0), Deg(60.0)),
+    ///     scale: Vec3(-1.0, 2.0, 3.0),
+    /// };
+    /// let d = Transform::from_mat4(&t.to_mat4()).unwrap();
+    /// assert!(d.almost_equal(&t, 1e-5));
+    /// ```
+    pub fn from_mat4(m: &Mat4<T>) -> Option<Self> {
+        let axis = |c: Vec4<T>| Vec3(c.0, c.1, c.2);
+        let (x, y, z) = (axis(m.0), axis(m.1), axis(m.2));
+        let mut scale = Vec3(x.length(), y.length(), z.length());
+        if scale.0 == T::zero() || scale.1 == T::zero() || scale.2 == T::zero() {
+            return None;
+        }
+        if x.cross(y).dot(z) < T::zero() {
+            scale.0 = -scale.0;
+        }
+        let rotation = Mat4(m.0.div_scalar(scale.0), m.1.div_scalar(scale.1), m.2.div_scalar(scale.2), m.3);
+        let rotation = Quat::from_mat4(&rotation).normalize()?;
+        Some(Transform {
+            translation: axis(m.3),
+            rotation,
+            scale,
+        })
+    }
+
+    /// to_mat4 returns the matrix of the transform.
+    pub fn to_mat4(&self) -> Mat4<T> {
+        Mat4::translation(self.translation) * self.rotation.to_mat4() * Mat4::scale(self.scale)
+    }
+
+    /// transform_point returns the point p transformed.
+    pub fn transform_point(&self, p: Vec3<T>) -> Vec3<T> {
+        self.rotation.rotate(p * self.scale) + self.translation
+    }
+
+    /// transform_vector returns the direction v transformed, which ignores
+    /// translation.
+    pub fn transform_vector(&self, v: Vec3<T>) -> Vec3<T> {
+        self.rotation.rotate(v * self.scale)
+    }
+
+    /// lerp returns the interpolation a fraction t of the way from self to
+    /// other: linear for translation and scale, and spherical for rotation.
+    pub fn lerp(&self, other: &Self, t: T) -> Self {
+        let mix = |a: Vec3<T>, b: Vec3<T>| a + (b - a).mul_scalar(t);
+        Transform {
+            translation: mix(self.translation, other.translation),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: mix(self.scale, other.scale),
+        }
+    }
+
+    /// almost_equal tells if each part of the transform is equal to that of
+    /// the other given an absolute tolerance (see `Float::almost_equal`).
+    /// Rotations are compared as rotations, so q and -q are equal.
+    pub fn almost_equal<N: Float>(&self, other: &Self, abs_tol: N) -> bool {
+        let r = other.rotation;
+        self.translation.almost_equal(other.translation, abs_tol) &&
+        self.scale.almost_equal(other.scale, abs_tol) &&
+        (self.rotation.almost_equal(r, abs_tol) || self.rotation.almost_equal(-r, abs_tol))
+    }
+}
@@ -15,17 +15,19 @@ pub trait ToCM{
     fn to_cm(self) -> CM<Self::Output>;
 }
 
-/// CM represents centimeters (1/100th a meter).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::CM;
-///
-/// let x = CM(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(CM);
+unit! {
+    /// CM represents centimeters (1/100th a meter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::CM;
+    ///
+    /// let x = CM(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    CM
+}
 
 impl<T: Num + NumCast> ToMM for CM<T> {
     type Output = T;
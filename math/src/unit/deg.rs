@@ -28,18 +28,20 @@ pub trait ToDeg{
     fn to_deg(self) -> Deg<Self::Output>;
 }
 
-/// Deg represents degrees (a measurement of plane angle, representing 1/360th a
-// full rotation).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::Deg;
-///
-/// let x = Deg(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(Deg);
+unit! {
+    /// Deg represents degrees (a measurement of plane angle, representing 1/360th a
+    // full rotation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::Deg;
+    ///
+    /// let x = Deg(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    Deg
+}
 
 impl<T: Num + NumCast> ToDeg for Deg<T> {
     type Output = T;
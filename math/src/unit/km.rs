@@ -29,17 +29,19 @@ pub trait ToKM{
     fn to_km(self) -> KM<Self::Output>;
 }
 
-/// KM represents kilometers (1000 meters).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::KM;
-///
-/// let x = KM(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(KM);
+unit! {
+    /// KM represents kilometers (1000 meters).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::KM;
+    ///
+    /// let x = KM(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    KM
+}
 
 impl<T: Num + NumCast> ToMM for KM<T> {
     type Output = T;
@@ -29,17 +29,19 @@ pub trait ToM{
     fn to_m(self) -> M<Self::Output>;
 }
 
-/// M represents meters (the SI base unit representing distance).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::M;
-///
-/// let x = M(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(M);
+unit! {
+    /// M represents meters (the SI base unit representing distance).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::M;
+    ///
+    /// let x = M(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    M
+}
 
 impl<T: Num + NumCast> ToMM for M<T> {
     type Output = T;
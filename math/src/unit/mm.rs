@@ -31,17 +31,19 @@ pub trait ToMM{
     fn to_mm(self) -> MM<Self::Output>;
 }
 
-/// MM represents millimeters (1/10th a centimeter).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::MM;
-///
-/// let x = MM(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(MM);
+unit! {
+    /// MM represents millimeters (1/10th a centimeter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::MM;
+    ///
+    /// let x = MM(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    MM
+}
 
 impl<T: Num + NumCast> ToMM for MM<T> {
     type Output = T;
@@ -28,17 +28,19 @@ pub trait ToRad{
     fn to_rad(self) -> Rad<Self::Output>;
 }
 
-/// Rad represents radians (the standard unit of angular measure).
-///
-/// # Examples
-///
-/// ```
-/// use fiz_math::unit::Rad;
-///
-/// let x = Rad(1.0);
-/// println!("{:?}", x);
-/// ```
-unit!(Rad);
+unit! {
+    /// Rad represents radians (the standard unit of angular measure).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::unit::Rad;
+    ///
+    /// let x = Rad(1.0);
+    /// println!("{:?}", x);
+    /// ```
+    Rad
+}
 
 impl<T: Num + NumCast> ToRad for Rad<T> {
     type Output = T;
@@ -13,6 +13,11 @@
 /// // SilverCoins are like gold coins, only worse!
 /// unit!(SilverCoins);
 ///
+/// unit! {
+///     /// Attributes, such as doc comments, apply to the unit type.
+///     CopperCoins
+/// }
+///
 /// fn collect<T: Debug>(coins: GoldCoins<T>) {
 ///     println!("We've collected {:?} golden coins!", coins.0);
 /// }
@@ -87,7 +92,8 @@
 ///
 #[macro_export]
 macro_rules! unit {
-    ( $ident:ident ) => {
+    ( $(#[$meta:meta])* $ident:ident ) => {
+        $(#[$meta])*
         #[derive(Copy, Clone, Debug)]
         pub struct $ident<T>(pub T);
 
@@ -8,6 +8,7 @@ use super::float::Float;
 use super::Vec3;
 use super::unit::{ToRad, Rad};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use clamp::Clamp;
 use std::iter::IntoIterator;
 
@@ -395,6 +396,28 @@ impl<T: PartialEq> PartialEq for Vec2<T> {
     }
 }
 
+impl<T: Eq> Eq for Vec2<T> {}
+
+impl<T: Hash> Hash for Vec2<T> {
+    /// hash feeds the components of the vector into the hasher, which allows
+    /// vectors of integers to be used as keys (e.g. grid cells) in hash maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use fiz_math::Vec2;
+    ///
+    /// let mut cells = HashMap::new();
+    /// cells.insert(Vec2(1, 2), "a");
+    /// assert_eq!(cells[&Vec2(1, 2)], "a");
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        self.1.hash(state);
+    }
+}
+
 impl<T: PartialOrd> PartialOrd for Vec2<T> {
     /// partial_cmp compares the two vectors component-wise.
     ///
@@ -8,6 +8,7 @@ use super::float::Float;
 use super::Vec2;
 use super::unit::ToRad;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use clamp::Clamp;
 use std::iter::IntoIterator;
 
@@ -399,6 +400,29 @@ impl<T: PartialEq> PartialEq for Vec3<T> {
     }
 }
 
+impl<T: Eq> Eq for Vec3<T> {}
+
+impl<T: Hash> Hash for Vec3<T> {
+    /// hash feeds the components of the vector into the hasher, which allows
+    /// vectors of integers to be used as keys (e.g. grid cells) in hash maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use fiz_math::Vec3;
+    ///
+    /// let mut cells = HashMap::new();
+    /// cells.insert(Vec3(1, 2, 3), "a");
+    /// assert_eq!(cells[&Vec3(1, 2, 3)], "a");
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        self.1.hash(state);
+        self.2.hash(state);
+    }
+}
+
 impl<T: PartialOrd> PartialOrd for Vec3<T> {
     /// partial_cmp compares the two vectors component-wise.
     ///
@@ -544,6 +568,23 @@ impl<T: Num + Copy> Vec3<T> {
     pub fn length_sq(self) -> T {
         self.dot(self)
     }
+
+    /// cross returns the cross product of self and b, a vector perpendicular
+    /// to both whose direction follows the right-hand rule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fiz_math::Vec3;
+    ///
+    /// assert_eq!(Vec3(1, 0, 0).cross(Vec3(0, 1, 0)), Vec3(0, 0, 1));
+    /// assert_eq!(Vec3(0, 1, 0).cross(Vec3(1, 0, 0)), Vec3(0, 0, -1));
+    /// ```
+    pub fn cross(self, b: Self) -> Self {
+        Vec3(self.1 * b.2 - self.2 * b.1,
+             self.2 * b.0 - self.0 * b.2,
+             self.0 * b.1 - self.1 * b.0)
+    }
 }
 
 impl<T: Float> Vec3<T> {
@@ -7,6 +7,7 @@ use num;
 use super::float::Float;
 use super::{Vec2, Vec3};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use clamp::Clamp;
 use std::iter::IntoIterator;
 
@@ -413,6 +414,30 @@ impl<T: PartialEq> PartialEq for Vec4<T> {
     }
 }
 
+impl<T: Eq> Eq for Vec4<T> {}
+
+impl<T: Hash> Hash for Vec4<T> {
+    /// hash feeds the components of the vector into the hasher, which allows
+    /// vectors of integers to be used as keys (e.g. grid cells) in hash maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use fiz_math::Vec4;
+    ///
+    /// let mut cells = HashMap::new();
+    /// cells.insert(Vec4(1, 2, 3, 4), "a");
+    /// assert_eq!(cells[&Vec4(1, 2, 3, 4)], "a");
+    /// ```
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        self.1.hash(state);
+        self.2.hash(state);
+        self.3.hash(state);
+    }
+}
+
 impl<T: PartialOrd> PartialOrd for Vec4<T> {
     /// partial_cmp compares the two vectors component-wise.
     ///